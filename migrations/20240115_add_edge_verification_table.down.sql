@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS biomedgps_relation_verification;
+
+DROP TABLE IF EXISTS biomedgps_publication;
@@ -0,0 +1,30 @@
+-- biomedgps_publication table is used to cache the publications (title, abstract, etc.) which are referenced by the pmids column of the relations. It is used to verify the relations by the LLM.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_publication (
+    id BIGSERIAL PRIMARY KEY, -- The publication ID
+    pmid VARCHAR(64) NOT NULL, -- The PubMed ID of the publication, such as 32353859
+    title TEXT NOT NULL, -- The title of the publication
+    abstract TEXT NOT NULL, -- The abstract of the publication
+    journal VARCHAR(255), -- The journal of the publication
+    year INTEGER, -- The published year of the publication
+    CONSTRAINT biomedgps_publication_uniq_key UNIQUE (pmid)
+  );
+
+-- biomedgps_relation_verification table is used to store the verdicts which are generated by the LLM for verifying the relations against the abstracts of the linked publications.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_relation_verification (
+    id BIGSERIAL PRIMARY KEY, -- The verification ID
+    relation_id BIGINT NOT NULL, -- The ID of the relation in the biomedgps_relation table
+    relation_type VARCHAR(64) NOT NULL, -- The relation type, such as DRUGBANK::treats::Compound:Disease
+    source_id VARCHAR(64) NOT NULL, -- The source entity ID
+    source_type VARCHAR(64) NOT NULL, -- The source entity type
+    target_id VARCHAR(64) NOT NULL, -- The target entity ID
+    target_type VARCHAR(64) NOT NULL, -- The target entity type
+    pmids TEXT NOT NULL, -- The pmids which are used to verify the relation, separated by "|"
+    verdict VARCHAR(32) NOT NULL, -- The verdict of the verification, such as SUPPORTED, REFUTED, INSUFFICIENT_EVIDENCE
+    rationale TEXT NOT NULL, -- The rationale of the verdict which is generated by the LLM
+    prompt_template_category VARCHAR(64) NOT NULL, -- The category of the prompt template which is used to verify the relation
+    session_uuid VARCHAR(64) NOT NULL, -- The UUID of the session in the biomedgps_ai_message table
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The created time of the verification
+    CONSTRAINT biomedgps_relation_verification_uniq_key UNIQUE (relation_id, prompt_template_category)
+  );
@@ -0,0 +1,21 @@
+-- biomedgps_entity_attribute table is used to store the attributes of the entities which come from the external databases. The attributes are versioned, a re-import will add a new version instead of overwriting the old one.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_entity_attribute (
+    idx BIGSERIAL PRIMARY KEY, -- The entity attribute index
+    entity_id VARCHAR(64) NOT NULL, -- The entity ID
+    entity_type VARCHAR(64) NOT NULL, -- The entity type, such as Anatomy, Disease, Gene, Compound, Biological Process, etc.
+    description TEXT NOT NULL, -- A human-readable summary of the entity in an external database
+    external_db_name VARCHAR(64) NOT NULL, -- The name of an external database, such as MESH, OMIM, etc.
+    external_url TEXT NOT NULL, -- The link to the entity in an external database
+    external_id VARCHAR(64) NOT NULL, -- The id of the entity in an external database
+    valid_from TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The timestamp of the source, the version is valid from this time
+    superseded_by BIGINT, -- The idx of the newer version, NULL means it is the latest version
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The created time of the version
+    CONSTRAINT biomedgps_entity_attribute_uniq_key UNIQUE (
+      entity_id,
+      entity_type,
+      external_db_name,
+      external_id,
+      valid_from
+    )
+  );
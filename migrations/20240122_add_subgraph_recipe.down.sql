@@ -0,0 +1 @@
+ALTER TABLE biomedgps_subgraph DROP COLUMN IF EXISTS recipe;
@@ -0,0 +1,2 @@
+-- Add a recipe column into the biomedgps_subgraph table for recording how the subgraph was built, such as the seed nodes, the endpoints called, the filters and the model used. It's a json string and can be replayed against the current knowledge graph.
+ALTER TABLE biomedgps_subgraph ADD COLUMN IF NOT EXISTS recipe TEXT;
@@ -0,0 +1,14 @@
+-- biomedgps_llm_usage table is used to record the token usage of each LLM call. It is used to summarize the usage and enforce the monthly budgets.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_llm_usage (
+    id BIGSERIAL PRIMARY KEY, -- The usage ID
+    username VARCHAR(255) NOT NULL, -- The user who called the LLM
+    endpoint VARCHAR(64) NOT NULL, -- The endpoint which called the LLM, such as askLLM, verifyRelation
+    model VARCHAR(64) NOT NULL, -- The model name, such as gpt-4-1106-preview
+    prompt_tokens INTEGER NOT NULL, -- The number of tokens in the prompt
+    completion_tokens INTEGER NOT NULL, -- The number of tokens in the completion
+    total_tokens INTEGER NOT NULL, -- The total number of tokens
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time of the LLM call
+  );
+
+CREATE INDEX IF NOT EXISTS biomedgps_llm_usage_username_idx ON biomedgps_llm_usage (username, created_at);
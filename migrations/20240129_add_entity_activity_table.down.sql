@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS biomedgps_trending_entity;
+DROP TABLE IF EXISTS biomedgps_entity_activity;
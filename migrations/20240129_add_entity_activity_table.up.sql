@@ -0,0 +1,24 @@
+-- biomedgps_entity_activity table is used to record which entities are queried by the users. It is the source of the trending entities.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_entity_activity (
+    id BIGSERIAL PRIMARY KEY, -- The activity ID
+    username VARCHAR(255) NOT NULL, -- The user who queried the entity
+    entity_id VARCHAR(64) NOT NULL, -- The entity ID
+    entity_type VARCHAR(64) NOT NULL, -- The entity type, such as Disease, Gene, Compound, etc.
+    endpoint VARCHAR(64) NOT NULL, -- The endpoint which queried the entity, such as fetchNodes, fetchPredictedNodes
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time of the query
+  );
+
+CREATE INDEX IF NOT EXISTS biomedgps_entity_activity_created_at_idx ON biomedgps_entity_activity (created_at);
+
+-- biomedgps_trending_entity table is used to store the most queried entities per week. It is refreshed by the aggregation job (biomedgps-cli inittable -t trending-entities).
+CREATE TABLE
+  IF NOT EXISTS biomedgps_trending_entity (
+    week DATE NOT NULL, -- The first day (Monday) of the week
+    entity_id VARCHAR(64) NOT NULL, -- The entity ID
+    entity_type VARCHAR(64) NOT NULL, -- The entity type, such as Disease, Gene, Compound, etc.
+    num_queries BIGINT NOT NULL, -- The number of the queries in the week
+    num_users BIGINT NOT NULL, -- The number of the distinct users who queried the entity in the week
+    rank INTEGER NOT NULL, -- The rank in the week, 1 is the most queried entity
+    CONSTRAINT biomedgps_trending_entity_uniq_key UNIQUE (week, entity_id, entity_type)
+  );
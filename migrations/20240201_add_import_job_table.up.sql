@@ -0,0 +1,12 @@
+-- biomedgps_import_job table is used to track the import jobs which reimport the datasets published to the external data registry.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_import_job (
+    id BIGSERIAL PRIMARY KEY, -- The job ID
+    dataset VARCHAR(64) NOT NULL, -- The dataset name, such as drkg, ctd, etc.
+    version VARCHAR(64) NOT NULL, -- The version of the dataset in the registry
+    status VARCHAR(16) NOT NULL, -- The status of the job, such as pending, running, succeeded, failed
+    message TEXT, -- The error message if the job failed
+    payload JSONB NOT NULL, -- The dataset-published event which triggers the job
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the job is scheduled
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time when the status is changed
+  );
@@ -0,0 +1,10 @@
+DROP POLICY IF EXISTS biomedgps_knowledge_curation_select_policy ON biomedgps_knowledge_curation;
+DROP POLICY IF EXISTS biomedgps_knowledge_curation_insert_policy ON biomedgps_knowledge_curation;
+DROP POLICY IF EXISTS biomedgps_knowledge_curation_update_policy ON biomedgps_knowledge_curation;
+DROP POLICY IF EXISTS biomedgps_knowledge_curation_delete_policy ON biomedgps_knowledge_curation;
+ALTER TABLE biomedgps_knowledge_curation NO FORCE ROW LEVEL SECURITY;
+ALTER TABLE biomedgps_knowledge_curation DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS biomedgps_subgraph_owner_policy ON biomedgps_subgraph;
+ALTER TABLE biomedgps_subgraph NO FORCE ROW LEVEL SECURITY;
+ALTER TABLE biomedgps_subgraph DISABLE ROW LEVEL SECURITY;
@@ -0,0 +1,38 @@
+-- Row-level security policies for the owner scoping of the curation and subgraph tables.
+-- The claims are set by the application with set_config(..., true) (the same as SET LOCAL) when ROW_LEVEL_SECURITY is enabled. All rows are visible when the claims are not set, so the policies don't change anything when the feature is disabled.
+-- NOTE: the policies are bypassed by the superusers and the roles with BYPASSRLS, so the application should connect as a normal role.
+ALTER TABLE biomedgps_knowledge_curation ENABLE ROW LEVEL SECURITY;
+ALTER TABLE biomedgps_knowledge_curation FORCE ROW LEVEL SECURITY;
+
+-- The curated knowledges are visible to the curator and the members of the same organization or project.
+CREATE POLICY biomedgps_knowledge_curation_select_policy ON biomedgps_knowledge_curation FOR SELECT USING (
+  COALESCE(current_setting('biomedgps.username', true), '') = ''
+  OR curator = current_setting('biomedgps.username', true)
+  OR payload->>'organization_id' = ANY(string_to_array(current_setting('biomedgps.organizations', true), ','))
+  OR payload->>'project_id' = ANY(string_to_array(current_setting('biomedgps.projects', true), ','))
+);
+
+-- Only the curator can create, update and delete the curated knowledges.
+CREATE POLICY biomedgps_knowledge_curation_insert_policy ON biomedgps_knowledge_curation FOR INSERT WITH CHECK (
+  COALESCE(current_setting('biomedgps.username', true), '') = ''
+  OR curator = current_setting('biomedgps.username', true)
+);
+
+CREATE POLICY biomedgps_knowledge_curation_update_policy ON biomedgps_knowledge_curation FOR UPDATE USING (
+  COALESCE(current_setting('biomedgps.username', true), '') = ''
+  OR curator = current_setting('biomedgps.username', true)
+);
+
+CREATE POLICY biomedgps_knowledge_curation_delete_policy ON biomedgps_knowledge_curation FOR DELETE USING (
+  COALESCE(current_setting('biomedgps.username', true), '') = ''
+  OR curator = current_setting('biomedgps.username', true)
+);
+
+ALTER TABLE biomedgps_subgraph ENABLE ROW LEVEL SECURITY;
+ALTER TABLE biomedgps_subgraph FORCE ROW LEVEL SECURITY;
+
+-- The subgraphs are only visible to and editable by the owner.
+CREATE POLICY biomedgps_subgraph_owner_policy ON biomedgps_subgraph FOR ALL USING (
+  COALESCE(current_setting('biomedgps.username', true), '') = ''
+  OR owner = current_setting('biomedgps.username', true)
+);
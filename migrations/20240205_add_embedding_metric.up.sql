@@ -0,0 +1,5 @@
+-- The distance metric of the embeddings, such as l2, inner_product and cosine. It decides how the embeddings are normalized at import time and which pgvector operator is used by the similarity queries.
+ALTER TABLE biomedgps_embedding_metadata ADD COLUMN IF NOT EXISTS metric VARCHAR(16) NOT NULL DEFAULT 'l2';
+
+-- The semantic matching models assume the inner product.
+UPDATE biomedgps_embedding_metadata SET metric = 'inner_product' WHERE model_type IN ('DistMult', 'ComplEx');
@@ -0,0 +1,18 @@
+-- biomedgps_export_job table is used to track the export jobs which export the knowledge graph in the background.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_export_job (
+    id BIGSERIAL PRIMARY KEY, -- The job ID
+    owner VARCHAR(64) NOT NULL, -- The username of the user who creates the job
+    format VARCHAR(16) NOT NULL, -- The format of the artifact, such as tsv, kgx, graphml
+    status VARCHAR(16) NOT NULL, -- The status of the job, such as pending, running, succeeded, failed, expired
+    processed BIGINT NOT NULL DEFAULT 0, -- How many relations have been exported
+    total BIGINT NOT NULL DEFAULT 0, -- How many relations will be exported
+    message TEXT, -- The error message if the job failed
+    filters JSONB NOT NULL, -- The format and the filters of the export
+    artifact TEXT, -- The path of the artifact on the server
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the job is created
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the job is updated
+    expired_at TIMESTAMPTZ -- The time after which the artifact can't be downloaded
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_export_job_owner ON biomedgps_export_job (owner);
@@ -0,0 +1,3 @@
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS license;
+
+DROP TABLE IF EXISTS biomedgps_dataset_license;
@@ -0,0 +1,13 @@
+-- biomedgps_dataset_license table is used to record the license of each source dataset, some datasets forbid redistribution, so they are excluded from the exports by default.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_dataset_license (
+    dataset VARCHAR(64) PRIMARY KEY, -- The dataset name, such as drkg, ctd, etc. It's the same as the dataset of the relations.
+    license VARCHAR(64) NOT NULL, -- The license name, such as CC-BY-4.0, CC-BY-NC-4.0, etc.
+    redistributable BOOLEAN NOT NULL DEFAULT TRUE, -- Whether the relations of the dataset can be exported and redistributed
+    url TEXT, -- The url of the license terms
+    description TEXT, -- The usage restrictions of the dataset
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time when the license is updated
+  );
+
+-- The license of the relations, it's tagged from the biomedgps_dataset_license table.
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS license VARCHAR(64);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS biomedgps_node_tag;
@@ -0,0 +1,14 @@
+-- biomedgps_node_tag table is used to store the tags of the nodes, such as validated, candidate and exclude. They are shown as badges on the nodes in the graph views.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_node_tag (
+    id BIGSERIAL PRIMARY KEY, -- The tag ID
+    node_id VARCHAR(255) NOT NULL, -- The composed id of the node, such as Gene::ENTREZ:1017
+    tag VARCHAR(32) NOT NULL, -- The tag, such as validated, candidate, exclude
+    color VARCHAR(16), -- The color of the badge, such as #52c41a
+    owner VARCHAR(64) NOT NULL, -- The username of the user who creates the tag
+    project_id INTEGER, -- The tag is visible to the members of the project if it's set
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the tag is created
+    CONSTRAINT biomedgps_node_tag_uniq_key UNIQUE (node_id, tag, owner)
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_node_tag_node_id ON biomedgps_node_tag (node_id);
@@ -0,0 +1,17 @@
+-- biomedgps_benchmark_result table is used to store the metrics of the prioritization methods on the benchmark tasks, they are shown in the models listing page.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_benchmark_result (
+    id BIGSERIAL PRIMARY KEY, -- The result ID
+    model_name VARCHAR(64) NOT NULL, -- The KGE model which is benchmarked with the graph-based methods, such as biomedgps
+    task VARCHAR(64) NOT NULL, -- The benchmark task, such as disease_gene_loo
+    method VARCHAR(32) NOT NULL, -- The prioritization method, such as kge, rwr, shared_neighbors
+    relation_type VARCHAR(255) NOT NULL, -- The relation type of the held-out relations, such as GNBR::J::Gene:Disease
+    num_cases INTEGER NOT NULL, -- How many held-out relations are evaluated
+    top_k INTEGER NOT NULL, -- The k of the Recall@K
+    auroc DOUBLE PRECISION NOT NULL, -- The mean AUROC over all cases
+    auprc DOUBLE PRECISION NOT NULL, -- The mean AUPRC over all cases
+    recall_at_k DOUBLE PRECISION NOT NULL, -- The mean Recall@K over all cases
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time when the benchmark is finished
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_benchmark_result_model_name ON biomedgps_benchmark_result (model_name);
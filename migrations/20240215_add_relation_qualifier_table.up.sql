@@ -0,0 +1,12 @@
+-- biomedgps_relation_qualifier table is used to store the qualifiers of the relations, such as dose, tissue and direction of effect.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_relation_qualifier (
+    id BIGSERIAL PRIMARY KEY, -- The qualifier ID
+    relation_id BIGINT NOT NULL REFERENCES biomedgps_relation (id) ON DELETE CASCADE, -- The ID of the relation
+    key VARCHAR(64) NOT NULL, -- The key of the qualifier, such as dose, tissue, direction
+    value_type VARCHAR(16) NOT NULL, -- The type of the value, such as string, number, boolean
+    value TEXT NOT NULL, -- The value of the qualifier, the numbers are stored as text and casted when they are compared
+    CONSTRAINT biomedgps_relation_qualifier_uniq_key UNIQUE (relation_id, key)
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_relation_qualifier_key ON biomedgps_relation_qualifier (key);
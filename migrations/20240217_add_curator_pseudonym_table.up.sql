@@ -0,0 +1,9 @@
+-- biomedgps_curator_pseudonym table is used to store the mapping between the pseudonyms and the curators in the exports, it's only visible to the admin users.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_curator_pseudonym (
+    pseudonym VARCHAR(64) PRIMARY KEY, -- The pseudonym of the curator, such as curator-3f2a9c0d41b7e865
+    username VARCHAR(64) NOT NULL, -- The username of the curator
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now() -- The time when the pseudonym is first exported
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_curator_pseudonym_username ON biomedgps_curator_pseudonym (username);
@@ -0,0 +1,16 @@
+-- biomedgps_variant table is used to store the attributes of the variants, the variants are also stored in the biomedgps_entity table with the Variant label.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_variant (
+    id VARCHAR(64) PRIMARY KEY, -- The entity ID of the variant, such as dbSNP:rs1042522, or HGVS:<hash> if the variant has no rsID
+    rsid VARCHAR(32), -- The rsID of the variant, such as rs1042522
+    hgvs TEXT, -- The normalized HGVS strings of the variant, separated by |, such as NM_000546.6:c.215C>G|NP_000537.3:p.Pro72Arg
+    chromosome VARCHAR(8), -- The chromosome, such as 17
+    position BIGINT, -- The position on the chromosome
+    ref_allele TEXT, -- The reference allele
+    alt_allele TEXT, -- The alternative allele
+    consequence VARCHAR(64), -- The molecular consequence, such as missense_variant
+    clinical_significance VARCHAR(64), -- The clinical significance, such as pathogenic
+    dataset VARCHAR(64) NOT NULL -- The dataset of the variant
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_variant_rsid ON biomedgps_variant (rsid);
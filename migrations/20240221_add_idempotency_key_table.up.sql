@@ -0,0 +1,15 @@
+-- biomedgps_idempotency_key table is used to store the responses of the POST requests with the Idempotency-Key header, so the retried requests get the same response instead of creating duplicate records.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_idempotency_key (
+    scope VARCHAR(64) NOT NULL, -- The sha256 of the Authorization header, the keys of different users never collide
+    idempotency_key VARCHAR(255) NOT NULL, -- The value of the Idempotency-Key header
+    fingerprint VARCHAR(64) NOT NULL, -- The sha256 of the method, the uri and the body of the request
+    status INTEGER, -- The status code of the response, NULL means the request is still running
+    content_type VARCHAR(255), -- The content type of the response
+    body BYTEA, -- The body of the response
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(), -- The time when the key is first used
+    expired_at TIMESTAMPTZ NOT NULL, -- The key can be reused after this time
+    PRIMARY KEY (scope, idempotency_key)
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_idempotency_key_expired_at ON biomedgps_idempotency_key (expired_at);
@@ -0,0 +1,10 @@
+-- biomedgps_entity_image table is used to record the cached images of the entities, such as the structures of the compounds. The images are stored in the directory which is specified by the ENTITY_IMAGE_DIR environment variable.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_entity_image (
+    entity_id VARCHAR(64) NOT NULL, -- The ID of the entity, such as PUBCHEM:2244
+    entity_type VARCHAR(64) NOT NULL, -- The type of the entity, such as Compound
+    source_url TEXT NOT NULL, -- The url which the image is fetched from
+    filename VARCHAR(255) NOT NULL, -- The path of the image which is relative to the image directory
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(), -- When the image is cached
+    PRIMARY KEY (entity_type, entity_id)
+  );
@@ -0,0 +1,10 @@
+-- biomedgps_import_progress table is used to record the progress of importing the large files chunk by chunk, so a failed import can be resumed by the --resume option.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_import_progress (
+    target VARCHAR(128) NOT NULL, -- Where the file is imported into, such as biomedgps_relation:<dataset> or neo4j:relation
+    file_key TEXT NOT NULL, -- The path, size and modified time of the file, such as /data/relations.tsv:1024:1708850000
+    imported_rows BIGINT NOT NULL DEFAULT 0, -- How many rows have been imported
+    finished BOOLEAN NOT NULL DEFAULT FALSE, -- Whether the file has been imported completely
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(), -- When the progress is updated
+    PRIMARY KEY (target, file_key)
+  );
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS biomedgps_confirmation_audit;
@@ -0,0 +1,16 @@
+-- biomedgps_confirmation_audit table is used to audit the two-step confirmation of the destructive admin requests, such as deleting a dataset.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_confirmation_audit (
+    id BIGSERIAL PRIMARY KEY, -- The audit ID
+    nonce VARCHAR(64), -- The nonce of the confirmation token, it's empty for the rejected requests
+    username VARCHAR(64), -- The user who requested the confirmation token
+    method VARCHAR(16) NOT NULL, -- The method of the destructive request, such as DELETE
+    path TEXT NOT NULL, -- The path of the destructive request, such as /api/v1/datasets/ctd
+    step VARCHAR(16) NOT NULL, -- One of requested, executed and rejected
+    status INTEGER, -- The status code of the executed or rejected request
+    message TEXT, -- Why the request is rejected
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now() -- When the step happened
+  );
+
+-- A confirmation token can be executed only once.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_biomedgps_confirmation_audit_executed ON biomedgps_confirmation_audit (nonce) WHERE step = 'executed';
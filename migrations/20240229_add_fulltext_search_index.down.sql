@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_fts_key_sentence_knowledge_curation_table;
+DROP INDEX IF EXISTS idx_fts_key_sentence_relation_table;
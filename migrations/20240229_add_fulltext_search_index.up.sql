@@ -0,0 +1,4 @@
+-- Enable the full-text search of the key sentences. The expressions must be the same as the ones in the KeySentenceMatch::search function, otherwise the indexes are not used.
+-- The curated key sentences are searched with the names of the entities, so the curators can find their triples by the entity names.
+CREATE INDEX IF NOT EXISTS idx_fts_key_sentence_knowledge_curation_table ON biomedgps_knowledge_curation USING gin(to_tsvector('english', key_sentence || ' ' || source_name || ' ' || target_name));
+CREATE INDEX IF NOT EXISTS idx_fts_key_sentence_relation_table ON biomedgps_relation USING gin(to_tsvector('english', key_sentence));
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_source_relation_table;
+DROP INDEX IF EXISTS idx_target_relation_table;
@@ -0,0 +1,3 @@
+-- The relations of a node are counted by the source and target columns, such as penalizing the hub nodes in the predictions.
+CREATE INDEX IF NOT EXISTS idx_source_relation_table ON biomedgps_relation (source_type, source_id);
+CREATE INDEX IF NOT EXISTS idx_target_relation_table ON biomedgps_relation (target_type, target_id);
@@ -0,0 +1 @@
+ALTER TABLE biomedgps_export_job DROP COLUMN IF EXISTS snapshot_id;
@@ -0,0 +1,2 @@
+-- The snapshot id of the transaction which reads the knowledge graph, it's only set for the snapshot-consistent exports.
+ALTER TABLE biomedgps_export_job ADD COLUMN IF NOT EXISTS snapshot_id VARCHAR(64);
@@ -0,0 +1 @@
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS warnings;
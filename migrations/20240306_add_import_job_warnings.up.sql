@@ -0,0 +1,2 @@
+-- The non-fatal issues of the imports in the job, such as the unknown relation types and the skipped columns. It's a json array of the kind, file and message of each warning.
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS warnings JSONB NOT NULL DEFAULT '[]'::jsonb;
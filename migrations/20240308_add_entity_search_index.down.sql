@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_fts_entity_table;
@@ -0,0 +1,2 @@
+-- Enable the full-text search of the entities for the type-ahead of the frontend. The expression must be the same as ENTITY_SEARCH_DOCUMENT in the EntitySearchMatch::search function, otherwise the index is not used.
+CREATE INDEX IF NOT EXISTS idx_fts_entity_table ON biomedgps_entity USING gin((setweight(to_tsvector('simple', name), 'A') || setweight(to_tsvector('simple', COALESCE(synonyms, '')), 'B') || setweight(to_tsvector('simple', COALESCE(xrefs, '')), 'C')));
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS biomedgps_entity_description_embedding;
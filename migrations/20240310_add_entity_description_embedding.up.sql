@@ -0,0 +1,10 @@
+-- biomedgps_entity_description_embedding table is used to cache the text embeddings of the entity descriptions, they are computed by the pgml extension on demand and used to rerank the predicted nodes by the query context.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_entity_description_embedding (
+    entity_id VARCHAR(64) NOT NULL, -- The entity ID
+    entity_type VARCHAR(64) NOT NULL, -- The entity type, such as Anatomy, Disease, Gene, Compound, Biological Process, etc.
+    model VARCHAR(255) NOT NULL, -- The text embedding model, such as sentence-transformers/all-MiniLM-L6-v2
+    embedding vector NOT NULL, -- The embedding of the description (or the name if the description is empty), the length depends on the model
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(), -- The time when the embedding is computed
+    UNIQUE (entity_id, entity_type, model)
+  );
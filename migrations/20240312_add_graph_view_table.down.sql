@@ -0,0 +1 @@
+DROP TABLE IF EXISTS biomedgps_graph_view;
@@ -0,0 +1,14 @@
+-- biomedgps_graph_view table is used to store the named dataset combinations of the users, such as drkg + ctd or only the curated findings. The entity, relation and graph endpoints only return the entities and the relations in the view if the view_id is set.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_graph_view (
+    id BIGSERIAL PRIMARY KEY, -- The view ID
+    name VARCHAR(64) NOT NULL, -- The name of the view, such as DRKG + CTD
+    description TEXT, -- The description of the view
+    datasets TEXT[] NOT NULL, -- The datasets of the relations in the view, such as {drkg,ctd}
+    relation_types TEXT[], -- Only the relations with these relation types are in the view if it's set
+    owner VARCHAR(64) NOT NULL, -- The username of the user who creates the view
+    project_id INTEGER, -- The view is visible to the members of the project if it's set
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the view is created
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the view is updated
+    CONSTRAINT biomedgps_graph_view_uniq_key UNIQUE (name, owner)
+  );
@@ -0,0 +1,7 @@
+-- biomedgps_configuration table is used to store the runtime configurations which are shared by the importers and the API servers, such as the maintenance state of a destructive import.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_configuration (
+    key VARCHAR(64) PRIMARY KEY, -- The key of the configuration, such as import_maintenance
+    value JSONB NOT NULL, -- The value of the configuration
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP -- The time when the configuration is updated
+  );
@@ -0,0 +1,14 @@
+-- biomedgps_entity_hierarchy table is used to store the parent-child relations between the entities, such as the is_a relations of the disease and phenotype ontologies.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_entity_hierarchy (
+    id BIGSERIAL PRIMARY KEY, -- The ID of the hierarchy row
+    child_id VARCHAR(64) NOT NULL, -- The entity ID of the child, such as MONDO:0005015
+    child_type VARCHAR(64) NOT NULL, -- The entity type of the child, such as Disease
+    parent_id VARCHAR(64) NOT NULL, -- The entity ID of the parent, such as MONDO:0000001
+    parent_type VARCHAR(64) NOT NULL, -- The entity type of the parent, such as Disease
+    relation VARCHAR(32) NOT NULL DEFAULT 'is_a', -- The relation between the child and the parent, such as is_a
+    resource VARCHAR(64) NOT NULL, -- The ontology which defines the relation, such as MONDO
+    CONSTRAINT biomedgps_entity_hierarchy_uniq_key UNIQUE (child_id, child_type, parent_id, parent_type, relation)
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_entity_hierarchy_parent ON biomedgps_entity_hierarchy (parent_id, parent_type);
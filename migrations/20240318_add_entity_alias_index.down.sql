@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_alias_entity_table;
@@ -0,0 +1,2 @@
+-- Enable the exact lookup of the entities by the ids and the cross references, such as `DrugBank:DB00001` or `DB00001`. The expression must be the same as ENTITY_ALIAS_ARRAY in the EntitySearchMatch::search function, otherwise the index is not used.
+CREATE INDEX IF NOT EXISTS idx_alias_entity_table ON biomedgps_entity USING gin((string_to_array(lower(id || COALESCE('|' || xrefs, '')), '|') || string_to_array(regexp_replace(lower(id || COALESCE('|' || xrefs, '')), '[^|]*:', '', 'g'), '|')));
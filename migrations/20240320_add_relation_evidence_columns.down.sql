@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_n_pmids_relation_table;
+DROP INDEX IF EXISTS idx_n_datasets_relation_table;
+DROP INDEX IF EXISTS idx_n_curations_relation_table;
+
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS n_pmids;
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS n_datasets;
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS n_curations;
@@ -0,0 +1,36 @@
+-- The evidence counts of the relations, they are maintained by the importer and the curation writes, so the relations can be sorted by the evidence strength without counting at query time.
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS n_pmids INTEGER NOT NULL DEFAULT 0; -- The number of the distinct PMIDs of the relation
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS n_datasets INTEGER NOT NULL DEFAULT 0; -- The number of the datasets which contain the same relation
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS n_curations INTEGER NOT NULL DEFAULT 0; -- The number of the curated knowledges of the same relation
+
+-- Backfill the counts of the existing relations.
+UPDATE biomedgps_relation r
+SET n_pmids = (SELECT COUNT(DISTINCT p) FROM unnest(string_to_array(r.pmids, '|')) p WHERE p <> ''),
+    n_datasets = c.n_datasets
+FROM (
+    SELECT relation_type, source_id, source_type, target_id, target_type, COUNT(DISTINCT dataset) AS n_datasets
+    FROM biomedgps_relation
+    GROUP BY relation_type, source_id, source_type, target_id, target_type
+) c
+WHERE r.relation_type = c.relation_type
+  AND r.source_id = c.source_id
+  AND r.source_type = c.source_type
+  AND r.target_id = c.target_id
+  AND r.target_type = c.target_type;
+
+UPDATE biomedgps_relation r
+SET n_curations = c.n_curations
+FROM (
+    SELECT relation_type, source_id, source_type, target_id, target_type, COUNT(*) AS n_curations
+    FROM biomedgps_knowledge_curation
+    GROUP BY relation_type, source_id, source_type, target_id, target_type
+) c
+WHERE r.relation_type = c.relation_type
+  AND r.source_id = c.source_id
+  AND r.source_type = c.source_type
+  AND r.target_id = c.target_id
+  AND r.target_type = c.target_type;
+
+CREATE INDEX IF NOT EXISTS idx_n_pmids_relation_table ON biomedgps_relation (n_pmids DESC, id);
+CREATE INDEX IF NOT EXISTS idx_n_datasets_relation_table ON biomedgps_relation (n_datasets DESC, id);
+CREATE INDEX IF NOT EXISTS idx_n_curations_relation_table ON biomedgps_relation (n_curations DESC, id);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_curator_created_at_curation_table;
+DROP INDEX IF EXISTS idx_owner_created_time_subgraph_table;
+DROP INDEX IF EXISTS idx_owner_created_at_export_job_table;
+DROP INDEX IF EXISTS idx_owner_updated_at_graph_view_table;
+DROP INDEX IF EXISTS idx_owner_created_at_node_tag_table;
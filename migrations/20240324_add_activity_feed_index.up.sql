@@ -0,0 +1,6 @@
+-- The activity feed reads the newest rows of each owned table, these indexes avoid scanning all rows of a user.
+CREATE INDEX IF NOT EXISTS idx_curator_created_at_curation_table ON biomedgps_knowledge_curation (curator, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_owner_created_time_subgraph_table ON biomedgps_subgraph (owner, created_time DESC);
+CREATE INDEX IF NOT EXISTS idx_owner_created_at_export_job_table ON biomedgps_export_job (owner, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_owner_updated_at_graph_view_table ON biomedgps_graph_view (owner, updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_owner_created_at_node_tag_table ON biomedgps_node_tag (owner, created_at DESC);
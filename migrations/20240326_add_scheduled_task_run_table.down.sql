@@ -0,0 +1 @@
+DROP TABLE IF EXISTS biomedgps_scheduled_task_run;
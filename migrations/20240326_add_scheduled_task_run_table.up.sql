@@ -0,0 +1,14 @@
+-- biomedgps_scheduled_task_run table is created to record the runs of the scheduled maintenance tasks, such as the nightly refresh of the trending entities, so the admins can check whether they succeeded.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_scheduled_task_run (
+    id BIGSERIAL PRIMARY KEY, -- The run ID
+    task_name VARCHAR(64) NOT NULL, -- The name of the task, such as refresh-trending-entities
+    status VARCHAR(16) NOT NULL, -- The status of the run, such as running, succeeded, failed
+    message TEXT, -- The summary of the run, such as how many rows are removed
+    error TEXT, -- The error message if the run failed
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the run is started
+    finished_at TIMESTAMPTZ -- The time when the run is finished
+  );
+
+CREATE INDEX IF NOT EXISTS idx_task_name_scheduled_task_run_table ON biomedgps_scheduled_task_run (task_name, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_status_scheduled_task_run_table ON biomedgps_scheduled_task_run (status);
@@ -0,0 +1,12 @@
+DROP INDEX IF EXISTS idx_status_import_job_table;
+-- The registry jobs are kept, only the admin jobs are removed.
+DELETE FROM biomedgps_import_job WHERE source <> 'registry';
+ALTER TABLE biomedgps_import_job ALTER COLUMN version SET NOT NULL;
+ALTER TABLE biomedgps_import_job ALTER COLUMN dataset SET NOT NULL;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS finished_at;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS logs;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS attempts;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS total_steps;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS finished_steps;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS owner;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS source;
@@ -0,0 +1,13 @@
+-- biomedgps_import_job table also tracks the admin import jobs which import the data files on the server, such as the entities, the relations and the KGE models. The payload of an admin job is the import job request which has the steps.
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS source VARCHAR(16) NOT NULL DEFAULT 'registry'; -- Who creates the job, registry for the webhook deliveries and admin for the admin users
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS owner VARCHAR(64); -- The username of the admin who creates the job
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS finished_steps INTEGER NOT NULL DEFAULT 0; -- How many steps (files of a registry job) have been finished, an admin job is resumed from this step
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS total_steps INTEGER NOT NULL DEFAULT 0; -- How many steps the job has
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0; -- How many times the job is started
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS logs TEXT; -- The logs of the steps
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS finished_at TIMESTAMPTZ; -- The time when the job is finished
+-- The admin jobs import the uploaded files, they don't have a dataset version.
+ALTER TABLE biomedgps_import_job ALTER COLUMN dataset DROP NOT NULL;
+ALTER TABLE biomedgps_import_job ALTER COLUMN version DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_status_import_job_table ON biomedgps_import_job (source, status);
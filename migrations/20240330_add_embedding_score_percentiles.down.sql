@@ -0,0 +1 @@
+ALTER TABLE biomedgps_embedding_metadata DROP COLUMN IF EXISTS score_percentiles;
@@ -0,0 +1,2 @@
+-- The score percentiles of the model, they are sampled at import time and map the raw scores to the percentiles (0-100), so the scores of the different models are comparable. It's NULL if the model is not calibrated.
+ALTER TABLE biomedgps_embedding_metadata ADD COLUMN IF NOT EXISTS score_percentiles FLOAT8[];
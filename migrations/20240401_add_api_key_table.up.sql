@@ -0,0 +1,15 @@
+-- biomedgps_api_key table is created to store the API keys of the users, so the pipelines can access the read and prediction endpoints by the X-API-Key header instead of the interactive token flow. Only the hashes of the keys are stored.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_api_key (
+    id BIGSERIAL PRIMARY KEY, -- The key ID
+    name VARCHAR(64) NOT NULL, -- The name of the key, such as nightly-pipeline
+    owner VARCHAR(255) NOT NULL, -- The user who creates the key, the requests with the key are sent as this user
+    key_prefix VARCHAR(16) NOT NULL, -- The first characters of the key, they help the users to identify the keys
+    key_hash VARCHAR(64) NOT NULL UNIQUE, -- The sha256 hash of the key
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP, -- The time when the key is created
+    expires_at TIMESTAMPTZ, -- The time when the key expires, the key never expires if it's NULL
+    last_used_at TIMESTAMPTZ, -- The last time when the key is used
+    revoked_at TIMESTAMPTZ -- The time when the key is revoked
+  );
+
+CREATE INDEX IF NOT EXISTS idx_owner_api_key_table ON biomedgps_api_key (owner);
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_tissue_relation_table;
+DROP INDEX IF EXISTS idx_tissue_id_relation_table;
+DROP INDEX IF EXISTS idx_cell_type_relation_table;
+DROP INDEX IF EXISTS idx_cell_type_id_relation_table;
+
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS tissue;
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS tissue_id;
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS cell_type;
+ALTER TABLE biomedgps_relation DROP COLUMN IF EXISTS cell_type_id;
@@ -0,0 +1,11 @@
+-- The biological context of the relations, such as the tissue and the cell type where the relation is observed. The ids are the terms of the source ontologies, such as UBERON and CL.
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS tissue VARCHAR(64); -- The name of the tissue, such as liver
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS tissue_id VARCHAR(64); -- The ontology id of the tissue, such as UBERON:0002107
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS cell_type VARCHAR(64); -- The name of the cell type, such as hepatocyte
+ALTER TABLE biomedgps_relation ADD COLUMN IF NOT EXISTS cell_type_id VARCHAR(64); -- The ontology id of the cell type, such as CL:0000182
+
+-- The relations are filtered by the context columns in the relation and the graph expansion endpoints.
+CREATE INDEX IF NOT EXISTS idx_tissue_relation_table ON biomedgps_relation (tissue) WHERE tissue IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_tissue_id_relation_table ON biomedgps_relation (tissue_id) WHERE tissue_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_cell_type_relation_table ON biomedgps_relation (cell_type) WHERE cell_type IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_cell_type_id_relation_table ON biomedgps_relation (cell_type_id) WHERE cell_type_id IS NOT NULL;
@@ -0,0 +1,17 @@
+-- biomedgps_audit_log table is used to record the mutating API requests, so we know who changed what.
+CREATE TABLE
+  IF NOT EXISTS biomedgps_audit_log (
+    id BIGSERIAL PRIMARY KEY, -- The audit log ID
+    username VARCHAR(64), -- The user who sent the request, it's NULL if the token is invalid
+    method VARCHAR(8) NOT NULL, -- The method of the request, such as POST, PUT and DELETE
+    path TEXT NOT NULL, -- The path of the request, such as /api/v1/subgraphs/1
+    table_name VARCHAR(64), -- The changed table, it's NULL if the endpoint doesn't change a known table
+    record_id VARCHAR(64), -- The id of the changed record
+    status INTEGER NOT NULL, -- The status code of the response
+    changes JSONB, -- The changed fields of the record, such as {"name": {"old": "a", "new": "b"}}
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now() -- When the request is finished
+  );
+
+CREATE INDEX IF NOT EXISTS idx_biomedgps_audit_log_created_at ON biomedgps_audit_log (created_at);
+CREATE INDEX IF NOT EXISTS idx_biomedgps_audit_log_username ON biomedgps_audit_log (username);
+CREATE INDEX IF NOT EXISTS idx_biomedgps_audit_log_record ON biomedgps_audit_log (table_name, record_id);
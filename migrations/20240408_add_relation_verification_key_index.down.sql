@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_key_relation_verification_table;
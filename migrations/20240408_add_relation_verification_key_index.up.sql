@@ -0,0 +1,2 @@
+-- The latest verdict of a relation is attached to the relation list by the relation type and the entities, so the verifications are looked up by them.
+CREATE INDEX IF NOT EXISTS idx_key_relation_verification_table ON biomedgps_relation_verification (relation_type, source_id, source_type, target_id, target_type, created_at);
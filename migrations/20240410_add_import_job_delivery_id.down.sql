@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_delivery_id_import_job_table;
+ALTER TABLE biomedgps_import_job DROP COLUMN IF EXISTS delivery_id;
@@ -0,0 +1,3 @@
+-- The id of the webhook delivery which schedules the job, it's the signature of the timestamped event, so a repeated delivery is rejected instead of importing the dataset again.
+ALTER TABLE biomedgps_import_job ADD COLUMN IF NOT EXISTS delivery_id TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_delivery_id_import_job_table ON biomedgps_import_job (delivery_id);
@@ -0,0 +1,290 @@
+//! Metrics and graph-based baselines for benchmarking the prioritization methods, such as KGE scores, random walk with restart (RWR) and shared neighbors.
+//!
+//! The functions in this file are pure, the data are loaded and the results are stored by the benchmark module in the model directory.
+
+use std::collections::HashMap;
+
+/// The restart probability of the random walk with restart.
+pub const DEFAULT_RESTART_PROB: f64 = 0.3;
+/// The number of iterations of the random walk with restart. It's enough for the scores of the nodes near the seed node to converge.
+pub const DEFAULT_RWR_STEPS: usize = 10;
+
+/// Sort the scored candidates by score in descending order. The negative candidates are ranked before the positive ones when they have the same score, so the ties never inflate the metrics.
+fn rank(scores: &[(f64, bool)]) -> Vec<(f64, bool)> {
+    let mut ranked = scores.to_vec();
+    ranked.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.1.cmp(&b.1))
+    });
+    ranked
+}
+
+/// Compute the area under the ROC curve, the ties between a positive and a negative candidate are counted as 0.5.
+///
+/// # Arguments
+///
+/// * `scores` - The (score, is_positive) pairs of the candidates.
+///
+/// # Returns
+///
+/// * `Option<f64>` - None if there are no positive or no negative candidates.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::benchmark::auroc;
+///
+/// let scores = vec![(0.9, true), (0.8, false), (0.7, true), (0.1, false)];
+/// assert_eq!(auroc(&scores), Some(0.75));
+/// ```
+pub fn auroc(scores: &[(f64, bool)]) -> Option<f64> {
+    let positives: Vec<f64> = scores.iter().filter(|s| s.1).map(|s| s.0).collect();
+    let negatives: Vec<f64> = scores.iter().filter(|s| !s.1).map(|s| s.0).collect();
+    if positives.is_empty() || negatives.is_empty() {
+        return None;
+    }
+
+    let mut hits = 0.0;
+    for p in positives.iter() {
+        for n in negatives.iter() {
+            if p > n {
+                hits += 1.0;
+            } else if p == n {
+                hits += 0.5;
+            }
+        }
+    }
+
+    Some(hits / (positives.len() * negatives.len()) as f64)
+}
+
+/// Compute the area under the precision-recall curve as the average precision.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::benchmark::auprc;
+///
+/// let scores = vec![(0.9, true), (0.8, false), (0.7, true), (0.1, false)];
+/// let value = auprc(&scores).unwrap();
+/// assert!((value - (1.0 + 2.0 / 3.0) / 2.0).abs() < 1e-9);
+/// ```
+pub fn auprc(scores: &[(f64, bool)]) -> Option<f64> {
+    let num_positives = scores.iter().filter(|s| s.1).count();
+    if num_positives == 0 {
+        return None;
+    }
+
+    let mut hits = 0;
+    let mut precision_sum = 0.0;
+    for (index, (_, is_positive)) in rank(scores).iter().enumerate() {
+        if *is_positive {
+            hits += 1;
+            precision_sum += hits as f64 / (index + 1) as f64;
+        }
+    }
+
+    Some(precision_sum / num_positives as f64)
+}
+
+/// Compute the fraction of the positive candidates which are ranked in the top k.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::benchmark::recall_at_k;
+///
+/// let scores = vec![(0.9, true), (0.8, false), (0.7, true), (0.1, false)];
+/// assert_eq!(recall_at_k(&scores, 1), Some(0.5));
+/// assert_eq!(recall_at_k(&scores, 3), Some(1.0));
+/// ```
+pub fn recall_at_k(scores: &[(f64, bool)], k: usize) -> Option<f64> {
+    let num_positives = scores.iter().filter(|s| s.1).count();
+    if num_positives == 0 {
+        return None;
+    }
+
+    let hits = rank(scores).iter().take(k).filter(|s| s.1).count();
+    Some(hits as f64 / num_positives as f64)
+}
+
+/// An undirected graph which is stored as adjacency lists, the nodes are identified by the composed node ids, such as Gene::ENTREZ:1017.
+#[derive(Debug, Clone, Default)]
+pub struct AdjacencyGraph {
+    index: HashMap<String, usize>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl AdjacencyGraph {
+    pub fn new() -> Self {
+        AdjacencyGraph::default()
+    }
+
+    fn add_node(&mut self, node_id: &str) -> usize {
+        match self.index.get(node_id) {
+            Some(index) => *index,
+            None => {
+                let index = self.neighbors.len();
+                self.index.insert(node_id.to_string(), index);
+                self.neighbors.push(vec![]);
+                index
+            }
+        }
+    }
+
+    /// Add an undirected edge, the self loops are ignored.
+    pub fn add_edge(&mut self, source_id: &str, target_id: &str) {
+        let source = self.add_node(source_id);
+        let target = self.add_node(target_id);
+        if source != target {
+            self.neighbors[source].push(target);
+            self.neighbors[target].push(source);
+        }
+    }
+
+    pub fn node_index(&self, node_id: &str) -> Option<usize> {
+        self.index.get(node_id).copied()
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// The neighbors of a node without the held-out edge. All the edges between the pair of the held-out nodes are skipped.
+    fn neighbors_of(&self, node: usize, excluded: Option<(usize, usize)>) -> Vec<usize> {
+        self.neighbors[node]
+            .iter()
+            .copied()
+            .filter(|neighbor| match excluded {
+                Some((a, b)) => !((node == a && *neighbor == b) || (node == b && *neighbor == a)),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Count the distinct common neighbors of two nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The index of the source node.
+    /// * `target` - The index of the target node.
+    /// * `excluded` - The held-out edge, it's ignored when collecting the neighbors.
+    pub fn shared_neighbors(
+        &self,
+        source: usize,
+        target: usize,
+        excluded: Option<(usize, usize)>,
+    ) -> f64 {
+        let source_neighbors: std::collections::HashSet<usize> =
+            self.neighbors_of(source, excluded).into_iter().collect();
+        let target_neighbors: std::collections::HashSet<usize> =
+            self.neighbors_of(target, excluded).into_iter().collect();
+
+        source_neighbors.intersection(&target_neighbors).count() as f64
+    }
+
+    /// Run the random walk with restart from the seed node. The probabilities are propagated sparsely, so only the nodes reached by the walker are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The index of the seed node.
+    /// * `restart_prob` - The probability of jumping back to the seed node at each step.
+    /// * `steps` - The number of iterations.
+    /// * `excluded` - The held-out edge, the walker never crosses it.
+    ///
+    /// # Returns
+    ///
+    /// * `HashMap<usize, f64>` - The visiting probabilities of the reached nodes.
+    pub fn rwr(
+        &self,
+        seed: usize,
+        restart_prob: f64,
+        steps: usize,
+        excluded: Option<(usize, usize)>,
+    ) -> HashMap<usize, f64> {
+        let mut probs: HashMap<usize, f64> = HashMap::from([(seed, 1.0)]);
+        let mut cached_neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for _ in 0..steps {
+            let mut next: HashMap<usize, f64> = HashMap::from([(seed, restart_prob)]);
+            for (node, prob) in probs.iter() {
+                let neighbors = cached_neighbors
+                    .entry(*node)
+                    .or_insert_with(|| self.neighbors_of(*node, excluded));
+
+                // A dangling node sends the walker back to the seed node.
+                if neighbors.is_empty() {
+                    *next.entry(seed).or_insert(0.0) += (1.0 - restart_prob) * prob;
+                    continue;
+                }
+
+                let share = (1.0 - restart_prob) * prob / neighbors.len() as f64;
+                for neighbor in neighbors.iter() {
+                    *next.entry(*neighbor).or_insert(0.0) += share;
+                }
+            }
+            probs = next;
+        }
+
+        probs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_graph() -> AdjacencyGraph {
+        // A triangle Disease::D1 - Gene::G1 - Gene::G2, and Gene::G3 is linked to it through Gene::G4.
+        let mut graph = AdjacencyGraph::new();
+        graph.add_edge("Disease::D1", "Gene::G1");
+        graph.add_edge("Disease::D1", "Gene::G2");
+        graph.add_edge("Gene::G1", "Gene::G2");
+        graph.add_edge("Gene::G3", "Gene::G4");
+        graph.add_edge("Gene::G4", "Gene::G2");
+        graph
+    }
+
+    #[test]
+    fn test_metrics_with_ties() {
+        // The positive candidate is tied with a negative one, it should be ranked after it.
+        let scores = vec![(0.5, false), (0.5, true), (0.1, false)];
+        assert_eq!(auroc(&scores), Some(0.75));
+        assert_eq!(recall_at_k(&scores, 1), Some(0.0));
+        assert_eq!(auprc(&scores), Some(0.5));
+        assert_eq!(auroc(&[(0.5, true)]), None);
+        assert_eq!(auprc(&[(0.5, false)]), None);
+    }
+
+    #[test]
+    fn test_shared_neighbors_without_held_out_edge() {
+        let graph = build_graph();
+        let d1 = graph.node_index("Disease::D1").unwrap();
+        let g1 = graph.node_index("Gene::G1").unwrap();
+        let g4 = graph.node_index("Gene::G4").unwrap();
+
+        assert_eq!(graph.shared_neighbors(d1, g1, None), 1.0);
+        assert_eq!(graph.shared_neighbors(d1, g4, None), 1.0);
+        // The held-out edge D1 - G2 hides G2 from the neighbors of D1.
+        let g2 = graph.node_index("Gene::G2").unwrap();
+        assert_eq!(graph.shared_neighbors(d1, g4, Some((d1, g2))), 0.0);
+    }
+
+    #[test]
+    fn test_rwr() {
+        let graph = build_graph();
+        let d1 = graph.node_index("Disease::D1").unwrap();
+        let g1 = graph.node_index("Gene::G1").unwrap();
+        let g3 = graph.node_index("Gene::G3").unwrap();
+
+        let probs = graph.rwr(d1, DEFAULT_RESTART_PROB, DEFAULT_RWR_STEPS, None);
+        let total: f64 = probs.values().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(probs[&g1] > probs[&g3]);
+
+        // The walker can't reach G1 directly when the edge D1 - G1 is held out.
+        let held_out = graph.rwr(d1, DEFAULT_RESTART_PROB, 1, Some((d1, g1)));
+        assert!(held_out.get(&g1).is_none());
+    }
+}
@@ -0,0 +1,161 @@
+//! A force-directed layout (Fruchterman-Reingold) for the large graphs which are too slow to be laid out in the browser.
+//!
+//! The repulsive forces are only computed between the nodes in the neighboring cells of a grid, so each iteration is close to linear in the number of nodes. The functions in this file are pure, the nodes are identified by their indexes.
+
+use std::collections::HashMap;
+
+/// The default number of iterations, the layout is stable enough for the graphs with thousands of nodes.
+pub const DEFAULT_LAYOUT_ITERATIONS: usize = 100;
+/// The max number of iterations which can be requested.
+pub const MAX_LAYOUT_ITERATIONS: usize = 1000;
+/// The ideal distance between two connected nodes, the canvas grows with the number of nodes.
+pub const DEFAULT_EDGE_LENGTH: f64 = 100.0;
+/// The max number of nodes which can be laid out in a request.
+pub const MAX_LAYOUT_NODES: usize = 20000;
+
+/// Compute the positions of the nodes by the force-directed layout.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `edges` - The (source, target) indexes of the edges, the self loops are ignored.
+/// * `iterations` - The number of iterations.
+/// * `edge_length` - The ideal distance between two connected nodes.
+///
+/// # Returns
+///
+/// * `Vec<(f64, f64)>` - The (x, y) positions of the nodes, the layout is centered at (0, 0). It's deterministic for the same input.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::layout::force_directed_layout;
+///
+/// let positions = force_directed_layout(3, &vec![(0, 1), (1, 2)], 50, 100.0);
+/// assert_eq!(positions.len(), 3);
+/// assert!(positions.iter().all(|(x, y)| x.is_finite() && y.is_finite()));
+/// ```
+pub fn force_directed_layout(
+    num_nodes: usize,
+    edges: &Vec<(usize, usize)>,
+    iterations: usize,
+    edge_length: f64,
+) -> Vec<(f64, f64)> {
+    if num_nodes == 0 {
+        return vec![];
+    }
+
+    let k = edge_length;
+    // The nodes start on a sunflower spiral, so the initial positions are spread evenly and deterministic.
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+    let mut positions = (0..num_nodes)
+        .map(|i| {
+            let r = k * (i as f64 + 0.5).sqrt();
+            let theta = i as f64 * golden_angle;
+            (r * theta.cos(), r * theta.sin())
+        })
+        .collect::<Vec<(f64, f64)>>();
+
+    // The temperature limits the max displacement of a node in an iteration, it cools down linearly.
+    let initial_temperature = k * (num_nodes as f64).sqrt() / 10.0;
+    // The repulsive forces are ignored between the nodes which are far away from each other.
+    let cell_size = 2.0 * k;
+
+    for iteration in 0..iterations {
+        let temperature = initial_temperature * (1.0 - iteration as f64 / iterations as f64);
+        let mut displacements = vec![(0.0, 0.0); num_nodes];
+
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, (x, y)) in positions.iter().enumerate() {
+            let cell = (
+                (x / cell_size).floor() as i64,
+                (y / cell_size).floor() as i64,
+            );
+            grid.entry(cell).or_default().push(i);
+        }
+
+        for (&(cx, cy), members) in grid.iter() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let neighbors = match grid.get(&(cx + dx, cy + dy)) {
+                        Some(neighbors) => neighbors,
+                        None => continue,
+                    };
+
+                    for &i in members {
+                        for &j in neighbors {
+                            if i == j {
+                                continue;
+                            }
+
+                            let (delta_x, delta_y, distance) = delta(&positions, i, j);
+                            if distance > cell_size {
+                                continue;
+                            }
+
+                            let force = k * k / distance;
+                            displacements[i].0 += delta_x / distance * force;
+                            displacements[i].1 += delta_y / distance * force;
+                        }
+                    }
+                }
+            }
+        }
+
+        for &(source, target) in edges {
+            if source == target || source >= num_nodes || target >= num_nodes {
+                continue;
+            }
+
+            let (delta_x, delta_y, distance) = delta(&positions, source, target);
+            let force = distance * distance / k;
+            displacements[source].0 -= delta_x / distance * force;
+            displacements[source].1 -= delta_y / distance * force;
+            displacements[target].0 += delta_x / distance * force;
+            displacements[target].1 += delta_y / distance * force;
+        }
+
+        for (i, (dx, dy)) in displacements.iter().enumerate() {
+            let length = (dx * dx + dy * dy).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature);
+                positions[i].0 += dx / length * step;
+                positions[i].1 += dy / length * step;
+            }
+        }
+    }
+
+    let center_x = positions.iter().map(|(x, _)| x).sum::<f64>() / num_nodes as f64;
+    let center_y = positions.iter().map(|(_, y)| y).sum::<f64>() / num_nodes as f64;
+    positions
+        .into_iter()
+        .map(|(x, y)| (x - center_x, y - center_y))
+        .collect()
+}
+
+/// The vector from node j to node i and its length, the length is never zero to avoid the division by zero.
+fn delta(positions: &Vec<(f64, f64)>, i: usize, j: usize) -> (f64, f64, f64) {
+    let delta_x = positions[i].0 - positions[j].0;
+    let delta_y = positions[i].1 - positions[j].1;
+    let distance = (delta_x * delta_x + delta_y * delta_y).sqrt().max(0.01);
+    (delta_x, delta_y, distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_force_directed_layout() {
+        assert!(force_directed_layout(0, &vec![], 10, DEFAULT_EDGE_LENGTH).is_empty());
+
+        let edges = vec![(0, 1), (1, 2), (2, 0), (3, 4)];
+        let positions = force_directed_layout(5, &edges, DEFAULT_LAYOUT_ITERATIONS, 100.0);
+        assert_eq!(positions, force_directed_layout(5, &edges, 100, 100.0));
+
+        // The connected nodes are closer than the disconnected ones.
+        let distance = |i: usize, j: usize| delta(&positions, i, j).2;
+        assert!(distance(0, 1) < distance(0, 3));
+        assert!(distance(3, 4) < distance(2, 4));
+    }
+}
@@ -1,2 +1,7 @@
 //! Algorithms for machine learning
 
+pub mod benchmark;
+pub mod layout;
+pub mod structure;
+pub mod path;
+pub mod node2vec;
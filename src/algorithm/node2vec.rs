@@ -0,0 +1,388 @@
+//! Train the node embeddings by the random walks on a graph (node2vec, DeepWalk), so the entity embeddings can be produced without an external KGE training pipeline.
+//!
+//! The walks are biased by the return parameter p and the in-out parameter q of node2vec, the walks are unbiased (DeepWalk) when p = q = 1. The nodes in the walks are embedded by the skip-gram model with negative sampling. The graph is treated as an undirected simple graph, the functions in this file are pure and the nodes are identified by their indexes.
+
+/// The hyperparameters of the node2vec training.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node2VecConfig {
+    pub dimension: usize,
+    // The number of nodes in a walk, including the start node.
+    pub walk_length: usize,
+    pub walks_per_node: usize,
+    // The max distance between a node and its context nodes in a walk.
+    pub window_size: usize,
+    pub num_negatives: usize,
+    pub epochs: usize,
+    // The initial learning rate, it decays linearly to 0.0001 of itself.
+    pub learning_rate: f32,
+    // The return parameter, a larger p makes the walks less likely to go back to the previous node.
+    pub p: f64,
+    // The in-out parameter, a smaller q makes the walks go further from the previous node (DFS-like) and a larger q keeps them local (BFS-like).
+    pub q: f64,
+    pub seed: u64,
+}
+
+impl Default for Node2VecConfig {
+    fn default() -> Self {
+        Node2VecConfig {
+            dimension: 128,
+            walk_length: 40,
+            walks_per_node: 10,
+            window_size: 5,
+            num_negatives: 5,
+            epochs: 1,
+            learning_rate: 0.025,
+            p: 1.0,
+            q: 1.0,
+            seed: 42,
+        }
+    }
+}
+
+/// A small seeded pseudo random number generator (SplitMix64), so the same embeddings are trained for the same graph and seed.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform number in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A uniform index in [0, n), n must be larger than 0.
+    pub fn next_index(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Build the sorted neighbor lists of the nodes, the directions, the duplicated edges, the self loops and the edges whose nodes are out of range are ignored.
+pub fn build_adjacency(num_nodes: usize, edges: &Vec<(usize, usize)>) -> Vec<Vec<usize>> {
+    let mut adjacency = vec![vec![]; num_nodes];
+    for &(source, target) in edges {
+        if source == target || source >= num_nodes || target >= num_nodes {
+            continue;
+        }
+
+        adjacency[source].push(target);
+        adjacency[target].push(source);
+    }
+
+    for neighbors in adjacency.iter_mut() {
+        neighbors.sort_unstable();
+        neighbors.dedup();
+    }
+
+    adjacency
+}
+
+/// Generate the node2vec walks, each node starts walks_per_node walks. The next node is sampled by rejection sampling, so no transition table is needed for the second-order walks.
+///
+/// # Arguments
+///
+/// * `adjacency` - The sorted neighbor lists of the nodes, such as the output of build_adjacency.
+/// * `config` - The walk_length, walks_per_node, p and q are used.
+/// * `rng` - The random number generator.
+///
+/// # Returns
+///
+/// * `Vec<Vec<usize>>` - The walks, a walk stops early at a node without neighbors. The isolated nodes have no walks.
+pub fn random_walks(
+    adjacency: &Vec<Vec<usize>>,
+    config: &Node2VecConfig,
+    rng: &mut SplitMix64,
+) -> Vec<Vec<usize>> {
+    let inverse_p = 1.0 / config.p;
+    let inverse_q = 1.0 / config.q;
+    let max_weight = inverse_p.max(1.0).max(inverse_q);
+
+    let mut walks = vec![];
+    for _ in 0..config.walks_per_node {
+        for start in 0..adjacency.len() {
+            if adjacency[start].is_empty() || config.walk_length == 0 {
+                continue;
+            }
+
+            let mut walk = vec![start];
+            while walk.len() < config.walk_length {
+                let current = *walk.last().unwrap();
+                let neighbors = &adjacency[current];
+                if neighbors.is_empty() {
+                    break;
+                }
+
+                let next = if walk.len() == 1 {
+                    neighbors[rng.next_index(neighbors.len())]
+                } else {
+                    let previous = walk[walk.len() - 2];
+                    loop {
+                        let candidate = neighbors[rng.next_index(neighbors.len())];
+                        let weight = if candidate == previous {
+                            inverse_p
+                        } else if adjacency[previous].binary_search(&candidate).is_ok() {
+                            1.0
+                        } else {
+                            inverse_q
+                        };
+
+                        if rng.next_f64() * max_weight < weight {
+                            break candidate;
+                        }
+                    }
+                };
+                walk.push(next);
+            }
+            walks.push(walk);
+        }
+    }
+
+    walks
+}
+
+/// The noise distribution of the negative sampling, the nodes are sampled in proportion to their frequencies in the walks raised to the power of 0.75.
+struct NegativeSampler {
+    cumulative: Vec<f64>,
+}
+
+impl NegativeSampler {
+    fn new(num_nodes: usize, walks: &Vec<Vec<usize>>) -> Self {
+        let mut counts = vec![0usize; num_nodes];
+        for walk in walks {
+            for &node in walk {
+                counts[node] += 1;
+            }
+        }
+
+        let mut total = 0.0;
+        let cumulative = counts
+            .iter()
+            .map(|&count| {
+                total += (count as f64).powf(0.75);
+                total
+            })
+            .collect();
+
+        NegativeSampler { cumulative }
+    }
+
+    fn sample(&self, rng: &mut SplitMix64) -> usize {
+        let total = *self.cumulative.last().unwrap_or(&0.0);
+        let value = rng.next_f64() * total;
+        self.cumulative
+            .partition_point(|&c| c <= value)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x.clamp(-6.0, 6.0)).exp())
+}
+
+/// Train the node embeddings on the walks by the skip-gram model with negative sampling.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `walks` - The walks, such as the output of random_walks.
+/// * `config` - The dimension, window_size, num_negatives, epochs and learning_rate are used.
+/// * `rng` - The random number generator.
+///
+/// # Returns
+///
+/// * `Vec<Vec<f32>>` - The embeddings, in the same order as the nodes. The nodes which are not in any walk keep their small random initial embeddings.
+pub fn train_skip_gram(
+    num_nodes: usize,
+    walks: &Vec<Vec<usize>>,
+    config: &Node2VecConfig,
+    rng: &mut SplitMix64,
+) -> Vec<Vec<f32>> {
+    let dimension = config.dimension;
+    let mut embeddings: Vec<Vec<f32>> = (0..num_nodes)
+        .map(|_| {
+            (0..dimension)
+                .map(|_| (rng.next_f64() as f32 - 0.5) / dimension as f32)
+                .collect()
+        })
+        .collect();
+    let mut contexts = vec![vec![0.0f32; dimension]; num_nodes];
+    if num_nodes == 0 || walks.is_empty() {
+        return embeddings;
+    }
+
+    let sampler = NegativeSampler::new(num_nodes, walks);
+    let total_steps = (config.epochs * walks.len()).max(1) as f32;
+    let min_learning_rate = config.learning_rate * 0.0001;
+    let mut gradient = vec![0.0f32; dimension];
+    let mut step = 0;
+    for _ in 0..config.epochs {
+        for walk in walks {
+            let learning_rate =
+                (config.learning_rate * (1.0 - step as f32 / total_steps)).max(min_learning_rate);
+            step += 1;
+
+            for (i, &node) in walk.iter().enumerate() {
+                let first = i.saturating_sub(config.window_size);
+                let last = (i + config.window_size).min(walk.len() - 1);
+                for j in first..=last {
+                    if j == i {
+                        continue;
+                    }
+
+                    // The context node is the positive sample, followed by the negative samples.
+                    gradient.iter_mut().for_each(|g| *g = 0.0);
+                    for k in 0..=config.num_negatives {
+                        let (target, label) = if k == 0 {
+                            (walk[j], 1.0)
+                        } else {
+                            let negative = sampler.sample(rng);
+                            if negative == walk[j] {
+                                continue;
+                            }
+                            (negative, 0.0)
+                        };
+
+                        let score = embeddings[node]
+                            .iter()
+                            .zip(contexts[target].iter())
+                            .map(|(a, b)| a * b)
+                            .sum::<f32>();
+                        let g = (label - sigmoid(score)) * learning_rate;
+                        for ((gradient, context), value) in gradient
+                            .iter_mut()
+                            .zip(contexts[target].iter_mut())
+                            .zip(embeddings[node].iter())
+                        {
+                            *gradient += g * *context;
+                            *context += g * value;
+                        }
+                    }
+
+                    for (value, gradient) in embeddings[node].iter_mut().zip(gradient.iter()) {
+                        *value += gradient;
+                    }
+                }
+            }
+        }
+    }
+
+    embeddings
+}
+
+/// Train the node2vec embeddings of a graph.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `edges` - The (source, target) indexes of the edges.
+/// * `config` - The hyperparameters.
+///
+/// # Returns
+///
+/// * `Vec<Vec<f32>>` - The embeddings, in the same order as the nodes. The results are deterministic for the same input.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::node2vec::{train_node2vec, Node2VecConfig};
+///
+/// let config = Node2VecConfig { dimension: 8, walk_length: 10, walks_per_node: 2, ..Default::default() };
+/// let embeddings = train_node2vec(4, &vec![(0, 1), (1, 2), (2, 3)], &config);
+/// assert_eq!(embeddings.len(), 4);
+/// assert!(embeddings.iter().all(|embedding| embedding.len() == 8));
+/// ```
+pub fn train_node2vec(
+    num_nodes: usize,
+    edges: &Vec<(usize, usize)>,
+    config: &Node2VecConfig,
+) -> Vec<Vec<f32>> {
+    let mut rng = SplitMix64::new(config.seed);
+    let adjacency = build_adjacency(num_nodes, edges);
+    let walks = random_walks(&adjacency, config, &mut rng);
+    train_skip_gram(num_nodes, &walks, config, &mut rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &Vec<f32>, b: &Vec<f32>) -> f32 {
+        let dot = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>();
+        let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm_a * norm_b)
+    }
+
+    #[test]
+    fn test_random_walks() {
+        let adjacency = build_adjacency(5, &vec![(0, 1), (1, 0), (1, 2), (2, 3), (3, 3), (0, 9)]);
+        assert_eq!(
+            adjacency,
+            vec![vec![1], vec![0, 2], vec![1, 3], vec![2], vec![]]
+        );
+
+        let config = Node2VecConfig {
+            walk_length: 6,
+            walks_per_node: 3,
+            p: 0.5,
+            q: 2.0,
+            ..Default::default()
+        };
+        let walks = random_walks(&adjacency, &config, &mut SplitMix64::new(1));
+
+        // The isolated node has no walks, and each step of a walk follows an edge.
+        assert_eq!(walks.len(), 12);
+        for walk in walks.iter() {
+            assert_eq!(walk.len(), 6);
+            assert!(walk
+                .windows(2)
+                .all(|w| adjacency[w[0]].binary_search(&w[1]).is_ok()));
+        }
+
+        assert_eq!(
+            walks,
+            random_walks(&adjacency, &config, &mut SplitMix64::new(1))
+        );
+    }
+
+    #[test]
+    fn test_train_node2vec() {
+        // Two 4-cliques which are linked by a single edge.
+        let mut edges = vec![];
+        for offset in [0, 4] {
+            for i in 0..4 {
+                for j in (i + 1)..4 {
+                    edges.push((offset + i, offset + j));
+                }
+            }
+        }
+        edges.push((3, 4));
+
+        let config = Node2VecConfig {
+            dimension: 16,
+            walk_length: 20,
+            walks_per_node: 20,
+            window_size: 3,
+            epochs: 3,
+            ..Default::default()
+        };
+        let embeddings = train_node2vec(8, &edges, &config);
+        assert_eq!(embeddings, train_node2vec(8, &edges, &config));
+
+        // The nodes in the same clique are closer than the nodes in different cliques.
+        assert!(cosine(&embeddings[0], &embeddings[1]) > cosine(&embeddings[0], &embeddings[6]));
+        assert!(cosine(&embeddings[5], &embeddings[7]) > cosine(&embeddings[5], &embeddings[1]));
+    }
+}
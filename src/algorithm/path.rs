@@ -0,0 +1,128 @@
+//! Enumerate the paths between two nodes of a graph, such as the union of the paths which are returned by the graph database, so the paths can be scored one by one.
+//!
+//! The edges are traversed in both directions like the path queries of the graph database, and a path never visits a node twice. The functions in this file are pure, the nodes and edges are identified by their indexes.
+
+/// The default max number of the enumerated paths, the dense graphs can have too many paths to be scored.
+pub const DEFAULT_MAX_ENUMERATED_PATHS: usize = 10000;
+
+/// Enumerate the simple paths between two nodes by depth-first search.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `edges` - The (source, target) indexes of the edges, the self loops and the edges whose nodes are out of range are ignored.
+/// * `start` - The index of the start node.
+/// * `end` - The index of the end node.
+/// * `max_hops` - The max number of edges in a path.
+/// * `max_paths` - The search stops after so many paths are found.
+///
+/// # Returns
+///
+/// * `Vec<Vec<usize>>` - The paths, each path is the indexes of its edges in the order from the start node to the end node. The order of the paths is deterministic for the same input.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::path::enumerate_paths;
+///
+/// // 0 - 1 - 2 and 0 - 2, the second edge is reversed.
+/// let paths = enumerate_paths(3, &vec![(0, 1), (2, 1), (0, 2)], 0, 2, 2, 100);
+/// assert_eq!(paths, vec![vec![0, 1], vec![2]]);
+/// ```
+pub fn enumerate_paths(
+    num_nodes: usize,
+    edges: &Vec<(usize, usize)>,
+    start: usize,
+    end: usize,
+    max_hops: usize,
+    max_paths: usize,
+) -> Vec<Vec<usize>> {
+    let mut paths = vec![];
+    if start >= num_nodes || end >= num_nodes || start == end || max_hops == 0 {
+        return paths;
+    }
+
+    // The adjacency lists keep the order of the edges, so the paths are found in a deterministic order.
+    let mut adjacency: Vec<Vec<(usize, usize)>> = vec![vec![]; num_nodes];
+    for (i, &(source, target)) in edges.iter().enumerate() {
+        if source == target || source >= num_nodes || target >= num_nodes {
+            continue;
+        }
+
+        adjacency[source].push((target, i));
+        adjacency[target].push((source, i));
+    }
+
+    let mut visited = vec![false; num_nodes];
+    let mut path = vec![];
+    visited[start] = true;
+    search(
+        &adjacency,
+        start,
+        end,
+        max_hops,
+        max_paths,
+        &mut visited,
+        &mut path,
+        &mut paths,
+    );
+
+    paths
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search(
+    adjacency: &Vec<Vec<(usize, usize)>>,
+    node: usize,
+    end: usize,
+    max_hops: usize,
+    max_paths: usize,
+    visited: &mut Vec<bool>,
+    path: &mut Vec<usize>,
+    paths: &mut Vec<Vec<usize>>,
+) {
+    for &(neighbor, edge) in adjacency[node].iter() {
+        if paths.len() >= max_paths {
+            return;
+        }
+
+        if visited[neighbor] {
+            continue;
+        }
+
+        path.push(edge);
+        if neighbor == end {
+            paths.push(path.clone());
+        } else if path.len() < max_hops {
+            visited[neighbor] = true;
+            search(
+                adjacency, neighbor, end, max_hops, max_paths, visited, path, paths,
+            );
+            visited[neighbor] = false;
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enumerate_paths() {
+        // 0 - 1 - 3, 0 - 2 - 3, 1 - 2 and a parallel edge between 0 and 1.
+        let edges = vec![(0, 1), (1, 3), (0, 2), (2, 3), (1, 2), (1, 0)];
+        let paths = enumerate_paths(4, &edges, 0, 3, 2, 100);
+        assert_eq!(paths, vec![vec![0, 1], vec![2, 3], vec![5, 1]]);
+
+        // The longer paths go through the edge between 1 and 2, but never visit a node twice.
+        let paths = enumerate_paths(4, &edges, 0, 3, 3, 100);
+        assert_eq!(paths.len(), 6);
+        assert!(paths.contains(&vec![0, 4, 3]));
+        assert!(paths.iter().all(|path| path.len() <= 3));
+
+        assert_eq!(enumerate_paths(4, &edges, 0, 3, 3, 2).len(), 2);
+        assert!(enumerate_paths(4, &edges, 0, 0, 3, 100).is_empty());
+        assert!(enumerate_paths(4, &edges, 0, 9, 3, 100).is_empty());
+    }
+}
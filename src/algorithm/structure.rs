@@ -0,0 +1,179 @@
+//! Local structure metrics of a graph, such as the clustering coefficients and the k-core decomposition, which can be mapped to the size or opacity of the nodes.
+//!
+//! The graph is treated as an undirected simple graph, so the directions, the duplicated edges and the self loops are ignored. The functions in this file are pure, the nodes are identified by their indexes.
+
+use std::collections::HashSet;
+
+/// Build the neighbor sets of the nodes, the edges whose nodes are out of range are ignored.
+fn neighbor_sets(num_nodes: usize, edges: &Vec<(usize, usize)>) -> Vec<HashSet<usize>> {
+    let mut neighbors = vec![HashSet::new(); num_nodes];
+    for &(source, target) in edges {
+        if source == target || source >= num_nodes || target >= num_nodes {
+            continue;
+        }
+
+        neighbors[source].insert(target);
+        neighbors[target].insert(source);
+    }
+
+    neighbors
+}
+
+/// Compute the local clustering coefficient of each node, i.e. the fraction of the pairs of its neighbors which are connected to each other.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `edges` - The (source, target) indexes of the edges.
+///
+/// # Returns
+///
+/// * `Vec<f64>` - The coefficients in [0, 1], in the same order as the nodes. It's 0 for the nodes with less than 2 neighbors.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::structure::local_clustering_coefficients;
+///
+/// // A triangle with a pendant node.
+/// let coefficients = local_clustering_coefficients(4, &vec![(0, 1), (1, 2), (2, 0), (2, 3)]);
+/// assert_eq!(coefficients, vec![1.0, 1.0, 1.0 / 3.0, 0.0]);
+/// ```
+pub fn local_clustering_coefficients(num_nodes: usize, edges: &Vec<(usize, usize)>) -> Vec<f64> {
+    let neighbors = neighbor_sets(num_nodes, edges);
+
+    neighbors
+        .iter()
+        .map(|node_neighbors| {
+            let degree = node_neighbors.len();
+            if degree < 2 {
+                return 0.0;
+            }
+
+            // Each link between two neighbors is counted twice, once from each end.
+            let links = node_neighbors
+                .iter()
+                .map(|&j| {
+                    neighbors[j]
+                        .iter()
+                        .filter(|k| node_neighbors.contains(k))
+                        .count()
+                })
+                .sum::<usize>()
+                / 2;
+
+            2.0 * links as f64 / (degree * (degree - 1)) as f64
+        })
+        .collect()
+}
+
+/// Compute the core number of each node by the k-core decomposition (Batagelj and Zaversnik), i.e. the largest k such that the node is in a subgraph whose nodes all have at least k neighbors.
+///
+/// # Arguments
+///
+/// * `num_nodes` - The number of nodes.
+/// * `edges` - The (source, target) indexes of the edges.
+///
+/// # Returns
+///
+/// * `Vec<usize>` - The core numbers, in the same order as the nodes. It's 0 for the isolated nodes.
+///
+/// # Example
+///
+/// ```
+/// use biomedgps::algorithm::structure::core_numbers;
+///
+/// // A triangle with a pendant node.
+/// let cores = core_numbers(4, &vec![(0, 1), (1, 2), (2, 0), (2, 3)]);
+/// assert_eq!(cores, vec![2, 2, 2, 1]);
+/// ```
+pub fn core_numbers(num_nodes: usize, edges: &Vec<(usize, usize)>) -> Vec<usize> {
+    let neighbors = neighbor_sets(num_nodes, edges);
+    let mut degrees = neighbors.iter().map(|n| n.len()).collect::<Vec<usize>>();
+    let max_degree = degrees.iter().copied().max().unwrap_or(0);
+
+    // The nodes are sorted by their degrees with a bucket sort, bin_starts[d] is the position of the first node with degree d.
+    let mut bin_starts = vec![0; max_degree + 1];
+    for &degree in degrees.iter() {
+        bin_starts[degree] += 1;
+    }
+    let mut start = 0;
+    for bin in bin_starts.iter_mut() {
+        let count = *bin;
+        *bin = start;
+        start += count;
+    }
+
+    let mut order = vec![0; num_nodes];
+    let mut positions = vec![0; num_nodes];
+    let mut next = bin_starts.clone();
+    for (node, &degree) in degrees.iter().enumerate() {
+        positions[node] = next[degree];
+        order[next[degree]] = node;
+        next[degree] += 1;
+    }
+
+    // The node with the smallest remaining degree is removed in each step, and the degrees of its neighbors with larger degrees are decreased by moving them to the previous bin.
+    for i in 0..num_nodes {
+        let node = order[i];
+        for &neighbor in neighbors[node].iter() {
+            if degrees[neighbor] > degrees[node] {
+                let degree = degrees[neighbor];
+                let first = order[bin_starts[degree]];
+                if first != neighbor {
+                    let (pos_neighbor, pos_first) = (positions[neighbor], positions[first]);
+                    order.swap(pos_neighbor, pos_first);
+                    positions[neighbor] = pos_first;
+                    positions[first] = pos_neighbor;
+                }
+                bin_starts[degree] += 1;
+                degrees[neighbor] -= 1;
+            }
+        }
+    }
+
+    degrees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_clustering_coefficients() {
+        assert!(local_clustering_coefficients(0, &vec![]).is_empty());
+
+        // The duplicated edges, the reversed edges and the self loops don't change the coefficients.
+        let edges = vec![(0, 1), (1, 0), (1, 2), (2, 0), (2, 2), (2, 3), (0, 9)];
+        assert_eq!(
+            local_clustering_coefficients(4, &edges),
+            vec![1.0, 1.0, 1.0 / 3.0, 0.0]
+        );
+
+        // A star has no links between the neighbors of the center.
+        let star = vec![(0, 1), (0, 2), (0, 3)];
+        assert_eq!(local_clustering_coefficients(4, &star), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_core_numbers() {
+        assert!(core_numbers(0, &vec![]).is_empty());
+
+        // A 4-clique (3-core) attached to a path, and an isolated node.
+        let edges = vec![
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (1, 2),
+            (1, 3),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+        ];
+        assert_eq!(core_numbers(7, &edges), vec![3, 3, 3, 3, 1, 1, 0]);
+
+        // A cycle is a 2-core.
+        let cycle = vec![(0, 1), (1, 2), (2, 3), (3, 0)];
+        assert_eq!(core_numbers(4, &cycle), vec![2; 4]);
+    }
+}
@@ -0,0 +1,372 @@
+//! API keys for the programmatic access, such as the pipelines which can't do the interactive token flow.
+//!
+//! A user creates a key by `/api/v1/api-keys`, and the key is only returned once. A request with the `X-API-Key` header is sent as the owner of the key, but it can only access the read and prediction endpoints. Only the hashes of the keys are stored, and the keys can be revoked at any time.
+
+use crate::api::auth::{required_scope, SCOPE_KG_READ, SCOPE_PREDICT_INVOKE};
+use crate::api::util::{error_response, sha256_hex};
+use anyhow::Ok as AnyOk;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use log::debug;
+use poem::http::{header, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use validator::Validate;
+
+/// The header which carries the API key.
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// The prefix of the generated keys, it helps the secret scanners to find the leaked keys.
+pub const API_KEY_PREFIX: &str = "bgps_";
+
+/// How many characters of a key are kept to identify it, such as `bgps_1a2b3c4`.
+pub const API_KEY_VISIBLE_LENGTH: usize = 12;
+
+/// How many active keys a user can have.
+pub const MAX_API_KEYS_PER_USER: i64 = 20;
+
+/// The scopes of the requests with the API keys, they can't access the write and LLM endpoints.
+pub const API_KEY_SCOPES: [&str; 2] = [SCOPE_KG_READ, SCOPE_PREDICT_INVOKE];
+
+/// Generate a new random key, such as `bgps_` followed by 64 hex characters.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::api_key::{generate_api_key, API_KEY_PREFIX};
+///
+/// let key = generate_api_key();
+/// assert!(key.starts_with(API_KEY_PREFIX));
+/// assert_eq!(key.len(), API_KEY_PREFIX.len() + 64);
+/// assert_ne!(key, generate_api_key());
+/// ```
+pub fn generate_api_key() -> String {
+    format!(
+        "{}{}{}",
+        API_KEY_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Whether a request with an API key can access the endpoint, only the read and prediction endpoints are allowed.
+///
+/// # Example
+/// ```
+/// use poem::http::Method;
+/// use biomedgps::api::api_key::is_api_key_endpoint;
+///
+/// assert!(is_api_key_endpoint(&Method::GET, "/api/v1/relations"));
+/// assert!(is_api_key_endpoint(&Method::POST, "/api/v1/predicted-nodes/batch"));
+/// assert!(!is_api_key_endpoint(&Method::POST, "/api/v1/curated-knowledges"));
+/// assert!(!is_api_key_endpoint(&Method::POST, "/api/v1/llm"));
+/// ```
+pub fn is_api_key_endpoint(method: &poem::http::Method, path: &str) -> bool {
+    API_KEY_SCOPES.contains(&required_scope(method, path, None))
+}
+
+/// The marker which is inserted into the request extensions by the [`ApiKeyAuth`] middleware. The jwt_token_checker will map the request to the owner of the key when it finds the marker.
+#[derive(Debug, Clone)]
+pub struct ApiKeyAccess {
+    pub key_id: i64,
+    pub owner: String,
+}
+
+/// The payload to create an API key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, Validate)]
+pub struct ApiKeyRequest {
+    // The name of the key, such as nightly-pipeline.
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "The length of name should be between 1 and 64."
+    ))]
+    pub name: String,
+    // The key expires after these days, it never expires if it's not set.
+    #[serde(default)]
+    #[validate(range(
+        min = 1,
+        max = 3650,
+        message = "The key should expire in 1 to 3650 days."
+    ))]
+    #[oai(skip_serializing_if_is_none)]
+    pub expires_in_days: Option<i64>,
+}
+
+/// An API key of a user. The key itself is only returned when it's created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub owner: String,
+    pub key_prefix: String,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub revoked_at: Option<DateTime<Utc>>,
+
+    // The key, it's only returned when it's created, store it in a safe place.
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub key: Option<String>,
+}
+
+const API_KEY_COLUMNS: &str =
+    "id, name, owner, key_prefix, created_at, expires_at, last_used_at, revoked_at";
+
+impl ApiKey {
+    /// Create a key for the user, the key is returned in the key field.
+    pub async fn create(
+        pool: &sqlx::PgPool,
+        owner: &str,
+        request: &ApiKeyRequest,
+    ) -> Result<ApiKey, anyhow::Error> {
+        request.validate()?;
+
+        let sql_str = "SELECT COUNT(*) FROM biomedgps_api_key WHERE owner = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())";
+        let count: i64 = sqlx::query_scalar(sql_str)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+        if count >= MAX_API_KEYS_PER_USER {
+            return Err(anyhow::anyhow!(
+                "A user can have at most {} active API keys, please revoke the unused keys first.",
+                MAX_API_KEYS_PER_USER
+            ));
+        }
+
+        let key = generate_api_key();
+        let expires_at = request
+            .expires_in_days
+            .map(|days| Utc::now() + chrono::Duration::days(days));
+        let sql_str = format!(
+            "INSERT INTO biomedgps_api_key (name, owner, key_prefix, key_hash, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+            API_KEY_COLUMNS
+        );
+        let mut api_key = sqlx::query_as::<_, ApiKey>(&sql_str)
+            .bind(&request.name)
+            .bind(owner)
+            .bind(&key[..API_KEY_VISIBLE_LENGTH])
+            .bind(sha256_hex(key.as_bytes()))
+            .bind(expires_at)
+            .fetch_one(pool)
+            .await?;
+        api_key.key = Some(key);
+
+        AnyOk(api_key)
+    }
+
+    /// Fetch the keys of the user, the newest first. The revoked and expired keys are also returned.
+    pub async fn list(pool: &sqlx::PgPool, owner: &str) -> Result<Vec<ApiKey>, anyhow::Error> {
+        let sql_str = format!(
+            "SELECT {} FROM biomedgps_api_key WHERE owner = $1 ORDER BY created_at DESC, id DESC",
+            API_KEY_COLUMNS
+        );
+        let keys = sqlx::query_as::<_, ApiKey>(&sql_str)
+            .bind(owner)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(keys)
+    }
+
+    /// Revoke a key of the user, the admins can revoke the keys of all users by setting the owner to None.
+    pub async fn revoke(
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: Option<&str>,
+    ) -> Result<ApiKey, anyhow::Error> {
+        let sql_str = format!(
+            "UPDATE biomedgps_api_key SET revoked_at = now() WHERE id = $1 AND ($2::text IS NULL OR owner = $2) AND revoked_at IS NULL RETURNING {}",
+            API_KEY_COLUMNS
+        );
+        match sqlx::query_as::<_, ApiKey>(&sql_str)
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(pool)
+            .await?
+        {
+            Some(key) => AnyOk(key),
+            None => Err(anyhow::anyhow!(
+                "The API key {} is not found or it has been revoked.",
+                id
+            )),
+        }
+    }
+
+    /// Find the active key by the key itself, it returns None if the key is unknown, revoked or expired. The last used time is updated at most once a minute.
+    pub async fn authenticate(
+        pool: &sqlx::PgPool,
+        key: &str,
+    ) -> Result<Option<ApiKey>, anyhow::Error> {
+        let sql_str = format!(
+            "SELECT {} FROM biomedgps_api_key WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())",
+            API_KEY_COLUMNS
+        );
+        let api_key = sqlx::query_as::<_, ApiKey>(&sql_str)
+            .bind(sha256_hex(key.as_bytes()))
+            .fetch_optional(pool)
+            .await?;
+
+        if let Some(api_key) = &api_key {
+            let sql_str = "UPDATE biomedgps_api_key SET last_used_at = now() WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < now() - INTERVAL '1 minute')";
+            sqlx::query(sql_str).bind(api_key.id).execute(pool).await?;
+        }
+
+        AnyOk(api_key)
+    }
+}
+
+/// A middleware which authenticates the requests with the `X-API-Key` header. The requests with the Authorization header are not affected.
+pub struct ApiKeyAuth {
+    pool: Arc<sqlx::PgPool>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(pool: Arc<sqlx::PgPool>) -> Self {
+        ApiKeyAuth { pool }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ApiKeyAuth {
+    type Output = ApiKeyAuthEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ApiKeyAuthEndpoint {
+            ep,
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+pub struct ApiKeyAuthEndpoint<E> {
+    ep: E,
+    pool: Arc<sqlx::PgPool>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for ApiKeyAuthEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let key = match req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(key)
+                if !req.headers().contains_key(header::AUTHORIZATION)
+                    && req.uri().path().starts_with("/api/") =>
+            {
+                key.trim().to_string()
+            }
+            _ => return self.ep.call(req).await.map(|resp| resp.into_response()),
+        };
+
+        if !is_api_key_endpoint(req.method(), req.uri().path()) {
+            return Ok(error_response(
+                StatusCode::FORBIDDEN,
+                &format!(
+                    "The API keys can only access the read and prediction endpoints, {} {} needs a token.",
+                    req.method(),
+                    req.uri().path()
+                ),
+            ));
+        }
+
+        let api_key = match ApiKey::authenticate(&self.pool, &key).await {
+            Ok(Some(api_key)) => api_key,
+            Ok(None) => {
+                return Ok(error_response(
+                    StatusCode::UNAUTHORIZED,
+                    "The API key is invalid, revoked or expired.",
+                ))
+            }
+            Err(e) => {
+                return Ok(error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    &format!("Failed to check the API key: {}", e),
+                ))
+            }
+        };
+
+        debug!(
+            "Map the request {} to the owner of the API key {}.",
+            req.uri().path(),
+            api_key.key_prefix
+        );
+        // The security scheme requires the Authorization header, the token itself is ignored when the marker exists. The token is unique for each key, so the idempotency keys are scoped by the API keys.
+        if let Ok(value) = header::HeaderValue::from_str(&format!("Bearer api-key-{}", api_key.id))
+        {
+            req.headers_mut().insert(header::AUTHORIZATION, value);
+        }
+        req.extensions_mut().insert(ApiKeyAccess {
+            key_id: api_key.id,
+            owner: api_key.owner,
+        });
+
+        self.ep.call(req).await.map(|resp| resp.into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDatabase;
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let db = TestDatabase::new().await.unwrap();
+
+        let request = ApiKeyRequest {
+            name: "nightly-pipeline".to_string(),
+            expires_in_days: None,
+        };
+        let created = ApiKey::create(&db.pool, "alice", &request).await.unwrap();
+        let key = created.key.clone().unwrap();
+        assert_eq!(created.key_prefix, &key[..API_KEY_VISIBLE_LENGTH]);
+
+        let found = ApiKey::authenticate(&db.pool, &key).await.unwrap().unwrap();
+        assert_eq!(found.id, created.id);
+        assert_eq!(found.owner, "alice");
+        assert!(found.key.is_none());
+        assert!(ApiKey::authenticate(&db.pool, "bgps_unknown")
+            .await
+            .unwrap()
+            .is_none());
+
+        // The other users can't revoke the key.
+        assert!(ApiKey::revoke(&db.pool, created.id, Some("bob"))
+            .await
+            .is_err());
+        ApiKey::revoke(&db.pool, created.id, Some("alice"))
+            .await
+            .unwrap();
+        assert!(ApiKey::authenticate(&db.pool, &key)
+            .await
+            .unwrap()
+            .is_none());
+
+        let keys = ApiKey::list(&db.pool, "alice").await.unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].revoked_at.is_some());
+        assert!(keys[0].key.is_none());
+
+        let invalid = ApiKeyRequest {
+            name: "".to_string(),
+            expires_in_days: Some(0),
+        };
+        assert!(ApiKey::create(&db.pool, "alice", &invalid).await.is_err());
+
+        db.cleanup().await.unwrap();
+    }
+}
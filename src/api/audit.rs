@@ -0,0 +1,371 @@
+//! Audit log of the mutating API operations, so we know who changed what.
+//!
+//! Every POST, PUT and DELETE request (except the read-only search endpoints) is recorded with the user, the table, the record id, the status and the changed fields. The record is snapshotted before and after the request when the endpoint is known to change a table, the changed fields are the difference of the snapshots. The secrets, such as the hashes of the API keys, are never recorded.
+
+use crate::api::auth::{get_request_user, LLM_ENDPOINTS};
+use crate::api::maintenance::{is_write_request, IMPORT_JOB_ENDPOINT_PREFIX};
+use crate::api::util::matches_endpoint;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use log::warn;
+use poem::http::{header, Method};
+use poem::{Body, Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+/// The endpoints which change a table, a segment starting with `:` matches any segment. The `:id` segment is the id of the changed record, the id of a created record is read from the response.
+pub const AUDITED_ENDPOINTS: [(&str, &str); 18] = [
+    ("/api/v1/curated-knowledges", "biomedgps_knowledge_curation"),
+    (
+        "/api/v1/curated-knowledges/:id",
+        "biomedgps_knowledge_curation",
+    ),
+    (
+        "/api/v1/relations/:relation_id/verification",
+        "biomedgps_relation_verification",
+    ),
+    ("/api/v1/subgraphs", "biomedgps_subgraph"),
+    ("/api/v1/subgraphs/:id", "biomedgps_subgraph"),
+    ("/api/v1/node-tags", "biomedgps_node_tag"),
+    ("/api/v1/node-tags/:id", "biomedgps_node_tag"),
+    ("/api/v1/graph-views", "biomedgps_graph_view"),
+    ("/api/v1/graph-views/:id", "biomedgps_graph_view"),
+    ("/api/v1/dataset-licenses", "biomedgps_dataset_license"),
+    ("/api/v1/export-jobs", "biomedgps_export_job"),
+    ("/api/v1/takeout-jobs", "biomedgps_export_job"),
+    ("/api/v1/import-jobs", "biomedgps_import_job"),
+    ("/api/v1/import-jobs/:id/cancel", "biomedgps_import_job"),
+    ("/api/v1/import-jobs/:id/resume", "biomedgps_import_job"),
+    ("/api/v1/api-keys", "biomedgps_api_key"),
+    ("/api/v1/api-keys/:id", "biomedgps_api_key"),
+    ("/api/v1/datasets/:dataset", "biomedgps_relation"),
+];
+
+/// The audited tables whose ids are text, such as the uuids of the subgraphs. The ids of the other tables are BIGINT.
+pub const TEXT_ID_TABLES: [&str; 1] = ["biomedgps_subgraph"];
+
+/// The fields which are never recorded in the changes.
+pub const REDACTED_FIELDS: [&str; 2] = ["key", "key_hash"];
+
+/// The responses larger than this are not parsed for the id of the created record.
+const MAX_AUDITED_RESPONSE_SIZE: usize = 1024 * 1024;
+
+/// Match a path with the audited endpoints, return the table and the record id in the path.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::audit::match_audited_endpoint;
+///
+/// assert_eq!(
+///     match_audited_endpoint("/api/v1/subgraphs/abc/"),
+///     Some(("biomedgps_subgraph", Some("abc".to_string())))
+/// );
+/// assert_eq!(
+///     match_audited_endpoint("/api/v1/curated-knowledges"),
+///     Some(("biomedgps_knowledge_curation", None))
+/// );
+/// assert_eq!(
+///     match_audited_endpoint("/api/v1/relations/12/verification"),
+///     Some(("biomedgps_relation_verification", None))
+/// );
+/// assert_eq!(match_audited_endpoint("/api/v1/import-files"), None);
+/// ```
+pub fn match_audited_endpoint(path: &str) -> Option<(&'static str, Option<String>)> {
+    let path_segments = path.trim_end_matches('/').split('/').collect::<Vec<&str>>();
+    AUDITED_ENDPOINTS.iter().find_map(|(pattern, table)| {
+        let pattern_segments = pattern.split('/').collect::<Vec<&str>>();
+        if pattern_segments.len() != path_segments.len() {
+            return None;
+        }
+
+        let mut record_id = None;
+        for (p, s) in pattern_segments.iter().zip(path_segments.iter()) {
+            if p.starts_with(':') && !s.is_empty() {
+                if *p == ":id" || *p == ":dataset" {
+                    record_id = Some(s.to_string());
+                }
+            } else if p != s {
+                return None;
+            }
+        }
+
+        Some((*table, record_id))
+    })
+}
+
+/// Whether a request is recorded in the audit log. The LLM calls are recorded in the LLM usages instead, unless they change a table such as the relation verifications.
+pub fn is_audited_request(method: &Method, path: &str) -> bool {
+    if LLM_ENDPOINTS.iter().any(|p| matches_endpoint(p, path))
+        && match_audited_endpoint(path).is_none()
+    {
+        return false;
+    }
+
+    is_write_request(method, path)
+        || (*method != Method::GET
+            && *method != Method::HEAD
+            && *method != Method::OPTIONS
+            && path.starts_with(IMPORT_JOB_ENDPOINT_PREFIX))
+}
+
+/// Compute the changed fields between two snapshots of a record, such as `{"name": {"old": "a", "new": "b"}}`. A missing snapshot (null) means the record is created or deleted, so all fields are changed. The REDACTED_FIELDS are skipped.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::audit::diff_fields;
+/// use serde_json::json;
+///
+/// let changes = diff_fields(
+///     &json!({"id": 1, "name": "a", "owner": "x", "key_hash": "h1"}),
+///     &json!({"id": 1, "name": "b", "owner": "x", "key_hash": "h2"}),
+/// );
+/// assert_eq!(changes, json!({"name": {"old": "a", "new": "b"}}));
+///
+/// let changes = diff_fields(&json!(null), &json!({"id": 1}));
+/// assert_eq!(changes, json!({"id": {"old": null, "new": 1}}));
+/// ```
+pub fn diff_fields(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for field in before.keys().chain(after.keys()) {
+        if REDACTED_FIELDS.contains(&field.as_str()) || changes.contains_key(field) {
+            continue;
+        }
+
+        let old = before.get(field).unwrap_or(&Value::Null);
+        let new = after.get(field).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(field.clone(), json!({ "old": old, "new": new }));
+        }
+    }
+
+    Value::Object(changes)
+}
+
+/// A recorded mutating request. The table and the record id are None if the endpoint doesn't change a known table, and the changes are None if the record can't be snapshotted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct AuditLog {
+    pub id: i64,
+    // The user is None if the token is invalid, the request is rejected by the endpoint then.
+    #[oai(skip_serializing_if_is_none)]
+    pub username: Option<String>,
+    pub method: String,
+    pub path: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub table_name: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub record_id: Option<String>,
+    pub status: i32,
+    #[oai(skip_serializing_if_is_none)]
+    pub changes: Option<Value>,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// Take a snapshot of a record as json, the table is one of the AUDITED_ENDPOINTS, so it can be formatted into the sql directly. The relations are deleted by dataset, so they are not snapshotted.
+    async fn snapshot(pool: &sqlx::PgPool, table: &str, record_id: &str) -> Option<Value> {
+        if table == "biomedgps_relation" {
+            return None;
+        }
+
+        let sql_str = format!("SELECT row_to_json(t)::TEXT FROM {} t WHERE id = $1", table);
+        let query = sqlx::query_scalar::<_, String>(&sql_str);
+        // The id is bound with the type of the id column, so the record is looked up by the primary key.
+        let query = if TEXT_ID_TABLES.contains(&table) {
+            query.bind(record_id)
+        } else {
+            match record_id.parse::<i64>() {
+                Ok(id) => query.bind(id),
+                // No record has a non-numeric id.
+                Err(_) => return Some(Value::Null),
+            }
+        };
+
+        match query.fetch_optional(pool).await {
+            Ok(row) => Some(
+                row.and_then(|row| serde_json::from_str(&row).ok())
+                    .unwrap_or(Value::Null),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to snapshot the record {} of {}: {}",
+                    record_id, table, e
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn record(
+        pool: &sqlx::PgPool,
+        username: Option<&str>,
+        method: &str,
+        path: &str,
+        table_name: Option<&str>,
+        record_id: Option<&str>,
+        status: u16,
+        changes: Option<&Value>,
+    ) -> Result<(), anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_audit_log (username, method, path, table_name, record_id, status, changes) VALUES ($1, $2, $3, $4, $5, $6, $7)";
+        sqlx::query(sql_str)
+            .bind(username)
+            .bind(method)
+            .bind(path)
+            .bind(table_name)
+            .bind(record_id)
+            .bind(status as i32)
+            .bind(changes)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// A middleware which records the mutating requests in the audit log. The request is not blocked if it can't be audited, the failure is only logged.
+pub struct AuditLogger {
+    pool: Arc<sqlx::PgPool>,
+}
+
+impl AuditLogger {
+    pub fn new(pool: Arc<sqlx::PgPool>) -> Self {
+        AuditLogger { pool }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for AuditLogger {
+    type Output = AuditLoggerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AuditLoggerEndpoint {
+            ep,
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+pub struct AuditLoggerEndpoint<E> {
+    ep: E,
+    pool: Arc<sqlx::PgPool>,
+}
+
+fn is_json(resp: &Response) -> bool {
+    resp.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map_or(false, |v| v.starts_with("application/json"))
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for AuditLoggerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        if !is_audited_request(&method, &path) {
+            return self.ep.call(req).await.map(|resp| resp.into_response());
+        }
+
+        let username = get_request_user(&req).await.map(|user| user.username);
+        let (table, path_record_id) = match match_audited_endpoint(&path) {
+            Some((table, record_id)) => (Some(table), record_id),
+            None => (None, None),
+        };
+
+        let before = match (table, &path_record_id) {
+            (Some(table), Some(record_id)) => {
+                AuditLog::snapshot(&self.pool, table, record_id).await
+            }
+            _ => None,
+        };
+
+        let mut resp = self.ep.call(req).await?.into_response();
+        let status = resp.status();
+
+        // The id of a created record is only known from the response.
+        let record_id = match (&path_record_id, table) {
+            (None, Some(_)) if status.is_success() && is_json(&resp) => {
+                let body = resp.take_body().into_vec().await?;
+                let id = if body.len() <= MAX_AUDITED_RESPONSE_SIZE {
+                    serde_json::from_slice::<Value>(&body)
+                        .ok()
+                        .and_then(|v| v.get("id").cloned())
+                        .and_then(|id| match id {
+                            Value::Number(id) => Some(id.to_string()),
+                            Value::String(id) => Some(id),
+                            _ => None,
+                        })
+                } else {
+                    None
+                };
+                resp.set_body(Body::from(body));
+                id
+            }
+            _ => path_record_id.clone(),
+        };
+
+        let changes = match (table, &record_id) {
+            (Some(table), Some(record_id)) if status.is_success() => {
+                let before = match &path_record_id {
+                    Some(_) => before,
+                    // The record is created by the request.
+                    None => Some(Value::Null),
+                };
+                let after = AuditLog::snapshot(&self.pool, table, record_id).await;
+                match (before, after) {
+                    (Some(before), Some(after)) => Some(diff_fields(&before, &after)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        if let Err(e) = AuditLog::record(
+            &self.pool,
+            username.as_deref(),
+            method.as_str(),
+            &path,
+            table,
+            record_id.as_deref(),
+            status.as_u16(),
+            changes.as_ref(),
+        )
+        .await
+        {
+            warn!("Failed to audit the request {} {}: {}", method, path, e);
+        }
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_audited_request() {
+        assert!(is_audited_request(
+            &Method::POST,
+            "/api/v1/curated-knowledges"
+        ));
+        assert!(is_audited_request(&Method::DELETE, "/api/v1/subgraphs/abc"));
+        assert!(is_audited_request(
+            &Method::POST,
+            "/api/v1/import-jobs/1/cancel"
+        ));
+        assert!(!is_audited_request(&Method::GET, "/api/v1/subgraphs"));
+        assert!(!is_audited_request(
+            &Method::POST,
+            "/api/v1/entities/search"
+        ));
+        assert!(!is_audited_request(&Method::POST, "/api/v1/llm"));
+    }
+}
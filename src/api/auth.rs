@@ -1,26 +1,171 @@
+use crate::api::api_key::{ApiKeyAccess, API_KEY_SCOPES};
+use crate::api::maintenance::is_write_request;
+use crate::api::public::PublicAccess;
+use crate::api::util::{error_response, matches_endpoint};
+use crate::model::core::OwnerScope;
 use base64;
 use jsonwebtoken::{decode, errors::ErrorKind, Algorithm, DecodingKey, Validation};
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
-use poem::Request;
+use poem::http::{header, Method, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result as PoemResult};
 use poem_openapi::auth::Bearer;
 use poem_openapi::SecurityScheme;
 use reqwest::Error as ReqwestError;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::sync::RwLock;
 
 pub const USERNAME_PLACEHOLDER: &str = "ANONYMOUS-USER-PLACEHOLDER";
+// The read-only persona for the unauthenticated requests in the public mode. You can share the precomputed graphs with the anonymous users by setting the owner of the subgraphs to this name, the persona only sees the subgraphs of this owner.
+pub const PUBLIC_USERNAME: &str = "PUBLIC-READ-ONLY-USER";
+
+// The scopes of a token, they limit which endpoints the token can access. A token without the scope claim can access all endpoints.
+pub const SCOPE_KG_READ: &str = "kg:read";
+pub const SCOPE_KG_WRITE: &str = "kg:write";
+pub const SCOPE_LLM_INVOKE: &str = "llm:invoke";
+pub const SCOPE_PREDICT_INVOKE: &str = "predict:invoke";
+
+/// The endpoints which call the LLMs, they need the `llm:invoke` scope. A segment starting with `:` matches any segment.
+pub const LLM_ENDPOINTS: [&str; 2] = ["/api/v1/llm", "/api/v1/relations/:id/verification"];
+
+/// The endpoints which only call the LLMs when the query param is true, such as polishing the narrated path.
+pub const LLM_QUERY_ENDPOINTS: [(&str, &str); 1] = [("/api/v1/paths/narrate", "polish")];
+
+/// The endpoints which compute the predictions by the KGE models, they need the `predict:invoke` scope.
+pub const PREDICTION_ENDPOINTS: [&str; 3] = [
+    "/api/v1/predicted-nodes",
+    "/api/v1/predicted-nodes/batch",
+    "/api/v1/subgraphs/:id/extension",
+];
+
+/// The role of a user, it decides which curation endpoints the user can access. The roles are ordered, a role has all permissions of the lower roles.
+///
+/// - `viewer`: fetch the curated knowledges.
+/// - `curator`: create, update and delete the curated knowledges.
+/// - `admin`: all permissions, such as the admin endpoints. The users in the ADMIN_USERS environment variable are also admins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Curator,
+    Admin,
+}
+
+impl Role {
+    /// Parse a role name, the names are case-insensitive.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::api::auth::Role;
+    ///
+    /// assert_eq!(Role::from_name("Curator"), Some(Role::Curator));
+    /// assert_eq!(Role::from_name("owner"), None);
+    /// assert!(Role::Admin > Role::Curator && Role::Curator > Role::Viewer);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Role> {
+        match name.trim().to_lowercase().as_str() {
+            "viewer" => Some(Role::Viewer),
+            "curator" => Some(Role::Curator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Curator => "curator",
+            Role::Admin => "admin",
+        }
+    }
+}
 
 lazy_static! {
     static ref PUBLIC_KEYS: RwLock<Vec<String>> = RwLock::new(vec![]);
 }
 
+/// Get the scope which is required by an endpoint. The LLM and prediction endpoints need their own scopes, the other endpoints need `kg:write` for the writes and `kg:read` for the reads. The query string is only used by the LLM_QUERY_ENDPOINTS.
+///
+/// # Example
+/// ```
+/// use poem::http::Method;
+/// use biomedgps::api::auth::required_scope;
+///
+/// assert_eq!(required_scope(&Method::GET, "/api/v1/relations", None), "kg:read");
+/// assert_eq!(required_scope(&Method::POST, "/api/v1/subgraphs", None), "kg:write");
+/// assert_eq!(required_scope(&Method::POST, "/api/v1/llm", None), "llm:invoke");
+/// assert_eq!(required_scope(&Method::POST, "/api/v1/relations/12/verification", None), "llm:invoke");
+/// assert_eq!(required_scope(&Method::POST, "/api/v1/paths/narrate", None), "kg:read");
+/// assert_eq!(required_scope(&Method::POST, "/api/v1/paths/narrate", Some("polish=true")), "llm:invoke");
+/// assert_eq!(required_scope(&Method::GET, "/api/v1/predicted-nodes", None), "predict:invoke");
+/// assert_eq!(required_scope(&Method::GET, "/api/v1/subgraphs/abc/extension", None), "predict:invoke");
+/// ```
+pub fn required_scope(method: &Method, path: &str, query: Option<&str>) -> &'static str {
+    let path = path.trim_end_matches('/');
+    let is_query_enabled = |param: &str| {
+        query
+            .unwrap_or_default()
+            .split('&')
+            .any(|kv| kv == format!("{}=true", param))
+    };
+
+    if LLM_ENDPOINTS.iter().any(|p| matches_endpoint(p, path))
+        || LLM_QUERY_ENDPOINTS
+            .iter()
+            .any(|(p, param)| matches_endpoint(p, path) && is_query_enabled(param))
+    {
+        SCOPE_LLM_INVOKE
+    } else if PREDICTION_ENDPOINTS
+        .iter()
+        .any(|p| matches_endpoint(p, path))
+    {
+        SCOPE_PREDICT_INVOKE
+    } else if is_write_request(method, path) {
+        SCOPE_KG_WRITE
+    } else {
+        SCOPE_KG_READ
+    }
+}
+
+/// The scope claim might be a space-separated string (OAuth 2.0) or a list of scopes. The roles claim is parsed in the same way.
+fn deserialize_scopes<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scopes {
+        Text(String),
+        List(Vec<String>),
+    }
+
+    Ok(match Option::<Scopes>::deserialize(deserializer)? {
+        Some(Scopes::Text(text)) => Some(text.split_whitespace().map(String::from).collect()),
+        Some(Scopes::List(scopes)) => Some(scopes),
+        None => None,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
     pub username: String,
     pub organizations: Vec<i32>,
     pub projects: Vec<i32>,
+    // The scopes of the token, None means the token can access all endpoints. It's for the backward compatibility with the tokens without the scope claim.
+    #[serde(
+        default,
+        rename = "scope",
+        deserialize_with = "deserialize_scopes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scopes: Option<Vec<String>>,
+    // The roles of the user, such as admin, curator and viewer. None means the token doesn't have the roles claim, the user is a curator then. It's for the backward compatibility with the tokens without the roles claim.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_scopes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub roles: Option<Vec<String>>,
 }
 
 impl User {
@@ -29,6 +174,43 @@ impl User {
             username,
             organizations: vec![-1],
             projects: vec![-1],
+            scopes: None,
+            roles: None,
+        }
+    }
+
+    /// The highest role of the user. The admin users in ADMIN_USERS are admins, the users without the roles claim are curators, the unknown roles are ignored and the users without a known role are viewers. The read-only persona of the public mode is always a viewer.
+    pub fn role(&self) -> Role {
+        if self.username == PUBLIC_USERNAME {
+            return Role::Viewer;
+        }
+
+        let role = match &self.roles {
+            Some(roles) => roles
+                .iter()
+                .filter_map(|role| Role::from_name(role))
+                .max()
+                .unwrap_or(Role::Viewer),
+            None => Role::Curator,
+        };
+
+        if role < Role::Admin && self.is_listed_admin() {
+            Role::Admin
+        } else {
+            role
+        }
+    }
+
+    /// Whether the user has the role or a higher role.
+    pub fn has_role(&self, role: Role) -> bool {
+        self.role() >= role
+    }
+
+    /// Whether the user can access the endpoints which need the scope. The scopes limit the token rather than the user, so a scoped token of an admin user is limited too.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.iter().any(|s| s == scope),
+            None => true,
         }
     }
 
@@ -39,6 +221,29 @@ impl User {
     fn add_projects(&mut self, projects: Vec<i32>) {
         self.projects = projects;
     }
+
+    /// The admin users are listed in the ADMIN_USERS environment variable (separated by comma) or have the admin role in their tokens.
+    pub fn is_admin(&self) -> bool {
+        self.role() == Role::Admin
+    }
+
+    fn is_listed_admin(&self) -> bool {
+        match std::env::var("ADMIN_USERS") {
+            Ok(admin_users) => admin_users
+                .split(",")
+                .any(|admin_user| admin_user.trim() == self.username),
+            Err(_) => false,
+        }
+    }
+
+    /// The claims for the row-level security policies. The admin users and the placeholder user (the auth mode is disabled) are not scoped, so they can access all rows.
+    pub fn owner_scope(&self) -> OwnerScope {
+        if self.is_admin() || self.username == USERNAME_PLACEHOLDER {
+            OwnerScope::new("", &vec![], &vec![])
+        } else {
+            OwnerScope::new(&self.username, &self.organizations, &self.projects)
+        }
+    }
 }
 
 fn get_username_from_claims(claims: &Claims) -> Option<String> {
@@ -81,6 +286,10 @@ struct Claims {
     exp: i64,
     sub: String,
     nonce: String,
+    #[serde(default, deserialize_with = "deserialize_scopes")]
+    scope: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_scopes")]
+    roles: Option<Vec<String>>,
 }
 
 pub async fn fetch_and_store_jwks(url: &str) -> Result<Jwks, ReqwestError> {
@@ -176,11 +385,109 @@ fn detect_kid(token_str: &str) -> Option<String> {
     }
 }
 
+/// A JWT bearer token (HS256 or RS256). The `scope` claim limits which endpoints the token can access, a token without it can access all endpoints.
+///
+/// - `kg:read`: browse the knowledge graph, such as the GET requests and the search endpoints.
+/// - `kg:write`: create, update and delete the records, such as the curated knowledges and the subgraphs.
+/// - `llm:invoke`: call the LLMs by `/api/v1/llm`, verify the relations by `/api/v1/relations/:id/verification` or polish the narratives by `/api/v1/paths/narrate`.
+/// - `predict:invoke`: predict the nodes by `/api/v1/predicted-nodes`, `/api/v1/predicted-nodes/batch` and `/api/v1/subgraphs/:id/extension`.
+///
+/// The scopes are checked by the [`ScopeCheck`] middleware, a token without the required scope gets 403.
+///
+/// The `roles` claim (such as `["curator"]`) decides who can change the curated knowledges, see [`Role`]. A token without it is a curator.
+///
+/// The pipelines can send an API key by the `X-API-Key` header instead of the token, see `/api/v1/api-keys`. The requests with the API keys can only access the read and prediction endpoints.
 #[derive(SecurityScheme)]
 #[oai(type = "bearer", checker = "jwt_token_checker")]
 pub struct CustomSecurityScheme(pub User);
 
-async fn jwt_token_checker(_: &Request, bearer: Bearer) -> Option<User> {
+/// Check whether the user has the scope which is required by the request, the error message is returned if not.
+pub fn check_scope(
+    user: &User,
+    method: &Method,
+    path: &str,
+    query: Option<&str>,
+) -> Result<(), String> {
+    let scope = required_scope(method, path, query);
+    if user.has_scope(scope) {
+        Ok(())
+    } else {
+        Err(format!(
+            "User {} doesn't have the {} scope which is required by {} {}.",
+            user.username, scope, method, path
+        ))
+    }
+}
+
+/// A middleware which rejects the requests without the required scope by 403. The requests without a valid token are passed to the endpoints, so they still get 401 from the security scheme.
+///
+/// It must be applied after the [`crate::api::api_key::ApiKeyAuth`] and [`crate::api::public::PublicMode`] middlewares, so the requests with the API keys and the anonymous requests are resolved to their users.
+pub struct ScopeCheck;
+
+impl<E: Endpoint> Middleware<E> for ScopeCheck {
+    type Output = ScopeCheckEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ScopeCheckEndpoint { ep }
+    }
+}
+
+pub struct ScopeCheckEndpoint<E> {
+    ep: E,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for ScopeCheckEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> PoemResult<Self::Output> {
+        if req.uri().path().starts_with("/api/") {
+            if let Some(user) = get_request_user(&req).await {
+                if let Err(msg) =
+                    check_scope(&user, req.method(), req.uri().path(), req.uri().query())
+                {
+                    return Ok(error_response(StatusCode::FORBIDDEN, &msg));
+                }
+            }
+        }
+
+        self.ep.call(req).await.map(|resp| resp.into_response())
+    }
+}
+
+/// Verify the token of a request outside of the endpoints, such as in the audit middleware. The scopes are not checked, and None is returned if the request has no valid bearer token.
+pub async fn get_request_user(req: &Request) -> Option<User> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+
+    match token {
+        Some(token) => jwt_token_checker(req, Bearer { token }).await,
+        // The public persona doesn't need a token.
+        None if req.extensions().get::<PublicAccess>().is_some() => {
+            Some(User::new(PUBLIC_USERNAME.to_string()))
+        }
+        None => None,
+    }
+}
+
+async fn jwt_token_checker(req: &Request, bearer: Bearer) -> Option<User> {
+    // The marker is only inserted by the PublicMode middleware for the whitelisted read-only endpoints.
+    if req.extensions().get::<PublicAccess>().is_some() {
+        return Some(User::new(PUBLIC_USERNAME.to_string()));
+    }
+
+    // The marker is only inserted by the ApiKeyAuth middleware after the API key is verified. The requests with the API keys can only read and predict.
+    if let Some(access) = req.extensions().get::<ApiKeyAccess>() {
+        let mut user = User::new(access.owner.clone());
+        user.scopes = Some(API_KEY_SCOPES.iter().map(|s| s.to_string()).collect());
+        return Some(user);
+    }
+
     // Get jwt_secret_key from environment variable
     let default_user = Some(User::new(USERNAME_PLACEHOLDER.to_string()));
 
@@ -268,6 +575,8 @@ async fn jwt_token_checker(_: &Request, bearer: Bearer) -> Option<User> {
                         // Be compatible with the old version, the token might not contain the organizations field.
                         organizations: vec![-1],
                         projects: vec![-1],
+                        scopes: claims.scope,
+                        roles: claims.roles,
                     });
                 }
                 Err(err) => {
@@ -291,6 +600,132 @@ mod tests {
     use crate::init_logger;
     use log::LevelFilter;
 
+    #[test]
+    fn test_user_scopes() {
+        let user: User = serde_json::from_str(
+            r#"{"username": "test", "organizations": [], "projects": [], "scope": "kg:read predict:invoke"}"#,
+        )
+        .unwrap();
+        assert!(user.has_scope(SCOPE_KG_READ));
+        assert!(user.has_scope(SCOPE_PREDICT_INVOKE));
+        assert!(!user.has_scope(SCOPE_LLM_INVOKE));
+
+        let user: User = serde_json::from_str(
+            r#"{"username": "test-admin", "organizations": [], "projects": [], "roles": ["admin"], "scope": "kg:read"}"#,
+        )
+        .unwrap();
+        assert!(user.is_admin());
+        assert!(!user.has_scope(SCOPE_LLM_INVOKE));
+
+        let user: User =
+            serde_json::from_str(r#"{"username": "test", "organizations": [], "projects": []}"#)
+                .unwrap();
+        assert!(user.has_scope(SCOPE_LLM_INVOKE));
+    }
+
+    #[test]
+    fn test_check_scope() {
+        let user: User = serde_json::from_str(
+            r#"{"username": "test", "organizations": [], "projects": [], "scope": "kg:read kg:write"}"#,
+        )
+        .unwrap();
+
+        let llm_requests = [
+            (Method::POST, "/api/v1/llm", None),
+            (Method::POST, "/api/v1/relations/12/verification", None),
+            (Method::POST, "/api/v1/paths/narrate", Some("polish=true")),
+        ];
+        let prediction_requests = [
+            (Method::GET, "/api/v1/predicted-nodes", None),
+            (Method::POST, "/api/v1/predicted-nodes/batch", None),
+            (Method::GET, "/api/v1/subgraphs/abc/extension", None),
+        ];
+        for (method, path, query) in llm_requests.iter().chain(prediction_requests.iter()) {
+            assert!(check_scope(&user, method, path, *query).is_err());
+        }
+        assert!(check_scope(&user, &Method::POST, "/api/v1/paths/narrate", None).is_ok());
+
+        let user: User = serde_json::from_str(
+            r#"{"username": "test", "organizations": [], "projects": [], "scope": "predict:invoke"}"#,
+        )
+        .unwrap();
+        for (method, path, query) in llm_requests.iter() {
+            assert!(check_scope(&user, method, path, *query).is_err());
+        }
+        for (method, path, query) in prediction_requests.iter() {
+            assert!(check_scope(&user, method, path, *query).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scope_check_middleware() {
+        use poem::endpoint::make_sync;
+        use poem::test::TestClient;
+        use poem::EndpointExt;
+
+        // The requests with the API keys only have the kg:read and predict:invoke scopes.
+        let app = make_sync(|_| "ok")
+            .with(ScopeCheck)
+            .before(|mut req| async move {
+                req.extensions_mut().insert(ApiKeyAccess {
+                    key_id: 1,
+                    owner: "test".to_string(),
+                });
+                Ok(req)
+            });
+        let cli = TestClient::new(app);
+
+        for path in [
+            "/api/v1/llm",
+            "/api/v1/relations/12/verification",
+            "/api/v1/paths/narrate?polish=true",
+        ] {
+            let resp = cli.post(path).send().await;
+            resp.assert_status(StatusCode::FORBIDDEN);
+        }
+
+        let resp = cli.post("/api/v1/paths/narrate").send().await;
+        resp.assert_status_is_ok();
+        let resp = cli.get("/api/v1/subgraphs/abc/extension").send().await;
+        resp.assert_status_is_ok();
+    }
+
+    #[test]
+    fn test_user_roles() {
+        let user: User = serde_json::from_str(
+            r#"{"username": "test-viewer", "organizations": [], "projects": [], "roles": ["viewer"]}"#,
+        )
+        .unwrap();
+        assert_eq!(user.role(), Role::Viewer);
+        assert!(!user.has_role(Role::Curator));
+
+        let user: User = serde_json::from_str(
+            r#"{"username": "test-curator", "organizations": [], "projects": [], "roles": "viewer curator"}"#,
+        )
+        .unwrap();
+        assert_eq!(user.role(), Role::Curator);
+        assert!(user.has_role(Role::Viewer));
+        assert!(!user.is_admin());
+
+        let user: User = serde_json::from_str(
+            r#"{"username": "test-admin", "organizations": [], "projects": [], "roles": ["admin"]}"#,
+        )
+        .unwrap();
+        assert!(user.is_admin());
+
+        // The tokens without the roles claim are curators, the unknown roles are ignored.
+        let user: User =
+            serde_json::from_str(r#"{"username": "test", "organizations": [], "projects": []}"#)
+                .unwrap();
+        assert_eq!(user.role(), Role::Curator);
+        let user: User = serde_json::from_str(
+            r#"{"username": "test", "organizations": [], "projects": [], "roles": ["owner"]}"#,
+        )
+        .unwrap();
+        assert_eq!(user.role(), Role::Viewer);
+        assert_eq!(User::new(PUBLIC_USERNAME.to_string()).role(), Role::Viewer);
+    }
+
     #[tokio::test]
     async fn test_valid_token() {
         let _ = init_logger("biomedgps-test", LevelFilter::Debug);
@@ -0,0 +1,480 @@
+//! Two-step confirmation for the destructive admin endpoints, such as deleting a dataset.
+//!
+//! An admin user requests a confirmation token for a method and a path first, then sends the destructive request with the token in the `X-Confirmation-Token` header before the token is expired. The token is signed with the hash of the Authorization header, so it can't be used by other users, and it can be used only once. Both steps are recorded in the audit table.
+
+use crate::api::util::{error_response, matches_endpoint, sha256_hex};
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use log::{error, warn};
+use poem::http::{header, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// The header which carries the confirmation token.
+pub const CONFIRMATION_TOKEN_HEADER: &str = "X-Confirmation-Token";
+
+/// The secret which signs the tokens. No tokens can be issued if it's not set, so the destructive requests are always rejected.
+pub const CONFIRMATION_SECRET_ENV: &str = "CONFIRMATION_TOKEN_SECRET";
+
+/// How long a confirmation token is valid.
+pub const CONFIRMATION_TOKEN_TTL_SECS: i64 = 300;
+
+/// The destructive endpoints which require a confirmation token, a segment starting with `:` matches any segment.
+pub const DEFAULT_CONFIRMED_ENDPOINTS: [(&str, &str); 1] =
+    [("DELETE", "/api/v1/datasets/:dataset")];
+
+lazy_static! {
+    static ref CONFIRMATION_SECRET: Option<Vec<u8>> = match std::env::var(CONFIRMATION_SECRET_ENV) {
+        Ok(secret) if !secret.is_empty() => Some(secret.into_bytes()),
+        _ => None,
+    };
+}
+
+fn get_secret() -> Result<&'static [u8], String> {
+    match CONFIRMATION_SECRET.as_ref() {
+        Some(secret) => Ok(secret),
+        None => Err(format!(
+            "{} is not set, the confirmation tokens can't be issued or verified.",
+            CONFIRMATION_SECRET_ENV
+        )),
+    }
+}
+
+/// Get the scope of a request, it's the hash of the Authorization header. The token itself is never persisted.
+pub fn get_scope(req: &Request) -> String {
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(|v| v.as_bytes().to_vec())
+        .unwrap_or_default();
+    sha256_hex(&authorization)
+}
+
+/// Whether the request to the method and the path requires a confirmation token.
+pub fn is_confirmed_endpoint(method: &str, path: &str) -> bool {
+    DEFAULT_CONFIRMED_ENDPOINTS
+        .iter()
+        .any(|(m, p)| m.eq_ignore_ascii_case(method) && matches_endpoint(p, path))
+}
+
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if data.len() % 2 != 0 || !data.is_ascii() {
+        return None;
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&data[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn new_mac(
+    secret: &[u8],
+    scope: &str,
+    method: &str,
+    path: &str,
+    expires: i64,
+    nonce: &str,
+) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(
+        format!(
+            "{}|{}|{}|{}|{}",
+            scope,
+            method.to_uppercase(),
+            path,
+            expires,
+            nonce
+        )
+        .as_bytes(),
+    );
+    mac
+}
+
+fn sign(secret: &[u8], scope: &str, method: &str, path: &str, expires: i64, nonce: &str) -> String {
+    new_mac(secret, scope, method, path, expires, nonce)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()
+}
+
+/// The destructive request which a confirmation token is requested for.
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct ConfirmationTokenRequest {
+    /// The method of the destructive request, such as DELETE.
+    pub method: String,
+    /// The path of the destructive request, such as /api/v1/datasets/ctd.
+    pub path: String,
+}
+
+/// A confirmation token for a destructive request, the token is like `<expires>.<nonce>.<signature>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct ConfirmationToken {
+    pub token: String,
+    pub method: String,
+    pub path: String,
+    #[serde(with = "ts_seconds")]
+    pub expired_at: DateTime<Utc>,
+}
+
+impl ConfirmationToken {
+    /// Issue a token for a request, it fails if the secret is not set.
+    pub fn issue(scope: &str, method: &str, path: &str) -> Result<ConfirmationToken, String> {
+        Ok(Self::issue_with_secret(get_secret()?, scope, method, path))
+    }
+
+    fn issue_with_secret(
+        secret: &[u8],
+        scope: &str,
+        method: &str,
+        path: &str,
+    ) -> ConfirmationToken {
+        let expires = Utc::now().timestamp() + CONFIRMATION_TOKEN_TTL_SECS;
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let signature = sign(secret, scope, method, path, expires, &nonce);
+
+        ConfirmationToken {
+            token: format!("{}.{}.{}", expires, nonce, signature),
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            expired_at: Utc.timestamp_opt(expires, 0).unwrap(),
+        }
+    }
+
+    /// Verify a token for a request, return the nonce of the token if it's valid.
+    pub fn verify(
+        token: &str,
+        scope: &str,
+        method: &str,
+        path: &str,
+        now: i64,
+    ) -> Result<String, String> {
+        Self::verify_with_secret(get_secret()?, token, scope, method, path, now)
+    }
+
+    fn verify_with_secret(
+        secret: &[u8],
+        token: &str,
+        scope: &str,
+        method: &str,
+        path: &str,
+        now: i64,
+    ) -> Result<String, String> {
+        let parts = token.trim().split('.').collect::<Vec<&str>>();
+        if parts.len() != 3 {
+            return Err("The confirmation token is malformed.".to_string());
+        }
+
+        let expires = parts[0]
+            .parse::<i64>()
+            .map_err(|_| "The confirmation token is malformed.".to_string())?;
+        let nonce = parts[1];
+        let signature = decode_hex(parts[2])
+            .ok_or_else(|| "The confirmation token is malformed.".to_string())?;
+        // The signature is compared in constant time.
+        if new_mac(secret, scope, method, path, expires, nonce)
+            .verify_slice(&signature)
+            .is_err()
+        {
+            return Err(
+                "The confirmation token is invalid for the request or the current user."
+                    .to_string(),
+            );
+        }
+
+        if expires < now {
+            return Err("The confirmation token is expired.".to_string());
+        }
+
+        Ok(nonce.to_string())
+    }
+}
+
+/// A step of a confirmed request. The step is one of requested, executed and rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct ConfirmationAudit {
+    pub id: i64,
+    #[oai(skip_serializing_if_is_none)]
+    pub nonce: Option<String>,
+    // The username is known when the token is requested, the executed step inherits it from the requested step.
+    #[oai(skip_serializing_if_is_none)]
+    pub username: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub step: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub status: Option<i32>,
+    #[oai(skip_serializing_if_is_none)]
+    pub message: Option<String>,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl ConfirmationAudit {
+    pub async fn record_requested(
+        pool: &sqlx::PgPool,
+        token: &ConfirmationToken,
+        username: &str,
+    ) -> Result<(), anyhow::Error> {
+        let nonce = token.token.split('.').nth(1).unwrap_or_default();
+        let sql_str = "INSERT INTO biomedgps_confirmation_audit (nonce, username, method, path, step) VALUES ($1, $2, $3, $4, 'requested')";
+        sqlx::query(sql_str)
+            .bind(nonce)
+            .bind(username)
+            .bind(&token.method)
+            .bind(&token.path)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record the executed step, return None if the token has been used.
+    async fn record_executed(
+        pool: &sqlx::PgPool,
+        nonce: &str,
+        method: &str,
+        path: &str,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_confirmation_audit (nonce, username, method, path, step)
+                       SELECT $1, (SELECT username FROM biomedgps_confirmation_audit WHERE nonce = $1 AND step = 'requested' LIMIT 1), $2, $3, 'executed'
+                       ON CONFLICT DO NOTHING RETURNING id";
+        let id = sqlx::query_scalar::<_, i64>(sql_str)
+            .bind(nonce)
+            .bind(method)
+            .bind(path)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(id)
+    }
+
+    async fn record_rejected(
+        pool: &sqlx::PgPool,
+        method: &str,
+        path: &str,
+        message: &str,
+    ) -> Result<(), anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_confirmation_audit (method, path, step, status, message) VALUES ($1, $2, 'rejected', $3, $4)";
+        sqlx::query(sql_str)
+            .bind(method)
+            .bind(path)
+            .bind(StatusCode::FORBIDDEN.as_u16() as i32)
+            .bind(message)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_status(pool: &sqlx::PgPool, id: i64, status: u16) -> Result<(), anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_confirmation_audit SET status = $1 WHERE id = $2";
+        sqlx::query(sql_str)
+            .bind(status as i32)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_records(
+        pool: &sqlx::PgPool,
+        limit: i64,
+    ) -> Result<Vec<ConfirmationAudit>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_confirmation_audit ORDER BY id DESC LIMIT $1";
+        let records = sqlx::query_as::<_, ConfirmationAudit>(sql_str)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(records)
+    }
+}
+
+/// A middleware which rejects the destructive requests without a valid confirmation token.
+pub struct DestructiveConfirmation {
+    pool: Arc<sqlx::PgPool>,
+}
+
+impl DestructiveConfirmation {
+    pub fn new(pool: Arc<sqlx::PgPool>) -> Self {
+        if let Err(e) = get_secret() {
+            error!("{} All destructive requests will be rejected.", e);
+        }
+
+        DestructiveConfirmation { pool }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for DestructiveConfirmation {
+    type Output = DestructiveConfirmationEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        DestructiveConfirmationEndpoint {
+            ep,
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+pub struct DestructiveConfirmationEndpoint<E> {
+    ep: E,
+    pool: Arc<sqlx::PgPool>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for DestructiveConfirmationEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        if !is_confirmed_endpoint(&method, &path) {
+            return self.ep.call(req).await.map(|resp| resp.into_response());
+        }
+
+        let token = req
+            .headers()
+            .get(CONFIRMATION_TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let verified = if token.is_empty() {
+            Err(format!(
+                "The request requires a confirmation token in the {} header, please request one by /api/v1/confirmation-tokens first.",
+                CONFIRMATION_TOKEN_HEADER
+            ))
+        } else {
+            ConfirmationToken::verify(
+                &token,
+                &get_scope(&req),
+                &method,
+                &path,
+                Utc::now().timestamp(),
+            )
+        };
+
+        let nonce = match verified {
+            Ok(nonce) => nonce,
+            Err(msg) => {
+                if let Err(e) =
+                    ConfirmationAudit::record_rejected(&self.pool, &method, &path, &msg).await
+                {
+                    warn!("Failed to audit the rejected request: {}", e);
+                }
+                return Ok(error_response(StatusCode::FORBIDDEN, &msg));
+            }
+        };
+
+        // The executed step is unique for each nonce, so a token can't be replayed.
+        let audit_id =
+            match ConfirmationAudit::record_executed(&self.pool, &nonce, &method, &path).await {
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    return Ok(error_response(
+                        StatusCode::CONFLICT,
+                        "The confirmation token has been used.",
+                    ))
+                }
+                Err(e) => {
+                    // The destructive request is not allowed if it can't be audited.
+                    return Ok(error_response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        &format!("Failed to audit the request: {}", e),
+                    ));
+                }
+            };
+
+        let resp = self.ep.call(req).await?.into_response();
+        if let Err(e) =
+            ConfirmationAudit::update_status(&self.pool, audit_id, resp.status().as_u16()).await
+        {
+            warn!("Failed to audit the status of the request: {}", e);
+        }
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_token() {
+        let secret = b"secret";
+        let token =
+            ConfirmationToken::issue_with_secret(secret, "scope", "delete", "/api/v1/datasets/ctd");
+        let now = Utc::now().timestamp();
+        assert!(ConfirmationToken::verify_with_secret(
+            secret,
+            &token.token,
+            "scope",
+            "DELETE",
+            "/api/v1/datasets/ctd",
+            now
+        )
+        .is_ok());
+        // The token is bound to the secret, the user, the method and the path.
+        assert!(ConfirmationToken::verify_with_secret(
+            b"other",
+            &token.token,
+            "scope",
+            "DELETE",
+            "/api/v1/datasets/ctd",
+            now
+        )
+        .is_err());
+        assert!(ConfirmationToken::verify_with_secret(
+            secret,
+            &token.token,
+            "other",
+            "DELETE",
+            "/api/v1/datasets/ctd",
+            now
+        )
+        .is_err());
+        assert!(ConfirmationToken::verify_with_secret(
+            secret,
+            &token.token,
+            "scope",
+            "DELETE",
+            "/api/v1/datasets/drugbank",
+            now
+        )
+        .is_err());
+        assert!(ConfirmationToken::verify_with_secret(
+            secret,
+            &token.token,
+            "scope",
+            "DELETE",
+            "/api/v1/datasets/ctd",
+            now + CONFIRMATION_TOKEN_TTL_SECS + 1
+        )
+        .is_err());
+        assert!(ConfirmationToken::verify_with_secret(
+            secret,
+            "malformed",
+            "scope",
+            "DELETE",
+            "/api/v1/datasets/ctd",
+            now
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_is_confirmed_endpoint() {
+        assert!(is_confirmed_endpoint("DELETE", "/api/v1/datasets/ctd"));
+        assert!(!is_confirmed_endpoint("GET", "/api/v1/datasets/ctd"));
+        assert!(!is_confirmed_endpoint("DELETE", "/api/v1/subgraphs/1"));
+    }
+}
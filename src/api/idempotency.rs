@@ -0,0 +1,352 @@
+//! Idempotency keys for the creation endpoints, so a retried POST from a flaky client doesn't create a duplicate curation, subgraph, etc.
+//!
+//! A client sends a unique `Idempotency-Key` header with a POST request. The first response of the key is persisted with a fingerprint of the request, and the later requests with the same key get the persisted response instead of running the endpoint again. The keys are scoped by the Authorization header, so the users can't see the responses of the others, and they are removed after the TTL.
+
+use crate::api::util::{error_response, matches_endpoint, sha256_hex};
+use log::{debug, warn};
+use poem::http::{header, Method, StatusCode};
+use poem::{Body, Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use std::sync::Arc;
+
+/// The header which carries the idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The header which is added to a replayed response.
+pub const IDEMPOTENCY_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// How long the responses are kept, it can be overridden by the environment variable.
+pub const IDEMPOTENCY_KEY_TTL_ENV: &str = "IDEMPOTENCY_KEY_TTL_SECS";
+pub const DEFAULT_IDEMPOTENCY_KEY_TTL_SECS: i64 = 86400;
+
+/// How often the server removes the expired keys.
+pub const IDEMPOTENCY_CLEANUP_INTERVAL_SECS: u64 = 3600;
+
+pub const IDEMPOTENCY_KEY_MAX_LENGTH: usize = 255;
+
+/// The creation endpoints which accept the idempotency key, a segment starting with `:` matches any segment.
+pub const DEFAULT_IDEMPOTENT_ENDPOINTS: [&str; 6] = [
+    "/api/v1/curated-knowledges",
+    "/api/v1/subgraphs",
+    "/api/v1/relations/:id/verification",
+    "/api/v1/node-tags",
+    "/api/v1/dataset-licenses",
+    "/api/v1/export-jobs",
+];
+
+/// A persisted response of an idempotency key. The status is None while the first request is still running.
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct IdempotencyRecord {
+    fingerprint: String,
+    status: Option<i32>,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+}
+
+impl IdempotencyRecord {
+    /// Claim the key for a request, return the existing record if the key has been claimed.
+    async fn claim(
+        pool: &sqlx::PgPool,
+        scope: &str,
+        key: &str,
+        fingerprint: &str,
+        ttl_secs: i64,
+    ) -> Result<Option<IdempotencyRecord>, anyhow::Error> {
+        // The expired key can be reused.
+        let sql_str = "DELETE FROM biomedgps_idempotency_key WHERE scope = $1 AND idempotency_key = $2 AND expired_at < now()";
+        sqlx::query(sql_str)
+            .bind(scope)
+            .bind(key)
+            .execute(pool)
+            .await?;
+
+        let sql_str = "INSERT INTO biomedgps_idempotency_key (scope, idempotency_key, fingerprint, expired_at) VALUES ($1, $2, $3, now() + make_interval(secs => $4)) ON CONFLICT (scope, idempotency_key) DO NOTHING";
+        let result = sqlx::query(sql_str)
+            .bind(scope)
+            .bind(key)
+            .bind(fingerprint)
+            .bind(ttl_secs as f64)
+            .execute(pool)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            return Ok(None);
+        }
+
+        let sql_str = "SELECT fingerprint, status, content_type, body FROM biomedgps_idempotency_key WHERE scope = $1 AND idempotency_key = $2";
+        let record = sqlx::query_as::<_, IdempotencyRecord>(sql_str)
+            .bind(scope)
+            .bind(key)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(Some(record))
+    }
+
+    async fn complete(
+        pool: &sqlx::PgPool,
+        scope: &str,
+        key: &str,
+        status: u16,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_idempotency_key SET status = $1, content_type = $2, body = $3 WHERE scope = $4 AND idempotency_key = $5";
+        sqlx::query(sql_str)
+            .bind(status as i32)
+            .bind(content_type)
+            .bind(body)
+            .bind(scope)
+            .bind(key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Release the key, so the request can be retried, such as after a server error.
+    async fn release(pool: &sqlx::PgPool, scope: &str, key: &str) -> Result<(), anyhow::Error> {
+        let sql_str =
+            "DELETE FROM biomedgps_idempotency_key WHERE scope = $1 AND idempotency_key = $2";
+        sqlx::query(sql_str)
+            .bind(scope)
+            .bind(key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Remove the expired keys. It's called by the cleanup task of the server periodically.
+///
+/// # Returns
+/// * `Result<u64, anyhow::Error>` - How many keys are removed or an error
+pub async fn cleanup_expired_idempotency_keys(pool: &sqlx::PgPool) -> Result<u64, anyhow::Error> {
+    let sql_str = "DELETE FROM biomedgps_idempotency_key WHERE expired_at < now()";
+    let result = sqlx::query(sql_str).execute(pool).await?;
+    Ok(result.rows_affected())
+}
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyConfig {
+    /// The path patterns of the endpoints which accept the idempotency key.
+    pub endpoints: Vec<String>,
+    pub ttl_secs: i64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        IdempotencyConfig {
+            endpoints: DEFAULT_IDEMPOTENT_ENDPOINTS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            ttl_secs: DEFAULT_IDEMPOTENCY_KEY_TTL_SECS,
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    /// Build the config with the TTL from the environment variable, the default TTL is used if it's not set or invalid.
+    pub fn from_env() -> Self {
+        let mut config = IdempotencyConfig::default();
+        if let Ok(ttl) = std::env::var(IDEMPOTENCY_KEY_TTL_ENV) {
+            match ttl.parse::<i64>() {
+                Ok(ttl) if ttl > 0 => config.ttl_secs = ttl,
+                _ => warn!(
+                    "{} should be a positive integer, the default value is used.",
+                    IDEMPOTENCY_KEY_TTL_ENV
+                ),
+            }
+        }
+
+        config
+    }
+
+    pub fn is_idempotent(&self, method: &Method, path: &str) -> bool {
+        *method == Method::POST && self.endpoints.iter().any(|e| matches_endpoint(e, path))
+    }
+}
+
+/// A middleware which replays the persisted responses of the idempotency keys.
+pub struct Idempotency {
+    pool: Arc<sqlx::PgPool>,
+    config: Arc<IdempotencyConfig>,
+}
+
+impl Idempotency {
+    pub fn new(pool: Arc<sqlx::PgPool>, config: IdempotencyConfig) -> Self {
+        Idempotency {
+            pool,
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Idempotency {
+    type Output = IdempotencyEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        IdempotencyEndpoint {
+            ep,
+            pool: self.pool.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+pub struct IdempotencyEndpoint<E> {
+    ep: E,
+    pool: Arc<sqlx::PgPool>,
+    config: Arc<IdempotencyConfig>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for IdempotencyEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let key = match req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(key) if self.config.is_idempotent(req.method(), req.uri().path()) => {
+                key.trim().to_string()
+            }
+            _ => return self.ep.call(req).await.map(|resp| resp.into_response()),
+        };
+
+        if key.is_empty() || key.len() > IDEMPOTENCY_KEY_MAX_LENGTH {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "The {} header should be 1 to {} characters.",
+                    IDEMPOTENCY_KEY_HEADER, IDEMPOTENCY_KEY_MAX_LENGTH
+                ),
+            ));
+        }
+
+        // The token itself is never persisted, only its hash is used to scope the keys.
+        let authorization = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .map(|v| v.as_bytes().to_vec())
+            .unwrap_or_default();
+        let scope = sha256_hex(&authorization);
+
+        let body = req.take_body().into_vec().await?;
+        let mut request_data = format!("{} {}\n", req.method(), req.uri()).into_bytes();
+        request_data.extend_from_slice(&body);
+        let fingerprint = sha256_hex(&request_data);
+        req.set_body(Body::from(body));
+
+        let record = match IdempotencyRecord::claim(
+            &self.pool,
+            &scope,
+            &key,
+            &fingerprint,
+            self.config.ttl_secs,
+        )
+        .await
+        {
+            Ok(record) => record,
+            Err(e) => {
+                // The request still runs without the idempotency if the database is unavailable for the keys.
+                warn!("Failed to claim the idempotency key {}: {}", key, e);
+                return self.ep.call(req).await.map(|resp| resp.into_response());
+            }
+        };
+
+        if let Some(record) = record {
+            if record.fingerprint != fingerprint {
+                return Ok(error_response(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    &format!(
+                        "The idempotency key {} has been used by a different request.",
+                        key
+                    ),
+                ));
+            }
+
+            return match (record.status, record.body) {
+                (Some(status), Some(body)) => {
+                    debug!("Replay the response of the idempotency key {}.", key);
+                    let mut resp = Response::builder()
+                        .status(StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK))
+                        .header(IDEMPOTENCY_REPLAYED_HEADER, "true");
+                    if let Some(content_type) = record.content_type {
+                        resp = resp.content_type(&content_type);
+                    }
+                    Ok(resp.body(body))
+                }
+                _ => Ok(error_response(
+                    StatusCode::CONFLICT,
+                    &format!(
+                        "The request with the idempotency key {} is still in progress.",
+                        key
+                    ),
+                )),
+            };
+        }
+
+        let mut resp = match self.ep.call(req).await {
+            Ok(resp) => resp.into_response(),
+            Err(e) => {
+                if let Err(e) = IdempotencyRecord::release(&self.pool, &scope, &key).await {
+                    warn!("Failed to release the idempotency key {}: {}", key, e);
+                }
+                return Err(e);
+            }
+        };
+
+        // The server errors are not persisted, so the client can retry with the same key.
+        if resp.status().is_server_error() {
+            if let Err(e) = IdempotencyRecord::release(&self.pool, &scope, &key).await {
+                warn!("Failed to release the idempotency key {}: {}", key, e);
+            }
+            return Ok(resp);
+        }
+
+        let body = resp.take_body().into_vec().await?;
+        let content_type = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        if let Err(e) = IdempotencyRecord::complete(
+            &self.pool,
+            &scope,
+            &key,
+            resp.status().as_u16(),
+            content_type.as_deref(),
+            &body,
+        )
+        .await
+        {
+            warn!(
+                "Failed to save the response of the idempotency key {}: {}",
+                key, e
+            );
+        }
+        resp.set_body(Body::from(body));
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_idempotent() {
+        let config = IdempotencyConfig::default();
+        assert!(config.is_idempotent(&Method::POST, "/api/v1/curated-knowledges"));
+        assert!(config.is_idempotent(&Method::POST, "/api/v1/subgraphs/"));
+        assert!(config.is_idempotent(&Method::POST, "/api/v1/relations/123/verification"));
+        assert!(!config.is_idempotent(&Method::POST, "/api/v1/subgraphs/search"));
+        assert!(!config.is_idempotent(&Method::GET, "/api/v1/subgraphs"));
+        assert!(!config.is_idempotent(&Method::POST, "/api/v1/relations//verification"));
+    }
+}
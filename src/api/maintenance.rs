@@ -0,0 +1,304 @@
+//! A maintenance mode which protects the API users from the partially rebuilt tables during a destructive import, such as `importdb --drop`.
+//!
+//! The importer stores the maintenance state in the configuration table before it drops a table, and removes it after the table is reloaded. While the state exists, the write requests are rejected with 503 and a Retry-After header, and the read responses are marked with the `X-Data-Stale` header because they might be incomplete.
+
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use log::{info, warn};
+use poem::http::{header, Method, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The key of the maintenance state in the configuration table.
+pub const MAINTENANCE_CONFIG_KEY: &str = "import_maintenance";
+
+/// The header which marks the read responses as potentially stale during a maintenance.
+pub const STALE_DATA_HEADER: &str = "X-Data-Stale";
+
+/// The estimated throughput of an import, it's used to estimate the ETA from the size of the data files.
+pub const IMPORT_BYTES_PER_SECOND: u64 = 5 * 1024 * 1024;
+
+/// The min estimated duration of an import in seconds.
+pub const MIN_MAINTENANCE_SECS: i64 = 60;
+
+/// A maintenance is ignored after this many times of its estimated duration (at least one hour), so a crashed import doesn't block the writes forever.
+pub const MAINTENANCE_EXPIRY_FACTOR: i32 = 4;
+
+/// How long the middleware caches the maintenance state, so the configuration table isn't queried by every request.
+const MAINTENANCE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// The POST endpoints which only read the database, they are allowed during a maintenance.
+pub const READ_ONLY_POST_ENDPOINTS: [&str; 10] = [
+    "/api/v1/entities/search",
+    "/api/v1/entities/exists",
+    "/api/v1/curated-knowledges/search",
+    "/api/v1/relations/search",
+    "/api/v1/entity2d/search",
+    "/api/v1/subgraphs/search",
+    "/api/v1/predicted-nodes/batch",
+    "/api/v1/graph-layout",
+    "/api/v1/graph-metrics",
+    "/api/v1/paths/narrate",
+];
+
+/// The endpoints which control the import jobs, they are allowed during a maintenance, so a running import can be cancelled.
+pub const IMPORT_JOB_ENDPOINT_PREFIX: &str = "/api/v1/import-jobs";
+
+/// The state of a running destructive import.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct MaintenanceState {
+    // The table which is dropped and reloaded, such as relation.
+    pub table: String,
+    #[serde(with = "ts_seconds")]
+    pub started_at: DateTime<Utc>,
+    // The estimated time when the import is finished.
+    #[serde(with = "ts_seconds")]
+    pub eta: DateTime<Utc>,
+    #[serde(with = "ts_seconds")]
+    pub expired_at: DateTime<Utc>,
+}
+
+impl MaintenanceState {
+    pub fn new(table: &str, estimated_secs: i64) -> Self {
+        let estimated_secs = estimated_secs.max(MIN_MAINTENANCE_SECS);
+        let started_at = Utc::now();
+        let expiry_secs = (estimated_secs * MAINTENANCE_EXPIRY_FACTOR as i64).max(3600);
+        MaintenanceState {
+            table: table.to_string(),
+            started_at,
+            eta: started_at + ChronoDuration::seconds(estimated_secs),
+            expired_at: started_at + ChronoDuration::seconds(expiry_secs),
+        }
+    }
+
+    /// Estimate the duration of an import by the size of the data file or all files in the directory.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::api::maintenance::{MaintenanceState, MIN_MAINTENANCE_SECS};
+    ///
+    /// assert_eq!(MaintenanceState::estimate_import_secs(&None), MIN_MAINTENANCE_SECS);
+    /// ```
+    pub fn estimate_import_secs(filepath: &Option<String>) -> i64 {
+        let path = match filepath {
+            Some(filepath) => PathBuf::from(filepath),
+            None => return MIN_MAINTENANCE_SECS,
+        };
+
+        let size = if path.is_dir() {
+            match std::fs::read_dir(&path) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| entry.metadata().ok())
+                    .filter(|metadata| metadata.is_file())
+                    .map(|metadata| metadata.len())
+                    .sum::<u64>(),
+                Err(_) => 0,
+            }
+        } else {
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        };
+
+        ((size / IMPORT_BYTES_PER_SECOND) as i64).max(MIN_MAINTENANCE_SECS)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expired_at
+    }
+
+    /// The seconds which the clients should wait before retrying the writes, the import might take longer than the estimation.
+    pub fn retry_after_secs(&self, now: DateTime<Utc>) -> i64 {
+        (self.eta - now).num_seconds().max(MIN_MAINTENANCE_SECS)
+    }
+
+    /// Get the maintenance state, None means no destructive import is running.
+    pub async fn get(pool: &sqlx::PgPool) -> Result<Option<Self>, anyhow::Error> {
+        let sql_str = "SELECT value FROM biomedgps_configuration WHERE key = $1";
+        let value = sqlx::query_scalar::<_, serde_json::Value>(sql_str)
+            .bind(MAINTENANCE_CONFIG_KEY)
+            .fetch_optional(pool)
+            .await?;
+
+        match value {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Enter the maintenance mode before the tables are dropped.
+    pub async fn enter(&self, pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_configuration (key, value) VALUES ($1, $2) ON CONFLICT (key) DO UPDATE SET value = $2, updated_at = now()";
+        sqlx::query(sql_str)
+            .bind(MAINTENANCE_CONFIG_KEY)
+            .bind(serde_json::to_value(self)?)
+            .execute(pool)
+            .await?;
+
+        info!(
+            "Enter the maintenance mode for reloading the {} table, the ETA is {}.",
+            self.table, self.eta
+        );
+        Ok(())
+    }
+
+    /// Exit the maintenance mode after the tables are reloaded.
+    pub async fn exit(pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+        let sql_str = "DELETE FROM biomedgps_configuration WHERE key = $1";
+        sqlx::query(sql_str)
+            .bind(MAINTENANCE_CONFIG_KEY)
+            .execute(pool)
+            .await?;
+
+        info!("Exit the maintenance mode.");
+        Ok(())
+    }
+}
+
+/// Whether a request writes the database, the safe methods, the read-only search endpoints and the import job endpoints are still allowed during a maintenance.
+pub fn is_write_request(method: &Method, path: &str) -> bool {
+    if *method == Method::GET || *method == Method::HEAD || *method == Method::OPTIONS {
+        return false;
+    }
+
+    if *method == Method::POST && READ_ONLY_POST_ENDPOINTS.contains(&path.trim_end_matches('/')) {
+        return false;
+    }
+
+    if path.starts_with(IMPORT_JOB_ENDPOINT_PREFIX) {
+        return false;
+    }
+
+    path.starts_with("/api/v1/")
+}
+
+/// A middleware which blocks the writes and marks the reads as stale during a destructive import.
+pub struct ImportMaintenance {
+    pool: Arc<sqlx::PgPool>,
+    cache: Arc<Mutex<Option<(Instant, Option<MaintenanceState>)>>>,
+}
+
+impl ImportMaintenance {
+    pub fn new(pool: Arc<sqlx::PgPool>) -> Self {
+        ImportMaintenance {
+            pool,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ImportMaintenance {
+    type Output = ImportMaintenanceEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ImportMaintenanceEndpoint {
+            ep,
+            pool: self.pool.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+pub struct ImportMaintenanceEndpoint<E> {
+    ep: E,
+    pool: Arc<sqlx::PgPool>,
+    cache: Arc<Mutex<Option<(Instant, Option<MaintenanceState>)>>>,
+}
+
+impl<E> ImportMaintenanceEndpoint<E> {
+    async fn get_state(&self) -> Option<MaintenanceState> {
+        if let Some((fetched_at, state)) = self.cache.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < MAINTENANCE_CACHE_TTL {
+                return state.clone();
+            }
+        }
+
+        // The requests are not blocked if the state can't be fetched, such as the configuration table doesn't exist.
+        let state = match MaintenanceState::get(&self.pool).await {
+            Ok(Some(state)) if state.is_expired(Utc::now()) => {
+                warn!(
+                    "The maintenance for the {} table is expired at {}, it's ignored. Please check whether the import is still running.",
+                    state.table, state.expired_at
+                );
+                None
+            }
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Failed to fetch the maintenance state: {}", e);
+                None
+            }
+        };
+
+        *self.cache.lock().unwrap() = Some((Instant::now(), state.clone()));
+        state
+    }
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for ImportMaintenanceEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let state = match self.get_state().await {
+            Some(state) => state,
+            None => return self.ep.call(req).await.map(|resp| resp.into_response()),
+        };
+
+        if is_write_request(req.method(), req.uri().path()) {
+            let msg = format!(
+                "The {} table is being reloaded, the writes are disabled until it's finished. The ETA is {}.",
+                state.table,
+                state.eta.to_rfc3339()
+            );
+            warn!("{}", msg);
+            return Ok(Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(
+                    header::RETRY_AFTER,
+                    state.retry_after_secs(Utc::now()).to_string(),
+                )
+                .content_type("application/json")
+                .body(
+                    serde_json::json!({ "msg": msg, "eta": state.eta.to_rfc3339() }).to_string(),
+                ));
+        }
+
+        let mut resp = self.ep.call(req).await?.into_response();
+        resp.headers_mut()
+            .insert(STALE_DATA_HEADER, header::HeaderValue::from_static("true"));
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_write_request() {
+        assert!(!is_write_request(&Method::GET, "/api/v1/relations"));
+        assert!(!is_write_request(&Method::POST, "/api/v1/relations/search"));
+        assert!(is_write_request(&Method::POST, "/api/v1/subgraphs"));
+        assert!(is_write_request(&Method::DELETE, "/api/v1/node-tags/1"));
+        assert!(!is_write_request(&Method::POST, "/webhooks/data-registry"));
+        assert!(!is_write_request(
+            &Method::POST,
+            "/api/v1/import-jobs/1/cancel"
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_state() {
+        let state = MaintenanceState::new("relation", 10);
+        assert_eq!(
+            state.eta - state.started_at,
+            ChronoDuration::seconds(MIN_MAINTENANCE_SECS)
+        );
+        assert!(!state.is_expired(state.started_at));
+        assert!(state.is_expired(state.started_at + ChronoDuration::seconds(3600)));
+        assert_eq!(state.retry_after_secs(state.eta), MIN_MAINTENANCE_SECS);
+    }
+}
@@ -2,4 +2,13 @@
 
 pub mod route;
 pub mod schema;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod public;
+pub mod webhook;
+pub mod idempotency;
+pub mod confirmation;
+pub mod maintenance;
+pub mod api_key;
+pub mod audit;
+pub mod rate_limit;
+pub mod util;
@@ -0,0 +1,224 @@
+//! A restricted read-only mode for the anonymous users, such as the visitors of a public demo.
+//!
+//! The unauthenticated requests are mapped to a read-only persona (see [`PUBLIC_USERNAME`](../auth/constant.PUBLIC_USERNAME.html)) only when they target a whitelisted endpoint with a safe method (GET/HEAD), and each client IP is limited by a fixed-window rate limiter. The X-Real-IP header of a trusted proxy is used as the client IP, see [`client_ip`](../util/fn.client_ip.html). All other requests still need the Authorization header.
+
+use crate::api::util::client_ip;
+use log::{debug, warn};
+use poem::http::{header, Method, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The default endpoints which are open to the anonymous users. They are all read-only endpoints for browsing entities and precomputed graphs.
+pub const DEFAULT_PUBLIC_ENDPOINTS: [&str; 12] = [
+    "/api/v1/statistics",
+    "/api/v1/enums",
+    "/api/v1/entity-metadata",
+    "/api/v1/entity-colormap",
+    "/api/v1/relation-metadata",
+    "/api/v1/entities",
+    "/api/v1/entity-attributes",
+    "/api/v1/relation-counts",
+    "/api/v1/subgraphs",
+    "/api/v1/nodes",
+    "/api/v1/one-step-linked-nodes",
+    "/api/v1/trending-entities",
+];
+
+/// The default number of requests which an anonymous client can send in one minute.
+pub const DEFAULT_PUBLIC_RATE_LIMIT: u32 = 30;
+
+/// A marker which is inserted into the request extensions by the [`PublicMode`] middleware. The jwt_token_checker will map the request to the read-only persona when it finds the marker.
+#[derive(Debug, Clone, Copy)]
+pub struct PublicAccess;
+
+#[derive(Debug, Clone)]
+pub struct PublicModeConfig {
+    /// The path prefixes of the whitelisted endpoints, such as `/api/v1/entities`.
+    pub endpoints: Vec<String>,
+    /// The maximum number of requests per client in one window.
+    pub rate_limit: u32,
+    pub window: Duration,
+}
+
+impl Default for PublicModeConfig {
+    fn default() -> Self {
+        PublicModeConfig {
+            endpoints: DEFAULT_PUBLIC_ENDPOINTS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            rate_limit: DEFAULT_PUBLIC_RATE_LIMIT,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PublicModeConfig {
+    /// Build the config from a comma-separated list of endpoints, such as `/api/v1/entities,/api/v1/subgraphs`. The default endpoints will be used if the list is empty.
+    pub fn new(endpoints: Option<&str>, rate_limit: Option<u32>) -> Self {
+        let mut config = PublicModeConfig::default();
+        if let Some(endpoints) = endpoints {
+            let endpoints = endpoints
+                .split(",")
+                .map(|x| x.trim().trim_end_matches("/").to_string())
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<String>>();
+
+            if !endpoints.is_empty() {
+                config.endpoints = endpoints;
+            }
+        }
+
+        if let Some(rate_limit) = rate_limit {
+            config.rate_limit = rate_limit;
+        }
+
+        config
+    }
+
+    /// Only the safe methods are allowed, so the write endpoints are always authenticated even if they share the same path with a whitelisted endpoint.
+    pub fn is_public(&self, method: &Method, path: &str) -> bool {
+        if *method != Method::GET && *method != Method::HEAD {
+            return false;
+        }
+
+        self.endpoints.iter().any(|endpoint| {
+            path == endpoint
+                || (path.starts_with(endpoint.as_str()) && path[endpoint.len()..].starts_with("/"))
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct RateLimiter {
+    // client -> (the start of the current window, the number of requests in the window)
+    clients: Mutex<HashMap<String, (Instant, u32)>>,
+    // When the expired windows are dropped last time.
+    last_pruned: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn check(&self, client: &str, limit: u32, window: Duration, now: Instant) -> bool {
+        let mut clients = self.clients.lock().unwrap();
+
+        // Drop the expired windows once per window to keep the map small, instead of scanning the map for every request.
+        let mut last_pruned = self.last_pruned.lock().unwrap();
+        if last_pruned.map_or(true, |last| now.saturating_duration_since(last) >= window) {
+            clients.retain(|_, (start, _)| now.saturating_duration_since(*start) < window);
+            *last_pruned = Some(now);
+        }
+
+        let entry = clients.entry(client.to_string()).or_insert((now, 0));
+        if now.saturating_duration_since(entry.0) >= window {
+            *entry = (now, 0);
+        }
+        if entry.1 >= limit {
+            return false;
+        }
+
+        entry.1 += 1;
+        true
+    }
+}
+
+/// A middleware which enables the anonymous read-only mode.
+pub struct PublicMode {
+    config: Arc<PublicModeConfig>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl PublicMode {
+    pub fn new(config: PublicModeConfig) -> Self {
+        PublicMode {
+            config: Arc::new(config),
+            limiter: Arc::new(RateLimiter::default()),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for PublicMode {
+    type Output = PublicModeEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        PublicModeEndpoint {
+            ep,
+            config: self.config.clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+pub struct PublicModeEndpoint<E> {
+    ep: E,
+    config: Arc<PublicModeConfig>,
+    limiter: Arc<RateLimiter>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for PublicModeEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let is_anonymous = !req.headers().contains_key(header::AUTHORIZATION);
+        if is_anonymous && self.config.is_public(req.method(), req.uri().path()) {
+            let client = client_ip(&req);
+            if !self.limiter.check(
+                &client,
+                self.config.rate_limit,
+                self.config.window,
+                Instant::now(),
+            ) {
+                warn!("Too many anonymous requests from {}.", client);
+                return Ok(Response::builder()
+                    .status(StatusCode::TOO_MANY_REQUESTS)
+                    .body("Too many requests, please login or try again later."));
+            }
+
+            debug!(
+                "Map the anonymous request {} to the read-only persona.",
+                req.uri().path()
+            );
+            // The security scheme requires the Authorization header, the token itself is ignored when the marker exists.
+            req.headers_mut().insert(
+                header::AUTHORIZATION,
+                header::HeaderValue::from_static("Bearer anonymous"),
+            );
+            req.extensions_mut().insert(PublicAccess);
+        }
+
+        self.ep.call(req).await.map(|resp| resp.into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public() {
+        let config = PublicModeConfig::new(Some("/api/v1/entities, /api/v1/subgraphs/"), None);
+        assert!(config.is_public(&Method::GET, "/api/v1/entities"));
+        assert!(config.is_public(&Method::GET, "/api/v1/subgraphs/123/stream"));
+        assert!(!config.is_public(&Method::POST, "/api/v1/subgraphs"));
+        assert!(!config.is_public(&Method::GET, "/api/v1/entities-extra"));
+        assert!(!config.is_public(&Method::GET, "/api/v1/curated-knowledges"));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::default();
+        let window = Duration::from_secs(60);
+        let now = Instant::now();
+        assert!(limiter.check("127.0.0.1", 2, window, now));
+        assert!(limiter.check("127.0.0.1", 2, window, now));
+        assert!(!limiter.check("127.0.0.1", 2, window, now));
+        assert!(limiter.check("127.0.0.2", 2, window, now));
+
+        // A new window starts after the old one is expired, and the expired windows are pruned.
+        let later = now + window;
+        assert!(limiter.check("127.0.0.1", 2, window, later));
+        assert_eq!(limiter.clients.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,258 @@
+//! Per-user rate limiting for the expensive graph endpoints, such as the path finding and the predictions, so a single user can't starve the others.
+//!
+//! Each user has a token bucket per server process. A request to a limited endpoint takes one token, the tokens are refilled at a steady rate up to the burst size, and the request is rejected with 429 and a Retry-After header when the bucket is empty. The users are identified by the verified token, the anonymous requests in the public mode and the requests without a valid token are limited by the client address.
+
+use crate::api::auth::{get_request_user, PUBLIC_USERNAME, USERNAME_PLACEHOLDER};
+use crate::api::util::client_ip;
+use log::{info, warn};
+use poem::http::{header, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The expensive endpoints which are limited by default.
+pub const DEFAULT_RATE_LIMITED_ENDPOINTS: [&str; 4] = [
+    "/api/v1/paths",
+    "/api/v1/shared-nodes",
+    "/api/v1/predicted-nodes",
+    "/api/v1/predicted-nodes/batch",
+];
+
+/// How many requests a user can send to the limited endpoints in one minute, 0 disables the rate limiting.
+pub const RATE_LIMIT_PER_MINUTE_ENV: &str = "RATE_LIMIT_PER_MINUTE";
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 30;
+
+/// How many requests a user can send in a burst after being idle.
+pub const RATE_LIMIT_BURST_ENV: &str = "RATE_LIMIT_BURST";
+pub const DEFAULT_RATE_LIMIT_BURST: u32 = 10;
+
+/// The limited endpoints separated by comma, such as `/api/v1/paths,/api/v1/shared-nodes`.
+pub const RATE_LIMITED_ENDPOINTS_ENV: &str = "RATE_LIMITED_ENDPOINTS";
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// The paths of the limited endpoints, the query string is ignored.
+    pub endpoints: Vec<String>,
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            endpoints: DEFAULT_RATE_LIMITED_ENDPOINTS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            burst: DEFAULT_RATE_LIMIT_BURST,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Build the config from the environment variables, the default values are used if they are not set or invalid.
+    pub fn from_env() -> Self {
+        let mut config = RateLimitConfig::default();
+        for (name, value) in [
+            (RATE_LIMIT_PER_MINUTE_ENV, &mut config.per_minute),
+            (RATE_LIMIT_BURST_ENV, &mut config.burst),
+        ] {
+            if let Ok(v) = std::env::var(name) {
+                match v.parse::<u32>() {
+                    Ok(v) => *value = v,
+                    _ => warn!(
+                        "{} should be a non-negative integer, the default value is used.",
+                        name
+                    ),
+                }
+            }
+        }
+
+        if let Ok(endpoints) = std::env::var(RATE_LIMITED_ENDPOINTS_ENV) {
+            let endpoints = endpoints
+                .split(',')
+                .map(|x| x.trim().trim_end_matches('/').to_string())
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<String>>();
+            if !endpoints.is_empty() {
+                config.endpoints = endpoints;
+            }
+        }
+
+        config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.per_minute > 0 && self.burst > 0
+    }
+
+    pub fn is_limited(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.endpoints.iter().any(|e| e == path)
+    }
+}
+
+/// The token buckets of the users, they are kept in memory, so each server process has its own limits.
+#[derive(Debug)]
+pub struct TokenBucketLimiter {
+    capacity: f64,
+    // How many tokens are refilled in one second.
+    refill_rate: f64,
+    // user -> (the remaining tokens, when the tokens are refilled last time)
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+impl TokenBucketLimiter {
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        TokenBucketLimiter {
+            capacity: burst.max(1) as f64,
+            refill_rate: per_minute.max(1) as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token of the user, return how long the user should wait if the bucket is empty.
+    pub fn acquire(&self, user: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // The full buckets are same as the missing ones, so they are dropped to keep the map small.
+        let secs_to_full = self.capacity / self.refill_rate;
+        buckets.retain(|_, (_, last)| {
+            now.saturating_duration_since(*last).as_secs_f64() < secs_to_full
+        });
+
+        let (tokens, last) = buckets
+            .entry(user.to_string())
+            .or_insert((self.capacity, now));
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *tokens = (*tokens + elapsed * self.refill_rate).min(self.capacity);
+        *last = now;
+
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / self.refill_rate))
+        }
+    }
+}
+
+/// A middleware which limits the requests of each user to the expensive endpoints.
+pub struct RateLimit {
+    config: Arc<RateLimitConfig>,
+    limiter: Arc<TokenBucketLimiter>,
+}
+
+impl RateLimit {
+    pub fn new(config: RateLimitConfig) -> Self {
+        if config.is_enabled() {
+            info!(
+                "Rate limiting is enabled, each user can send {} requests per minute (burst {}) to {:?}.",
+                config.per_minute, config.burst, config.endpoints
+            );
+        }
+
+        RateLimit {
+            limiter: Arc::new(TokenBucketLimiter::new(config.per_minute, config.burst)),
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RateLimit {
+    type Output = RateLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            ep,
+            config: self.config.clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+pub struct RateLimitEndpoint<E> {
+    ep: E,
+    config: Arc<RateLimitConfig>,
+    limiter: Arc<TokenBucketLimiter>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for RateLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        if !self.config.is_enabled() || !self.config.is_limited(req.uri().path()) {
+            return self.ep.call(req).await.map(|resp| resp.into_response());
+        }
+
+        // All the anonymous users share the public persona (or the placeholder user if the auth mode is disabled), so they are told apart by the client IP.
+        let user = match get_request_user(&req).await {
+            Some(user)
+                if user.username != PUBLIC_USERNAME && user.username != USERNAME_PLACEHOLDER =>
+            {
+                user.username
+            }
+            _ => client_ip(&req),
+        };
+
+        if let Err(wait) = self.limiter.acquire(&user, Instant::now()) {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let msg = format!(
+                "Too many requests to {}, please try again after {} seconds.",
+                req.uri().path(),
+                retry_after
+            );
+            warn!("{} (user: {})", msg, user);
+            return Ok(Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(header::RETRY_AFTER, retry_after.to_string())
+                .content_type("application/json")
+                .body(serde_json::json!({ "msg": msg }).to_string()));
+        }
+
+        self.ep.call(req).await.map(|resp| resp.into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_limited() {
+        let config = RateLimitConfig::default();
+        assert!(config.is_limited("/api/v1/paths"));
+        assert!(config.is_limited("/api/v1/predicted-nodes/"));
+        assert!(!config.is_limited("/api/v1/paths/narrate"));
+        assert!(!config.is_limited("/api/v1/entities"));
+    }
+
+    #[test]
+    fn test_token_bucket_limiter() {
+        // One token per second, and 2 tokens in a burst.
+        let limiter = TokenBucketLimiter::new(60, 2);
+        let now = Instant::now();
+        assert!(limiter.acquire("alice", now).is_ok());
+        assert!(limiter.acquire("alice", now).is_ok());
+        let wait = limiter.acquire("alice", now).unwrap_err();
+        assert_eq!(wait.as_secs(), 1);
+
+        // The other users have their own buckets.
+        assert!(limiter.acquire("bob", now).is_ok());
+
+        // The tokens are refilled over time, but not more than the burst.
+        assert!(limiter
+            .acquire("alice", now + Duration::from_secs(1))
+            .is_ok());
+        assert!(limiter
+            .acquire("alice", now + Duration::from_secs(1))
+            .is_err());
+        let later = now + Duration::from_secs(100);
+        assert!(limiter.acquire("alice", later).is_ok());
+        assert!(limiter.acquire("alice", later).is_ok());
+        assert!(limiter.acquire("alice", later).is_err());
+    }
+}
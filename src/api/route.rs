@@ -1,26 +1,73 @@
 //! This module defines the routes of the API.
 
-use crate::api::auth::{CustomSecurityScheme, USERNAME_PLACEHOLDER};
+use crate::algorithm::layout::{
+    DEFAULT_LAYOUT_ITERATIONS, MAX_LAYOUT_ITERATIONS, MAX_LAYOUT_NODES,
+};
+use crate::api::api_key::{ApiKey, ApiKeyRequest};
+use crate::api::audit::AuditLog;
+use crate::api::auth::{CustomSecurityScheme, Role, PUBLIC_USERNAME, USERNAME_PLACEHOLDER};
+use crate::api::confirmation::{
+    get_scope, is_confirmed_endpoint, ConfirmationAudit, ConfirmationToken,
+    ConfirmationTokenRequest,
+};
 use crate::api::schema::{
-    ApiTags, DeleteResponse, GetEntityColorMapResponse, GetGraphResponse, GetRecordsResponse,
-    GetRelationCountResponse, GetStatisticsResponse, GetWholeTableResponse, NodeIdsQuery,
-    Pagination, PaginationQuery, PostResponse, PredictedNodeQuery, SubgraphIdQuery,
+    ApiTags, DeleteResponse, GetArtifactResponse, GetConsistencyReportResponse,
+    GetEntityColorMapResponse, GetEntityImageResponse, GetGraphResponse, GetGraphStreamResponse,
+    GetRecordsResponse, GetRelationCountResponse, GetStatisticsResponse,
+    GetSubgraphExtensionResponse, GetWholeTableResponse, NodeIdQuery, NodeIdsQuery, Pagination,
+    PaginationQuery, PostResponse, PredictedNodeQuery, SearchEntitiesResponse,
+    SearchKeySentencesResponse, SubgraphIdQuery,
 };
 use crate::model::core::{
-    Entity, Entity2D, EntityMetadata, KnowledgeCuration, RecordResponse, Relation, RelationCount,
-    RelationMetadata, Statistics, Subgraph,
+    ActivityFeedItem, CountComparison, CuratedKnowledgeFilter, DatasetLicense, Entity, Entity2D,
+    EntityActivity, EntityAttribute, EntityExistence, EntityLabelOption, EntityMetadata, EntityRef,
+    EntitySearchMatch, EntitySuggestion, GraphConsistencyReport, GraphView, IncludeCurated,
+    KeySentenceMatch, KnowledgeCuration, NodeTag, QualifierFilter, RecordResponse, Relation,
+    RelationContext, RelationCount, RelationMetadata, RelationTypeOption, Statistics, StreamFormat,
+    Subgraph, TrendingEntity, DEFAULT_NUM_TRENDING_ENTITIES, MAX_NUM_ENTITY_REFS,
+};
+use crate::model::benchmark::BenchmarkResult;
+use crate::model::export::{CuratorPseudonym, ExportJob, ExportJobRequest};
+use crate::model::graph::{
+    stream_linked_nodes, BatchPrediction, BatchPredictionRequest, ExpansionRecipe, Graph,
+    GraphBackend, GraphLayoutRequest, GraphStatsRecorder, NodeMetrics, NodePosition,
+    PathScoreMethod, PredictionDirection, SubgraphExtension, COMPOSED_ENTITY_DELIMITER,
+    DEFAULT_MIN_ANCHORS, MAX_BATCH_PREDICTION_PAIRS, MAX_DEGREE_PENALTY, MAX_SCORED_PATHS,
+    MAX_STREAM_PAGES, MAX_STREAM_PAGE_SIZE,
+};
+use crate::model::image::{get_image_source_url, EntityImage};
+use crate::model::import_job::{
+    get_import_data_dir, is_valid_upload_name, ImportFile, ImportJob, ImportJobRequest,
+    IMPORT_UPLOAD_DIR,
 };
-use crate::model::graph::Graph;
-use crate::model::init_db::get_kg_score_table_name;
-use crate::model::kge::DEFAULT_MODEL_NAME;
-use crate::model::llm::{ChatBot, Context, LlmResponse};
+use crate::model::init_db::check_kg_score_table;
+use crate::model::kge::{
+    get_embedding_metadata, get_model_table_prefix, EntityEmbedding, KgeModelStatus, SimilarEntity,
+};
+use crate::model::llm::{
+    ChatBot, Context, LlmBudgetExceeded, LlmResponse, LlmUsage, LlmUsageSummary, PathNarrative,
+    PathStep, RelationVerification,
+};
+use crate::model::schedule::{ScheduledTaskRun, TASK_RUN_STATUSES};
 use crate::model::util::match_color;
-use crate::query_builder::cypher_builder::{query_nhops, query_shared_nodes};
-use crate::query_builder::sql_builder::{get_all_field_pairs, make_order_clause_by_pairs};
+use crate::model::variant::Variant;
+use crate::query_builder::cypher_builder::{
+    count_nodes_by_label, count_relations_by_type, get_query_memo_stats, query_expanded_nodes,
+    query_nhops, query_shared_nodes, track_memo_hits, ExpansionMode, QueryMemoStats,
+    MAX_PATHWAY_EXPANSION_DEPTH, MAX_PATHWAY_EXPANSION_LIMIT,
+};
+use crate::query_builder::sql_builder::{
+    get_all_field_pairs, make_order_clause_by_pairs, ComposeQuery,
+};
+use crate::{get_pool_stats, DatabaseUrl, PoolStats};
+use chrono::{DateTime, NaiveDate, Utc};
 use log::{debug, info, warn};
 use poem::web::Data;
-use poem_openapi::{param::Path, param::Query, payload::Json, OpenApi};
+use poem::Body;
+use poem_openapi::{param::Path, param::Query, payload::Binary, payload::Json, OpenApi};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
+use std::time::Instant;
 use validator::Validate;
 
 pub struct BiomedgpsApi;
@@ -65,6 +112,177 @@ impl BiomedgpsApi {
         GetStatisticsResponse::ok(statistics)
     }
 
+    /// Call `/api/v1/statistics/consistency` with query params to compare the entity & relation counts between Postgres and Neo4j.
+    #[oai(
+        path = "/statistics/consistency",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchGraphConsistency"
+    )]
+    async fn fetch_graph_consistency(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        graph: Data<&Arc<neo4rs::Graph>>,
+        dataset: Query<Option<String>>,
+        only_discrepancies: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetConsistencyReportResponse {
+        let pool_arc = pool.clone();
+        let graph_arc = graph.clone();
+        let dataset = dataset.0;
+        let only_discrepancies = only_discrepancies.0.unwrap_or(false);
+
+        let relation_metadata = match RelationMetadata::get_relation_metadata(&pool_arc).await {
+            Ok(relation_metadata) => relation_metadata,
+            Err(e) => {
+                let err = format!("Failed to fetch relation metadata: {}", e);
+                warn!("{}", err);
+                return GetConsistencyReportResponse::bad_request(err);
+            }
+        };
+
+        // The entity metadata is split by resources, but the nodes in Neo4j are only labeled by the entity type.
+        // The entities don't belong to any dataset, so only the entities linked by the relations of the dataset are counted if the dataset is specified.
+        let mut entity_pg_counts: BTreeMap<String, i64> = BTreeMap::new();
+        match &dataset {
+            Some(dataset) => {
+                match EntityMetadata::count_entities_in_dataset(&pool_arc, dataset).await {
+                    Ok(counts) => entity_pg_counts.extend(counts),
+                    Err(e) => {
+                        let err = format!("Failed to count the entities of {}: {}", dataset, e);
+                        warn!("{}", err);
+                        return GetConsistencyReportResponse::bad_request(err);
+                    }
+                }
+            }
+            None => match EntityMetadata::get_entity_metadata(&pool_arc).await {
+                Ok(entity_metadata) => {
+                    for metadata in entity_metadata {
+                        *entity_pg_counts.entry(metadata.entity_type).or_insert(0) +=
+                            metadata.entity_count;
+                    }
+                }
+                Err(e) => {
+                    let err = format!("Failed to fetch entity metadata: {}", e);
+                    warn!("{}", err);
+                    return GetConsistencyReportResponse::bad_request(err);
+                }
+            },
+        };
+
+        let mut entity_counts = vec![];
+        for (label, pg_count) in entity_pg_counts {
+            match count_nodes_by_label(&graph_arc, &label, dataset.as_deref()).await {
+                Ok(neo4j_count) => entity_counts.push(CountComparison::new(
+                    &label,
+                    dataset.as_deref(),
+                    pg_count,
+                    neo4j_count,
+                )),
+                Err(e) => {
+                    let err = format!("Failed to count the nodes of {} in Neo4j: {}", label, e);
+                    warn!("{}", err);
+                    return GetConsistencyReportResponse::bad_request(err);
+                }
+            }
+        }
+
+        let mut relation_pg_counts: BTreeMap<(String, String), i64> = BTreeMap::new();
+        for metadata in relation_metadata {
+            if dataset.is_some() && dataset.as_ref() != Some(&metadata.dataset) {
+                continue;
+            }
+
+            *relation_pg_counts
+                .entry((metadata.relation_type, metadata.dataset))
+                .or_insert(0) += metadata.relation_count;
+        }
+
+        let relation_neo4j_counts =
+            match count_relations_by_type(&graph_arc, dataset.as_deref()).await {
+                Ok(counts) => counts,
+                Err(e) => {
+                    let err = format!("Failed to count the relations in Neo4j: {}", e);
+                    warn!("{}", err);
+                    return GetConsistencyReportResponse::bad_request(err);
+                }
+            };
+
+        // The relations which only exist in Neo4j are also discrepancies.
+        let keys: BTreeSet<(String, String)> = relation_pg_counts
+            .keys()
+            .chain(relation_neo4j_counts.keys())
+            .cloned()
+            .collect();
+        let relation_counts = keys
+            .iter()
+            .map(|key| {
+                CountComparison::new(
+                    &key.0,
+                    Some(&key.1),
+                    *relation_pg_counts.get(key).unwrap_or(&0),
+                    *relation_neo4j_counts.get(key).unwrap_or(&0),
+                )
+            })
+            .collect();
+
+        GetConsistencyReportResponse::ok(GraphConsistencyReport::new(
+            entity_counts,
+            relation_counts,
+            only_discrepancies,
+        ))
+    }
+
+    /// Call `/api/v1/enums/relation-types` with query params to fetch all relation types for the dropdowns.
+    #[oai(
+        path = "/enums/relation-types",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchRelationTypeOptions"
+    )]
+    async fn fetch_relation_type_options(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        dataset: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<RelationTypeOption> {
+        let pool_arc = pool.clone();
+
+        match RelationTypeOption::get_relation_types(&pool_arc, dataset.0.as_deref()).await {
+            Ok(options) => GetWholeTableResponse::ok(options),
+            Err(e) => {
+                let err = format!("Failed to fetch relation types: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/enums/entity-labels` with query params to fetch all entity labels for the dropdowns.
+    #[oai(
+        path = "/enums/entity-labels",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchEntityLabelOptions"
+    )]
+    async fn fetch_entity_label_options(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        dataset: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<EntityLabelOption> {
+        let pool_arc = pool.clone();
+
+        match EntityLabelOption::get_entity_labels(&pool_arc, dataset.0.as_deref()).await {
+            Ok(options) => GetWholeTableResponse::ok(options),
+            Err(e) => {
+                let err = format!("Failed to fetch entity labels: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
     /// Call `/api/v1/entity-metadata` with query params to fetch all entity metadata.
     #[oai(
         path = "/entity-metadata",
@@ -144,7 +362,7 @@ impl BiomedgpsApi {
         }
     }
 
-    /// Call `/api/v1/entities` with query params to fetch entities.
+    /// Call `/api/v1/entities` with query params to fetch entities. Set `view_id` to only fetch the entities which are linked by the relations of a graph view.
     #[oai(
         path = "/entities",
         method = "get",
@@ -158,6 +376,7 @@ impl BiomedgpsApi {
         page_size: Query<Option<u64>>,
         query_str: Query<Option<String>>,
         model_table_prefix: Query<Option<String>>, // A prefix of the entity embedding table name, such as "biomedgps"
+        view_id: Query<Option<i64>>,
         _token: CustomSecurityScheme,
     ) -> GetRecordsResponse<Entity> {
         let pool_arc = pool.clone();
@@ -165,6 +384,28 @@ impl BiomedgpsApi {
         let page_size = page_size.0;
         let model_table_prefix = model_table_prefix.0;
 
+        if view_id.0.is_some() && model_table_prefix.is_some() {
+            let err = "The view_id and the model_table_prefix can't be used together.".to_string();
+            warn!("{}", err);
+            return GetRecordsResponse::bad_request(err);
+        }
+
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
+                        warn!("{}", err);
+                        return GetRecordsResponse::bad_request(err);
+                    }
+                }
+            }
+            None => None,
+        };
+
         let query_str = match query_str.0 {
             Some(query_str) => query_str,
             None => {
@@ -201,10 +442,15 @@ impl BiomedgpsApi {
             None => "id ASC".to_string(),
         };
 
+        let table_name = match &view {
+            Some(view) => view.gen_entity_table_expr("biomedgps_entity"),
+            None => "biomedgps_entity".to_string(),
+        };
+
         let resp = if model_table_prefix.is_none() {
             match RecordResponse::<Entity>::get_records(
                 &pool_arc,
-                "biomedgps_entity",
+                table_name.as_str(),
                 &query,
                 page,
                 page_size,
@@ -242,614 +488,716 @@ impl BiomedgpsApi {
         resp
     }
 
-    /// Call `/api/v1/curated-graph` with query params to fetch curated graph.
+    /// Call `/api/v1/entities/stream` with query params to download all matched entities as NDJSON or CSV (`format=csv`). The entities are streamed in batches, so it works for millions of entities. Set `view_id` to only download the entities which are linked by the relations of a graph view.
     #[oai(
-        path = "/curated-graph",
+        path = "/entities/stream",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchCuratedGraph"
+        operation_id = "streamEntities"
     )]
-    async fn fetch_curated_graph(
+    async fn stream_entities(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        curator: Query<String>,
-        project_id: Query<Option<String>>,
-        organization_id: Query<Option<String>>,
-        page: Query<Option<u64>>,
-        page_size: Query<Option<u64>>,
-        strict_mode: Query<bool>,
+        query_str: Query<Option<String>>,
+        format: Query<Option<StreamFormat>>,
+        view_id: Query<Option<i64>>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> GetArtifactResponse {
         let pool_arc = pool.clone();
-        let curator = curator.0;
-
-        // if curator != _token.0.username {
-        //     let err = format!(
-        //         "You cannot query curated graph from other users. You are {} and you are querying {}'s curated graph.",
-        //         _token.0.username, curator
-        //     );
-        //     warn!("{}", err);
-        //     return GetGraphResponse::bad_request(err);
-        // }
+        let format = format.0.unwrap_or(StreamFormat::Ndjson);
 
-        let project_id = match project_id.0 {
-            Some(project_id) => {
-                // Convert project_id to i32
-                match project_id.parse::<i32>() {
-                    Ok(project_id) => project_id,
-                    Err(e) => {
-                        let err = format!("Failed to parse project id: {}", e);
-                        warn!("{}", err);
-                        return GetGraphResponse::bad_request(err);
-                    }
+        let query_str = query_str.0.unwrap_or_default();
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetArtifactResponse::bad_request(err);
                 }
             }
-            None => {
-                warn!("Project id is empty.");
-                -1
-            }
         };
 
-        let organization_id = match organization_id.0 {
-            Some(organization_id) => {
-                // Convert organization_id to i32
-                match organization_id.parse::<i32>() {
-                    Ok(organization_id) => organization_id,
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
                     Err(e) => {
-                        let err = format!("Failed to parse organization id: {}", e);
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
                         warn!("{}", err);
-                        return GetGraphResponse::bad_request(err);
+                        return GetArtifactResponse::bad_request(err);
                     }
                 }
             }
-            None => {
-                warn!("Organization id is empty.");
-                -1
-            }
-        };
-
-        // Get organizations and projects from the token
-        let user = &_token.0;
-        if organization_id != -1 && !user.organizations.contains(&organization_id) {
-            let err = format!(
-                "User {} doesn't have access to organization {}. Your system might not support querying curated graph by organization or you don't have access to this organization.",
-                user.username, organization_id
-            );
-            warn!("{}", err);
-            return GetGraphResponse::bad_request(err);
+            None => None,
         };
 
-        if project_id != -1 && !user.projects.contains(&project_id) {
-            let err = format!(
-                "User {} doesn't have access to project {}. Your system might not support querying curated graph by project or you don't have access to this project.",
-                user.username, project_id
-            );
-            warn!("{}", err);
-            return GetGraphResponse::bad_request(err);
+        let table_name = match &view {
+            Some(view) => view.gen_entity_table_expr("biomedgps_entity"),
+            None => "biomedgps_entity".to_string(),
         };
 
-        let mut graph = Graph::new();
-        let page = page.0;
-        let page_size = page_size.0;
-        let strict_mode = strict_mode.0;
+        let stream = RecordResponse::<Entity>::stream_records(
+            pool_arc,
+            _token.0.owner_scope(),
+            table_name,
+            query,
+            Some("id ASC".to_string()),
+            format,
+        );
+        GetArtifactResponse::ok(
+            Body::from_bytes_stream(stream),
+            &format!("entities.{}", format.extension()),
+        )
+    }
 
-        match graph
-            .fetch_curated_knowledges(
-                &pool_arc,
-                &curator[..],
-                project_id,
-                organization_id,
-                page,
-                page_size,
-                Some("id ASC"),
-                strict_mode,
-            )
-            .await
-        {
-            Ok(data) => GetGraphResponse::ok(data.to_owned().get_graph(None).unwrap()),
+    /// Call `/api/v1/entities/search` with a query in the body to fetch entities. It's the same as `/api/v1/entities`, but the query is a typed json body instead of the `query_str` param.
+    #[oai(
+        path = "/entities/search",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchEntities"
+    )]
+    async fn search_entities(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query: Json<ComposeQuery>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        model_table_prefix: Query<Option<String>>,
+        view_id: Query<Option<i64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Entity> {
+        let query_str = match serde_json::to_string(&query.0) {
+            Ok(query_str) => query_str,
             Err(e) => {
-                let err = format!("Failed to fetch curated graph: {}", e);
+                let err = format!("Failed to serialize query: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return GetRecordsResponse::bad_request(err);
             }
-        }
+        };
+
+        self.fetch_entities(
+            pool,
+            page,
+            page_size,
+            Query(Some(query_str)),
+            model_table_prefix,
+            view_id,
+            _token,
+        )
+        .await
     }
 
-    /// Call `/api/v1/curated-knowledges-by-owner` with query params to fetch curated knowledges by owner.
+    /// Call `/api/v1/entities/search` with a text to search the entities by the names, the synonyms and the cross references, such as `alzh` or `TP53`. Every term of the text is matched as a prefix, so it works for the type-ahead. The text is also matched exactly against the ids and the cross references, so a CURIE like `DB00001` finds the entity which only has `DrugBank:DB00001` in the cross references. The entities are ranked by the relevance, an exact match of the id is ranked first, then an exact match of the name, and the cross references are ranked above the synonyms. The `matched_field` of each entity tells which field is matched, such as id, name, synonym or xref. Set `entity_type` to filter the entities by the labels, such as `Gene,Protein`. Set `similarity` (such as 0.4) to search the names and the synonyms by the trigram similarity instead, so the misspelled text like `ashtma` still matches Asthma, the score of each entity is the similarity.
     #[oai(
-        path = "/curated-knowledges-by-owner",
+        path = "/entities/search",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchCuratedKnowledgesByOwner"
+        operation_id = "fullTextSearchEntities"
     )]
-    async fn fetch_curated_knowledges_by_owner(
+    async fn full_text_search_entities(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        curator: Query<String>,
-        project_id: Query<Option<String>>,
-        organization_id: Query<Option<String>>,
-        page: Query<Option<u64>>,
-        page_size: Query<Option<u64>>,
-        // We need to confirm the token is valid and contains all projects and organizations which the user has access to.
+        text: Query<String>,
+        entity_type: Query<Option<String>>,
+        limit: Query<Option<i64>>,
+        similarity: Query<Option<f64>>,
         _token: CustomSecurityScheme,
-    ) -> GetRecordsResponse<KnowledgeCuration> {
+    ) -> SearchEntitiesResponse {
         let pool_arc = pool.clone();
-        let curator = curator.0;
+        let text = text.0.trim().to_string();
+        let limit = limit.0.unwrap_or(10).clamp(1, 100);
 
-        if curator != _token.0.username {
-            let err = format!(
-                "You cannot query curated knowledges from other users. You are {} and you are querying {}'s curated knowledges.",
-                _token.0.username, curator
-            );
+        if text.is_empty() {
+            let err = "The text to search is empty.".to_string();
             warn!("{}", err);
-            return GetRecordsResponse::bad_request(err);
+            return SearchEntitiesResponse::bad_request(err);
         }
 
-        let project_id = match project_id.0 {
-            Some(project_id) => {
-                // Convert project_id to i32
-                match project_id.parse::<i32>() {
-                    Ok(project_id) => project_id,
-                    Err(e) => {
-                        let err = format!("Failed to parse project id: {}", e);
-                        warn!("{}", err);
-                        return GetRecordsResponse::bad_request(err);
-                    }
+        let entity_types = entity_type.0.map(|entity_type| {
+            entity_type
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<String>>()
+        });
+
+        let result = match similarity.0 {
+            Some(similarity) => {
+                if !(similarity > 0.0 && similarity <= 1.0) {
+                    let err = format!(
+                        "The similarity should be in (0, 1], such as 0.4, but got {}.",
+                        similarity
+                    );
+                    warn!("{}", err);
+                    return SearchEntitiesResponse::bad_request(err);
                 }
+
+                EntitySearchMatch::fuzzy_search(&pool_arc, &text, &entity_types, similarity, limit)
+                    .await
             }
-            None => {
-                warn!("Project id is empty.");
-                -1
-            }
+            None => EntitySearchMatch::search(&pool_arc, &text, &entity_types, limit).await,
         };
 
-        let organization_id = match organization_id.0 {
-            Some(organization_id) => {
-                // Convert organization_id to i32
-                match organization_id.parse::<i32>() {
-                    Ok(organization_id) => organization_id,
-                    Err(e) => {
-                        let err = format!("Failed to parse organization id: {}", e);
-                        warn!("{}", err);
-                        return GetRecordsResponse::bad_request(err);
-                    }
-                }
-            }
-            None => {
-                warn!("Organization id is empty.");
-                -1
-            }
-        };
-
-        // Get organizations and projects from the token
-        let user = &_token.0;
-        if organization_id != -1 && !user.organizations.contains(&organization_id) {
-            let err = format!(
-                "User {} doesn't have access to organization {}. Your system might not support querying curated knowledges by organization or you don't have access to this organization.",
-                user.username, organization_id
-            );
-            warn!("{}", err);
-            return GetRecordsResponse::bad_request(err);
-        };
-
-        if project_id != -1 && !user.projects.contains(&project_id) {
-            let err = format!(
-                "User {} doesn't have access to project {}. Your system might not support querying curated knowledges by project or you don't have access to this project.",
-                user.username, project_id
-            );
-            warn!("{}", err);
-            return GetRecordsResponse::bad_request(err);
-        };
-
-        match KnowledgeCuration::get_records_by_owner(
-            &pool_arc,
-            &curator,
-            project_id,
-            organization_id,
-            page.0,
-            page_size.0,
-            // TODO: get an order_by clause from query
-            Some("id ASC"),
-        )
-        .await
-        {
-            Ok(entities) => GetRecordsResponse::ok(entities),
+        match result {
+            Ok(matches) => SearchEntitiesResponse::ok(matches),
             Err(e) => {
-                let err = format!("Failed to fetch curated knowledges: {}", e);
+                let err = format!("Failed to search the entities: {}", e);
                 warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
+                return SearchEntitiesResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/curated-knowledges` with query params to fetch curated knowledges.
+    /// Call `/api/v1/entities/exists` with a list of (id, label) pairs to check whether the entities exist. At most 10000 pairs are accepted in one request.
     #[oai(
-        path = "/curated-knowledges",
-        method = "get",
+        path = "/entities/exists",
+        method = "post",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchCuratedKnowledges"
+        operation_id = "checkEntitiesExist"
     )]
-    async fn fetch_curated_knowledges(
+    async fn check_entities_exist(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        page: Query<Option<u64>>,
-        page_size: Query<Option<u64>>,
-        query_str: Query<Option<String>>,
+        payload: Json<Vec<EntityRef>>,
         _token: CustomSecurityScheme,
-    ) -> GetRecordsResponse<KnowledgeCuration> {
+    ) -> GetWholeTableResponse<EntityExistence> {
         let pool_arc = pool.clone();
-        let page = page.0;
-        let page_size = page_size.0;
+        let entities = payload.0;
 
-        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to parse query string: {}", e);
-                warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
-            }
+        if entities.is_empty() {
+            let err = "The entities should not be empty.".to_string();
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
         }
 
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
-            None => {
-                warn!("Query string is empty.");
-                "".to_string()
-            }
-        };
-
-        let query = if query_str == "" {
-            None
-        } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
-                Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
-                    warn!("{}", err);
-                    return GetRecordsResponse::bad_request(err);
-                }
-            }
-        };
+        if entities.len() > MAX_NUM_ENTITY_REFS {
+            let err = format!(
+                "Too many entities ({}), the maximum number is {}.",
+                entities.len(),
+                MAX_NUM_ENTITY_REFS
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
 
-        match RecordResponse::<KnowledgeCuration>::get_records(
-            &pool_arc,
-            "biomedgps_knowledge_curation",
-            &query,
-            page,
-            page_size,
-            Some("id ASC"),
-        )
-        .await
-        {
-            Ok(entities) => GetRecordsResponse::ok(entities),
+        match Entity::check_existence(&pool_arc, &entities).await {
+            Ok(results) => GetWholeTableResponse::ok(results),
             Err(e) => {
-                let err = format!("Failed to fetch curated knowledges: {}", e);
+                let err = format!("Failed to check the entities: {}", e);
                 warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/curated-knowledges` with payload to create a curated knowledge.
+    /// Call `/api/v1/trending-entities` with query params to fetch the most queried entities in a week, such as the trending diseases or genes. The week can be any day (YYYY-MM-DD) in the week, the latest aggregated week is used by default.
     #[oai(
-        path = "/curated-knowledges",
-        method = "post",
+        path = "/trending-entities",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "postCuratedKnowledge"
+        operation_id = "fetchTrendingEntities"
     )]
-    async fn post_curated_knowledge(
+    async fn fetch_trending_entities(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        payload: Json<KnowledgeCuration>,
+        week: Query<Option<String>>,
+        entity_type: Query<Option<String>>,
+        topk: Query<Option<u64>>,
         _token: CustomSecurityScheme,
-    ) -> PostResponse<KnowledgeCuration> {
+    ) -> GetWholeTableResponse<TrendingEntity> {
         let pool_arc = pool.clone();
-        let payload = payload.0;
+        let topk = topk.0.unwrap_or(10);
 
-        match payload.validate() {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate payload: {}", e);
-                warn!("{}", err);
-                return PostResponse::bad_request(err);
-            }
+        if topk == 0 || topk > DEFAULT_NUM_TRENDING_ENTITIES as u64 {
+            let err = format!(
+                "The topk should be between 1 and {}.",
+                DEFAULT_NUM_TRENDING_ENTITIES
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let week = match week.0 {
+            Some(week) => match NaiveDate::parse_from_str(&week, "%Y-%m-%d") {
+                Ok(week) => Some(week),
+                Err(e) => {
+                    let err = format!(
+                        "Failed to parse the week, it must be a date such as 2024-01-22: {}",
+                        e
+                    );
+                    warn!("{}", err);
+                    return GetWholeTableResponse::bad_request(err);
+                }
+            },
+            None => None,
         };
 
-        match payload.insert(&pool_arc).await {
-            Ok(kc) => PostResponse::created(kc),
+        match TrendingEntity::get_records(&pool_arc, week, entity_type.0.as_deref(), topk).await {
+            Ok(entities) => GetWholeTableResponse::ok(entities),
             Err(e) => {
-                let err = format!("Failed to insert curated knowledge: {}", e);
+                let err = format!("Failed to fetch trending entities: {}", e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/curated-knowledges/:id` with payload to create a curated knowledge.
+    /// Call `/api/v1/activities` with query params to fetch the recent items of the current user, such as the subgraphs, the curated knowledges, the export jobs, the graph views and the node tags, the newest first. Set `item_type` to only fetch some types of the items, such as `subgraph,curation`.
     #[oai(
-        path = "/curated-knowledges/:id",
-        method = "put",
+        path = "/activities",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "putCuratedKnowledge"
+        operation_id = "fetchActivities"
     )]
-    async fn put_curated_knowledge(
+    async fn fetch_activities(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        payload: Json<KnowledgeCuration>,
-        id: Path<i64>,
+        item_type: Query<Option<String>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
         _token: CustomSecurityScheme,
-    ) -> PostResponse<KnowledgeCuration> {
+    ) -> GetWholeTableResponse<ActivityFeedItem> {
         let pool_arc = pool.clone();
-        let payload = payload.0;
-        let id = id.0;
+        let page = page.0.unwrap_or(1);
+        let page_size = page_size.0.unwrap_or(10);
 
-        if id < 0 {
-            let err = format!("Invalid id: {}", id);
+        if page == 0 || page_size == 0 || page_size > 100 {
+            let err =
+                "The page should be at least 1 and the page size should be between 1 and 100."
+                    .to_string();
             warn!("{}", err);
-            return PostResponse::bad_request(err);
+            return GetWholeTableResponse::bad_request(err);
         }
 
-        match payload.validate() {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate payload: {}", e);
-                warn!("{}", err);
-                return PostResponse::bad_request(err);
-            }
-        };
+        let item_types = item_type.0.map(|item_type| {
+            item_type
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<String>>()
+        });
 
-        match payload.update(&pool_arc, id).await {
-            Ok(kc) => PostResponse::created(kc),
+        match ActivityFeedItem::get_records(
+            &pool_arc,
+            &_token.0.username,
+            &item_types,
+            page,
+            page_size,
+        )
+        .await
+        {
+            Ok(items) => GetWholeTableResponse::ok(items),
             Err(e) => {
-                let err = format!("Failed to insert curated knowledge: {}", e);
+                let err = format!("Failed to fetch the activities: {}", e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/curated-knowledges/:id` with payload to delete a curated knowledge.
+    /// Call `/api/v1/variants` with query params to find the variants by a rsID or a HGVS string, such as rs1042522 or NM_000546.6:c.215C>G. The id of a variant can be used to query its genes and diseases by `/api/v1/relations`.
     #[oai(
-        path = "/curated-knowledges/:id",
-        method = "delete",
+        path = "/variants",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "deleteCuratedKnowledge"
+        operation_id = "fetchVariants"
     )]
-    async fn delete_curated_knowledge(
+    async fn fetch_variants(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        id: Path<i64>,
+        query: Query<String>,
         _token: CustomSecurityScheme,
-    ) -> DeleteResponse {
+    ) -> GetWholeTableResponse<Variant> {
         let pool_arc = pool.clone();
-        let id = id.0;
-
-        if id < 0 {
-            let err = format!("Invalid id: {}", id);
-            warn!("{}", err);
-            return DeleteResponse::bad_request(err);
-        }
+        let query = query.0;
 
-        match KnowledgeCuration::delete(&pool_arc, id).await {
-            Ok(_) => DeleteResponse::no_content(),
+        match Variant::search(&pool_arc, &query).await {
+            Ok(variants) => GetWholeTableResponse::ok(variants),
             Err(e) => {
-                let err = format!("Failed to delete curated knowledge: {}", e);
+                let err = format!("Failed to fetch the variants: {}", e);
                 warn!("{}", err);
-                DeleteResponse::not_found(err)
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/relations` with query params to fetch relations.
+    /// Call `/api/v1/entity-attributes` with query params to fetch the attributes of an entity. It returns the latest version by default, or the historical snapshot at the given time (RFC3339, such as 2023-01-01T00:00:00Z).
     #[oai(
-        path = "/relations",
+        path = "/entity-attributes",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchRelations"
+        operation_id = "fetchEntityAttributes"
     )]
-    async fn fetch_relations(
+    async fn fetch_entity_attributes(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        page: Query<Option<u64>>,
-        page_size: Query<Option<u64>>,
-        query_str: Query<Option<String>>,
+        node_id: Query<String>,
+        at: Query<Option<String>>,
         _token: CustomSecurityScheme,
-    ) -> GetRecordsResponse<Relation> {
+    ) -> GetWholeTableResponse<EntityAttribute> {
         let pool_arc = pool.clone();
-        let page = page.0;
-        let page_size = page_size.0;
+        let node_id = node_id.0;
 
-        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+        match NodeIdQuery::new(&node_id) {
             Ok(_) => {}
             Err(e) => {
-                let err = format!("Failed to parse query string: {}", e);
+                let err = format!("Failed to parse node id: {}", e);
                 warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
-            }
-        };
-
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
-            None => {
-                warn!("Query string is empty.");
-                "".to_string()
+                return GetWholeTableResponse::bad_request(err);
             }
         };
 
-        let query = if query_str == "" {
-            None
-        } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
+        let at = match at.0 {
+            Some(at) => match DateTime::parse_from_rfc3339(&at) {
+                Ok(at) => Some(at.with_timezone(&Utc)),
                 Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
+                    let err = format!(
+                        "Failed to parse the time, it must be a RFC3339 string: {}",
+                        e
+                    );
                     warn!("{}", err);
-                    return GetRecordsResponse::bad_request(err);
+                    return GetWholeTableResponse::bad_request(err);
                 }
-            }
+            },
+            None => None,
         };
 
-        // TODO: We need to add the model name to the query if we allow users to use different model.
-        // TODO: We need to ensure the table exists before we use it.
-        let table_name = get_kg_score_table_name(DEFAULT_MODEL_NAME);
+        let (entity_type, entity_id) = match node_id.split_once(COMPOSED_ENTITY_DELIMITER) {
+            Some((entity_type, entity_id)) => (entity_type, entity_id),
+            None => {
+                let err = format!("Invalid node id: {}", node_id);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        };
 
-        match RecordResponse::<Relation>::get_records(
+        EntityActivity::record(
             &pool_arc,
-            table_name.as_str(),
-            &query,
-            page,
-            page_size,
-            Some("score ASC"),
+            &_token.0.username,
+            "fetchEntityAttributes",
+            &vec![node_id.as_str()],
         )
-        .await
-        {
-            Ok(entities) => GetRecordsResponse::ok(entities),
+        .await;
+
+        match EntityAttribute::fetch_attributes(&pool_arc, entity_id, entity_type, at).await {
+            Ok(attributes) => GetWholeTableResponse::ok(attributes),
             Err(e) => {
-                let err = format!("Failed to fetch relations: {}", e);
+                let err = format!("Failed to fetch entity attributes: {}", e);
                 warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/relation-counts` with query params to fetch relation counts.
+    /// Call `/api/v1/curated-graph` with query params to fetch curated graph. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field.
     #[oai(
-        path = "/relation-counts",
+        path = "/curated-graph",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchRelationCounts"
+        operation_id = "fetchCuratedGraph"
     )]
-    async fn fetch_relation_counts(
+    async fn fetch_curated_graph(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        query_str: Query<Option<String>>,
+        curator: Query<String>,
+        project_id: Query<Option<String>>,
+        organization_id: Query<Option<String>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        strict_mode: Query<bool>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        include_stats: Query<Option<bool>>,
         _token: CustomSecurityScheme,
-    ) -> GetRelationCountResponse {
+    ) -> GetGraphResponse {
         let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Postgres);
+        let curator = curator.0;
 
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
+        // if curator != _token.0.username {
+        //     let err = format!(
+        //         "You cannot query curated graph from other users. You are {} and you are querying {}'s curated graph.",
+        //         _token.0.username, curator
+        //     );
+        //     warn!("{}", err);
+        //     return GetGraphResponse::bad_request(err);
+        // }
+
+        let project_id = match project_id.0 {
+            Some(project_id) => {
+                // Convert project_id to i32
+                match project_id.parse::<i32>() {
+                    Ok(project_id) => project_id,
+                    Err(e) => {
+                        let err = format!("Failed to parse project id: {}", e);
+                        warn!("{}", err);
+                        return GetGraphResponse::bad_request(err);
+                    }
+                }
+            }
             None => {
-                warn!("Query string is empty.");
-                "".to_string()
+                warn!("Project id is empty.");
+                -1
             }
         };
 
-        let query = if query_str == "" {
-            None
-        } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
-                Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
-                    warn!("{}", err);
-                    return GetRelationCountResponse::bad_request(err);
+        let organization_id = match organization_id.0 {
+            Some(organization_id) => {
+                // Convert organization_id to i32
+                match organization_id.parse::<i32>() {
+                    Ok(organization_id) => organization_id,
+                    Err(e) => {
+                        let err = format!("Failed to parse organization id: {}", e);
+                        warn!("{}", err);
+                        return GetGraphResponse::bad_request(err);
+                    }
                 }
             }
+            None => {
+                warn!("Organization id is empty.");
+                -1
+            }
         };
 
-        match RelationCount::get_records(&pool_arc, &query).await {
-            Ok(entities) => GetRelationCountResponse::ok(entities),
+        // Get organizations and projects from the token
+        let user = &_token.0;
+        if organization_id != -1 && !user.organizations.contains(&organization_id) {
+            let err = format!(
+                "User {} doesn't have access to organization {}. Your system might not support querying curated graph by organization or you don't have access to this organization.",
+                user.username, organization_id
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        };
+
+        if project_id != -1 && !user.projects.contains(&project_id) {
+            let err = format!(
+                "User {} doesn't have access to project {}. Your system might not support querying curated graph by project or you don't have access to this project.",
+                user.username, project_id
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        };
+
+        let mut graph = Graph::new();
+        let page = page.0;
+        let page_size = page_size.0;
+        let strict_mode = strict_mode.0;
+
+        let query_started_at = Instant::now();
+        match graph
+            .fetch_curated_knowledges(
+                &pool_arc,
+                &curator[..],
+                project_id,
+                organization_id,
+                page,
+                page_size,
+                Some("id ASC"),
+                strict_mode,
+            )
+            .await
+        {
+            Ok(data) => {
+                stats.record_query(query_started_at, data.count_items());
+                let mut graph = data
+                    .to_owned()
+                    .get_graph(None, aggregate_edges.0, dedupe.0)
+                    .unwrap();
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                stats.attach(&mut graph);
+                GetGraphResponse::ok(graph)
+            }
             Err(e) => {
-                let err = format!("Failed to fetch relations: {}", e);
+                let err = format!("Failed to fetch curated graph: {}", e);
                 warn!("{}", err);
-                return GetRelationCountResponse::bad_request(err);
+                return GetGraphResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/entity2d` with query params to fetch entity2d.
+    /// Call `/api/v1/curated-knowledges-by-owner` with query params to fetch curated knowledges by owner.
     #[oai(
-        path = "/entity2d",
+        path = "/curated-knowledges-by-owner",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchEntity2d"
+        operation_id = "fetchCuratedKnowledgesByOwner"
     )]
-    async fn fetch_entity2d(
+    async fn fetch_curated_knowledges_by_owner(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
+        curator: Query<String>,
+        project_id: Query<Option<String>>,
+        organization_id: Query<Option<String>>,
         page: Query<Option<u64>>,
         page_size: Query<Option<u64>>,
-        query_str: Query<Option<String>>,
+        // We need to confirm the token is valid and contains all projects and organizations which the user has access to.
         _token: CustomSecurityScheme,
-    ) -> GetRecordsResponse<Entity2D> {
+    ) -> GetRecordsResponse<KnowledgeCuration> {
         let pool_arc = pool.clone();
-        let page = page.0;
-        let page_size = page_size.0;
+        let curator = curator.0;
 
-        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to parse query string: {}", e);
-                warn!("{}", err);
-                return GetRecordsResponse::bad_request(err);
-            }
+        if curator != _token.0.username {
+            let err = format!(
+                "You cannot query curated knowledges from other users. You are {} and you are querying {}'s curated knowledges.",
+                _token.0.username, curator
+            );
+            warn!("{}", err);
+            return GetRecordsResponse::bad_request(err);
         }
 
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
+        let project_id = match project_id.0 {
+            Some(project_id) => {
+                // Convert project_id to i32
+                match project_id.parse::<i32>() {
+                    Ok(project_id) => project_id,
+                    Err(e) => {
+                        let err = format!("Failed to parse project id: {}", e);
+                        warn!("{}", err);
+                        return GetRecordsResponse::bad_request(err);
+                    }
+                }
+            }
             None => {
-                warn!("Query string is empty.");
-                "".to_string()
+                warn!("Project id is empty.");
+                -1
             }
         };
 
-        let query = if query_str == "" {
-            None
-        } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
-                Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
-                    warn!("{}", err);
-                    return GetRecordsResponse::bad_request(err);
+        let organization_id = match organization_id.0 {
+            Some(organization_id) => {
+                // Convert organization_id to i32
+                match organization_id.parse::<i32>() {
+                    Ok(organization_id) => organization_id,
+                    Err(e) => {
+                        let err = format!("Failed to parse organization id: {}", e);
+                        warn!("{}", err);
+                        return GetRecordsResponse::bad_request(err);
+                    }
                 }
             }
+            None => {
+                warn!("Organization id is empty.");
+                -1
+            }
         };
 
-        // TODO: Could we compute the 2d embedding on the fly or by the biomedgps-cli tool?
-        match RecordResponse::<Entity2D>::get_records(
+        // Get organizations and projects from the token
+        let user = &_token.0;
+        if organization_id != -1 && !user.organizations.contains(&organization_id) {
+            let err = format!(
+                "User {} doesn't have access to organization {}. Your system might not support querying curated knowledges by organization or you don't have access to this organization.",
+                user.username, organization_id
+            );
+            warn!("{}", err);
+            return GetRecordsResponse::bad_request(err);
+        };
+
+        if project_id != -1 && !user.projects.contains(&project_id) {
+            let err = format!(
+                "User {} doesn't have access to project {}. Your system might not support querying curated knowledges by project or you don't have access to this project.",
+                user.username, project_id
+            );
+            warn!("{}", err);
+            return GetRecordsResponse::bad_request(err);
+        };
+
+        match KnowledgeCuration::get_records_by_owner(
             &pool_arc,
-            "biomedgps_entity2d",
-            &query,
-            page,
-            page_size,
-            Some("embedding_id ASC"),
+            &curator,
+            project_id,
+            organization_id,
+            page.0,
+            page_size.0,
+            // TODO: get an order_by clause from query
+            Some("id ASC"),
         )
         .await
         {
             Ok(entities) => GetRecordsResponse::ok(entities),
             Err(e) => {
-                let err = format!("Failed to fetch entity2d: {}", e);
+                let err = format!("Failed to fetch curated knowledges: {}", e);
                 warn!("{}", err);
                 return GetRecordsResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/subgraphs` with query params to fetch subgraphs.
+    /// Call `/api/v1/curated-knowledges/disambiguation` with query params to fetch the candidate entities of an ambiguous mention.
     #[oai(
-        path = "/subgraphs",
+        path = "/curated-knowledges/disambiguation",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchSubgraphs"
+        operation_id = "fetchEntitySuggestions"
     )]
-    async fn fetch_subgraphs(
+    async fn fetch_entity_suggestions(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        mention: Query<String>,
+        context: Query<Option<String>>,
+        entity_type: Query<Option<String>>,
+        topk: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<EntitySuggestion> {
+        let pool_arc = pool.clone();
+        let mention = mention.0;
+
+        if mention.trim().is_empty() {
+            let err = "The mention cannot be empty.".to_string();
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let topk = match topk.0 {
+            Some(topk) => topk,
+            None => 10,
+        };
+
+        match EntitySuggestion::disambiguate(
+            &pool_arc,
+            mention.trim(),
+            context.0.as_deref(),
+            entity_type.0.as_deref(),
+            topk,
+        )
+        .await
+        {
+            Ok(suggestions) => GetWholeTableResponse::ok(suggestions),
+            Err(e) => {
+                let err = format!("Failed to fetch entity suggestions: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/curated-knowledges` with query params to fetch curated knowledges.
+    #[oai(
+        path = "/curated-knowledges",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchCuratedKnowledges"
+    )]
+    async fn fetch_curated_knowledges(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
         page: Query<Option<u64>>,
         page_size: Query<Option<u64>>,
         query_str: Query<Option<String>>,
         _token: CustomSecurityScheme,
-    ) -> GetRecordsResponse<Subgraph> {
+    ) -> GetRecordsResponse<KnowledgeCuration> {
         let pool_arc = pool.clone();
         let page = page.0;
         let page_size = page_size.0;
@@ -886,573 +1234,3950 @@ impl BiomedgpsApi {
             }
         };
 
-        match RecordResponse::<Subgraph>::get_records(
-            &pool_arc,
-            "biomedgps_subgraph",
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        match RecordResponse::<KnowledgeCuration>::get_records_with_conn(
+            &mut tx,
+            "biomedgps_knowledge_curation",
             &query,
             page,
             page_size,
-            Some("created_time DESC"),
+            Some("id ASC"),
         )
         .await
         {
             Ok(entities) => GetRecordsResponse::ok(entities),
             Err(e) => {
-                let err = format!("Failed to fetch subgraphs: {}", e);
+                let err = format!("Failed to fetch curated knowledges: {}", e);
                 warn!("{}", err);
                 return GetRecordsResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/subgraphs` with payload to create a subgraph.
+    /// Call `/api/v1/curated-knowledges/stream` with query params to download all matched curated knowledges which are visible to the user as NDJSON or CSV (`format=csv`). The curated knowledges are streamed in batches, so it works for millions of records.
+    #[oai(
+        path = "/curated-knowledges/stream",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "streamCuratedKnowledges"
+    )]
+    async fn stream_curated_knowledges(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query_str: Query<Option<String>>,
+        format: Query<Option<StreamFormat>>,
+        _token: CustomSecurityScheme,
+    ) -> GetArtifactResponse {
+        let pool_arc = pool.clone();
+        let format = format.0.unwrap_or(StreamFormat::Ndjson);
+
+        let query_str = query_str.0.unwrap_or_default();
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetArtifactResponse::bad_request(err);
+                }
+            }
+        };
+
+        let stream = RecordResponse::<KnowledgeCuration>::stream_records(
+            pool_arc,
+            _token.0.owner_scope(),
+            "biomedgps_knowledge_curation".to_string(),
+            query,
+            Some("id ASC".to_string()),
+            format,
+        );
+        GetArtifactResponse::ok(
+            Body::from_bytes_stream(stream),
+            &format!("curated-knowledges.{}", format.extension()),
+        )
+    }
+
+    /// Call `/api/v1/curated-knowledges/search` with a query in the body to fetch curated knowledges. It's the same as `/api/v1/curated-knowledges`, but the query is a typed json body instead of the `query_str` param.
+    #[oai(
+        path = "/curated-knowledges/search",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchCuratedKnowledges"
+    )]
+    async fn search_curated_knowledges(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query: Json<ComposeQuery>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<KnowledgeCuration> {
+        let query_str = match serde_json::to_string(&query.0) {
+            Ok(query_str) => query_str,
+            Err(e) => {
+                let err = format!("Failed to serialize query: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        self.fetch_curated_knowledges(pool, page, page_size, Query(Some(query_str)), _token)
+            .await
+    }
+
+    /// Call `/api/v1/key-sentences/search` with a phrase to search the curated key sentences and triples, such as `"tumor suppressor" TP53`. The matched key sentences are returned with the highlighted snippets, the curated knowledges which share the key sentences and the webpages of the source publications, ranked by the relevance and the recency. Only your own curated knowledges are searched by default, set `include_curated` to `project` to search the curated knowledges of your projects, and set `include_relations` to search the key sentences of the relations too.
+    #[oai(
+        path = "/key-sentences/search",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchKeySentences"
+    )]
+    async fn search_key_sentences(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        text: Query<String>,
+        include_curated: Query<Option<IncludeCurated>>,
+        include_relations: Query<Option<bool>>,
+        limit: Query<Option<i64>>,
+        _token: CustomSecurityScheme,
+    ) -> SearchKeySentencesResponse {
+        let pool_arc = pool.clone();
+        let text = text.0.trim().to_string();
+        let limit = limit.0.unwrap_or(20).clamp(1, 100);
+
+        if text.is_empty() {
+            let err = "The text to search is empty.".to_string();
+            warn!("{}", err);
+            return SearchKeySentencesResponse::bad_request(err);
+        }
+
+        let curated = CuratedKnowledgeFilter::new(
+            include_curated.0.unwrap_or(IncludeCurated::Own),
+            &_token.0.username,
+            &_token.0.projects,
+        );
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return SearchKeySentencesResponse::bad_request(err);
+            }
+        };
+
+        match KeySentenceMatch::search(
+            &mut tx,
+            &text,
+            &curated,
+            include_relations.0.unwrap_or(false),
+            limit,
+        )
+        .await
+        {
+            Ok(matches) => SearchKeySentencesResponse::ok(matches),
+            Err(e) => {
+                let err = format!("Failed to search the key sentences: {}", e);
+                warn!("{}", err);
+                return SearchKeySentencesResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/curated-knowledges` with payload to create a curated knowledge. Only the curators and the admins can create the curated knowledges, the viewers get 403.
+    #[oai(
+        path = "/curated-knowledges",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postCuratedKnowledge"
+    )]
+    async fn post_curated_knowledge(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<KnowledgeCuration>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<KnowledgeCuration> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        if !_token.0.has_role(Role::Curator) {
+            let err = format!(
+                "User {} is a {}, only the curators and the admins can create the curated knowledges.",
+                _token.0.username,
+                _token.0.role().as_str()
+            );
+            warn!("{}", err);
+            return PostResponse::forbidden(err);
+        }
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        let violations = payload.check_rules();
+        if violations.len() > 0 {
+            let err = format!("Failed to validate payload: {}", violations.join(" "));
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match payload.insert(&pool_arc).await {
+            Ok(kc) => PostResponse::created(kc),
+            Err(e) => {
+                let err = format!("Failed to insert curated knowledge: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/curated-knowledges/:id` with payload to update a curated knowledge. Only the curators and the admins can update the curated knowledges, the viewers get 403.
+    #[oai(
+        path = "/curated-knowledges/:id",
+        method = "put",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "putCuratedKnowledge"
+    )]
+    async fn put_curated_knowledge(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<KnowledgeCuration>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<KnowledgeCuration> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+        let id = id.0;
+
+        if !_token.0.has_role(Role::Curator) {
+            let err = format!(
+                "User {} is a {}, only the curators and the admins can update the curated knowledges.",
+                _token.0.username,
+                _token.0.role().as_str()
+            );
+            warn!("{}", err);
+            return PostResponse::forbidden(err);
+        }
+
+        if id < 0 {
+            let err = format!("Invalid id: {}", id);
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        let violations = payload.check_rules();
+        if violations.len() > 0 {
+            let err = format!("Failed to validate payload: {}", violations.join(" "));
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        match payload.update(&mut tx, id).await {
+            Ok(kc) => match tx.commit().await {
+                Ok(_) => PostResponse::created(kc),
+                Err(e) => {
+                    let err = format!("Failed to commit the transaction: {}", e);
+                    warn!("{}", err);
+                    PostResponse::bad_request(err)
+                }
+            },
+            Err(e) => {
+                let err = format!("Failed to insert curated knowledge: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/curated-knowledges/:id` with payload to delete a curated knowledge. Only the curators and the admins can delete the curated knowledges, the viewers get 403.
+    #[oai(
+        path = "/curated-knowledges/:id",
+        method = "delete",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "deleteCuratedKnowledge"
+    )]
+    async fn delete_curated_knowledge(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> DeleteResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        if !_token.0.has_role(Role::Curator) {
+            let err = format!(
+                "User {} is a {}, only the curators and the admins can delete the curated knowledges.",
+                _token.0.username,
+                _token.0.role().as_str()
+            );
+            warn!("{}", err);
+            return DeleteResponse::forbidden(err);
+        }
+
+        if id < 0 {
+            let err = format!("Invalid id: {}", id);
+            warn!("{}", err);
+            return DeleteResponse::bad_request(err);
+        }
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return DeleteResponse::bad_request(err);
+            }
+        };
+
+        match KnowledgeCuration::delete(&mut tx, id).await {
+            Ok(_) => match tx.commit().await {
+                Ok(_) => DeleteResponse::no_content(),
+                Err(e) => {
+                    let err = format!("Failed to commit the transaction: {}", e);
+                    warn!("{}", err);
+                    DeleteResponse::bad_request(err)
+                }
+            },
+            Err(e) => {
+                let err = format!("Failed to delete curated knowledge: {}", e);
+                warn!("{}", err);
+                DeleteResponse::not_found(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/models` to fetch all models with their metadata and the status of their score tables, such as the model name, model type, datasets and dimension. Their model names are the valid `model_name` values of the prediction endpoints, and the score table must exist before the relations can be ranked by a model.
+    #[oai(
+        path = "/models",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchModels"
+    )]
+    async fn fetch_models(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<KgeModelStatus> {
+        let pool_arc = pool.clone();
+
+        match KgeModelStatus::list(&pool_arc).await {
+            Ok(models) => GetWholeTableResponse::ok(models),
+            Err(e) => {
+                let err = format!("Failed to fetch models: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/kge-models` to fetch the registered KGE models, such as the model name, model type, datasets and dimension. It's the same as `/api/v1/models`, their model names are the valid `model_name` values of the prediction endpoints.
+    #[oai(
+        path = "/kge-models",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchKgeModels"
+    )]
+    async fn fetch_kge_models(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<KgeModelStatus> {
+        self.fetch_models(pool, _token).await
+    }
+
+    /// Call `/api/v1/benchmark-results` with query params to fetch the benchmark results of the models, the newest first. They are generated by the `benchmark` command of the cli.
+    #[oai(
+        path = "/benchmark-results",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchBenchmarkResults"
+    )]
+    async fn fetch_benchmark_results(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        model_name: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<BenchmarkResult> {
+        let pool_arc = pool.clone();
+        let model_name = model_name.0;
+
+        match BenchmarkResult::list(&pool_arc, model_name.as_deref()).await {
+            Ok(results) => GetWholeTableResponse::ok(results),
+            Err(e) => {
+                let err = format!("Failed to fetch benchmark results: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/similar-entities` with query params to fetch the most similar entities of an entity by the embeddings of a model, such as a node2vec model. The distance is computed by the declared metric of the model, so the smaller the closer.
+    #[oai(
+        path = "/similar-entities",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchSimilarEntities"
+    )]
+    async fn fetch_similar_entities(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        entity_id: Query<String>,
+        entity_type: Query<String>,
+        topk: Query<Option<u64>>,
+        model_name: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<SimilarEntity> {
+        let pool_arc = pool.clone();
+        let topk = topk.0.unwrap_or(10);
+        if topk == 0 || topk > 500 {
+            let err = "The topk should be between 1 and 500.".to_string();
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let metadata = match get_model_table_prefix(model_name.0.as_deref())
+            .map(|table_name| get_embedding_metadata(&table_name))
+        {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => {
+                let err = "The default model is not registered in the embedding metadata table."
+                    .to_string();
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+            Err(e) => {
+                let err = format!("Failed to fetch similar entities: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        };
+
+        match EntityEmbedding::fetch_similar_entities(
+            &pool_arc,
+            &metadata,
+            &entity_id.0,
+            &entity_type.0,
+            topk,
+        )
+        .await
+        {
+            Ok(entities) => GetWholeTableResponse::ok(entities),
+            Err(e) => {
+                let err = format!("Failed to fetch similar entities: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/relations` with query params to fetch relations. Set `dedupe=true` to collapse the identical relations from multiple datasets into one row, their datasets and resources are listed in the `datasets` and `resources` fields. Set `qualifiers` to filter the relations by the qualifier values, such as `dose>=10;tissue=liver`. Set `view_id` to only fetch the relations of the datasets in a graph view. Set `context` to only fetch the relations in a biological context, such as `tissue:liver` or `cell_type:CL:0000182`, the contexts are separated by semicolons. Set `model_name` to choose the KGE model which computes the scores, such as a TransE or RotatE model registered in the embedding metadata table. Set `order_by` to sort the relations by the evidence counts, one of `n_pmids`, `n_datasets` and `n_curations` (the most first), or by `score` (the default).
+    #[oai(
+        path = "/relations",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchRelations"
+    )]
+    async fn fetch_relations(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        dedupe: Query<Option<bool>>,
+        qualifiers: Query<Option<String>>,
+        view_id: Query<Option<i64>>,
+        context: Query<Option<String>>,
+        model_name: Query<Option<String>>,
+        order_by: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Relation> {
+        let pool_arc = pool.clone();
+        let page = page.0;
+        let page_size = page_size.0;
+
+        let order_by_clause = match order_by.0 {
+            Some(order_by) => match Relation::gen_order_clause(&order_by) {
+                Ok(order_by_clause) => order_by_clause,
+                Err(e) => {
+                    let err = format!("Failed to fetch relations: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            },
+            None => "score ASC".to_string(),
+        };
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            }
+        };
+
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
+                        warn!("{}", err);
+                        return GetRecordsResponse::bad_request(err);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // Only the relations of the datasets in the view are matched.
+        let query = match &view {
+            Some(view) => view.apply_to_relation_query(&query),
+            None => query,
+        };
+
+        // Only the relations in the biological contexts are matched, such as tissue:liver.
+        let query = match context.0 {
+            Some(context) => match RelationContext::parse(&context) {
+                Ok(contexts) => RelationContext::apply_to_relation_query(&contexts, &query),
+                Err(e) => {
+                    let err = format!("Failed to parse context: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            },
+            None => query,
+        };
+
+        let table_name = match check_kg_score_table(&pool_arc, &model_table_prefix).await {
+            Ok(table_name) => table_name,
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::not_found(err);
+            }
+        };
+
+        // Only the relations with the matched qualifiers are kept, it must be done before the deduplication.
+        let (table_name, binds) = match qualifiers.0 {
+            Some(qualifiers) => match QualifierFilter::parse(&qualifiers) {
+                Ok(filters) if !filters.is_empty() => {
+                    QualifierFilter::gen_filtered_table_expr(&table_name, &filters)
+                }
+                Ok(_) => (table_name, vec![]),
+                Err(e) => {
+                    let err = format!("Failed to parse qualifiers: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            },
+            None => (table_name, vec![]),
+        };
+
+        // The identical relations from multiple datasets are collapsed into one row with the provenance lists.
+        let (table_name, query) = if dedupe.0.unwrap_or(false) {
+            (Relation::gen_dedupe_table_expr(&table_name, &query), None)
+        } else {
+            (table_name, query)
+        };
+
+        // The verdicts are attached after the deduplication, the identical relations share the same verdict.
+        let table_name = Relation::gen_verified_table_expr(&table_name);
+
+        match RecordResponse::<Relation>::get_records_with_binds(
+            &pool_arc,
+            table_name.as_str(),
+            &binds,
+            &query,
+            page,
+            page_size,
+            Some(order_by_clause.as_str()),
+        )
+        .await
+        {
+            Ok(entities) => GetRecordsResponse::ok(entities),
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/relations/stream` with query params to download all matched relations as NDJSON or CSV (`format=csv`). The relations are streamed in batches, so it works for millions of relations. Set `view_id` to only download the relations of the datasets in a graph view, `context` to only download the relations in a biological context such as `tissue:liver`, and `model_name` to choose the KGE model which computes the scores.
+    #[oai(
+        path = "/relations/stream",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "streamRelations"
+    )]
+    async fn stream_relations(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query_str: Query<Option<String>>,
+        format: Query<Option<StreamFormat>>,
+        view_id: Query<Option<i64>>,
+        context: Query<Option<String>>,
+        model_name: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetArtifactResponse {
+        let pool_arc = pool.clone();
+        let format = format.0.unwrap_or(StreamFormat::Ndjson);
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetArtifactResponse::bad_request(err);
+            }
+        };
+
+        let query_str = query_str.0.unwrap_or_default();
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetArtifactResponse::bad_request(err);
+                }
+            }
+        };
+
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
+                        warn!("{}", err);
+                        return GetArtifactResponse::bad_request(err);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // Only the relations of the datasets in the view are matched.
+        let query = match &view {
+            Some(view) => view.apply_to_relation_query(&query),
+            None => query,
+        };
+
+        // Only the relations in the biological contexts are matched, such as tissue:liver.
+        let query = match context.0 {
+            Some(context) => match RelationContext::parse(&context) {
+                Ok(contexts) => RelationContext::apply_to_relation_query(&contexts, &query),
+                Err(e) => {
+                    let err = format!("Failed to parse context: {}", e);
+                    warn!("{}", err);
+                    return GetArtifactResponse::bad_request(err);
+                }
+            },
+            None => query,
+        };
+
+        let table_name = match check_kg_score_table(&pool_arc, &model_table_prefix).await {
+            Ok(table_name) => table_name,
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetArtifactResponse::not_found(err);
+            }
+        };
+
+        let stream = RecordResponse::<Relation>::stream_records(
+            pool_arc,
+            _token.0.owner_scope(),
+            table_name,
+            query,
+            Some("id ASC".to_string()),
+            format,
+        );
+        GetArtifactResponse::ok(
+            Body::from_bytes_stream(stream),
+            &format!("relations.{}", format.extension()),
+        )
+    }
+
+    /// Call `/api/v1/relations/search` with a query in the body to fetch relations. It's the same as `/api/v1/relations`, but the query is a typed json body instead of the `query_str` param.
+    #[oai(
+        path = "/relations/search",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchRelations"
+    )]
+    async fn search_relations(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query: Json<ComposeQuery>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        dedupe: Query<Option<bool>>,
+        qualifiers: Query<Option<String>>,
+        view_id: Query<Option<i64>>,
+        model_name: Query<Option<String>>,
+        order_by: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Relation> {
+        let query_str = match serde_json::to_string(&query.0) {
+            Ok(query_str) => query_str,
+            Err(e) => {
+                let err = format!("Failed to serialize query: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        self.fetch_relations(
+            pool,
+            page,
+            page_size,
+            Query(Some(query_str)),
+            dedupe,
+            qualifiers,
+            view_id,
+            model_name,
+            order_by,
+            _token,
+        )
+        .await
+    }
+
+    /// Call `/api/v1/relations/:id/verification` to verify a relation against the abstracts of the linked publications by the LLM.
+    #[oai(
+        path = "/relations/:id/verification",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "verifyRelation"
+    )]
+    async fn verify_relation(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<i64>,
+        prompt_template_id: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<RelationVerification> {
+        let pool_arc = pool.clone();
+        let id = id.0;
+        let prompt_template_id = prompt_template_id.0;
+
+        if id < 0 {
+            let err = format!("Invalid id: {}", id);
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        let openai_api_key = match std::env::var("OPENAI_API_KEY") {
+            Ok(openai_api_key) => openai_api_key,
+            Err(e) => {
+                let err = format!("Failed to get OPENAI_API_KEY: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        let chatbot = ChatBot::new("GPT4", &openai_api_key)
+            .with_usage_context(&_token.0.username, "verifyRelation");
+        match RelationVerification::verify(&pool_arc, &chatbot, id, prompt_template_id.as_deref())
+            .await
+        {
+            Ok(verification) => PostResponse::created(verification),
+            Err(e) => {
+                let err = format!("Failed to verify the relation: {}", e);
+                warn!("{}", err);
+                if e.downcast_ref::<LlmBudgetExceeded>().is_some() {
+                    return PostResponse::too_many_requests(err);
+                }
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/relation-verifications` with query params to fetch the verdicts of the relations.
+    #[oai(
+        path = "/relation-verifications",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchRelationVerifications"
+    )]
+    async fn fetch_relation_verifications(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<RelationVerification> {
+        let pool_arc = pool.clone();
+        let page = page.0;
+        let page_size = page_size.0;
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            }
+        };
+
+        match RecordResponse::<RelationVerification>::get_records(
+            &pool_arc,
+            "biomedgps_relation_verification",
+            &query,
+            page,
+            page_size,
+            Some("created_at DESC"),
+        )
+        .await
+        {
+            Ok(records) => GetRecordsResponse::ok(records),
+            Err(e) => {
+                let err = format!("Failed to fetch relation verifications: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/relation-counts` with query params to fetch relation counts.
+    #[oai(
+        path = "/relation-counts",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchRelationCounts"
+    )]
+    async fn fetch_relation_counts(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query_str: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRelationCountResponse {
+        let pool_arc = pool.clone();
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRelationCountResponse::bad_request(err);
+                }
+            }
+        };
+
+        match RelationCount::get_records(&pool_arc, &query).await {
+            Ok(entities) => GetRelationCountResponse::ok(entities),
+            Err(e) => {
+                let err = format!("Failed to fetch relations: {}", e);
+                warn!("{}", err);
+                return GetRelationCountResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/entity2d` with query params to fetch entity2d.
+    #[oai(
+        path = "/entity2d",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchEntity2d"
+    )]
+    async fn fetch_entity2d(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Entity2D> {
+        let pool_arc = pool.clone();
+        let page = page.0;
+        let page_size = page_size.0;
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            }
+        };
+
+        // TODO: Could we compute the 2d embedding on the fly or by the biomedgps-cli tool?
+        match RecordResponse::<Entity2D>::get_records(
+            &pool_arc,
+            "biomedgps_entity2d",
+            &query,
+            page,
+            page_size,
+            Some("embedding_id ASC"),
+        )
+        .await
+        {
+            Ok(entities) => GetRecordsResponse::ok(entities),
+            Err(e) => {
+                let err = format!("Failed to fetch entity2d: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/entity2d/search` with a query in the body to fetch entity2d. It's the same as `/api/v1/entity2d`, but the query is a typed json body instead of the `query_str` param.
+    #[oai(
+        path = "/entity2d/search",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchEntity2d"
+    )]
+    async fn search_entity2d(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query: Json<ComposeQuery>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Entity2D> {
+        let query_str = match serde_json::to_string(&query.0) {
+            Ok(query_str) => query_str,
+            Err(e) => {
+                let err = format!("Failed to serialize query: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        self.fetch_entity2d(pool, page, page_size, Query(Some(query_str)), _token)
+            .await
+    }
+
+    /// Call `/api/v1/subgraphs` with query params to fetch subgraphs.
+    #[oai(
+        path = "/subgraphs",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchSubgraphs"
+    )]
+    async fn fetch_subgraphs(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Subgraph> {
+        let pool_arc = pool.clone();
+        let page = page.0;
+        let page_size = page_size.0;
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            }
+        };
+
+        // The anonymous users can only see the subgraphs which are shared with the public persona, the row-level security is optional.
+        let query = if _token.0.username == PUBLIC_USERNAME {
+            Subgraph::apply_owner_to_query(PUBLIC_USERNAME, &query)
+        } else {
+            query
+        };
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        match RecordResponse::<Subgraph>::get_records_with_conn(
+            &mut tx,
+            "biomedgps_subgraph",
+            &query,
+            page,
+            page_size,
+            Some("created_time DESC"),
+        )
+        .await
+        {
+            Ok(entities) => GetRecordsResponse::ok(entities),
+            Err(e) => {
+                let err = format!("Failed to fetch subgraphs: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/search` with a query in the body to fetch subgraphs. It's the same as `/api/v1/subgraphs`, but the query is a typed json body instead of the `query_str` param.
+    #[oai(
+        path = "/subgraphs/search",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "searchSubgraphs"
+    )]
+    async fn search_subgraphs(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        query: Json<ComposeQuery>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetRecordsResponse<Subgraph> {
+        let query_str = match serde_json::to_string(&query.0) {
+            Ok(query_str) => query_str,
+            Err(e) => {
+                let err = format!("Failed to serialize query: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        };
+
+        self.fetch_subgraphs(pool, page, page_size, Query(Some(query_str)), _token)
+            .await
+    }
+
+    /// Call `/api/v1/subgraphs` with payload to create a subgraph.
+    #[oai(
+        path = "/subgraphs",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postSubgraph"
+    )]
+    async fn post_subgraph(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<Subgraph>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<Subgraph> {
+        let pool_arc = pool.clone();
+        let mut payload = payload.0;
+        let username = _token.0.username.clone();
+
+        // When we enabled auth mode, we need to use the username from an access_token instead.
+        if username != USERNAME_PLACEHOLDER.to_string() {
+            payload.update_owner(username);
+        }
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate subgraph: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        match payload.insert(&pool_arc).await {
+            Ok(kc) => PostResponse::created(kc),
+            Err(e) => {
+                let err = format!("Failed to insert curated knowledge: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/:id` with payload to update a subgraph.
+    #[oai(
+        path = "/subgraphs/:id",
+        method = "put",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "putSubgraph"
+    )]
+    async fn put_subgraph(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<String>,
+        payload: Json<Subgraph>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<Subgraph> {
+        let pool_arc = pool.clone();
+        let id = id.0;
+        let mut payload = payload.0;
+        let username = _token.0.username.clone();
+
+        // When we enabled auth mode, we need to use the username from an access_token instead.
+        if username != "user-placeholder".to_string() {
+            payload.update_owner(username);
+        }
+
+        match SubgraphIdQuery::new(&id) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse subgraph id: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate subgraph: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        match payload.update(&mut tx, &id).await {
+            Ok(kc) => match tx.commit().await {
+                Ok(_) => PostResponse::created(kc),
+                Err(e) => {
+                    let err = format!("Failed to commit the transaction: {}", e);
+                    warn!("{}", err);
+                    PostResponse::bad_request(err)
+                }
+            },
+            Err(e) => {
+                let err = format!("Failed to update subgraph: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/:id` with payload to create subgraph.
+    #[oai(
+        path = "/subgraphs/:id",
+        method = "delete",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "deleteSubgraph"
+    )]
+    async fn delete_subgraph(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<String>,
+        _token: CustomSecurityScheme,
+    ) -> DeleteResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match SubgraphIdQuery::new(&id) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate subgraph id: {}", e);
+                warn!("{}", err);
+                return DeleteResponse::bad_request(err);
+            }
+        }
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return DeleteResponse::bad_request(err);
+            }
+        };
+
+        match Subgraph::delete(&mut tx, &id).await {
+            Ok(_) => match tx.commit().await {
+                Ok(_) => DeleteResponse::NoContent,
+                Err(e) => {
+                    let err = format!("Failed to commit the transaction: {}", e);
+                    warn!("{}", err);
+                    DeleteResponse::bad_request(err)
+                }
+            },
+            Err(e) => {
+                let err = format!("Failed to delete a subgraph: {}", e);
+                warn!("{}", err);
+                DeleteResponse::not_found(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/:id/replay` to re-execute the recipe of a subgraph against the current knowledge graph.
+    #[oai(
+        path = "/subgraphs/:id/replay",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "replaySubgraph"
+    )]
+    async fn replay_subgraph(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<String>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match SubgraphIdQuery::new(&id) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse subgraph id: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let subgraph = match Subgraph::fetch_by_id(&mut tx, &id).await {
+            Ok(subgraph) => subgraph,
+            Err(e) => {
+                let err = format!("Failed to fetch subgraph: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::not_found(err);
+            }
+        };
+
+        // Only the owner and the admins can replay a subgraph, the recipe might contain the private queries of the owner.
+        if !_token.0.is_admin() && subgraph.owner != _token.0.username {
+            let err = format!("The subgraph {} is not found.", id);
+            warn!(
+                "User {} tried to replay the subgraph {} of {}.",
+                _token.0.username, id, subgraph.owner
+            );
+            return GetGraphResponse::not_found(err);
+        }
+
+        let recipe = match subgraph.recipe {
+            Some(recipe) => match ExpansionRecipe::from_json(&recipe) {
+                Ok(recipe) => recipe,
+                Err(e) => {
+                    let err = format!("Failed to parse the recipe of the subgraph: {}", e);
+                    warn!("{}", err);
+                    return GetGraphResponse::bad_request(err);
+                }
+            },
+            None => {
+                let err = format!("The subgraph {} doesn't have a recipe.", id);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        match recipe.replay(&pool_arc).await {
+            Ok(mut graph) => {
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                GetGraphResponse::ok(graph)
+            }
+            Err(e) => {
+                let err = format!("Failed to replay the subgraph: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/:id/extension` with query params to predict the nodes which extend a subgraph. The candidates must be predicted from at least `min_anchors` nodes of the subgraph, they are ranked by the number of their anchors and the mean score. Set `degree_penalty` (such as 0.1) to penalize the hub nodes, the raw scores are kept in the `raw_score` field of the edges.
+    #[oai(
+        path = "/subgraphs/:id/extension",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchSubgraphExtension"
+    )]
+    async fn fetch_subgraph_extension(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<String>,
+        relation_type: Query<String>,
+        min_anchors: Query<Option<usize>>,
+        topk_per_anchor: Query<Option<u64>>,
+        topk: Query<Option<u64>>,
+        model_name: Query<Option<String>>,
+        degree_penalty: Query<Option<f64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetSubgraphExtensionResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match SubgraphIdQuery::new(&id) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse subgraph id: {}", e);
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::bad_request(err);
+            }
+        };
+
+        let min_anchors = min_anchors.0.unwrap_or(DEFAULT_MIN_ANCHORS);
+        if min_anchors < 1 {
+            let err = "The min_anchors must be greater than 0.".to_string();
+            warn!("{}", err);
+            return GetSubgraphExtensionResponse::bad_request(err);
+        }
+
+        let topk_per_anchor = topk_per_anchor.0.unwrap_or(50);
+        let topk = topk.0.unwrap_or(10);
+        if topk_per_anchor > 500 || topk > 500 {
+            let err = "The topk and topk_per_anchor must be less than or equal to 500.".to_string();
+            warn!("{}", err);
+            return GetSubgraphExtensionResponse::bad_request(err);
+        }
+
+        if let Some(alpha) = degree_penalty.0 {
+            if !(0.0..=MAX_DEGREE_PENALTY).contains(&alpha) {
+                let err = format!(
+                    "The degree_penalty must be between 0 and {}.",
+                    MAX_DEGREE_PENALTY
+                );
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::bad_request(err);
+            }
+        }
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::bad_request(err);
+            }
+        };
+
+        let subgraph = match Subgraph::fetch_by_id(&mut tx, &id).await {
+            Ok(subgraph) => subgraph,
+            Err(e) => {
+                let err = format!("Failed to fetch subgraph: {}", e);
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::not_found(err);
+            }
+        };
+
+        let node_ids = match subgraph.get_node_ids() {
+            Ok(node_ids) => node_ids,
+            Err(e) => {
+                let err = format!("Failed to parse the payload of the subgraph: {}", e);
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::bad_request(err);
+            }
+        };
+
+        let mut graph = Graph::new();
+        match graph
+            .fetch_subgraph_extension(
+                &pool_arc,
+                &node_ids,
+                &relation_type.0,
+                min_anchors,
+                topk_per_anchor,
+                topk as usize,
+                model_name.0,
+                degree_penalty.0,
+            )
+            .await
+        {
+            Ok(candidates) => {
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                GetSubgraphExtensionResponse::ok(SubgraphExtension { candidates, graph })
+            }
+            Err(e) => {
+                let err = format!("Failed to predict the extension of the subgraph: {}", e);
+                warn!("{}", err);
+                return GetSubgraphExtensionResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/subgraphs/:id/stream` to export a subgraph as a NDJSON stream.
+    #[oai(
+        path = "/subgraphs/:id/stream",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "streamSubgraph"
+    )]
+    async fn stream_subgraph(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<String>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphStreamResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match SubgraphIdQuery::new(&id) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse subgraph id: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::bad_request(err);
+            }
+        };
+
+        let mut tx = match _token.0.owner_scope().begin(&pool_arc).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                let err = format!("Failed to start a transaction: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::bad_request(err);
+            }
+        };
+
+        let subgraph = match Subgraph::fetch_by_id(&mut tx, &id).await {
+            Ok(subgraph) => subgraph,
+            Err(e) => {
+                let err = format!("Failed to fetch subgraph: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::not_found(err);
+            }
+        };
+
+        // The subgraph is shared to the members of the organizations and projects by the row-level security, but only the owner and the admins can export it.
+        if !_token.0.is_admin() && subgraph.owner != _token.0.username {
+            let err = format!("The subgraph {} is not found.", id);
+            warn!(
+                "User {} tried to export the subgraph {} of {}.",
+                _token.0.username, id, subgraph.owner
+            );
+            return GetGraphStreamResponse::not_found(err);
+        }
+
+        match subgraph.to_ndjson_lines() {
+            Ok(lines) => {
+                let stream = futures::stream::iter(
+                    lines.map(|line| Ok::<_, std::io::Error>(bytes::Bytes::from(line))),
+                );
+                GetGraphStreamResponse::ok(Body::from_bytes_stream(stream))
+            }
+            Err(e) => {
+                let err = format!("Failed to parse the payload of the subgraph: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/nodes` with query params to fetch nodes. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field.
+    #[oai(
+        path = "/nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchNodes"
+    )]
+    async fn fetch_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        node_ids: Query<String>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Postgres);
+        let node_ids = node_ids.0;
+
+        match NodeIdsQuery::new(&node_ids) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate node ids: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let mut graph = Graph::new();
+
+        if node_ids == "" {
+            return GetGraphResponse::ok(graph);
+        }
+
+        let node_ids: Vec<&str> = node_ids.split(",").collect();
+        EntityActivity::record(&pool_arc, &_token.0.username, "fetchNodes", &node_ids).await;
+
+        let query_started_at = Instant::now();
+        match graph.fetch_nodes_by_ids(&pool_arc, &node_ids).await {
+            Ok(graph) => {
+                stats.record_query(query_started_at, graph.count_items());
+                let mut graph = graph
+                    .to_owned()
+                    .get_graph(None, aggregate_edges.0, dedupe.0)
+                    .unwrap();
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                stats.attach(&mut graph);
+                GetGraphResponse::ok(graph)
+            }
+            Err(e) => {
+                let err = format!("Failed to fetch nodes: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/auto-connect-nodes` with query params to fetch edges which connect the input nodes. Set `model_name` to choose the KGE model which computes the scores of the edges. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field.
+    #[oai(
+        path = "/auto-connect-nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchEdgesAutoConnectNodes"
+    )]
+    async fn fetch_edges_auto_connect_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        node_ids: Query<String>,
+        include_curated: Query<Option<IncludeCurated>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        model_name: Query<Option<String>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Postgres);
+        let node_ids = node_ids.0;
+        let curated = CuratedKnowledgeFilter::new(
+            include_curated.0.unwrap_or_default(),
+            &_token.0.username,
+            &_token.0.projects,
+        );
+
+        match NodeIdsQuery::new(&node_ids) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate node ids: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let mut graph = Graph::new();
+
+        if node_ids == "" {
+            return GetGraphResponse::ok(graph);
+        }
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch edges: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let node_ids: Vec<&str> = node_ids.split(",").collect();
+        let query_started_at = Instant::now();
+        match graph
+            .auto_connect_nodes(
+                &pool_arc,
+                &node_ids,
+                Some(&model_table_prefix),
+                Some(&curated),
+            )
+            .await
+        {
+            Ok(graph) => {
+                stats.record_query(query_started_at, graph.count_items());
+                let mut graph = graph
+                    .to_owned()
+                    .get_graph(None, aggregate_edges.0, dedupe.0)
+                    .unwrap();
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                stats.attach(&mut graph);
+                GetGraphResponse::ok(graph)
+            }
+            Err(e) => {
+                let err = format!("Failed to fetch nodes: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/one-step-linked-nodes` with query params to fetch linked nodes with one step. Set `view_id` to only follow the relations of the datasets in a graph view. Set `context` to only follow the relations in a biological context, such as `tissue:liver`. Set `model_name` to choose the KGE model which computes the scores of the relations. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field.
+    #[oai(
+        path = "/one-step-linked-nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchOneStepLinkedNodes"
+    )]
+    async fn fetch_one_step_linked_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        include_curated: Query<Option<IncludeCurated>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        view_id: Query<Option<i64>>,
+        context: Query<Option<String>>,
+        model_name: Query<Option<String>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Postgres);
+        let page = page.0;
+        let page_size = page_size.0;
+        let curated = CuratedKnowledgeFilter::new(
+            include_curated.0.unwrap_or_default(),
+            &_token.0.username,
+            &_token.0.projects,
+        );
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch linked nodes: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetGraphResponse::bad_request(err);
+                }
+            }
+        };
+
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
+                        warn!("{}", err);
+                        return GetGraphResponse::bad_request(err);
+                    }
+                }
+            }
+            None => None,
+        };
+        let query = match &view {
+            Some(view) => view.apply_to_relation_query(&query),
+            None => query,
+        };
+
+        // Only the relations in the biological contexts are matched, such as tissue:liver.
+        let query = match context.0 {
+            Some(context) => match RelationContext::parse(&context) {
+                Ok(contexts) => RelationContext::apply_to_relation_query(&contexts, &query),
+                Err(e) => {
+                    let err = format!("Failed to parse context: {}", e);
+                    warn!("{}", err);
+                    return GetGraphResponse::bad_request(err);
+                }
+            },
+            None => query,
+        };
+
+        let mut graph = Graph::new();
+        // score DESC is the order_by clause for making the engine generate results with scores which computed by the model.
+        let query_started_at = Instant::now();
+        match graph
+            .fetch_linked_nodes(
+                &pool_arc,
+                &query,
+                page,
+                page_size,
+                Some("score DESC"),
+                Some(&model_table_prefix),
+                Some(&curated),
+            )
+            .await
+        {
+            Ok(graph) => {
+                stats.record_query(query_started_at, graph.count_items());
+                let mut graph = graph
+                    .to_owned()
+                    .get_graph(None, aggregate_edges.0, dedupe.0)
+                    .unwrap();
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                stats.attach(&mut graph);
+                GetGraphResponse::ok(graph)
+            }
+            Err(e) => {
+                let err = format!("Failed to fetch linked nodes: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/one-step-linked-nodes/stream` with query params to fetch linked nodes with one step as a NDJSON stream. It is useful for the dense neighborhoods, the relations are fetched page by page in the order of their scores and each page is emitted as soon as it is ready. The `page_size` is at most 5000 and the `max_pages` is at most 200 (the default). Set `view_id` to only follow the relations of the datasets in a graph view. Set `context` to only follow the relations in a biological context, such as `tissue:liver`. Set `model_name` to choose the KGE model which computes the scores of the relations.
+    #[oai(
+        path = "/one-step-linked-nodes/stream",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "streamOneStepLinkedNodes"
+    )]
+    async fn stream_one_step_linked_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page_size: Query<Option<u64>>,
+        max_pages: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
+        view_id: Query<Option<i64>>,
+        context: Query<Option<String>>,
+        model_name: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphStreamResponse {
+        let pool_arc = pool.clone();
+        let page_size = page_size.0.unwrap_or(1000);
+        let max_pages = max_pages.0.unwrap_or(MAX_STREAM_PAGES);
+        if page_size == 0 || page_size > MAX_STREAM_PAGE_SIZE {
+            let err = format!(
+                "The page_size should be between 1 and {}, but got {}.",
+                MAX_STREAM_PAGE_SIZE, page_size
+            );
+            warn!("{}", err);
+            return GetGraphStreamResponse::bad_request(err);
+        }
+
+        if max_pages == 0 || max_pages > MAX_STREAM_PAGES {
+            let err = format!(
+                "The max_pages should be between 1 and {}, but got {}.",
+                MAX_STREAM_PAGES, max_pages
+            );
+            warn!("{}", err);
+            return GetGraphStreamResponse::bad_request(err);
+        }
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch linked nodes: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::bad_request(err);
+            }
+        };
+
+        match PaginationQuery::new(Some(1), Some(page_size), query_str.0.clone()) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetGraphStreamResponse::bad_request(err);
+            }
+        };
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetGraphStreamResponse::bad_request(err);
+                }
+            }
+        };
+
+        let view = match view_id.0 {
+            Some(id) => {
+                match GraphView::get_visible(&pool_arc, id, &_token.0.username, &_token.0.projects)
+                    .await
+                {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        let err = format!("Failed to fetch the graph view {}: {}", id, e);
+                        warn!("{}", err);
+                        return GetGraphStreamResponse::bad_request(err);
+                    }
+                }
+            }
+            None => None,
+        };
+        let query = match &view {
+            Some(view) => view.apply_to_relation_query(&query),
+            None => query,
+        };
+
+        // Only the relations in the biological contexts are matched, such as tissue:liver.
+        let query = match context.0 {
+            Some(context) => match RelationContext::parse(&context) {
+                Ok(contexts) => RelationContext::apply_to_relation_query(&contexts, &query),
+                Err(e) => {
+                    let err = format!("Failed to parse context: {}", e);
+                    warn!("{}", err);
+                    return GetGraphStreamResponse::bad_request(err);
+                }
+            },
+            None => query,
+        };
+
+        // The relations are ordered by the scores which are computed by the model.
+        let stream = stream_linked_nodes(
+            pool_arc,
+            query,
+            page_size,
+            max_pages,
+            model_table_prefix,
+            _token.0.username.clone(),
+            _token.0.projects.clone(),
+        );
+
+        GetGraphStreamResponse::ok(Body::from_bytes_stream(stream))
+    }
+
+    /// Call `/api/v1/predicted-nodes` with query params to fetch predicted nodes. Set `degree_penalty` (such as 0.1) to penalize the hub nodes which are favored by the raw scores, the nodes are reranked by the penalized scores and the raw scores are kept in the `raw_score` field of the edges. Set `direction` to `tail` to predict the tails of (node, r, ?) or `head` to predict the heads of (?, r, node), it's inferred from the node type if it's not set. Set `rerank_context` (such as a question or a phenotype) to retrieve the top 200 candidates and rerank them by the similarity between their descriptions and the context, it improves the precision for the ambiguous entity types. The predicted compounds are flagged by their known adverse events and contraindications in the knowledge graph, see the `warnings` field of the nodes, the supporting edges are included in the warnings. The `percentile` field of the edges is the percentile (0-100) of the model score among the sampled scores of the model, it's comparable across the models, so the UI can use a fixed cutoff. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field. It requires the `predict:invoke` scope.
+    #[oai(
+        path = "/predicted-nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchPredictedNodes"
+    )]
+    async fn fetch_predicted_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        node_id: Query<String>,
+        relation_type: Query<String>,
+        query_str: Query<Option<String>>,
+        topk: Query<Option<u64>>,
+        model_name: Query<Option<String>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        degree_penalty: Query<Option<f64>>,
+        direction: Query<Option<PredictionDirection>>,
+        rerank_context: Query<Option<String>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Postgres);
+
+        match PredictedNodeQuery::new(
+            &node_id.0,
+            &relation_type.0,
+            &query_str.0,
+            topk.0,
+            degree_penalty.0,
+        ) {
+            Ok(query) => query,
+            Err(e) => {
+                let err = format!("Failed to parse query string: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let query_str = match query_str.0 {
+            Some(query_str) => query_str,
+            None => {
+                warn!("Query string is empty.");
+                "".to_string()
+            }
+        };
+
+        let topk = topk.0;
+
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetGraphResponse::bad_request(err);
+                }
+            }
+        };
+
+        EntityActivity::record(
+            &pool_arc,
+            &_token.0.username,
+            "fetchPredictedNodes",
+            &vec![node_id.0.as_str()],
+        )
+        .await;
+
+        let mut graph = Graph::new();
+        let query_started_at = Instant::now();
+        match graph
+            .fetch_predicted_nodes(
+                &pool_arc,
+                &node_id,
+                &relation_type,
+                &query,
+                topk,
+                model_name.0,
+                degree_penalty.0,
+                direction.0,
+                rerank_context.0.as_deref(),
+            )
+            .await
+        {
+            Ok(graph) => {
+                stats.record_query(query_started_at, graph.count_items());
+                let mut graph = graph
+                    .to_owned()
+                    .get_graph(None, aggregate_edges.0, dedupe.0)
+                    .unwrap();
+                graph
+                    .attach_node_tags(&pool_arc, &_token.0.username, &_token.0.projects)
+                    .await;
+                graph.attach_thumbnails(&pool_arc).await;
+                graph.attach_edge_qualifiers(&pool_arc).await;
+                graph
+                    .attach_safety_warnings(&pool_arc, &node_id.0.split(",").collect::<Vec<&str>>())
+                    .await;
+                stats.attach(&mut graph);
+                GetGraphResponse::ok(graph)
+            }
+            Err(e) => {
+                let err = format!("{}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/predicted-nodes/batch` with payload to predict the nodes of up to 500 (node_id, relation_type) pairs in one request, such as `{"pairs": [{"node_id": "Compound::MESH:C000601183", "relation_type": "biomedgps::treats::Compound:Disease"}], "topk": 10}`. All pairs are scored by one batched query, and the topk nodes are returned for each pair in the same order. The invalid pairs are reported in their `error` fields. Each prediction has the `percentile` of its score if the model is calibrated. It requires the `predict:invoke` scope.
+    #[oai(
+        path = "/predicted-nodes/batch",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "batchPredictNodes"
+    )]
+    async fn batch_predict_nodes(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<BatchPredictionRequest>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<BatchPrediction> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        if payload.pairs.is_empty() || payload.pairs.len() > MAX_BATCH_PREDICTION_PAIRS {
+            let err = format!(
+                "The number of the pairs should be between 1 and {}, but got {}.",
+                MAX_BATCH_PREDICTION_PAIRS,
+                payload.pairs.len()
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        // Keep the same limit as the topk of the predicted-nodes endpoint.
+        if let Some(topk) = payload.topk {
+            if topk > 500 {
+                let err = format!("Invalid topk {}, it must be between 0 and 500.", topk);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+
+        match BatchPrediction::fetch(&pool_arc, &payload.pairs, payload.topk, payload.model_name)
+            .await
+        {
+            Ok(predictions) => GetWholeTableResponse::ok(predictions),
+            Err(e) => {
+                let err = format!("Failed to predict the nodes: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-layout` with a graph to compute the positions of its nodes by the force-directed layout on the server, it's for the large subgraphs which freeze the browser. Set `subgraph_id` to cache the layout, it's reused until the nodes or edges of the subgraph are changed.
+    #[oai(
+        path = "/graph-layout",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "computeGraphLayout"
+    )]
+    async fn compute_graph_layout(
+        &self,
+        payload: Json<GraphLayoutRequest>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<NodePosition> {
+        let mut payload = payload.0;
+        let iterations = payload.iterations.unwrap_or(DEFAULT_LAYOUT_ITERATIONS);
+        if iterations == 0 || iterations > MAX_LAYOUT_ITERATIONS {
+            let err = format!(
+                "The iterations should be between 1 and {}, but got {}.",
+                MAX_LAYOUT_ITERATIONS, iterations
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let num_nodes = payload.graph.get_nodes().len();
+        if num_nodes > MAX_LAYOUT_NODES {
+            let err = format!(
+                "The graph has {} nodes, but at most {} nodes can be laid out.",
+                num_nodes, MAX_LAYOUT_NODES
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        // The layout is CPU bound, so it's computed in a blocking thread.
+        let subgraph_id = payload.subgraph_id.clone();
+        let graph = payload.graph;
+        match tokio::task::spawn_blocking(move || {
+            graph.compute_layout(iterations, subgraph_id.as_deref())
+        })
+        .await
+        {
+            Ok(positions) => GetWholeTableResponse::ok(positions),
+            Err(e) => {
+                let err = format!("Failed to compute the layout: {}", e);
+                warn!("{}", err);
+                GetWholeTableResponse::bad_request(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-metrics` with a graph to compute the local structure metrics of its nodes, such as the degree, the clustering coefficient and the core number. The UI can map them to the size or opacity of the nodes.
+    #[oai(
+        path = "/graph-metrics",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "computeGraphMetrics"
+    )]
+    async fn compute_graph_metrics(
+        &self,
+        graph: Json<Graph>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<NodeMetrics> {
+        let graph = graph.0;
+        let num_nodes = graph.get_nodes().len();
+        if num_nodes > MAX_LAYOUT_NODES {
+            let err = format!(
+                "The graph has {} nodes, but the metrics can be computed for at most {} nodes.",
+                num_nodes, MAX_LAYOUT_NODES
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        // The metrics are CPU bound, so they are computed in a blocking thread.
+        match tokio::task::spawn_blocking(move || graph.compute_node_metrics()).await {
+            Ok(metrics) => GetWholeTableResponse::ok(metrics),
+            Err(e) => {
+                let err = format!("Failed to compute the graph metrics: {}", e);
+                warn!("{}", err);
+                GetWholeTableResponse::bad_request(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/shared-nodes` with query params to fetch shared nodes. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the cache hit, in the `stats` field.
+    #[oai(
+        path = "/shared-nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchSharedNodes"
+    )]
+    async fn fetch_shared_nodes(
+        &self,
+        pool: Data<&Arc<neo4rs::Graph>>,
+        pg_pool: Data<&Arc<sqlx::PgPool>>,
+        node_ids: Query<String>,
+        target_node_types: Query<Option<String>>,
+        topk: Query<Option<u64>>,
+        nhops: Query<Option<usize>>,
+        nums_shared_by: Query<Option<u64>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Neo4j);
+        let node_ids = node_ids.0;
+        let target_node_types = target_node_types.0;
+
+        match NodeIdsQuery::new(&node_ids) {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate node ids: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let graph = Graph::new();
+
+        if node_ids == "" {
+            return GetGraphResponse::ok(graph);
+        }
+
+        let node_ids: Vec<&str> = node_ids.split(",").collect();
+
+        let target_node_type_vec = match &target_node_types {
+            Some(t) => {
+                // TODO: We need to validate the target_node_types.
+                let target_node_types: Vec<&str> = t.split(",").collect();
+                if target_node_types.len() == 0 {
+                    None
+                } else {
+                    Some(target_node_types)
+                }
+            }
+            None => None,
+        };
+
+        let topk = match topk.0 {
+            Some(topk) => topk,
+            None => 10,
+        };
+
+        let nhops = match nhops.0 {
+            Some(nhops) => nhops,
+            None => 2,
+        };
+
+        let nums_shared_by = match nums_shared_by.0 {
+            Some(nums_shared_by) => nums_shared_by,
+            None => node_ids.len() as u64,
+        };
+
+        let query_started_at = Instant::now();
+        let (result, cache_hit) = track_memo_hits(query_shared_nodes(
+            &pool_arc,
+            &node_ids,
+            target_node_type_vec,
+            nhops as usize,
+            topk as usize,
+            nums_shared_by as usize,
+        ))
+        .await;
+        let (nodes, edges) = match result {
+            Ok((nodes, edges)) => (nodes, edges),
+            Err(e) => {
+                let err = format!("Failed to fetch paths: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        if nodes.len() == 0 {
+            let err = format!(
+                "No shared nodes found between {:?} with {:?} hops and {:?} node types.",
+                node_ids, nhops, target_node_types
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        };
+
+        stats.record_query(query_started_at, nodes.len() + edges.len());
+        stats.record_cache_hit(cache_hit);
+
+        let nodes = nodes.iter().collect();
+        let edges = edges.iter().collect();
+        // TODO: How to get the topk paths based on the scores?
+        let graph = Graph::from_data(nodes, edges);
+        let mut graph = graph
+            .to_owned()
+            .get_graph(None, aggregate_edges.0, dedupe.0)
+            .unwrap();
+        graph
+            .attach_node_tags(&pg_pool, &_token.0.username, &_token.0.projects)
+            .await;
+        graph.attach_thumbnails(&pg_pool).await;
+        graph.attach_edge_qualifiers(&pg_pool).await;
+        stats.attach(&mut graph);
+        GetGraphResponse::ok(graph)
+    }
+
+    /// Call `/api/v1/paths` with query params to fetch paths. Set `topk` to score the paths by the KGE model (`model_name`) and only keep the topk paths, the score of a path is composed of the model scores of its edges by `score_method` (`product` by default or `sum`). The model scores replace the scores of the edges, and the ranked paths are returned in the `paths` field. Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the cache hit, in the `stats` field.
+    #[oai(
+        path = "/paths",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchPaths"
+    )]
+    async fn fetch_paths(
+        &self,
+        pool: Data<&Arc<neo4rs::Graph>>,
+        pg_pool: Data<&Arc<sqlx::PgPool>>,
+        start_node_id: Query<String>,
+        end_node_id: Query<String>,
+        nhops: Query<Option<usize>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        topk: Query<Option<usize>>,
+        model_name: Query<Option<String>>,
+        score_method: Query<Option<PathScoreMethod>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Neo4j);
+        let start_node_id = start_node_id.0;
+        let end_node_id = end_node_id.0;
+        let nhops = match nhops.0 {
+            Some(nhops) => nhops,
+            None => {
+                warn!("nhops is empty.");
+                2
+            }
+        };
+
+        if let Some(topk) = topk.0 {
+            if topk == 0 || topk > MAX_SCORED_PATHS {
+                let err = format!(
+                    "Invalid topk {}, it must be between 1 and {}.",
+                    topk, MAX_SCORED_PATHS
+                );
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+
+        let model_table_prefix = match get_model_table_prefix(model_name.0.as_deref()) {
+            Ok(model_table_prefix) => model_table_prefix,
+            Err(e) => {
+                let err = format!("Failed to fetch paths: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        let query_started_at = Instant::now();
+        let (result, cache_hit) =
+            track_memo_hits(query_nhops(&pool_arc, &start_node_id, &end_node_id, nhops)).await;
+        let (nodes, edges) = match result {
+            Ok((nodes, edges)) => (nodes, edges),
+            Err(e) => {
+                let err = format!("Failed to fetch paths: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+
+        if nodes.len() == 0 {
+            let err = format!(
+                "No path found between {} and {} with {} hops.",
+                start_node_id, end_node_id, nhops
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        };
+
+        stats.record_query(query_started_at, nodes.len() + edges.len());
+        stats.record_cache_hit(cache_hit);
+
+        let nodes = nodes.iter().collect();
+        let edges = edges.iter().collect();
+        let graph = Graph::from_data(nodes, edges);
+        let mut graph = graph
+            .to_owned()
+            .get_graph(None, aggregate_edges.0, dedupe.0)
+            .unwrap();
+
+        if let Some(topk) = topk.0 {
+            if let Err(e) = graph
+                .rank_paths(
+                    &pg_pool,
+                    start_node_id.trim(),
+                    end_node_id.trim(),
+                    nhops,
+                    topk,
+                    Some(model_table_prefix),
+                    score_method.0.unwrap_or_default(),
+                )
+                .await
+            {
+                let err = format!("Failed to score the paths: {}", e);
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        }
+
+        graph
+            .attach_node_tags(&pg_pool, &_token.0.username, &_token.0.projects)
+            .await;
+        graph.attach_thumbnails(&pg_pool).await;
+        graph.attach_edge_qualifiers(&pg_pool).await;
+        stats.attach(&mut graph);
+        GetGraphResponse::ok(graph)
+    }
+
+    /// Call `/api/v1/expanded-nodes` with query params to expand a node in one call. The `pathway` mode expands a Pathway node to its member genes, which are identified by the membership relation types such as `Hetionet::GpPW::Gene:Pathway`, and the diseases linked to the genes within `depth` hops (default 1, 0 means only the member genes, at most 3). Set `limit` to cap the gene-disease paths (default 100, at most 1000). Set `include_stats` to true to attach the execution metadata, such as the backend, the query time and the number of fetched rows, in the `stats` field.
+    #[oai(
+        path = "/expanded-nodes",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchExpandedNodes"
+    )]
+    async fn fetch_expanded_nodes(
+        &self,
+        pool: Data<&Arc<neo4rs::Graph>>,
+        pg_pool: Data<&Arc<sqlx::PgPool>>,
+        node_id: Query<String>,
+        mode: Query<Option<String>>,
+        depth: Query<Option<usize>>,
+        limit: Query<Option<usize>>,
+        aggregate_edges: Query<Option<bool>>,
+        dedupe: Query<Option<bool>>,
+        include_stats: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetGraphResponse {
+        let pool_arc = pool.clone();
+        let mut stats = GraphStatsRecorder::new(include_stats.0, GraphBackend::Neo4j);
+        let node_id = node_id.0;
+        let mode_name = mode.0.unwrap_or("pathway".to_string());
+        let mode = match ExpansionMode::from_name(&mode_name) {
+            Some(mode) => mode,
+            None => {
+                let err = format!(
+                    "Invalid expansion mode: {}, it should be pathway.",
+                    mode_name
+                );
+                warn!("{}", err);
+                return GetGraphResponse::bad_request(err);
+            }
+        };
+        let depth = depth.0.unwrap_or(1);
+        if depth > MAX_PATHWAY_EXPANSION_DEPTH {
+            let err = format!(
+                "The depth should be between 0 and {}, but got {}.",
+                MAX_PATHWAY_EXPANSION_DEPTH, depth
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        }
+
+        let limit = limit.0.unwrap_or(100);
+        if limit == 0 || limit > MAX_PATHWAY_EXPANSION_LIMIT {
+            let err = format!(
+                "The limit should be between 1 and {}, but got {}.",
+                MAX_PATHWAY_EXPANSION_LIMIT, limit
+            );
+            warn!("{}", err);
+            return GetGraphResponse::bad_request(err);
+        }
+
+        let query_started_at = Instant::now();
+        let (nodes, edges) =
+            match query_expanded_nodes(&pool_arc, &node_id, mode, depth, limit).await {
+                Ok((nodes, edges)) => (nodes, edges),
+                Err(e) => {
+                    let err = format!("Failed to expand the node: {}", e);
+                    warn!("{}", err);
+                    return GetGraphResponse::bad_request(err);
+                }
+            };
+
+        stats.record_query(query_started_at, nodes.len() + edges.len());
+
+        let nodes = nodes.iter().collect();
+        let edges = edges.iter().collect();
+        let graph = Graph::from_data(nodes, edges);
+        let mut graph = graph
+            .to_owned()
+            .get_graph(None, aggregate_edges.0, dedupe.0)
+            .unwrap();
+        graph
+            .attach_node_tags(&pg_pool, &_token.0.username, &_token.0.projects)
+            .await;
+        graph.attach_thumbnails(&pg_pool).await;
+        graph.attach_edge_qualifiers(&pg_pool).await;
+        stats.attach(&mut graph);
+        GetGraphResponse::ok(graph)
+    }
+
+    /// Call `/api/v1/llm` with query params to get answer from LLM. It requires the `llm:invoke` scope.
+    #[oai(
+        path = "/llm",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "askLLM"
+    )]
+    async fn ask_llm(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        prompt_template_id: Query<String>,
+        context: Json<Context>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<LlmResponse> {
+        let pool_arc = pool.clone();
+        let prompt_template_id = prompt_template_id.0;
+        let context = context.0;
+        debug!("Prompt template id: {}", prompt_template_id);
+        debug!("Context: {:?}", context);
+
+        let openai_api_key = match std::env::var("OPENAI_API_KEY") {
+            Ok(openai_api_key) => openai_api_key,
+            Err(e) => {
+                let err = format!("Failed to get OPENAI_API_KEY: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        let chatbot =
+            ChatBot::new("GPT4", &openai_api_key).with_usage_context(&_token.0.username, "askLLM");
+        match context
+            .answer(&chatbot, &prompt_template_id, Some(&pool_arc))
+            .await
+        {
+            Ok(llm_response) => PostResponse::created(llm_response),
+            Err(e) => {
+                let err = format!("Failed to get answer from LLM: {}", e);
+                warn!("{}", err);
+                if e.downcast_ref::<LlmBudgetExceeded>().is_some() {
+                    return PostResponse::too_many_requests(err);
+                }
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/paths/narrate` with a path to convert it into readable sentences for the reports. The sentences cite the pmids of the edges, and they can be polished by the LLM. The polishing requires the `llm:invoke` scope.
+    #[oai(
+        path = "/paths/narrate",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "narratePath"
+    )]
+    async fn narrate_path(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        steps: Json<Vec<PathStep>>,
+        polish: Query<Option<bool>>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<PathNarrative> {
+        let pool_arc = pool.clone();
+        let steps = steps.0;
+        let polish = polish.0.unwrap_or(false);
+
+        let mut narrative = match PathNarrative::from_steps(&pool_arc, &steps).await {
+            Ok(narrative) => narrative,
+            Err(e) => {
+                let err = format!("Failed to narrate the path: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        if !polish {
+            return PostResponse::created(narrative);
+        }
+
+        let openai_api_key = match std::env::var("OPENAI_API_KEY") {
+            Ok(openai_api_key) => openai_api_key,
+            Err(e) => {
+                let err = format!("Failed to get OPENAI_API_KEY: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        let chatbot = ChatBot::new("GPT4", &openai_api_key)
+            .with_usage_context(&_token.0.username, "narratePath");
+        match narrative.polish(&chatbot, Some(&pool_arc)).await {
+            Ok(_) => PostResponse::created(narrative),
+            Err(e) => {
+                let err = format!("Failed to polish the narrative: {}", e);
+                warn!("{}", err);
+                if e.downcast_ref::<LlmBudgetExceeded>().is_some() {
+                    return PostResponse::too_many_requests(err);
+                }
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/llm-usages/summary` to summarize the token usage of the LLM per user or for the whole deployment. Only for the admin users.
+    #[oai(
+        path = "/llm-usages/summary",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchLlmUsageSummary"
+    )]
+    async fn fetch_llm_usage_summary(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        per_user: Query<Option<bool>>,
+        month: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<LlmUsageSummary> {
+        let pool_arc = pool.clone();
+        let per_user = per_user.0.unwrap_or(true);
+        let month = month.0;
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the LLM usages.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        match LlmUsage::summarize(&pool_arc, per_user, month.as_deref()).await {
+            Ok(summaries) => GetWholeTableResponse::ok(summaries),
+            Err(e) => {
+                let err = format!("Failed to summarize the LLM usages: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/node-tags` with query params to fetch the node tags which are visible to the current user, such as the own tags and the tags shared with the projects of the user.
+    #[oai(
+        path = "/node-tags",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchNodeTags"
+    )]
+    async fn fetch_node_tags(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        node_ids: Query<Option<String>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<NodeTag> {
+        let pool_arc = pool.clone();
+        let node_ids = match node_ids.0 {
+            Some(node_ids) => match NodeIdsQuery::new(&node_ids) {
+                Ok(_) => Some(
+                    node_ids
+                        .split(",")
+                        .map(|s| s.to_string())
+                        .collect::<Vec<String>>(),
+                ),
+                Err(e) => {
+                    let err = format!("Failed to validate node ids: {}", e);
+                    warn!("{}", err);
+                    return GetWholeTableResponse::bad_request(err);
+                }
+            },
+            None => None,
+        };
+
+        match NodeTag::fetch_visible(
+            &pool_arc,
+            &_token.0.username,
+            &_token.0.projects,
+            node_ids.as_ref(),
+        )
+        .await
+        {
+            Ok(tags) => GetWholeTableResponse::ok(tags),
+            Err(e) => {
+                let err = format!("Failed to fetch the node tags: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/node-tags` with payload to tag a node. The tag is shared with the members of the project if the project_id is set.
+    #[oai(
+        path = "/node-tags",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postNodeTag"
+    )]
+    async fn post_node_tag(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<NodeTag>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<NodeTag> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        if let Some(project_id) = payload.project_id {
+            if !_token.0.projects.contains(&project_id) {
+                let err = format!(
+                    "User {} is not a member of the project {}.",
+                    _token.0.username, project_id
+                );
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        match payload.insert(&pool_arc, &_token.0.username).await {
+            Ok(tag) => PostResponse::created(tag),
+            Err(e) => {
+                let err = format!("Failed to insert the node tag: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/node-tags/:id` with payload to update a node tag. Only the owner can update the tag.
+    #[oai(
+        path = "/node-tags/:id",
+        method = "put",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "putNodeTag"
+    )]
+    async fn put_node_tag(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<NodeTag>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<NodeTag> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+        let id = id.0;
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        if let Some(project_id) = payload.project_id {
+            if !_token.0.projects.contains(&project_id) {
+                let err = format!(
+                    "User {} is not a member of the project {}.",
+                    _token.0.username, project_id
+                );
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        match payload.update(&pool_arc, id, &_token.0.username).await {
+            Ok(tag) => PostResponse::created(tag),
+            Err(e) => {
+                let err = format!("Failed to update the node tag {}: {}", id, e);
+                warn!("{}", err);
+                return PostResponse::not_found(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/node-tags/:id` to delete a node tag. Only the owner can delete the tag.
+    #[oai(
+        path = "/node-tags/:id",
+        method = "delete",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "deleteNodeTag"
+    )]
+    async fn delete_node_tag(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> DeleteResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match NodeTag::delete(&pool_arc, id, &_token.0.username).await {
+            Ok(_) => DeleteResponse::no_content(),
+            Err(e) => {
+                let err = format!("Failed to delete the node tag {}: {}", id, e);
+                warn!("{}", err);
+                DeleteResponse::not_found(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-views` to fetch the graph views which are visible to the current user, such as the own views and the views shared with the projects of the user. The id of a view can be set as the `view_id` param of the entity, relation and graph endpoints.
+    #[oai(
+        path = "/graph-views",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchGraphViews"
+    )]
+    async fn fetch_graph_views(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<GraphView> {
+        let pool_arc = pool.clone();
+
+        match GraphView::fetch_visible(&pool_arc, &_token.0.username, &_token.0.projects).await {
+            Ok(views) => GetWholeTableResponse::ok(views),
+            Err(e) => {
+                let err = format!("Failed to fetch the graph views: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-views` with payload to create a graph view, such as `{"name": "DRKG + CTD", "datasets": ["drkg", "ctd"]}`. The view is shared with the members of the project if the project_id is set.
+    #[oai(
+        path = "/graph-views",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postGraphView"
+    )]
+    async fn post_graph_view(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<GraphView>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<GraphView> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        if let Some(project_id) = payload.project_id {
+            if !_token.0.projects.contains(&project_id) {
+                let err = format!(
+                    "User {} is not a member of the project {}.",
+                    _token.0.username, project_id
+                );
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        match payload.insert(&pool_arc, &_token.0.username).await {
+            Ok(view) => PostResponse::created(view),
+            Err(e) => {
+                let err = format!("Failed to insert the graph view: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-views/:id` with payload to update a graph view. Only the owner can update the view.
+    #[oai(
+        path = "/graph-views/:id",
+        method = "put",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "putGraphView"
+    )]
+    async fn put_graph_view(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<GraphView>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<GraphView> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+        let id = id.0;
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        if let Some(project_id) = payload.project_id {
+            if !_token.0.projects.contains(&project_id) {
+                let err = format!(
+                    "User {} is not a member of the project {}.",
+                    _token.0.username, project_id
+                );
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+
+        match payload.update(&pool_arc, id, &_token.0.username).await {
+            Ok(view) => PostResponse::created(view),
+            Err(e) => {
+                let err = format!("Failed to update the graph view {}: {}", id, e);
+                warn!("{}", err);
+                return PostResponse::not_found(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/graph-views/:id` to delete a graph view. Only the owner can delete the view.
+    #[oai(
+        path = "/graph-views/:id",
+        method = "delete",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "deleteGraphView"
+    )]
+    async fn delete_graph_view(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> DeleteResponse {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        match GraphView::delete(&pool_arc, id, &_token.0.username).await {
+            Ok(_) => DeleteResponse::no_content(),
+            Err(e) => {
+                let err = format!("Failed to delete the graph view {}: {}", id, e);
+                warn!("{}", err);
+                DeleteResponse::not_found(err)
+            }
+        }
+    }
+
+    /// Call `/api/v1/dataset-licenses` to fetch the licenses of the source datasets.
+    #[oai(
+        path = "/dataset-licenses",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchDatasetLicenses"
+    )]
+    async fn fetch_dataset_licenses(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<DatasetLicense> {
+        let pool_arc = pool.clone();
+
+        match DatasetLicense::get_records(&pool_arc).await {
+            Ok(licenses) => GetWholeTableResponse::ok(licenses),
+            Err(e) => {
+                let err = format!("Failed to fetch the dataset licenses: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/dataset-licenses` with payload to set the license of a dataset, the relations of the dataset are tagged with the license. Only for the admin users.
+    #[oai(
+        path = "/dataset-licenses",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postDatasetLicense"
+    )]
+    async fn post_dataset_license(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        payload: Json<DatasetLicense>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<DatasetLicense> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can set the dataset licenses.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match payload.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                let err = format!("Failed to validate payload: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+
+        match payload.upsert(&pool_arc).await {
+            Ok(license) => PostResponse::created(license),
+            Err(e) => {
+                let err = format!("Failed to set the dataset license: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/export-jobs` with payload to export the relations in the background. The relations of the restricted datasets are excluded unless `include_restricted` is set. The curated knowledges are exported if `include_curations` is set, the non-admin users must also set `pseudonymize_curators`. Set `entity_types`, `min_score` and `max_relations` to extract a slice of the knowledge graph, and use the `nodelink` format to load it by networkx or igraph in a notebook. The job is returned immediately, and its progress can be fetched by `/api/v1/export-jobs`.
+    #[oai(
+        path = "/export-jobs",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postExportJob"
+    )]
+    async fn post_export_job(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        database_url: Data<&DatabaseUrl>,
+        payload: Json<ExportJobRequest>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ExportJob> {
+        let pool_arc = pool.clone();
+        let mut payload = payload.0;
+        // The job runs in the background without the token, so the scope of the user is stored with the filters.
+        payload.curation_scope = Some(_token.0.owner_scope());
+
+        if payload.include_curations && !payload.pseudonymize_curators && !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, the curators must be pseudonymized when the curations are exported.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match ExportJob::schedule(&pool_arc, database_url.as_str(), &_token.0.username, &payload).await {
+            Ok(job) => PostResponse::created(job),
+            Err(e) => {
+                let err = format!("Failed to create the export job: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/takeout-jobs` to export all rows owned by the current user, such as the curated knowledges, the subgraphs and the node tags, into a zip file of json and tsv files. It's an export job, so its progress can be fetched by `/api/v1/export-jobs` and the artifact can be downloaded by `/api/v1/export-jobs/:id/artifact`.
+    #[oai(
+        path = "/takeout-jobs",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postTakeoutJob"
+    )]
+    async fn post_takeout_job(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        database_url: Data<&DatabaseUrl>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ExportJob> {
+        let pool_arc = pool.clone();
+
+        let request = ExportJobRequest::takeout();
+        match ExportJob::schedule(&pool_arc, database_url.as_str(), &_token.0.username, &request).await {
+            Ok(job) => PostResponse::created(job),
+            Err(e) => {
+                let err = format!("Failed to create the takeout job: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/export-jobs` to fetch the export jobs of the current user with their progress, the newest first.
+    #[oai(
+        path = "/export-jobs",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchExportJobs"
+    )]
+    async fn fetch_export_jobs(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<ExportJob> {
+        let pool_arc = pool.clone();
+
+        match ExportJob::list(&pool_arc, &_token.0.username).await {
+            Ok(jobs) => GetWholeTableResponse::ok(jobs),
+            Err(e) => {
+                let err = format!("Failed to fetch the export jobs: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/import-files?filename=relations.tsv.gz` with the file as the body to upload a data file for the import jobs, it's stored in the uploads directory of IMPORT_DATA_DIR. The returned path is used as the filepath of the import steps. Set `overwrite` to replace an existing file. Only the admin users can upload the files.
+    #[oai(
+        path = "/import-files",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "uploadImportFile"
+    )]
+    async fn upload_import_file(
+        &self,
+        filename: Query<String>,
+        overwrite: Query<Option<bool>>,
+        body: Binary<Body>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ImportFile> {
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can upload the data files.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        let filename = filename.0;
+        if !is_valid_upload_name(&filename) {
+            let err = format!(
+                "Invalid filename {}, only the letters, digits, dots, hyphens and underscores are allowed.",
+                filename
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        let dir = get_import_data_dir().join(IMPORT_UPLOAD_DIR);
+        let path = dir.join(&filename);
+        if path.exists() && !overwrite.0.unwrap_or(false) {
+            let err = format!(
+                "The file {} already exists, set overwrite to replace it.",
+                filename
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        // The file is written into a temporary file first, so a broken upload doesn't replace the existing file.
+        let temp_path = dir.join(format!(".{}.part", filename));
+        let result = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            let mut reader = body.0.into_async_read();
+            let size = tokio::io::copy(&mut reader, &mut file).await?;
+            tokio::io::AsyncWriteExt::flush(&mut file).await?;
+            tokio::fs::rename(&temp_path, &path).await?;
+            Ok::<u64, std::io::Error>(size)
+        }
+        .await;
+
+        match result {
+            Ok(size) => PostResponse::created(ImportFile {
+                path: format!("{}/{}", IMPORT_UPLOAD_DIR, filename),
+                size: size as i64,
+            }),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                let err = format!("Failed to save the file {}: {}", filename, e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/import-jobs` with payload to import the data files on the server, such as `{"steps": [{"table": "entity", "filepath": "uploads/entities.tsv"}, {"table": "entity_metadata"}]}`. The filepaths are relative to IMPORT_DATA_DIR, see `/api/v1/import-files`. Each step imports a table like `importdb --table`, or a KGE model like `importkge` if the table is `kge`. The steps run one by one in the background, and only one import job can run at a time. The job is returned immediately, and its progress and logs can be fetched by `/api/v1/import-jobs`. Only the admin users can import the data.
+    #[oai(
+        path = "/import-jobs",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "postImportJob"
+    )]
+    async fn post_import_job(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        database_url: Data<&DatabaseUrl>,
+        payload: Json<ImportJobRequest>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ImportJob> {
+        let pool_arc = pool.clone();
+        let payload = payload.0;
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can import the data.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match ImportJob::schedule(&pool_arc, database_url.as_str(), &_token.0.username, &payload).await {
+            Ok(job) => PostResponse::created(job),
+            Err(e) => {
+                let err = format!("Failed to create the import job: {}", e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/import-jobs` to fetch the import jobs with their progress and logs, the newest first. The jobs of the data registry webhook are listed with the admin jobs, the `source` field tells them apart. Set `status` to filter the jobs by the statuses, such as `running,failed`. All matched jobs are returned unless `page` is set, `page_size` is 10 by default. Only the admin users can fetch them.
+    #[oai(
+        path = "/import-jobs",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchImportJobs"
+    )]
+    async fn fetch_import_jobs(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        status: Query<Option<String>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<ImportJob> {
+        let pool_arc = pool.clone();
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can fetch the import jobs.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let statuses = status.0.map(|status| {
+            status
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<String>>()
+        });
+
+        match ImportJob::list(&pool_arc, &statuses, page.0, page_size.0).await {
+            Ok(jobs) => GetWholeTableResponse::ok(jobs),
+            Err(e) => {
+                let err = format!("Failed to fetch the import jobs: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/import-jobs/:id/cancel` to cancel a pending or running admin import job. The running step is stopped within a few seconds, and the job can be resumed later. Only the admin users can cancel the jobs.
+    #[oai(
+        path = "/import-jobs/:id/cancel",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "cancelImportJob"
+    )]
+    async fn cancel_import_job(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ImportJob> {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can cancel the import jobs.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match ImportJob::cancel(&pool_arc, id).await {
+            Ok(job) => PostResponse::created(job),
+            Err(e) => {
+                let err = format!("Failed to cancel the import job {}: {}", id, e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/import-jobs/:id/resume` to resume a failed or cancelled admin import job from the step where it stopped, the entity and relation tables continue from the last imported chunk. Only the admin users can resume the jobs.
+    #[oai(
+        path = "/import-jobs/:id/resume",
+        method = "post",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "resumeImportJob"
+    )]
+    async fn resume_import_job(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        database_url: Data<&DatabaseUrl>,
+        id: Path<i64>,
+        _token: CustomSecurityScheme,
+    ) -> PostResponse<ImportJob> {
+        let pool_arc = pool.clone();
+        let id = id.0;
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can resume the import jobs.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        match ImportJob::resume(&pool_arc, database_url.as_str(), id).await {
+            Ok(job) => PostResponse::created(job),
+            Err(e) => {
+                let err = format!("Failed to resume the import job {}: {}", id, e);
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/scheduled-tasks` to fetch the runs of the scheduled maintenance tasks, such as the nightly refresh of the trending entities, the newest first. Set `task_name` to fetch the runs of a task and `status` to filter the runs by the statuses, such as `failed`. All matched runs are returned unless `page` is set, `page_size` is 10 by default. Only the admin users can fetch them.
+    #[oai(
+        path = "/scheduled-tasks",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchScheduledTaskRuns"
+    )]
+    async fn fetch_scheduled_task_runs(
+        &self,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        task_name: Query<Option<String>>,
+        status: Query<Option<String>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<ScheduledTaskRun> {
+        let pool_arc = pool.clone();
+
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can fetch the scheduled task runs.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        let statuses = status.0.map(|status| {
+            status
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<String>>()
+        });
+        if let Some(statuses) = &statuses {
+            if let Some(status) = statuses
+                .iter()
+                .find(|s| !TASK_RUN_STATUSES.contains(&s.as_str()))
+            {
+                let err = format!(
+                    "Invalid status {}, it must be one of {}.",
+                    status,
+                    TASK_RUN_STATUSES.join(", ")
+                );
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+
+        match ScheduledTaskRun::list(
+            &pool_arc,
+            task_name.0.as_deref(),
+            &statuses,
+            page.0,
+            page_size.0,
+        )
+        .await
+        {
+            Ok(runs) => GetWholeTableResponse::ok(runs),
+            Err(e) => {
+                let err = format!("Failed to fetch the scheduled task runs: {}", e);
+                warn!("{}", err);
+                return GetWholeTableResponse::bad_request(err);
+            }
+        }
+    }
+
+    /// Call `/api/v1/pool-stats` to fetch the usage of the database pools, such as the pool of the API requests and the pools of the running jobs. Only the admin users can access it.
+    #[oai(
+        path = "/pool-stats",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchPoolStats"
+    )]
+    async fn fetch_pool_stats(
+        &self,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<PoolStats> {
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the pool stats.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        GetWholeTableResponse::ok(get_pool_stats())
+    }
+
+    /// Call `/api/v1/query-memo-stats` to fetch the hit rates of the memoized shared nodes and paths queries. Only the admin users can access it.
+    #[oai(
+        path = "/query-memo-stats",
+        method = "get",
+        tag = "ApiTags::KnowledgeGraph",
+        operation_id = "fetchQueryMemoStats"
+    )]
+    async fn fetch_query_memo_stats(
+        &self,
+        _token: CustomSecurityScheme,
+    ) -> GetWholeTableResponse<QueryMemoStats> {
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the query memo stats.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
+        }
+
+        GetWholeTableResponse::ok(get_query_memo_stats())
+    }
+
+    /// Call `/api/v1/curator-pseudonyms` to fetch the mapping between the pseudonyms and the curators in the exports. Only the admin users can access it.
     #[oai(
-        path = "/subgraphs",
-        method = "post",
+        path = "/curator-pseudonyms",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "postSubgraph"
+        operation_id = "fetchCuratorPseudonyms"
     )]
-    async fn post_subgraph(
+    async fn fetch_curator_pseudonyms(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        payload: Json<Subgraph>,
         _token: CustomSecurityScheme,
-    ) -> PostResponse<Subgraph> {
+    ) -> GetWholeTableResponse<CuratorPseudonym> {
         let pool_arc = pool.clone();
-        let mut payload = payload.0;
-        let username = _token.0.username.clone();
 
-        // When we enabled auth mode, we need to use the username from an access_token instead.
-        if username != USERNAME_PLACEHOLDER.to_string() {
-            payload.update_owner(username);
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the curator pseudonyms.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
         }
 
-        match payload.validate() {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate subgraph: {}", e);
-                warn!("{}", err);
-                return PostResponse::bad_request(err);
-            }
-        };
-
-        match payload.insert(&pool_arc).await {
-            Ok(kc) => PostResponse::created(kc),
+        match CuratorPseudonym::get_records(&pool_arc).await {
+            Ok(records) => GetWholeTableResponse::ok(records),
             Err(e) => {
-                let err = format!("Failed to insert curated knowledge: {}", e);
+                let err = format!("Failed to fetch the curator pseudonyms: {}", e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/subgraphs/:id` with payload to update a subgraph.
+    /// Call `/api/v1/entity-images/:entity_type/:entity_id` to fetch the image of an entity, such as the structure of a compound. The image is fetched from the external service and cached if it's not cached yet.
     #[oai(
-        path = "/subgraphs/:id",
-        method = "put",
+        path = "/entity-images/:entity_type/:entity_id",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "putSubgraph"
+        operation_id = "fetchEntityImage"
     )]
-    async fn put_subgraph(
+    async fn fetch_entity_image(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        id: Path<String>,
-        payload: Json<Subgraph>,
+        entity_type: Path<String>,
+        entity_id: Path<String>,
         _token: CustomSecurityScheme,
-    ) -> PostResponse<Subgraph> {
+    ) -> GetEntityImageResponse {
         let pool_arc = pool.clone();
-        let id = id.0;
-        let mut payload = payload.0;
-        let username = _token.0.username.clone();
-
-        // When we enabled auth mode, we need to use the username from an access_token instead.
-        if username != "user-placeholder".to_string() {
-            payload.update_owner(username);
-        }
+        let entity_type = entity_type.0;
+        let entity_id = entity_id.0;
 
-        match SubgraphIdQuery::new(&id) {
+        let node_id = format!("{}{}{}", entity_type, COMPOSED_ENTITY_DELIMITER, entity_id);
+        match NodeIdQuery::new(&node_id) {
             Ok(_) => {}
             Err(e) => {
-                let err = format!("Failed to parse subgraph id: {}", e);
+                let err = format!("Failed to parse the entity: {}", e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetEntityImageResponse::bad_request(err);
             }
-        }
+        };
 
-        match payload.validate() {
-            Ok(_) => {}
+        let image = match EntityImage::fetch(&pool_arc, &entity_type, &entity_id).await {
+            Ok(Some(image)) => image,
+            Ok(None) => match get_image_source_url(&entity_type, &entity_id) {
+                Some(source_url) => {
+                    match EntityImage::cache(&pool_arc, &entity_type, &entity_id, &source_url).await
+                    {
+                        Ok(image) => image,
+                        Err(e) => {
+                            let err = format!("Failed to fetch the image of {}: {}", node_id, e);
+                            warn!("{}", err);
+                            return GetEntityImageResponse::not_found(err);
+                        }
+                    }
+                }
+                None => {
+                    let err = format!("No image is available for {}.", node_id);
+                    warn!("{}", err);
+                    return GetEntityImageResponse::not_found(err);
+                }
+            },
             Err(e) => {
-                let err = format!("Failed to validate subgraph: {}", e);
+                let err = format!("Failed to fetch the image of {}: {}", node_id, e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetEntityImageResponse::bad_request(err);
             }
-        }
+        };
 
-        match payload.update(&pool_arc, &id).await {
-            Ok(kc) => PostResponse::created(kc),
+        match tokio::fs::File::open(image.get_filepath()).await {
+            Ok(file) => GetEntityImageResponse::ok(Body::from_async_read(file)),
             Err(e) => {
-                let err = format!("Failed to update subgraph: {}", e);
+                let err = format!("Failed to open the image of {}: {}", node_id, e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return GetEntityImageResponse::not_found(err);
             }
         }
     }
 
-    /// Call `/api/v1/subgraphs/:id` with payload to create subgraph.
+    /// Call `/api/v1/export-jobs/:id/artifact` to download the artifact of a succeeded export job before it's expired.
     #[oai(
-        path = "/subgraphs/:id",
-        method = "delete",
+        path = "/export-jobs/:id/artifact",
+        method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "deleteSubgraph"
+        operation_id = "downloadExportArtifact"
     )]
-    async fn delete_subgraph(
+    async fn download_export_artifact(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        id: Path<String>,
+        id: Path<i64>,
         _token: CustomSecurityScheme,
-    ) -> DeleteResponse {
+    ) -> GetArtifactResponse {
         let pool_arc = pool.clone();
         let id = id.0;
 
-        match SubgraphIdQuery::new(&id) {
-            Ok(_) => {}
+        let job = match ExportJob::fetch(&pool_arc, id, &_token.0.username).await {
+            Ok(job) => job,
             Err(e) => {
-                let err = format!("Failed to validate subgraph id: {}", e);
+                let err = format!("Failed to fetch the export job {}: {}", id, e);
                 warn!("{}", err);
-                return DeleteResponse::bad_request(err);
+                return GetArtifactResponse::not_found(err);
             }
-        }
+        };
 
-        match Subgraph::delete(&pool_arc, &id).await {
-            Ok(_) => DeleteResponse::NoContent,
+        let artifact = match job.get_artifact() {
+            Ok(artifact) => artifact,
             Err(e) => {
-                let err = format!("Failed to delete a subgraph: {}", e);
+                let err = format!("{}", e);
                 warn!("{}", err);
-                DeleteResponse::not_found(err)
+                return GetArtifactResponse::bad_request(err);
+            }
+        };
+
+        match tokio::fs::File::open(&artifact).await {
+            Ok(file) => GetArtifactResponse::ok(Body::from_async_read(file), &job.get_filename()),
+            Err(e) => {
+                let err = format!(
+                    "Failed to open the artifact of the export job {}: {}",
+                    id, e
+                );
+                warn!("{}", err);
+                return GetArtifactResponse::not_found(err);
             }
         }
     }
 
-    /// Call `/api/v1/nodes` with query params to fetch nodes.
+    /// Call `/api/v1/api-keys` with payload to create an API key for the current user, such as `{"name": "nightly-pipeline", "expires_in_days": 90}`. The key is only returned in this response, send it in the `X-API-Key` header to access the read and prediction endpoints as the current user. A user can have at most 20 active keys.
     #[oai(
-        path = "/nodes",
-        method = "get",
+        path = "/api-keys",
+        method = "post",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchNodes"
+        operation_id = "postApiKey"
     )]
-    async fn fetch_nodes(
+    async fn post_api_key(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        node_ids: Query<String>,
+        payload: Json<ApiKeyRequest>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> PostResponse<ApiKey> {
         let pool_arc = pool.clone();
-        let node_ids = node_ids.0;
-
-        match NodeIdsQuery::new(&node_ids) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate node ids: {}", e);
-                warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
-            }
-        };
-
-        let mut graph = Graph::new();
-
-        if node_ids == "" {
-            return GetGraphResponse::ok(graph);
-        }
+        let payload = payload.0;
 
-        let node_ids: Vec<&str> = node_ids.split(",").collect();
-        match graph.fetch_nodes_by_ids(&pool_arc, &node_ids).await {
-            Ok(graph) => GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap()),
+        match ApiKey::create(&pool_arc, &_token.0.username, &payload).await {
+            Ok(api_key) => PostResponse::created(api_key),
             Err(e) => {
-                let err = format!("Failed to fetch nodes: {}", e);
+                let err = format!("Failed to create the API key: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return PostResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/auto-connect-nodes` with query params to fetch edges which connect the input nodes.
+    /// Call `/api/v1/api-keys` to fetch the API keys of the current user, the newest first. The keys themselves are never returned, use the `key_prefix` to identify them.
     #[oai(
-        path = "/auto-connect-nodes",
+        path = "/api-keys",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchEdgesAutoConnectNodes"
+        operation_id = "fetchApiKeys"
     )]
-    async fn fetch_edges_auto_connect_nodes(
+    async fn fetch_api_keys(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        node_ids: Query<String>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> GetWholeTableResponse<ApiKey> {
         let pool_arc = pool.clone();
-        let node_ids = node_ids.0;
-
-        match NodeIdsQuery::new(&node_ids) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate node ids: {}", e);
-                warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
-            }
-        };
-
-        let mut graph = Graph::new();
-
-        if node_ids == "" {
-            return GetGraphResponse::ok(graph);
-        }
 
-        let node_ids: Vec<&str> = node_ids.split(",").collect();
-        // TODO: we need to get the model_table_prefix from the parameter, so users can get the score from a specific model.
-        let model_table_prefix = Some(DEFAULT_MODEL_NAME);
-        match graph
-            .auto_connect_nodes(&pool_arc, &node_ids, model_table_prefix)
-            .await
-        {
-            Ok(graph) => GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap()),
+        match ApiKey::list(&pool_arc, &_token.0.username).await {
+            Ok(keys) => GetWholeTableResponse::ok(keys),
             Err(e) => {
-                let err = format!("Failed to fetch nodes: {}", e);
+                let err = format!("Failed to fetch the API keys: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/one-step-linked-nodes` with query params to fetch linked nodes with one step.
+    /// Call `/api/v1/api-keys/:id` to revoke an API key of the current user, the requests with the key are rejected immediately. The admin users can revoke the keys of all users.
     #[oai(
-        path = "/one-step-linked-nodes",
-        method = "get",
+        path = "/api-keys/:id",
+        method = "delete",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchOneStepLinkedNodes"
+        operation_id = "revokeApiKey"
     )]
-    async fn fetch_one_step_linked_nodes(
+    async fn revoke_api_key(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        page: Query<Option<u64>>,
-        page_size: Query<Option<u64>>,
-        query_str: Query<Option<String>>,
+        id: Path<i64>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> DeleteResponse {
         let pool_arc = pool.clone();
-        let page = page.0;
-        let page_size = page_size.0;
-
-        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to parse query string: {}", e);
-                warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
-            }
-        };
-
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
-            None => {
-                warn!("Query string is empty.");
-                "".to_string()
-            }
-        };
+        let id = id.0;
 
-        let query = if query_str == "" {
+        let owner = if _token.0.is_admin() {
             None
         } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
-                Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
-                    warn!("{}", err);
-                    return GetGraphResponse::bad_request(err);
-                }
-            }
+            Some(_token.0.username.as_str())
         };
-
-        let mut graph = Graph::new();
-        // score DESC is the order_by clause for making the engine generate results with scores which computed by the model.
-        match graph
-            .fetch_linked_nodes(&pool_arc, &query, page, page_size, Some("score DESC"))
-            .await
-        {
-            Ok(graph) => GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap()),
+        match ApiKey::revoke(&pool_arc, id, owner).await {
+            Ok(_) => DeleteResponse::no_content(),
             Err(e) => {
-                let err = format!("Failed to fetch linked nodes: {}", e);
+                let err = format!("Failed to revoke the API key: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                DeleteResponse::not_found(err)
             }
         }
     }
 
-    /// Call `/api/v1/predicted-nodes` with query params to fetch predicted nodes.
+    /// Call `/api/v1/confirmation-tokens` with payload to request a confirmation token for a destructive request, such as `DELETE /api/v1/datasets/ctd`. The token must be sent in the `X-Confirmation-Token` header of the destructive request within 5 minutes, and it can be used only once. Only the admin users can request it.
     #[oai(
-        path = "/predicted-nodes",
-        method = "get",
+        path = "/confirmation-tokens",
+        method = "post",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchPredictedNodes"
+        operation_id = "postConfirmationToken"
     )]
-    async fn fetch_predicted_nodes(
+    async fn post_confirmation_token(
         &self,
+        req: &poem::Request,
         pool: Data<&Arc<sqlx::PgPool>>,
-        node_id: Query<String>,
-        relation_type: Query<String>,
-        query_str: Query<Option<String>>,
-        topk: Query<Option<u64>>,
-        model_name: Query<Option<String>>,
+        payload: Json<ConfirmationTokenRequest>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> PostResponse<ConfirmationToken> {
         let pool_arc = pool.clone();
+        let payload = payload.0;
 
-        match PredictedNodeQuery::new(&node_id.0, &relation_type.0, &query_str.0, topk.0) {
-            Ok(query) => query,
-            Err(e) => {
-                let err = format!("Failed to parse query string: {}", e);
-                warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
-            }
-        };
-
-        let query_str = match query_str.0 {
-            Some(query_str) => query_str,
-            None => {
-                warn!("Query string is empty.");
-                "".to_string()
-            }
-        };
-
-        let topk = topk.0;
-
-        let query = if query_str == "" {
-            None
-        } else {
-            debug!("Query string: {}", &query_str);
-            // Parse query string as json
-            match serde_json::from_str(&query_str) {
-                Ok(query) => Some(query),
-                Err(e) => {
-                    let err = format!("Failed to parse query string: {}", e);
-                    warn!("{}", err);
-                    return GetGraphResponse::bad_request(err);
-                }
-            }
-        };
-
-        let mut graph = Graph::new();
-        match graph
-            .fetch_predicted_nodes(
-                &pool_arc,
-                &node_id,
-                &relation_type,
-                &query,
-                topk,
-                model_name.0,
-            )
-            .await
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can request the confirmation tokens.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        if !is_confirmed_endpoint(&payload.method, &payload.path) {
+            let err = format!(
+                "{} {} is not a destructive request which requires a confirmation token.",
+                payload.method, payload.path
+            );
+            warn!("{}", err);
+            return PostResponse::bad_request(err);
+        }
+
+        let token = match ConfirmationToken::issue(&get_scope(req), &payload.method, &payload.path)
         {
-            Ok(graph) => GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap()),
+            Ok(token) => token,
+            Err(err) => {
+                warn!("{}", err);
+                return PostResponse::bad_request(err);
+            }
+        };
+        match ConfirmationAudit::record_requested(&pool_arc, &token, &_token.0.username).await {
+            Ok(_) => PostResponse::created(token),
             Err(e) => {
-                let err = format!("{}", e);
+                let err = format!("Failed to audit the confirmation token: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return PostResponse::bad_request(err);
             }
         }
     }
 
-    /// Call `/api/v1/shared-nodes` with query params to fetch shared nodes.
+    /// Call `/api/v1/confirmation-audits` to fetch the latest audit records of the destructive requests, including the requested tokens, the executed requests and the rejected requests. Only the admin users can access it.
     #[oai(
-        path = "/shared-nodes",
+        path = "/confirmation-audits",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchSharedNodes"
+        operation_id = "fetchConfirmationAudits"
     )]
-    async fn fetch_shared_nodes(
+    async fn fetch_confirmation_audits(
         &self,
-        pool: Data<&Arc<neo4rs::Graph>>,
-        node_ids: Query<String>,
-        target_node_types: Query<Option<String>>,
-        topk: Query<Option<u64>>,
-        nhops: Query<Option<usize>>,
-        nums_shared_by: Query<Option<u64>>,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        limit: Query<Option<i64>>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> GetWholeTableResponse<ConfirmationAudit> {
         let pool_arc = pool.clone();
-        let node_ids = node_ids.0;
-        let target_node_types = target_node_types.0;
-
-        match NodeIdsQuery::new(&node_ids) {
-            Ok(_) => {}
-            Err(e) => {
-                let err = format!("Failed to validate node ids: {}", e);
-                warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
-            }
-        };
-
-        let graph = Graph::new();
+        let limit = limit.0.unwrap_or(100).clamp(1, 1000);
 
-        if node_ids == "" {
-            return GetGraphResponse::ok(graph);
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the confirmation audits.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetWholeTableResponse::bad_request(err);
         }
 
-        let node_ids: Vec<&str> = node_ids.split(",").collect();
-
-        let target_node_type_vec = match &target_node_types {
-            Some(t) => {
-                // TODO: We need to validate the target_node_types.
-                let target_node_types: Vec<&str> = t.split(",").collect();
-                if target_node_types.len() == 0 {
-                    None
-                } else {
-                    Some(target_node_types)
-                }
-            }
-            None => None,
-        };
-
-        let topk = match topk.0 {
-            Some(topk) => topk,
-            None => 10,
-        };
-
-        let nhops = match nhops.0 {
-            Some(nhops) => nhops,
-            None => 2,
-        };
-
-        let nums_shared_by = match nums_shared_by.0 {
-            Some(nums_shared_by) => nums_shared_by,
-            None => node_ids.len() as u64,
-        };
-
-        let (nodes, edges) = match query_shared_nodes(
-            &pool_arc,
-            &node_ids,
-            target_node_type_vec,
-            nhops as usize,
-            topk as usize,
-            nums_shared_by as usize,
-        )
-        .await
-        {
-            Ok((nodes, edges)) => (nodes, edges),
+        match ConfirmationAudit::get_records(&pool_arc, limit).await {
+            Ok(records) => GetWholeTableResponse::ok(records),
             Err(e) => {
-                let err = format!("Failed to fetch paths: {}", e);
+                let err = format!("Failed to fetch the confirmation audits: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return GetWholeTableResponse::bad_request(err);
             }
-        };
-
-        if nodes.len() == 0 {
-            let err = format!(
-                "No shared nodes found between {:?} with {:?} hops and {:?} node types.",
-                node_ids, nhops, target_node_types
-            );
-            warn!("{}", err);
-            return GetGraphResponse::bad_request(err);
-        };
-
-        let nodes = nodes.iter().collect();
-        let edges = edges.iter().collect();
-        // TODO: How to get the topk paths based on the scores?
-        let graph = Graph::from_data(nodes, edges);
-        GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap())
+        }
     }
 
-    /// Call `/api/v1/paths` with query params to fetch paths.
+    /// Call `/api/v1/audit-logs` with query params to fetch the audit logs of the mutating requests, the latest first. Each log has the user, the changed table and record, the status and the changed fields. Set `query_str` to filter the logs, such as by the `username`, `table_name` and `record_id` fields. Only the admin users can access it.
     #[oai(
-        path = "/paths",
+        path = "/audit-logs",
         method = "get",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "fetchPaths"
+        operation_id = "fetchAuditLogs"
     )]
-    async fn fetch_paths(
+    async fn fetch_audit_logs(
         &self,
-        pool: Data<&Arc<neo4rs::Graph>>,
-        start_node_id: Query<String>,
-        end_node_id: Query<String>,
-        nhops: Query<Option<usize>>,
+        pool: Data<&Arc<sqlx::PgPool>>,
+        page: Query<Option<u64>>,
+        page_size: Query<Option<u64>>,
+        query_str: Query<Option<String>>,
         _token: CustomSecurityScheme,
-    ) -> GetGraphResponse {
+    ) -> GetRecordsResponse<AuditLog> {
         let pool_arc = pool.clone();
-        let start_node_id = start_node_id.0;
-        let end_node_id = end_node_id.0;
-        let nhops = match nhops.0 {
-            Some(nhops) => nhops,
-            None => {
-                warn!("nhops is empty.");
-                2
-            }
-        };
+        let page = page.0;
+        let page_size = page_size.0;
 
-        let (nodes, edges) = match query_nhops(&pool_arc, &start_node_id, &end_node_id, nhops).await
-        {
-            Ok((nodes, edges)) => (nodes, edges),
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can access the audit logs.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return GetRecordsResponse::bad_request(err);
+        }
+
+        match PaginationQuery::new(page.clone(), page_size.clone(), query_str.0.clone()) {
+            Ok(_) => {}
             Err(e) => {
-                let err = format!("Failed to fetch paths: {}", e);
+                let err = format!("Failed to parse query string: {}", e);
                 warn!("{}", err);
-                return GetGraphResponse::bad_request(err);
+                return GetRecordsResponse::bad_request(err);
             }
         };
 
-        if nodes.len() == 0 {
-            let err = format!(
-                "No path found between {} and {} with {} hops.",
-                start_node_id, end_node_id, nhops
-            );
-            warn!("{}", err);
-            return GetGraphResponse::bad_request(err);
+        let query_str = query_str.0.unwrap_or_default();
+        let query = if query_str == "" {
+            None
+        } else {
+            debug!("Query string: {}", &query_str);
+            // Parse query string as json
+            match serde_json::from_str(&query_str) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    let err = format!("Failed to parse query string: {}", e);
+                    warn!("{}", err);
+                    return GetRecordsResponse::bad_request(err);
+                }
+            }
         };
 
-        let nodes = nodes.iter().collect();
-        let edges = edges.iter().collect();
-        // TODO: How to get the topk paths based on the scores?
-        let graph = Graph::from_data(nodes, edges);
-        GetGraphResponse::ok(graph.to_owned().get_graph(None).unwrap())
+        match RecordResponse::<AuditLog>::get_records(
+            &pool_arc,
+            "biomedgps_audit_log",
+            &query,
+            page,
+            page_size,
+            Some("id DESC"),
+        )
+        .await
+        {
+            Ok(records) => GetRecordsResponse::ok(records),
+            Err(e) => {
+                let err = format!("Failed to fetch the audit logs: {}", e);
+                warn!("{}", err);
+                return GetRecordsResponse::bad_request(err);
+            }
+        }
     }
 
-    /// Call `/api/v1/llm` with query params to get answer from LLM.
+    /// Call `/api/v1/datasets/:dataset` to delete all relations of a dataset. It's a destructive request, so a confirmation token from `/api/v1/confirmation-tokens` is required in the `X-Confirmation-Token` header. Only the admin users can delete a dataset.
     #[oai(
-        path = "/llm",
-        method = "post",
+        path = "/datasets/:dataset",
+        method = "delete",
         tag = "ApiTags::KnowledgeGraph",
-        operation_id = "askLLM"
+        operation_id = "deleteDataset"
     )]
-    async fn ask_llm(
+    async fn delete_dataset(
         &self,
         pool: Data<&Arc<sqlx::PgPool>>,
-        prompt_template_id: Query<String>,
-        context: Json<Context>,
+        dataset: Path<String>,
         _token: CustomSecurityScheme,
-    ) -> PostResponse<LlmResponse> {
+    ) -> DeleteResponse {
         let pool_arc = pool.clone();
-        let prompt_template_id = prompt_template_id.0;
-        let context = context.0;
-        debug!("Prompt template id: {}", prompt_template_id);
-        debug!("Context: {:?}", context);
+        let dataset = dataset.0;
 
-        let openai_api_key = match std::env::var("OPENAI_API_KEY") {
-            Ok(openai_api_key) => openai_api_key,
-            Err(e) => {
-                let err = format!("Failed to get OPENAI_API_KEY: {}", e);
+        if !_token.0.is_admin() {
+            let err = format!(
+                "User {} is not an admin user, only the admin users can delete a dataset.",
+                _token.0.username
+            );
+            warn!("{}", err);
+            return DeleteResponse::bad_request(err);
+        }
+
+        match Relation::delete_dataset(&pool_arc, &dataset).await {
+            Ok(0) => {
+                let err = format!("The dataset {} is not found.", dataset);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return DeleteResponse::not_found(err);
+            }
+            Ok(deleted) => {
+                info!(
+                    "{} relations of the dataset {} are deleted.",
+                    deleted, dataset
+                );
+                DeleteResponse::no_content()
             }
-        };
-
-        let chatbot = ChatBot::new("GPT4", &openai_api_key);
-        match context
-            .answer(&chatbot, &prompt_template_id, Some(&pool_arc))
-            .await
-        {
-            Ok(llm_response) => PostResponse::created(llm_response),
             Err(e) => {
-                let err = format!("Failed to get answer from LLM: {}", e);
+                let err = format!("Failed to delete the dataset {}: {}", dataset, e);
                 warn!("{}", err);
-                return PostResponse::bad_request(err);
+                return DeleteResponse::bad_request(err);
             }
         }
     }
@@ -1462,6 +5187,7 @@ impl BiomedgpsApi {
 mod tests {
     use super::*;
     use crate::{init_logger, kv2urlstr, setup_test_db};
+    use crate::testing::TestDatabase;
     use log::{debug, error, LevelFilter};
     use poem::middleware::{AddData, AddDataEndpoint};
     use poem::test::TestClient;
@@ -1585,4 +5311,89 @@ mod tests {
         // let mut records = json.value().deserialize::<Graph>();
         // assert!(records.get_nodes().len() == 10);
     }
+
+    #[tokio::test]
+    async fn test_fetch_entities_with_fixture() {
+        let _ = init_logger("biomedgps-test", LevelFilter::Debug);
+        let db = TestDatabase::with_demo_data().await.unwrap();
+
+        let shared_rb = AddData::new(Arc::new(db.pool.clone()));
+        let service = OpenApiService::new(BiomedgpsApi, "BioMedGPS", "v0.1.0");
+        let cli = TestClient::new(Route::new().nest("/", service).with(shared_rb));
+
+        let resp = cli
+            .get("/api/v1/entities?page=1&page_size=100")
+            .header("Authorization", "Bearer test")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let json = resp.json().await;
+        let entity_records = json.value().deserialize::<RecordResponse<Entity>>();
+        assert_eq!(entity_records.records.len(), 24);
+
+        // The queries fail like the table is dropped during an import.
+        db.break_table("biomedgps_entity").await.unwrap();
+        let resp = cli
+            .get("/api/v1/entities")
+            .header("Authorization", "Bearer test")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        db.restore_table("biomedgps_entity").await.unwrap();
+
+        db.cleanup().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_activities() {
+        let _ = init_logger("biomedgps-test", LevelFilter::Debug);
+        let db = TestDatabase::new().await.unwrap();
+        sqlx::query("INSERT INTO biomedgps_graph_view (name, datasets, owner, updated_at) VALUES ('DRKG + CTD', '{drkg,ctd}', $1, now() - interval '1 day')")
+            .bind(USERNAME_PLACEHOLDER)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO biomedgps_node_tag (node_id, tag, owner) VALUES ('Gene::ENTREZ:1017', 'candidate', $1), ('Gene::ENTREZ:7157', 'candidate', 'someone-else')")
+            .bind(USERNAME_PLACEHOLDER)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let shared_rb = AddData::new(Arc::new(db.pool.clone()));
+        let service = OpenApiService::new(BiomedgpsApi, "BioMedGPS", "v0.1.0");
+        let cli = TestClient::new(Route::new().nest("/", service).with(shared_rb));
+
+        let resp = cli
+            .get("/api/v1/activities")
+            .header("Authorization", "Bearer test")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let json = resp.json().await;
+        let items = json.value().deserialize::<Vec<ActivityFeedItem>>();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].item_type, "node_tag");
+        assert_eq!(items[0].title, "candidate: Gene::ENTREZ:1017");
+        assert_eq!(items[1].item_type, "graph_view");
+
+        let resp = cli
+            .get("/api/v1/activities?item_type=graph_view&page=1&page_size=1")
+            .header("Authorization", "Bearer test")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let json = resp.json().await;
+        let items = json.value().deserialize::<Vec<ActivityFeedItem>>();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "DRKG + CTD");
+
+        let resp = cli
+            .get("/api/v1/activities?page_size=0")
+            .header("Authorization", "Bearer test")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+
+        db.cleanup().await.unwrap();
+    }
 }
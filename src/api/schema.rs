@@ -1,15 +1,19 @@
 use std::collections::HashMap;
 
-use crate::model::core::{RecordResponse, RelationCount, Statistics};
+use crate::model::core::{
+    EntitySearchMatch, GraphConsistencyReport, KeySentenceMatch, RecordResponse, RelationCount,
+    Statistics,
+};
 use crate::model::core::{JSON_REGEX, SUBGRAPH_UUID_REGEX};
-use crate::model::graph::Graph;
+use crate::model::graph::{Graph, SubgraphExtension, MAX_DEGREE_PENALTY};
 use crate::model::graph::{COMPOSED_ENTITIES_REGEX, COMPOSED_ENTITY_REGEX, RELATION_TYPE_REGEX};
 use crate::model::llm::Context;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use log::{debug, info, warn};
+use poem::Body;
 use poem_openapi::Object;
-use poem_openapi::{payload::Json, ApiResponse, Tags};
+use poem_openapi::{payload::Binary, payload::Json, ApiResponse, Tags};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 use validator::ValidationErrors;
@@ -50,6 +54,90 @@ impl GetGraphResponse {
     }
 }
 
+#[derive(ApiResponse)]
+pub enum GetGraphStreamResponse {
+    /// The graph in NDJSON format, each line is a node or an edge. Such as {"type": "node", "data": {...}}.
+    #[oai(status = 200, content_type = "application/x-ndjson")]
+    Ok(Binary<Body>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+
+    #[oai(status = 404)]
+    NotFound(Json<ErrorMessage>),
+}
+
+impl GetGraphStreamResponse {
+    pub fn ok(body: Body) -> Self {
+        Self::Ok(Binary(body))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+
+    pub fn not_found(msg: String) -> Self {
+        Self::NotFound(Json(ErrorMessage { msg }))
+    }
+}
+
+#[derive(ApiResponse)]
+pub enum GetArtifactResponse {
+    /// The artifact of an export job, the filename is in the Content-Disposition header.
+    #[oai(status = 200, content_type = "application/octet-stream")]
+    Ok(Binary<Body>, #[oai(header = "Content-Disposition")] String),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+
+    #[oai(status = 404)]
+    NotFound(Json<ErrorMessage>),
+}
+
+impl GetArtifactResponse {
+    pub fn ok(body: Body, filename: &str) -> Self {
+        Self::Ok(
+            Binary(body),
+            format!("attachment; filename=\"{}\"", filename),
+        )
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+
+    pub fn not_found(msg: String) -> Self {
+        Self::NotFound(Json(ErrorMessage { msg }))
+    }
+}
+
+#[derive(ApiResponse)]
+pub enum GetEntityImageResponse {
+    /// The cached image of an entity, only the png images are cached.
+    #[oai(status = 200, content_type = "image/png")]
+    Ok(Binary<Body>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+
+    #[oai(status = 404)]
+    NotFound(Json<ErrorMessage>),
+}
+
+impl GetEntityImageResponse {
+    pub fn ok(body: Body) -> Self {
+        Self::Ok(Binary(body))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+
+    pub fn not_found(msg: String) -> Self {
+        Self::NotFound(Json(ErrorMessage { msg }))
+    }
+}
+
 #[derive(ApiResponse)]
 pub enum GetEntityColorMapResponse {
     #[oai(status = 200)]
@@ -128,6 +216,89 @@ impl GetStatisticsResponse {
     }
 }
 
+#[derive(ApiResponse)]
+pub enum GetConsistencyReportResponse {
+    #[oai(status = 200)]
+    Ok(Json<GraphConsistencyReport>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+}
+
+impl GetConsistencyReportResponse {
+    pub fn ok(report: GraphConsistencyReport) -> Self {
+        Self::Ok(Json(report))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+}
+
+#[derive(ApiResponse)]
+pub enum GetSubgraphExtensionResponse {
+    #[oai(status = 200)]
+    Ok(Json<SubgraphExtension>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+
+    #[oai(status = 404)]
+    NotFound(Json<ErrorMessage>),
+}
+
+impl GetSubgraphExtensionResponse {
+    pub fn ok(extension: SubgraphExtension) -> Self {
+        Self::Ok(Json(extension))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+
+    pub fn not_found(msg: String) -> Self {
+        Self::NotFound(Json(ErrorMessage { msg }))
+    }
+}
+
+#[derive(ApiResponse)]
+pub enum SearchKeySentencesResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<KeySentenceMatch>>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+}
+
+impl SearchKeySentencesResponse {
+    pub fn ok(matches: Vec<KeySentenceMatch>) -> Self {
+        Self::Ok(Json(matches))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+}
+
+#[derive(ApiResponse)]
+pub enum SearchEntitiesResponse {
+    #[oai(status = 200)]
+    Ok(Json<Vec<EntitySearchMatch>>),
+
+    #[oai(status = 400)]
+    BadRequest(Json<ErrorMessage>),
+}
+
+impl SearchEntitiesResponse {
+    pub fn ok(matches: Vec<EntitySearchMatch>) -> Self {
+        Self::Ok(Json(matches))
+    }
+
+    pub fn bad_request(msg: String) -> Self {
+        Self::BadRequest(Json(ErrorMessage { msg }))
+    }
+}
+
 #[derive(ApiResponse)]
 pub enum GetWholeTableResponse<
     T: Serialize
@@ -240,8 +411,14 @@ pub enum PostResponse<
     #[oai(status = 400)]
     BadRequest(Json<ErrorMessage>),
 
+    #[oai(status = 403)]
+    Forbidden(Json<ErrorMessage>),
+
     #[oai(status = 404)]
     NotFound(Json<ErrorMessage>),
+
+    #[oai(status = 429)]
+    TooManyRequests(Json<ErrorMessage>),
 }
 
 impl<
@@ -267,6 +444,14 @@ impl<
     pub fn not_found(msg: String) -> Self {
         Self::NotFound(Json(ErrorMessage { msg }))
     }
+
+    pub fn forbidden(msg: String) -> Self {
+        Self::Forbidden(Json(ErrorMessage { msg }))
+    }
+
+    pub fn too_many_requests(msg: String) -> Self {
+        Self::TooManyRequests(Json(ErrorMessage { msg }))
+    }
 }
 
 #[derive(ApiResponse)]
@@ -277,6 +462,9 @@ pub enum DeleteResponse {
     #[oai(status = 400)]
     BadRequest(Json<ErrorMessage>),
 
+    #[oai(status = 403)]
+    Forbidden(Json<ErrorMessage>),
+
     #[oai(status = 404)]
     NotFound(Json<ErrorMessage>),
 }
@@ -290,6 +478,10 @@ impl DeleteResponse {
         Self::BadRequest(Json(ErrorMessage { msg }))
     }
 
+    pub fn forbidden(msg: String) -> Self {
+        Self::Forbidden(Json(ErrorMessage { msg }))
+    }
+
     pub fn not_found(msg: String) -> Self {
         Self::NotFound(Json(ErrorMessage { msg }))
     }
@@ -397,6 +589,13 @@ pub struct PredictedNodeQuery {
         message = "Invalid threshold, it must be between 0 and 500"
     ))]
     pub topk: Option<u64>,
+
+    #[validate(range(
+        min = 0.0,
+        max = "MAX_DEGREE_PENALTY",
+        message = "Invalid degree penalty, it must be between 0 and 10"
+    ))]
+    pub degree_penalty: Option<f64>,
 }
 
 impl PredictedNodeQuery {
@@ -405,12 +604,14 @@ impl PredictedNodeQuery {
         relation_type: &str,
         query_str: &Option<String>,
         topk: Option<u64>,
+        degree_penalty: Option<f64>,
     ) -> Result<Self, ValidationErrors> {
         let query = Self {
             node_id: node_id.to_string(),
             relation_type: relation_type.to_string(),
             query_str: query_str.clone(),
             topk,
+            degree_penalty,
         };
 
         match query.validate() {
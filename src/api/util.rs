@@ -0,0 +1,129 @@
+//! Utility functions which are shared by the middlewares and the webhooks, such as matching the endpoint patterns, resolving the client IPs and building the error responses.
+
+use lazy_static::lazy_static;
+use log::warn;
+use poem::http::StatusCode;
+use poem::{Request, Response};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+/// The header which carries the client IP, it's set by the reverse proxy, see `build/biomedgps_nginx.conf`.
+pub const REAL_IP_HEADER: &str = "X-Real-IP";
+
+/// The IPs of the reverse proxies separated by comma, their X-Real-IP headers are trusted. The default is the loopback addresses, because the proxy runs on the same host in the nginx example.
+pub const TRUSTED_PROXIES_ENV: &str = "TRUSTED_PROXIES";
+
+lazy_static! {
+    static ref TRUSTED_PROXIES: Vec<IpAddr> = match std::env::var(TRUSTED_PROXIES_ENV) {
+        Ok(proxies) => proxies
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .filter_map(|x| match x.parse::<IpAddr>() {
+                Ok(ip) => Some(ip),
+                Err(_) => {
+                    warn!("Invalid IP {} in {}, it's ignored.", x, TRUSTED_PROXIES_ENV);
+                    None
+                }
+            })
+            .collect(),
+        Err(_) => vec![
+            IpAddr::from([127, 0, 0, 1]),
+            IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]),
+        ],
+    };
+}
+
+/// The hex digest of the data, such as the hashes of the API keys and the Authorization headers.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::util::sha256_hex;
+///
+/// assert_eq!(
+///     sha256_hex(b"abc"),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// ```
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()
+}
+
+/// Check whether a path matches an endpoint pattern, such as `/api/v1/relations/:id/verification`. A segment starting with `:` matches any non-empty segment.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::util::matches_endpoint;
+///
+/// assert!(matches_endpoint("/api/v1/datasets/:dataset", "/api/v1/datasets/ctd/"));
+/// assert!(!matches_endpoint("/api/v1/datasets/:dataset", "/api/v1/datasets"));
+/// assert!(!matches_endpoint("/api/v1/datasets/:dataset", "/api/v1/datasets/ctd/versions"));
+/// ```
+pub fn matches_endpoint(pattern: &str, path: &str) -> bool {
+    let pattern_segments = pattern
+        .trim_end_matches('/')
+        .split('/')
+        .collect::<Vec<&str>>();
+    let path_segments = path.trim_end_matches('/').split('/').collect::<Vec<&str>>();
+
+    pattern_segments.len() == path_segments.len()
+        && pattern_segments
+            .iter()
+            .zip(path_segments.iter())
+            .all(|(p, s)| (p.starts_with(':') && !s.is_empty()) || p == s)
+}
+
+/// Log the message and return it as a JSON response, such as `{"msg": "..."}`. It's used by the middlewares which reject a request before it reaches the endpoints.
+pub fn error_response(status: StatusCode, msg: &str) -> Response {
+    warn!("{}", msg);
+    Response::builder()
+        .status(status)
+        .content_type("application/json")
+        .body(serde_json::json!({ "msg": msg }).to_string())
+}
+
+/// Resolve the IP of a client from the peer address. The X-Real-IP header is only used when the peer is a trusted proxy, otherwise any client could choose its own IP.
+///
+/// # Example
+/// ```
+/// use biomedgps::api::util::resolve_client_ip;
+/// use std::net::IpAddr;
+///
+/// let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+/// let client: IpAddr = "10.0.0.8".parse().unwrap();
+/// assert_eq!(resolve_client_ip(Some(proxy), Some("10.0.0.8"), &[proxy]), Some(client));
+/// assert_eq!(resolve_client_ip(Some(proxy), Some("unknown"), &[proxy]), Some(proxy));
+/// assert_eq!(resolve_client_ip(Some(client), Some("10.0.0.9"), &[proxy]), Some(client));
+/// assert_eq!(resolve_client_ip(None, Some("10.0.0.9"), &[proxy]), None);
+/// ```
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    real_ip: Option<&str>,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if trusted_proxies.contains(&peer) {
+        if let Some(ip) = real_ip.and_then(|v| v.trim().parse::<IpAddr>().ok()) {
+            return Some(ip);
+        }
+    }
+
+    Some(peer)
+}
+
+/// The IP of the client which sent the request, the port is not a part of it, so a client has the same IP for all its connections. See [`resolve_client_ip`] for the reverse proxies.
+pub fn client_ip(req: &Request) -> String {
+    let peer = req.remote_addr().as_socket_addr().map(|addr| addr.ip());
+    let real_ip = req
+        .headers()
+        .get(REAL_IP_HEADER)
+        .and_then(|v| v.to_str().ok());
+
+    match resolve_client_ip(peer, real_ip, &TRUSTED_PROXIES) {
+        Some(ip) => ip.to_string(),
+        None => req.remote_addr().to_string(),
+    }
+}
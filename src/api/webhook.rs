@@ -0,0 +1,98 @@
+//! The webhooks which are called by the external services instead of the users, so they are authenticated by the signatures instead of the JWT tokens.
+//!
+//! They are plain poem handlers instead of OpenAPI endpoints, because the signature is computed from the raw body.
+
+use crate::api::util::error_response;
+use crate::model::import_job::ImportJob;
+use crate::model::registry::{
+    verify_signature, DatasetPublishedEvent, REGISTRY_WEBHOOK_SECRET_ENV,
+};
+use crate::DatabaseUrl;
+use chrono::Utc;
+use log::info;
+use poem::http::StatusCode;
+use poem::web::{Data, Json};
+use poem::{handler, IntoResponse, Request, Response};
+use std::sync::Arc;
+
+/// The header which carries the signature of the body, such as `sha256=<hex digest>`.
+pub const REGISTRY_SIGNATURE_HEADER: &str = "X-Registry-Signature";
+
+/// The header which carries the unix time when the delivery is sent, it's signed with the body.
+pub const REGISTRY_TIMESTAMP_HEADER: &str = "X-Registry-Timestamp";
+
+/// Call `/webhooks/data-registry` when a new version of a dataset is published to the data registry. The files will be downloaded, validated and imported in the background, and the `callback_url` in the event will be notified with the import job when it's finished.
+#[handler]
+pub async fn data_registry_webhook(
+    req: &Request,
+    body: Vec<u8>,
+    pool: Data<&Arc<sqlx::PgPool>>,
+    database_url: Data<&DatabaseUrl>,
+) -> Response {
+    let secret = match std::env::var(REGISTRY_WEBHOOK_SECRET_ENV) {
+        Ok(secret) if !secret.is_empty() => secret,
+        _ => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                &format!(
+                    "The data registry webhook is disabled, please set the {} environment variable to enable it.",
+                    REGISTRY_WEBHOOK_SECRET_ENV
+                ),
+            )
+        }
+    };
+
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+    };
+    let signature = header(REGISTRY_SIGNATURE_HEADER);
+    if !verify_signature(
+        &secret,
+        header(REGISTRY_TIMESTAMP_HEADER),
+        &body,
+        signature,
+        Utc::now().timestamp(),
+    ) {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            "The signature of the webhook payload is invalid or expired.",
+        );
+    }
+
+    let event = match serde_json::from_slice::<DatasetPublishedEvent>(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Failed to parse the event: {}", e),
+            )
+        }
+    };
+
+    if let Err(e) = event.validate() {
+        return error_response(StatusCode::BAD_REQUEST, &format!("Invalid event: {}", e));
+    }
+
+    // The signature is unique for each delivery, so it identifies the repeated deliveries. The hex digest is case-insensitive.
+    let delivery_id = signature.to_lowercase();
+    match ImportJob::schedule_reimport(&pool, database_url.as_str(), &delivery_id, &event).await {
+        Ok(Some(job)) => {
+            info!(
+                "Schedule the import job {} for the dataset {} ({}).",
+                job.id, event.dataset, event.version
+            );
+            (StatusCode::ACCEPTED, Json(job)).into_response()
+        }
+        Ok(None) => error_response(
+            StatusCode::CONFLICT,
+            "The delivery has been received, it's not imported again.",
+        ),
+        Err(e) => error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Failed to schedule the import job: {}", e),
+        ),
+    }
+}
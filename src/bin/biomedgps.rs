@@ -3,12 +3,35 @@ extern crate log;
 #[macro_use]
 extern crate lazy_static;
 
-use biomedgps::api::auth::fetch_and_store_jwks;
+use biomedgps::api::api_key::ApiKeyAuth;
+use biomedgps::api::audit::AuditLogger;
+use biomedgps::api::auth::{fetch_and_store_jwks, ScopeCheck};
+use biomedgps::api::confirmation::DestructiveConfirmation;
+use biomedgps::api::maintenance::ImportMaintenance;
+use biomedgps::api::idempotency::{
+    cleanup_expired_idempotency_keys, Idempotency, IdempotencyConfig,
+    IDEMPOTENCY_CLEANUP_INTERVAL_SECS,
+};
+use biomedgps::api::public::{PublicMode, PublicModeConfig};
+use biomedgps::api::rate_limit::{RateLimit, RateLimitConfig};
 use biomedgps::api::route::BiomedgpsApi;
-use biomedgps::model::core::EntityMetadata;
+use biomedgps::api::webhook::data_registry_webhook;
+use biomedgps::model::backup::run_scheduled_backup;
+use biomedgps::model::core::{
+    listen_metadata_updates, EntityMetadata, TrendingEntity, DEFAULT_NUM_TRENDING_ENTITIES,
+};
+use biomedgps::model::export::{ExportJob, EXPORT_CLEANUP_INTERVAL_SECS};
+use biomedgps::model::import_job::ImportJob;
 use biomedgps::model::kge::init_kge_models;
-use biomedgps::model::util::update_existing_colors;
-use biomedgps::{check_db_version, connect_db, connect_graph_db, init_logger};
+use biomedgps::model::schedule::{
+    get_task_schedule, register_task, stop_all_tasks, ScheduledTaskRun, TaskSchedule,
+};
+use biomedgps::model::util::{update_entity_metadata, update_existing_colors};
+use biomedgps::{
+    check_db_version, connect_db_with_config, connect_graph_db, get_pool_stats, init_logger,
+    register_pool, DatabaseUrl, PoolConfig, DB_POOL_MONITOR_INTERVAL_SECS,
+};
+use chrono::Utc;
 use dotenv::dotenv;
 use itertools::Itertools;
 use log::LevelFilter;
@@ -21,6 +44,7 @@ use poem::{
     http::{header, Method, StatusCode},
     listener::TcpListener,
     middleware::Cors,
+    post,
     web::Redirect,
     Endpoint, Request, Response, Result, Route, Server,
 };
@@ -30,6 +54,9 @@ use std::sync::Arc;
 
 use structopt::StructOpt;
 
+/// How long the server waits for the running requests when it's shutting down.
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
 /// BioMedGPS backend server.
 #[derive(Debug, PartialEq, StructOpt)]
 #[structopt(setting=structopt::clap::AppSettings::ColoredHelp, name="biomedgps", author="Jingcheng Yang <yjcyxky@163.com>")]
@@ -61,10 +88,12 @@ struct Opt {
 
     /// Database url, such as postgres:://user:pass@host:port/dbname.
     /// You can also set it with env var: DATABASE_URL.
+    /// The pool of the API requests is configured by the env vars API_DB_POOL_MAX_CONNECTIONS, API_DB_POOL_MIN_CONNECTIONS, API_DB_POOL_ACQUIRE_TIMEOUT_SECS, API_DB_POOL_IDLE_TIMEOUT_SECS and API_DB_POOL_MAX_LIFETIME_SECS, and the pools of the background jobs by the same env vars with the JOB_DB_POOL prefix.
     #[structopt(name = "database-url", short = "d", long = "database-url")]
     database_url: Option<String>,
 
     /// Graph Database url, such as neo4j:://user:pass@host:port. We will always use the default database.
+    /// Use neo4j+s or bolt+s for the TLS connections, and set max_connections, fetch_size or connect_timeout by query parameters.
     /// You can also set it with env var: NEO4J_URL.
     #[structopt(name = "neo4j-url", short = "g", long = "neo4j-url")]
     neo4j_url: Option<String>,
@@ -86,6 +115,21 @@ struct Opt {
     /// If you don't set it, the server will disable JWT verification with RS256 algorithm. You can use the API with Authorization header and set it to any value.
     #[structopt(name = "jwt-jwks-url", short = "j", long = "jwt-jwks-url")]
     jwt_jwks_url: Option<String>,
+
+    /// Activate public mode, the anonymous users can access the whitelisted read-only endpoints without the Authorization header.
+    /// You can also set it with env var: PUBLIC_MODE=true.
+    #[structopt(name = "public-mode", long = "public-mode")]
+    public_mode: bool,
+
+    /// The whitelisted endpoints for the public mode, separated by comma. e.g. /api/v1/entities,/api/v1/subgraphs.
+    /// You can also set it with env var: PUBLIC_ENDPOINTS. If you don't set it, the default read-only endpoints will be used.
+    #[structopt(name = "public-endpoints", long = "public-endpoints")]
+    public_endpoints: Option<String>,
+
+    /// The maximum number of requests per minute for each anonymous client in the public mode.
+    /// You can also set it with env var: PUBLIC_RATE_LIMIT.
+    #[structopt(name = "public-rate-limit", long = "public-rate-limit")]
+    public_rate_limit: Option<u32>,
 }
 
 #[derive(RustEmbed)]
@@ -238,9 +282,17 @@ async fn main() -> Result<(), std::io::Error> {
         database_url.unwrap()
     };
 
-    let pool = connect_db(&database_url, 10).await;
+    let api_pool_config = PoolConfig::api();
+    let pool = connect_db_with_config(&database_url, &api_pool_config).await;
+    register_pool("api", &pool, &api_pool_config);
+    info!(
+        "The database pool of the API requests: {:?}",
+        api_pool_config
+    );
     let arc_pool = Arc::new(pool);
     let shared_rb = AddData::new(arc_pool.clone());
+    // The import jobs triggered by the data registry webhook and the export jobs need the database url.
+    let shared_database_url = AddData::new(DatabaseUrl(database_url.clone()));
 
     // Check the environment, such as database version.
     match check_db_version(&arc_pool.clone()).await {
@@ -274,6 +326,171 @@ async fn main() -> Result<(), std::io::Error> {
         }
     };
 
+    // The maintenance tasks use their own pool, so they never compete with the API requests for the connections.
+    let maintenance_pool_config = PoolConfig::job();
+    let maintenance_pool = connect_db_with_config(&database_url, &maintenance_pool_config).await;
+    register_pool("maintenance", &maintenance_pool, &maintenance_pool_config);
+
+    // The runs which were running when the server stopped never finish.
+    match ScheduledTaskRun::fail_interrupted(&maintenance_pool).await {
+        Ok(0) => {}
+        Ok(n) => warn!(
+            "{} scheduled task runs were interrupted by the last shutdown.",
+            n
+        ),
+        Err(err) => error!("Mark the interrupted scheduled task runs failed, {}", err),
+    }
+
+    // The import jobs which were running when the server stopped need to be resumed by the admins.
+    match ImportJob::fail_interrupted(&maintenance_pool).await {
+        Ok(0) => {}
+        Ok(n) => warn!(
+            "{} import jobs were interrupted by the last shutdown, resume the admin jobs to continue.",
+            n
+        ),
+        Err(err) => error!("Mark the interrupted import jobs failed, {}", err),
+    }
+
+    // Remove the expired artifacts of the export jobs periodically.
+    let cleanup_pool = maintenance_pool.clone();
+    register_task(
+        &maintenance_pool,
+        "cleanup-export-artifacts",
+        get_task_schedule(
+            "EXPORT_CLEANUP_SCHEDULE",
+            TaskSchedule::Interval(std::time::Duration::from_secs(EXPORT_CLEANUP_INTERVAL_SECS)),
+        ),
+        move || {
+            let pool = cleanup_pool.clone();
+            async move {
+                let n = ExportJob::cleanup_expired(&pool).await?;
+                Ok(if n > 0 {
+                    format!("remove the artifacts of {} expired export jobs.", n)
+                } else {
+                    String::new()
+                })
+            }
+        },
+    );
+
+    // Remove the expired idempotency keys periodically.
+    let cleanup_pool = maintenance_pool.clone();
+    register_task(
+        &maintenance_pool,
+        "cleanup-idempotency-keys",
+        get_task_schedule(
+            "IDEMPOTENCY_CLEANUP_SCHEDULE",
+            TaskSchedule::Interval(std::time::Duration::from_secs(
+                IDEMPOTENCY_CLEANUP_INTERVAL_SECS,
+            )),
+        ),
+        move || {
+            let pool = cleanup_pool.clone();
+            async move {
+                let n = cleanup_expired_idempotency_keys(&pool).await?;
+                Ok(if n > 0 {
+                    format!("remove {} expired idempotency keys.", n)
+                } else {
+                    String::new()
+                })
+            }
+        },
+    );
+
+    // The nightly tasks are opt-in, such as TRENDING_ENTITIES_REFRESH_SCHEDULE="0 3 * * *". They are usually run by the cli after an import.
+    if let Ok(schedule) = std::env::var("TRENDING_ENTITIES_REFRESH_SCHEDULE") {
+        let task_pool = maintenance_pool.clone();
+        match TaskSchedule::parse(&schedule) {
+            Ok(schedule) => register_task(
+                &maintenance_pool,
+                "refresh-trending-entities",
+                schedule,
+                move || {
+                    let pool = task_pool.clone();
+                    async move {
+                        // Only the last two weeks are recomputed, the older weeks don't change.
+                        let since = Utc::now() - chrono::Duration::weeks(1);
+                        let n = TrendingEntity::refresh(
+                            &pool,
+                            Some(since),
+                            DEFAULT_NUM_TRENDING_ENTITIES,
+                        )
+                        .await?;
+                        Ok(format!("{} entities are ranked.", n))
+                    }
+                },
+            ),
+            Err(err) => error!("Invalid TRENDING_ENTITIES_REFRESH_SCHEDULE, {}", err),
+        }
+    }
+
+    // The backups of the curated tables are opt-in, such as BACKUP_SCHEDULE="30 2 * * *", see the backup module for the directory, the retention and the notification url.
+    if let Ok(schedule) = std::env::var("BACKUP_SCHEDULE") {
+        let task_pool = maintenance_pool.clone();
+        match TaskSchedule::parse(&schedule) {
+            Ok(schedule) => register_task(
+                &maintenance_pool,
+                "backup-curated-tables",
+                schedule,
+                move || {
+                    let pool = task_pool.clone();
+                    async move { run_scheduled_backup(&pool).await }
+                },
+            ),
+            Err(err) => error!("Invalid BACKUP_SCHEDULE, {}", err),
+        }
+    }
+
+    if let Ok(schedule) = std::env::var("ENTITY_METADATA_REFRESH_SCHEDULE") {
+        let task_pool = maintenance_pool.clone();
+        match TaskSchedule::parse(&schedule) {
+            Ok(schedule) => register_task(
+                &maintenance_pool,
+                "refresh-entity-metadata",
+                schedule,
+                move || {
+                    let pool = task_pool.clone();
+                    async move {
+                        update_entity_metadata(&pool, true)
+                            .await
+                            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+                        Ok(String::new())
+                    }
+                },
+            ),
+            Err(err) => error!("Invalid ENTITY_METADATA_REFRESH_SCHEDULE, {}", err),
+        }
+    }
+
+    // The metadata endpoints are cached in memory, the caches are cleared when the metadata tables are updated by any process, such as importdb.
+    let listener_pool = maintenance_pool.clone();
+    tokio::spawn(async move {
+        if let Err(err) = listen_metadata_updates(&listener_pool).await {
+            error!(
+                "Failed to listen to the metadata updates, the metadata caches are only refreshed by their TTL. {}",
+                err
+            );
+        }
+    });
+
+    // Warn the operators when a pool is saturated, the requests are waiting for the connections then.
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            DB_POOL_MONITOR_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            for stats in get_pool_stats() {
+                if stats.in_use >= stats.max_connections {
+                    warn!(
+                        "The database pool {} is saturated, {} of {} connections are in use. Increase the max connections if it happens frequently.",
+                        stats.name, stats.in_use, stats.max_connections
+                    );
+                }
+            }
+        }
+    });
+
     // Connect to graph database.
     let neo4j_url = args.neo4j_url;
     let _neo4j_url = if neo4j_url.is_none() {
@@ -326,21 +543,118 @@ async fn main() -> Result<(), std::io::Error> {
         route
     };
 
+    let public_mode = args.public_mode
+        || match std::env::var("PUBLIC_MODE") {
+            Ok(v) => v == "true" || v == "1",
+            Err(_) => false,
+        };
+
+    let public_mode_config = if public_mode {
+        let public_endpoints = match args.public_endpoints {
+            Some(v) => Some(v),
+            None => std::env::var("PUBLIC_ENDPOINTS").ok(),
+        };
+        let public_rate_limit = match args.public_rate_limit {
+            Some(v) => Some(v),
+            None => match std::env::var("PUBLIC_RATE_LIMIT") {
+                Ok(v) => match v.parse::<u32>() {
+                    Ok(v) => Some(v),
+                    Err(_) => {
+                        error!("PUBLIC_RATE_LIMIT should be a positive integer.");
+                        std::process::exit(1);
+                    }
+                },
+                Err(_) => None,
+            },
+        };
+
+        let config = PublicModeConfig::new(public_endpoints.as_deref(), public_rate_limit);
+        info!(
+            "Public mode is enabled. Anonymous users can access {:?} with {} requests per minute.",
+            config.endpoints, config.rate_limit
+        );
+        Some(config)
+    } else {
+        None
+    };
+
     let route = route
         .nest_no_strip("/api/v1", api_service)
+        .at("/webhooks/data-registry", post(data_registry_webhook))
+        // The replayed responses of the idempotency keys are not audited again.
+        .with(AuditLogger::new(arc_pool.clone()))
+        .with(Idempotency::new(
+            arc_pool.clone(),
+            IdempotencyConfig::from_env(),
+        ))
+        .with(DestructiveConfirmation::new(arc_pool.clone()))
+        // The writes are rejected before they consume the confirmation tokens during a destructive import.
+        .with(ImportMaintenance::new(arc_pool.clone()))
         .with(shared_rb)
-        .with(shared_graph_pool);
-
-    if args.cors {
+        .with(shared_database_url)
+        .with(shared_graph_pool)
+        // The scopes are checked before the idempotency keys and the confirmation tokens are consumed.
+        .with(ScopeCheck)
+        // The users are identified after the API keys and the public mode are resolved.
+        .with(RateLimit::new(RateLimitConfig::from_env()))
+        .with_if(
+            public_mode,
+            PublicMode::new(public_mode_config.unwrap_or_default()),
+        )
+        // The requests with the API keys get the Authorization header here, so they are never mapped to the anonymous persona.
+        .with(ApiKeyAuth::new(arc_pool.clone()));
+
+    // The server stops accepting the requests on SIGINT or SIGTERM, and waits for the running requests.
+    let timeout = Some(std::time::Duration::from_secs(SHUTDOWN_TIMEOUT_SECS));
+    let result = if args.cors {
         info!("CORS mode is enabled.");
         let route = route.with(Cors::new().allow_origin("*"));
         Server::new(TcpListener::bind(format!("{}:{}", host, port)))
-            .run(route)
+            .run_with_graceful_shutdown(route, shutdown_signal(), timeout)
             .await
     } else {
         warn!("CORS mode is disabled. If you need the CORS, please use `--cors` flag.");
         Server::new(TcpListener::bind(format!("{}:{}", host, port)))
-            .run(route)
+            .run_with_graceful_shutdown(route, shutdown_signal(), timeout)
             .await
+    };
+
+    // The maintenance tasks are stopped after the server, so their running runs are recorded before the pools are closed.
+    info!("Stop the maintenance tasks.");
+    stop_all_tasks().await;
+    maintenance_pool.close().await;
+
+    result
+}
+
+/// Wait for SIGINT (Ctrl-C) or SIGTERM (such as `docker stop`).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            futures::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                futures::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = futures::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
     }
+    info!("Shutting down the server.");
 }
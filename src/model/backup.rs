@@ -0,0 +1,321 @@
+//! Scheduled logical backups of the tables which can't be rebuilt from the datasets, such as the curated knowledges, the subgraphs and the workspaces of the users.
+//!
+//! Each backup is a zip file in the backup directory, it contains a tsv file per table (same format as the exportdb command, so it can be imported again) and a manifest with the row counts. The backup is verified by counting the rows in the zip file again, the old backups are rotated, and the result is posted to the notification url if it's set.
+
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// The directory which stores the backups, it should be a mounted volume or a synced directory of the storage backend.
+pub const BACKUP_DIR_ENV: &str = "BACKUP_DIR";
+
+/// How many backups are kept, the older ones are removed after a new backup is verified.
+pub const BACKUP_RETENTION_ENV: &str = "BACKUP_RETENTION";
+pub const DEFAULT_BACKUP_RETENTION: usize = 7;
+
+/// The url which the backup reports are posted to, such as a Slack incoming webhook or an alerting gateway.
+pub const BACKUP_NOTIFY_URL_ENV: &str = "BACKUP_NOTIFY_URL";
+
+/// The prefix of the backup files, such as `backup-20240402T030000.zip`. Only the files with the prefix are rotated.
+pub const BACKUP_FILE_PREFIX: &str = "backup-";
+
+/// The backed up tables, each one is a tuple of the file name in the zip and the table name.
+pub const BACKUP_TABLES: [(&str, &str); 6] = [
+    ("knowledge_curations", "biomedgps_knowledge_curation"),
+    ("relation_verifications", "biomedgps_relation_verification"),
+    ("subgraphs", "biomedgps_subgraph"),
+    ("node_tags", "biomedgps_node_tag"),
+    ("graph_views", "biomedgps_graph_view"),
+    ("dataset_licenses", "biomedgps_dataset_license"),
+];
+
+/// Get the directory which stores the backups.
+pub fn get_backup_dir() -> PathBuf {
+    match std::env::var(BACKUP_DIR_ENV) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir().join("biomedgps-backups"),
+    }
+}
+
+fn get_backup_retention() -> usize {
+    std::env::var(BACKUP_RETENTION_ENV)
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_BACKUP_RETENTION)
+}
+
+/// The result of a verified backup.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupReport {
+    pub filepath: String,
+    pub size: u64,
+    // The number of rows of each table, they are verified in the zip file.
+    pub counts: BTreeMap<String, i64>,
+    // The old backups which are removed by the rotation.
+    pub removed: Vec<String>,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl BackupReport {
+    /// A short summary of the backup, it's recorded as the message of the scheduled task run.
+    pub fn summary(&self) -> String {
+        format!(
+            "backup {} rows into {} ({} bytes), {} old backups are removed.",
+            self.counts.values().sum::<i64>(),
+            self.filepath,
+            self.size,
+            self.removed.len()
+        )
+    }
+}
+
+/// Back up the tables into a zip file in the backup directory, verify it and rotate the old backups.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `backup_dir` - The directory which stores the backups.
+/// * `retention` - How many backups are kept, including the new one.
+///
+/// # Returns
+/// * `BackupReport` - The row counts of the tables and the removed backups. An unverified backup is removed, and an error is returned.
+pub async fn run_backup(
+    pool: &sqlx::PgPool,
+    backup_dir: &Path,
+    retention: usize,
+) -> Result<BackupReport, anyhow::Error> {
+    std::fs::create_dir_all(backup_dir)?;
+    let created_at = Utc::now();
+    let filepath = backup_dir.join(format!(
+        "{}{}.zip",
+        BACKUP_FILE_PREFIX,
+        created_at.format("%Y%m%dT%H%M%S")
+    ));
+    // The partial file is never rotated or restored, it's renamed after it's verified.
+    let part_filepath = backup_dir.join(format!(
+        ".{}.part",
+        filepath.file_name().unwrap().to_string_lossy()
+    ));
+
+    let counts = match write_backup(pool, &part_filepath, &created_at).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            let _ = std::fs::remove_file(&part_filepath);
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = verify_backup(&part_filepath, &counts) {
+        let _ = std::fs::remove_file(&part_filepath);
+        return Err(e);
+    }
+    std::fs::rename(&part_filepath, &filepath)?;
+
+    let removed = rotate_backups(backup_dir, retention)?;
+    let report = BackupReport {
+        filepath: filepath.to_string_lossy().to_string(),
+        size: std::fs::metadata(&filepath)?.len(),
+        counts,
+        removed: removed
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        created_at,
+    };
+    info!("{}", report.summary());
+
+    Ok(report)
+}
+
+/// Write all rows of the tables into a zip file, the tables are read in the same transaction, so they are consistent with each other.
+async fn write_backup(
+    pool: &sqlx::PgPool,
+    filepath: &Path,
+    created_at: &DateTime<Utc>,
+) -> Result<BTreeMap<String, i64>, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut tx)
+        .await?;
+
+    let mut zip = ZipWriter::new(File::create(filepath)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut counts = BTreeMap::new();
+    for (name, table) in BACKUP_TABLES.iter() {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&mut tx)
+            .await?;
+
+        zip.start_file(format!("{}.tsv", name), options)?;
+        let mut stream = tx
+            .copy_out_raw(&format!(
+                "COPY (SELECT * FROM {} ORDER BY 1) TO STDOUT WITH (FORMAT csv, DELIMITER E'\\t', HEADER)",
+                table
+            ))
+            .await?;
+        while let Some(chunk) = stream.next().await {
+            zip.write_all(&chunk?)?;
+        }
+        drop(stream);
+
+        counts.insert(name.to_string(), count);
+    }
+
+    zip.start_file("manifest.json", options)?;
+    let tables = BACKUP_TABLES
+        .iter()
+        .map(|(name, table)| (name.to_string(), table.to_string()))
+        .collect::<BTreeMap<String, String>>();
+    let manifest = json!({
+        "created_at": created_at.to_rfc3339(),
+        "tables": tables,
+        "counts": counts,
+    });
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    tx.rollback().await?;
+
+    Ok(counts)
+}
+
+/// Count the rows of each table in a backup file again, they must be same as the counts in the database.
+pub fn verify_backup(filepath: &Path, counts: &BTreeMap<String, i64>) -> Result<(), anyhow::Error> {
+    let mut archive = ZipArchive::new(File::open(filepath)?)?;
+    for (name, expected) in counts.iter() {
+        let file = archive.by_name(&format!("{}.tsv", name))?;
+        let mut reader = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(file);
+        let mut count: i64 = 0;
+        for record in reader.byte_records() {
+            record?;
+            count += 1;
+        }
+
+        if count != *expected {
+            return Err(anyhow::anyhow!(
+                "The backup {} is broken, {} has {} rows but {} rows are expected.",
+                filepath.display(),
+                name,
+                count,
+                expected
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the oldest backups in the directory and keep the latest `retention` ones. The backups are ordered by the timestamps in their names.
+pub fn rotate_backups(backup_dir: &Path, retention: usize) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut backups = std::fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .map_or(false, |name| {
+                    name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(".zip")
+                })
+        })
+        .collect::<Vec<PathBuf>>();
+    backups.sort();
+
+    let mut removed = vec![];
+    let num_removed = backups.len().saturating_sub(retention);
+    for path in backups.into_iter().take(num_removed) {
+        std::fs::remove_file(&path)?;
+        info!("Remove the old backup {}.", path.display());
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
+/// Post the result of a backup to the notification url, the failure of the notification is only logged.
+pub async fn notify_backup(url: &str, result: &Result<BackupReport, anyhow::Error>) {
+    let payload = match result {
+        Ok(report) => json!({ "status": "succeeded", "text": report.summary(), "report": report }),
+        Err(e) => json!({ "status": "failed", "text": format!("The backup failed: {}", e) }),
+    };
+
+    let client = reqwest::Client::new();
+    match client.post(url).json(&payload).send().await {
+        Ok(response) if response.status().is_success() => {
+            info!("Notify {} of the backup.", url);
+        }
+        Ok(response) => {
+            warn!(
+                "Failed to notify {} of the backup: {}",
+                url,
+                response.status()
+            );
+        }
+        Err(e) => {
+            warn!("Failed to notify {} of the backup: {}", url, e);
+        }
+    }
+}
+
+/// Run a backup with the settings from the environment variables, it's called by the scheduled task of the server. The result is also returned, so it's recorded as a scheduled task run.
+pub async fn run_scheduled_backup(pool: &sqlx::PgPool) -> Result<String, anyhow::Error> {
+    let result = run_backup(pool, &get_backup_dir(), get_backup_retention()).await;
+    if let Ok(url) = std::env::var(BACKUP_NOTIFY_URL_ENV) {
+        if !url.is_empty() {
+            notify_backup(&url, &result).await;
+        }
+    }
+
+    result.map(|report| report.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDatabase;
+
+    #[test]
+    fn test_rotate_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "backup-20240401T030000.zip",
+            "backup-20240402T030000.zip",
+            "backup-20240403T030000.zip",
+            ".backup-20240404T030000.zip.part",
+            "other.zip",
+        ] {
+            File::create(dir.path().join(name)).unwrap();
+        }
+
+        let removed = rotate_backups(dir.path(), 2).unwrap();
+        assert_eq!(removed, vec![dir.path().join("backup-20240401T030000.zip")]);
+        assert!(dir.path().join("backup-20240403T030000.zip").exists());
+        assert!(dir.path().join(".backup-20240404T030000.zip.part").exists());
+        assert!(dir.path().join("other.zip").exists());
+    }
+
+    #[tokio::test]
+    async fn test_run_backup() {
+        let db = TestDatabase::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let report = run_backup(&db.pool, dir.path(), 1).await.unwrap();
+        assert_eq!(report.counts.len(), BACKUP_TABLES.len());
+        assert!(PathBuf::from(&report.filepath).exists());
+        verify_backup(&PathBuf::from(&report.filepath), &report.counts).unwrap();
+
+        // A wrong count is detected.
+        let mut counts = report.counts.clone();
+        *counts.get_mut("subgraphs").unwrap() += 1;
+        assert!(verify_backup(&PathBuf::from(&report.filepath), &counts).is_err());
+
+        db.cleanup().await.unwrap();
+    }
+}
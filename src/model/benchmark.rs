@@ -0,0 +1,393 @@
+//! This module is used to benchmark the prioritization methods on the disease-gene association recovery task, so the models and the algorithm changes can be compared.
+//!
+//! Each known disease-gene relation is held out in turn (leave-one-out), the held-out gene is ranked against the genes which are not linked to the disease by each method (KGE scores, RWR and shared neighbors). The metrics are averaged over all cases and stored in the benchmark result table, they are shown in the models listing page.
+
+use crate::algorithm::benchmark::{
+    auprc, auroc, recall_at_k, AdjacencyGraph, DEFAULT_RESTART_PROB, DEFAULT_RWR_STEPS,
+};
+use crate::model::graph::Graph;
+use crate::model::kge::{
+    get_embedding_metadata, get_entity_emb_table_name, get_relation_emb_table_name,
+    EmbeddingMetadata,
+};
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+pub const DISEASE_GENE_TASK: &str = "disease_gene_loo";
+
+/// How many relations are read from the database at a time when building the graph.
+const BENCHMARK_BATCH_SIZE: i64 = 10000;
+
+/// The methods which are benchmarked.
+pub const BENCHMARK_METHODS: [&str; 3] = ["kge", "rwr", "shared_neighbors"];
+
+/// The averaged metrics of a method on a benchmark task.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct BenchmarkResult {
+    pub id: i64,
+    // The KGE model used by the kge method. The graph-based methods don't depend on it, but they are stored with the same model for the comparison.
+    pub model_name: String,
+    pub task: String,
+    // One of kge, rwr and shared_neighbors.
+    pub method: String,
+    // The relation type of the disease-gene relations, such as GNBR::J::Gene:Disease.
+    pub relation_type: String,
+    // How many held-out relations are evaluated.
+    pub num_cases: i32,
+    pub top_k: i32,
+    pub auroc: f64,
+    pub auprc: f64,
+    pub recall_at_k: f64,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl BenchmarkResult {
+    pub async fn insert(&self, pool: &sqlx::PgPool) -> Result<BenchmarkResult, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_benchmark_result (model_name, task, method, relation_type, num_cases, top_k, auroc, auprc, recall_at_k) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *";
+        let result = sqlx::query_as::<_, BenchmarkResult>(sql_str)
+            .bind(&self.model_name)
+            .bind(&self.task)
+            .bind(&self.method)
+            .bind(&self.relation_type)
+            .bind(self.num_cases)
+            .bind(self.top_k)
+            .bind(self.auroc)
+            .bind(self.auprc)
+            .bind(self.recall_at_k)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(result)
+    }
+
+    /// List the benchmark results, the newest first. Only the results of the model are returned if the model name is set.
+    pub async fn list(
+        pool: &sqlx::PgPool,
+        model_name: Option<&str>,
+    ) -> Result<Vec<BenchmarkResult>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_benchmark_result WHERE $1::TEXT IS NULL OR model_name = $1 ORDER BY id DESC";
+        let results = sqlx::query_as::<_, BenchmarkResult>(sql_str)
+            .bind(model_name)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(results)
+    }
+}
+
+/// The disease-gene association recovery task. The relations of the relation type are the known associations, the genes in these relations are the candidates.
+pub struct DiseaseGeneBenchmark {
+    pub model_name: String,
+    pub relation_type: String,
+    pub max_cases: usize,
+    pub num_negatives: usize,
+    pub top_k: usize,
+}
+
+/// The accumulated per-case metrics of a method.
+#[derive(Default)]
+struct MethodMetrics {
+    aurocs: Vec<f64>,
+    auprcs: Vec<f64>,
+    recalls: Vec<f64>,
+}
+
+impl MethodMetrics {
+    fn add(&mut self, scores: &[(f64, bool)], top_k: usize) {
+        if let (Some(roc), Some(prc), Some(recall)) =
+            (auroc(scores), auprc(scores), recall_at_k(scores, top_k))
+        {
+            self.aurocs.push(roc);
+            self.auprcs.push(prc);
+            self.recalls.push(recall);
+        }
+    }
+
+    fn mean(values: &[f64]) -> f64 {
+        if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    }
+}
+
+impl DiseaseGeneBenchmark {
+    /// Run the benchmark and store the results.
+    ///
+    /// NOTE: The relations can't be held out from the KGE model because it's trained on them, so the KGE metrics are optimistic unless the model is trained without the benchmark relations.
+    pub async fn run(&self, pool: &sqlx::PgPool) -> Result<Vec<BenchmarkResult>, anyhow::Error> {
+        let (source_type, target_type) = Graph::parse_relation_type(&self.relation_type)?;
+        let disease_is_source = match (source_type.as_str(), target_type.as_str()) {
+            ("Disease", "Gene") => true,
+            ("Gene", "Disease") => false,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "The relation type {} is not a disease-gene relation type, such as GNBR::J::Gene:Disease.",
+                    self.relation_type
+                ))
+            }
+        };
+
+        let embedding_metadata = match get_embedding_metadata(&self.model_name) {
+            Some(metadata) => metadata,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "The model {} doesn't exist, please import it first.",
+                    self.model_name
+                ))
+            }
+        };
+
+        let associations = self.load_associations(pool, disease_is_source).await?;
+        if associations.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No relations are found for the relation type {}.",
+                self.relation_type
+            ));
+        }
+
+        let mut genes: Vec<String> = associations
+            .iter()
+            .map(|(_, gene)| gene.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+        genes.sort();
+
+        let mut known: HashMap<String, HashSet<String>> = HashMap::new();
+        for (disease, gene) in associations.iter() {
+            known
+                .entry(disease.clone())
+                .or_default()
+                .insert(gene.clone());
+        }
+
+        info!("Loading the graph for the benchmark...");
+        let graph = load_graph(pool).await?;
+        info!("The graph is loaded, {} nodes.", graph.num_nodes());
+
+        let mut metrics: HashMap<&str, MethodMetrics> = HashMap::new();
+        // The cases are picked by a hash of the relations, so they are spread over the diseases and the same cases are used in every run.
+        let mut cases: Vec<&(String, String)> = associations.iter().collect();
+        cases.sort_by_key(|case| hash_of(case));
+        cases.truncate(self.max_cases);
+        for (index, (disease, gene)) in cases.iter().enumerate() {
+            let negatives = sample_negatives(disease, &genes, &known[disease], self.num_negatives);
+            if negatives.is_empty() {
+                continue;
+            }
+
+            let mut candidates = vec![gene.clone()];
+            candidates.extend(negatives);
+
+            let kge_scores = self
+                .score_by_kge(
+                    pool,
+                    &embedding_metadata,
+                    disease,
+                    &candidates,
+                    disease_is_source,
+                )
+                .await?;
+            let disease_node = graph.node_index(&format!("Disease::{}", disease));
+            let gene_node = graph.node_index(&format!("Gene::{}", gene));
+            let excluded = match (disease_node, gene_node) {
+                (Some(d), Some(g)) => Some((d, g)),
+                _ => None,
+            };
+            let rwr_probs = match disease_node {
+                Some(d) => graph.rwr(d, DEFAULT_RESTART_PROB, DEFAULT_RWR_STEPS, excluded),
+                None => HashMap::new(),
+            };
+
+            let mut kge = vec![];
+            let mut rwr = vec![];
+            let mut shared = vec![];
+            for candidate in candidates.iter() {
+                let is_positive = candidate == gene;
+                // The genes without embeddings are ranked at the bottom.
+                kge.push((
+                    *kge_scores.get(candidate).unwrap_or(&f64::NEG_INFINITY),
+                    is_positive,
+                ));
+
+                let candidate_node = graph.node_index(&format!("Gene::{}", candidate));
+                let (rwr_score, shared_score) = match (disease_node, candidate_node) {
+                    (Some(d), Some(c)) => (
+                        *rwr_probs.get(&c).unwrap_or(&0.0),
+                        graph.shared_neighbors(d, c, excluded),
+                    ),
+                    _ => (0.0, 0.0),
+                };
+                rwr.push((rwr_score, is_positive));
+                shared.push((shared_score, is_positive));
+            }
+
+            metrics.entry("kge").or_default().add(&kge, self.top_k);
+            metrics.entry("rwr").or_default().add(&rwr, self.top_k);
+            metrics
+                .entry("shared_neighbors")
+                .or_default()
+                .add(&shared, self.top_k);
+
+            debug!("Benchmark case {}/{} is done.", index + 1, cases.len());
+        }
+
+        let mut results = vec![];
+        for method in BENCHMARK_METHODS.iter() {
+            let method_metrics = match metrics.get(method) {
+                Some(m) if !m.aurocs.is_empty() => m,
+                _ => continue,
+            };
+
+            let result = BenchmarkResult {
+                id: 0,
+                model_name: self.model_name.clone(),
+                task: DISEASE_GENE_TASK.to_string(),
+                method: method.to_string(),
+                relation_type: self.relation_type.clone(),
+                num_cases: method_metrics.aurocs.len() as i32,
+                top_k: self.top_k as i32,
+                auroc: MethodMetrics::mean(&method_metrics.aurocs),
+                auprc: MethodMetrics::mean(&method_metrics.auprcs),
+                recall_at_k: MethodMetrics::mean(&method_metrics.recalls),
+                created_at: Utc::now(),
+            };
+            results.push(result.insert(pool).await?);
+        }
+
+        Ok(results)
+    }
+
+    /// Load the distinct (disease, gene) pairs of the relation type, the ids are not composed with the types.
+    async fn load_associations(
+        &self,
+        pool: &sqlx::PgPool,
+        disease_is_source: bool,
+    ) -> Result<Vec<(String, String)>, anyhow::Error> {
+        let sql_str = if disease_is_source {
+            "SELECT DISTINCT source_id, target_id FROM biomedgps_relation WHERE relation_type = $1 ORDER BY source_id, target_id"
+        } else {
+            "SELECT DISTINCT target_id, source_id FROM biomedgps_relation WHERE relation_type = $1 ORDER BY target_id, source_id"
+        };
+
+        let associations = sqlx::query_as::<_, (String, String)>(sql_str)
+            .bind(&self.relation_type)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(associations)
+    }
+
+    /// Score the candidate genes for the disease with the KGE model, the same score function as the target node prediction is used.
+    async fn score_by_kge(
+        &self,
+        pool: &sqlx::PgPool,
+        embedding_metadata: &EmbeddingMetadata,
+        disease: &str,
+        candidates: &[String],
+        disease_is_source: bool,
+    ) -> Result<HashMap<String, f64>, anyhow::Error> {
+        let gamma = 12.0;
+        let sql_str = format!(
+            "SELECT
+                ee2.entity_id AS entity_id,
+                {score_function_name}(
+                    vector_to_float4(ee1.embedding, {dimension}, false),
+                    vector_to_float4(rte.embedding, {dimension}, false),
+                    vector_to_float4(ee2.embedding, {dimension}, false),
+                    {gamma},
+                    true,
+                    {reverse}
+                )::FLOAT8 AS score
+            FROM
+                {entity_embedding_table} ee1,
+                {relation_type_embedding_table} rte,
+                {entity_embedding_table} ee2
+            WHERE
+                ee1.entity_id = $1
+                AND ee1.entity_type = 'Disease'
+                AND rte.relation_type = $2
+                AND ee2.entity_type = 'Gene'
+                AND ee2.entity_id = ANY($3)",
+            score_function_name = embedding_metadata.detect_score_fn(),
+            dimension = embedding_metadata.dimension,
+            gamma = gamma,
+            reverse = !disease_is_source,
+            entity_embedding_table = get_entity_emb_table_name(&embedding_metadata.table_name),
+            relation_type_embedding_table =
+                get_relation_emb_table_name(&embedding_metadata.table_name),
+        );
+
+        let scores = sqlx::query_as::<_, (String, f64)>(&sql_str)
+            .bind(disease)
+            .bind(&self.relation_type)
+            .bind(candidates)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(scores.into_iter().collect())
+    }
+}
+
+/// Load all relations into an undirected graph for the graph-based methods.
+async fn load_graph(pool: &sqlx::PgPool) -> Result<AdjacencyGraph, anyhow::Error> {
+    let mut graph = AdjacencyGraph::new();
+    let mut last_id: i64 = 0;
+    loop {
+        let records = sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT id, source_type || '::' || source_id, target_type || '::' || target_id FROM biomedgps_relation WHERE id > $1 ORDER BY id LIMIT $2",
+        )
+        .bind(last_id)
+        .bind(BENCHMARK_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        if records.is_empty() {
+            break;
+        }
+
+        last_id = records.last().unwrap().0;
+        for (_, source, target) in records.iter() {
+            graph.add_edge(source, target);
+        }
+    }
+
+    Ok(graph)
+}
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Pick the negative genes for a disease, they are the candidate genes which are not known to be associated with the disease. The genes are shuffled by a hash of the disease and the gene, so the same negatives are used for the same disease in every run.
+fn sample_negatives(
+    disease: &str,
+    genes: &[String],
+    known: &HashSet<String>,
+    num_negatives: usize,
+) -> Vec<String> {
+    let mut negatives: Vec<(u64, &String)> = genes
+        .iter()
+        .filter(|gene| !known.contains(*gene))
+        .map(|gene| (hash_of(&(disease, gene)), gene))
+        .collect();
+    negatives.sort();
+
+    negatives
+        .into_iter()
+        .take(num_negatives)
+        .map(|(_, gene)| gene.clone())
+        .collect()
+}
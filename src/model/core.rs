@@ -1,19 +1,24 @@
 //! The database schema for the application. These are the models that will be used to interact with the database.
 
-use super::graph::COMPOSED_ENTITY_DELIMITER;
+use super::graph::{COMPOSED_ENTITY_DELIMITER, COMPOSED_ENTITY_REGEX, RELATION_TYPE_REGEX};
 use super::kge::get_entity_emb_table_name;
-use super::util::{get_delimiter, parse_csv_error, ValidationError};
-use std::collections::HashMap;
+use super::util::{
+    deserialize_pmid, get_delimiter, normalize_pmids, open_data_file, parse_csv_error,
+    sniff_data_file, validate_pmids, ValidationError, MAX_PMID,
+};
+use std::collections::{BTreeSet, HashMap};
 // use crate::model::util::match_color;
-use crate::query_builder::sql_builder::ComposeQuery;
+use crate::query_builder::sql_builder::{ComposeQuery, ComposeQueryItem, QueryItem, Value};
 use anyhow::Ok as AnyOk;
 use chrono::serde::ts_seconds;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use lazy_static::lazy_static;
-use log::{debug, info};
-use poem_openapi::Object;
+use log::{debug, info, warn};
+use poem_openapi::{Enum, Object};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{error::Error, option::Option, path::PathBuf};
 use validator::Validate;
 
@@ -22,6 +27,12 @@ pub const ENTITY_NAME_MAX_LENGTH: u64 = 255;
 pub const RELATION_ID_MAX_LENGTH: u64 = 255;
 pub const DEFAULT_MAX_LENGTH: u64 = 64;
 pub const DEFAULT_MIN_LENGTH: u64 = 1;
+pub const NODE_TAG_MAX_LENGTH: u64 = 32;
+
+/// The extra columns of a relation file which are imported as the qualifiers, such as qualifier_dose.
+pub const RELATION_QUALIFIER_PREFIX: &str = "qualifier_";
+const RELATION_QUALIFIER_BATCH_SIZE: usize = 1000;
+const ENTITY_ATTRIBUTE_BATCH_SIZE: usize = 1000;
 
 lazy_static! {
     // The relation_id is like "<RELATION_TYPE>|<SOURCE_ID>|<TARGET_ID>", e.g. "STRING::ACTIVATOR::Gene:Compound|Gene::ENTREZ:1017|Compound::DrugBank:2083"
@@ -32,6 +43,12 @@ lazy_static! {
     pub static ref EMBEDDING_REGEX: Regex = Regex::new(r"^(?:-?\d+(?:\.\d+)?\|)*-?\d+(?:\.\d+)?$").unwrap();
     pub static ref SUBGRAPH_UUID_REGEX: Regex = Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$").unwrap();
     pub static ref JSON_REGEX: Regex = Regex::new(r"^(\{.*\}|\[.*\])$").expect("Failed to compile regex");
+    // dose>=10, tissue=liver
+    pub static ref QUALIFIER_FILTER_REGEX: Regex = Regex::new(r"^([A-Za-z0-9_]+)\s*(>=|<=|!=|=|>|<)\s*(.+)$").unwrap();
+    // liver, CD4-positive T cell, UBERON:0002107
+    pub static ref RELATION_CONTEXT_VALUE_REGEX: Regex = Regex::new(r"^[A-Za-z0-9][A-Za-z0-9 _\-:\.,/\(\)\+]*$").unwrap();
+    // drkg, ctd, CuratedFindings
+    pub static ref DATASET_REGEX: Regex = Regex::new(r"^[A-Za-z0-9_\-\.]+$").unwrap();
 }
 
 pub trait CheckData {
@@ -57,12 +74,36 @@ pub trait CheckData {
         };
 
         debug!("The delimiter is: {:?}", delimiter as char);
+        // Report the encoding and delimiter problems before they become cryptic errors of the deserialization.
+        match sniff_data_file(filepath, delimiter) {
+            Ok(diagnostics) => {
+                for diagnostic in diagnostics {
+                    if diagnostic.fatal {
+                        validation_errors
+                            .push(Box::new(ValidationError::new(&diagnostic.message, vec![])));
+                    } else {
+                        warn!("{:?}: {}", filepath, diagnostic.message);
+                    }
+                }
+
+                if !validation_errors.is_empty() {
+                    return validation_errors;
+                }
+            }
+            Err(e) => {
+                validation_errors.push(Box::new(ValidationError::new(
+                    &format!("Failed to read the file: ({})", e),
+                    vec![],
+                )));
+                return validation_errors;
+            }
+        }
+
         // Build the CSV reader
-        let mut reader = match csv::ReaderBuilder::new()
-            .delimiter(delimiter)
-            .from_path(filepath)
-        {
-            Ok(r) => r,
+        let mut reader = match open_data_file(filepath) {
+            Ok(file) => csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .from_reader(file),
             Err(e) => {
                 validation_errors.push(Box::new(ValidationError::new(
                     &format!("Failed to read CSV: ({})", e),
@@ -115,6 +156,11 @@ pub trait CheckData {
 
     fn unique_fields() -> Vec<String>;
 
+    /// Normalize the value of a field before importing it into the database, such as the PMIDs. Keep the value as it is by default.
+    fn normalize_field(_field: &str, value: &str) -> String {
+        value.to_string()
+    }
+
     fn get_error_msg<S: for<'de> serde::Deserialize<'de> + Validate + std::fmt::Debug>(
         r: Result<Vec<S>, Box<dyn Error>>,
     ) -> String {
@@ -136,7 +182,7 @@ pub trait CheckData {
         debug!("The delimiter is: {:?}", delimiter as char);
         let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(in_filepath)?;
+            .from_reader(open_data_file(in_filepath)?);
 
         let headers = reader.headers()?.clone();
         debug!("The headers are: {:?}", headers);
@@ -169,7 +215,10 @@ pub trait CheckData {
         // Read each record, keep only the desired fields, and write to the output file
         for result in reader.records() {
             let record = result?;
-            let record_to_keep: Vec<&str> = indices_to_keep.iter().map(|&i| &record[i]).collect();
+            let record_to_keep: Vec<String> = indices_to_keep
+                .iter()
+                .map(|&i| Self::normalize_field(&headers[i], &record[i]))
+                .collect();
             wtr.write_record(&record_to_keep)?;
         }
 
@@ -190,7 +239,7 @@ pub trait CheckData {
         let delimiter = get_delimiter(filepath)?;
         let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)?;
+            .from_reader(open_data_file(filepath)?);
 
         let headers = reader.headers()?;
         let mut column_names = Vec::new();
@@ -215,7 +264,7 @@ pub trait CheckData {
         let delimiter = get_delimiter(filepath)?;
         let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)?;
+            .from_reader(open_data_file(filepath)?);
 
         let mut records = Vec::new();
         for result in reader.deserialize::<S>() {
@@ -272,6 +321,101 @@ impl<
         page_size: Option<u64>,
         order_by: Option<&str>,
     ) -> Result<RecordResponse<S>, anyhow::Error> {
+        let mut conn = pool.acquire().await?;
+        Self::get_records_with_conn(&mut conn, table_name, query, page, page_size, order_by).await
+    }
+
+    /// Same as `get_records`, but the table expression has the numeric parameters `$1`, `$2`, ..., such as the derived table of the qualifier filters, and the values are bound to both the page and the total queries.
+    pub async fn get_records_with_binds(
+        pool: &sqlx::PgPool,
+        table_name: &str,
+        binds: &[f64],
+        query: &Option<ComposeQuery>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+        order_by: Option<&str>,
+    ) -> Result<RecordResponse<S>, anyhow::Error> {
+        let (sql_str, query_str) =
+            Self::format_page_sql(table_name, query, page, page_size, order_by);
+
+        let mut records_query = sqlx::query_as::<_, S>(sql_str.as_str());
+        for value in binds {
+            records_query = records_query.bind(*value);
+        }
+        let records = records_query.fetch_all(pool).await?;
+
+        let sql_str = format!("SELECT COUNT(*) FROM {} WHERE {}", table_name, query_str);
+
+        let mut total_query = sqlx::query_as::<_, (i64,)>(sql_str.as_str());
+        for value in binds {
+            total_query = total_query.bind(*value);
+        }
+        let total = total_query.fetch_one(pool).await?;
+
+        AnyOk(RecordResponse {
+            records: records,
+            total: total.0 as u64,
+            page: page.unwrap_or(1),
+            page_size: page_size.unwrap_or(10),
+        })
+    }
+
+    /// Same as `get_records`, but the queries are executed on the given connection, such as a transaction which is scoped by an [`OwnerScope`].
+    pub async fn get_records_with_conn(
+        conn: &mut sqlx::PgConnection,
+        table_name: &str,
+        query: &Option<ComposeQuery>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+        order_by: Option<&str>,
+    ) -> Result<RecordResponse<S>, anyhow::Error> {
+        let (sql_str, query_str) =
+            Self::format_page_sql(table_name, query, page, page_size, order_by);
+
+        let records = sqlx::query_as::<_, S>(sql_str.as_str())
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let sql_str = format!("SELECT COUNT(*) FROM {} WHERE {}", table_name, query_str);
+
+        let total = sqlx::query_as::<_, (i64,)>(sql_str.as_str())
+            .fetch_one(&mut *conn)
+            .await?;
+
+        AnyOk(RecordResponse {
+            records: records,
+            total: total.0 as u64,
+            page: page.unwrap_or(1),
+            page_size: page_size.unwrap_or(10),
+        })
+    }
+
+    /// Same as `get_records`, but only the records of the page are fetched and the total is not counted. It's used when the total is not needed or the table can't tell the total, such as the top relations table which only keeps the top-N relations of each node.
+    pub async fn fetch_page(
+        pool: &sqlx::PgPool,
+        table_name: &str,
+        query: &Option<ComposeQuery>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+        order_by: Option<&str>,
+    ) -> Result<Vec<S>, anyhow::Error> {
+        let (sql_str, _) = Self::format_page_sql(table_name, query, page, page_size, order_by);
+
+        let records = sqlx::query_as::<_, S>(sql_str.as_str())
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
+    }
+
+    /// Format the sql for fetching a page of the records, the where clause is also returned for counting the total.
+    fn format_page_sql(
+        table_name: &str,
+        query: &Option<ComposeQuery>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+        order_by: Option<&str>,
+    ) -> (String, String) {
         let mut query_str = match query {
             Some(ComposeQuery::QueryItem(item)) => item.format(),
             Some(ComposeQuery::ComposeQueryItem(item)) => item.format(),
@@ -312,25 +456,258 @@ impl<
             table_name, query_str, order_by_str, pagination_str
         );
 
-        let records = sqlx::query_as::<_, S>(sql_str.as_str())
-            .fetch_all(pool)
-            .await?;
+        (sql_str, query_str)
+    }
 
-        let sql_str = format!("SELECT COUNT(*) FROM {} WHERE {}", table_name, query_str);
+    /// Stream all matched records for the downloads, such as exporting millions of relations. The records are read by a server-side cursor in batches of `STREAM_BATCH_SIZE`, so the memory usage doesn't grow with the number of records.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `owner_scope` - The cursor is opened in a transaction which is scoped by the owner, so the row-level security policies are applied.
+    /// * `table_name` - The table name or a table expression
+    /// * `query` - The query to filter the records
+    /// * `order_by` - The order by clause
+    /// * `format` - NDJSON (one record per line) or CSV (with a header line)
+    pub fn stream_records(
+        pool: std::sync::Arc<sqlx::PgPool>,
+        owner_scope: OwnerScope,
+        table_name: String,
+        query: Option<ComposeQuery>,
+        order_by: Option<String>,
+        format: StreamFormat,
+    ) -> impl futures::Stream<Item = Result<bytes::Bytes, std::io::Error>>
+    where
+        S: 'static,
+    {
+        let query_str = match &query {
+            Some(ComposeQuery::QueryItem(item)) => item.format(),
+            Some(ComposeQuery::ComposeQueryItem(item)) => item.format(),
+            None => "".to_string(),
+        };
+        let query_str = if query_str.is_empty() {
+            "1=1".to_string()
+        } else {
+            query_str
+        };
+        let order_by_str = match order_by {
+            Some(order_by) => format!("ORDER BY {}", order_by),
+            None => "".to_string(),
+        };
+        let declare_str = format!(
+            "DECLARE {} NO SCROLL CURSOR FOR SELECT * FROM {} WHERE {} {}",
+            STREAM_CURSOR_NAME, table_name, query_str, order_by_str
+        );
+        let fetch_str = format!("FETCH {} FROM {}", STREAM_BATCH_SIZE, STREAM_CURSOR_NAME);
+
+        futures::stream::unfold(StreamCursor::Closed, move |cursor| {
+            let pool = pool.clone();
+            let owner_scope = owner_scope.clone();
+            let declare_str = declare_str.clone();
+            let fetch_str = fetch_str.clone();
+            async move {
+                // The cursor is declared before the first batch, the header of the CSV is written with it.
+                let (mut tx, is_first_batch) = match cursor {
+                    StreamCursor::Finished => return None,
+                    StreamCursor::Open(tx) => (tx, false),
+                    StreamCursor::Closed => {
+                        let mut tx = match owner_scope.begin(&pool).await {
+                            Ok(tx) => tx,
+                            Err(e) => return Some((Err(to_io_error(e)), StreamCursor::Finished)),
+                        };
+
+                        match sqlx::query(&declare_str).execute(&mut tx).await {
+                            Ok(_) => (tx, true),
+                            Err(e) => {
+                                warn!("Failed to declare the cursor: {}", e);
+                                return Some((Err(to_io_error(e)), StreamCursor::Finished));
+                            }
+                        }
+                    }
+                };
 
-        let total = sqlx::query_as::<_, (i64,)>(sql_str.as_str())
-            .fetch_one(pool)
-            .await?;
+                let records = match sqlx::query_as::<_, S>(&fetch_str).fetch_all(&mut tx).await {
+                    Ok(records) => records,
+                    Err(e) => {
+                        warn!("Failed to fetch the records from the cursor: {}", e);
+                        return Some((Err(to_io_error(e)), StreamCursor::Finished));
+                    }
+                };
 
-        AnyOk(RecordResponse {
-            records: records,
-            total: total.0 as u64,
-            page: page.unwrap_or(1),
-            page_size: page_size.unwrap_or(10),
+                // The cursor is closed with the transaction, it's read-only so there is nothing to commit.
+                if records.is_empty() {
+                    let _ = tx.rollback().await;
+                    return None;
+                }
+
+                let chunk = match format {
+                    StreamFormat::Ndjson => records_to_ndjson(&records),
+                    StreamFormat::Csv => records_to_csv(&records, is_first_batch),
+                };
+
+                match chunk {
+                    Ok(chunk) => Some((Ok(bytes::Bytes::from(chunk)), StreamCursor::Open(tx))),
+                    Err(e) => Some((Err(to_io_error(e)), StreamCursor::Finished)),
+                }
+            }
         })
     }
 }
 
+/// The state of the cursor which streams the records. An error aborts the stream, so the client gets a truncated download instead of a silently incomplete one.
+enum StreamCursor {
+    Closed,
+    Open(sqlx::Transaction<'static, sqlx::Postgres>),
+    Finished,
+}
+
+fn to_io_error<E: std::fmt::Display>(e: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
+}
+
+/// The number of records which are fetched from the cursor at a time when streaming the records.
+pub const STREAM_BATCH_SIZE: u64 = 5000;
+
+const STREAM_CURSOR_NAME: &str = "biomedgps_stream_cursor";
+
+/// The formats of the streamed records.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    Ndjson,
+    Csv,
+}
+
+impl StreamFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            StreamFormat::Ndjson => "ndjson",
+            StreamFormat::Csv => "csv",
+        }
+    }
+}
+
+fn records_to_ndjson<S: Serialize>(records: &Vec<S>) -> Result<String, anyhow::Error> {
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+
+    Ok(lines)
+}
+
+/// Convert a value of a record into a CSV cell, the lists are joined by `|` which is same as the pmids.
+fn to_csv_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "".to_string(),
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|value| to_csv_cell(value))
+            .collect::<Vec<String>>()
+            .join("|"),
+        _ => value.to_string(),
+    }
+}
+
+/// Convert the records into the CSV lines, the columns are the serialized fields of the records.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::core::records_to_csv;
+/// use serde_json::json;
+///
+/// let records = vec![json!({"id": 1, "name": "a,b", "synonyms": ["x", "y"], "xrefs": null})];
+/// let csv = records_to_csv(&records, true).unwrap();
+/// assert_eq!(csv, "id,name,synonyms,xrefs\n1,\"a,b\",x|y,\n");
+/// ```
+pub fn records_to_csv<S: Serialize>(
+    records: &Vec<S>,
+    with_header: bool,
+) -> Result<String, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for (i, record) in records.iter().enumerate() {
+        let fields = match serde_json::to_value(record)? {
+            serde_json::Value::Object(fields) => fields,
+            value => {
+                return Err(anyhow::anyhow!(
+                    "The record should be an object, but got {}.",
+                    value
+                ))
+            }
+        };
+
+        if i == 0 && with_header {
+            writer.write_record(fields.keys())?;
+        }
+        writer.write_record(fields.values().map(to_csv_cell))?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Enable the row-level security policies (see the `add_owner_rls_policies` migration) for the owner scoping, such as `ROW_LEVEL_SECURITY=true`. The database user must not be a superuser or the owner of the tables with `BYPASSRLS`, otherwise the policies are bypassed.
+pub const ROW_LEVEL_SECURITY_ENV: &str = "ROW_LEVEL_SECURITY";
+
+pub fn is_row_level_security_enabled() -> bool {
+    match std::env::var(ROW_LEVEL_SECURITY_ENV) {
+        Ok(value) => value == "true" || value == "1",
+        Err(_) => false,
+    }
+}
+
+/// The claims of the current user which are used by the row-level security policies of the curation and subgraph tables.
+///
+/// The claims are set by `set_config(..., true)` which is the same as `SET LOCAL`, so they only live in the transaction and never leak to other requests which share the same pooled connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnerScope {
+    pub username: String,
+    pub organizations: Vec<i32>,
+    pub projects: Vec<i32>,
+}
+
+impl OwnerScope {
+    pub fn new(username: &str, organizations: &Vec<i32>, projects: &Vec<i32>) -> Self {
+        OwnerScope {
+            username: username.to_string(),
+            organizations: organizations.clone(),
+            projects: projects.clone(),
+        }
+    }
+
+    /// The ids are joined by comma and the negative ids (the placeholder for no organization/project) are skipped.
+    pub fn join_ids(ids: &Vec<i32>) -> String {
+        ids.iter()
+            .filter(|id| **id >= 0)
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Begin a transaction with the claims of the user. The claims are not set if the row-level security is disabled, so the policies allow all rows and the transaction works like a plain connection.
+    ///
+    /// The transaction must be committed after the writes, otherwise they are rolled back when it's dropped.
+    pub async fn begin(
+        &self,
+        pool: &sqlx::PgPool,
+    ) -> Result<sqlx::Transaction<'static, sqlx::Postgres>, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+
+        if is_row_level_security_enabled() {
+            let sql_str = "SELECT set_config('biomedgps.username', $1, true), set_config('biomedgps.organizations', $2, true), set_config('biomedgps.projects', $3, true)";
+            sqlx::query(sql_str)
+                .bind(&self.username)
+                .bind(Self::join_ids(&self.organizations))
+                .bind(Self::join_ids(&self.projects))
+                .execute(&mut tx)
+                .await?;
+        }
+
+        AnyOk(tx)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
 pub struct Entity {
     // Ignore this field when deserialize from json
@@ -472,11 +849,116 @@ impl Entity {
             page_size: page_size.unwrap_or(10),
         })
     }
+
+    /// Check whether the entities exist in the entity table by one query instead of N sequential lookups.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `entities` - The (id, label) pairs, such as [("ENTREZ:1", "Gene")]. The duplicated pairs will be removed.
+    ///
+    /// # Returns
+    /// * `Result<Vec<EntityExistence>, anyhow::Error>` - The existence of each pair, the order is the same as the input.
+    pub async fn check_existence(
+        pool: &sqlx::PgPool,
+        entities: &Vec<EntityRef>,
+    ) -> Result<Vec<EntityExistence>, anyhow::Error> {
+        if entities.len() > MAX_NUM_ENTITY_REFS {
+            return Err(anyhow::anyhow!(
+                "Too many entities, the maximum number is {}.",
+                MAX_NUM_ENTITY_REFS
+            ));
+        }
+
+        let mut ids = entities
+            .iter()
+            .map(|e| e.id.clone())
+            .collect::<Vec<String>>();
+        ids.sort();
+        ids.dedup();
+        let mut labels = entities
+            .iter()
+            .map(|e| e.label.clone())
+            .collect::<Vec<String>>();
+        labels.sort();
+        labels.dedup();
+
+        // The result might contain the pairs which are not in the input, such as (id1, label2), they will be ignored.
+        let sql_str =
+            "SELECT id, label FROM biomedgps_entity WHERE id = ANY($1) AND label = ANY($2)";
+        let found = sqlx::query_as::<_, (String, String)>(sql_str)
+            .bind(&ids)
+            .bind(&labels)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect::<std::collections::HashSet<(String, String)>>();
+
+        let mut seen = std::collections::HashSet::new();
+        let mut results = vec![];
+        for entity in entities {
+            let pair = (entity.id.clone(), entity.label.clone());
+            if !seen.insert(pair.clone()) {
+                continue;
+            }
+
+            results.push(EntityExistence {
+                exists: found.contains(&pair),
+                id: pair.0,
+                label: pair.1,
+            });
+        }
+
+        AnyOk(results)
+    }
+}
+
+// The maximum number of the (id, label) pairs in one existence check request.
+pub const MAX_NUM_ENTITY_REFS: usize = 10000;
+
+/// The (id, label) pair which identifies an entity, such as ("ENTREZ:1", "Gene").
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct EntityRef {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct EntityExistence {
+    pub id: String,
+    pub label: String,
+    pub exists: bool,
 }
 
 impl CheckData for Entity {
     fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
-        Self::check_csv_is_valid_default::<Entity>(filepath)
+        let mut validation_errors = Self::check_csv_is_valid_default::<Entity>(filepath);
+        if validation_errors.len() > 0 {
+            return validation_errors;
+        }
+
+        // The rules of the entity types are only checked when the file can be parsed.
+        let records = match Self::get_records::<Entity>(filepath) {
+            Ok(r) => r,
+            Err(e) => {
+                validation_errors.push(e);
+                return validation_errors;
+            }
+        };
+
+        for (i, record) in records.iter().enumerate() {
+            for violation in record.check_rules() {
+                validation_errors.push(Box::new(ValidationError::new(
+                    &format!(
+                        "Failed to validate the data, line: {}, details: ({})",
+                        i + 2,
+                        violation
+                    ),
+                    vec![],
+                )));
+            }
+        }
+
+        validation_errors
     }
 
     fn unique_fields() -> Vec<String> {
@@ -498,268 +980,1487 @@ impl CheckData for Entity {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
-pub struct EntityAttribute {
-    // Ignore this field when deserialize from json
-    #[serde(skip_deserializing)]
-    #[oai(read_only)]
-    pub idx: i64,
+/// The path of a json file which contains the validation rules of the entity types, the bundled rules are used if it's not set.
+pub const ENTITY_VALIDATION_RULES_ENV: &str = "ENTITY_VALIDATION_RULES";
 
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of entity_id should be between 1 and 64."
-    ))]
-    #[validate(regex(
-        path = "ENTITY_ID_REGEX",
-        message = "The entity id is invalid. It should match ^[A-Za-z0-9\\-]+:[a-z0-9A-Z\\.\\-_]+$. Such as 'MESH:D000001'."
-    ))]
-    pub entity_id: String,
+/// The fields of an entity which can be required by a validation rule.
+pub const ENTITY_RULE_FIELDS: [&str; 5] = ["description", "taxid", "synonyms", "pmids", "xrefs"];
 
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of entity_type should be between 1 and 64."
-    ))]
-    #[validate(regex = "ENTITY_LABEL_REGEX")]
-    pub entity_type: String,
+/// A validation rule of an entity type, such as the genes need a taxid and the compounds need an id from the known resources.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntityValidationRule {
+    // The entity type, such as Gene.
+    pub label: String,
+    // The optional fields which must not be empty, such as taxid.
+    #[serde(default)]
+    pub required_fields: Vec<String>,
+    // The allowed prefixes of the entity id, such as ENTREZ in ENTREZ:1017. Any prefix is allowed if it's empty.
+    #[serde(default)]
+    pub id_prefixes: Vec<String>,
+}
 
-    // A human-readable summary of the entity in an external database.
-    pub description: String,
+impl EntityValidationRule {
+    /// Check the entity id, return a message if the prefix of the id is not allowed.
+    pub fn check_id(&self, id: &str) -> Option<String> {
+        if self.id_prefixes.is_empty() {
+            return None;
+        }
 
-    // The name of an external database. such as MESH, OMIM, etc. Also, we can develop a integrated database for collecting information for each entity. It could be a external service. We can call it as 'biomedgps-metadata-service'. So, we can extract all related information into the external service, and then we can get the information from the external service by the entity_id and entity_type.
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of external_db_name should be between 1 and 64."
-    ))]
-    pub external_db_name: String,
+        let prefix = id.split(':').next().unwrap_or_default();
+        if self.id_prefixes.iter().any(|p| p == prefix) {
+            None
+        } else {
+            Some(format!(
+                "The id {} of the {} entity is invalid, its prefix should be one of {}.",
+                id,
+                self.label,
+                self.id_prefixes.join(", ")
+            ))
+        }
+    }
 
-    // The link to the entity in an external database. It should be a valid URL, like https://www.ncbi.nlm.nih.gov/mesh/68000001.
-    pub external_url: String,
+    /// Check an entity, return a message for each violation of the rule.
+    pub fn check_entity(&self, entity: &Entity) -> Vec<String> {
+        let mut violations = vec![];
+        if let Some(violation) = self.check_id(&entity.id) {
+            violations.push(violation);
+        }
 
-    // The id of the entity in an external database.
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of external_id should be between 1 and 64."
-    ))]
-    pub external_id: String,
-}
+        for field in self.required_fields.iter() {
+            let value = match field.as_str() {
+                "description" => &entity.description,
+                "taxid" => &entity.taxid,
+                "synonyms" => &entity.synonyms,
+                "pmids" => &entity.pmids,
+                "xrefs" => &entity.xrefs,
+                _ => continue,
+            };
 
-impl CheckData for EntityAttribute {
-    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
-        Self::check_csv_is_valid_default::<EntityAttribute>(filepath)
+            if value.as_ref().map(|v| v.trim().is_empty()).unwrap_or(true) {
+                violations.push(format!(
+                    "The {} field is required for the {} entity {}.",
+                    field, self.label, entity.id
+                ));
+            }
+        }
+
+        violations
     }
+}
 
-    fn unique_fields() -> Vec<String> {
-        vec![
-            "entity_id".to_string(),
-            "entity_type".to_string(),
-            "external_db_name".to_string(),
-            "external_id".to_string(),
-        ]
+/// Parse the validation rules of the entity types from a json string.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::core::parse_entity_validation_rules;
+///
+/// let rules = parse_entity_validation_rules(r#"[{"label": "Gene", "required_fields": ["taxid"]}]"#).unwrap();
+/// assert_eq!(rules[0].label, "Gene");
+/// assert!(rules[0].id_prefixes.is_empty());
+///
+/// assert!(parse_entity_validation_rules(r#"[{"label": "Gene", "required_fields": ["name"]}]"#).is_err());
+/// ```
+pub fn parse_entity_validation_rules(
+    json_str: &str,
+) -> Result<Vec<EntityValidationRule>, anyhow::Error> {
+    let rules: Vec<EntityValidationRule> = serde_json::from_str(json_str)?;
+    for rule in rules.iter() {
+        for field in rule.required_fields.iter() {
+            if !ENTITY_RULE_FIELDS.contains(&field.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "The required field {} of the {} rule is not supported, it should be one of {}.",
+                    field,
+                    rule.label,
+                    ENTITY_RULE_FIELDS.join(", ")
+                ));
+            }
+        }
     }
 
-    fn fields() -> Vec<String> {
-        vec![
-            "entity_id".to_string(),
-            "entity_type".to_string(),
-            "description".to_string(),
-            "external_db_name".to_string(),
-            "external_url".to_string(),
-            "external_id".to_string(),
-        ]
+    AnyOk(rules)
+}
+
+fn load_entity_validation_rules() -> Vec<EntityValidationRule> {
+    let bundled_rules = include_str!("../../resources/entity_validation_rules.json");
+    let rules = match std::env::var(ENTITY_VALIDATION_RULES_ENV) {
+        Ok(path) => match std::fs::read_to_string(&path) {
+            Ok(json_str) => parse_entity_validation_rules(&json_str),
+            Err(e) => Err(anyhow::anyhow!("Failed to read {}: {}", path, e)),
+        },
+        Err(_) => {
+            return parse_entity_validation_rules(bundled_rules)
+                .expect("The bundled entity_validation_rules.json is not valid.")
+        }
+    };
+
+    match rules {
+        Ok(rules) => rules,
+        Err(e) => {
+            warn!(
+                "Failed to load the entity validation rules, use the bundled rules instead: {}",
+                e
+            );
+            parse_entity_validation_rules(bundled_rules)
+                .expect("The bundled entity_validation_rules.json is not valid.")
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
-pub struct Statistics {
-    entity_stat: Vec<EntityMetadata>,
-    relation_stat: Vec<RelationMetadata>,
+lazy_static! {
+    pub static ref ENTITY_VALIDATION_RULES: HashMap<String, EntityValidationRule> =
+        load_entity_validation_rules()
+            .into_iter()
+            .map(|rule| (rule.label.clone(), rule))
+            .collect();
 }
 
-impl Statistics {
-    pub fn new(
-        entity_stat: Vec<EntityMetadata>,
-        relation_stat: Vec<RelationMetadata>,
-    ) -> Statistics {
-        Statistics {
-            entity_stat: entity_stat,
-            relation_stat: relation_stat,
+impl Entity {
+    /// Check the entity by the validation rule of its entity type, return a message for each violation.
+    pub fn check_rules(&self) -> Vec<String> {
+        match ENTITY_VALIDATION_RULES.get(&self.label) {
+            Some(rule) => rule.check_entity(self),
+            None => vec![],
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
-pub struct EntityMetadata {
-    // Ignore this field when deserialize from json
-    #[serde(skip_deserializing)]
-    #[oai(read_only)]
-    pub id: i64,
+// The text embedding model in the pgml extension, it is used to compare the context of a mention with the descriptions of the candidate entities.
+pub const DEFAULT_TEXT_EMBEDDING_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+// The weight of the string similarity when combining it with the context similarity.
+pub const DISAMBIGUATION_STRING_WEIGHT: f64 = 0.4;
+// How many candidates are fetched by the string similarity before reranking them by the context.
+pub const DISAMBIGUATION_MAX_CANDIDATES: u64 = 50;
 
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of resource should be between 1 and 64."
-    ))]
+/// A candidate entity for an ambiguous mention, such as "ER" which might be Estrogen Receptor or Endoplasmic Reticulum.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct EntitySuggestion {
+    pub id: String,
+    pub name: String,
+    pub label: String,
     pub resource: String,
+    pub description: Option<String>,
+    pub synonyms: Option<String>,
+    // The best similarity between the mention and the name or the synonyms of the entity.
+    pub string_score: f64,
+    // The cosine similarity between the context and the description of the entity, None if the context is not provided.
+    #[sqlx(default)]
+    pub context_score: Option<f64>,
+    #[sqlx(default)]
+    pub score: f64,
+}
 
-    #[validate(regex(
-        path = "ENTITY_LABEL_REGEX",
-        message = "The entity type should match ^[A-Za-z]+$. Such as Disease."
-    ))]
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of entity_type should be between 1 and 64."
-    ))]
-    pub entity_type: String,
+impl EntitySuggestion {
+    /// Rank the candidate entities of a mention by combining the string similarity on the names and synonyms with the context embedding similarity against the descriptions.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `mention` - The ambiguous mention, such as "ER".
+    /// * `context` - The sentence around the mention, the candidates are ranked only by the string similarity if it is None.
+    /// * `entity_type` - Only the entities with the label will be returned, None means all labels.
+    /// * `topk` - The number of suggestions.
+    ///
+    pub async fn disambiguate(
+        pool: &sqlx::PgPool,
+        mention: &str,
+        context: Option<&str>,
+        entity_type: Option<&str>,
+        topk: u64,
+    ) -> Result<Vec<EntitySuggestion>, anyhow::Error> {
+        // The synonyms are separated by `|`, so an exact match in the synonyms is as good as an exact match in the name.
+        let candidate_sql = "SELECT id, name, label, resource, description, synonyms,
+                                    GREATEST(
+                                        similarity(name, $1),
+                                        word_similarity($1, COALESCE(synonyms, '')),
+                                        CASE WHEN lower($1) = ANY(string_to_array(lower(COALESCE(synonyms, '')), '|')) THEN 1.0 ELSE 0.0 END
+                                    )::FLOAT8 AS string_score
+                             FROM biomedgps_entity
+                             WHERE ($2::VARCHAR IS NULL OR label = $2)
+                               AND (name % $1 OR $1 <% COALESCE(synonyms, '') OR lower(name) = lower($1))
+                             ORDER BY string_score DESC
+                             LIMIT $3";
+
+        let mut candidates = match context {
+            Some(context) if !context.trim().is_empty() => {
+                let sql_str = format!(
+                    "WITH candidates AS ({candidate_sql}),
+                          context AS (SELECT pgml.embed($4, $5)::REAL[] AS embedding)
+                     SELECT candidates.*,
+                            pgml.cosine_similarity(
+                                pgml.embed($4, COALESCE(candidates.description, candidates.name))::REAL[],
+                                context.embedding
+                            )::FLOAT8 AS context_score
+                     FROM candidates, context"
+                );
+
+                sqlx::query_as::<_, EntitySuggestion>(sql_str.as_str())
+                    .bind(mention)
+                    .bind(entity_type)
+                    .bind(DISAMBIGUATION_MAX_CANDIDATES as i64)
+                    .bind(DEFAULT_TEXT_EMBEDDING_MODEL)
+                    .bind(context)
+                    .fetch_all(pool)
+                    .await?
+            }
+            _ => {
+                sqlx::query_as::<_, EntitySuggestion>(candidate_sql)
+                    .bind(mention)
+                    .bind(entity_type)
+                    .bind(DISAMBIGUATION_MAX_CANDIDATES as i64)
+                    .fetch_all(pool)
+                    .await?
+            }
+        };
 
-    pub entity_count: i64,
-}
+        for candidate in candidates.iter_mut() {
+            candidate.score = Self::combine_scores(candidate.string_score, candidate.context_score);
+        }
 
-impl CheckData for EntityMetadata {
-    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
-        Self::check_csv_is_valid_default::<EntityMetadata>(filepath)
+        candidates.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        candidates.truncate(topk as usize);
+
+        AnyOk(candidates)
     }
 
-    fn unique_fields() -> Vec<String> {
-        vec!["resource".to_string(), "entity_type".to_string()]
+    pub fn combine_scores(string_score: f64, context_score: Option<f64>) -> f64 {
+        match context_score {
+            Some(context_score) => {
+                DISAMBIGUATION_STRING_WEIGHT * string_score
+                    + (1.0 - DISAMBIGUATION_STRING_WEIGHT) * context_score
+            }
+            None => string_score,
+        }
     }
+}
 
-    fn fields() -> Vec<String> {
-        vec![
-            "resource".to_string(),
-            "entity_type".to_string(),
-            "entity_count".to_string(),
-        ]
+/// The full-text search of the entities uses the simple config, so the names and the ids are not stemmed. The names are weighted higher than the synonyms, and the synonyms are weighted higher than the cross references. The document must be the same as the expression of the index in the `add_entity_search_index` migration, otherwise the index is not used.
+const ENTITY_SEARCH_DOCUMENT: &str = "setweight(to_tsvector('simple', name), 'A') || setweight(to_tsvector('simple', COALESCE(synonyms, '')), 'B') || setweight(to_tsvector('simple', COALESCE(xrefs, '')), 'C')";
+
+/// The aliases of an entity are the id, the cross references and their local parts without the prefixes, all in lower case, such as `drugbank:db00001` and `db00001`. So an entity can be found by a CURIE which is only stored in the cross references. The expression must be the same as the expression of the index in the `add_entity_alias_index` migration, otherwise the index is not used.
+const ENTITY_ALIAS_ARRAY: &str = "(string_to_array(lower(id || COALESCE('|' || xrefs, '')), '|') || string_to_array(regexp_replace(lower(id || COALESCE('|' || xrefs, '')), '[^|]*:', '', 'g'), '|'))";
+
+/// The max number of the terms in a full-text search of the entities.
+pub const ENTITY_SEARCH_MAX_TERMS: usize = 8;
+
+/// Build a prefix tsquery from the text of a type-ahead search, every term must be matched and the terms are matched as prefixes, such as `tp53:* & bind:*`. The characters which are not letters or digits are treated as separators, so the users can't inject the tsquery operators.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::core::build_prefix_tsquery;
+///
+/// assert_eq!(build_prefix_tsquery("TP53 bind"), Some("tp53:* & bind:*".to_string()));
+/// assert_eq!(build_prefix_tsquery("MESH:D0011"), Some("mesh:* & d0011:*".to_string()));
+/// assert_eq!(build_prefix_tsquery("alzheimer's & !"), Some("alzheimer:* & s:*".to_string()));
+/// assert_eq!(build_prefix_tsquery(" :* "), None);
+/// ```
+pub fn build_prefix_tsquery(text: &str) -> Option<String> {
+    let terms = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .take(ENTITY_SEARCH_MAX_TERMS)
+        .map(|t| format!("{}:*", t))
+        .collect::<Vec<String>>();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" & "))
     }
 }
 
-impl EntityMetadata {
-    pub async fn get_entity_metadata(
+/// An entity which matches the full-text search, it's used by the type-ahead of the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct EntitySearchMatch {
+    pub id: String,
+    pub name: String,
+    pub label: String,
+    pub resource: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub synonyms: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub xrefs: Option<String>,
+    // The relevance of the entity, an exact match of the id is ranked first, then an exact match of the name, the cross references, and the names which start with the text. It's the trigram similarity in [0, 1] for the fuzzy search.
+    pub score: f64,
+    // Which field is matched, such as id, name, synonym or xref. It's text if the terms are matched across the fields.
+    pub matched_field: String,
+}
+
+impl EntitySearchMatch {
+    /// Search the entities by the names, the synonyms and the cross references. The terms of the text are matched as prefixes, so it works for the partial inputs of a type-ahead, such as `alzh` for Alzheimer's disease. The text is also matched exactly against the ids and the cross references, with or without the prefixes, so `DB00001` finds the entity which has `DrugBank:DB00001` in the cross references.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `text` - The text to search, such as `TP53` or `alzheimer`.
+    /// * `entity_types` - Only the entities with these labels are returned, None means all labels.
+    /// * `limit` - The max number of the matched entities.
+    ///
+    pub async fn search(
         pool: &sqlx::PgPool,
-    ) -> Result<Vec<EntityMetadata>, anyhow::Error> {
-        let sql_str = "SELECT * FROM biomedgps_entity_metadata";
-        let entity_metadata = sqlx::query_as::<_, EntityMetadata>(sql_str)
+        text: &str,
+        entity_types: &Option<Vec<String>>,
+        limit: i64,
+    ) -> Result<Vec<EntitySearchMatch>, anyhow::Error> {
+        let tsquery = match build_prefix_tsquery(text) {
+            Some(tsquery) => tsquery,
+            None => return AnyOk(vec![]),
+        };
+
+        // An exact match of the id or the cross references is ranked above the full-text matches, except an exact match of the name. Each entity is returned once with the best score.
+        let sql_str = format!(
+            "SELECT id, name, label, resource, description, synonyms, xrefs, score, matched_field
+             FROM (
+                SELECT DISTINCT ON (id, label) *
+                FROM (
+                    SELECT id, name, label, resource, description, synonyms, xrefs,
+                           CASE WHEN lower(id) = lower($2) OR lower(split_part(id, ':', 2)) = lower($2)
+                                THEN 3.0 ELSE 2.0 END::FLOAT8 AS score,
+                           CASE WHEN lower(id) = lower($2) OR lower(split_part(id, ':', 2)) = lower($2)
+                                THEN 'id' ELSE 'xref' END AS matched_field
+                    FROM biomedgps_entity
+                    WHERE {aliases} @> ARRAY[lower($2)] AND ($4::text[] IS NULL OR label = ANY($4))
+                    UNION ALL
+                    SELECT id, name, label, resource, description, synonyms, xrefs,
+                           (ts_rank_cd({document}, q)
+                             + CASE WHEN lower(name) = lower($2) OR lower(id) = lower($2) THEN 1.0 ELSE 0.0 END
+                             + CASE WHEN lower(name) LIKE lower($3) THEN 0.5 ELSE 0.0 END)::FLOAT8 AS score,
+                           CASE WHEN to_tsvector('simple', name) @@ q THEN 'name'
+                                WHEN to_tsvector('simple', COALESCE(synonyms, '')) @@ q THEN 'synonym'
+                                WHEN to_tsvector('simple', COALESCE(xrefs, '')) @@ q THEN 'xref'
+                                ELSE 'text' END AS matched_field
+                    FROM biomedgps_entity, to_tsquery('simple', $1) q
+                    WHERE {document} @@ q AND ($4::text[] IS NULL OR label = ANY($4))
+                ) candidates
+                ORDER BY id, label, score DESC
+             ) matches
+             ORDER BY score DESC, length(name) ASC, id ASC
+             LIMIT $5",
+            aliases = ENTITY_ALIAS_ARRAY,
+            document = ENTITY_SEARCH_DOCUMENT
+        );
+
+        // The wildcards in the text are escaped, so only the names which start with the text are boosted.
+        let prefix = format!(
+            "{}%",
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let matches = sqlx::query_as::<_, EntitySearchMatch>(&sql_str)
+            .bind(&tsquery)
+            .bind(text)
+            .bind(&prefix)
+            .bind(entity_types)
+            .bind(limit)
             .fetch_all(pool)
             .await?;
 
-        AnyOk(entity_metadata)
+        AnyOk(matches)
+    }
+
+    /// Search the entities by the trigram similarity of the names and the synonyms, so the misspelled text still matches, such as `ashtma` for Asthma. The synonyms are matched by the word similarity, because they are joined into one string.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `text` - The text to search, such as `ashtma`.
+    /// * `entity_types` - Only the entities with these labels are returned, None means all labels.
+    /// * `similarity` - The min similarity in (0, 1] of the matched entities, such as 0.4.
+    /// * `limit` - The max number of the matched entities.
+    ///
+    pub async fn fuzzy_search(
+        pool: &sqlx::PgPool,
+        text: &str,
+        entity_types: &Option<Vec<String>>,
+        similarity: f64,
+        limit: i64,
+    ) -> Result<Vec<EntitySearchMatch>, anyhow::Error> {
+        // The thresholds of the % and <% operators are set locally, so the trigram indexes of the names and the synonyms are used.
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "SELECT set_config('pg_trgm.similarity_threshold', $1, true),
+                    set_config('pg_trgm.word_similarity_threshold', $1, true)",
+        )
+        .bind(similarity.to_string())
+        .execute(&mut tx)
+        .await?;
+
+        let sql_str = "SELECT id, name, label, resource, description, synonyms, xrefs,
+                              GREATEST(similarity(name, $1), word_similarity($1, COALESCE(synonyms, '')))::FLOAT8 AS score,
+                              CASE WHEN similarity(name, $1) >= word_similarity($1, COALESCE(synonyms, ''))
+                                   THEN 'name' ELSE 'synonym' END AS matched_field
+                       FROM biomedgps_entity
+                       WHERE (name % $1 OR $1 <% synonyms) AND ($2::text[] IS NULL OR label = ANY($2))
+                       ORDER BY score DESC, length(name) ASC, id ASC
+                       LIMIT $3";
+        let matches = sqlx::query_as::<_, EntitySearchMatch>(sql_str)
+            .bind(text)
+            .bind(entity_types)
+            .bind(limit)
+            .fetch_all(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        AnyOk(matches)
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
-pub struct RelationMetadata {
+pub struct EntityAttribute {
     // Ignore this field when deserialize from json
     #[serde(skip_deserializing)]
     #[oai(read_only)]
-    pub id: i64,
-
-    // The resource of the relation. such as STRING, BIOMEDGPS, etc.
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of resource should be between 1 and 64."
-    ))]
-    pub resource: String,
+    pub idx: i64,
 
-    // The dataset of the relation for labeling different datasets. such DRKG, HSDN, CTD, CuratedFindings, etc. Users can choose different dataset combinations to build a knowledge graph and train models.
     #[validate(length(
         max = "DEFAULT_MAX_LENGTH",
         min = "DEFAULT_MIN_LENGTH",
-        message = "The length of dataset should be between 1 and 64."
+        message = "The length of entity_id should be between 1 and 64."
     ))]
-    pub dataset: String,
-
-    // The relation type, such as STRING::ACTIVATOR::Gene:Compound, STRING::INHIBITOR::Gene:Compound, etc.
-    #[validate(length(
-        max = "DEFAULT_MAX_LENGTH",
-        min = "DEFAULT_MIN_LENGTH",
-        message = "The length of relation_type should be between 1 and 64."
+    #[validate(regex(
+        path = "ENTITY_ID_REGEX",
+        message = "The entity id is invalid. It should match ^[A-Za-z0-9\\-]+:[a-z0-9A-Z\\.\\-_]+$. Such as 'MESH:D000001'."
     ))]
-    pub relation_type: String,
+    pub entity_id: String,
 
-    // The formatted relation type, such as BIOMEDGPS::ACTIVATOR::Gene::Compound, BIOMEDGPS::TREATMENT::Compound::Disease, etc.
     #[validate(length(
         max = "DEFAULT_MAX_LENGTH",
         min = "DEFAULT_MIN_LENGTH",
-        message = "The length of formatted_relation_type should be between 1 and 64."
+        message = "The length of entity_type should be between 1 and 64."
     ))]
-    pub formatted_relation_type: String,
+    #[validate(regex = "ENTITY_LABEL_REGEX")]
+    pub entity_type: String,
 
-    pub relation_count: i64,
+    // A human-readable summary of the entity in an external database.
+    pub description: String,
 
-    #[validate(regex = "ENTITY_LABEL_REGEX")]
+    // The name of an external database. such as MESH, OMIM, etc. Also, we can develop a integrated database for collecting information for each entity. It could be a external service. We can call it as 'biomedgps-metadata-service'. So, we can extract all related information into the external service, and then we can get the information from the external service by the entity_id and entity_type.
     #[validate(length(
         max = "DEFAULT_MAX_LENGTH",
         min = "DEFAULT_MIN_LENGTH",
-        message = "The length of start_entity_type should be between 1 and 64."
+        message = "The length of external_db_name should be between 1 and 64."
     ))]
-    pub start_entity_type: String,
+    pub external_db_name: String,
 
-    #[validate(regex = "ENTITY_LABEL_REGEX")]
+    // The link to the entity in an external database. It should be a valid URL, like https://www.ncbi.nlm.nih.gov/mesh/68000001.
+    pub external_url: String,
+
+    // The id of the entity in an external database.
     #[validate(length(
         max = "DEFAULT_MAX_LENGTH",
         min = "DEFAULT_MIN_LENGTH",
-        message = "The length of end_entity_type should be between 1 and 64."
+        message = "The length of external_id should be between 1 and 64."
     ))]
-    pub end_entity_type: String,
+    pub external_id: String,
 
-    // To describe the relation type with a human-readable sentence.
+    // The timestamp of the source (RFC3339), such as the release date of the external database. It will be the import time if it is not provided.
+    #[serde(default)]
     #[oai(skip_serializing_if_is_none)]
-    pub description: Option<String>,
-}
-
-impl CheckData for RelationMetadata {
-    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
-        Self::check_csv_is_valid_default::<RelationMetadata>(filepath)
-    }
-
-    fn unique_fields() -> Vec<String> {
-        vec![
-            "dataset".to_string(),
-            "resource".to_string(),
-            "formatted_relation_type".to_string(),
-            "relation_type".to_string(),
-            "start_entity_type".to_string(),
-            "end_entity_type".to_string(),
-        ]
-    }
+    pub valid_from: Option<DateTime<Utc>>,
 
-    fn fields() -> Vec<String> {
-        vec![
-            "dataset".to_string(),
-            "resource".to_string(),
-            "relation_type".to_string(),
-            "formatted_relation_type".to_string(),
-            "relation_count".to_string(),
-            "start_entity_type".to_string(),
-            "end_entity_type".to_string(),
-            "description".to_string(),
-        ]
-    }
+    // The idx of the newer version, None means it is the latest version.
+    #[serde(skip_deserializing)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub superseded_by: Option<i64>,
 }
 
-impl RelationMetadata {
-    pub async fn get_relation_metadata(
+impl EntityAttribute {
+    /// Get the attributes of an entity. It returns the latest version of each external attribute if `at` is None, otherwise it returns the versions which are valid at the given time.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `entity_id` - The entity id, such as MESH:D000001
+    /// * `entity_type` - The entity type, such as Disease
+    /// * `at` - The time of the snapshot
+    ///
+    /// # Returns
+    /// * `Result<Vec<EntityAttribute>, anyhow::Error>` - The attributes or an error
+    pub async fn fetch_attributes(
         pool: &sqlx::PgPool,
-    ) -> Result<Vec<RelationMetadata>, anyhow::Error> {
-        let sql_str = "SELECT * FROM biomedgps_relation_metadata";
-        let relation_metadata = sqlx::query_as::<_, RelationMetadata>(sql_str)
-            .fetch_all(pool)
-            .await?;
+        entity_id: &str,
+        entity_type: &str,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<EntityAttribute>, anyhow::Error> {
+        let records = match at {
+            Some(at) => {
+                let sql_str = "SELECT DISTINCT ON (external_db_name, external_id) * FROM biomedgps_entity_attribute WHERE entity_id = $1 AND entity_type = $2 AND valid_from <= $3 ORDER BY external_db_name, external_id, valid_from DESC";
+                sqlx::query_as::<_, EntityAttribute>(sql_str)
+                    .bind(entity_id)
+                    .bind(entity_type)
+                    .bind(at)
+                    .fetch_all(pool)
+                    .await?
+            }
+            None => {
+                let sql_str = "SELECT * FROM biomedgps_entity_attribute WHERE entity_id = $1 AND entity_type = $2 AND superseded_by IS NULL ORDER BY external_db_name, external_id";
+                sqlx::query_as::<_, EntityAttribute>(sql_str)
+                    .bind(entity_id)
+                    .bind(entity_type)
+                    .fetch_all(pool)
+                    .await?
+            }
+        };
 
-        AnyOk(relation_metadata)
+        AnyOk(records)
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize, Object, PartialEq, Eq)]
-pub struct Payload {
-    pub project_id: String,
-    pub organization_id: String,
-}
+    /// Import the attributes as new versions. The latest version will be superseded by the new one if the description or the external_url is changed, the unchanged and the older ones will be skipped. So the history is kept when we re-import the attributes.
+    /// The records are loaded, inserted and superseded by UNNEST in batches, so a batch takes three queries instead of several queries per record.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `records` - The attributes to import
+    ///
+    /// # Returns
+    /// * `Result<usize, anyhow::Error>` - The number of the new versions or an error
+    pub async fn import_versions(
+        pool: &sqlx::PgPool,
+        records: &Vec<EntityAttribute>,
+    ) -> Result<usize, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        // The description, external_url and valid_from of the latest version of each attribute, the new versions in the previous batches are included.
+        let mut latest_versions: HashMap<
+            (String, String, String, String),
+            (String, String, Option<DateTime<Utc>>),
+        > = HashMap::new();
+        let mut num_of_versions = 0;
+        for chunk in records.chunks(ENTITY_ATTRIBUTE_BATCH_SIZE) {
+            // The keys (entity_id, entity_type, external_db_name, external_id) whose latest versions are not loaded yet.
+            let mut keys: Vec<Vec<String>> = vec![vec![]; 4];
+            let mut pending = BTreeSet::new();
+            for record in chunk {
+                let key = Self::version_key(record);
+                if !latest_versions.contains_key(&key) && pending.insert(key.clone()) {
+                    keys[0].push(key.0);
+                    keys[1].push(key.1);
+                    keys[2].push(key.2);
+                    keys[3].push(key.3);
+                }
+            }
+
+            if !keys[0].is_empty() {
+                let sql_str = "SELECT a.* FROM biomedgps_entity_attribute a
+                    JOIN UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[]) AS k(entity_id, entity_type, external_db_name, external_id)
+                    ON a.entity_id = k.entity_id AND a.entity_type = k.entity_type AND a.external_db_name = k.external_db_name AND a.external_id = k.external_id
+                    WHERE a.superseded_by IS NULL";
+                let latest = sqlx::query_as::<_, EntityAttribute>(sql_str)
+                    .bind(&keys[0])
+                    .bind(&keys[1])
+                    .bind(&keys[2])
+                    .bind(&keys[3])
+                    .fetch_all(&mut tx)
+                    .await?;
+
+                for latest in latest {
+                    latest_versions.insert(
+                        Self::version_key(&latest),
+                        (latest.description, latest.external_url, latest.valid_from),
+                    );
+                }
+            }
+
+            // One column per field of the new versions, they are inserted by UNNEST.
+            let mut columns: Vec<Vec<String>> = vec![vec![]; 6];
+            let mut valid_froms: Vec<DateTime<Utc>> = vec![];
+            for record in chunk {
+                let valid_from = record.valid_from.unwrap_or(Utc::now());
+                let key = Self::version_key(record);
+                if let Some((description, external_url, latest_valid_from)) =
+                    latest_versions.get(&key)
+                {
+                    if description == &record.description && external_url == &record.external_url {
+                        debug!(
+                            "The attribute of {}::{} in {} is not changed, skip it.",
+                            record.entity_type, record.entity_id, record.external_db_name
+                        );
+                        continue;
+                    }
+
+                    if latest_valid_from.map_or(false, |v| v >= valid_from) {
+                        debug!(
+                            "The attribute of {}::{} in {} is older than the latest version, skip it.",
+                            record.entity_type, record.entity_id, record.external_db_name
+                        );
+                        continue;
+                    }
+                }
+
+                columns[0].push(record.entity_id.clone());
+                columns[1].push(record.entity_type.clone());
+                columns[2].push(record.description.clone());
+                columns[3].push(record.external_db_name.clone());
+                columns[4].push(record.external_url.clone());
+                columns[5].push(record.external_id.clone());
+                valid_froms.push(valid_from);
+                latest_versions.insert(
+                    key,
+                    (
+                        record.description.clone(),
+                        record.external_url.clone(),
+                        Some(valid_from),
+                    ),
+                );
+            }
+
+            if valid_froms.is_empty() {
+                continue;
+            }
+
+            let sql_str = "INSERT INTO biomedgps_entity_attribute (entity_id, entity_type, description, external_db_name, external_url, external_id, valid_from)
+                SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TIMESTAMPTZ[])";
+            let mut query = sqlx::query(sql_str);
+            for column in columns.iter() {
+                query = query.bind(column);
+            }
+            let result = query.bind(&valid_froms).execute(&mut tx).await?;
+            num_of_versions += result.rows_affected() as usize;
+
+            // The new versions are newer than the latest ones, so each version is superseded by the next one ordered by valid_from.
+            let sql_str = "UPDATE biomedgps_entity_attribute a SET superseded_by = v.next_idx
+                FROM (
+                    SELECT e.idx, LEAD(e.idx) OVER (
+                        PARTITION BY e.entity_id, e.entity_type, e.external_db_name, e.external_id ORDER BY e.valid_from
+                    ) AS next_idx
+                    FROM biomedgps_entity_attribute e
+                    JOIN (SELECT DISTINCT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[])) AS k(entity_id, entity_type, external_db_name, external_id)
+                    ON e.entity_id = k.entity_id AND e.entity_type = k.entity_type AND e.external_db_name = k.external_db_name AND e.external_id = k.external_id
+                    WHERE e.superseded_by IS NULL
+                ) v
+                WHERE a.idx = v.idx AND v.next_idx IS NOT NULL";
+            sqlx::query(sql_str)
+                .bind(&columns[0])
+                .bind(&columns[1])
+                .bind(&columns[3])
+                .bind(&columns[5])
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        AnyOk(num_of_versions)
+    }
+
+    fn version_key(record: &EntityAttribute) -> (String, String, String, String) {
+        (
+            record.entity_id.clone(),
+            record.entity_type.clone(),
+            record.external_db_name.clone(),
+            record.external_id.clone(),
+        )
+    }
+}
+
+impl CheckData for EntityAttribute {
+    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
+        Self::check_csv_is_valid_default::<EntityAttribute>(filepath)
+    }
+
+    fn unique_fields() -> Vec<String> {
+        vec![
+            "entity_id".to_string(),
+            "entity_type".to_string(),
+            "external_db_name".to_string(),
+            "external_id".to_string(),
+        ]
+    }
+
+    fn fields() -> Vec<String> {
+        vec![
+            "entity_id".to_string(),
+            "entity_type".to_string(),
+            "description".to_string(),
+            "external_db_name".to_string(),
+            "external_url".to_string(),
+            "external_id".to_string(),
+            "valid_from".to_string(),
+        ]
+    }
+}
+
+/// An entity queried by a user, it's saved in the biomedgps_entity_activity table and aggregated into the trending entities.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct EntityActivity {
+    #[oai(read_only)]
+    pub id: i64,
+    pub username: String,
+    pub entity_id: String,
+    pub entity_type: String,
+    pub endpoint: String,
+
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl EntityActivity {
+    /// Record the entities queried by a user. The activity log is only used for the analytics, so the failures are logged instead of being returned to break the query.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `username` - The user who queried the entities
+    /// * `endpoint` - The operation id of the endpoint, such as fetchNodes
+    /// * `node_ids` - The node ids, such as ["Disease::MESH:D000001", "Gene::ENTREZ:1"]. The invalid ones are ignored.
+    pub async fn record(pool: &sqlx::PgPool, username: &str, endpoint: &str, node_ids: &Vec<&str>) {
+        let (entity_types, entity_ids): (Vec<String>, Vec<String>) = node_ids
+            .iter()
+            .filter_map(|node_id| node_id.split_once(COMPOSED_ENTITY_DELIMITER))
+            .map(|(entity_type, entity_id)| (entity_type.to_string(), entity_id.to_string()))
+            .unzip();
+
+        if entity_ids.is_empty() {
+            return;
+        }
+
+        let sql_str = "INSERT INTO biomedgps_entity_activity (username, entity_id, entity_type, endpoint) SELECT $1, entity_id, entity_type, $4 FROM UNNEST($2::VARCHAR[], $3::VARCHAR[]) AS t(entity_id, entity_type)";
+        match sqlx::query(sql_str)
+            .bind(username)
+            .bind(&entity_ids)
+            .bind(&entity_types)
+            .bind(endpoint)
+            .execute(pool)
+            .await
+        {
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to record the entity activity: {}", e);
+            }
+        }
+    }
+}
+
+/// The kinds of the items in the activity feed, each one is a tuple of the item type, the table, the owner column, the time column, the id expression and the title expression.
+pub const ACTIVITY_FEED_SOURCES: [(&str, &str, &str, &str, &str, &str); 5] = [
+    (
+        "subgraph",
+        "biomedgps_subgraph",
+        "owner",
+        "created_time",
+        "id",
+        "name",
+    ),
+    (
+        "curation",
+        "biomedgps_knowledge_curation",
+        "curator",
+        "created_at",
+        "id::TEXT",
+        "source_name || ' ' || relation_type || ' ' || target_name",
+    ),
+    (
+        "export_job",
+        "biomedgps_export_job",
+        "owner",
+        "created_at",
+        "id::TEXT",
+        "format || ' export (' || status || ')'",
+    ),
+    (
+        "graph_view",
+        "biomedgps_graph_view",
+        "owner",
+        "updated_at",
+        "id::TEXT",
+        "name",
+    ),
+    (
+        "node_tag",
+        "biomedgps_node_tag",
+        "owner",
+        "created_at",
+        "id::TEXT",
+        "tag || ': ' || node_id",
+    ),
+];
+
+/// An item which is created or updated by a user recently, such as a subgraph, a curated knowledge or an export job. It's used by the activity feed of the workspace home page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ActivityFeedItem {
+    // The type of the item, such as subgraph, curation, export_job, graph_view or node_tag.
+    pub item_type: String,
+    pub item_id: String,
+    pub title: String,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl ActivityFeedItem {
+    /// Fetch the recent items of a user across the owned tables, the newest first. Each table only reads its newest rows by the (owner, time) index, so it's cheap even if the user has many items.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `username` - The owner of the items
+    /// * `item_types` - Only the items with these types are returned, None means all types
+    /// * `page` - The page number, starts from 1
+    /// * `page_size` - The number of the items per page
+    ///
+    /// # Returns
+    /// * `Result<Vec<ActivityFeedItem>, anyhow::Error>` - The items or an error
+    pub async fn get_records(
+        pool: &sqlx::PgPool,
+        username: &str,
+        item_types: &Option<Vec<String>>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<Vec<ActivityFeedItem>, anyhow::Error> {
+        let sources = ACTIVITY_FEED_SOURCES
+            .iter()
+            .filter(|(item_type, ..)| match item_types {
+                Some(item_types) => item_types.iter().any(|t| t == item_type),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+
+        if sources.is_empty() {
+            return AnyOk(vec![]);
+        }
+
+        // A table contributes at most `page * page_size` items to the merged page.
+        let limit = page.max(1) * page_size;
+        let offset = (page.max(1) - 1) * page_size;
+        let sql_str = format!(
+            "SELECT item_type, item_id, title, created_at FROM ({}) AS items ORDER BY created_at DESC, item_type, item_id LIMIT {} OFFSET {}",
+            sources
+                .iter()
+                .map(|(item_type, table, owner_column, time_column, id_expr, title_expr)| {
+                    format!(
+                        "(SELECT '{item_type}' AS item_type, {id_expr} AS item_id, {title_expr} AS title, {time_column} AS created_at FROM {table} WHERE {owner_column} = $1 ORDER BY {time_column} DESC LIMIT {limit})",
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" UNION ALL "),
+            page_size,
+            offset
+        );
+
+        let items = sqlx::query_as::<_, ActivityFeedItem>(&sql_str)
+            .bind(username)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(items)
+    }
+}
+
+// The default number of the trending entities which are kept for each week.
+pub const DEFAULT_NUM_TRENDING_ENTITIES: usize = 100;
+
+/// The most queried entities in a week, it powers the "trending nodes" widget on the homepage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct TrendingEntity {
+    // The first day (Monday) of the week.
+    pub week: NaiveDate,
+    pub entity_id: String,
+    pub entity_type: String,
+    // The name of the entity, it's None if the entity doesn't exist in the entity table.
+    #[oai(skip_serializing_if_is_none)]
+    pub entity_name: Option<String>,
+    pub num_queries: i64,
+    pub num_users: i64,
+    pub rank: i32,
+}
+
+impl TrendingEntity {
+    /// Aggregate the activity log into the trending entities per week. The weeks since `since` (the whole log if None) are recomputed, so it's safe to run the job repeatedly, such as by a daily cron job.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `since` - Only the weeks which contain or follow this time are recomputed
+    /// * `top_n` - The number of the trending entities to keep for each week
+    ///
+    /// # Returns
+    /// * `Result<u64, anyhow::Error>` - The number of the trending entities or an error
+    pub async fn refresh(
+        pool: &sqlx::PgPool,
+        since: Option<DateTime<Utc>>,
+        top_n: usize,
+    ) -> Result<u64, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        let since_week = "date_trunc('week', COALESCE($1::TIMESTAMPTZ, '-infinity'::TIMESTAMPTZ))";
+
+        let sql_str = format!(
+            "DELETE FROM biomedgps_trending_entity WHERE week >= {}",
+            since_week
+        );
+        sqlx::query(&sql_str).bind(since).execute(&mut tx).await?;
+
+        let sql_str = format!(
+            "INSERT INTO biomedgps_trending_entity (week, entity_id, entity_type, num_queries, num_users, rank)
+             SELECT week, entity_id, entity_type, num_queries, num_users, rank FROM (
+                SELECT
+                    date_trunc('week', created_at)::DATE AS week,
+                    entity_id,
+                    entity_type,
+                    COUNT(*) AS num_queries,
+                    COUNT(DISTINCT username) AS num_users,
+                    ROW_NUMBER() OVER (PARTITION BY date_trunc('week', created_at) ORDER BY COUNT(*) DESC, COUNT(DISTINCT username) DESC, entity_id)::INTEGER AS rank
+                FROM biomedgps_entity_activity
+                WHERE created_at >= {}
+                GROUP BY date_trunc('week', created_at), entity_id, entity_type
+             ) AS ranked_entities
+             WHERE rank <= $2",
+            since_week
+        );
+        let result = sqlx::query(&sql_str)
+            .bind(since)
+            .bind(top_n as i64)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        AnyOk(result.rows_affected())
+    }
+
+    /// Get the trending entities of a week.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `week` - Any day in the week, the latest aggregated week is used if None
+    /// * `entity_type` - Only the entities with the type are returned if it's not None, such as Disease
+    /// * `topk` - The number of the entities to return
+    pub async fn get_records(
+        pool: &sqlx::PgPool,
+        week: Option<NaiveDate>,
+        entity_type: Option<&str>,
+        topk: u64,
+    ) -> Result<Vec<TrendingEntity>, anyhow::Error> {
+        let sql_str = "SELECT t.week, t.entity_id, t.entity_type, e.name AS entity_name, t.num_queries, t.num_users, t.rank
+                       FROM biomedgps_trending_entity t
+                       LEFT JOIN biomedgps_entity e ON t.entity_id = e.id AND t.entity_type = e.label
+                       WHERE t.week = COALESCE(date_trunc('week', $1::DATE)::DATE, (SELECT MAX(week) FROM biomedgps_trending_entity))
+                       AND ($2::VARCHAR IS NULL OR t.entity_type = $2)
+                       ORDER BY t.rank
+                       LIMIT $3";
+        let records = sqlx::query_as::<_, TrendingEntity>(sql_str)
+            .bind(week)
+            .bind(entity_type)
+            .bind(topk as i64)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct Statistics {
+    entity_stat: Vec<EntityMetadata>,
+    relation_stat: Vec<RelationMetadata>,
+}
+
+impl Statistics {
+    pub fn new(
+        entity_stat: Vec<EntityMetadata>,
+        relation_stat: Vec<RelationMetadata>,
+    ) -> Statistics {
+        Statistics {
+            entity_stat: entity_stat,
+            relation_stat: relation_stat,
+        }
+    }
+}
+
+/// The number of the entities (by label) or relations (by relation type and dataset) in Postgres and Neo4j.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct CountComparison {
+    // The entity label or the relation type.
+    pub key: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub dataset: Option<String>,
+    pub postgres_count: i64,
+    pub neo4j_count: i64,
+    // postgres_count - neo4j_count
+    pub difference: i64,
+}
+
+impl CountComparison {
+    pub fn new(key: &str, dataset: Option<&str>, postgres_count: i64, neo4j_count: i64) -> Self {
+        CountComparison {
+            key: key.to_string(),
+            dataset: dataset.map(|d| d.to_string()),
+            postgres_count,
+            neo4j_count,
+            difference: postgres_count - neo4j_count,
+        }
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.difference == 0
+    }
+}
+
+/// The consistency report between Postgres and Neo4j, the Postgres counts come from the metadata tables.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct GraphConsistencyReport {
+    pub entity_counts: Vec<CountComparison>,
+    pub relation_counts: Vec<CountComparison>,
+    pub num_discrepancies: u64,
+    pub consistent: bool,
+}
+
+impl GraphConsistencyReport {
+    pub fn new(
+        entity_counts: Vec<CountComparison>,
+        relation_counts: Vec<CountComparison>,
+        only_discrepancies: bool,
+    ) -> Self {
+        let num_discrepancies = entity_counts
+            .iter()
+            .chain(relation_counts.iter())
+            .filter(|c| !c.is_consistent())
+            .count() as u64;
+
+        let (entity_counts, relation_counts) = if only_discrepancies {
+            (
+                entity_counts
+                    .into_iter()
+                    .filter(|c| !c.is_consistent())
+                    .collect(),
+                relation_counts
+                    .into_iter()
+                    .filter(|c| !c.is_consistent())
+                    .collect(),
+            )
+        } else {
+            (entity_counts, relation_counts)
+        };
+
+        GraphConsistencyReport {
+            entity_counts,
+            relation_counts,
+            num_discrepancies,
+            consistent: num_discrepancies == 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct EntityMetadata {
+    // Ignore this field when deserialize from json
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of resource should be between 1 and 64."
+    ))]
+    pub resource: String,
+
+    #[validate(regex(
+        path = "ENTITY_LABEL_REGEX",
+        message = "The entity type should match ^[A-Za-z]+$. Such as Disease."
+    ))]
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of entity_type should be between 1 and 64."
+    ))]
+    pub entity_type: String,
+
+    pub entity_count: i64,
+}
+
+impl CheckData for EntityMetadata {
+    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
+        Self::check_csv_is_valid_default::<EntityMetadata>(filepath)
+    }
+
+    fn unique_fields() -> Vec<String> {
+        vec!["resource".to_string(), "entity_type".to_string()]
+    }
+
+    fn fields() -> Vec<String> {
+        vec![
+            "resource".to_string(),
+            "entity_type".to_string(),
+            "entity_count".to_string(),
+        ]
+    }
+}
+
+impl EntityMetadata {
+    pub async fn get_entity_metadata(
+        pool: &sqlx::PgPool,
+    ) -> Result<Vec<EntityMetadata>, anyhow::Error> {
+        if let Some(entity_metadata) = get_cached_options(&ENTITY_METADATA_CACHE, "") {
+            return AnyOk(entity_metadata);
+        }
+
+        let sql_str = "SELECT * FROM biomedgps_entity_metadata";
+        let entity_metadata = sqlx::query_as::<_, EntityMetadata>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        set_cached_options(&ENTITY_METADATA_CACHE, "", &entity_metadata);
+        AnyOk(entity_metadata)
+    }
+
+    /// Count the entities which are linked by the relations of the dataset, grouped by the entity type. The entities don't belong to any dataset, so the entity metadata can't be filtered by the dataset.
+    pub async fn count_entities_in_dataset(
+        pool: &sqlx::PgPool,
+        dataset: &str,
+    ) -> Result<Vec<(String, i64)>, anyhow::Error> {
+        let sql_str = "SELECT entity_type, COUNT(*) AS entity_count FROM (
+            SELECT source_type AS entity_type, source_id AS entity_id FROM biomedgps_relation WHERE dataset = $1
+            UNION
+            SELECT target_type AS entity_type, target_id AS entity_id FROM biomedgps_relation WHERE dataset = $1
+        ) AS linked_entities GROUP BY entity_type";
+        let counts = sqlx::query_as::<_, (String, i64)>(sql_str)
+            .bind(dataset)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(counts)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct RelationMetadata {
+    // Ignore this field when deserialize from json
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    // The resource of the relation. such as STRING, BIOMEDGPS, etc.
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of resource should be between 1 and 64."
+    ))]
+    pub resource: String,
+
+    // The dataset of the relation for labeling different datasets. such DRKG, HSDN, CTD, CuratedFindings, etc. Users can choose different dataset combinations to build a knowledge graph and train models.
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of dataset should be between 1 and 64."
+    ))]
+    pub dataset: String,
+
+    // The relation type, such as STRING::ACTIVATOR::Gene:Compound, STRING::INHIBITOR::Gene:Compound, etc.
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of relation_type should be between 1 and 64."
+    ))]
+    pub relation_type: String,
+
+    // The formatted relation type, such as BIOMEDGPS::ACTIVATOR::Gene::Compound, BIOMEDGPS::TREATMENT::Compound::Disease, etc.
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of formatted_relation_type should be between 1 and 64."
+    ))]
+    pub formatted_relation_type: String,
+
+    pub relation_count: i64,
+
+    #[validate(regex = "ENTITY_LABEL_REGEX")]
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of start_entity_type should be between 1 and 64."
+    ))]
+    pub start_entity_type: String,
+
+    #[validate(regex = "ENTITY_LABEL_REGEX")]
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of end_entity_type should be between 1 and 64."
+    ))]
+    pub end_entity_type: String,
+
+    // To describe the relation type with a human-readable sentence.
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+}
+
+impl CheckData for RelationMetadata {
+    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
+        Self::check_csv_is_valid_default::<RelationMetadata>(filepath)
+    }
+
+    fn unique_fields() -> Vec<String> {
+        vec![
+            "dataset".to_string(),
+            "resource".to_string(),
+            "formatted_relation_type".to_string(),
+            "relation_type".to_string(),
+            "start_entity_type".to_string(),
+            "end_entity_type".to_string(),
+        ]
+    }
+
+    fn fields() -> Vec<String> {
+        vec![
+            "dataset".to_string(),
+            "resource".to_string(),
+            "relation_type".to_string(),
+            "formatted_relation_type".to_string(),
+            "relation_count".to_string(),
+            "start_entity_type".to_string(),
+            "end_entity_type".to_string(),
+            "description".to_string(),
+        ]
+    }
+}
+
+impl RelationMetadata {
+    pub async fn get_relation_metadata(
+        pool: &sqlx::PgPool,
+    ) -> Result<Vec<RelationMetadata>, anyhow::Error> {
+        if let Some(relation_metadata) = get_cached_options(&RELATION_METADATA_CACHE, "") {
+            return AnyOk(relation_metadata);
+        }
+
+        let sql_str = "SELECT * FROM biomedgps_relation_metadata";
+        let relation_metadata = sqlx::query_as::<_, RelationMetadata>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        set_cached_options(&RELATION_METADATA_CACHE, "", &relation_metadata);
+        AnyOk(relation_metadata)
+    }
+}
+
+// The metadata and the enum values are cached for a while, because they are only changed when the metadata tables are reimported. The caches are also cleared when the metadata tables are updated, so the TTL only bounds the staleness when a notification is missed.
+pub const ENUM_CACHE_TTL_SECS: u64 = 300;
+
+/// The Postgres channel which is notified when the metadata tables are updated, so the servers clear their caches even if the tables are updated by importdb in another process.
+pub const METADATA_UPDATED_CHANNEL: &str = "biomedgps_metadata_updated";
+
+lazy_static! {
+    // The key is the dataset, the empty string means all datasets.
+    static ref RELATION_TYPE_CACHE: Mutex<HashMap<String, (Instant, Vec<RelationTypeOption>)>> =
+        Mutex::new(HashMap::new());
+    static ref ENTITY_LABEL_CACHE: Mutex<HashMap<String, (Instant, Vec<EntityLabelOption>)>> =
+        Mutex::new(HashMap::new());
+    // The whole metadata tables are cached with the empty string as the key.
+    static ref ENTITY_METADATA_CACHE: Mutex<HashMap<String, (Instant, Vec<EntityMetadata>)>> =
+        Mutex::new(HashMap::new());
+    static ref RELATION_METADATA_CACHE: Mutex<HashMap<String, (Instant, Vec<RelationMetadata>)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Clear the cached metadata and enum values of this process.
+pub fn clear_metadata_caches() {
+    RELATION_TYPE_CACHE.lock().unwrap().clear();
+    ENTITY_LABEL_CACHE.lock().unwrap().clear();
+    ENTITY_METADATA_CACHE.lock().unwrap().clear();
+    RELATION_METADATA_CACHE.lock().unwrap().clear();
+}
+
+/// Clear the caches of this process and notify the other processes which listen to the metadata channel, it's called after the metadata tables are updated.
+pub async fn notify_metadata_updated(pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+    clear_metadata_caches();
+    sqlx::query("SELECT pg_notify($1, '')")
+        .bind(METADATA_UPDATED_CHANNEL)
+        .execute(pool)
+        .await?;
+
+    AnyOk(())
+}
+
+/// Clear the metadata caches whenever the metadata tables are updated by any process. It runs until the server stops, and the caches are also cleared after a reconnection because the notifications might be missed while disconnected.
+pub async fn listen_metadata_updates(pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+    let mut listener = sqlx::postgres::PgListener::connect_with(pool).await?;
+    listener.listen(METADATA_UPDATED_CHANNEL).await?;
+    info!(
+        "Listen to the {} channel for clearing the metadata caches.",
+        METADATA_UPDATED_CHANNEL
+    );
+
+    loop {
+        match listener.try_recv().await {
+            Ok(Some(_)) => {
+                debug!("The metadata tables are updated, clear the metadata caches.");
+            }
+            Ok(None) => {
+                warn!("The connection of the metadata listener is lost, reconnecting.");
+            }
+            Err(err) => {
+                warn!("Failed to receive the metadata notifications: {}", err);
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+        }
+        clear_metadata_caches();
+    }
+}
+
+fn get_cached_options<T: Clone>(
+    cache: &Mutex<HashMap<String, (Instant, Vec<T>)>>,
+    key: &str,
+) -> Option<Vec<T>> {
+    let cache = cache.lock().unwrap();
+    match cache.get(key) {
+        Some((cached_at, options))
+            if cached_at.elapsed() < Duration::from_secs(ENUM_CACHE_TTL_SECS) =>
+        {
+            Some(options.clone())
+        }
+        _ => None,
+    }
+}
+
+fn set_cached_options<T: Clone>(
+    cache: &Mutex<HashMap<String, (Instant, Vec<T>)>>,
+    key: &str,
+    options: &Vec<T>,
+) {
+    let mut cache = cache.lock().unwrap();
+    cache.insert(key.to_string(), (Instant::now(), options.clone()));
+}
+
+/// A relation type for the dropdown in the UI, the count is summed over all resources of the selected datasets.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct RelationTypeOption {
+    pub relation_type: String,
+    pub formatted_relation_type: String,
+    pub start_entity_type: String,
+    pub end_entity_type: String,
+    pub relation_count: i64,
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+}
+
+impl RelationTypeOption {
+    /// Fetch the relation types from the biomedgps_relation_metadata table.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - Only the relation types in the dataset will be returned, None means all datasets.
+    ///
+    pub async fn get_relation_types(
+        pool: &sqlx::PgPool,
+        dataset: Option<&str>,
+    ) -> Result<Vec<RelationTypeOption>, anyhow::Error> {
+        let key = dataset.unwrap_or("");
+        if let Some(options) = get_cached_options(&RELATION_TYPE_CACHE, key) {
+            return AnyOk(options);
+        }
+
+        let sql_str = "SELECT relation_type, formatted_relation_type, start_entity_type, end_entity_type,
+                              SUM(relation_count)::BIGINT AS relation_count, MAX(description) AS description
+                       FROM biomedgps_relation_metadata
+                       WHERE $1::VARCHAR IS NULL OR dataset = $1
+                       GROUP BY relation_type, formatted_relation_type, start_entity_type, end_entity_type
+                       ORDER BY relation_type";
+        let options = sqlx::query_as::<_, RelationTypeOption>(sql_str)
+            .bind(dataset)
+            .fetch_all(pool)
+            .await?;
+
+        set_cached_options(&RELATION_TYPE_CACHE, key, &options);
+        AnyOk(options)
+    }
+}
+
+/// An entity label for the dropdown in the UI, the count is summed over all resources.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct EntityLabelOption {
+    pub entity_type: String,
+    pub entity_count: i64,
+}
+
+impl EntityLabelOption {
+    /// Fetch the entity labels from the biomedgps_entity_metadata table.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - The entity metadata doesn't have a dataset column, so only the entity labels which are used by the relations in the dataset will be returned. None means all entity labels.
+    ///
+    pub async fn get_entity_labels(
+        pool: &sqlx::PgPool,
+        dataset: Option<&str>,
+    ) -> Result<Vec<EntityLabelOption>, anyhow::Error> {
+        let key = dataset.unwrap_or("");
+        if let Some(options) = get_cached_options(&ENTITY_LABEL_CACHE, key) {
+            return AnyOk(options);
+        }
+
+        let sql_str = "SELECT entity_type, SUM(entity_count)::BIGINT AS entity_count
+                       FROM biomedgps_entity_metadata
+                       WHERE $1::VARCHAR IS NULL OR entity_type IN (
+                           SELECT start_entity_type FROM biomedgps_relation_metadata WHERE dataset = $1
+                           UNION
+                           SELECT end_entity_type FROM biomedgps_relation_metadata WHERE dataset = $1
+                       )
+                       GROUP BY entity_type
+                       ORDER BY entity_type";
+        let options = sqlx::query_as::<_, EntityLabelOption>(sql_str)
+            .bind(dataset)
+            .fetch_all(pool)
+            .await?;
+
+        set_cached_options(&ENTITY_LABEL_CACHE, key, &options);
+        AnyOk(options)
+    }
+}
+
+/// Which curated knowledges should be merged into the graph queries.
+///
+/// * `own` - Only the knowledges curated by the current user.
+/// * `project` - The knowledges curated by any member of the projects which the current user belongs to.
+/// * `none` - Only the public relations, it is the default behavior.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum IncludeCurated {
+    Own,
+    Project,
+    None,
+}
+
+impl Default for IncludeCurated {
+    fn default() -> Self {
+        IncludeCurated::None
+    }
+}
+
+/// The owner information which is used to decide which curated knowledges are visible for the current user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CuratedKnowledgeFilter {
+    pub include_curated: IncludeCurated,
+    pub curator: String,
+    pub projects: Vec<i32>,
+}
+
+impl CuratedKnowledgeFilter {
+    pub fn new(include_curated: IncludeCurated, curator: &str, projects: &Vec<i32>) -> Self {
+        CuratedKnowledgeFilter {
+            include_curated,
+            curator: curator.to_string(),
+            // The negative project id means the user doesn't belong to any project.
+            projects: projects.iter().filter(|&id| *id >= 0).cloned().collect(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self.include_curated {
+            IncludeCurated::Own => true,
+            IncludeCurated::Project => !self.projects.is_empty(),
+            IncludeCurated::None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Object, PartialEq, Eq)]
+pub struct Payload {
+    pub project_id: String,
+    pub organization_id: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
 pub struct KnowledgeCuration {
@@ -847,7 +2548,12 @@ pub struct KnowledgeCuration {
     ))]
     pub curator: String,
 
-    #[validate(range(min = 1, message = "pmid must be greater than 0"))]
+    #[serde(deserialize_with = "deserialize_pmid")]
+    #[validate(range(
+        min = 1,
+        max = "MAX_PMID",
+        message = "pmid must be between 1 and 99999999"
+    ))]
     pub pmid: i64,
 
     // The payload field is a jsonb field which contains the project_id and organization_id.
@@ -855,6 +2561,53 @@ pub struct KnowledgeCuration {
 }
 
 impl KnowledgeCuration {
+    /// The condition of the curated knowledges which are visible in the owner scope, i.e. the own curated knowledges and the curated knowledges of the organizations and projects. All curated knowledges are visible if the scope has no username, such as the admins.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::{KnowledgeCuration, OwnerScope};
+    ///
+    /// assert_eq!(KnowledgeCuration::visible_condition(&OwnerScope::new("", &vec![], &vec![])), "TRUE");
+    /// assert_eq!(
+    ///     KnowledgeCuration::visible_condition(&OwnerScope::new("o'neil", &vec![-1], &vec![1, 2])),
+    ///     "(curator = 'o''neil' OR payload->>'organization_id' = ANY(string_to_array('', ',')) OR payload->>'project_id' = ANY(string_to_array('1,2', ',')))"
+    /// );
+    /// ```
+    pub fn visible_condition(scope: &OwnerScope) -> String {
+        if scope.username.is_empty() {
+            return "TRUE".to_string();
+        }
+
+        format!(
+            "(curator = '{}' OR payload->>'organization_id' = ANY(string_to_array('{}', ',')) OR payload->>'project_id' = ANY(string_to_array('{}', ',')))",
+            scope.username.replace("'", "''"),
+            OwnerScope::join_ids(&scope.organizations),
+            OwnerScope::join_ids(&scope.projects)
+        )
+    }
+
+    /// Check the source and target ids by the validation rules of their entity types, return a message for each violation. The unmapped entities (Unknown:Unknown) are skipped.
+    pub fn check_rules(&self) -> Vec<String> {
+        let mut violations = vec![];
+        for (entity_type, entity_id) in [
+            (&self.source_type, &self.source_id),
+            (&self.target_type, &self.target_id),
+        ] {
+            if entity_id == "Unknown:Unknown" {
+                continue;
+            }
+
+            if let Some(violation) = ENTITY_VALIDATION_RULES
+                .get(entity_type)
+                .and_then(|rule| rule.check_id(entity_id))
+            {
+                violations.push(violation);
+            }
+        }
+
+        violations
+    }
+
     pub fn to_relation(&self) -> Relation {
         Relation {
             id: self.id,
@@ -868,7 +2621,18 @@ impl KnowledgeCuration {
             resource: self.curator.clone(),
             dataset: Some(DEFAULT_DATASET_NAME.to_string()),
             pmids: Some(format!("{}", self.pmid)),
+            tissue: None,
+            tissue_id: None,
+            cell_type: None,
+            cell_type_id: None,
             score: None,
+            datasets: None,
+            resources: None,
+            license: None,
+            n_pmids: None,
+            n_datasets: None,
+            n_curations: None,
+            verdict: None,
         }
     }
 
@@ -904,10 +2668,12 @@ impl KnowledgeCuration {
             format!("payload->>'organization_id' IS NOT NULL")
         };
 
-        let curator_qstr = if project_id < 0 && organization_id < 0 {
-            format!("curator = '{}'", curator)
+        // The curator is bound as a parameter, it's only used when no project or organization is given.
+        let by_curator = project_id < 0 && organization_id < 0;
+        let curator_qstr = if by_curator {
+            "curator = $1"
         } else {
-            format!("curator IS NOT NULL")
+            "curator IS NOT NULL"
         };
 
         let where_str = format!(
@@ -939,18 +2705,26 @@ impl KnowledgeCuration {
             where_str, order_by_str, limit, offset
         );
 
-        let records = sqlx::query_as::<_, KnowledgeCuration>(sql_str.as_str())
-            .fetch_all(pool)
-            .await?;
+        let query = sqlx::query_as::<_, KnowledgeCuration>(sql_str.as_str());
+        let query = if by_curator {
+            query.bind(curator)
+        } else {
+            query
+        };
+        let records = query.fetch_all(pool).await?;
 
         let sql_str = format!(
             "SELECT COUNT(*) FROM biomedgps_knowledge_curation WHERE {}",
             where_str
         );
 
-        let total = sqlx::query_as::<_, (i64,)>(sql_str.as_str())
-            .fetch_one(pool)
-            .await?;
+        let query = sqlx::query_as::<_, (i64,)>(sql_str.as_str());
+        let query = if by_curator {
+            query.bind(curator)
+        } else {
+            query
+        };
+        let total = query.fetch_one(pool).await?;
 
         AnyOk(RecordResponse {
             records: records,
@@ -960,6 +2734,58 @@ impl KnowledgeCuration {
         })
     }
 
+    /// Fetch the curated knowledges which are visible for the current user and connect to the given nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `node_ids` - The composed node ids, such as `Gene::ENTREZ:123`.
+    /// * `filter` - Which curated knowledges are visible for the current user.
+    /// * `both_ends` - If true, both the source and the target must be in the node ids, otherwise one of them is enough.
+    ///
+    pub async fn fetch_by_node_ids(
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<&str>,
+        filter: &CuratedKnowledgeFilter,
+        both_ends: bool,
+    ) -> Result<Vec<KnowledgeCuration>, anyhow::Error> {
+        if !filter.is_enabled() || node_ids.is_empty() {
+            return AnyOk(vec![]);
+        }
+
+        let owner_qstr = match filter.include_curated {
+            IncludeCurated::Own => "curator = $2",
+            _ => "payload->>'project_id' = ANY($2)",
+        };
+
+        let node_qstr = format!(
+            "(source_type || '{delimiter}' || source_id) = ANY($1) {op} (target_type || '{delimiter}' || target_id) = ANY($1)",
+            delimiter = COMPOSED_ENTITY_DELIMITER,
+            op = if both_ends { "AND" } else { "OR" }
+        );
+
+        let sql_str = format!(
+            "SELECT * FROM biomedgps_knowledge_curation WHERE {} AND ({})",
+            owner_qstr, node_qstr
+        );
+
+        let query = sqlx::query_as::<_, KnowledgeCuration>(sql_str.as_str()).bind(node_ids);
+        let query = match filter.include_curated {
+            IncludeCurated::Own => query.bind(&filter.curator),
+            _ => query.bind(
+                filter
+                    .projects
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>(),
+            ),
+        };
+
+        let records = query.fetch_all(pool).await?;
+
+        AnyOk(records)
+    }
+
     fn get_value(key: &str, json: &serde_json::Value) -> Result<String, anyhow::Error> {
         match json[key].as_str() {
             Some(value) => Ok(value.to_string()),
@@ -967,6 +2793,27 @@ impl KnowledgeCuration {
         }
     }
 
+    /// Add the delta to the curation count of the relations which have the same source, relation type and target as the curated knowledge, it's called after each curation write. The count isn't recounted, because the curated knowledges of the others might be invisible in an owner-scoped transaction.
+    async fn adjust_relation_counts(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        delta: i32,
+    ) -> Result<u64, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_relation SET n_curations = GREATEST(n_curations + $1, 0)
+                       WHERE relation_type = $2 AND source_id = $3 AND source_type = $4 AND target_id = $5 AND target_type = $6";
+        let result = sqlx::query(sql_str)
+            .bind(delta)
+            .bind(&self.relation_type)
+            .bind(&self.source_id)
+            .bind(&self.source_type)
+            .bind(&self.target_id)
+            .bind(&self.target_type)
+            .execute(conn)
+            .await?;
+
+        AnyOk(result.rows_affected())
+    }
+
     pub async fn insert(&self, pool: &sqlx::PgPool) -> Result<KnowledgeCuration, anyhow::Error> {
         let sql_str = "INSERT INTO biomedgps_knowledge_curation (relation_type, source_name, source_type, source_id, target_name, target_type, target_id, key_sentence, curator, pmid, payload) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING *";
         let payload = match &self.payload {
@@ -980,6 +2827,7 @@ impl KnowledgeCuration {
             }),
         };
 
+        let mut tx = pool.begin().await?;
         let knowledge_curation = sqlx::query_as::<_, KnowledgeCuration>(sql_str)
             .bind(&self.relation_type)
             .bind(&self.source_name)
@@ -992,17 +2840,29 @@ impl KnowledgeCuration {
             .bind(&self.curator)
             .bind(&self.pmid)
             .bind(&payload)
-            .fetch_one(pool)
+            .fetch_one(&mut tx)
             .await?;
+        knowledge_curation
+            .adjust_relation_counts(&mut tx, 1)
+            .await?;
+        tx.commit().await?;
 
         AnyOk(knowledge_curation)
     }
 
     pub async fn update(
         &self,
-        pool: &sqlx::PgPool,
+        conn: &mut sqlx::PgConnection,
         id: i64,
     ) -> Result<KnowledgeCuration, anyhow::Error> {
+        // The curated knowledge might be moved to another relation, so the counts of both relations are adjusted.
+        let original = sqlx::query_as::<_, KnowledgeCuration>(
+            "SELECT * FROM biomedgps_knowledge_curation WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
         let sql_str = "UPDATE biomedgps_knowledge_curation SET relation_type = $1, source_name = $2, source_type = $3, source_id = $4, target_name = $5, target_type = $6, target_id = $7, key_sentence = $8, created_at = now(), pmid = $9 WHERE id = $10 RETURNING *";
         let knowledge_curation = sqlx::query_as::<_, KnowledgeCuration>(sql_str)
             .bind(&self.relation_type)
@@ -1015,17 +2875,27 @@ impl KnowledgeCuration {
             .bind(&self.key_sentence)
             .bind(&self.pmid)
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(&mut *conn)
+            .await?;
+        original.adjust_relation_counts(&mut *conn, -1).await?;
+        knowledge_curation
+            .adjust_relation_counts(&mut *conn, 1)
             .await?;
 
         AnyOk(knowledge_curation)
     }
 
-    pub async fn delete(pool: &sqlx::PgPool, id: i64) -> Result<KnowledgeCuration, anyhow::Error> {
+    pub async fn delete(
+        conn: &mut sqlx::PgConnection,
+        id: i64,
+    ) -> Result<KnowledgeCuration, anyhow::Error> {
         let sql_str = "DELETE FROM biomedgps_knowledge_curation WHERE id = $1 RETURNING *";
         let knowledge_curation = sqlx::query_as::<_, KnowledgeCuration>(sql_str)
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(&mut *conn)
+            .await?;
+        knowledge_curation
+            .adjust_relation_counts(&mut *conn, -1)
             .await?;
 
         AnyOk(knowledge_curation)
@@ -1063,6 +2933,269 @@ impl CheckData for KnowledgeCuration {
             "pmid".to_string(),
         ]
     }
+
+    fn normalize_field(field: &str, value: &str) -> String {
+        // Strip the PMID: prefix, so the pmid column can be imported as a number.
+        if field == "pmid" {
+            normalize_pmids(value).unwrap_or(value.to_string())
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// The full-text search of the key sentences uses the same text search config as the indexes in the `add_fulltext_search_index` migration, otherwise the indexes are not used.
+const FULLTEXT_SEARCH_CONFIG: &str = "english";
+
+/// The relevance of a curated key sentence is halved after the days, so the recent curations are ranked higher.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Get the url of the PubMed webpage of a publication.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::core::get_pubmed_url;
+///
+/// assert_eq!(get_pubmed_url("32353859"), "https://pubmed.ncbi.nlm.nih.gov/32353859/");
+/// ```
+pub fn get_pubmed_url(pmid: &str) -> String {
+    format!("https://pubmed.ncbi.nlm.nih.gov/{}/", pmid.trim())
+}
+
+/// A key sentence which matches the full-text search, the matched terms in the snippet are wrapped by `<mark>` and `</mark>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct KeySentenceMatch {
+    pub key_sentence: String,
+    pub snippet: String,
+    /// Where the key sentence comes from, it's one of curation and relation.
+    pub source: String,
+    /// The curated knowledges which share the key sentence, it's empty for the key sentences of the relations.
+    pub curations: Vec<KnowledgeCuration>,
+    /// The relations which share the key sentence, it's empty for the curated key sentences.
+    pub relation_ids: Vec<i64>,
+    /// The webpages of the publications which the key sentence comes from.
+    pub webpages: Vec<String>,
+    pub score: f64,
+}
+
+impl KeySentenceMatch {
+    /// Search the key sentences of the curated knowledges and the relations by a phrase, such as `"tumor suppressor" p53`. The quoted words are matched as a phrase. The curated key sentences are ranked by the relevance and the recency, the key sentences of the relations have no created time, so they're ranked by the relevance only.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - The connection which is scoped by an [`OwnerScope`].
+    /// * `text` - The phrase to search.
+    /// * `filter` - Which curated knowledges are visible for the current user, the curated key sentences are skipped if it's disabled.
+    /// * `include_relations` - Whether to search the key sentences of the relations.
+    /// * `limit` - The max number of the matched key sentences.
+    ///
+    pub async fn search(
+        conn: &mut sqlx::PgConnection,
+        text: &str,
+        filter: &CuratedKnowledgeFilter,
+        include_relations: bool,
+        limit: i64,
+    ) -> Result<Vec<KeySentenceMatch>, anyhow::Error> {
+        let mut matches = vec![];
+
+        if filter.is_enabled() {
+            let owner_qstr = match filter.include_curated {
+                IncludeCurated::Own => "curator = ANY($2)",
+                _ => "payload->>'project_id' = ANY($2)",
+            };
+            let sql_str = format!(
+                "SELECT key_sentence,
+                        ts_headline('{config}', key_sentence, q, 'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS snippet,
+                        array_agg(id) AS ids,
+                        max(ts_rank_cd(to_tsvector('{config}', key_sentence || ' ' || source_name || ' ' || target_name), q))
+                          / (1 + EXTRACT(EPOCH FROM now() - max(created_at)) / 86400 / {half_life})::float8 AS score
+                 FROM biomedgps_knowledge_curation, websearch_to_tsquery('{config}', $1) q
+                 WHERE to_tsvector('{config}', key_sentence || ' ' || source_name || ' ' || target_name) @@ q AND {owner_qstr}
+                 GROUP BY key_sentence, q ORDER BY score DESC LIMIT $3",
+                config = FULLTEXT_SEARCH_CONFIG,
+                half_life = RECENCY_HALF_LIFE_DAYS,
+                owner_qstr = owner_qstr
+            );
+            let query = sqlx::query_as::<_, (String, String, Vec<i64>, f64)>(&sql_str).bind(text);
+            let query = match filter.include_curated {
+                IncludeCurated::Own => query.bind(vec![filter.curator.clone()]),
+                _ => query.bind(
+                    filter
+                        .projects
+                        .iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<String>>(),
+                ),
+            };
+            let rows = query.bind(limit).fetch_all(&mut *conn).await?;
+
+            let ids = rows
+                .iter()
+                .flat_map(|(_, _, ids, _)| ids.clone())
+                .collect::<Vec<i64>>();
+            let curations = sqlx::query_as::<_, KnowledgeCuration>(
+                "SELECT * FROM biomedgps_knowledge_curation WHERE id = ANY($1) ORDER BY created_at DESC",
+            )
+            .bind(&ids)
+            .fetch_all(&mut *conn)
+            .await?;
+
+            for (key_sentence, snippet, ids, score) in rows {
+                let curations = curations
+                    .iter()
+                    .filter(|c| ids.contains(&c.id))
+                    .cloned()
+                    .collect::<Vec<KnowledgeCuration>>();
+                let webpages = curations
+                    .iter()
+                    .map(|c| get_pubmed_url(&c.pmid.to_string()))
+                    .collect::<BTreeSet<String>>()
+                    .into_iter()
+                    .collect::<Vec<String>>();
+                matches.push(KeySentenceMatch {
+                    key_sentence,
+                    snippet,
+                    source: "curation".to_string(),
+                    curations,
+                    relation_ids: vec![],
+                    webpages,
+                    score,
+                });
+            }
+        }
+
+        if include_relations {
+            let sql_str = format!(
+                "SELECT key_sentence,
+                        ts_headline('{config}', key_sentence, q, 'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS snippet,
+                        array_agg(id) AS ids,
+                        COALESCE(string_agg(pmids, '|'), '') AS pmids,
+                        max(ts_rank_cd(to_tsvector('{config}', key_sentence), q))::float8 AS score
+                 FROM biomedgps_relation, websearch_to_tsquery('{config}', $1) q
+                 WHERE to_tsvector('{config}', key_sentence) @@ q
+                 GROUP BY key_sentence, q ORDER BY score DESC LIMIT $2",
+                config = FULLTEXT_SEARCH_CONFIG
+            );
+            let rows = sqlx::query_as::<_, (String, String, Vec<i64>, String, f64)>(&sql_str)
+                .bind(text)
+                .bind(limit)
+                .fetch_all(&mut *conn)
+                .await?;
+
+            for (key_sentence, snippet, relation_ids, pmids, score) in rows {
+                let pmids = pmids
+                    .split('|')
+                    .map(|pmid| pmid.trim())
+                    .filter(|pmid| !pmid.is_empty())
+                    .collect::<BTreeSet<&str>>();
+                matches.push(KeySentenceMatch {
+                    key_sentence,
+                    snippet,
+                    source: "relation".to_string(),
+                    curations: vec![],
+                    relation_ids,
+                    webpages: pmids.into_iter().map(get_pubmed_url).collect(),
+                    score,
+                });
+            }
+        }
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit as usize);
+
+        AnyOk(matches)
+    }
+}
+
+/// The license of a source dataset. The relations of the datasets which are not redistributable are excluded from the exports by default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct DatasetLicense {
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of dataset should be between 1 and 64."
+    ))]
+    pub dataset: String,
+
+    // Such as CC-BY-4.0, CC-BY-NC-4.0, etc.
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of license should be between 1 and 64."
+    ))]
+    pub license: String,
+
+    pub redistributable: bool,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub url: Option<String>,
+
+    // The usage restrictions of the dataset.
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+
+    #[serde(skip_deserializing)]
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DatasetLicense {
+    pub async fn get_records(pool: &sqlx::PgPool) -> Result<Vec<DatasetLicense>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_dataset_license ORDER BY dataset";
+        let records = sqlx::query_as::<_, DatasetLicense>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
+    }
+
+    /// Get the datasets which are not redistributable, such as ["drugbank"].
+    pub async fn get_restricted_datasets(
+        pool: &sqlx::PgPool,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let sql_str =
+            "SELECT dataset FROM biomedgps_dataset_license WHERE NOT redistributable ORDER BY dataset";
+        let datasets = sqlx::query_scalar::<_, String>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(datasets)
+    }
+
+    /// Insert or update the license of a dataset, and tag the relations of the dataset with the license.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<DatasetLicense, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        let sql_str = "INSERT INTO biomedgps_dataset_license (dataset, license, redistributable, url, description) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (dataset) DO UPDATE SET license = $2, redistributable = $3, url = $4, description = $5, updated_at = now() RETURNING *";
+        let record = sqlx::query_as::<_, DatasetLicense>(sql_str)
+            .bind(&self.dataset)
+            .bind(&self.license)
+            .bind(self.redistributable)
+            .bind(&self.url)
+            .bind(&self.description)
+            .fetch_one(&mut tx)
+            .await?;
+
+        let num_relations = Self::tag_relations(&mut tx, &self.dataset).await?;
+        tx.commit().await?;
+        info!(
+            "The license of the dataset {} is set to {}, {} relations are tagged.",
+            record.dataset, record.license, num_relations
+        );
+
+        AnyOk(record)
+    }
+
+    /// Tag the relations of a dataset with its license, the tags are removed if the dataset has no license. It should be called after the relations of a dataset are imported.
+    pub async fn tag_relations(
+        conn: &mut sqlx::PgConnection,
+        dataset: &str,
+    ) -> Result<u64, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_relation SET license = (SELECT license FROM biomedgps_dataset_license WHERE dataset = $1) WHERE dataset = $1";
+        let result = sqlx::query(sql_str).bind(dataset).execute(conn).await?;
+
+        AnyOk(result.rows_affected())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow, Validate)]
@@ -1130,22 +3263,412 @@ pub struct Relation {
     ))]
     pub target_type: String,
 
-    #[oai(skip_serializing_if_is_none)]
-    pub score: Option<f64>,
+    #[oai(skip_serializing_if_is_none)]
+    pub score: Option<f64>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub key_sentence: Option<String>,
+
+    pub resource: String,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub dataset: Option<String>,
+
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(custom(
+        function = "validate_pmids",
+        message = "The pmids must be numbers between 1 and 99999999, separated by |, such as 12345|23456."
+    ))]
+    pub pmids: Option<String>,
+
+    // The biological context of the relation, such as the tissue (liver) and the cell type (hepatocyte) where it's observed. The ids are the terms of the source ontologies, such as UBERON:0002107 and CL:0000182.
+    #[serde(default)]
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(regex(
+        path = "RELATION_CONTEXT_VALUE_REGEX",
+        message = "The tissue must only contain letters, numbers, spaces and -_:.,/()+ characters."
+    ))]
+    pub tissue: Option<String>,
+
+    #[serde(default)]
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(regex(
+        path = "ENTITY_ID_REGEX",
+        message = "The tissue_id must match the ^[A-Za-z0-9\\-]+:[a-z0-9A-Z\\.\\-_]+$ pattern. eg: UBERON:0002107"
+    ))]
+    pub tissue_id: Option<String>,
+
+    #[serde(default)]
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(regex(
+        path = "RELATION_CONTEXT_VALUE_REGEX",
+        message = "The cell_type must only contain letters, numbers, spaces and -_:.,/()+ characters."
+    ))]
+    pub cell_type: Option<String>,
+
+    #[serde(default)]
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(regex(
+        path = "ENTITY_ID_REGEX",
+        message = "The cell_type_id must match the ^[A-Za-z0-9\\-]+:[a-z0-9A-Z\\.\\-_]+$ pattern. eg: CL:0000182"
+    ))]
+    pub cell_type_id: Option<String>,
+
+    // The provenance of the identical relations which are imported from multiple datasets, only available when the relations are deduplicated.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub datasets: Option<Vec<String>>,
+
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub resources: Option<Vec<String>>,
+
+    // The license of the dataset, it's tagged from the dataset licenses.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub license: Option<String>,
+
+    // The evidence counts of the relation, they are maintained by the importer and the curation writes. They are the distinct PMIDs, the datasets which contain the same relation and the curated knowledges of the same relation.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub n_pmids: Option<i32>,
+
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub n_datasets: Option<i32>,
+
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub n_curations: Option<i32>,
+
+    // The latest verdict of the LLM verification of the relation, such as SUPPORTED, REFUTED or INSUFFICIENT_EVIDENCE. The rationales are in the relation verifications.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub verdict: Option<String>,
+}
+
+/// The columns which can be used to sort the relations, the evidence counts are sorted in descending order and the score is sorted in ascending order.
+pub const RELATION_ORDER_FIELDS: [&str; 4] = ["score", "n_pmids", "n_datasets", "n_curations"];
+
+/// Count the distinct PMIDs of a relation row, the PMIDs are separated by `|`.
+const RELATION_N_PMIDS_EXPR: &str =
+    "(SELECT COUNT(DISTINCT p) FROM unnest(string_to_array(r.pmids, '|')) p WHERE p <> '')::INTEGER";
+
+/// Match the relation rows `r` and the rows `c` of the same source, relation type and target.
+const RELATION_TRIPLE_JOIN: &str = "r.relation_type = c.relation_type AND r.source_id = c.source_id AND r.source_type = c.source_type AND r.target_id = c.target_id AND r.target_type = c.target_type";
+
+impl Relation {
+    /// Generate the order clause of the relations by one of the RELATION_ORDER_FIELDS. The id is appended, so the pagination is stable.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::Relation;
+    ///
+    /// assert_eq!(Relation::gen_order_clause("n_pmids").unwrap(), "n_pmids DESC, id ASC");
+    /// assert_eq!(Relation::gen_order_clause("score").unwrap(), "score ASC, id ASC");
+    /// assert!(Relation::gen_order_clause("id; DROP TABLE biomedgps_relation").is_err());
+    /// ```
+    pub fn gen_order_clause(order_by: &str) -> Result<String, anyhow::Error> {
+        match order_by {
+            "score" => AnyOk("score ASC, id ASC".to_string()),
+            field if RELATION_ORDER_FIELDS.contains(&field) => {
+                AnyOk(format!("{} DESC, id ASC", field))
+            }
+            _ => Err(anyhow::anyhow!(
+                "The relations can only be sorted by {}, but got {}.",
+                RELATION_ORDER_FIELDS.join(", "),
+                order_by
+            )),
+        }
+    }
+
+    /// Refresh the evidence counts of the relations which have the same source, relation type and target as the relations of a dataset. It should be called after the relations of a dataset are imported.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - The imported dataset, such as ctd.
+    ///
+    /// # Returns
+    /// `Result<u64, anyhow::Error>` - The number of refreshed relations.
+    ///
+    pub async fn refresh_evidence_counts(
+        pool: &sqlx::PgPool,
+        dataset: &str,
+    ) -> Result<u64, anyhow::Error> {
+        let sql_str = format!(
+            "UPDATE biomedgps_relation r
+             SET n_pmids = {n_pmids},
+                 n_datasets = c.n_datasets,
+                 n_curations = (SELECT COUNT(*) FROM biomedgps_knowledge_curation c WHERE {join})
+             FROM (
+                 SELECT relation_type, source_id, source_type, target_id, target_type, COUNT(DISTINCT dataset)::INTEGER AS n_datasets
+                 FROM biomedgps_relation r
+                 WHERE EXISTS (
+                     SELECT 1 FROM biomedgps_relation c WHERE c.dataset = $1 AND {join}
+                 )
+                 GROUP BY relation_type, source_id, source_type, target_id, target_type
+             ) c
+             WHERE {join}",
+            n_pmids = RELATION_N_PMIDS_EXPR,
+            join = RELATION_TRIPLE_JOIN
+        );
+        let result = sqlx::query(&sql_str).bind(dataset).execute(pool).await?;
+
+        AnyOk(result.rows_affected())
+    }
+
+    /// Refresh the curation counts of all relations, it should be called after the curated knowledges are imported.
+    pub async fn refresh_curation_counts(pool: &sqlx::PgPool) -> Result<u64, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        let reset = sqlx::query(&format!(
+            "UPDATE biomedgps_relation r SET n_curations = 0
+             WHERE n_curations > 0 AND NOT EXISTS (SELECT 1 FROM biomedgps_knowledge_curation c WHERE {join})",
+            join = RELATION_TRIPLE_JOIN
+        ))
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        let updated = sqlx::query(&format!(
+            "UPDATE biomedgps_relation r SET n_curations = c.n_curations
+             FROM (
+                 SELECT relation_type, source_id, source_type, target_id, target_type, COUNT(*)::INTEGER AS n_curations
+                 FROM biomedgps_knowledge_curation
+                 GROUP BY relation_type, source_id, source_type, target_id, target_type
+             ) c
+             WHERE {join} AND r.n_curations <> c.n_curations",
+            join = RELATION_TRIPLE_JOIN
+        ))
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+
+        AnyOk(reset + updated)
+    }
+
+    /// Delete all relations of a dataset and the metadata of the dataset, the qualifiers of the relations are deleted by cascade.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - The dataset to delete, such as ctd.
+    ///
+    /// # Returns
+    /// `Result<u64, anyhow::Error>` - The number of deleted relations.
+    ///
+    pub async fn delete_dataset(pool: &sqlx::PgPool, dataset: &str) -> Result<u64, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        // The same relations of the other datasets lose one dataset.
+        sqlx::query(&format!(
+            "UPDATE biomedgps_relation r SET n_datasets = GREATEST(n_datasets - 1, 1)
+             WHERE r.dataset <> $1 AND EXISTS (SELECT 1 FROM biomedgps_relation c WHERE c.dataset = $1 AND {join})",
+            join = RELATION_TRIPLE_JOIN
+        ))
+        .bind(dataset)
+        .execute(&mut tx)
+        .await?;
+        let deleted = sqlx::query("DELETE FROM biomedgps_relation WHERE dataset = $1")
+            .bind(dataset)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM biomedgps_relation_metadata WHERE dataset = $1")
+            .bind(dataset)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    /// Count the relations of the nodes in both directions, it's used to penalize the hub nodes in the predictions.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `node_ids` - The composed node ids, such as `Gene::ENTREZ:123`.
+    ///
+    /// # Returns
+    /// `Result<HashMap<String, i64>, anyhow::Error>` - The degrees of the nodes, the nodes without any relation are not in the map.
+    ///
+    pub async fn count_degrees(
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<String>,
+    ) -> Result<HashMap<String, i64>, anyhow::Error> {
+        let (types, ids): (Vec<String>, Vec<String>) = node_ids
+            .iter()
+            .filter_map(|id| id.split_once(COMPOSED_ENTITY_DELIMITER))
+            .map(|(t, i)| (t.to_string(), i.to_string()))
+            .unzip();
+
+        let sql_str = format!(
+            "SELECT node_id, COUNT(*)::BIGINT AS degree FROM (
+                SELECT source_type || '{delimiter}' || source_id AS node_id FROM biomedgps_relation
+                WHERE (source_type, source_id) IN (SELECT * FROM unnest($1::text[], $2::text[]))
+                UNION ALL
+                SELECT target_type || '{delimiter}' || target_id AS node_id FROM biomedgps_relation
+                WHERE (target_type, target_id) IN (SELECT * FROM unnest($1::text[], $2::text[]))
+            ) AS t GROUP BY node_id",
+            delimiter = COMPOSED_ENTITY_DELIMITER
+        );
+        let degrees = sqlx::query_as::<_, (String, i64)>(&sql_str)
+            .bind(&types)
+            .bind(&ids)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(degrees.into_iter().collect())
+    }
+
+    /// Generate a derived table which collapses the identical relations (same source, relation type and target) from multiple datasets into one row. The filter is applied before grouping, so the deduplicated rows can be paginated by the RecordResponse::get_records function as a normal table.
+    ///
+    /// # Arguments
+    /// * `table_name` - The relation table, such as biomedgps_relation or the kg score table.
+    /// * `query` - The query to filter the relations before deduplication.
+    ///
+    /// # Returns
+    /// `String` - A derived table expression, such as `(SELECT ... GROUP BY ...) AS deduped_relations`.
+    ///
+    pub fn gen_dedupe_table_expr(table_name: &str, query: &Option<ComposeQuery>) -> String {
+        let query_str = match query {
+            Some(ComposeQuery::QueryItem(item)) => item.format(),
+            Some(ComposeQuery::ComposeQueryItem(item)) => item.format(),
+            None => "".to_string(),
+        };
+
+        let query_str = if query_str.is_empty() {
+            "1=1".to_string()
+        } else {
+            query_str
+        };
+
+        format!(
+            "(SELECT
+                MIN(id) AS id,
+                relation_type,
+                MIN(formatted_relation_type) AS formatted_relation_type,
+                source_id,
+                source_type,
+                target_id,
+                target_type,
+                MAX(score) AS score,
+                MIN(key_sentence) AS key_sentence,
+                MIN(resource) AS resource,
+                MIN(dataset) AS dataset,
+                NULLIF(STRING_AGG(DISTINCT pmids, '|'), '') AS pmids,
+                (SELECT COUNT(DISTINCT p) FROM unnest(string_to_array(STRING_AGG(pmids, '|'), '|')) p WHERE p <> '')::INTEGER AS n_pmids,
+                COUNT(DISTINCT dataset)::INTEGER AS n_datasets,
+                (SELECT COUNT(*) FROM biomedgps_knowledge_curation c
+                 WHERE c.relation_type = relations.relation_type
+                   AND c.source_id = relations.source_id
+                   AND c.source_type = relations.source_type
+                   AND c.target_id = relations.target_id
+                   AND c.target_type = relations.target_type)::INTEGER AS n_curations,
+                ARRAY_REMOVE(ARRAY_AGG(DISTINCT dataset ORDER BY dataset), NULL) AS datasets,
+                ARRAY_AGG(DISTINCT resource ORDER BY resource) AS resources
+            FROM (SELECT * FROM {table_name}) AS relations
+            WHERE {query_str}
+            GROUP BY source_id, source_type, relation_type, target_id, target_type) AS deduped_relations",
+            table_name = table_name,
+            query_str = query_str
+        )
+    }
 
-    #[oai(skip_serializing_if_is_none)]
-    pub key_sentence: Option<String>,
+    /// Generate a table expression which attaches the latest verdict of the relation verifications to the relations. The verifications are matched by the relation type and the entities instead of the id, so the deduplicated relations get the verdicts too.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::Relation;
+    ///
+    /// let table_expr = Relation::gen_verified_table_expr("biomedgps_relation");
+    /// assert!(table_expr.starts_with("(SELECT relations.*, verifications.verdict FROM (SELECT * FROM biomedgps_relation) AS relations"));
+    /// assert!(table_expr.ends_with("AS verified_relations"));
+    /// ```
+    pub fn gen_verified_table_expr(table_name: &str) -> String {
+        format!(
+            "(SELECT relations.*, verifications.verdict FROM (SELECT * FROM {table_name}) AS relations
+            LEFT JOIN LATERAL (
+                SELECT v.verdict FROM biomedgps_relation_verification v
+                WHERE v.relation_type = relations.relation_type
+                  AND v.source_id = relations.source_id
+                  AND v.source_type = relations.source_type
+                  AND v.target_id = relations.target_id
+                  AND v.target_type = relations.target_type
+                ORDER BY v.created_at DESC
+                LIMIT 1
+            ) AS verifications ON TRUE) AS verified_relations",
+            table_name = table_name
+        )
+    }
 
-    pub resource: String,
+    /// Fetch the relations of the nodes whose relation types are in the given predicates, such as the side effects and the contraindications of the candidate compounds. The relations between the nodes and the query nodes come first, then the relations with higher scores.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `node_ids` - The composed ids of the nodes, such as ["Compound::DrugBank:DB00001"]
+    /// * `predicates` - The normalized predicates of the relation types, such as ["causes_side_effect"]. The predicate is the middle part of the relation type, it's lowercased and the spaces and hyphens are replaced by underscores
+    /// * `query_node_ids` - The composed ids of the query nodes, such as the disease which the compounds are ranked for
+    /// * `limit_per_node` - The max number of the relations for each node
+    ///
+    /// # Returns
+    /// * `Result<Vec<Relation>, anyhow::Error>` - The relations or an error
+    pub async fn fetch_by_predicates(
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<String>,
+        predicates: &Vec<String>,
+        query_node_ids: &Vec<String>,
+        limit_per_node: i64,
+    ) -> Result<Vec<Relation>, anyhow::Error> {
+        if node_ids.is_empty() || predicates.is_empty() {
+            return AnyOk(vec![]);
+        }
 
-    #[oai(skip_serializing_if_is_none)]
-    pub dataset: Option<String>,
+        let source_str = format!(
+            "CONCAT(source_type, '{}', source_id)",
+            COMPOSED_ENTITY_DELIMITER
+        );
+        let target_str = format!(
+            "CONCAT(target_type, '{}', target_id)",
+            COMPOSED_ENTITY_DELIMITER
+        );
+        let sql_str = format!(
+            "SELECT * FROM (
+                SELECT *, ROW_NUMBER() OVER (
+                    PARTITION BY CASE WHEN {source} = ANY($1) THEN {source} ELSE {target} END
+                    ORDER BY ({source} = ANY($3) OR {target} = ANY($3)) DESC, score DESC NULLS LAST
+                ) AS predicate_rank
+                FROM biomedgps_relation
+                WHERE ({source} = ANY($1) OR {target} = ANY($1))
+                  AND regexp_replace(lower(split_part(relation_type, '{delimiter}', 2)), '[ -]', '_', 'g') = ANY($2)
+            ) AS relations
+            WHERE predicate_rank <= $4
+            ORDER BY predicate_rank",
+            source = source_str,
+            target = target_str,
+            delimiter = COMPOSED_ENTITY_DELIMITER
+        );
 
-    #[oai(skip_serializing_if_is_none)]
-    pub pmids: Option<String>,
-}
+        let records = sqlx::query_as::<_, Relation>(&sql_str)
+            .bind(node_ids)
+            .bind(predicates)
+            .bind(query_node_ids)
+            .bind(limit_per_node)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
+    }
 
-impl Relation {
     pub fn gen_composed_key(first_node_id: &str, second_node_id: &str) -> String {
         if first_node_id < second_node_id {
             format!(
@@ -1209,66 +3732,614 @@ impl Relation {
             )
         };
 
-        let sql_str = match relation_type {
-            Some(relation_type) => {
-                format!("{} AND relation_type = '{}'", where_clauses, relation_type)
-            }
-            None => where_clauses,
-        };
+        let sql_str = match relation_type {
+            Some(relation_type) => {
+                format!("{} AND relation_type = '{}'", where_clauses, relation_type)
+            }
+            None => where_clauses,
+        };
+
+        let records = sqlx::query_as::<_, Relation>(sql_str.as_str())
+            .fetch_all(pool)
+            .await?;
+
+        let mut relation_map = HashMap::new();
+        for record in records {
+            let source_node_id = format!(
+                "{}{}{}",
+                record.source_type, COMPOSED_ENTITY_DELIMITER, record.source_id
+            );
+            let target_node_id = format!(
+                "{}{}{}",
+                record.target_type, COMPOSED_ENTITY_DELIMITER, record.target_id
+            );
+            let ordered_key_str = Self::gen_composed_key(&source_node_id, &target_node_id);
+            relation_map.insert(ordered_key_str, record);
+        }
+
+        AnyOk(relation_map)
+    }
+}
+
+impl CheckData for Relation {
+    fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
+        Self::check_csv_is_valid_default::<Relation>(filepath)
+    }
+
+    fn unique_fields() -> Vec<String> {
+        vec![
+            "resource".to_string(),
+            "dataset".to_string(),
+            "formatted_relation_type".to_string(),
+            "relation_type".to_string(),
+            "source_id".to_string(),
+            "source_type".to_string(),
+            "target_id".to_string(),
+            "target_type".to_string(),
+        ]
+    }
+
+    fn fields() -> Vec<String> {
+        vec![
+            "formatted_relation_type".to_string(),
+            "relation_type".to_string(),
+            "source_id".to_string(),
+            "source_type".to_string(),
+            "target_id".to_string(),
+            "target_type".to_string(),
+            "score".to_string(),
+            "key_sentence".to_string(),
+            "resource".to_string(),
+            "dataset".to_string(),
+            "pmids".to_string(),
+            "tissue".to_string(),
+            "tissue_id".to_string(),
+            "cell_type".to_string(),
+            "cell_type_id".to_string(),
+        ]
+    }
+
+    fn normalize_field(field: &str, value: &str) -> String {
+        // The invalid pmids have been reported by the check_csv_is_valid function, so keep them as they are.
+        if field == "pmids" {
+            normalize_pmids(value).unwrap_or(value.to_string())
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// A qualifier of a relation, such as the dose, the tissue and the direction of effect. The value is stored as text with its type, so the numbers can be compared in the relation queries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct RelationQualifier {
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    pub relation_id: i64,
+
+    // The key of the qualifier, such as dose, tissue and direction.
+    pub key: String,
+
+    // One of string, number and boolean.
+    pub value_type: String,
+
+    pub value: String,
+}
+
+/// A filter on the qualifier values, such as `dose>=10` or `tissue=liver`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualifierFilter {
+    pub key: String,
+    pub operator: String,
+    pub value: String,
+}
+
+impl RelationQualifier {
+    /// Detect the type of a qualifier value.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::RelationQualifier;
+    ///
+    /// assert_eq!(RelationQualifier::detect_value_type("12.5"), "number");
+    /// assert_eq!(RelationQualifier::detect_value_type("TRUE"), "boolean");
+    /// assert_eq!(RelationQualifier::detect_value_type("liver"), "string");
+    /// ```
+    pub fn detect_value_type(value: &str) -> &'static str {
+        if value.parse::<f64>().is_ok() {
+            "number"
+        } else if ["true", "false"].contains(&value.to_lowercase().as_str()) {
+            "boolean"
+        } else {
+            "string"
+        }
+    }
+
+    /// Fetch the qualifiers of the relations between the nodes, they are grouped by the edge key which is `<source_id>-<relation_type>-<target_id>` with the composed node ids.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `node_ids` - The composed ids of the nodes, such as ["Gene::ENTREZ:1017"]
+    pub async fn fetch_by_node_ids(
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<String>,
+    ) -> Result<HashMap<String, Vec<RelationQualifier>>, anyhow::Error> {
+        let sql_str = format!(
+            "SELECT
+                r.source_type || '{delimiter}' || r.source_id || '-' || r.relation_type || '-' || r.target_type || '{delimiter}' || r.target_id AS edge_key,
+                q.id, q.relation_id, q.key, q.value_type, q.value
+            FROM biomedgps_relation_qualifier q
+            JOIN biomedgps_relation r ON r.id = q.relation_id
+            WHERE r.source_type || '{delimiter}' || r.source_id = ANY($1)
+                AND r.target_type || '{delimiter}' || r.target_id = ANY($1)
+            ORDER BY q.relation_id, q.key",
+            delimiter = COMPOSED_ENTITY_DELIMITER
+        );
+
+        let records = sqlx::query_as::<_, (String, i64, i64, String, String, String)>(&sql_str)
+            .bind(node_ids)
+            .fetch_all(pool)
+            .await?;
+
+        let mut qualifiers: HashMap<String, Vec<RelationQualifier>> = HashMap::new();
+        for (edge_key, id, relation_id, key, value_type, value) in records {
+            qualifiers
+                .entry(edge_key)
+                .or_default()
+                .push(RelationQualifier {
+                    id,
+                    relation_id,
+                    key,
+                    value_type,
+                    value,
+                });
+        }
+
+        AnyOk(qualifiers)
+    }
+
+    /// Import the qualifiers from the extra columns of a relation file, the columns are named as `qualifier_<key>`, such as qualifier_dose and qualifier_tissue. The relations must be imported before, they are matched by the unique fields of the relation.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `filepath` - The original relation file, the qualifier columns are dropped from the file which is imported into the relation table
+    /// * `dataset` - The dataset of the relations
+    ///
+    /// # Returns
+    /// * `usize` - How many qualifiers are imported
+    pub async fn import_from_file(
+        pool: &sqlx::PgPool,
+        filepath: &PathBuf,
+        dataset: &str,
+    ) -> Result<usize, Box<dyn Error>> {
+        let delimiter = get_delimiter(filepath)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(open_data_file(filepath)?);
+
+        let headers = reader.headers()?.clone();
+        let qualifier_columns: Vec<(usize, String)> = headers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| {
+                h.strip_prefix(RELATION_QUALIFIER_PREFIX)
+                    .filter(|key| !key.is_empty())
+                    .map(|key| (i, key.to_string()))
+            })
+            .collect();
+
+        if qualifier_columns.is_empty() {
+            return Ok(0);
+        }
+
+        let key_fields = [
+            "resource",
+            "relation_type",
+            "source_id",
+            "source_type",
+            "target_id",
+            "target_type",
+        ];
+        let mut key_indices = vec![];
+        for field in key_fields.iter() {
+            match headers.iter().position(|h| h == *field) {
+                Some(i) => key_indices.push(i),
+                None => {
+                    return Err(Box::new(ValidationError::new(
+                        &format!(
+                            "The {} column is required for importing the qualifiers.",
+                            field
+                        ),
+                        vec![field.to_string()],
+                    )))
+                }
+            }
+        }
+
+        // One column per field of the qualifiers, they are inserted by UNNEST in batches.
+        let mut columns: Vec<Vec<String>> = vec![vec![]; key_fields.len() + 3];
+        let mut imported = 0;
+        for record in reader.records() {
+            let record = record?;
+            for (index, key) in qualifier_columns.iter() {
+                let value = record.get(*index).unwrap_or("").trim();
+                if value.is_empty() {
+                    continue;
+                }
+
+                for (i, key_index) in key_indices.iter().enumerate() {
+                    columns[i].push(record.get(*key_index).unwrap_or("").to_string());
+                }
+                columns[key_fields.len()].push(key.clone());
+                columns[key_fields.len() + 1].push(Self::detect_value_type(value).to_string());
+                columns[key_fields.len() + 2].push(value.to_string());
+            }
+
+            if columns[0].len() >= RELATION_QUALIFIER_BATCH_SIZE {
+                imported += Self::insert_batch(pool, &columns, dataset).await?;
+                columns.iter_mut().for_each(|c| c.clear());
+            }
+        }
+
+        if !columns[0].is_empty() {
+            imported += Self::insert_batch(pool, &columns, dataset).await?;
+        }
+
+        Ok(imported)
+    }
+
+    async fn insert_batch(
+        pool: &sqlx::PgPool,
+        columns: &Vec<Vec<String>>,
+        dataset: &str,
+    ) -> Result<usize, Box<dyn Error>> {
+        let sql_str = "INSERT INTO biomedgps_relation_qualifier (relation_id, key, value_type, value)
+            SELECT r.id, q.key, q.value_type, q.value
+            FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[])
+                AS q(resource, relation_type, source_id, source_type, target_id, target_type, key, value_type, value)
+            JOIN biomedgps_relation r ON r.resource = q.resource
+                AND r.relation_type = q.relation_type
+                AND r.source_id = q.source_id
+                AND r.source_type = q.source_type
+                AND r.target_id = q.target_id
+                AND r.target_type = q.target_type
+                AND r.dataset = $10
+            ON CONFLICT (relation_id, key) DO UPDATE SET value_type = EXCLUDED.value_type, value = EXCLUDED.value";
+
+        let mut query = sqlx::query(sql_str);
+        for column in columns.iter() {
+            query = query.bind(column);
+        }
+        let result = query.bind(dataset).execute(pool).await?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+impl QualifierFilter {
+    /// Parse the qualifier filters which are separated by semicolons, such as `dose>=10;tissue=liver`. The operators are =, !=, >, >=, < and <=, the comparison operators only work with the numbers.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::QualifierFilter;
+    ///
+    /// let filters = QualifierFilter::parse("dose>=10; tissue=liver").unwrap();
+    /// assert_eq!(filters.len(), 2);
+    /// assert_eq!(filters[0].key, "dose");
+    /// assert_eq!(filters[0].operator, ">=");
+    /// assert_eq!(filters[1].value, "liver");
+    ///
+    /// assert!(QualifierFilter::parse("tissue>liver").is_err());
+    /// assert!(QualifierFilter::parse("tissue").is_err());
+    /// ```
+    pub fn parse(expr: &str) -> Result<Vec<QualifierFilter>, ValidationError> {
+        let mut filters = vec![];
+        for item in expr.split(';').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let captures = match QUALIFIER_FILTER_REGEX.captures(item) {
+                Some(captures) => captures,
+                None => {
+                    return Err(ValidationError::new(
+                        &format!(
+                            "The qualifier filter {} is invalid, it should be like dose>=10 or tissue=liver.",
+                            item
+                        ),
+                        vec![item.to_string()],
+                    ))
+                }
+            };
+
+            let filter = QualifierFilter {
+                key: captures[1].to_string(),
+                operator: captures[2].to_string(),
+                value: captures[3].trim().to_string(),
+            };
+
+            if !["=", "!="].contains(&filter.operator.as_str())
+                && filter.value.parse::<f64>().is_err()
+            {
+                return Err(ValidationError::new(
+                    &format!(
+                        "The value of the qualifier filter {} must be a number for the {} operator.",
+                        item, filter.operator
+                    ),
+                    vec![item.to_string()],
+                ));
+            }
+
+            filters.push(filter);
+        }
+
+        Ok(filters)
+    }
+
+    /// Generate the condition which checks whether the relation of the table has a matched qualifier. The number of a comparison is not formatted into the sql, it's the parameter `$param_index`, see [`QualifierFilter::gen_filtered_table_expr`] for the values.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::QualifierFilter;
+    ///
+    /// let filters = QualifierFilter::parse("tissue=O'Brien; dose>=10").unwrap();
+    /// assert_eq!(
+    ///     filters[0].to_sql("relations", 1),
+    ///     "EXISTS (SELECT 1 FROM biomedgps_relation_qualifier q WHERE q.relation_id = relations.id AND q.key = 'tissue' AND q.value = 'O''Brien')"
+    /// );
+    /// assert_eq!(
+    ///     filters[1].to_sql("relations", 1),
+    ///     "EXISTS (SELECT 1 FROM biomedgps_relation_qualifier q WHERE q.relation_id = relations.id AND q.key = 'dose' AND CASE WHEN q.value_type = 'number' THEN q.value::FLOAT8 END >= $1)"
+    /// );
+    /// ```
+    pub fn to_sql(&self, table_alias: &str, param_index: usize) -> String {
+        let condition = if self.is_numeric() {
+            // The cast is only evaluated for the numbers, postgres doesn't guarantee the order of the AND conditions.
+            format!(
+                "CASE WHEN q.value_type = 'number' THEN q.value::FLOAT8 END {} ${}",
+                self.operator, param_index
+            )
+        } else {
+            format!(
+                "q.value {} '{}'",
+                self.operator,
+                self.value.replace('\'', "''")
+            )
+        };
+
+        format!(
+            "EXISTS (SELECT 1 FROM biomedgps_relation_qualifier q WHERE q.relation_id = {}.id AND q.key = '{}' AND {})",
+            table_alias, self.key, condition
+        )
+    }
+
+    /// Whether the filter compares the numbers, the value is checked to be a number when it's parsed.
+    fn is_numeric(&self) -> bool {
+        !["=", "!="].contains(&self.operator.as_str())
+    }
+
+    /// Generate a derived table which only keeps the relations matched all the filters, it can be used as a normal table like the dedupe table expression. The numbers of the comparisons are returned in the order of their parameters, they must be bound to the queries which use the table, see `RecordResponse::get_records_with_binds`.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::QualifierFilter;
+    ///
+    /// let filters = QualifierFilter::parse("dose>=10; tissue=liver; age<3.5").unwrap();
+    /// let (table_expr, binds) = QualifierFilter::gen_filtered_table_expr("relations", &filters);
+    /// assert!(table_expr.contains(">= $1)") && table_expr.contains("< $2)"));
+    /// assert_eq!(binds, vec![10.0, 3.5]);
+    /// ```
+    pub fn gen_filtered_table_expr(
+        table_name: &str,
+        filters: &Vec<QualifierFilter>,
+    ) -> (String, Vec<f64>) {
+        let mut binds = vec![];
+        let mut conditions = vec![];
+        for filter in filters {
+            if filter.is_numeric() {
+                binds.push(filter.value.parse::<f64>().unwrap_or_default());
+            }
+            conditions.push(filter.to_sql("relations", binds.len()));
+        }
+        let conditions = conditions.join(" AND ");
+
+        let table_expr = format!(
+            "(SELECT * FROM {table_name} relations WHERE {conditions}) AS qualified_relations",
+            table_name = table_name,
+            conditions = if conditions.is_empty() {
+                "1=1".to_string()
+            } else {
+                conditions
+            }
+        );
+
+        (table_expr, binds)
+    }
+}
+
+/// The context columns of the relations, the names are matched case-insensitively and the ids are matched exactly.
+pub const RELATION_CONTEXT_FIELDS: [(&str, &str); 2] =
+    [("tissue", "tissue_id"), ("cell_type", "cell_type_id")];
+
+/// A biological context which the relations are restricted to, such as `tissue:liver` or `cell_type:CL:0000182`. The context without a field, such as `liver`, matches both the tissue and the cell type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationContext {
+    pub field: Option<String>,
+    pub value: String,
+}
+
+impl RelationContext {
+    /// Parse the contexts which are separated by semicolons, such as `tissue:liver;cell_type:hepatocyte`. The relations must match all the contexts.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::RelationContext;
+    ///
+    /// let contexts = RelationContext::parse("tissue:UBERON:0002107; hepatocyte").unwrap();
+    /// assert_eq!(contexts.len(), 2);
+    /// assert_eq!(contexts[0].field, Some("tissue".to_string()));
+    /// assert_eq!(contexts[0].value, "UBERON:0002107");
+    /// assert_eq!(contexts[1].field, None);
+    ///
+    /// assert!(RelationContext::parse("tissue:").is_err());
+    /// assert!(RelationContext::parse("liver' OR 1=1").is_err());
+    /// ```
+    pub fn parse(expr: &str) -> Result<Vec<RelationContext>, ValidationError> {
+        let mut contexts = vec![];
+        for item in expr.split(';').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            // The ids contain colons too, so only the known fields are split.
+            let (field, value) = match item.split_once(':') {
+                Some((field, value))
+                    if RELATION_CONTEXT_FIELDS
+                        .iter()
+                        .any(|(f, _)| *f == field.trim()) =>
+                {
+                    (Some(field.trim().to_string()), value.trim())
+                }
+                _ => (None, item),
+            };
+
+            if !RELATION_CONTEXT_VALUE_REGEX.is_match(value) {
+                return Err(ValidationError::new(
+                    &format!(
+                        "The context {} is invalid, it should be like tissue:liver, cell_type:CL:0000182 or liver, and only contain letters, numbers, spaces and -_:.,/()+ characters.",
+                        item
+                    ),
+                    vec![item.to_string()],
+                ));
+            }
+
+            contexts.push(RelationContext {
+                field,
+                value: value.to_string(),
+            });
+        }
+
+        Ok(contexts)
+    }
+
+    /// Generate the conditions of the context, the value is checked by the RELATION_CONTEXT_VALUE_REGEX when it's parsed, so it can be formatted into the sql directly.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::core::RelationContext;
+    ///
+    /// let contexts = RelationContext::parse("tissue:liver").unwrap();
+    /// assert_eq!(contexts[0].to_query().format(), "tissue ilike 'liver' or tissue_id = 'liver'");
+    /// ```
+    pub fn to_query(&self) -> ComposeQueryItem {
+        let mut context_query = ComposeQueryItem::new("or");
+        for (name_field, id_field) in RELATION_CONTEXT_FIELDS.iter() {
+            if self.field.as_ref().map_or(false, |f| f != name_field) {
+                continue;
+            }
+
+            // The ilike operator without wildcards matches the names case-insensitively.
+            context_query.add_item(ComposeQuery::QueryItem(QueryItem::new(
+                name_field.to_string(),
+                Value::String(self.value.clone()),
+                "ilike".to_string(),
+            )));
+            context_query.add_item(ComposeQuery::QueryItem(QueryItem::new(
+                id_field.to_string(),
+                Value::String(self.value.clone()),
+                "=".to_string(),
+            )));
+        }
+
+        context_query
+    }
+
+    /// Combine the query of the relations with the contexts, so only the relations in all the contexts are matched. It works with the relation table and the score tables, so it can be used by the relation and the graph expansion endpoints.
+    pub fn apply_to_relation_query(
+        contexts: &Vec<RelationContext>,
+        query: &Option<ComposeQuery>,
+    ) -> Option<ComposeQuery> {
+        if contexts.is_empty() {
+            return query.clone();
+        }
+
+        let mut context_query = ComposeQueryItem::new("and");
+        for context in contexts {
+            context_query.add_item(ComposeQuery::ComposeQueryItem(context.to_query()));
+        }
+        if let Some(query) = query {
+            context_query.add_item(query.clone());
+        }
+
+        Some(ComposeQuery::ComposeQueryItem(context_query))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct Publication {
+    // Ignore this field when deserialize from json
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of pmid must be between 1 and 64."
+    ))]
+    pub pmid: String,
+
+    pub title: String,
 
-        let records = sqlx::query_as::<_, Relation>(sql_str.as_str())
-            .fetch_all(pool)
-            .await?;
+    // The abstract is a reserved keyword in rust, so we rename it.
+    #[serde(rename = "abstract")]
+    #[sqlx(rename = "abstract")]
+    #[oai(rename = "abstract")]
+    pub abstract_text: String,
 
-        let mut relation_map = HashMap::new();
-        for record in records {
-            let source_node_id = format!(
-                "{}{}{}",
-                record.source_type, COMPOSED_ENTITY_DELIMITER, record.source_id
-            );
-            let target_node_id = format!(
-                "{}{}{}",
-                record.target_type, COMPOSED_ENTITY_DELIMITER, record.target_id
-            );
-            let ordered_key_str = Self::gen_composed_key(&source_node_id, &target_node_id);
-            relation_map.insert(ordered_key_str, record);
+    #[oai(skip_serializing_if_is_none)]
+    pub journal: Option<String>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub year: Option<i32>,
+}
+
+impl Publication {
+    /// Fetch the cached publications by the pmids.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `pmids` - The pmids, such as ["32353859", "32353860"]
+    ///
+    /// # Returns
+    /// * `Result<Vec<Publication>, anyhow::Error>` - The cached publications, the missing pmids are ignored.
+    pub async fn fetch_by_pmids(
+        pool: &sqlx::PgPool,
+        pmids: &Vec<&str>,
+    ) -> Result<Vec<Publication>, anyhow::Error> {
+        if pmids.is_empty() {
+            return AnyOk(vec![]);
         }
 
-        AnyOk(relation_map)
+        let sql_str = "SELECT * FROM biomedgps_publication WHERE pmid = ANY($1)";
+        let records = sqlx::query_as::<_, Publication>(sql_str)
+            .bind(pmids)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
     }
 }
 
-impl CheckData for Relation {
+impl CheckData for Publication {
     fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
-        Self::check_csv_is_valid_default::<Relation>(filepath)
+        Self::check_csv_is_valid_default::<Publication>(filepath)
     }
 
     fn unique_fields() -> Vec<String> {
-        vec![
-            "resource".to_string(),
-            "dataset".to_string(),
-            "formatted_relation_type".to_string(),
-            "relation_type".to_string(),
-            "source_id".to_string(),
-            "source_type".to_string(),
-            "target_id".to_string(),
-            "target_type".to_string(),
-        ]
+        vec!["pmid".to_string()]
     }
 
     fn fields() -> Vec<String> {
         vec![
-            "formatted_relation_type".to_string(),
-            "relation_type".to_string(),
-            "source_id".to_string(),
-            "source_type".to_string(),
-            "target_id".to_string(),
-            "target_type".to_string(),
-            "score".to_string(),
-            "key_sentence".to_string(),
-            "resource".to_string(),
-            "dataset".to_string(),
-            "pmids".to_string(),
+            "pmid".to_string(),
+            "title".to_string(),
+            "abstract".to_string(),
+            "journal".to_string(),
+            "year".to_string(),
         ]
     }
 }
@@ -1462,6 +4533,15 @@ pub struct Subgraph {
         message = "The parent must match the ^[a-f0-9]{8}-[a-f0-9]{4}-[a-f0-9]{4}-[a-f0-9]{4}-[a-f0-9]{12}$ pattern."
     ))]
     pub parent: Option<String>, // parent subgraph id, it is same as id if it is a root subgraph (no parent), otherwise it is the parent subgraph id
+
+    // It should be a valid json string which records the expansion operations of the subgraph. e.g. {"steps": [{"operation": "nodes", "node_ids": ["Gene::ENTREZ:123"]}]}. More details on the recipe can be found in the ExpansionRecipe struct.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(regex(
+        path = "JSON_REGEX",
+        message = "The recipe must be a valid json string."
+    ))]
+    pub recipe: Option<String>,
 }
 
 impl CheckData for Subgraph {
@@ -1488,6 +4568,7 @@ impl CheckData for Subgraph {
             "version".to_string(),
             "db_version".to_string(),
             "parent".to_string(),
+            "recipe".to_string(),
         ]
     }
 }
@@ -1498,6 +4579,21 @@ impl Subgraph {
         return self;
     }
 
+    /// Combine the query of the subgraphs with the owner, so only the subgraphs of the owner are matched.
+    pub fn apply_owner_to_query(owner: &str, query: &Option<ComposeQuery>) -> Option<ComposeQuery> {
+        let mut owner_query = ComposeQueryItem::new("and");
+        owner_query.add_item(ComposeQuery::QueryItem(QueryItem::new(
+            "owner".to_string(),
+            Value::String(owner.to_string()),
+            "=".to_string(),
+        )));
+        if let Some(query) = query {
+            owner_query.add_item(query.clone());
+        }
+
+        Some(ComposeQuery::ComposeQueryItem(owner_query))
+    }
+
     pub async fn insert(&self, pool: &sqlx::PgPool) -> Result<Subgraph, anyhow::Error> {
         let id = uuid::Uuid::new_v4().to_string();
         let parent = if self.parent.is_none() {
@@ -1506,7 +4602,7 @@ impl Subgraph {
             self.parent.clone().unwrap()
         };
 
-        let sql_str = "INSERT INTO biomedgps_subgraph (id, name, description, payload, owner, version, db_version, parent) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *";
+        let sql_str = "INSERT INTO biomedgps_subgraph (id, name, description, payload, owner, version, db_version, parent, recipe) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *";
         let subgraph = sqlx::query_as::<_, Subgraph>(sql_str)
             .bind(id)
             .bind(&self.name)
@@ -1516,32 +4612,418 @@ impl Subgraph {
             .bind(&self.version)
             .bind(&self.db_version)
             .bind(parent)
+            .bind(&self.recipe)
             .fetch_one(pool)
             .await?;
 
         AnyOk(subgraph)
     }
 
-    pub async fn update(&self, pool: &sqlx::PgPool, id: &str) -> Result<Subgraph, anyhow::Error> {
-        let sql_str = "UPDATE biomedgps_subgraph SET name = $1, description = $2, payload = $3, WHERE id = $4 RETURNING *";
+    pub async fn update(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        id: &str,
+    ) -> Result<Subgraph, anyhow::Error> {
+        // Keep the existing recipe if the new one is not provided.
+        let sql_str = "UPDATE biomedgps_subgraph SET name = $1, description = $2, payload = $3, recipe = COALESCE($4, recipe) WHERE id = $5 RETURNING *";
         let subgraph = sqlx::query_as::<_, Subgraph>(sql_str)
             .bind(&self.name)
             .bind(&self.description)
             .bind(&self.payload)
+            .bind(&self.recipe)
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(conn)
+            .await?;
+
+        AnyOk(subgraph)
+    }
+
+    pub async fn fetch_by_id(
+        conn: &mut sqlx::PgConnection,
+        id: &str,
+    ) -> Result<Subgraph, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_subgraph WHERE id = $1";
+        let subgraph = sqlx::query_as::<_, Subgraph>(sql_str)
+            .bind(id)
+            .fetch_one(conn)
             .await?;
 
         AnyOk(subgraph)
     }
 
-    pub async fn delete(pool: &sqlx::PgPool, id: &str) -> Result<Subgraph, anyhow::Error> {
+    /// Get the ids of the nodes in the payload of the subgraph, the payload should be like {"data": {"nodes": [{"id": "Gene::ENTREZ:1"}], "edges": []}}.
+    pub fn get_node_ids(&self) -> Result<Vec<String>, anyhow::Error> {
+        let payload: serde_json::Value = serde_json::from_str(&self.payload)?;
+        let node_ids = match payload["data"]["nodes"].as_array() {
+            Some(nodes) => nodes
+                .iter()
+                .filter_map(|node| node["id"].as_str().map(|id| id.to_string()))
+                .collect(),
+            None => vec![],
+        };
+
+        AnyOk(node_ids)
+    }
+
+    /// Convert the payload of the subgraph to NDJSON lines. The payload should be like {"data": {"nodes": [], "edges": []}}, the nodes are emitted before the edges.
+    ///
+    /// The lines are formatted lazily when they are consumed, so a large subgraph isn't copied into the lines at once.
+    pub fn to_ndjson_lines(&self) -> Result<impl Iterator<Item = String>, anyhow::Error> {
+        let mut payload: serde_json::Value = serde_json::from_str(&self.payload)?;
+        let mut take_items = |key: &str| match payload
+            .pointer_mut(&format!("/data/{}", key))
+            .map(|items| items.take())
+        {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => vec![],
+        };
+        let nodes = take_items("nodes");
+        let edges = take_items("edges");
+
+        let lines = nodes
+            .into_iter()
+            .map(|item| ("node", item))
+            .chain(edges.into_iter().map(|item| ("edge", item)))
+            .map(|(item_type, item)| {
+                format!("{}\n", serde_json::json!({"type": item_type, "data": item}))
+            });
+
+        AnyOk(lines)
+    }
+
+    pub async fn delete(
+        conn: &mut sqlx::PgConnection,
+        id: &str,
+    ) -> Result<Subgraph, anyhow::Error> {
         let sql_str = "DELETE FROM biomedgps_subgraph WHERE id = $1 RETURNING *";
         let subgraph = sqlx::query_as::<_, Subgraph>(sql_str)
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(conn)
             .await?;
 
         AnyOk(subgraph)
     }
 }
+
+/// A tag of a node, such as validated, candidate and exclude. The tags are shown as badges on the nodes in every graph view, they are visible to the owner and the members of the project if the project is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct NodeTag {
+    // Ignore this field when deserialize from json
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    // The composed id of the node, such as Gene::ENTREZ:1017.
+    #[validate(regex(
+        path = "COMPOSED_ENTITY_REGEX",
+        message = "The node_id must be a composed id, such as Gene::ENTREZ:1017."
+    ))]
+    pub node_id: String,
+
+    #[validate(length(
+        max = "NODE_TAG_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of tag should be between 1 and 32."
+    ))]
+    pub tag: String,
+
+    // The color of the badge, such as #52c41a.
+    #[oai(skip_serializing_if_is_none)]
+    pub color: Option<String>,
+
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub owner: String,
+
+    // Share the tag with the members of the project.
+    #[oai(skip_serializing_if_is_none)]
+    pub project_id: Option<i32>,
+
+    #[serde(skip_deserializing)]
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl NodeTag {
+    /// Fetch the tags which are visible to the user, the tags of all nodes are returned if the node ids are not set.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `username` - The user who reads the tags
+    /// * `projects` - The projects of the user, the tags shared with these projects are also visible
+    /// * `node_ids` - The composed ids of the nodes, such as ["Gene::ENTREZ:1017"]
+    pub async fn fetch_visible(
+        pool: &sqlx::PgPool,
+        username: &str,
+        projects: &Vec<i32>,
+        node_ids: Option<&Vec<String>>,
+    ) -> Result<Vec<NodeTag>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_node_tag WHERE (owner = $1 OR project_id = ANY($2)) AND ($3::text[] IS NULL OR node_id = ANY($3)) ORDER BY node_id, id";
+        let tags = sqlx::query_as::<_, NodeTag>(sql_str)
+            .bind(username)
+            .bind(projects)
+            .bind(node_ids)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(tags)
+    }
+
+    pub async fn insert(&self, pool: &sqlx::PgPool, owner: &str) -> Result<NodeTag, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_node_tag (node_id, tag, color, owner, project_id) VALUES ($1, $2, $3, $4, $5) RETURNING *";
+        let tag = sqlx::query_as::<_, NodeTag>(sql_str)
+            .bind(&self.node_id)
+            .bind(&self.tag)
+            .bind(&self.color)
+            .bind(owner)
+            .bind(self.project_id)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(tag)
+    }
+
+    /// Update a tag of the owner, the tags of the others can't be updated.
+    pub async fn update(
+        &self,
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+    ) -> Result<NodeTag, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_node_tag SET node_id = $1, tag = $2, color = $3, project_id = $4 WHERE id = $5 AND owner = $6 RETURNING *";
+        let tag = sqlx::query_as::<_, NodeTag>(sql_str)
+            .bind(&self.node_id)
+            .bind(&self.tag)
+            .bind(&self.color)
+            .bind(self.project_id)
+            .bind(id)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(tag)
+    }
+
+    /// Delete a tag of the owner, the tags of the others can't be deleted.
+    pub async fn delete(
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+    ) -> Result<NodeTag, anyhow::Error> {
+        let sql_str = "DELETE FROM biomedgps_node_tag WHERE id = $1 AND owner = $2 RETURNING *";
+        let tag = sqlx::query_as::<_, NodeTag>(sql_str)
+            .bind(id)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(tag)
+    }
+}
+
+/// Validate the datasets of a graph view by the validator crate, at least one dataset is required and they are formatted into the sql conditions, so only the safe characters are allowed.
+pub fn validate_datasets(datasets: &Vec<String>) -> Result<(), validator::ValidationError> {
+    if datasets.is_empty() || datasets.iter().any(|d| !DATASET_REGEX.is_match(d)) {
+        return Err(validator::ValidationError::new("invalid_datasets"));
+    }
+
+    Ok(())
+}
+
+/// Validate the relation types of a graph view by the validator crate, such as ["biomedgps::Treats::Compound:Disease"].
+pub fn validate_relation_types(
+    relation_types: &Vec<String>,
+) -> Result<(), validator::ValidationError> {
+    if relation_types
+        .iter()
+        .any(|r| !RELATION_TYPE_REGEX.is_match(r))
+    {
+        return Err(validator::ValidationError::new("invalid_relation_types"));
+    }
+
+    Ok(())
+}
+
+/// A named combination of the datasets, such as DRKG + CTD or only the curated findings. The entity, relation and graph endpoints only return the relations of the datasets (and the relation types if they are set) and the entities which are linked by them if the view_id is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow, Validate)]
+pub struct GraphView {
+    // Ignore this field when deserialize from json
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub id: i64,
+
+    #[validate(length(
+        max = "DEFAULT_MAX_LENGTH",
+        min = "DEFAULT_MIN_LENGTH",
+        message = "The length of name should be between 1 and 64."
+    ))]
+    pub name: String,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+
+    #[validate(custom(
+        function = "validate_datasets",
+        message = "At least one dataset is required, and the dataset should only contain letters, digits, _, - and ., such as drkg."
+    ))]
+    pub datasets: Vec<String>,
+
+    #[oai(skip_serializing_if_is_none)]
+    #[validate(custom(
+        function = "validate_relation_types",
+        message = "The relation types should be like biomedgps::Treats::Compound:Disease."
+    ))]
+    pub relation_types: Option<Vec<String>>,
+
+    #[serde(skip_deserializing)]
+    #[oai(read_only)]
+    pub owner: String,
+
+    // Share the view with the members of the project.
+    #[oai(skip_serializing_if_is_none)]
+    pub project_id: Option<i32>,
+
+    #[serde(skip_deserializing)]
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(skip_deserializing)]
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GraphView {
+    /// Fetch the views which are visible to the user, such as the own views and the views shared with the projects of the user.
+    pub async fn fetch_visible(
+        pool: &sqlx::PgPool,
+        username: &str,
+        projects: &Vec<i32>,
+    ) -> Result<Vec<GraphView>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_graph_view WHERE owner = $1 OR project_id = ANY($2) ORDER BY name, id";
+        let views = sqlx::query_as::<_, GraphView>(sql_str)
+            .bind(username)
+            .bind(projects)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(views)
+    }
+
+    /// Get a view by id, it fails if the view is not visible to the user.
+    pub async fn get_visible(
+        pool: &sqlx::PgPool,
+        id: i64,
+        username: &str,
+        projects: &Vec<i32>,
+    ) -> Result<GraphView, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_graph_view WHERE id = $1 AND (owner = $2 OR project_id = ANY($3))";
+        let view = sqlx::query_as::<_, GraphView>(sql_str)
+            .bind(id)
+            .bind(username)
+            .bind(projects)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(view)
+    }
+
+    pub async fn insert(
+        &self,
+        pool: &sqlx::PgPool,
+        owner: &str,
+    ) -> Result<GraphView, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_graph_view (name, description, datasets, relation_types, owner, project_id) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *";
+        let view = sqlx::query_as::<_, GraphView>(sql_str)
+            .bind(&self.name)
+            .bind(&self.description)
+            .bind(&self.datasets)
+            .bind(&self.relation_types)
+            .bind(owner)
+            .bind(self.project_id)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(view)
+    }
+
+    /// Update a view of the owner, the views of the others can't be updated.
+    pub async fn update(
+        &self,
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+    ) -> Result<GraphView, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_graph_view SET name = $1, description = $2, datasets = $3, relation_types = $4, project_id = $5, updated_at = now() WHERE id = $6 AND owner = $7 RETURNING *";
+        let view = sqlx::query_as::<_, GraphView>(sql_str)
+            .bind(&self.name)
+            .bind(&self.description)
+            .bind(&self.datasets)
+            .bind(&self.relation_types)
+            .bind(self.project_id)
+            .bind(id)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(view)
+    }
+
+    /// Delete a view of the owner, the views of the others can't be deleted.
+    pub async fn delete(
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+    ) -> Result<GraphView, anyhow::Error> {
+        let sql_str = "DELETE FROM biomedgps_graph_view WHERE id = $1 AND owner = $2 RETURNING *";
+        let view = sqlx::query_as::<_, GraphView>(sql_str)
+            .bind(id)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(view)
+    }
+
+    /// The conditions of the relations in the view, the datasets and the relation types are validated, so they can be formatted into the sql directly.
+    fn gen_relation_query(&self) -> ComposeQueryItem {
+        let mut view_query = ComposeQueryItem::new("and");
+        view_query.add_item(ComposeQuery::QueryItem(QueryItem::new(
+            "dataset".to_string(),
+            Value::ArrayString(self.datasets.clone()),
+            "in".to_string(),
+        )));
+        if let Some(relation_types) = &self.relation_types {
+            if !relation_types.is_empty() {
+                view_query.add_item(ComposeQuery::QueryItem(QueryItem::new(
+                    "relation_type".to_string(),
+                    Value::ArrayString(relation_types.clone()),
+                    "in".to_string(),
+                )));
+            }
+        }
+
+        view_query
+    }
+
+    /// Combine the query of the relations with the conditions of the view, so only the relations in the view are matched.
+    pub fn apply_to_relation_query(&self, query: &Option<ComposeQuery>) -> Option<ComposeQuery> {
+        let mut view_query = self.gen_relation_query();
+        if let Some(query) = query {
+            view_query.add_item(query.clone());
+        }
+
+        Some(ComposeQuery::ComposeQueryItem(view_query))
+    }
+
+    /// Generate a derived table which only keeps the entities linked by the relations in the view, it can be used as a normal table by the RecordResponse::get_records function.
+    pub fn gen_entity_table_expr(&self, table_name: &str) -> String {
+        format!(
+            "(SELECT * FROM {table_name} entities WHERE EXISTS (SELECT 1 FROM biomedgps_relation WHERE {conditions} AND ((source_id = entities.id AND source_type = entities.label) OR (target_id = entities.id AND target_type = entities.label)))) AS view_entities",
+            table_name = table_name,
+            conditions = self.gen_relation_query().format()
+        )
+    }
+}
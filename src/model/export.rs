@@ -0,0 +1,1252 @@
+//! This module is used to export the knowledge graph in the background, such as a full relation dump, a KGX file, a GraphML file or a node-link json file for networkx. The large exports take minutes, so they are not suitable to run inside an HTTP request.
+//!
+//! A user creates an export job with a format and filters, the job runs on a dedicated thread and tracks the progress in the export job table. The artifact can be downloaded until it's expired, the expired artifacts are removed by the cleanup task of the server.
+
+use crate::{connect_db_with_config, quote_sql_literal, register_pool, unregister_pool, PoolConfig};
+use crate::model::core::{
+    CheckData, DatasetLicense, Entity, KnowledgeCuration, OwnerScope, Relation,
+};
+use crate::model::graph::Node;
+use crate::model::util::normalize_pmids;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The directory which stores the artifacts of the export jobs, a directory in the system temp directory is used if it's not set.
+pub const EXPORT_DIR_ENV: &str = "EXPORT_DIR";
+
+/// How long an artifact can be downloaded after the job is finished.
+pub const DEFAULT_EXPORT_EXPIRATION_HOURS: i64 = 24;
+
+/// How many relations are read from the database and written into the artifact at a time, the progress is updated after each batch.
+pub const EXPORT_BATCH_SIZE: i64 = 10000;
+
+/// How often the server removes the expired artifacts.
+pub const EXPORT_CLEANUP_INTERVAL_SECS: u64 = 3600;
+
+/// The secret salt of the curator pseudonyms. It must be kept unchanged, otherwise the same curator gets a different pseudonym in the later exports.
+pub const EXPORT_PSEUDONYM_SALT_ENV: &str = "EXPORT_PSEUDONYM_SALT";
+
+/// The prefix of the curator pseudonyms, such as `curator-3f2a9c0d41b7e865`.
+pub const CURATOR_PSEUDONYM_PREFIX: &str = "curator-";
+
+/// The prefix of the edge ids of the curations, so they don't collide with the ids of the relations in the same artifact.
+pub const CURATION_EDGE_ID_PREFIX: &str = "curation-";
+
+/// The tables which are included in a takeout, each one is a tuple of the file name in the zip, the table name, the column of the owner and the exported columns. The path of the export artifacts is only known by the server, so it's not exported.
+pub const TAKEOUT_TABLES: [(&str, &str, &str, &str); 7] = [
+    ("knowledge_curations", "biomedgps_knowledge_curation", "curator", "*"),
+    ("subgraphs", "biomedgps_subgraph", "owner", "*"),
+    ("node_tags", "biomedgps_node_tag", "owner", "*"),
+    ("graph_views", "biomedgps_graph_view", "owner", "*"),
+    ("entity_activities", "biomedgps_entity_activity", "username", "*"),
+    ("llm_usages", "biomedgps_llm_usage", "username", "*"),
+    (
+        "export_jobs",
+        "biomedgps_export_job",
+        "owner",
+        "id, owner, format, status, processed, total, message, filters, created_at, updated_at, expired_at, snapshot_id",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    // A tab-separated relation dump which has the same columns as the relation table.
+    Tsv,
+    // A KGX json file which contains the nodes and the edges, see https://github.com/biolink/kgx.
+    Kgx,
+    GraphML,
+    // A directed weighted graph in the node-link json format, it can be loaded by `networkx.node_link_graph` and converted into an igraph graph.
+    NodeLink,
+    // A zip file which contains all rows owned by the user in the json and tsv formats, the filters are ignored.
+    Takeout,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Kgx => "kgx",
+            ExportFormat::GraphML => "graphml",
+            ExportFormat::NodeLink => "nodelink",
+            ExportFormat::Takeout => "takeout",
+        }
+    }
+
+    pub fn from_name(format: &str) -> Option<Self> {
+        match format {
+            "tsv" => Some(ExportFormat::Tsv),
+            "kgx" => Some(ExportFormat::Kgx),
+            "graphml" => Some(ExportFormat::GraphML),
+            "nodelink" => Some(ExportFormat::NodeLink),
+            "takeout" => Some(ExportFormat::Takeout),
+            _ => None,
+        }
+    }
+
+    /// The extension of the artifact, such as `export-1.kgx.json`.
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Kgx => "kgx.json",
+            ExportFormat::GraphML => "graphml",
+            ExportFormat::NodeLink => "nodelink.json",
+            ExportFormat::Takeout => "zip",
+        }
+    }
+}
+
+/// The payload to create an export job. All relations except the ones of the restricted datasets are exported if no filters are set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct ExportJobRequest {
+    pub format: ExportFormat,
+    // Only export the relations in these datasets, such as ["drkg", "ctd"].
+    #[oai(skip_serializing_if_is_none)]
+    pub datasets: Option<Vec<String>>,
+    // Only export the relations with these relation types, such as ["DRUGBANK::treats::Compound:Disease"].
+    #[oai(skip_serializing_if_is_none)]
+    pub relation_types: Option<Vec<String>>,
+    // Only export the relations whose source and target are both in these entity types, such as ["Compound", "Disease"].
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub entity_types: Option<Vec<String>>,
+    // Only export the relations whose score is at least the threshold, the relations without a score are excluded. The curated knowledges have no score, so they are not filtered by it.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub min_score: Option<f64>,
+    // Export at most this number of relations, such as 100000 for a slice which fits in the memory of a notebook. The relations are exported in the order of the ids.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none, validator(minimum(value = "1")))]
+    pub max_relations: Option<i64>,
+    // The relations of the datasets which are not redistributable are excluded by default. Set it to true to include them, the override is logged for compliance.
+    #[serde(default)]
+    #[oai(default)]
+    pub include_restricted: bool,
+    // Also export the curated knowledges, the curator is exported as the resource of the curations.
+    #[serde(default)]
+    #[oai(default)]
+    pub include_curations: bool,
+    // Replace the curators with the stable pseudonyms, it's required for the non-admin users when the curations are included.
+    #[serde(default)]
+    #[oai(default)]
+    pub pseudonymize_curators: bool,
+    // Read the knowledge graph in a repeatable read transaction, so the artifact corresponds to a consistent snapshot even if the data is imported during the export.
+    #[serde(default)]
+    #[oai(default)]
+    pub snapshot: bool,
+    // The scope of the user who creates the job, it's set by the server and only the curated knowledges visible in the scope are exported. Only the curations of the owner are exported if it's missing.
+    #[serde(default)]
+    #[oai(skip)]
+    pub curation_scope: Option<OwnerScope>,
+}
+
+impl ExportJobRequest {
+    /// The request of a takeout, which exports all rows owned by the user.
+    pub fn takeout() -> Self {
+        Self {
+            format: ExportFormat::Takeout,
+            datasets: None,
+            relation_types: None,
+            entity_types: None,
+            min_score: None,
+            max_relations: None,
+            include_restricted: false,
+            include_curations: false,
+            pseudonymize_curators: false,
+            snapshot: false,
+            curation_scope: None,
+        }
+    }
+}
+
+/// The mapping between a pseudonym and a curator, it's only visible to the admin users.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct CuratorPseudonym {
+    pub pseudonym: String,
+    pub username: String,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl CuratorPseudonym {
+    pub async fn get_records(pool: &sqlx::PgPool) -> Result<Vec<CuratorPseudonym>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_curator_pseudonym ORDER BY username";
+        let records = sqlx::query_as::<_, CuratorPseudonym>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(records)
+    }
+}
+
+/// Get the salt of the curator pseudonyms.
+pub fn get_pseudonym_salt() -> Result<String, anyhow::Error> {
+    match std::env::var(EXPORT_PSEUDONYM_SALT_ENV) {
+        Ok(salt) if !salt.is_empty() => Ok(salt),
+        _ => Err(anyhow::anyhow!(
+            "{} is not set, the curators can't be pseudonymized.",
+            EXPORT_PSEUDONYM_SALT_ENV
+        )),
+    }
+}
+
+/// Replaces the curators with the pseudonyms. A pseudonym is derived from the HMAC-SHA256 of the username with the salt, so a curator always gets the same pseudonym in all exports and it can't be reversed without the salt.
+pub struct Pseudonymizer {
+    salt: String,
+    mapping: HashMap<String, String>,
+}
+
+impl Pseudonymizer {
+    pub fn new(salt: &str) -> Self {
+        Self {
+            salt: salt.to_string(),
+            mapping: HashMap::new(),
+        }
+    }
+
+    /// Get the pseudonym of a username.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::export::Pseudonymizer;
+    ///
+    /// let mut pseudonymizer = Pseudonymizer::new("salt");
+    /// let pseudonym = pseudonymizer.pseudonymize("alice");
+    /// assert!(pseudonym.starts_with("curator-"));
+    /// assert_eq!(pseudonym.len(), "curator-".len() + 16);
+    /// assert_eq!(pseudonymizer.pseudonymize("alice"), pseudonym);
+    /// assert_ne!(pseudonymizer.pseudonymize("bob"), pseudonym);
+    /// assert_ne!(Pseudonymizer::new("another-salt").pseudonymize("alice"), pseudonym);
+    /// ```
+    pub fn pseudonymize(&mut self, username: &str) -> String {
+        if let Some(pseudonym) = self.mapping.get(username) {
+            return pseudonym.clone();
+        }
+
+        // The salt can be any length for HMAC, so it never fails.
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.salt.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(username.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hex = digest
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let pseudonym = format!("{}{}", CURATOR_PSEUDONYM_PREFIX, hex);
+
+        self.mapping.insert(username.to_string(), pseudonym.clone());
+        pseudonym
+    }
+
+    /// Save the mapping, so the admin users can find the curator of a pseudonym.
+    pub async fn save(&self, pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+        if self.mapping.is_empty() {
+            return Ok(());
+        }
+
+        let (usernames, pseudonyms): (Vec<String>, Vec<String>) =
+            self.mapping.clone().into_iter().unzip();
+        let sql_str = "INSERT INTO biomedgps_curator_pseudonym (pseudonym, username) SELECT * FROM UNNEST($1::text[], $2::text[]) ON CONFLICT (pseudonym) DO NOTHING";
+        sqlx::query(sql_str)
+            .bind(&pseudonyms)
+            .bind(&usernames)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An export job. The status is one of pending, running, succeeded, failed and expired.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ExportJob {
+    pub id: i64,
+    pub owner: String,
+    pub format: String,
+    pub status: String,
+    // How many relations have been exported.
+    pub processed: i64,
+    // How many relations will be exported.
+    pub total: i64,
+    #[oai(skip_serializing_if_is_none)]
+    pub message: Option<String>,
+    pub filters: serde_json::Value,
+
+    // The path of the artifact on the server, it's not exposed to the users.
+    #[serde(skip)]
+    #[oai(skip)]
+    pub artifact: Option<String>,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(with = "ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+
+    // The artifact can't be downloaded after this time.
+    #[oai(skip_serializing_if_is_none)]
+    pub expired_at: Option<DateTime<Utc>>,
+
+    // The id of the snapshot which the artifact corresponds to, it's only set for the snapshot-consistent exports.
+    #[oai(skip_serializing_if_is_none)]
+    pub snapshot_id: Option<String>,
+}
+
+/// Get the directory which stores the artifacts.
+pub fn get_export_dir() -> PathBuf {
+    match std::env::var(EXPORT_DIR_ENV) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir().join("biomedgps-exports"),
+    }
+}
+
+/// Escape the special characters in the attribute values and the texts of a xml file.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::export::escape_xml;
+///
+/// assert_eq!(escape_xml("TP53 <-> \"MDM2\" & 'p21'"), "TP53 &lt;-&gt; &quot;MDM2&quot; &amp; &apos;p21&apos;");
+/// ```
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Writes the relations into an artifact batch by batch. The nodes are collected from the relations and written at the end, because the nodes can't be known until all relations are read.
+struct ArtifactWriter {
+    format: ExportFormat,
+    writer: BufWriter<File>,
+    nodes: HashSet<(String, String)>,
+    num_edges: usize,
+}
+
+impl ArtifactWriter {
+    /// Create the artifact and write the header, the snapshot id is recorded in the metadata of the kgx and graphml artifacts.
+    fn new(
+        format: ExportFormat,
+        filepath: &PathBuf,
+        snapshot_id: Option<&str>,
+    ) -> Result<Self, anyhow::Error> {
+        if format == ExportFormat::Takeout {
+            return Err(anyhow::anyhow!(
+                "The takeout is not a relation export, it's written by the takeout job."
+            ));
+        }
+
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        match format {
+            ExportFormat::Tsv => {
+                writeln!(
+                    writer,
+                    "id\trelation_type\tsource_id\tsource_type\ttarget_id\ttarget_type\tkey_sentence\tresource\tdataset\tpmids\tlicense"
+                )?;
+            }
+            ExportFormat::Kgx => {
+                write!(writer, "{{")?;
+                if let Some(snapshot_id) = snapshot_id {
+                    write!(writer, "\"snapshot_id\": {}, ", json!(snapshot_id))?;
+                }
+                write!(writer, "\"edges\": [")?;
+            }
+            ExportFormat::NodeLink => {
+                let mut graph = json!({"name": "biomedgps"});
+                if let Some(snapshot_id) = snapshot_id {
+                    graph["snapshot_id"] = json!(snapshot_id);
+                }
+                write!(
+                    writer,
+                    "{{\"directed\": true, \"multigraph\": true, \"graph\": {}, \"links\": [",
+                    graph
+                )?;
+            }
+            ExportFormat::GraphML => {
+                writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+                writeln!(
+                    writer,
+                    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+                )?;
+                for (key, target) in [
+                    ("id", "node"),
+                    ("name", "node"),
+                    ("label", "node"),
+                    ("resource", "node"),
+                    ("relation_type", "edge"),
+                    ("resource", "edge"),
+                    ("dataset", "edge"),
+                    ("license", "edge"),
+                    ("pmids", "edge"),
+                    ("key_sentence", "edge"),
+                ] {
+                    writeln!(
+                        writer,
+                        "  <key id=\"{}_{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"string\"/>",
+                        target, key, target, key
+                    )?;
+                }
+                if snapshot_id.is_some() {
+                    writeln!(
+                        writer,
+                        "  <key id=\"graph_snapshot_id\" for=\"graph\" attr.name=\"snapshot_id\" attr.type=\"string\"/>"
+                    )?;
+                }
+                writeln!(
+                    writer,
+                    "  <graph id=\"biomedgps\" edgedefault=\"directed\">"
+                )?;
+                if let Some(snapshot_id) = snapshot_id {
+                    writeln!(
+                        writer,
+                        "    <data key=\"graph_snapshot_id\">{}</data>",
+                        escape_xml(snapshot_id)
+                    )?;
+                }
+            }
+            ExportFormat::Takeout => {}
+        }
+
+        Ok(Self {
+            format,
+            writer,
+            nodes: HashSet::new(),
+            num_edges: 0,
+        })
+    }
+
+    /// Write the relations, the id prefix is prepended to the edge ids, such as `curation-1`.
+    fn write_relations(
+        &mut self,
+        relations: &Vec<Relation>,
+        id_prefix: &str,
+    ) -> Result<(), anyhow::Error> {
+        for relation in relations {
+            let edge_id = format!("{}{}", id_prefix, relation.id);
+            let key_sentence = relation.key_sentence.clone().unwrap_or_default();
+            let dataset = relation.dataset.clone().unwrap_or_default();
+            let license = relation.license.clone().unwrap_or_default();
+            let pmids = match &relation.pmids {
+                Some(pmids) => normalize_pmids(pmids).unwrap_or(pmids.clone()),
+                None => "".to_string(),
+            };
+
+            match self.format {
+                ExportFormat::Tsv => {
+                    let values = vec![
+                        edge_id,
+                        relation.relation_type.clone(),
+                        relation.source_id.clone(),
+                        relation.source_type.clone(),
+                        relation.target_id.clone(),
+                        relation.target_type.clone(),
+                        key_sentence,
+                        relation.resource.clone(),
+                        dataset,
+                        pmids,
+                        license,
+                    ];
+                    // Keep one relation per line even if the key sentence contains tabs or newlines.
+                    let values = values
+                        .into_iter()
+                        .map(|v| v.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " "))
+                        .collect::<Vec<String>>();
+                    writeln!(self.writer, "{}", values.join("\t"))?;
+                }
+                ExportFormat::Kgx => {
+                    let publications = pmids
+                        .split('|')
+                        .filter(|p| !p.is_empty())
+                        .map(|p| format!("PMID:{}", p))
+                        .collect::<Vec<String>>();
+                    let edge = json!({
+                        "id": edge_id,
+                        "subject": relation.source_id,
+                        "predicate": "biolink:related_to",
+                        "original_predicate": relation.relation_type,
+                        "object": relation.target_id,
+                        "primary_knowledge_source": relation.resource,
+                        "knowledge_source": dataset,
+                        "license": license,
+                        "publications": publications,
+                        "description": key_sentence,
+                    });
+                    if self.num_edges > 0 {
+                        write!(self.writer, ",")?;
+                    }
+                    write!(self.writer, "\n  {}", edge)?;
+                }
+                ExportFormat::NodeLink => {
+                    // The typed attributes are kept, so the weights and the pmids don't need to be parsed in python.
+                    let pmids = pmids
+                        .split('|')
+                        .filter_map(|p| p.parse::<i64>().ok())
+                        .collect::<Vec<i64>>();
+                    let link = json!({
+                        "source": Node::format_id(&relation.source_type, &relation.source_id),
+                        "target": Node::format_id(&relation.target_type, &relation.target_id),
+                        "key": edge_id,
+                        "weight": relation.score.unwrap_or(1.0),
+                        "relation_type": relation.relation_type,
+                        "resource": relation.resource,
+                        "dataset": dataset,
+                        "license": license,
+                        "pmids": pmids,
+                        "key_sentence": key_sentence,
+                    });
+                    if self.num_edges > 0 {
+                        write!(self.writer, ",")?;
+                    }
+                    write!(self.writer, "\n  {}", link)?;
+                }
+                ExportFormat::GraphML => {
+                    writeln!(
+                        self.writer,
+                        "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+                        escape_xml(&edge_id),
+                        escape_xml(&Node::format_id(&relation.source_type, &relation.source_id)),
+                        escape_xml(&Node::format_id(&relation.target_type, &relation.target_id)),
+                    )?;
+                    for (key, value) in [
+                        ("relation_type", &relation.relation_type),
+                        ("resource", &relation.resource),
+                        ("dataset", &dataset),
+                        ("license", &license),
+                        ("pmids", &pmids),
+                        ("key_sentence", &key_sentence),
+                    ] {
+                        writeln!(
+                            self.writer,
+                            "      <data key=\"edge_{}\">{}</data>",
+                            key,
+                            escape_xml(value)
+                        )?;
+                    }
+                    writeln!(self.writer, "    </edge>")?;
+                }
+                ExportFormat::Takeout => {}
+            }
+
+            self.nodes
+                .insert((relation.source_type.clone(), relation.source_id.clone()));
+            self.nodes
+                .insert((relation.target_type.clone(), relation.target_id.clone()));
+            self.num_edges += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Write the nodes which are referenced by the relations and close the artifact. The tsv artifact doesn't contain the nodes.
+    async fn finish(mut self, conn: &mut sqlx::PgConnection) -> Result<(), anyhow::Error> {
+        if self.format == ExportFormat::Tsv || self.format == ExportFormat::Takeout {
+            self.writer.flush()?;
+            return Ok(());
+        }
+
+        if self.format == ExportFormat::Kgx || self.format == ExportFormat::NodeLink {
+            write!(self.writer, "\n], \"nodes\": [")?;
+        }
+
+        let nodes = self.nodes.drain().collect::<Vec<(String, String)>>();
+        // The same id might be used by several entity types in the relations, such as a MESH id, but the ids must be unique in a KGX file.
+        let mut kgx_ids = HashSet::new();
+        let mut num_nodes = 0;
+        for chunk in nodes.chunks(EXPORT_BATCH_SIZE as usize) {
+            let (labels, ids): (Vec<String>, Vec<String>) = chunk.iter().cloned().unzip();
+            let sql_str = "SELECT * FROM biomedgps_entity WHERE (label, id) IN (SELECT * FROM UNNEST($1::text[], $2::text[]))";
+            let entities = sqlx::query_as::<_, Entity>(sql_str)
+                .bind(&labels)
+                .bind(&ids)
+                .fetch_all(&mut *conn)
+                .await?;
+
+            // The nodes which are not in the entity table are still exported, so the edges are not dangling.
+            let mut missing = chunk.iter().cloned().collect::<HashSet<(String, String)>>();
+            let mut records = entities
+                .into_iter()
+                .map(|e| {
+                    missing.remove(&(e.label.clone(), e.id.clone()));
+                    (e.label, e.id, e.name, e.resource)
+                })
+                .collect::<Vec<(String, String, String, String)>>();
+            records.extend(
+                missing
+                    .into_iter()
+                    .map(|(label, id)| (label, id.clone(), id, "".to_string())),
+            );
+
+            for (label, id, name, resource) in records {
+                match self.format {
+                    ExportFormat::Kgx => {
+                        if !kgx_ids.insert(id.clone()) {
+                            continue;
+                        }
+
+                        let node = json!({
+                            "id": id,
+                            "category": [format!("biolink:{}", label)],
+                            "name": name,
+                            "provided_by": [resource],
+                        });
+                        if kgx_ids.len() > 1 {
+                            write!(self.writer, ",")?;
+                        }
+                        write!(self.writer, "\n  {}", node)?;
+                    }
+                    ExportFormat::NodeLink => {
+                        let node = json!({
+                            "id": Node::format_id(&label, &id),
+                            "entity_id": id,
+                            "name": name,
+                            "label": label,
+                            "resource": resource,
+                        });
+                        if num_nodes > 0 {
+                            write!(self.writer, ",")?;
+                        }
+                        write!(self.writer, "\n  {}", node)?;
+                        num_nodes += 1;
+                    }
+                    ExportFormat::GraphML => {
+                        writeln!(
+                            self.writer,
+                            "    <node id=\"{}\">",
+                            escape_xml(&Node::format_id(&label, &id))
+                        )?;
+                        for (key, value) in [
+                            ("id", &id),
+                            ("name", &name),
+                            ("label", &label),
+                            ("resource", &resource),
+                        ] {
+                            writeln!(
+                                self.writer,
+                                "      <data key=\"node_{}\">{}</data>",
+                                key,
+                                escape_xml(value)
+                            )?;
+                        }
+                        writeln!(self.writer, "    </node>")?;
+                    }
+                    ExportFormat::Tsv | ExportFormat::Takeout => {}
+                }
+            }
+        }
+
+        match self.format {
+            ExportFormat::Kgx | ExportFormat::NodeLink => writeln!(self.writer, "\n]}}")?,
+            ExportFormat::GraphML => {
+                writeln!(self.writer, "  </graph>")?;
+                writeln!(self.writer, "</graphml>")?;
+            }
+            ExportFormat::Tsv | ExportFormat::Takeout => {}
+        }
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl ExportJob {
+    /// Save the job as pending and run it in the background. The job runs on a dedicated thread with its own runtime and connection pool like the import jobs, so the long export doesn't block the server.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `database_url` - The database url which is used by the export job
+    /// * `owner` - The username of the user who creates the job
+    /// * `request` - The format and the filters of the export
+    ///
+    /// # Returns
+    /// * `Result<ExportJob, anyhow::Error>` - The pending job or an error
+    pub async fn schedule(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        owner: &str,
+        request: &ExportJobRequest,
+    ) -> Result<ExportJob, anyhow::Error> {
+        if request.include_curations && request.pseudonymize_curators {
+            get_pseudonym_salt()?;
+        }
+
+        let sql_str = "INSERT INTO biomedgps_export_job (owner, format, status, filters) VALUES ($1, $2, 'pending', $3) RETURNING *";
+        let job = sqlx::query_as::<_, ExportJob>(sql_str)
+            .bind(owner)
+            .bind(request.format.as_str())
+            .bind(serde_json::to_value(request)?)
+            .fetch_one(pool)
+            .await?;
+
+        // The override is also kept in the filters of the job, so it can be audited later.
+        if request.include_restricted {
+            let restricted_datasets = DatasetLicense::get_restricted_datasets(pool)
+                .await?
+                .into_iter()
+                .filter(|d| match &request.datasets {
+                    Some(datasets) => datasets.contains(d),
+                    None => true,
+                })
+                .collect::<Vec<String>>();
+            warn!(
+                "[Compliance] The export job {} of the user {} includes the relations of the restricted datasets {:?} by the include_restricted override.",
+                job.id, owner, restricted_datasets
+            );
+        }
+
+        let database_url = database_url.to_string();
+        let owner = owner.to_string();
+        let request = request.clone();
+        let job_id = job.id;
+        std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Runtime::new() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    error!("Failed to start the export job {}: {}", job_id, e);
+                    return;
+                }
+            };
+
+            runtime.block_on(async {
+                let pool_name = format!("export-job-{}", job_id);
+                let pool_config = PoolConfig::job();
+                let pool = connect_db_with_config(&database_url, &pool_config).await;
+                register_pool(&pool_name, &pool, &pool_config);
+                let result = match request.format {
+                    ExportFormat::Takeout => Self::run_takeout(&pool, job_id, &owner).await,
+                    _ => Self::run(&pool, job_id, &owner, &request).await,
+                };
+                if let Err(e) = result {
+                    error!("The export job {} failed: {}", job_id, e);
+                    let sql_str = "UPDATE biomedgps_export_job SET status = 'failed', message = $1, updated_at = now() WHERE id = $2";
+                    if let Err(e) = sqlx::query(sql_str)
+                        .bind(e.to_string())
+                        .bind(job_id)
+                        .execute(&pool)
+                        .await
+                    {
+                        error!("Failed to update the export job {}: {}", job_id, e);
+                    }
+                }
+
+                unregister_pool(&pool_name);
+                pool.close().await;
+            });
+        });
+
+        Ok(job)
+    }
+
+    async fn run(
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+        request: &ExportJobRequest,
+    ) -> Result<(), anyhow::Error> {
+        // All reads of the knowledge graph are in the same transaction, the progress is updated by the pool, so it's visible before the job is finished.
+        let mut tx = pool.begin().await?;
+        let snapshot_id = if request.snapshot {
+            sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+                .execute(&mut tx)
+                .await?;
+            let snapshot_id: String = sqlx::query_scalar("SELECT pg_export_snapshot()")
+                .fetch_one(&mut tx)
+                .await?;
+            info!("The export job {} reads the snapshot {}.", id, snapshot_id);
+            Some(snapshot_id)
+        } else {
+            None
+        };
+
+        let where_str = "($1::text[] IS NULL OR dataset = ANY($1)) AND ($2::text[] IS NULL OR relation_type = ANY($2)) AND ($3 OR NOT EXISTS (SELECT 1 FROM biomedgps_dataset_license l WHERE l.dataset = biomedgps_relation.dataset AND NOT l.redistributable)) AND ($4::text[] IS NULL OR (source_type = ANY($4) AND target_type = ANY($4))) AND ($5::float8 IS NULL OR score >= $5)";
+        let num_relations: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM biomedgps_relation WHERE {}",
+            where_str
+        ))
+        .bind(&request.datasets)
+        .bind(&request.relation_types)
+        .bind(request.include_restricted)
+        .bind(&request.entity_types)
+        .bind(request.min_score)
+        .fetch_one(&mut tx)
+        .await?;
+        let max_relations = request.max_relations.unwrap_or(i64::MAX);
+        let mut total = num_relations.min(max_relations);
+
+        // Same as the curation list endpoints, the users only export their own curations and the ones of their organizations and projects.
+        let curation_scope = match &request.curation_scope {
+            Some(scope) => scope.clone(),
+            None => OwnerScope::new(owner, &vec![], &vec![]),
+        };
+        let curation_where_str = format!(
+            "($1::text[] IS NULL OR relation_type = ANY($1)) AND ($2::text[] IS NULL OR (source_type = ANY($2) AND target_type = ANY($2))) AND {}",
+            KnowledgeCuration::visible_condition(&curation_scope)
+        );
+        if request.include_curations {
+            let num_curations: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM biomedgps_knowledge_curation WHERE {}",
+                curation_where_str
+            ))
+            .bind(&request.relation_types)
+            .bind(&request.entity_types)
+            .fetch_one(&mut tx)
+            .await?;
+            total += num_curations;
+        }
+
+        let export_dir = get_export_dir();
+        std::fs::create_dir_all(&export_dir)?;
+        let filepath = export_dir.join(format!("export-{}.{}", id, request.format.extension()));
+
+        let sql_str = "UPDATE biomedgps_export_job SET status = 'running', total = $1, artifact = $2, snapshot_id = $3, updated_at = now() WHERE id = $4";
+        sqlx::query(sql_str)
+            .bind(total)
+            .bind(filepath.to_string_lossy().to_string())
+            .bind(&snapshot_id)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        let mut writer = ArtifactWriter::new(request.format, &filepath, snapshot_id.as_deref())?;
+        let sql_str = format!(
+            "SELECT * FROM biomedgps_relation WHERE {} AND id > $6 ORDER BY id LIMIT $7",
+            where_str
+        );
+        let mut processed: i64 = 0;
+        let mut last_id: i64 = 0;
+        while processed < max_relations {
+            let relations = sqlx::query_as::<_, Relation>(&sql_str)
+                .bind(&request.datasets)
+                .bind(&request.relation_types)
+                .bind(request.include_restricted)
+                .bind(&request.entity_types)
+                .bind(request.min_score)
+                .bind(last_id)
+                .bind(EXPORT_BATCH_SIZE.min(max_relations - processed))
+                .fetch_all(&mut tx)
+                .await?;
+
+            if relations.is_empty() {
+                break;
+            }
+
+            last_id = relations.last().unwrap().id;
+            processed += relations.len() as i64;
+            writer.write_relations(&relations, "")?;
+
+            sqlx::query(
+                "UPDATE biomedgps_export_job SET processed = $1, updated_at = now() WHERE id = $2",
+            )
+            .bind(processed)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        }
+
+        if request.include_curations {
+            let mut pseudonymizer = if request.pseudonymize_curators {
+                Some(Pseudonymizer::new(&get_pseudonym_salt()?))
+            } else {
+                None
+            };
+
+            let columns = <KnowledgeCuration as CheckData>::fields().join(",");
+            let sql_str = format!(
+                "SELECT id,created_at,payload,{} FROM biomedgps_knowledge_curation WHERE {} AND id > $3 ORDER BY id LIMIT $4",
+                columns, curation_where_str
+            );
+            let mut last_id: i64 = 0;
+            loop {
+                let curations = sqlx::query_as::<_, KnowledgeCuration>(&sql_str)
+                    .bind(&request.relation_types)
+                    .bind(&request.entity_types)
+                    .bind(last_id)
+                    .bind(EXPORT_BATCH_SIZE)
+                    .fetch_all(&mut tx)
+                    .await?;
+
+                if curations.is_empty() {
+                    break;
+                }
+
+                last_id = curations.last().unwrap().id;
+                processed += curations.len() as i64;
+                let relations = curations
+                    .iter()
+                    .map(|c| {
+                        let mut relation = c.to_relation();
+                        if let Some(pseudonymizer) = pseudonymizer.as_mut() {
+                            relation.resource = pseudonymizer.pseudonymize(&c.curator);
+                        }
+                        relation
+                    })
+                    .collect::<Vec<Relation>>();
+                writer.write_relations(&relations, CURATION_EDGE_ID_PREFIX)?;
+
+                sqlx::query(
+                    "UPDATE biomedgps_export_job SET processed = $1, updated_at = now() WHERE id = $2",
+                )
+                .bind(processed)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            }
+
+            // The mapping is saved before the artifact is available, so an admin can always resolve the pseudonyms in it.
+            if let Some(pseudonymizer) = pseudonymizer {
+                pseudonymizer.save(pool).await?;
+            }
+        }
+
+        writer.finish(&mut tx).await?;
+        // Nothing is written in the transaction, it's only used for reading.
+        tx.rollback().await?;
+
+        let expired_at = Utc::now() + Duration::hours(DEFAULT_EXPORT_EXPIRATION_HOURS);
+        let sql_str = "UPDATE biomedgps_export_job SET status = 'succeeded', expired_at = $1, updated_at = now() WHERE id = $2";
+        sqlx::query(sql_str)
+            .bind(expired_at)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        info!(
+            "The export job {} exported {} relations into {}.",
+            id,
+            processed,
+            filepath.display()
+        );
+
+        Ok(())
+    }
+
+    /// Write all rows owned by the user into a zip file. Each table is written as a json file and a tsv file, and a manifest with the row counts is written at the end.
+    async fn run_takeout(pool: &sqlx::PgPool, id: i64, owner: &str) -> Result<(), anyhow::Error> {
+        // Read all tables in the same transaction, so the files are consistent with each other.
+        let mut tx = pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut tx)
+            .await?;
+
+        let mut counts = vec![];
+        for (_, table, owner_column, _) in TAKEOUT_TABLES.iter() {
+            let count: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {} WHERE {} = $1",
+                table, owner_column
+            ))
+            .bind(owner)
+            .fetch_one(&mut tx)
+            .await?;
+            counts.push(count);
+        }
+        let total: i64 = counts.iter().sum();
+
+        let export_dir = get_export_dir();
+        std::fs::create_dir_all(&export_dir)?;
+        let filepath = export_dir.join(format!(
+            "export-{}.{}",
+            id,
+            ExportFormat::Takeout.extension()
+        ));
+
+        let sql_str = "UPDATE biomedgps_export_job SET status = 'running', total = $1, artifact = $2, updated_at = now() WHERE id = $3";
+        sqlx::query(sql_str)
+            .bind(total)
+            .bind(filepath.to_string_lossy().to_string())
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        let mut zip = ZipWriter::new(File::create(&filepath)?);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut processed: i64 = 0;
+        let mut manifest = serde_json::Map::new();
+        for ((name, table, owner_column, columns), count) in TAKEOUT_TABLES.iter().zip(counts) {
+            let query_str = format!(
+                "SELECT {} FROM {} WHERE {} = {} ORDER BY 1",
+                columns,
+                table,
+                owner_column,
+                quote_sql_literal(owner)
+            );
+
+            let rows: String = sqlx::query_scalar(&format!(
+                "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM ({}) t",
+                query_str
+            ))
+            .fetch_one(&mut tx)
+            .await?;
+            zip.start_file(format!("{}.json", name), options)?;
+            zip.write_all(rows.as_bytes())?;
+
+            // The same format as the exportdb command, so the files can be imported again.
+            zip.start_file(format!("{}.tsv", name), options)?;
+            let mut stream = tx
+                .copy_out_raw(&format!(
+                    "COPY ({}) TO STDOUT WITH (FORMAT csv, DELIMITER E'\\t', HEADER)",
+                    query_str
+                ))
+                .await?;
+            while let Some(chunk) = stream.next().await {
+                zip.write_all(&chunk?)?;
+            }
+            drop(stream);
+
+            manifest.insert(name.to_string(), json!(count));
+            processed += count;
+            sqlx::query(
+                "UPDATE biomedgps_export_job SET processed = $1, updated_at = now() WHERE id = $2",
+            )
+            .bind(processed)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        }
+
+        zip.start_file("manifest.json", options)?;
+        let manifest = json!({
+            "owner": owner,
+            "created_at": Utc::now().to_rfc3339(),
+            "counts": manifest,
+        });
+        zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+        zip.finish()?;
+        tx.rollback().await?;
+
+        let expired_at = Utc::now() + Duration::hours(DEFAULT_EXPORT_EXPIRATION_HOURS);
+        let sql_str = "UPDATE biomedgps_export_job SET status = 'succeeded', expired_at = $1, updated_at = now() WHERE id = $2";
+        sqlx::query(sql_str)
+            .bind(expired_at)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        info!(
+            "The takeout job {} exported {} rows of the user {} into {}.",
+            id,
+            processed,
+            owner,
+            filepath.display()
+        );
+
+        Ok(())
+    }
+
+    /// Fetch a job of the owner, so the users can't see the jobs of the others.
+    pub async fn fetch(
+        pool: &sqlx::PgPool,
+        id: i64,
+        owner: &str,
+    ) -> Result<ExportJob, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_export_job WHERE id = $1 AND owner = $2";
+        let job = sqlx::query_as::<_, ExportJob>(sql_str)
+            .bind(id)
+            .bind(owner)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(job)
+    }
+
+    /// List the jobs of the owner, the newest first.
+    pub async fn list(pool: &sqlx::PgPool, owner: &str) -> Result<Vec<ExportJob>, anyhow::Error> {
+        let sql_str = "SELECT * FROM biomedgps_export_job WHERE owner = $1 ORDER BY id DESC";
+        let jobs = sqlx::query_as::<_, ExportJob>(sql_str)
+            .bind(owner)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(jobs)
+    }
+
+    /// Get the path of the artifact if it can be downloaded.
+    pub fn get_artifact(&self) -> Result<PathBuf, anyhow::Error> {
+        if self.status != "succeeded" {
+            return Err(anyhow::anyhow!(
+                "The export job {} is {}, only the artifact of a succeeded job can be downloaded.",
+                self.id,
+                self.status
+            ));
+        }
+
+        if self.expired_at.map(|t| t < Utc::now()).unwrap_or(false) {
+            return Err(anyhow::anyhow!(
+                "The artifact of the export job {} is expired.",
+                self.id
+            ));
+        }
+
+        match &self.artifact {
+            Some(artifact) if PathBuf::from(artifact).is_file() => Ok(PathBuf::from(artifact)),
+            _ => Err(anyhow::anyhow!(
+                "The artifact of the export job {} doesn't exist.",
+                self.id
+            )),
+        }
+    }
+
+    /// The filename of the artifact when it's downloaded, such as `biomedgps-export-1.graphml`.
+    pub fn get_filename(&self) -> String {
+        let extension = match ExportFormat::from_name(&self.format) {
+            Some(format) => format.extension().to_string(),
+            None => self.format.clone(),
+        };
+        format!("biomedgps-export-{}.{}", self.id, extension)
+    }
+
+    /// Remove the artifacts of the expired jobs and mark the jobs as expired. It's called by the cleanup task of the server periodically.
+    ///
+    /// # Returns
+    /// * `Result<usize, anyhow::Error>` - How many jobs are expired or an error
+    pub async fn cleanup_expired(pool: &sqlx::PgPool) -> Result<usize, anyhow::Error> {
+        let sql_str =
+            "SELECT * FROM biomedgps_export_job WHERE status = 'succeeded' AND expired_at < now()";
+        let jobs = sqlx::query_as::<_, ExportJob>(sql_str)
+            .fetch_all(pool)
+            .await?;
+
+        for job in jobs.iter() {
+            if let Some(artifact) = &job.artifact {
+                match std::fs::remove_file(artifact) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        // Keep the job as succeeded, so the artifact is removed in the next round.
+                        warn!("Failed to remove the artifact {}: {}", artifact, e);
+                        continue;
+                    }
+                }
+            }
+
+            let sql_str = "UPDATE biomedgps_export_job SET status = 'expired', artifact = NULL, updated_at = now() WHERE id = $1";
+            sqlx::query(sql_str).bind(job.id).execute(pool).await?;
+        }
+
+        Ok(jobs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(id: i64, source_id: &str, target_id: &str) -> Relation {
+        Relation {
+            id,
+            relation_type: "DRUGBANK::treats::Compound:Disease".to_string(),
+            formatted_relation_type: None,
+            source_id: source_id.to_string(),
+            source_type: "Compound".to_string(),
+            target_id: target_id.to_string(),
+            target_type: "Disease".to_string(),
+            score: None,
+            key_sentence: Some("A\ttreats <B>".to_string()),
+            resource: "DRUGBANK".to_string(),
+            dataset: Some("drkg".to_string()),
+            pmids: None,
+            tissue: None,
+            tissue_id: None,
+            cell_type: None,
+            cell_type_id: None,
+            datasets: None,
+            resources: None,
+            license: None,
+            n_pmids: None,
+            n_datasets: None,
+            n_curations: None,
+            verdict: None,
+        }
+    }
+
+    #[test]
+    fn test_export_format() {
+        for format in [
+            ExportFormat::Tsv,
+            ExportFormat::Kgx,
+            ExportFormat::GraphML,
+            ExportFormat::NodeLink,
+            ExportFormat::Takeout,
+        ] {
+            assert_eq!(ExportFormat::from_name(format.as_str()), Some(format));
+        }
+        assert_eq!(ExportFormat::from_name("csv"), None);
+        assert_eq!(ExportFormat::Takeout.extension(), "zip");
+        assert!(ArtifactWriter::new(
+            ExportFormat::Takeout,
+            &std::env::temp_dir().join("biomedgps-takeout-test.zip"),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_write_relations() {
+        let dir = tempfile::tempdir().unwrap();
+        let relations = vec![
+            relation(1, "DrugBank:DB00001", "MESH:D000001"),
+            relation(2, "DrugBank:DB00002", "MESH:D000001"),
+        ];
+
+        let filepath = dir.path().join("export.tsv");
+        let mut writer = ArtifactWriter::new(ExportFormat::Tsv, &filepath, None).unwrap();
+        writer.write_relations(&relations, "").unwrap();
+        writer.writer.flush().unwrap();
+        let content = std::fs::read_to_string(&filepath).unwrap();
+        let lines = content.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].split('\t').count(), 11);
+        assert!(lines[1].contains("A treats <B>"));
+
+        let filepath = dir.path().join("export.graphml");
+        let mut writer = ArtifactWriter::new(ExportFormat::GraphML, &filepath, None).unwrap();
+        writer.write_relations(&relations, "").unwrap();
+        writer
+            .write_relations(&relations[..1].to_vec(), CURATION_EDGE_ID_PREFIX)
+            .unwrap();
+        assert_eq!(writer.nodes.len(), 3);
+        writer.writer.flush().unwrap();
+        let content = std::fs::read_to_string(&filepath).unwrap();
+        assert!(content.contains("<edge id=\"e1\" source=\"Compound::DrugBank:DB00001\" target=\"Disease::MESH:D000001\">"));
+        assert!(content.contains("A\ttreats &lt;B&gt;"));
+        assert!(content.contains("<edge id=\"ecuration-1\""));
+
+        let filepath = dir.path().join("export.nodelink.json");
+        let mut writer = ArtifactWriter::new(ExportFormat::NodeLink, &filepath, None).unwrap();
+        let mut scored = relation(3, "DrugBank:DB00003", "MESH:D000001");
+        scored.score = Some(0.5);
+        scored.pmids = Some("123|456".to_string());
+        writer
+            .write_relations(&relations[..1].to_vec(), "")
+            .unwrap();
+        writer.write_relations(&vec![scored], "").unwrap();
+        write!(writer.writer, "\n]}}").unwrap();
+        writer.writer.flush().unwrap();
+        let graph: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&filepath).unwrap()).unwrap();
+        assert_eq!(graph["directed"], json!(true));
+        assert_eq!(
+            graph["links"][0]["source"],
+            json!("Compound::DrugBank:DB00001")
+        );
+        assert_eq!(graph["links"][0]["weight"], json!(1.0));
+        assert_eq!(graph["links"][1]["weight"], json!(0.5));
+        assert_eq!(graph["links"][1]["pmids"], json!([123, 456]));
+    }
+}
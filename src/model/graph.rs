@@ -5,9 +5,19 @@
 //! - The module is used to fetch the graph data from the postgresql database or neo4j graph database and convert it to the graph data structure which can be used by the frontend.
 //!
 
-use super::core::KnowledgeCuration;
-use super::init_db::get_kg_score_table_name;
-use crate::model::core::{Entity, RecordResponse, Relation, DEFAULT_DATASET_NAME};
+use super::core::{CuratedKnowledgeFilter, KnowledgeCuration};
+use crate::algorithm::layout::{force_directed_layout, DEFAULT_EDGE_LENGTH};
+use crate::algorithm::path::{enumerate_paths, DEFAULT_MAX_ENUMERATED_PATHS};
+use crate::algorithm::structure::{core_numbers, local_clustering_coefficients};
+use super::init_db::{
+    check_kg_score_table, get_kg_score_table_name, get_top_relations_size,
+    get_top_relations_table_name,
+};
+use crate::model::core::{
+    Entity, NodeTag, RecordResponse, Relation, RelationQualifier, DEFAULT_DATASET_NAME,
+    DEFAULT_TEXT_EMBEDDING_MODEL,
+};
+use crate::model::image::{get_thumbnail_url, EntityImage};
 use crate::model::init_db::get_triple_entity_score_table_name;
 use crate::model::kge::{
     get_embedding_metadata, get_entity_emb_table_name, get_relation_emb_table_name,
@@ -19,15 +29,23 @@ use crate::query_builder::sql_builder::ComposeQuery;
 use lazy_static::lazy_static;
 use log::{debug, error};
 use neo4rs::{Node as NeoNode, Relation as NeoRelation};
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::vec;
 
 // The delimiter is defined here, if we want to change it, please change it here.
 pub const COMPOSED_ENTITY_DELIMITER: &str = "::";
 pub const PREDICTED_EDGE_TYPE: &str = "PredictedRelation";
+// It's used in the relid of the aggregate edge which collapses the parallel edges.
+pub const AGGREGATE_EDGE_TYPE: &str = "AggregatedRelation";
+// A candidate must be predicted from at least two nodes of the subgraph to extend it coherently.
+pub const DEFAULT_MIN_ANCHORS: usize = 2;
 
 lazy_static! {
     pub static ref COMPOSED_ENTITY_REGEX: Regex =
@@ -51,6 +69,35 @@ lazy_static! {
         m
     };
 
+    // The relation types which flag the candidate compounds in the drug ranking, grouped by the kind of the warning. The predicates are normalized, such as `ccse` for `Hetionet::CcSE::Compound:SideEffect`.
+    pub static ref SAFETY_WARNING_GROUPS: Vec<(&'static str, Vec<&'static str>)> = vec![
+        (
+            "contraindication",
+            vec![
+                "contraindication",
+                "contraindicates",
+                "contraindicated_for",
+                "contraindicated_in",
+                "has_contraindication",
+            ],
+        ),
+        (
+            "adverse_event",
+            vec![
+                "adverse_event",
+                "adverse_effect",
+                "side_effect",
+                "has_side_effect",
+                "causes_side_effect",
+                "ccse",
+                "causes",
+                "causes_or_contributes_to_condition",
+                "exacerbates",
+                "worsens",
+            ],
+        ),
+    ];
+
     pub static ref PREDICTED_EDGE_TYPES: Vec<&'static str> = {
         let mut v = Vec::new();
         v.push(PREDICTED_EDGE_TYPE);
@@ -236,12 +283,12 @@ impl NodeData {
             id: id,
             label: labels[0].to_owned(),
             name: node.get::<String>("name").unwrap_or_default(),
-            description: node.get::<String>("description"),
+            description: node.get::<String>("description").ok(),
             resource: node.get::<String>("resource").unwrap_or_default(),
-            xrefs: node.get::<String>("xrefs"),
-            pmids: node.get::<String>("pmids"),
-            taxid: node.get::<String>("taxid"),
-            synonyms: node.get::<String>("synonyms"),
+            xrefs: node.get::<String>("xrefs").ok(),
+            pmids: node.get::<String>("pmids").ok(),
+            taxid: node.get::<String>("taxid").ok(),
+            synonyms: node.get::<String>("synonyms").ok(),
         }
     }
 
@@ -295,6 +342,16 @@ pub struct Node {
     pub x: Option<f64>,
     pub y: Option<f64>,
     pub data: NodeData,
+    // The tags of the node which are visible to the current user, they are shown as badges.
+    #[oai(skip_serializing_if_is_none)]
+    pub tags: Option<Vec<NodeTag>>,
+    // The url of the cached image of the entity, such as the structure of a compound.
+    #[oai(skip_serializing_if_is_none)]
+    pub thumbnail: Option<String>,
+    // The known adverse events and contraindications of a candidate compound, it's empty if the compound is checked and nothing is found.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub warnings: Option<Vec<SafetyWarning>>,
 }
 
 impl Node {
@@ -314,6 +371,9 @@ impl Node {
             x: None,
             y: None,
             data: NodeData::new(entity),
+            tags: None,
+            thumbnail: None,
+            warnings: None,
         }
     }
 
@@ -331,6 +391,9 @@ impl Node {
             x: None,
             y: None,
             data: node.clone(),
+            tags: None,
+            thumbnail: None,
+            warnings: None,
         }
     }
 
@@ -434,6 +497,25 @@ impl EdgeStyle {
     }
 }
 
+/// The number of the collapsed edges with the same relation type.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct RelationTypeCount {
+    pub relation_type: String,
+    pub count: usize,
+}
+
+/// The summary of the parallel edges which are collapsed into one aggregate edge.
+///
+/// * `num_edges` - The number of the collapsed edges.
+/// * `relation_type_counts` - The number of the collapsed edges per relation type.
+/// * `mean_score` - The mean score of the collapsed edges. The representative score (the max score) is in the `data.score` field of the aggregate edge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct EdgeAggregation {
+    pub num_edges: usize,
+    pub relation_type_counts: Vec<RelationTypeCount>,
+    pub mean_score: f64,
+}
+
 /// The Edge struct is used to store the edge information. The frontend will use these information.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
 pub struct EdgeData {
@@ -447,6 +529,25 @@ pub struct EdgeData {
     pub resource: String,
     pub pmids: String,
     pub dataset: String,
+    // The provenance of the identical relations which are imported from multiple datasets, only available when the edges are deduplicated.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub datasets: Option<Vec<String>>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub resources: Option<Vec<String>>,
+    // The qualifiers of the relation, such as dose, tissue and direction of effect.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub qualifiers: Option<Vec<RelationQualifier>>,
+    // The score before the degree penalty or the reranking, only available when the predictions are penalized by the degrees of the nodes or reranked by the query context. The score field is the final score.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub raw_score: Option<f64>,
+    // The percentile (0-100) of the model score among the sampled scores of the model, only available for the predicted edges of a calibrated model. The raw scores of the different models aren't comparable, but the percentiles are.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub percentile: Option<f64>,
     // In future, we can add more fields here after we add additional fields for the Relation struct
 }
 
@@ -467,6 +568,11 @@ impl EdgeData {
                 .clone()
                 .unwrap_or(DEFAULT_DATASET_NAME.to_string()),
             pmids: relation.pmids.clone().unwrap_or("".to_string()),
+            datasets: relation.datasets.clone(),
+            resources: relation.resources.clone(),
+            qualifiers: None,
+            raw_score: None,
+            percentile: None,
         }
     }
 
@@ -486,6 +592,11 @@ impl EdgeData {
             resource: relation.get::<String>("resource").unwrap_or_default(),
             dataset: relation.get::<String>("dataset").unwrap_or_default(),
             pmids: relation.get::<String>("pmids").unwrap_or_default(),
+            datasets: None,
+            resources: None,
+            qualifiers: None,
+            raw_score: None,
+            percentile: None,
         }
     }
 }
@@ -499,6 +610,7 @@ impl EdgeData {
 /// * `reltype` - The relation type of the edge. Such as "Inhibitor::Gene:Gene".
 /// * `style` - The style of the edge. It contains the label and the keyshape. More details can be found in the [`EdgeStyle`](struct.EdgeStyle.html) struct.
 /// * `data` - The data of the edge. It contains the relation information. Its fields are the same as the [`Relation`](struct.Relation.html) struct.
+/// * `aggregation` - Only for the aggregate edge which collapses the parallel edges between the same node pair. More details can be found in the [`EdgeAggregation`](struct.EdgeAggregation.html) struct.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
 pub struct Edge {
     pub relid: String,
@@ -508,6 +620,9 @@ pub struct Edge {
     pub reltype: String,
     pub style: EdgeStyle,
     pub data: EdgeData,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub aggregation: Option<EdgeAggregation>,
 }
 
 impl Edge {
@@ -540,7 +655,13 @@ impl Edge {
                 resource: "".to_string(),
                 dataset: DEFAULT_DATASET_NAME.to_string(),
                 pmids: "".to_string(),
+                datasets: None,
+                resources: None,
+                qualifiers: None,
+                raw_score: None,
+                percentile: None,
             },
+            aggregation: None,
         }
     }
 
@@ -557,6 +678,7 @@ impl Edge {
             reltype: edge.relation_type.clone(),
             style: EdgeStyle::new(&edge.relation_type),
             data: edge.clone(),
+            aggregation: None,
         }
     }
 
@@ -574,6 +696,7 @@ impl Edge {
             reltype: relation.relation_type.clone(),
             style: EdgeStyle::new(&relation.relation_type),
             data: EdgeData::new(relation),
+            aggregation: None,
         }
     }
 
@@ -590,15 +713,318 @@ impl Edge {
             reltype: knowledge.relation_type.clone(),
             style: EdgeStyle::new(&knowledge.relation_type),
             data: EdgeData::new(&knowledge.to_relation()),
+            aggregation: None,
         }
     }
 }
 
+/// The direction of a prediction, the relation types don't need to be inverted manually for the reverse queries.
+///
+/// * `tail` - Predict the tails of (h, r, ?), the query nodes are the heads of the relation type.
+/// * `head` - Predict the heads of (?, r, t), the query nodes are the tails of the relation type.
+///
+/// The direction is inferred from the types of the query nodes if it's not set, the query nodes are treated as the tails when both sides of the relation type have the same type.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PredictionDirection {
+    Tail,
+    Head,
+}
+
+/// The max number of the (node, relation type) pairs in a batch prediction.
+// The max number of the warnings of a candidate compound, the warnings against the query nodes come first.
+pub const MAX_SAFETY_WARNINGS_PER_NODE: i64 = 10;
+
+/// A known adverse event or contraindication of a candidate compound, the edge is the supporting relation in the knowledge graph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct SafetyWarning {
+    // The group of the relation type, such as contraindication or adverse_event.
+    pub group: String,
+    // Whether the other node of the edge is a query node, such as a contraindication against the disease which the compounds are ranked for.
+    pub on_query_node: bool,
+    pub edge: EdgeData,
+}
+
+/// Get the group of the safety warnings which the relation type belongs to, such as contraindication for `DrugBank::contraindicated_for::Compound:Disease`.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::graph::get_safety_warning_group;
+///
+/// assert_eq!(get_safety_warning_group("Hetionet::CcSE::Compound:SideEffect"), Some("adverse_event"));
+/// assert_eq!(get_safety_warning_group("DrugBank::Contraindicated for::Compound:Disease"), Some("contraindication"));
+/// assert_eq!(get_safety_warning_group("DRUGBANK::treats::Compound:Disease"), None);
+/// ```
+pub fn get_safety_warning_group(relation_type: &str) -> Option<&'static str> {
+    let predicate =
+        normalize_safety_predicate(relation_type.split(COMPOSED_ENTITY_DELIMITER).nth(1)?);
+    SAFETY_WARNING_GROUPS
+        .iter()
+        .find(|(_, predicates)| predicates.contains(&predicate.as_str()))
+        .map(|(group, _)| *group)
+}
+
+// Keep it same with the normalization in Relation::fetch_by_predicates.
+fn normalize_safety_predicate(predicate: &str) -> String {
+    predicate
+        .to_lowercase()
+        .replace(|c: char| c == ' ' || c == '-', "_")
+}
+
+pub const MAX_BATCH_PREDICTION_PAIRS: usize = 500;
+
+/// A query node and the relation type to predict the linked nodes, it's an item of a batch prediction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct PredictionPair {
+    // The composed id of the query node, such as Compound::MESH:C000601183.
+    pub node_id: String,
+    // Such as biomedgps::treats::Compound:Disease.
+    pub relation_type: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub direction: Option<PredictionDirection>,
+}
+
+/// The pairs to predict in one request, the topk nodes are predicted for each pair.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct BatchPredictionRequest {
+    pub pairs: Vec<PredictionPair>,
+    #[oai(skip_serializing_if_is_none)]
+    pub topk: Option<u64>,
+    // The model used to predict the nodes, the default model is used if it's not set.
+    #[oai(skip_serializing_if_is_none)]
+    pub model_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct PredictedScore {
+    pub node_id: String,
+    pub score: f64,
+    // The percentile (0-100) of the score among the sampled scores of the model, it's None if the model is not calibrated.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub percentile: Option<f64>,
+}
+
+/// The predicted nodes of a pair, they are ranked by the scores. The error is set if the pair is invalid, the other pairs are still predicted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct BatchPrediction {
+    pub node_id: String,
+    pub relation_type: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub direction: Option<PredictionDirection>,
+    pub predictions: Vec<PredictedScore>,
+    #[oai(skip_serializing_if_is_none)]
+    pub error: Option<String>,
+}
+
+impl BatchPrediction {
+    /// Predict the topk target nodes of every (node, relation type) pair by one batched score query. The invalid pairs are reported in the error fields instead of failing the whole batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `pairs` - The query nodes and the relation types, such as [("Compound::MESH:C000601183", "biomedgps::treats::Compound:Disease")].
+    /// * `topk` - The number of the target nodes to be fetched for each pair. default is 10.
+    /// * `model_table_name` - The model used to predict the nodes, the default model is used if it's not set.
+    ///
+    pub async fn fetch(
+        pool: &sqlx::PgPool,
+        pairs: &Vec<PredictionPair>,
+        topk: Option<u64>,
+        model_table_name: Option<String>,
+    ) -> Result<Vec<BatchPrediction>, ValidationError> {
+        let model_or_table_name = model_table_name.unwrap_or(DEFAULT_MODEL_NAME.to_string());
+        let topk = topk.unwrap_or(10);
+        let embedding_metadata = match get_embedding_metadata(&model_or_table_name) {
+            Some(metadata) => metadata,
+            None => {
+                let err_msg = format!(
+                    "Failed to get the embedding metadata of the model/table name {}, please check the model/table name you provided.",
+                    model_or_table_name
+                );
+                error!("{}", &err_msg);
+                return Err(ValidationError::new(&err_msg, vec![]));
+            }
+        };
+
+        let mut predictions = pairs
+            .iter()
+            .map(|pair| BatchPrediction {
+                node_id: pair.node_id.clone(),
+                relation_type: pair.relation_type.clone(),
+                direction: pair.direction,
+                predictions: vec![],
+                error: None,
+            })
+            .collect::<Vec<BatchPrediction>>();
+
+        // The ids and the relation types are formatted into the sql, so they must be validated first.
+        let mut pair_sqls: Vec<(usize, String)> = vec![];
+        for (index, pair) in pairs.iter().enumerate() {
+            if !COMPOSED_ENTITY_REGEX.is_match(&pair.node_id) {
+                predictions[index].error = Some(format!(
+                    "Invalid node id {}, it must be composed of entity type, ::, and entity id. e.g. Disease::MESH:D001",
+                    pair.node_id
+                ));
+                continue;
+            }
+
+            if !RELATION_TYPE_REGEX.is_match(&pair.relation_type) {
+                predictions[index].error = Some(format!(
+                    "Invalid relation type {}, it must be like biomedgps::treats::Compound:Disease",
+                    pair.relation_type
+                ));
+                continue;
+            }
+
+            let (entity_type, entity_id) = Node::parse_id(&pair.node_id);
+            match Graph::format_score_sql(
+                &entity_id,
+                &entity_type,
+                &pair.relation_type,
+                &embedding_metadata,
+                topk,
+                DEFAULT_KGE_GAMMA,
+                pair.direction,
+            ) {
+                Ok(sql_str) => pair_sqls.push((index, sql_str)),
+                Err(err) => predictions[index].error = Some(err.to_string()),
+            }
+        }
+
+        if pair_sqls.is_empty() {
+            return Ok(predictions);
+        }
+
+        let sql_str = union_pair_score_sqls(&pair_sqls);
+        debug!(
+            "The batched score sql of {} pairs: {}",
+            pair_sqls.len(),
+            sql_str
+        );
+        let rows = match sqlx::query_as::<_, (i32, String, String, Option<f32>)>(&sql_str)
+            .fetch_all(pool)
+            .await
+        {
+            Ok(rows) => rows,
+            Err(err) => {
+                let err_msg = format!("Failed to fetch the batched predictions: {}", err);
+                error!("{}", &err_msg);
+                return Err(ValidationError::new(&err_msg, vec![]));
+            }
+        };
+
+        // The rows are ordered by the pairs and then by the scores.
+        for (index, _, node_id, score) in rows {
+            if let Some(score) = score {
+                predictions[index as usize]
+                    .predictions
+                    .push(PredictedScore {
+                        node_id,
+                        score: score as f64,
+                        percentile: embedding_metadata.get_score_percentile(score as f64),
+                    });
+            }
+        }
+
+        Ok(predictions)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
 struct TargetNode {
     query_node_id: String,
     node_id: String,
     score: Option<f32>, // The score is the distance between the nodes and the relation type
+    // The score before the degree penalty and the reranking, it's only set when one of them is applied.
+    #[sqlx(default)]
+    raw_score: Option<f32>,
+    // The percentile of the model score, see EmbeddingMetadata::get_score_percentile.
+    #[sqlx(default)]
+    percentile: Option<f64>,
+}
+
+/// The max alpha of the degree penalty.
+pub const MAX_DEGREE_PENALTY: f64 = 10.0;
+
+/// How many times of the topk nodes are predicted before the degree penalty is applied, so the nodes which are ranked lower by the raw scores can be promoted.
+pub const DEGREE_PENALTY_OVERSAMPLING: u64 = 5;
+
+/// Penalize the score of a predicted node by its degree, the raw scores of the KGE models favor the hub nodes which have many relations. The penalty is `alpha * ln(1 + degree)`, so it's the same for all scores no matter whether they are distances or probabilities, and `alpha = 0` keeps the raw score.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::graph::penalize_score_by_degree;
+///
+/// assert_eq!(penalize_score_by_degree(0.9, 0, 0.1), 0.9);
+/// assert!(penalize_score_by_degree(0.9, 1000, 0.1) < penalize_score_by_degree(0.9, 10, 0.1));
+/// ```
+pub fn penalize_score_by_degree(score: f64, degree: i64, alpha: f64) -> f64 {
+    score - alpha * (1.0 + degree.max(0) as f64).ln()
+}
+
+/// Combine the score sqls of the pairs into one query, every row is tagged with the index of its pair, so the predictions of all pairs are fetched in one round-trip. The rows are ordered by the pairs and then by the scores.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::graph::union_pair_score_sqls;
+///
+/// let sql_str = union_pair_score_sqls(&vec![(0, "SELECT 1 LIMIT 10;".to_string()), (2, "SELECT 2".to_string())]);
+/// assert!(sql_str.starts_with("SELECT 0 AS pair_index, pair_0.query_node_id, pair_0.node_id, pair_0.score::FLOAT4 AS score FROM (SELECT 1 LIMIT 10) AS pair_0 UNION ALL SELECT 2 AS pair_index"));
+/// assert!(sql_str.ends_with("FROM (SELECT 2) AS pair_2 ORDER BY pair_index ASC, score DESC, node_id ASC"));
+/// ```
+pub fn union_pair_score_sqls(pair_sqls: &Vec<(usize, String)>) -> String {
+    let union_sql = pair_sqls
+        .iter()
+        .map(|(index, sql_str)| {
+            format!(
+                "SELECT {index} AS pair_index, pair_{index}.query_node_id, pair_{index}.node_id, pair_{index}.score::FLOAT4 AS score FROM ({sql_str}) AS pair_{index}",
+                index = index,
+                sql_str = sql_str.trim().trim_end_matches(';').trim()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" UNION ALL ");
+
+    format!(
+        "{} ORDER BY pair_index ASC, score DESC, node_id ASC",
+        union_sql
+    )
+}
+
+// TODO: We need to allow the user to set the score function, gamma and exp_enabled
+/// The gamma of the score functions of the KGE models.
+pub const DEFAULT_KGE_GAMMA: f64 = 12.0;
+
+/// How many candidates are predicted by the KGE model before they are reranked by the query context.
+pub const RERANK_NUM_CANDIDATES: u64 = 200;
+
+/// The weight of the normalized KGE score when the predicted nodes are reranked, the rest is the weight of the similarity between the entity description and the query context.
+pub const RERANK_KGE_WEIGHT: f64 = 0.5;
+
+/// Scale the scores to [0, 1] by the min and the max of them, so the KGE scores can be combined with the cosine similarities. All scores are 1.0 if they are the same.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::graph::normalize_scores;
+///
+/// assert_eq!(normalize_scores(&vec![2.0, 4.0, 3.0]), vec![0.0, 1.0, 0.5]);
+/// assert_eq!(normalize_scores(&vec![3.0, 3.0]), vec![1.0, 1.0]);
+/// ```
+pub fn normalize_scores(scores: &Vec<f64>) -> Vec<f64> {
+    let min = scores.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    scores
+        .iter()
+        .map(|score| {
+            if max > min {
+                (score - min) / (max - min)
+            } else {
+                1.0
+            }
+        })
+        .collect()
 }
 
 impl TargetNode {
@@ -612,6 +1038,9 @@ impl TargetNode {
     /// * `relation_type` - The relation type of the nodes. It is the combination of the source type and the target type. Such as "STRING::BINDING::Gene:Gene".
     /// * `query` - The query to filter the nodes. It is a compose query. More details on the compose query can be found in the [`ComposeQuery`](struct.ComposeQuery.html) struct.
     /// * `topk` - The number of the target nodes to be fetched. default is 10.
+    /// * `degree_penalty` - The alpha of the degree penalty, the target nodes are reranked by the penalized scores if it's set. More details can be found in the [`penalize_score_by_degree`] function.
+    /// * `direction` - Predict the tails or the heads of the relation type, it's inferred from the node types if it's not set.
+    /// * `rerank_context` - The query context, such as a question or a phenotype. If it's set, the top [`RERANK_NUM_CANDIDATES`] nodes are reranked by the similarity between their descriptions and the context.
     ///
     /// # Returns
     ///
@@ -624,6 +1053,9 @@ impl TargetNode {
         query: &Option<ComposeQuery>,
         topk: Option<u64>,
         model_table_name: Option<String>,
+        degree_penalty: Option<f64>,
+        direction: Option<PredictionDirection>,
+        rerank_context: Option<&str>,
     ) -> Result<Vec<Self>, ValidationError> {
         let model_or_table_name = match model_table_name {
             Some(name) => name,
@@ -648,6 +1080,20 @@ impl TargetNode {
         let entity_id = entity_ids.join(",");
         let entity_type = entity_types.join(",");
 
+        // More nodes are predicted for the degree penalty and the reranking, they are reranked and truncated to the topk later.
+        let final_topk = topk;
+        let rerank_context = rerank_context
+            .map(|context| context.trim())
+            .filter(|context| !context.is_empty());
+        let topk = match degree_penalty {
+            Some(alpha) if alpha > 0.0 => topk * DEGREE_PENALTY_OVERSAMPLING,
+            _ => topk,
+        };
+        let topk = match rerank_context {
+            Some(_) => topk.max(RERANK_NUM_CANDIDATES),
+            None => topk,
+        };
+
         let embedding_metadata = match get_embedding_metadata(&model_or_table_name) {
             Some(metadata) => metadata,
             None => {
@@ -659,15 +1105,14 @@ impl TargetNode {
             }
         };
 
-        // TODO: We need to allow the user to set the score function, gamma and exp_enabled
-        let gamma = 12.0;
         let sql_str = match Graph::format_score_sql(
             &entity_id,
             &entity_type,
             relation_type,
             &embedding_metadata,
             topk,
-            gamma,
+            DEFAULT_KGE_GAMMA,
+            direction,
         ) {
             Ok(sql_str) => sql_str,
             Err(err) => {
@@ -699,9 +1144,36 @@ impl TargetNode {
                     );
                     error!("{}", &err_msg);
                     return Err(ValidationError::new(&err_msg, vec![]));
-                } else {
-                    return Ok(filtered_nodes);
                 }
+
+                let penalized_nodes = match degree_penalty {
+                    Some(alpha) if alpha > 0.0 => {
+                        // All candidates are kept for the reranking.
+                        let penalty_topk = match rerank_context {
+                            Some(_) => filtered_nodes.len() as u64,
+                            None => final_topk,
+                        };
+                        Self::apply_degree_penalty(pool, filtered_nodes, alpha, penalty_topk)
+                            .await?
+                    }
+                    _ => filtered_nodes,
+                };
+
+                let mut nodes = match rerank_context {
+                    Some(context) => {
+                        Self::rerank_by_context(pool, penalized_nodes, context, final_topk).await?
+                    }
+                    None => penalized_nodes,
+                };
+
+                // The percentiles are computed from the model scores, the penalized or reranked scores aren't comparable across the models.
+                for node in nodes.iter_mut() {
+                    let model_score = node.raw_score.or(node.score);
+                    node.percentile = model_score
+                        .and_then(|score| embedding_metadata.get_score_percentile(score as f64));
+                }
+
+                Ok(nodes)
             }
             Err(err) => {
                 let err_msg = format!("Failed to fetch similarity nodes from database: {}", err);
@@ -710,6 +1182,590 @@ impl TargetNode {
             }
         }
     }
+
+    /// Penalize the scores of the target nodes by their degrees, then rerank them and keep the topk nodes. The raw scores are kept in the raw_score field.
+    async fn apply_degree_penalty(
+        pool: &sqlx::PgPool,
+        nodes: Vec<Self>,
+        alpha: f64,
+        topk: u64,
+    ) -> Result<Vec<Self>, ValidationError> {
+        let node_ids = nodes
+            .iter()
+            .map(|node| node.node_id.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        let degrees = match Relation::count_degrees(pool, &node_ids).await {
+            Ok(degrees) => degrees,
+            Err(err) => {
+                let err_msg = format!("Failed to count the degrees of the nodes: {}", err);
+                error!("{}", &err_msg);
+                return Err(ValidationError::new(&err_msg, vec![]));
+            }
+        };
+
+        let mut nodes = nodes
+            .into_iter()
+            .map(|mut node| {
+                let raw_score = node.score.unwrap_or_default();
+                let degree = degrees.get(&node.node_id).cloned().unwrap_or(0);
+                node.raw_score = Some(raw_score);
+                node.score = Some(penalize_score_by_degree(raw_score as f64, degree, alpha) as f32);
+                node
+            })
+            .collect::<Vec<Self>>();
+        nodes.sort_by(|a, b| {
+            b.score
+                .unwrap_or_default()
+                .total_cmp(&a.score.unwrap_or_default())
+                .then_with(|| a.node_id.cmp(&b.node_id))
+        });
+        nodes.truncate(topk as usize);
+
+        Ok(nodes)
+    }
+
+    /// Cache the text embeddings of the entity descriptions which are not computed yet. The name is embedded if the description is empty.
+    async fn cache_description_embeddings(
+        pool: &sqlx::PgPool,
+        entity_types: &Vec<String>,
+        entity_ids: &Vec<String>,
+    ) -> Result<(), sqlx::Error> {
+        let sql_str = "
+            INSERT INTO biomedgps_entity_description_embedding (entity_id, entity_type, model, embedding)
+            SELECT e.id, e.label, $3, pgml.embed($3, COALESCE(NULLIF(e.description, ''), e.name))::REAL[]::vector
+            FROM biomedgps_entity e
+            JOIN UNNEST($1::TEXT[], $2::TEXT[]) AS c(entity_type, entity_id)
+              ON e.label = c.entity_type AND e.id = c.entity_id
+            WHERE NOT EXISTS (
+                SELECT 1 FROM biomedgps_entity_description_embedding d
+                WHERE d.entity_id = e.id AND d.entity_type = e.label AND d.model = $3
+            )
+            ON CONFLICT (entity_id, entity_type, model) DO NOTHING
+        ";
+
+        sqlx::query(sql_str)
+            .bind(entity_types)
+            .bind(entity_ids)
+            .bind(DEFAULT_TEXT_EMBEDDING_MODEL)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Rerank the target nodes by combining the normalized scores with the similarities between the descriptions of the nodes and the context, then keep the topk nodes. The scores before the reranking are kept in the raw_score field if they are not set by the degree penalty.
+    async fn rerank_by_context(
+        pool: &sqlx::PgPool,
+        nodes: Vec<Self>,
+        context: &str,
+        topk: u64,
+    ) -> Result<Vec<Self>, ValidationError> {
+        let mut entity_types: Vec<String> = vec![];
+        let mut entity_ids: Vec<String> = vec![];
+        for node in &nodes {
+            let (entity_type, entity_id) = Node::parse_id(&node.node_id);
+            entity_types.push(entity_type);
+            entity_ids.push(entity_id);
+        }
+
+        if let Err(err) = Self::cache_description_embeddings(pool, &entity_types, &entity_ids).await
+        {
+            let err_msg = format!(
+                "Failed to compute the embeddings of the descriptions: {}",
+                err
+            );
+            error!("{}", &err_msg);
+            return Err(ValidationError::new(&err_msg, vec![]));
+        }
+
+        let sql_str = "
+            WITH context AS (SELECT pgml.embed($3, $4)::REAL[] AS embedding)
+            SELECT d.entity_type || '::' || d.entity_id AS node_id,
+                   pgml.cosine_similarity(d.embedding::REAL[], context.embedding)::FLOAT8 AS context_score
+            FROM biomedgps_entity_description_embedding d
+            JOIN UNNEST($1::TEXT[], $2::TEXT[]) AS c(entity_type, entity_id)
+              ON d.entity_type = c.entity_type AND d.entity_id = c.entity_id
+            CROSS JOIN context
+            WHERE d.model = $3
+        ";
+        let context_scores = match sqlx::query_as::<_, (String, f64)>(sql_str)
+            .bind(&entity_types)
+            .bind(&entity_ids)
+            .bind(DEFAULT_TEXT_EMBEDDING_MODEL)
+            .bind(context)
+            .fetch_all(pool)
+            .await
+        {
+            Ok(rows) => rows.into_iter().collect::<HashMap<String, f64>>(),
+            Err(err) => {
+                let err_msg = format!(
+                    "Failed to compare the descriptions with the context: {}",
+                    err
+                );
+                error!("{}", &err_msg);
+                return Err(ValidationError::new(&err_msg, vec![]));
+            }
+        };
+
+        let kge_scores = normalize_scores(
+            &nodes
+                .iter()
+                .map(|node| node.score.unwrap_or_default() as f64)
+                .collect(),
+        );
+        let mut nodes = nodes
+            .into_iter()
+            .zip(kge_scores)
+            .map(|(mut node, kge_score)| {
+                // The nodes without descriptions or names are not comparable with the context.
+                let context_score = context_scores.get(&node.node_id).cloned().unwrap_or(0.0);
+                if node.raw_score.is_none() {
+                    node.raw_score = node.score;
+                }
+                node.score = Some(
+                    (RERANK_KGE_WEIGHT * kge_score + (1.0 - RERANK_KGE_WEIGHT) * context_score)
+                        as f32,
+                );
+                node
+            })
+            .collect::<Vec<Self>>();
+        nodes.sort_by(|a, b| {
+            b.score
+                .unwrap_or_default()
+                .total_cmp(&a.score.unwrap_or_default())
+                .then_with(|| a.node_id.cmp(&b.node_id))
+        });
+        nodes.truncate(topk as usize);
+
+        Ok(nodes)
+    }
+}
+
+/// A candidate node which extends a subgraph, it's predicted from several nodes (anchors) of the subgraph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct ExtensionCandidate {
+    pub node_id: String,
+    // The anchors and their scores are in the same order.
+    pub anchors: Vec<String>,
+    pub scores: Vec<f64>,
+    // The mean of the scores of the anchors.
+    pub score: f64,
+}
+
+/// The ranked candidates which extend a subgraph and the graph of the candidates and their anchors.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct SubgraphExtension {
+    pub candidates: Vec<ExtensionCandidate>,
+    pub graph: Graph,
+}
+
+/// Rank the candidates by the number of their anchors and then by the aggregated score.
+///
+/// # Arguments
+///
+/// * `predictions` - The predictions like (anchor id, candidate id, score).
+/// * `excluded_node_ids` - The nodes which are already in the subgraph, they are not candidates.
+/// * `min_anchors` - A candidate must be predicted from at least `min_anchors` anchors.
+/// * `topk` - The number of the candidates to return.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use biomedgps::model::graph::rank_extension_candidates;
+///
+/// let predictions = vec![
+///     ("Gene::ENTREZ:1".to_string(), "Compound::MESH:D1".to_string(), 1.0),
+///     ("Gene::ENTREZ:2".to_string(), "Compound::MESH:D1".to_string(), 3.0),
+///     ("Gene::ENTREZ:1".to_string(), "Compound::MESH:D2".to_string(), 9.0),
+/// ];
+/// let candidates = rank_extension_candidates(&predictions, &HashSet::new(), 2, 10);
+/// assert_eq!(candidates.len(), 1);
+/// assert_eq!(candidates[0].node_id, "Compound::MESH:D1");
+/// assert_eq!(candidates[0].score, 2.0);
+/// ```
+pub fn rank_extension_candidates(
+    predictions: &Vec<(String, String, f64)>,
+    excluded_node_ids: &HashSet<String>,
+    min_anchors: usize,
+    topk: usize,
+) -> Vec<ExtensionCandidate> {
+    let mut candidates: Vec<ExtensionCandidate> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (anchor, node_id, score) in predictions {
+        if excluded_node_ids.contains(node_id) {
+            continue;
+        }
+
+        let i = *index.entry(node_id.clone()).or_insert_with(|| {
+            candidates.push(ExtensionCandidate {
+                node_id: node_id.clone(),
+                anchors: vec![],
+                scores: vec![],
+                score: 0.0,
+            });
+            candidates.len() - 1
+        });
+
+        let candidate = &mut candidates[i];
+        // The same anchor might be queried twice if the relation type connects two nodes of the same type.
+        if !candidate.anchors.contains(anchor) {
+            candidate.anchors.push(anchor.clone());
+            candidate.scores.push(*score);
+        }
+    }
+
+    let mut candidates = candidates
+        .into_iter()
+        .filter(|c| c.anchors.len() >= min_anchors)
+        .map(|mut c| {
+            c.score = c.scores.iter().sum::<f64>() / c.scores.len() as f64;
+            c
+        })
+        .collect::<Vec<ExtensionCandidate>>();
+
+    candidates.sort_by(|a, b| {
+        b.anchors
+            .len()
+            .cmp(&a.anchors.len())
+            .then(b.score.total_cmp(&a.score))
+            .then(a.node_id.cmp(&b.node_id))
+    });
+    candidates.truncate(topk);
+
+    candidates
+}
+
+/// A line in the NDJSON stream of a graph. Such as {"type": "node", "data": {...}}. The nodes are emitted before the edges which refer to them, so the frontend can render the graph progressively.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum GraphItem {
+    Node(Node),
+    Edge(Edge),
+    Error(String),
+}
+
+impl GraphItem {
+    pub fn to_line(&self) -> String {
+        match serde_json::to_string(self) {
+            Ok(line) => format!("{}\n", line),
+            Err(e) => format!(
+                "{}\n",
+                serde_json::json!({"type": "error", "data": e.to_string()})
+            ),
+        }
+    }
+}
+
+/// The max number of relations in a page of the streamed linked nodes.
+pub const MAX_STREAM_PAGE_SIZE: u64 = 5000;
+
+/// The max number of pages of the streamed linked nodes, it's also the default, so a stream never reads more than MAX_STREAM_PAGE_SIZE * MAX_STREAM_PAGES relations.
+pub const MAX_STREAM_PAGES: u64 = 200;
+
+/// The state of the stream of the linked nodes. The pages are read by the keyset pagination on (score, id), so a page doesn't get slower as the stream goes deeper like the OFFSET pagination.
+struct LinkedNodesCursor {
+    // The number of the emitted pages.
+    page: u64,
+    done: bool,
+    // The score table is resolved before the first page.
+    table_name: Option<String>,
+    // The (score, id) of the last relation of the previous page.
+    after: Option<(f64, i64)>,
+    seen_nodes: HashSet<String>,
+    seen_edges: HashSet<String>,
+}
+
+/// Stream the linked nodes page by page as NDJSON lines, the relations are ordered by their scores. Only one page is kept in memory at a time, the seen node ids and edge ids are used to skip the duplicated nodes and edges across the pages.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool
+/// * `query` - The query to filter the relations, same as the `fetch_linked_nodes` function.
+/// * `page_size` - The number of relations in each page, it's capped by MAX_STREAM_PAGE_SIZE.
+/// * `max_pages` - The maximum number of pages, it's capped by MAX_STREAM_PAGES.
+/// * `model_table_prefix` - The table prefix of the model which computes the scores.
+/// * `username` - The user who reads the graph, the tags which are visible to the user are attached to the nodes.
+/// * `projects` - The projects of the user.
+///
+/// # Returns
+///
+/// * `impl Stream<Item = Result<bytes::Bytes, std::io::Error>>` - The NDJSON stream which can be used as the body of a response.
+///
+pub fn stream_linked_nodes(
+    pool: std::sync::Arc<sqlx::PgPool>,
+    query: Option<ComposeQuery>,
+    page_size: u64,
+    max_pages: u64,
+    model_table_prefix: String,
+    username: String,
+    projects: Vec<i32>,
+) -> impl futures::Stream<Item = Result<bytes::Bytes, std::io::Error>> {
+    let page_size = page_size.clamp(1, MAX_STREAM_PAGE_SIZE);
+    let max_pages = max_pages.clamp(1, MAX_STREAM_PAGES);
+    let query_str = match &query {
+        Some(ComposeQuery::QueryItem(item)) => item.format(),
+        Some(ComposeQuery::ComposeQueryItem(item)) => item.format(),
+        None => "".to_string(),
+    };
+    let query_str = if query_str.is_empty() {
+        "1=1".to_string()
+    } else {
+        query_str
+    };
+
+    let cursor = LinkedNodesCursor {
+        page: 0,
+        done: false,
+        table_name: None,
+        after: None,
+        seen_nodes: HashSet::new(),
+        seen_edges: HashSet::new(),
+    };
+    futures::stream::unfold(cursor, move |mut cursor| {
+        let pool = pool.clone();
+        let query_str = query_str.clone();
+        let model_table_prefix = model_table_prefix.clone();
+        let username = username.clone();
+        let projects = projects.clone();
+        async move {
+            if cursor.done || cursor.page >= max_pages {
+                return None;
+            }
+
+            // An error is emitted as the last line, so the client can tell it from the end of the graph.
+            let error_line = |e: String, mut cursor: LinkedNodesCursor| {
+                cursor.done = true;
+                let line = GraphItem::Error(e).to_line();
+                Some((Ok::<_, std::io::Error>(bytes::Bytes::from(line)), cursor))
+            };
+
+            let table_name = match cursor.table_name.clone() {
+                Some(table_name) => table_name,
+                None => match check_kg_score_table(&pool, &model_table_prefix).await {
+                    Ok(table_name) => {
+                        cursor.table_name = Some(table_name.clone());
+                        table_name
+                    }
+                    Err(e) => return error_line(e.to_string(), cursor),
+                },
+            };
+
+            // The relations without a score are sorted last, same as the score DESC order of the other endpoints.
+            let sql_str = format!(
+                "SELECT * FROM {} WHERE ({}) AND ($1::float8 IS NULL OR (COALESCE(score, '-Infinity'::float8), id) < ($1, $2)) ORDER BY COALESCE(score, '-Infinity'::float8) DESC, id DESC LIMIT $3",
+                table_name, query_str
+            );
+            let records = match sqlx::query_as::<_, Relation>(&sql_str)
+                .bind(cursor.after.map(|(score, _)| score))
+                .bind(cursor.after.map(|(_, id)| id))
+                .bind(page_size as i64)
+                .fetch_all(pool.as_ref())
+                .await
+            {
+                // An empty page means there is no more relations.
+                Ok(records) if records.is_empty() => return None,
+                Ok(records) => records,
+                Err(e) => return error_line(e.to_string(), cursor),
+            };
+
+            if let Some(last) = records.last() {
+                cursor.after = Some((last.score.unwrap_or(f64::NEG_INFINITY), last.id));
+            }
+            cursor.done = (records.len() as u64) < page_size;
+            cursor.page += 1;
+
+            let mut graph = Graph::new();
+            for record in records.iter() {
+                graph.add_edge(Edge::from_relation(record));
+            }
+
+            let node_ids = graph.get_node_ids_from_edges();
+            let node_ids_str = node_ids.iter().map(|id| id.as_str()).collect();
+            match graph.fetch_nodes_from_db(&pool, &node_ids_str).await {
+                Ok(nodes) => {
+                    for node in nodes {
+                        graph.add_node(node);
+                    }
+                }
+                Err(e) => return error_line(e.to_string(), cursor),
+            };
+            graph.attach_node_tags(&pool, &username, &projects).await;
+
+            let lines = graph.to_ndjson(&mut cursor.seen_nodes, &mut cursor.seen_edges);
+            Some((Ok(bytes::Bytes::from(lines)), cursor))
+        }
+    })
+}
+
+/// The max number of the cached layouts, the oldest one is evicted when it's full.
+pub const MAX_CACHED_LAYOUTS: usize = 64;
+
+lazy_static! {
+    // The key is the subgraph id, the value is the fingerprint of the laid out graph and the positions of its nodes.
+    static ref LAYOUT_CACHE: Mutex<Vec<(String, u64, Vec<NodePosition>)>> = Mutex::new(vec![]);
+}
+
+/// The position of a node which is computed by the server-side layout.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct NodePosition {
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// The local structure metrics of a node, the UI can map them to the size or opacity of the node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct NodeMetrics {
+    pub id: String,
+    // The number of distinct neighbors, the directions of the edges are ignored.
+    pub degree: usize,
+    pub clustering_coefficient: f64,
+    pub core_number: usize,
+}
+
+/// A graph which needs to be laid out by the server, such as a large subgraph which freezes the browser.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct GraphLayoutRequest {
+    pub graph: Graph,
+    // The number of iterations of the force-directed layout, more iterations make a more stable layout.
+    pub iterations: Option<usize>,
+    // The layout is cached by the subgraph id, it's reused until the nodes or edges of the subgraph are changed.
+    pub subgraph_id: Option<String>,
+}
+
+/// How the scores of the edges are composed into the score of a path.
+///
+/// * `product` - Multiply the edge scores, so a weak edge makes the whole path weak.
+/// * `sum` - Sum the edge scores, the longer paths get higher scores.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PathScoreMethod {
+    Product,
+    Sum,
+}
+
+impl Default for PathScoreMethod {
+    fn default() -> Self {
+        PathScoreMethod::Product
+    }
+}
+
+impl PathScoreMethod {
+    /// Compose the scores of the edges of a path.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::graph::PathScoreMethod;
+    ///
+    /// assert_eq!(PathScoreMethod::Product.compose(&vec![0.5, 0.4]), 0.2);
+    /// assert_eq!(PathScoreMethod::Sum.compose(&vec![0.5, 0.25]), 0.75);
+    /// ```
+    pub fn compose(&self, scores: &Vec<f64>) -> f64 {
+        match self {
+            PathScoreMethod::Product => scores.iter().product(),
+            PathScoreMethod::Sum => scores.iter().sum(),
+        }
+    }
+}
+
+/// The max number of the scored paths which can be requested.
+pub const MAX_SCORED_PATHS: usize = 500;
+
+/// A path between the start node and the end node which is scored by the KGE model.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct ScoredPath {
+    // The ids of the nodes from the start node to the end node, such as `Compound::DrugBank:DB00818`.
+    pub nodes: Vec<String>,
+    // The relids of the edges in the same order.
+    pub edges: Vec<String>,
+    // It's empty if the model can't score an edge of the path, such as an entity without the embedding. These paths are ranked after the scored ones.
+    #[oai(skip_serializing_if_is_none)]
+    pub score: Option<f64>,
+}
+
+/// The backend which serves a graph endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Enum)]
+#[oai(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum GraphBackend {
+    Postgres,
+    Neo4j,
+}
+
+/// The execution metadata of a graph endpoint, it's only attached when `include_stats=true` for debugging the slow graph loads.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct GraphStats {
+    pub backend: GraphBackend,
+    // The time spent in the endpoint, including the annotations of the nodes and edges, in milliseconds.
+    pub total_ms: f64,
+    // The time spent in the main query of the backend, in milliseconds.
+    pub query_ms: f64,
+    // The nodes and edges returned by the backend, before they are deduplicated and aggregated.
+    pub rows_fetched: i64,
+    pub num_nodes: i64,
+    pub num_edges: i64,
+    // Whether the result is served by the memoized queries, it's empty if the endpoint isn't memoized.
+    #[oai(skip_serializing_if_is_none)]
+    pub cache_hit: Option<bool>,
+}
+
+/// Measure the execution of a graph endpoint, the stats are only attached to the graph if they are requested.
+pub struct GraphStatsRecorder {
+    enabled: bool,
+    backend: GraphBackend,
+    started_at: Instant,
+    query_duration: Duration,
+    rows_fetched: usize,
+    cache_hit: Option<bool>,
+}
+
+impl GraphStatsRecorder {
+    /// Start measuring a graph endpoint.
+    ///
+    /// # Arguments
+    /// * `include_stats` - The `include_stats` query param, nothing is attached if it isn't true.
+    /// * `backend` - The backend which serves the endpoint.
+    pub fn new(include_stats: Option<bool>, backend: GraphBackend) -> Self {
+        GraphStatsRecorder {
+            enabled: include_stats.unwrap_or(false),
+            backend,
+            started_at: Instant::now(),
+            query_duration: Duration::ZERO,
+            rows_fetched: 0,
+            cache_hit: None,
+        }
+    }
+
+    /// Record a query of the backend which was started at `query_started_at` and returned `rows` nodes and edges.
+    pub fn record_query(&mut self, query_started_at: Instant, rows: usize) {
+        self.query_duration += query_started_at.elapsed();
+        self.rows_fetched += rows;
+    }
+
+    pub fn record_cache_hit(&mut self, cache_hit: Option<bool>) {
+        self.cache_hit = cache_hit;
+    }
+
+    /// Attach the stats to the graph if they are requested, it should be called after the graph is fully annotated.
+    pub fn attach(&self, graph: &mut Graph) {
+        if !self.enabled {
+            return;
+        }
+
+        graph.stats = Some(GraphStats {
+            backend: self.backend,
+            total_ms: self.started_at.elapsed().as_secs_f64() * 1000.0,
+            query_ms: self.query_duration.as_secs_f64() * 1000.0,
+            rows_fetched: self.rows_fetched as i64,
+            num_nodes: graph.nodes.len() as i64,
+            num_edges: graph.edges.len() as i64,
+            cache_hit: self.cache_hit,
+        });
+    }
 }
 
 /// The graph struct, which contains the nodes and edges
@@ -717,6 +1773,14 @@ impl TargetNode {
 pub struct Graph {
     nodes: Vec<Node>,
     edges: Vec<Edge>,
+    // The execution metadata of the endpoint, it's only set when `include_stats=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[oai(skip_serializing_if_is_none)]
+    stats: Option<GraphStats>,
+    // The ranked paths between two nodes, it's only set when the paths are scored by a KGE model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[oai(skip_serializing_if_is_none)]
+    paths: Option<Vec<ScoredPath>>,
 }
 
 impl Graph {
@@ -730,9 +1794,16 @@ impl Graph {
         Graph {
             nodes: vec![],
             edges: vec![],
+            stats: None,
+            paths: None,
         }
     }
 
+    /// The number of the nodes and edges in the graph, they might be duplicated before the graph is built by `get_graph`.
+    pub fn count_items(&self) -> usize {
+        self.nodes.len() + self.edges.len()
+    }
+
     ///
     pub fn from_data(nodes: Vec<&NodeData>, edges: Vec<&EdgeData>) -> Self {
         let mut graph = Graph::new();
@@ -761,13 +1832,203 @@ impl Graph {
     /// * `Result<Graph, ValidationError>` - The graph or the error
     ///
     /// NOTE: If you don't care about the duplicated or missed nodes and edges, you can just call the `graph.to_owned()` method to get the graph.
-    pub fn get_graph(&mut self, strict_mode: Option<bool>) -> Result<Graph, ValidationError> {
+    pub fn get_graph(
+        &mut self,
+        strict_mode: Option<bool>,
+        aggregate_edges: Option<bool>,
+        dedupe: Option<bool>,
+    ) -> Result<Graph, ValidationError> {
+        // The get_edges function keeps only one of the identical edges, so we need to merge their provenance before that.
+        if dedupe.unwrap_or(false) {
+            self.dedupe_edges();
+        }
+
         match self.get_edges(strict_mode) {
-            Ok(_) => Ok(self.to_owned()),
+            Ok(_) => {
+                if aggregate_edges.unwrap_or(false) {
+                    self.aggregate_edges();
+                }
+
+                Ok(self.to_owned())
+            }
             Err(err) => Err(err),
         }
     }
 
+    /// Collapse the identical edges which have the same source, relation type and target but come from different datasets. The edge with the max score is kept as the representative, and the datasets, resources and pmids of all the identical edges are merged into it.
+    pub fn dedupe_edges(&mut self) -> &Self {
+        let mut groups: Vec<Vec<Edge>> = vec![];
+        let mut group_indexes: HashMap<String, usize> = HashMap::new();
+        for edge in self.edges.drain(..) {
+            match group_indexes.get(&edge.relid) {
+                Some(index) => groups[*index].push(edge),
+                None => {
+                    group_indexes.insert(edge.relid.clone(), groups.len());
+                    groups.push(vec![edge]);
+                }
+            }
+        }
+
+        for group in groups {
+            let mut datasets: Vec<String> = vec![];
+            let mut resources: Vec<String> = vec![];
+            let mut pmids: Vec<String> = vec![];
+            for edge in group.iter() {
+                datasets.extend(
+                    edge.data
+                        .datasets
+                        .clone()
+                        .unwrap_or(vec![edge.data.dataset.clone()]),
+                );
+                resources.extend(
+                    edge.data
+                        .resources
+                        .clone()
+                        .unwrap_or(vec![edge.data.resource.clone()]),
+                );
+                pmids.extend(
+                    edge.data
+                        .pmids
+                        .split("|")
+                        .filter(|pmid| !pmid.is_empty())
+                        .map(|pmid| pmid.to_string()),
+                );
+            }
+
+            for values in [&mut datasets, &mut resources, &mut pmids] {
+                values.retain(|value| !value.is_empty());
+                values.sort();
+                values.dedup();
+            }
+
+            let mut edge = group
+                .into_iter()
+                .max_by(|a, b| {
+                    a.data
+                        .score
+                        .partial_cmp(&b.data.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap();
+            edge.data.datasets = Some(datasets);
+            edge.data.resources = Some(resources);
+            edge.data.pmids = pmids.join("|");
+            self.edges.push(edge);
+        }
+
+        self
+    }
+
+    /// Collapse the parallel edges between the same node pair (regardless of the direction) into a single aggregate edge for simplifying the dense graphs. The aggregate edge keeps the data of the edge with the max score as the representative, and the counts per relation type are in the `aggregation` field. The edges which have no parallel edges are kept as they are.
+    pub fn aggregate_edges(&mut self) -> &Self {
+        let mut groups: Vec<Vec<Edge>> = vec![];
+        let mut group_indexes: HashMap<(String, String), usize> = HashMap::new();
+        for edge in self.edges.drain(..) {
+            let key = if edge.source <= edge.target {
+                (edge.source.clone(), edge.target.clone())
+            } else {
+                (edge.target.clone(), edge.source.clone())
+            };
+
+            match group_indexes.get(&key) {
+                Some(index) => groups[*index].push(edge),
+                None => {
+                    group_indexes.insert(key, groups.len());
+                    groups.push(vec![edge]);
+                }
+            }
+        }
+
+        for group in groups {
+            if group.len() == 1 {
+                self.edges.extend(group);
+                continue;
+            }
+
+            let mut relation_type_counts: Vec<RelationTypeCount> = vec![];
+            for edge in group.iter() {
+                match relation_type_counts
+                    .iter_mut()
+                    .find(|c| c.relation_type == edge.reltype)
+                {
+                    Some(count) => count.count += 1,
+                    None => relation_type_counts.push(RelationTypeCount {
+                        relation_type: edge.reltype.clone(),
+                        count: 1,
+                    }),
+                }
+            }
+            relation_type_counts.sort_by(|a, b| {
+                b.count
+                    .cmp(&a.count)
+                    .then(a.relation_type.cmp(&b.relation_type))
+            });
+
+            let mean_score =
+                group.iter().map(|edge| edge.data.score).sum::<f64>() / group.len() as f64;
+            let mut aggregate_edge = group
+                .iter()
+                .max_by(|a, b| {
+                    a.data
+                        .score
+                        .partial_cmp(&b.data.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap()
+                .clone();
+
+            aggregate_edge.relid = format!(
+                "{}-{}-{}",
+                aggregate_edge.source, AGGREGATE_EDGE_TYPE, aggregate_edge.target
+            );
+            aggregate_edge.style.label.value = format!(
+                "{} (+{})",
+                aggregate_edge.style.label.value,
+                group.len() - 1
+            );
+            aggregate_edge.aggregation = Some(EdgeAggregation {
+                num_edges: group.len(),
+                relation_type_counts,
+                mean_score,
+            });
+            self.edges.push(aggregate_edge);
+        }
+
+        self
+    }
+
+    /// Convert the graph to NDJSON lines, the nodes are emitted before the edges. The nodes and edges which are in the seen sets will be skipped, and the new ones will be added into the seen sets.
+    ///
+    /// # Arguments
+    ///
+    /// * `seen_node_ids` - The ids of the nodes which have been emitted
+    /// * `seen_edge_ids` - The relids of the edges which have been emitted
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The NDJSON lines, it is empty if there is no edges and nodes in the graph.
+    ///
+    pub fn to_ndjson(
+        &mut self,
+        seen_node_ids: &mut HashSet<String>,
+        seen_edge_ids: &mut HashSet<String>,
+    ) -> String {
+        let mut lines = String::new();
+        for node in self.get_nodes() {
+            if seen_node_ids.insert(node.id.clone()) {
+                lines.push_str(&GraphItem::Node(node.clone()).to_line());
+            }
+        }
+
+        for edge in self.edges.iter() {
+            if seen_edge_ids.insert(edge.relid.clone()) {
+                lines.push_str(&GraphItem::Edge(edge.clone()).to_line());
+            }
+        }
+
+        lines
+    }
+
     /// Get the nodes in the graph
     ///
     /// # Returns
@@ -781,6 +2042,204 @@ impl Graph {
         &self.nodes
     }
 
+    /// Attach the tags which are visible to the user to the nodes. The graph is kept as it is if the tags can't be fetched, because the tags are only annotations.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    /// * `username` - The user who reads the graph
+    /// * `projects` - The projects of the user, the tags shared with these projects are also attached
+    ///
+    pub async fn attach_node_tags(
+        &mut self,
+        pool: &sqlx::PgPool,
+        username: &str,
+        projects: &Vec<i32>,
+    ) -> &Self {
+        if self.nodes.is_empty() {
+            return self;
+        }
+
+        let node_ids = self
+            .nodes
+            .iter()
+            .map(|n| n.id.clone())
+            .collect::<Vec<String>>();
+        let tags = match NodeTag::fetch_visible(pool, username, projects, Some(&node_ids)).await {
+            Ok(tags) => tags,
+            Err(e) => {
+                error!("Failed to fetch the node tags: {}", e);
+                return self;
+            }
+        };
+
+        let mut tags_by_node: HashMap<String, Vec<NodeTag>> = HashMap::new();
+        for tag in tags {
+            tags_by_node
+                .entry(tag.node_id.clone())
+                .or_default()
+                .push(tag);
+        }
+
+        for node in self.nodes.iter_mut() {
+            node.tags = tags_by_node.get(&node.id).cloned();
+        }
+
+        self
+    }
+
+    /// Attach the urls of the cached images to the nodes, the nodes without a cached image are not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    ///
+    pub async fn attach_thumbnails(&mut self, pool: &sqlx::PgPool) -> &Self {
+        if self.nodes.is_empty() {
+            return self;
+        }
+
+        let node_ids = self
+            .nodes
+            .iter()
+            .map(|n| n.id.clone())
+            .collect::<Vec<String>>();
+        let images = match EntityImage::fetch_by_node_ids(pool, &node_ids).await {
+            Ok(images) => images,
+            Err(e) => {
+                error!("Failed to fetch the images of the nodes: {}", e);
+                return self;
+            }
+        };
+
+        let thumbnails = images
+            .iter()
+            .map(|image| {
+                (
+                    Node::format_id(&image.entity_type, &image.entity_id),
+                    get_thumbnail_url(&image.entity_type, &image.entity_id),
+                )
+            })
+            .collect::<HashMap<String, String>>();
+
+        for node in self.nodes.iter_mut() {
+            if let Some(url) = thumbnails.get(&node.id) {
+                node.thumbnail = Some(url.clone());
+            }
+        }
+
+        self
+    }
+
+    /// Attach the qualifiers of the relations to the edges, such as dose and tissue. The qualifiers of the identical relations from multiple datasets are all attached, they can be distinguished by the relation_id field.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    ///
+    pub async fn attach_edge_qualifiers(&mut self, pool: &sqlx::PgPool) -> &Self {
+        if self.edges.is_empty() {
+            return self;
+        }
+
+        let node_ids = self
+            .edges
+            .iter()
+            .flat_map(|e| vec![e.source.clone(), e.target.clone()])
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        let qualifiers = match RelationQualifier::fetch_by_node_ids(pool, &node_ids).await {
+            Ok(qualifiers) => qualifiers,
+            Err(e) => {
+                error!("Failed to fetch the relation qualifiers: {}", e);
+                return self;
+            }
+        };
+
+        for edge in self.edges.iter_mut() {
+            let edge_key = format!("{}-{}-{}", edge.source, edge.reltype, edge.target);
+            edge.data.qualifiers = qualifiers.get(&edge_key).cloned();
+        }
+
+        self
+    }
+
+    /// Flag the candidate compounds which have known adverse events or contraindications in the knowledge graph, the supporting edges are attached to the warnings of the nodes. The query nodes are not flagged, but the warnings against them come first, such as a contraindication against the disease which the compounds are ranked for.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    /// * `query_node_ids` - The composed ids of the query nodes, such as ["Disease::MESH:D001755"]
+    ///
+    pub async fn attach_safety_warnings(
+        &mut self,
+        pool: &sqlx::PgPool,
+        query_node_ids: &Vec<&str>,
+    ) -> &Self {
+        let node_ids = self
+            .nodes
+            .iter()
+            .filter(|n| n.nlabel == "Compound" && !query_node_ids.contains(&n.id.as_str()))
+            .map(|n| n.id.clone())
+            .collect::<Vec<String>>();
+        if node_ids.is_empty() {
+            return self;
+        }
+
+        let predicates = SAFETY_WARNING_GROUPS
+            .iter()
+            .flat_map(|(_, predicates)| predicates.iter().map(|p| p.to_string()))
+            .collect::<Vec<String>>();
+        let query_node_ids = query_node_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>();
+        let relations = match Relation::fetch_by_predicates(
+            pool,
+            &node_ids,
+            &predicates,
+            &query_node_ids,
+            MAX_SAFETY_WARNINGS_PER_NODE,
+        )
+        .await
+        {
+            Ok(relations) => relations,
+            Err(e) => {
+                error!("Failed to fetch the safety warnings of the nodes: {}", e);
+                return self;
+            }
+        };
+
+        let mut warnings: HashMap<String, Vec<SafetyWarning>> = HashMap::new();
+        for relation in relations {
+            let group = match get_safety_warning_group(&relation.relation_type) {
+                Some(group) => group,
+                None => continue,
+            };
+            let source_id = Node::format_id(&relation.source_type, &relation.source_id);
+            let target_id = Node::format_id(&relation.target_type, &relation.target_id);
+            let (node_id, other_id) = if node_ids.contains(&source_id) {
+                (source_id, target_id)
+            } else {
+                (target_id, source_id)
+            };
+            warnings.entry(node_id).or_default().push(SafetyWarning {
+                group: group.to_string(),
+                on_query_node: query_node_ids.contains(&other_id),
+                edge: EdgeData::new(&relation),
+            });
+        }
+
+        for node in self.nodes.iter_mut() {
+            if node_ids.contains(&node.id) {
+                node.warnings = Some(warnings.remove(&node.id).unwrap_or_default());
+            }
+        }
+
+        self
+    }
+
     /// Get the edges in the graph and check if the related nodes are in the graph if the strict_mode is true. It will return the missed nodes here instead of fetching the missed nodes in the get_nodes function.
     ///
     /// # Arguments
@@ -882,15 +2341,294 @@ impl Graph {
         self.edges.push(edge);
     }
 
-    /// Remove the edges by node id
-    /// It will remove the edges which contain the node id as the source or target node id.
-    pub fn remove_edges_by_node_id(&mut self, node_id: &str) {
+    /// Remove the edges by node id
+    /// It will remove the edges which contain the node id as the source or target node id.
+    pub fn remove_edges_by_node_id(&mut self, node_id: &str) {
+        self.edges = self
+            .edges
+            .iter()
+            .filter(|edge| edge.source != node_id && edge.target != node_id)
+            .map(|edge| edge.to_owned())
+            .collect::<Vec<Edge>>();
+    }
+
+    /// Compute the positions of the nodes by the force-directed layout, the edges whose nodes are not in the graph are ignored.
+    ///
+    /// # Arguments
+    /// * `iterations` - The number of iterations of the layout.
+    /// * `subgraph_id` - The layout is cached by the subgraph id if it's set, the cached layout is used if the nodes, edges and iterations are unchanged.
+    ///
+    /// # Returns
+    /// * `Vec<NodePosition>` - The positions of the nodes, in the same order as the nodes.
+    pub fn compute_layout(
+        &self,
+        iterations: usize,
+        subgraph_id: Option<&str>,
+    ) -> Vec<NodePosition> {
+        let mut hasher = DefaultHasher::new();
+        self.nodes.iter().for_each(|node| node.id.hash(&mut hasher));
+        self.edges.iter().for_each(|edge| {
+            edge.source.hash(&mut hasher);
+            edge.target.hash(&mut hasher);
+        });
+        iterations.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+
+        if let Some(subgraph_id) = subgraph_id {
+            let cache = LAYOUT_CACHE.lock().unwrap();
+            let cached = cache.iter().find(|(id, cached_fingerprint, _)| {
+                id == subgraph_id && *cached_fingerprint == fingerprint
+            });
+            if let Some((_, _, positions)) = cached {
+                debug!("Use the cached layout of the subgraph {}.", subgraph_id);
+                return positions.clone();
+            }
+        }
+
+        let edges = self.get_index_edges();
+        let positions =
+            force_directed_layout(self.nodes.len(), &edges, iterations, DEFAULT_EDGE_LENGTH)
+                .into_iter()
+                .zip(self.nodes.iter())
+                .map(|((x, y), node)| NodePosition {
+                    id: node.id.clone(),
+                    x,
+                    y,
+                })
+                .collect::<Vec<NodePosition>>();
+
+        if let Some(subgraph_id) = subgraph_id {
+            let mut cache = LAYOUT_CACHE.lock().unwrap();
+            cache.retain(|(id, _, _)| id != subgraph_id);
+            if cache.len() >= MAX_CACHED_LAYOUTS {
+                cache.remove(0);
+            }
+            cache.push((subgraph_id.to_string(), fingerprint, positions.clone()));
+        }
+
+        positions
+    }
+
+    /// Compute the clustering coefficient and the core number of each node, the graph is treated as undirected and the edges whose nodes are not in the graph are ignored.
+    ///
+    /// # Returns
+    /// * `Vec<NodeMetrics>` - The metrics of the nodes, in the same order as the nodes.
+    pub fn compute_node_metrics(&self) -> Vec<NodeMetrics> {
+        let edges = self.get_index_edges();
+        let coefficients = local_clustering_coefficients(self.nodes.len(), &edges);
+        let cores = core_numbers(self.nodes.len(), &edges);
+
+        let mut neighbors = vec![HashSet::new(); self.nodes.len()];
+        for &(source, target) in edges.iter().filter(|(s, t)| s != t) {
+            neighbors[source].insert(target);
+            neighbors[target].insert(source);
+        }
+
+        self.nodes
+            .iter()
+            .zip(neighbors.iter())
+            .zip(coefficients.into_iter().zip(cores.into_iter()))
+            .map(|((node, node_neighbors), (coefficient, core))| NodeMetrics {
+                id: node.id.clone(),
+                degree: node_neighbors.len(),
+                clustering_coefficient: coefficient,
+                core_number: core,
+            })
+            .collect()
+    }
+
+    /// Score the paths between two nodes by a KGE model and keep the topk paths. The score of an edge is computed from the embeddings of its triple, and the scores of the edges are composed into the score of the path. Only the nodes and edges of the topk paths are kept, the scores of the edges are replaced by the model scores and the ranked paths are attached to the graph.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `start_node_id` - The start node id, such as `Compound::DrugBank:DB00818`.
+    /// * `end_node_id` - The end node id, such as `Disease::MONDO:0005404`.
+    /// * `nhops` - The max number of edges in a path.
+    /// * `topk` - The number of the paths to be kept.
+    /// * `model_table_name` - The model used to score the edges, the default model is used if it's not set.
+    /// * `method` - How the scores of the edges are composed.
+    ///
+    /// # Returns
+    /// * `Result<&Self, anyhow::Error>` - The graph with the topk paths.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn rank_paths(
+        &mut self,
+        pool: &sqlx::PgPool,
+        start_node_id: &str,
+        end_node_id: &str,
+        nhops: usize,
+        topk: usize,
+        model_table_name: Option<String>,
+        method: PathScoreMethod,
+    ) -> Result<&Self, anyhow::Error> {
+        let model_or_table_name = model_table_name.unwrap_or(DEFAULT_MODEL_NAME.to_string());
+        let embedding_metadata = match get_embedding_metadata(&model_or_table_name) {
+            Some(metadata) => metadata,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Failed to get the embedding metadata of the model/table name {}, please check the model/table name you provided.",
+                    model_or_table_name
+                ));
+            }
+        };
+
+        let indexes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect::<HashMap<&str, usize>>();
+        let (start, end) = match (indexes.get(start_node_id), indexes.get(end_node_id)) {
+            (Some(start), Some(end)) => (*start, *end),
+            _ => {
+                self.paths = Some(vec![]);
+                return Ok(self);
+            }
+        };
+
+        // The edges keep their indexes, the ones whose nodes are not in the graph are out of range and ignored.
+        let index_edges = self
+            .edges
+            .iter()
+            .map(|edge| {
+                match (
+                    indexes.get(edge.source.as_str()),
+                    indexes.get(edge.target.as_str()),
+                ) {
+                    (Some(source), Some(target)) => (*source, *target),
+                    _ => (usize::MAX, usize::MAX),
+                }
+            })
+            .collect::<Vec<(usize, usize)>>();
+        let paths = enumerate_paths(
+            self.nodes.len(),
+            &index_edges,
+            start,
+            end,
+            nhops,
+            DEFAULT_MAX_ENUMERATED_PATHS,
+        );
+        if paths.len() >= DEFAULT_MAX_ENUMERATED_PATHS {
+            debug!(
+                "Only the first {} paths between {} and {} are scored.",
+                DEFAULT_MAX_ENUMERATED_PATHS, start_node_id, end_node_id
+            );
+        }
+
+        // Each edge is scored once even if it's shared by many paths.
+        let used_edges = paths
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<HashSet<usize>>()
+            .into_iter()
+            .collect::<Vec<usize>>();
+        let triples = used_edges
+            .iter()
+            .map(|&i| {
+                let data = &self.edges[i].data;
+                (
+                    data.source_type.clone(),
+                    data.source_id.clone(),
+                    data.relation_type.clone(),
+                    data.target_type.clone(),
+                    data.target_id.clone(),
+                )
+            })
+            .collect::<Vec<(String, String, String, String, String)>>();
+        let scores = embedding_metadata
+            .score_triples(pool, &triples)
+            .await?
+            .into_iter()
+            .zip(used_edges.iter())
+            .filter_map(|(score, &i)| score.map(|score| (i, score)))
+            .collect::<HashMap<usize, f64>>();
+
+        let mut scored_paths = paths
+            .iter()
+            .map(|path| {
+                let edge_scores = path
+                    .iter()
+                    .map(|i| scores.get(i).copied())
+                    .collect::<Option<Vec<f64>>>();
+                (path, edge_scores.map(|edge_scores| method.compose(&edge_scores)))
+            })
+            .collect::<Vec<(&Vec<usize>, Option<f64>)>>();
+        // The scored paths come first, the unscored paths keep their order.
+        scored_paths.sort_by(|a, b| match (a.1, b.1) {
+            (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        scored_paths.truncate(topk);
+
+        let mut kept_nodes: HashSet<usize> = HashSet::new();
+        let mut kept_edges: HashSet<usize> = HashSet::new();
+        let mut ranked_paths = vec![];
+        for (path, score) in scored_paths {
+            let mut node = start;
+            let mut node_ids = vec![self.nodes[start].id.clone()];
+            kept_nodes.insert(start);
+            for &i in path {
+                let (source, target) = index_edges[i];
+                node = if source == node { target } else { source };
+                node_ids.push(self.nodes[node].id.clone());
+                kept_nodes.insert(node);
+                kept_edges.insert(i);
+            }
+
+            ranked_paths.push(ScoredPath {
+                nodes: node_ids,
+                edges: path.iter().map(|&i| self.edges[i].relid.clone()).collect(),
+                score,
+            });
+        }
+
+        for (&i, &score) in scores.iter() {
+            self.edges[i].data.score = score;
+            self.edges[i].data.percentile = embedding_metadata.get_score_percentile(score);
+        }
+
+        self.nodes = self
+            .nodes
+            .drain(..)
+            .enumerate()
+            .filter(|(i, _)| kept_nodes.contains(i))
+            .map(|(_, node)| node)
+            .collect();
         self.edges = self
             .edges
+            .drain(..)
+            .enumerate()
+            .filter(|(i, _)| kept_edges.contains(i))
+            .map(|(_, edge)| edge)
+            .collect();
+        self.paths = Some(ranked_paths);
+
+        Ok(self)
+    }
+
+    /// Convert the edges to the (source, target) indexes of the nodes, the edges whose nodes are not in the graph are ignored.
+    fn get_index_edges(&self) -> Vec<(usize, usize)> {
+        let indexes = self
+            .nodes
             .iter()
-            .filter(|edge| edge.source != node_id && edge.target != node_id)
-            .map(|edge| edge.to_owned())
-            .collect::<Vec<Edge>>();
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect::<HashMap<&str, usize>>();
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                match (
+                    indexes.get(edge.source.as_str()),
+                    indexes.get(edge.target.as_str()),
+                ) {
+                    (Some(source), Some(target)) => Some((*source, *target)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<(usize, usize)>>()
     }
 
     /// Get the node ids from the edges, it contains the source and target node ids
@@ -1057,6 +2795,7 @@ impl Graph {
     /// * `embedding_metadata` - The metadata of the embedding
     /// * `topk` - The number of the target nodes to be fetched
     /// * `gamma` - The gamma value for the score function
+    /// * `direction` - Predict the tails or the heads of the relation type, it's inferred from the source type if it's not set
     ///
     /// # Returns
     ///
@@ -1083,10 +2822,12 @@ impl Graph {
     ///    created_at: DateTime::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
     ///    datasets: vec!("STRING".to_string()),
     ///    description: "The entity embedding trained by the TransE_l2 model".to_string(),
+    ///    metric: "l2".to_string(),
+    ///    score_percentiles: None,
     /// };
     /// let topk = 10;
     /// let gamma = 12.0;
-    /// let sql_str = Graph::format_score_sql(source_id, source_type, relation_type, &embedding_metadata, topk, gamma).unwrap();
+    /// let sql_str = Graph::format_score_sql(source_id, source_type, relation_type, &embedding_metadata, topk, gamma, None).unwrap();
     /// let expected_sql_str = "
     /// SELECT
     ///     COALESCE(ee2.entity_type, '') || '::' || COALESCE(ee2.entity_id, '') AS node_id,
@@ -1127,6 +2868,7 @@ impl Graph {
         embedding_metadata: &EmbeddingMetadata,
         topk: u64,
         gamma: f64,
+        direction: Option<PredictionDirection>,
     ) -> Result<String, ValidationError> {
         let source_id = source_id.split(",").collect::<Vec<&str>>().join("', '");
         let source_type_vec = source_type
@@ -1163,13 +2905,32 @@ impl Graph {
             ));
         }
 
-        let reverse = if source_type == r_target_type {
-            true
-        } else {
-            false
+        // The source nodes are the tails of the relation type when the heads are predicted, so the score function is called in reverse.
+        let reverse = match direction {
+            Some(PredictionDirection::Tail) if r_source_type != source_type => {
+                return Err(ValidationError::new(
+                    &format!(
+                        "The source type {} is not the head of the relation type {}, so the tails can't be predicted",
+                        source_type, relation_type
+                    ),
+                    vec![source_type.to_string()],
+                ));
+            }
+            Some(PredictionDirection::Head) if r_target_type != source_type => {
+                return Err(ValidationError::new(
+                    &format!(
+                        "The source type {} is not the tail of the relation type {}, so the heads can't be predicted",
+                        source_type, relation_type
+                    ),
+                    vec![source_type.to_string()],
+                ));
+            }
+            Some(PredictionDirection::Tail) => false,
+            Some(PredictionDirection::Head) => true,
+            None => source_type == r_target_type,
         };
 
-        let target_type = if source_type == r_target_type {
+        let target_type = if reverse {
             r_source_type
         } else {
             r_target_type
@@ -1415,7 +3176,7 @@ impl Graph {
     ///         "Gene::ENTREZ:108715297",
     ///     ];
     ///
-    ///     graph.auto_connect_nodes(&pool, &node_ids).await.unwrap();
+    ///     graph.auto_connect_nodes(&pool, &node_ids, None, None).await.unwrap();
     ///
     ///     println!("graph: {:?}", graph);
     ///     assert_eq!(graph.get_nodes().len(), 3);
@@ -1427,6 +3188,8 @@ impl Graph {
     ///
     /// * `pool` - The database connection pool
     /// * `node_ids` - The node ids, like `["Compound::MESH:D0001", "Compound::MESH:D0002"]`
+    /// * `model_table_prefix` - The model name which is used to get the score of the relations
+    /// * `curated` - Which curated knowledges should be merged into the graph, None means only the public relations
     ///
     /// # Returns
     ///
@@ -1438,7 +3201,12 @@ impl Graph {
         pool: &sqlx::PgPool,
         node_ids: &Vec<&str>,
         model_table_prefix: Option<&str>,
+        curated: Option<&CuratedKnowledgeFilter>,
     ) -> Result<&Self, anyhow::Error> {
+        if let Some(prefix) = model_table_prefix {
+            check_kg_score_table(pool, prefix).await?;
+        }
+
         let query_str = Self::gen_relation_query_from_node_ids(node_ids, model_table_prefix);
 
         debug!("query_str: {}", query_str);
@@ -1459,6 +3227,16 @@ impl Graph {
             }
         };
 
+        if let Some(curated) = curated {
+            // Only the curated knowledges which connect two of the input nodes are needed.
+            if let Err(e) = self
+                .merge_curated_edges(pool, node_ids, curated, true)
+                .await
+            {
+                error_msg = format!("{}\n{}", error_msg, e);
+            }
+        };
+
         match self.fetch_nodes_from_db(pool, node_ids).await {
             Ok(nodes) => {
                 for node in nodes {
@@ -1542,6 +3320,10 @@ impl Graph {
     /// * `node_id` - The node id, like `Compound::MESH:D0001`
     /// * `query` - The query to filter the nodes
     /// * `topk` - The number of nodes to return
+    /// * `model_table_name` - The model used to predict the nodes
+    /// * `degree_penalty` - The alpha of the degree penalty, the raw scores are kept in the `raw_score` field of the edges if it's set
+    /// * `direction` - Predict the tails of (node, r, ?) or the heads of (?, r, node), it's inferred from the node type if it's not set
+    /// * `rerank_context` - The query context to rerank the predicted nodes by their descriptions, the raw scores are kept in the `raw_score` field of the edges if it's set
     ///
     /// # Returns
     ///
@@ -1565,7 +3347,7 @@ impl Graph {
     ///     let topk = Some(10);
     ///
     ///     // If you choose None as the model_table_name, it will use the default model/table name `DEFAULT_MODEL_NAME`.
-    ///     match graph.fetch_predicted_nodes(&pool, &node_id, &query, topk, None).await {
+    ///     match graph.fetch_predicted_nodes(&pool, &node_id, &query, topk, None, None).await {
     ///         Ok(graph) => {
     ///             println!("graph: {:?}", graph);
     ///         }
@@ -1582,6 +3364,9 @@ impl Graph {
         query: &Option<ComposeQuery>,
         topk: Option<u64>,
         model_table_name: Option<String>,
+        degree_penalty: Option<f64>,
+        direction: Option<PredictionDirection>,
+        rerank_context: Option<&str>,
     ) -> Result<&Self, ValidationError> {
         match TargetNode::fetch_target_nodes(
             pool,
@@ -1590,6 +3375,9 @@ impl Graph {
             query,
             topk,
             model_table_name,
+            degree_penalty,
+            direction,
+            rerank_context,
         )
         .await
         {
@@ -1604,7 +3392,7 @@ impl Graph {
                     node_ids.push(id);
                 }
 
-                // Convert predicted nodes to a hashmap which key is node id and value is distance.
+                // Convert predicted nodes to a hashmap which key is node id and value is distance, the raw distance before the degree penalty and the percentile of the model score.
                 let predicted_node_map = predicted_nodes
                     .iter()
                     .map(|predicted_node| {
@@ -1612,9 +3400,16 @@ impl Graph {
                             "{}-{}",
                             predicted_node.query_node_id, predicted_node.node_id
                         );
-                        (key, predicted_node.score.unwrap() as f64)
+                        (
+                            key,
+                            (
+                                predicted_node.score.unwrap() as f64,
+                                predicted_node.raw_score.map(|s| s as f64),
+                                predicted_node.percentile,
+                            ),
+                        )
                     })
-                    .collect::<HashMap<String, f64>>();
+                    .collect::<HashMap<String, (f64, Option<f64>, Option<f64>)>>();
 
                 // Allow to label the existing records with any relation type
                 let existing_records = match Relation::exist_records(
@@ -1652,7 +3447,7 @@ impl Graph {
                                 let key = format!("{}-{}", source_node.id, node.id);
                                 let distance = predicted_node_map.get(&key);
                                 match distance {
-                                    Some(&d) => {
+                                    Some(&(d, raw_d, percentile)) => {
                                         if node.id == source_node.id {
                                             continue;
                                         }
@@ -1673,7 +3468,8 @@ impl Graph {
                                             &first_node_id,
                                             &second_node_id,
                                         );
-                                        let edge = match existing_records.get(&ordered_key_str) {
+                                        let mut edge = match existing_records.get(&ordered_key_str)
+                                        {
                                             Some(record) => Edge::new(
                                                 &record.relation_type,
                                                 source_node.data.id.as_str(),
@@ -1691,6 +3487,8 @@ impl Graph {
                                                 Some(d),
                                             ),
                                         };
+                                        edge.data.raw_score = raw_d;
+                                        edge.data.percentile = percentile;
 
                                         edges.push(edge);
                                     }
@@ -1724,6 +3522,167 @@ impl Graph {
         }
     }
 
+    /// Predict the nodes which extend a subgraph. Each node of the subgraph which matches the relation type is used as an anchor, the candidates are predicted from each anchor and only the candidates which are predicted from at least `min_anchors` anchors are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    /// * `node_ids` - The ids of the nodes in the subgraph, like `Gene::ENTREZ:1`
+    /// * `relation_type` - The relation type used to predict the candidates, like `STRING::BINDING::Gene:Gene`
+    /// * `min_anchors` - The minimum number of the anchors of a candidate
+    /// * `topk_per_anchor` - The number of the nodes predicted from each anchor
+    /// * `topk` - The number of the candidates to return
+    /// * `model_table_name` - The model used to predict the candidates
+    /// * `degree_penalty` - The alpha of the degree penalty, the candidates are ranked by the penalized scores if it's set
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ExtensionCandidate>, ValidationError>` - The ranked candidates, the candidates and their anchors are added into the graph.
+    ///
+    pub async fn fetch_subgraph_extension(
+        &mut self,
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<String>,
+        relation_type: &str,
+        min_anchors: usize,
+        topk_per_anchor: u64,
+        topk: usize,
+        model_table_name: Option<String>,
+        degree_penalty: Option<f64>,
+    ) -> Result<Vec<ExtensionCandidate>, ValidationError> {
+        let (source_type, target_type) = Graph::parse_relation_type(relation_type)?;
+        let anchors = node_ids
+            .iter()
+            .filter(|id| {
+                let (label, _) = Node::parse_id(id);
+                label == source_type || label == target_type
+            })
+            .collect::<Vec<&String>>();
+
+        if anchors.len() < min_anchors {
+            return Err(ValidationError::new(
+                &format!(
+                    "The subgraph has {} nodes matched with the relation type {}, at least {} nodes are required.",
+                    anchors.len(),
+                    relation_type,
+                    min_anchors
+                ),
+                vec![],
+            ));
+        }
+
+        let mut predictions: Vec<(String, String, f64)> = vec![];
+        // The raw scores before the degree penalty, the key is the anchor and the candidate.
+        let mut raw_scores: HashMap<(String, String), f64> = HashMap::new();
+        // The percentiles of the model scores, the key is the anchor and the candidate.
+        let mut percentiles: HashMap<(String, String), f64> = HashMap::new();
+        for anchor in anchors {
+            match TargetNode::fetch_target_nodes(
+                pool,
+                anchor,
+                relation_type,
+                &None,
+                Some(topk_per_anchor),
+                model_table_name.clone(),
+                degree_penalty,
+                None,
+                None,
+            )
+            .await
+            {
+                Ok(nodes) => {
+                    for node in nodes {
+                        if let Some(raw_score) = node.raw_score {
+                            raw_scores.insert(
+                                (anchor.to_string(), node.node_id.clone()),
+                                raw_score as f64,
+                            );
+                        }
+                        if let Some(percentile) = node.percentile {
+                            percentiles
+                                .insert((anchor.to_string(), node.node_id.clone()), percentile);
+                        }
+                        predictions.push((
+                            anchor.clone(),
+                            node.node_id,
+                            node.score.unwrap() as f64,
+                        ));
+                    }
+                }
+                // Some nodes might not have embeddings, skip them and use the other anchors.
+                Err(e) => debug!("Skip the anchor {}: {}", anchor, e),
+            }
+        }
+
+        let excluded_node_ids = node_ids.iter().cloned().collect::<HashSet<String>>();
+        let candidates =
+            rank_extension_candidates(&predictions, &excluded_node_ids, min_anchors, topk);
+
+        let mut ids: Vec<&str> = vec![];
+        for candidate in &candidates {
+            ids.push(candidate.node_id.as_str());
+            for anchor in &candidate.anchors {
+                if !ids.contains(&anchor.as_str()) {
+                    ids.push(anchor.as_str());
+                }
+            }
+        }
+
+        if ids.is_empty() {
+            return Ok(candidates);
+        }
+
+        self.fetch_nodes_by_ids(pool, &ids).await?;
+        for candidate in &candidates {
+            let (target_type, target_id) = Node::parse_id(&candidate.node_id);
+            for (anchor, score) in candidate.anchors.iter().zip(candidate.scores.iter()) {
+                let (source_type, source_id) = Node::parse_id(anchor);
+                let mut edge = Edge::new(
+                    PREDICTED_EDGE_TYPE,
+                    &source_id,
+                    &source_type,
+                    &target_id,
+                    &target_type,
+                    Some(*score),
+                );
+                edge.data.raw_score = raw_scores
+                    .get(&(anchor.clone(), candidate.node_id.clone()))
+                    .cloned();
+                edge.data.percentile = percentiles
+                    .get(&(anchor.clone(), candidate.node_id.clone()))
+                    .cloned();
+                self.add_edge(edge);
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Merge the curated knowledges which are visible for the current user into the graph as edges.
+    /// The nodes are not fetched here, the caller should fetch them together with the nodes of the public relations.
+    async fn merge_curated_edges(
+        &mut self,
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<&str>,
+        curated: &CuratedKnowledgeFilter,
+        both_ends: bool,
+    ) -> Result<&Self, anyhow::Error> {
+        match KnowledgeCuration::fetch_by_node_ids(pool, node_ids, curated, both_ends).await {
+            Ok(records) => {
+                for record in records {
+                    let edge = Edge::from_curated_knowledge(&record);
+                    self.add_edge(edge);
+                }
+
+                Ok(self)
+            }
+            Err(e) => Err(anyhow::Error::msg(format!(
+                "Error in merge_curated_edges: {}",
+                e
+            ))),
+        }
+    }
+
     /// Fetch the curated knowledges and convert them to nodes and edges in the graph.
     pub async fn fetch_curated_knowledges(
         &mut self,
@@ -1809,6 +3768,48 @@ impl Graph {
         }
     }
 
+    /// Whether the query only selects the relations by the ids of the nodes, such as `source_id = 'X' OR target_id in ('Y', 'Z')`. The top-N relations of these nodes are complete for such queries, but the other filters (e.g. relation_type) might need the relations which are out of the top-N.
+    fn is_node_id_query(query: &ComposeQuery) -> bool {
+        match query {
+            ComposeQuery::QueryItem(item) => {
+                (item.field == "source_id" || item.field == "target_id")
+                    && (item.operator == "=" || item.operator == "in")
+            }
+            ComposeQuery::ComposeQueryItem(item) => {
+                (item.operator.to_lowercase() == "or" || item.items.len() == 1)
+                    && item.items.iter().all(|item| Self::is_node_id_query(item))
+            }
+        }
+    }
+
+    async fn can_use_top_relations(
+        pool: &sqlx::PgPool,
+        query: &Option<ComposeQuery>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+        order_by: &str,
+        model_table_prefix: &str,
+    ) -> bool {
+        if order_by.trim().to_lowercase() != "score desc" {
+            return false;
+        }
+
+        match query {
+            Some(query) if Self::is_node_id_query(query) => {}
+            _ => return false,
+        };
+
+        let top_n = match get_top_relations_size(pool, model_table_prefix).await {
+            Some(top_n) => top_n as u64,
+            None => return false,
+        };
+
+        // Keep the same defaults as the RecordResponse::get_records function.
+        let page_size = page_size.unwrap_or(10);
+        let page = page.unwrap_or(1).max(1);
+        page * page_size <= top_n
+    }
+
     /// Fetch the linked nodes with some relation types or other conditions, but only one step
     ///
     /// # Arguments
@@ -1817,6 +3818,9 @@ impl Graph {
     /// * `page` - The page number
     /// * `page_size` - The page size
     /// * `order_by` - The order by clause
+    /// * `model_table_prefix` - The table prefix of the model which computes the scores, None means the default model.
+    /// * `curated` - Which curated knowledges should be merged into the graph, None means only the public relations.
+    ///   The curated knowledges which connect to any node of the public relations will be merged.
     ///
     /// # Returns
     /// * `Ok(&Self)` - The graph
@@ -1838,7 +3842,7 @@ impl Graph {
     ///     let page_size = Some(10);
     ///     let order_by = None;
     ///
-    ///     match graph.fetch_linked_nodes(&pool, &query, page, page_size, order_by).await {
+    ///     match graph.fetch_linked_nodes(&pool, &query, page, page_size, order_by, None, None).await {
     ///         Ok(graph) => {
     ///             println!("graph: {:?}", graph);
     ///         }
@@ -1855,16 +3859,32 @@ impl Graph {
         page: Option<u64>,
         page_size: Option<u64>,
         order_by: Option<&str>,
+        model_table_prefix: Option<&str>,
+        curated: Option<&CuratedKnowledgeFilter>,
     ) -> Result<&Self, ValidationError> {
+        let model_table_prefix = model_table_prefix.unwrap_or(DEFAULT_MODEL_NAME);
         let table_name = if order_by.is_some() && order_by.unwrap().starts_with("score") {
-            // TODO: We need to add the model name to the query if we allow users to use different model.
-            // TODO: We need to ensure the table exists before we use it.
-            get_kg_score_table_name(DEFAULT_MODEL_NAME)
+            if Self::can_use_top_relations(
+                pool,
+                query,
+                page,
+                page_size,
+                order_by.unwrap(),
+                model_table_prefix,
+            )
+            .await
+            {
+                debug!("Use the top relations table as the fast path.");
+                get_top_relations_table_name(model_table_prefix)
+            } else {
+                check_kg_score_table(pool, model_table_prefix).await?
+            }
         } else {
             "biomedgps_relation".to_string()
         };
 
-        match RecordResponse::<Relation>::get_records(
+        // The graph has no total, so it's not counted. The top relations table can't tell the total anyway, because it only keeps the top-N relations of each node.
+        match RecordResponse::<Relation>::fetch_page(
             pool,
             table_name.as_str(),
             query,
@@ -1875,11 +3895,22 @@ impl Graph {
         .await
         {
             Ok(records) => {
-                for record in records.records {
+                for record in records {
                     let edge = Edge::from_relation(&record);
                     self.add_edge(edge);
                 }
 
+                if let Some(curated) = curated {
+                    let node_ids = self.get_node_ids_from_edges();
+                    let node_ids_str = node_ids.iter().map(|id| id.as_str()).collect();
+                    if let Err(e) = self
+                        .merge_curated_edges(pool, &node_ids_str, curated, false)
+                        .await
+                    {
+                        return Err(ValidationError::new(&e.to_string(), vec![]));
+                    }
+                }
+
                 // Fetch the nodes
                 let node_ids = self.get_node_ids_from_edges();
                 let node_ids_str = &node_ids.iter().map(|id| id.as_str()).collect();
@@ -1905,11 +3936,162 @@ impl Graph {
     }
 }
 
+/// One expansion operation which was used to build a subgraph. The fields are same as the query params of the related endpoint.
+///
+/// * `operation` - The endpoint which was called, such as `nodes`, `auto-connect-nodes`, `one-step-linked-nodes` and `predicted-nodes`.
+/// * `node_ids` - The seed nodes, such as `["Gene::ENTREZ:123"]`. It's required by the `nodes`, `auto-connect-nodes` and `predicted-nodes` operations.
+/// * `query_str` - The filters, it's a json string of the [`ComposeQuery`](struct.ComposeQuery.html).
+/// * `model` - The model used to compute the scores or predict the nodes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct ExpansionStep {
+    pub operation: String,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub node_ids: Option<Vec<String>>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub query_str: Option<String>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub relation_type: Option<String>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub topk: Option<u64>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub page: Option<u64>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub page_size: Option<u64>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub model: Option<String>,
+}
+
+/// The sequence of the expansion operations which were used to build a subgraph, it's stored in the recipe field of the subgraph.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct ExpansionRecipe {
+    pub steps: Vec<ExpansionStep>,
+}
+
+impl ExpansionRecipe {
+    pub fn from_json(recipe: &str) -> Result<Self, ValidationError> {
+        match serde_json::from_str::<ExpansionRecipe>(recipe) {
+            Ok(recipe) => Ok(recipe),
+            Err(e) => Err(ValidationError::new(
+                &format!("Failed to parse the recipe: {}", e),
+                vec![],
+            )),
+        }
+    }
+
+    fn parse_query(query_str: &Option<String>) -> Result<Option<ComposeQuery>, ValidationError> {
+        match query_str {
+            Some(query_str) if !query_str.is_empty() => {
+                match serde_json::from_str::<ComposeQuery>(query_str) {
+                    Ok(query) => Ok(Some(query)),
+                    Err(e) => Err(ValidationError::new(
+                        &format!("Failed to parse the query string: {}", e),
+                        vec![],
+                    )),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Re-execute the expansion operations against the current knowledge graph, the results of all steps are merged into one graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Graph)` - The updated graph
+    /// * `Err(ValidationError)` - The error message, it contains the index of the failed step
+    ///
+    pub async fn replay(&self, pool: &sqlx::PgPool) -> Result<Graph, ValidationError> {
+        let mut graph = Graph::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let node_ids = step.node_ids.clone().unwrap_or_default();
+            let node_ids = node_ids.iter().map(|id| id.as_str()).collect::<Vec<&str>>();
+            let query = Self::parse_query(&step.query_str)?;
+
+            let result = match step.operation.as_str() {
+                "nodes" => graph.fetch_nodes_by_ids(pool, &node_ids).await.map(|_| ()),
+                "auto-connect-nodes" => graph
+                    .auto_connect_nodes(
+                        pool,
+                        &node_ids,
+                        Some(step.model.as_deref().unwrap_or(DEFAULT_MODEL_NAME)),
+                        None,
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| ValidationError::new(&e.to_string(), vec![])),
+                "one-step-linked-nodes" => graph
+                    .fetch_linked_nodes(
+                        pool,
+                        &query,
+                        step.page,
+                        step.page_size,
+                        Some("score DESC"),
+                        Some(step.model.as_deref().unwrap_or(DEFAULT_MODEL_NAME)),
+                        None,
+                    )
+                    .await
+                    .map(|_| ()),
+                "predicted-nodes" => match &step.relation_type {
+                    Some(relation_type) => graph
+                        .fetch_predicted_nodes(
+                            pool,
+                            &node_ids.join(","),
+                            relation_type,
+                            &query,
+                            step.topk,
+                            step.model.clone(),
+                            None,
+                            None,
+                            None,
+                        )
+                        .await
+                        .map(|_| ()),
+                    None => Err(ValidationError::new(
+                        "The relation_type is required by the predicted-nodes operation.",
+                        vec![],
+                    )),
+                },
+                operation => Err(ValidationError::new(
+                    &format!("The operation {} cannot be replayed.", operation),
+                    vec![],
+                )),
+            };
+
+            if let Err(e) = result {
+                return Err(ValidationError::new(
+                    &format!(
+                        "Failed to replay the step {} ({}): {}",
+                        index, step.operation, e
+                    ),
+                    vec![],
+                ));
+            }
+        }
+
+        match graph.get_graph(None, None, None) {
+            Ok(graph) => Ok(graph),
+            Err(e) => Err(e),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate log;
     use super::*;
     use crate::{init_logger, setup_test_db};
+    use crate::testing::TestDatabase;
     use log::LevelFilter;
     use regex::Regex;
 
@@ -1963,6 +4145,229 @@ mod tests {
         assert_eq!(query_str, "".to_string());
     }
 
+    #[test]
+    fn test_dedupe_edges() {
+        let mut graph = Graph::new();
+        let mut edge = Edge::new(
+            "STRING::BINDING::Gene:Gene",
+            "ENTREZ:1",
+            "Gene",
+            "ENTREZ:2",
+            "Gene",
+            Some(0.5),
+        );
+        edge.data.dataset = "STRING".to_string();
+        edge.data.resource = "STRING".to_string();
+        edge.data.pmids = "123|456".to_string();
+        graph.add_edge(edge.clone());
+
+        edge.data.dataset = "DRKG".to_string();
+        edge.data.score = 0.9;
+        edge.data.pmids = "456".to_string();
+        graph.add_edge(edge);
+
+        let graph = graph.get_graph(None, None, Some(true)).unwrap();
+        assert_eq!(graph.edges.len(), 1);
+
+        let data = &graph.edges[0].data;
+        assert_eq!(data.score, 0.9);
+        assert_eq!(
+            data.datasets,
+            Some(vec!["DRKG".to_string(), "STRING".to_string()])
+        );
+        assert_eq!(data.resources, Some(vec!["STRING".to_string()]));
+        assert_eq!(data.pmids, "123|456");
+    }
+
+    #[test]
+    fn test_aggregate_edges() {
+        let _ = init_logger("biomedgps-test", LevelFilter::Debug);
+        let mut graph = Graph::new();
+        graph.add_edge(Edge::new(
+            "STRING::BINDING::Gene:Gene",
+            "ENTREZ:1",
+            "Gene",
+            "ENTREZ:2",
+            "Gene",
+            Some(0.5),
+        ));
+        graph.add_edge(Edge::new(
+            "STRING::REACTION::Gene:Gene",
+            "ENTREZ:2",
+            "Gene",
+            "ENTREZ:1",
+            "Gene",
+            Some(0.9),
+        ));
+        graph.add_edge(Edge::new(
+            "STRING::BINDING::Gene:Gene",
+            "ENTREZ:1",
+            "Gene",
+            "ENTREZ:3",
+            "Gene",
+            Some(0.1),
+        ));
+
+        let graph = graph.get_graph(None, Some(true), None).unwrap();
+        assert_eq!(graph.edges.len(), 2);
+
+        let aggregate_edge = graph
+            .edges
+            .iter()
+            .find(|e| e.aggregation.is_some())
+            .unwrap();
+        let aggregation = aggregate_edge.aggregation.as_ref().unwrap();
+        assert_eq!(aggregation.num_edges, 2);
+        assert_eq!(aggregation.relation_type_counts.len(), 2);
+        assert_eq!(aggregate_edge.data.score, 0.9);
+        assert!((aggregation.mean_score - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compute_node_metrics() {
+        // A triangle with a pendant node, the edge to an unknown node is ignored.
+        let mut graph = Graph::new();
+        for id in ["ENTREZ:1", "ENTREZ:2", "ENTREZ:3", "ENTREZ:4"] {
+            graph.add_node(Node::new(&Entity {
+                idx: 0,
+                id: id.to_string(),
+                name: id.to_string(),
+                label: "Gene".to_string(),
+                resource: "ENTREZ".to_string(),
+                description: None,
+                taxid: None,
+                synonyms: None,
+                pmids: None,
+                xrefs: None,
+            }));
+        }
+        for (source, target) in [
+            ("ENTREZ:1", "ENTREZ:2"),
+            ("ENTREZ:2", "ENTREZ:3"),
+            ("ENTREZ:3", "ENTREZ:1"),
+            ("ENTREZ:3", "ENTREZ:4"),
+            ("ENTREZ:4", "ENTREZ:5"),
+        ] {
+            graph.add_edge(Edge::new(
+                "STRING::BINDING::Gene:Gene",
+                source,
+                "Gene",
+                target,
+                "Gene",
+                None,
+            ));
+        }
+
+        let metrics = graph.compute_node_metrics();
+        assert_eq!(
+            metrics.iter().map(|m| m.id.as_str()).collect::<Vec<&str>>(),
+            vec![
+                "Gene::ENTREZ:1",
+                "Gene::ENTREZ:2",
+                "Gene::ENTREZ:3",
+                "Gene::ENTREZ:4"
+            ]
+        );
+        assert_eq!(
+            metrics.iter().map(|m| m.degree).collect::<Vec<usize>>(),
+            vec![2, 2, 3, 1]
+        );
+        assert_eq!(
+            metrics
+                .iter()
+                .map(|m| m.clustering_coefficient)
+                .collect::<Vec<f64>>(),
+            vec![1.0, 1.0, 1.0 / 3.0, 0.0]
+        );
+        assert_eq!(
+            metrics
+                .iter()
+                .map(|m| m.core_number)
+                .collect::<Vec<usize>>(),
+            vec![2, 2, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_is_node_id_query() {
+        let _ = init_logger("biomedgps-test", LevelFilter::Debug);
+        let query: ComposeQuery = serde_json::from_str(
+            r#"{"operator": "or", "items": [
+                {"field": "source_id", "operator": "=", "value": "ENTREZ:1"},
+                {"field": "target_id", "operator": "in", "value": ["ENTREZ:1", "ENTREZ:2"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(Graph::is_node_id_query(&query));
+
+        let query: ComposeQuery = serde_json::from_str(
+            r#"{"operator": "and", "items": [
+                {"field": "source_id", "operator": "=", "value": "ENTREZ:1"},
+                {"field": "relation_type", "operator": "=", "value": "STRING::BINDING::Gene:Gene"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(!Graph::is_node_id_query(&query));
+    }
+
+    #[tokio::test]
+    async fn test_attach_safety_warnings() {
+        let _ = init_logger("biomedgps-test", LevelFilter::Debug);
+        let db = TestDatabase::new().await.unwrap();
+        sqlx::query(
+            "INSERT INTO biomedgps_relation (relation_type, source_id, source_type, target_id, target_type, resource, key_sentence, pmids, dataset) VALUES
+            ('Hetionet::CcSE::Compound:SideEffect', 'DrugBank:DB00001', 'Compound', 'UMLS:C0018681', 'SideEffect', 'Hetionet', '', '', 'hetionet'),
+            ('DrugBank::contraindicated_for::Compound:Disease', 'DrugBank:DB00001', 'Compound', 'MESH:D001755', 'Disease', 'DrugBank', '', '', 'drugbank'),
+            ('DRUGBANK::treats::Compound:Disease', 'DrugBank:DB00002', 'Compound', 'MESH:D001755', 'Disease', 'DrugBank', '', '', 'drugbank')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let node = |label: &str, id: &str| NodeData {
+            identity: Node::format_id(label, id),
+            id: id.to_string(),
+            label: label.to_string(),
+            name: id.to_string(),
+            description: None,
+            resource: "DrugBank".to_string(),
+            xrefs: None,
+            pmids: None,
+            taxid: None,
+            synonyms: None,
+        };
+        let nodes = vec![
+            node("Disease", "MESH:D001755"),
+            node("Compound", "DrugBank:DB00001"),
+            node("Compound", "DrugBank:DB00002"),
+        ];
+        let mut graph = Graph::from_data(nodes.iter().collect(), vec![]);
+        graph
+            .attach_safety_warnings(&db.pool, &vec!["Disease::MESH:D001755"])
+            .await;
+
+        let warnings = |id: &str| {
+            graph
+                .nodes
+                .iter()
+                .find(|n| n.id == id)
+                .unwrap()
+                .warnings
+                .clone()
+        };
+        assert_eq!(warnings("Disease::MESH:D001755"), None);
+        assert_eq!(warnings("Compound::DrugBank:DB00002"), Some(vec![]));
+
+        let flagged = warnings("Compound::DrugBank:DB00001").unwrap();
+        assert_eq!(flagged.len(), 2);
+        assert_eq!(flagged[0].group, "contraindication");
+        assert!(flagged[0].on_query_node);
+        assert_eq!(flagged[1].group, "adverse_event");
+        assert_eq!(flagged[1].edge.target_id, "UMLS:C0018681");
+
+        db.cleanup().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_auto_connect_nodes() {
         let _ = init_logger("biomedgps-test", LevelFilter::Debug);
@@ -1977,7 +4382,7 @@ mod tests {
         ];
 
         graph
-            .auto_connect_nodes(&pool, &node_ids, None)
+            .auto_connect_nodes(&pool, &node_ids, None, None)
             .await
             .unwrap();
 
@@ -1986,6 +4391,50 @@ mod tests {
         assert_eq!(graph.edges.len(), 3);
     }
 
+    #[test]
+    fn test_format_score_sql_direction() {
+        let embedding_metadata = EmbeddingMetadata {
+            id: 1,
+            metadata: None,
+            model_name: "biomedgps_transe_l2".to_string(),
+            model_type: "TransE_l2".to_string(),
+            dimension: 400,
+            table_name: "biomedgps".to_string(),
+            created_at: chrono::Utc::now(),
+            datasets: vec!["DRKG".to_string()],
+            description: "The entity embedding trained by the TransE_l2 model".to_string(),
+            metric: "l2".to_string(),
+            score_percentiles: None,
+        };
+        let relation_type = "DRUGBANK::treats::Compound:Disease";
+        let re = Regex::new(r"\s+").unwrap();
+        let score_sql = |source_type: &str, direction: Option<PredictionDirection>| {
+            Graph::format_score_sql(
+                "MESH:D000001",
+                source_type,
+                relation_type,
+                &embedding_metadata,
+                10,
+                12.0,
+                direction,
+            )
+            .map(|sql_str| re.replace_all(&sql_str, " ").to_string())
+        };
+
+        let tail_sql = score_sql("Compound", Some(PredictionDirection::Tail)).unwrap();
+        assert!(tail_sql.contains("ee2.entity_type = 'Disease'"));
+        assert!(tail_sql.contains("12, true, false )"));
+        assert_eq!(score_sql("Compound", None).unwrap(), tail_sql);
+
+        let head_sql = score_sql("Disease", Some(PredictionDirection::Head)).unwrap();
+        assert!(head_sql.contains("ee2.entity_type = 'Compound'"));
+        assert!(head_sql.contains("12, true, true )"));
+        assert_eq!(score_sql("Disease", None).unwrap(), head_sql);
+
+        assert!(score_sql("Compound", Some(PredictionDirection::Head)).is_err());
+        assert!(score_sql("Disease", Some(PredictionDirection::Tail)).is_err());
+    }
+
     #[tokio::test]
     async fn test_fetch_predicted_nodes() {
         let _ = init_logger("biomedgps-test", LevelFilter::Debug);
@@ -2000,7 +4449,17 @@ mod tests {
         let topk = Some(10);
 
         match graph
-            .fetch_predicted_nodes(&pool, &node_id, &relation_type, &query, topk, None)
+            .fetch_predicted_nodes(
+                &pool,
+                &node_id,
+                &relation_type,
+                &query,
+                topk,
+                None,
+                None,
+                None,
+                None,
+            )
             .await
         {
             Ok(graph) => {
@@ -0,0 +1,299 @@
+//! This module is used to cache the images of the entities, such as the structures of the compounds and the snapshots of the protein structures. The images are fetched from the external services and stored in a directory, so the graph can be rendered with the thumbnails without calling the external services for each node.
+//!
+//! The images of the compounds are fetched from PubChem, ChEBI and ChEMBL by their ids. The images of other entities, such as the snapshots of the protein structures, can be imported from a file which contains the entity_id, entity_type and source_url columns.
+
+use crate::model::util::{get_delimiter, open_data_file};
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The directory which stores the images, it's a temporary directory if it's not set.
+pub const ENTITY_IMAGE_DIR_ENV: &str = "ENTITY_IMAGE_DIR";
+
+/// The images are served by the `/api/v1/entity-images/:entity_type/:entity_id` endpoint.
+pub const ENTITY_IMAGE_URL_PREFIX: &str = "/api/v1/entity-images";
+
+// Only the png images are cached, so the endpoint can serve them with the same content type.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const MAX_IMAGE_SIZE: usize = 2 * 1024 * 1024;
+
+lazy_static! {
+    // The key is the entity type and the prefix of the entity id, {id} in the url is replaced by the entity id without the prefix.
+    static ref IMAGE_SOURCES: HashMap<(&'static str, &'static str), &'static str> = HashMap::from([
+        (
+            ("Compound", "PUBCHEM"),
+            "https://pubchem.ncbi.nlm.nih.gov/rest/pug/compound/cid/{id}/PNG?image_size=small",
+        ),
+        (
+            ("Compound", "CHEBI"),
+            "https://www.ebi.ac.uk/chebi/displayImage.do?defaultImage=true&imageIndex=0&chebiId={id}&dimensions=300",
+        ),
+        (
+            ("Compound", "CHEMBL"),
+            "https://www.ebi.ac.uk/chembl/api/data/image/{id}?format=png",
+        ),
+    ]);
+}
+
+/// Get the directory which stores the images.
+pub fn get_image_dir() -> PathBuf {
+    match std::env::var(ENTITY_IMAGE_DIR_ENV) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir().join("biomedgps-images"),
+    }
+}
+
+/// Get the url of the external service which provides the image of an entity.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::image::get_image_source_url;
+///
+/// assert_eq!(
+///     get_image_source_url("Compound", "PUBCHEM:2244"),
+///     Some("https://pubchem.ncbi.nlm.nih.gov/rest/pug/compound/cid/2244/PNG?image_size=small".to_string())
+/// );
+/// assert_eq!(get_image_source_url("Gene", "ENTREZ:7157"), None);
+/// ```
+pub fn get_image_source_url(entity_type: &str, entity_id: &str) -> Option<String> {
+    let (prefix, id) = entity_id.split_once(':')?;
+    IMAGE_SOURCES
+        .get(&(entity_type, prefix.to_uppercase().as_str()))
+        .map(|url| url.replace("{id}", id))
+}
+
+/// Get the entity types which have a built-in image source.
+pub fn get_image_entity_types() -> Vec<String> {
+    let mut entity_types = IMAGE_SOURCES
+        .keys()
+        .map(|(entity_type, _)| entity_type.to_string())
+        .collect::<Vec<String>>();
+    entity_types.sort();
+    entity_types.dedup();
+    entity_types
+}
+
+/// Get the url of the thumbnail of an entity, it's attached to the nodes.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::image::get_thumbnail_url;
+///
+/// assert_eq!(get_thumbnail_url("Compound", "PUBCHEM:2244"), "/api/v1/entity-images/Compound/PUBCHEM:2244");
+/// ```
+pub fn get_thumbnail_url(entity_type: &str, entity_id: &str) -> String {
+    format!("{}/{}/{}", ENTITY_IMAGE_URL_PREFIX, entity_type, entity_id)
+}
+
+/// The cached image of an entity.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct EntityImage {
+    pub entity_id: String,
+    pub entity_type: String,
+    pub source_url: String,
+    // The path of the image which is relative to the image directory.
+    pub filename: String,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl EntityImage {
+    /// Get the path of the cached image.
+    pub fn get_filepath(&self) -> PathBuf {
+        get_image_dir().join(&self.filename)
+    }
+
+    pub async fn fetch(
+        pool: &sqlx::PgPool,
+        entity_type: &str,
+        entity_id: &str,
+    ) -> Result<Option<EntityImage>, anyhow::Error> {
+        let sql_str =
+            "SELECT * FROM biomedgps_entity_image WHERE entity_type = $1 AND entity_id = $2";
+        let image = sqlx::query_as::<_, EntityImage>(sql_str)
+            .bind(entity_type)
+            .bind(entity_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(image)
+    }
+
+    /// Fetch the cached images of the nodes, the node ids are like `Compound::PUBCHEM:2244`.
+    pub async fn fetch_by_node_ids(
+        pool: &sqlx::PgPool,
+        node_ids: &Vec<String>,
+    ) -> Result<Vec<EntityImage>, anyhow::Error> {
+        let sql_str =
+            "SELECT * FROM biomedgps_entity_image WHERE entity_type || '::' || entity_id = ANY($1)";
+        let images = sqlx::query_as::<_, EntityImage>(sql_str)
+            .bind(node_ids)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(images)
+    }
+
+    /// Download the image of an entity and cache it in the image directory. The cached image is replaced if it exists.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `entity_type` - The type of the entity, such as Compound
+    /// * `entity_id` - The id of the entity, such as PUBCHEM:2244
+    /// * `source_url` - The url of the image, it must be a png image
+    ///
+    /// # Returns
+    /// * `Result<EntityImage, anyhow::Error>` - The cached image or an error
+    pub async fn cache(
+        pool: &sqlx::PgPool,
+        entity_type: &str,
+        entity_id: &str,
+        source_url: &str,
+    ) -> Result<EntityImage, anyhow::Error> {
+        let bytes = reqwest::get(source_url)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        if bytes.len() > MAX_IMAGE_SIZE {
+            return Err(anyhow::anyhow!(
+                "The image of {} is too large ({} bytes).",
+                source_url,
+                bytes.len()
+            ));
+        }
+
+        if !bytes.starts_with(&PNG_SIGNATURE) {
+            return Err(anyhow::anyhow!(
+                "The image of {} is not a png image.",
+                source_url
+            ));
+        }
+
+        // The entity type and id might contain the characters which are not allowed in a filename, so the hash is used.
+        let hash = Sha256::digest(format!("{}::{}", entity_type, entity_id).as_bytes())
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let filename = format!("{}/{}.png", &hash[..2], hash);
+        let filepath = get_image_dir().join(&filename);
+        if let Some(dir) = filepath.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&filepath, &bytes).await?;
+
+        let sql_str = "INSERT INTO biomedgps_entity_image (entity_id, entity_type, source_url, filename) VALUES ($1, $2, $3, $4)
+                       ON CONFLICT (entity_type, entity_id) DO UPDATE SET source_url = EXCLUDED.source_url, filename = EXCLUDED.filename, created_at = now()
+                       RETURNING *";
+        let image = sqlx::query_as::<_, EntityImage>(sql_str)
+            .bind(entity_id)
+            .bind(entity_type)
+            .bind(source_url)
+            .bind(&filename)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(image)
+    }
+
+    /// Cache the images of the entities. The entities and the urls of their images are read from a file which contains the entity_id, entity_type and optional source_url columns. If no file is specified, the images of all entities which have a built-in image source are cached.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `filepath` - The file of the entities and the urls of their images
+    /// * `overwrite` - Whether to fetch the images which have been cached again
+    ///
+    /// # Returns
+    /// * `Result<(usize, usize), anyhow::Error>` - The numbers of the cached images and the failed ones
+    pub async fn cache_images(
+        pool: &sqlx::PgPool,
+        filepath: Option<&PathBuf>,
+        overwrite: bool,
+    ) -> Result<(usize, usize), anyhow::Error> {
+        let mut targets: Vec<(String, String, String)> = vec![];
+        match filepath {
+            Some(filepath) => {
+                let delimiter = get_delimiter(filepath).map_err(|e| anyhow::anyhow!("{}", e))?;
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(delimiter)
+                    .from_reader(open_data_file(filepath).map_err(|e| anyhow::anyhow!("{}", e))?);
+                let headers = reader.headers()?.clone();
+                let column = |name: &str| headers.iter().position(|h| h == name);
+                let (id_index, type_index) = match (column("entity_id"), column("entity_type")) {
+                    (Some(i), Some(t)) => (i, t),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "The entity_id and entity_type columns are required for caching the images."
+                        ))
+                    }
+                };
+                let url_index = column("source_url");
+
+                for record in reader.records() {
+                    let record = record?;
+                    let entity_id = record.get(id_index).unwrap_or("").trim().to_string();
+                    let entity_type = record.get(type_index).unwrap_or("").trim().to_string();
+                    let source_url = url_index
+                        .and_then(|i| record.get(i))
+                        .map(|v| v.trim().to_string())
+                        .filter(|v| !v.is_empty())
+                        .or(get_image_source_url(&entity_type, &entity_id));
+                    match source_url {
+                        Some(url) => targets.push((entity_type, entity_id, url)),
+                        None => warn!(
+                            "No image source for {}::{}, skip it.",
+                            entity_type, entity_id
+                        ),
+                    }
+                }
+            }
+            None => {
+                let sql_str = "SELECT e.label, e.id FROM biomedgps_entity e WHERE e.label = ANY($1)
+                               AND ($2 OR NOT EXISTS (SELECT 1 FROM biomedgps_entity_image i WHERE i.entity_type = e.label AND i.entity_id = e.id))";
+                let entities = sqlx::query_as::<_, (String, String)>(sql_str)
+                    .bind(get_image_entity_types())
+                    .bind(overwrite)
+                    .fetch_all(pool)
+                    .await?;
+                for (entity_type, entity_id) in entities {
+                    if let Some(url) = get_image_source_url(&entity_type, &entity_id) {
+                        targets.push((entity_type, entity_id, url));
+                    }
+                }
+            }
+        }
+
+        let mut cached = 0;
+        let mut failed = 0;
+        for (entity_type, entity_id, source_url) in targets {
+            if !overwrite && Self::fetch(pool, &entity_type, &entity_id).await?.is_some() {
+                continue;
+            }
+
+            match Self::cache(pool, &entity_type, &entity_id, &source_url).await {
+                Ok(_) => cached += 1,
+                Err(e) => {
+                    failed += 1;
+                    warn!(
+                        "Failed to cache the image of {}::{}: {}",
+                        entity_type, entity_id, e
+                    );
+                }
+            }
+
+            if (cached + failed) % 100 == 0 {
+                info!("{} images are cached, {} failed.", cached, failed);
+            }
+        }
+
+        Ok((cached, failed))
+    }
+}
@@ -0,0 +1,873 @@
+//! This module is used to run the imports on the server, so the admins don't need the shell access to run `importdb` and `importkge` for the large imports.
+//!
+//! The data files are uploaded into IMPORT_DATA_DIR or copied there by other means. An import job is a list of steps, each step imports a table (same as `importdb --table`) or the embeddings of a KGE model (same as `importkge`). The jobs run one by one on the import worker, the progress and the logs are stored in the biomedgps_import_job table with the jobs of the data registry. A running job can be cancelled, and a failed or cancelled job can be resumed from the step where it stopped, the entity and relation tables are resumed from the last imported chunk.
+
+use crate::api::maintenance::MaintenanceState;
+use crate::model::util::{
+    is_supported_file, read_annotation_file, ImportWarning, ImportWarningKind,
+};
+use crate::{
+    connect_db_with_config, import_data, register_pool, try_import_kge, unregister_pool,
+    ImportOptions, PoolConfig, DEFAULT_IMPORT_CHUNK_SIZE,
+};
+use anyhow::Ok as AnyOk;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::panic::AssertUnwindSafe;
+use std::path::{Component, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+
+/// The directory which stores the data files of the import jobs, a directory in the system temp directory is used if it's not set.
+pub const IMPORT_DATA_DIR_ENV: &str = "IMPORT_DATA_DIR";
+
+/// The uploaded files are stored in this subdirectory of the data directory.
+pub const IMPORT_UPLOAD_DIR: &str = "uploads";
+
+/// The max number of the steps of an import job.
+pub const MAX_IMPORT_JOB_STEPS: usize = 20;
+
+/// How often a running job checks whether it's cancelled.
+pub const IMPORT_CANCEL_POLL_INTERVAL_SECS: u64 = 5;
+
+/// The tables which can be imported by the import jobs, they are same as the tables of `importdb`. The kge step imports the embeddings of a KGE model.
+pub const IMPORT_JOB_TABLES: [&str; 12] = [
+    "entity",
+    "relation",
+    "entity2d",
+    "entity_attribute",
+    "entity_image",
+    "entity_metadata",
+    "relation_metadata",
+    "knowledge_curation",
+    "publication",
+    "subgraph",
+    "variant",
+    "kge",
+];
+
+/// The files of a KGE model in the directory of a kge step, they are same as the files of the bundled demo model.
+pub const KGE_STEP_FILES: [&str; 3] = [
+    "entity_embedding.tsv",
+    "relation_embedding.tsv",
+    "metadata.json",
+];
+
+pub fn get_import_data_dir() -> PathBuf {
+    match std::env::var(IMPORT_DATA_DIR_ENV) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::temp_dir().join("biomedgps-imports"),
+    }
+}
+
+/// Resolve a path which is relative to the data directory, the absolute paths and the paths out of the data directory are rejected.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::import_job::resolve_import_path;
+/// use std::path::PathBuf;
+///
+/// let dir = PathBuf::from("/data/imports");
+/// assert_eq!(resolve_import_path(&dir, "uploads/relations.tsv").unwrap(), PathBuf::from("/data/imports/uploads/relations.tsv"));
+/// assert!(resolve_import_path(&dir, "../secrets.tsv").is_err());
+/// assert!(resolve_import_path(&dir, "/etc/passwd").is_err());
+/// assert!(resolve_import_path(&dir, "").is_err());
+/// ```
+pub fn resolve_import_path(data_dir: &PathBuf, path: &str) -> Result<PathBuf, anyhow::Error> {
+    let relative = PathBuf::from(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow::anyhow!(
+            "Invalid path {}, it must be a relative path in the data directory.",
+            path
+        ));
+    }
+
+    AnyOk(data_dir.join(relative))
+}
+
+/// Check the name of an uploaded file, only the letters, digits, dots, hyphens and underscores are allowed.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::import_job::is_valid_upload_name;
+///
+/// assert!(is_valid_upload_name("drkg_relations.tsv.gz"));
+/// assert!(!is_valid_upload_name("../relations.tsv"));
+/// assert!(!is_valid_upload_name(".env"));
+/// ```
+pub fn is_valid_upload_name(filename: &str) -> bool {
+    !filename.is_empty()
+        && filename.len() <= 128
+        && !filename.starts_with('.')
+        && filename
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+}
+
+/// A data file which is uploaded for the import jobs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ImportFile {
+    // The path which is relative to the data directory, it's used as the filepath of the import steps.
+    pub path: String,
+    pub size: i64,
+}
+
+/// A step of an import job, it imports a table like `importdb` or a KGE model like `importkge`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct ImportJobStep {
+    // The table to import, such as entity, relation and entity_metadata, see IMPORT_JOB_TABLES. Use kge to import the embeddings of a KGE model.
+    pub table: String,
+    // The data file or directory which is relative to the data directory, such as uploads/relations.tsv. The kge step needs a directory which contains entity_embedding.tsv, relation_embedding.tsv and metadata.json.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub filepath: Option<String>,
+    // The dataset of the relations, or the datasets (separated by comma) which the KGE model is trained with.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub dataset: Option<String>,
+    // The annotation file of the relation types, it's required by the relation table.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub annotation_file: Option<String>,
+    #[serde(default)]
+    pub drop: bool,
+    #[serde(default)]
+    pub skip_check: bool,
+    // The entity and relation tables are imported chunk by chunk, so they can be resumed from the last imported chunk.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub chunk_size: Option<usize>,
+    // The name, the type (such as TransE_l2), the metric and the description of the KGE model, only for the kge step.
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub model_name: Option<String>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub model_type: Option<String>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub metric: Option<String>,
+    #[serde(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub description: Option<String>,
+}
+
+impl ImportJobStep {
+    pub fn validate(&self, data_dir: &PathBuf) -> Result<(), anyhow::Error> {
+        if !IMPORT_JOB_TABLES.contains(&self.table.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Invalid table {}, it must be one of {}.",
+                self.table,
+                IMPORT_JOB_TABLES.join(", ")
+            ));
+        }
+
+        // The metadata tables are computed from the other tables.
+        let filepath = match &self.filepath {
+            Some(filepath) => resolve_import_path(data_dir, filepath)?,
+            None if ["entity_metadata", "relation_metadata", "entity_image"]
+                .contains(&self.table.as_str()) =>
+            {
+                return AnyOk(())
+            }
+            None => {
+                return Err(anyhow::anyhow!(
+                    "The filepath is required for the {} table.",
+                    self.table
+                ))
+            }
+        };
+
+        if self.table == "kge" {
+            if self.model_name.is_none() || self.model_type.is_none() || self.dataset.is_none() {
+                return Err(anyhow::anyhow!(
+                    "The model_name, model_type and dataset are required for the kge step."
+                ));
+            }
+            for file in KGE_STEP_FILES {
+                if !filepath.join(file).is_file() {
+                    return Err(anyhow::anyhow!(
+                        "{} is not found in {}.",
+                        file,
+                        self.filepath.as_deref().unwrap_or_default()
+                    ));
+                }
+            }
+            return AnyOk(());
+        }
+
+        if filepath.is_dir() {
+            let has_files = std::fs::read_dir(&filepath)?
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.path().is_file() && is_supported_file(&entry.path()));
+            if !has_files {
+                return Err(anyhow::anyhow!(
+                    "No supported data files are found in {}.",
+                    self.filepath.as_deref().unwrap_or_default()
+                ));
+            }
+        } else if !filepath.is_file() {
+            return Err(anyhow::anyhow!(
+                "{} is not found.",
+                self.filepath.as_deref().unwrap_or_default()
+            ));
+        }
+
+        if self.table == "relation" {
+            if self.dataset.is_none() {
+                return Err(anyhow::anyhow!(
+                    "The dataset is required for the relation table."
+                ));
+            }
+            match &self.annotation_file {
+                Some(annotation_file) => {
+                    if !resolve_import_path(data_dir, annotation_file)?.is_file() {
+                        return Err(anyhow::anyhow!("{} is not found.", annotation_file));
+                    }
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "The annotation_file is required for the relation table, it annotates the relation types."
+                    ))
+                }
+            }
+        }
+
+        if self.chunk_size == Some(0) {
+            return Err(anyhow::anyhow!("The chunk size must be greater than 0."));
+        }
+
+        AnyOk(())
+    }
+
+    /// Whether the step can continue from the last imported chunk instead of the beginning, the dropped tables are always reloaded from the beginning.
+    pub fn is_resumable(&self) -> bool {
+        !self.drop && (self.table == "entity" || self.table == "relation")
+    }
+
+    pub fn describe(&self) -> String {
+        match (&self.table[..], &self.filepath) {
+            ("kge", Some(filepath)) => format!(
+                "import the KGE model {} from {}",
+                self.model_name.as_deref().unwrap_or_default(),
+                filepath
+            ),
+            (table, Some(filepath)) => format!("import the {} table from {}", table, filepath),
+            (table, None) => format!("update the {} table", table),
+        }
+    }
+
+    /// Run the step, it returns the warnings of the import. It fails if any data file fails to import.
+    async fn run(
+        &self,
+        database_url: &str,
+        data_dir: &PathBuf,
+        resume: bool,
+    ) -> Result<Vec<ImportWarning>, anyhow::Error> {
+        let filepath = match &self.filepath {
+            Some(filepath) => Some(resolve_import_path(data_dir, filepath)?),
+            None => None,
+        };
+
+        if self.table == "kge" {
+            let dir = filepath.unwrap_or_default();
+            let model_name = self.model_name.clone().unwrap_or_default();
+            let dataset = self.dataset.clone().unwrap_or_default();
+            let datasets = dataset.split(',').map(|d| d.trim()).collect::<Vec<&str>>();
+            try_import_kge(
+                database_url,
+                &model_name,
+                &model_name,
+                self.model_type.as_deref().unwrap_or_default(),
+                &datasets,
+                self.description.as_deref(),
+                &dir.join(KGE_STEP_FILES[0]),
+                &dir.join(KGE_STEP_FILES[1]),
+                &dir.join(KGE_STEP_FILES[2]),
+                self.drop,
+                self.skip_check,
+                false,
+                &None,
+                self.metric.as_deref(),
+            )
+            .await?;
+            return AnyOk(vec![]);
+        }
+
+        let relation_type_mappings = match &self.annotation_file {
+            Some(annotation_file) if self.table == "relation" => {
+                let annotation_file = resolve_import_path(data_dir, annotation_file)?;
+                match read_annotation_file(&annotation_file) {
+                    Ok(mappings) => Some(mappings),
+                    Err(e) => {
+                        return Err(anyhow::anyhow!("Failed to read the annotation file: {}", e))
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        // The chunked mode saves the progress, so the entity and relation tables can be resumed.
+        let chunk_size = match self.chunk_size {
+            Some(chunk_size) => Some(chunk_size),
+            None if self.is_resumable() => Some(DEFAULT_IMPORT_CHUNK_SIZE),
+            None => None,
+        };
+
+        let warnings = import_data(
+            database_url,
+            &filepath.map(|f| f.to_string_lossy().to_string()),
+            &self.table,
+            &ImportOptions {
+                dataset: self.dataset.clone(),
+                relation_type_mappings,
+                drop: self.drop,
+                skip_check: self.skip_check,
+                chunk_size,
+                resume,
+                ..ImportOptions::default()
+            },
+        )
+        .await;
+
+        let failures = warnings
+            .iter()
+            .filter(|w| w.kind == ImportWarningKind::FailedFile)
+            .map(|w| w.message.clone())
+            .collect::<Vec<String>>();
+        if !failures.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} data files failed to import: {}",
+                failures.len(),
+                failures.join("; ")
+            ));
+        }
+
+        AnyOk(warnings)
+    }
+}
+
+/// The payload of an import job.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct ImportJobRequest {
+    pub steps: Vec<ImportJobStep>,
+}
+
+impl ImportJobRequest {
+    pub fn validate(&self, data_dir: &PathBuf) -> Result<(), anyhow::Error> {
+        if self.steps.is_empty() || self.steps.len() > MAX_IMPORT_JOB_STEPS {
+            return Err(anyhow::anyhow!(
+                "The number of the steps should be between 1 and {}, but got {}.",
+                MAX_IMPORT_JOB_STEPS,
+                self.steps.len()
+            ));
+        }
+
+        for (idx, step) in self.steps.iter().enumerate() {
+            if let Err(e) = step.validate(data_dir) {
+                return Err(anyhow::anyhow!("Invalid step {}: {}", idx + 1, e));
+            }
+        }
+
+        AnyOk(())
+    }
+}
+
+/// The source of the import jobs which reimport the datasets published to the data registry, see `crate::model::registry`.
+pub const REGISTRY_IMPORT_JOB: &str = "registry";
+
+/// The source of the import jobs which are created by the admins to import the uploaded data files.
+pub const ADMIN_IMPORT_JOB: &str = "admin";
+
+/// How many import jobs can wait for the worker, the new jobs fail when the queue is full.
+pub const IMPORT_JOB_QUEUE_SIZE: usize = 16;
+
+/// An import job, it's created by the data registry webhook or by the admins. The status is one of pending, running, succeeded, failed and cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ImportJob {
+    pub id: i64,
+    // Who creates the job, registry or admin.
+    pub source: String,
+    // The username of the admin who creates the job, it's empty for the registry jobs.
+    #[oai(skip_serializing_if_is_none)]
+    pub owner: Option<String>,
+    // The dataset and the version which are published to the registry, they are empty for the admin jobs.
+    #[oai(skip_serializing_if_is_none)]
+    pub dataset: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub version: Option<String>,
+    pub status: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub message: Option<String>,
+    // The dataset-published event of a registry job, or the import job request of an admin job.
+    pub payload: serde_json::Value,
+    // The non-fatal issues of the registry imports, such as the unknown relation types. The job succeeds with the warnings, the pipelines decide whether to use the imported dataset.
+    pub warnings: serde_json::Value,
+    // How many steps have been finished, an admin job is resumed from this step. A registry job has a step for each file.
+    pub finished_steps: i32,
+    pub total_steps: i32,
+    // How many times the job is started, it's more than 1 if the job is resumed.
+    pub attempts: i32,
+    // The logs of the steps, such as the started steps and the warnings of the imports.
+    #[oai(skip_serializing_if_is_none)]
+    pub logs: Option<String>,
+
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(with = "ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+
+    #[oai(skip_serializing_if_is_none)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A job which is waiting for the import worker.
+struct ImportTask {
+    database_url: String,
+    job_id: i64,
+}
+
+lazy_static! {
+    /// The queue of the import worker, the worker is started when the first job is queued.
+    static ref IMPORT_JOB_QUEUE: SyncSender<ImportTask> = start_import_worker();
+}
+
+/// Start the worker which runs the import jobs one by one on a dedicated thread with its own runtime, so the long imports don't block the API server and a burst of jobs doesn't start a thread for each of them.
+fn start_import_worker() -> SyncSender<ImportTask> {
+    let (sender, receiver) = sync_channel::<ImportTask>(IMPORT_JOB_QUEUE_SIZE);
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to start the import worker: {}", e);
+                return;
+            }
+        };
+
+        for task in receiver {
+            runtime.block_on(ImportJob::execute(&task.database_url, task.job_id));
+        }
+    });
+
+    sender
+}
+
+impl ImportJob {
+    /// Lock the table and check whether an unfinished admin job exists, so two admin jobs can't be started at the same time.
+    async fn lock_unfinished(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query("LOCK TABLE biomedgps_import_job IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+        let unfinished: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM biomedgps_import_job WHERE source = $1 AND status IN ('pending', 'running') LIMIT 1",
+        )
+        .bind(ADMIN_IMPORT_JOB)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match unfinished {
+            Some(id) => Err(anyhow::anyhow!(
+                "The import job {} is not finished, only one import job can run at a time.",
+                id
+            )),
+            None => AnyOk(()),
+        }
+    }
+
+    /// Create an admin import job and queue it for the import worker. Only one admin job can run at a time, because the imports share the maintenance mode and the progress of the chunks.
+    pub async fn schedule(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        owner: &str,
+        request: &ImportJobRequest,
+    ) -> Result<ImportJob, anyhow::Error> {
+        request.validate(&get_import_data_dir())?;
+
+        let mut tx = pool.begin().await?;
+        Self::lock_unfinished(&mut tx).await?;
+
+        let sql_str = "INSERT INTO biomedgps_import_job (source, owner, status, payload, total_steps) VALUES ($1, $2, 'pending', $3, $4) RETURNING *";
+        let job = sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(ADMIN_IMPORT_JOB)
+            .bind(owner)
+            .bind(serde_json::to_value(request)?)
+            .bind(request.steps.len() as i32)
+            .fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Self::enqueue(pool, database_url, job.id).await?;
+        AnyOk(job)
+    }
+
+    /// Resume a failed or cancelled admin job from the step where it stopped.
+    pub async fn resume(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        id: i64,
+    ) -> Result<ImportJob, anyhow::Error> {
+        let mut tx = pool.begin().await?;
+        Self::lock_unfinished(&mut tx).await?;
+
+        let sql_str = "UPDATE biomedgps_import_job SET status = 'pending', message = NULL, finished_at = NULL, updated_at = now() WHERE id = $1 AND source = $2 AND status IN ('failed', 'cancelled') RETURNING *";
+        let job = match sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(id)
+            .bind(ADMIN_IMPORT_JOB)
+            .fetch_optional(&mut tx)
+            .await?
+        {
+            Some(job) => job,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "The admin import job {} is not found or it's not failed or cancelled.",
+                    id
+                ))
+            }
+        };
+        tx.commit().await?;
+
+        Self::enqueue(pool, database_url, job.id).await?;
+        AnyOk(job)
+    }
+
+    /// Cancel a pending or running admin job. The running step is stopped within a few seconds, the entity and relation tables keep the imported chunks, so the job can be resumed later.
+    pub async fn cancel(pool: &sqlx::PgPool, id: i64) -> Result<ImportJob, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_import_job SET status = 'cancelled', message = 'The job is cancelled by the user.', finished_at = now(), updated_at = now() WHERE id = $1 AND source = $2 AND status IN ('pending', 'running') RETURNING *";
+        match sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(id)
+            .bind(ADMIN_IMPORT_JOB)
+            .fetch_optional(pool)
+            .await?
+        {
+            Some(job) => AnyOk(job),
+            None => Err(anyhow::anyhow!(
+                "The admin import job {} is not found or it's finished, only a pending or running job can be cancelled.",
+                id
+            )),
+        }
+    }
+
+    /// Mark the unfinished jobs as failed, they are interrupted when the server stopped. The admin jobs can be resumed by the admins, and the registry jobs are scheduled again when the datasets are published again. It should be called when the server starts.
+    pub async fn fail_interrupted(pool: &sqlx::PgPool) -> Result<u64, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_import_job SET status = 'failed', message = 'The server stopped before the job finished.', finished_at = now(), updated_at = now() WHERE status IN ('pending', 'running')";
+        let result = sqlx::query(sql_str).execute(pool).await?;
+
+        AnyOk(result.rows_affected())
+    }
+
+    /// Fetch the import jobs of both sources, the newest first. All matched jobs are returned unless the page is set.
+    pub async fn list(
+        pool: &sqlx::PgPool,
+        statuses: &Option<Vec<String>>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<ImportJob>, anyhow::Error> {
+        let (limit, offset) = match page {
+            Some(page) => {
+                let page_size = page_size.unwrap_or(10).max(1);
+                (
+                    Some(page_size as i64),
+                    (page.max(1) - 1) as i64 * page_size as i64,
+                )
+            }
+            None => (None, 0),
+        };
+
+        let sql_str = "SELECT * FROM biomedgps_import_job
+                       WHERE ($1::text[] IS NULL OR status = ANY($1))
+                       ORDER BY id DESC
+                       LIMIT $2 OFFSET $3";
+        let jobs = sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(statuses)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(jobs)
+    }
+
+    pub(crate) async fn append_log(
+        pool: &sqlx::PgPool,
+        id: i64,
+        line: &str,
+    ) -> Result<(), anyhow::Error> {
+        info!("The import job {}: {}", id, line);
+        let sql_str = "UPDATE biomedgps_import_job SET logs = COALESCE(logs, '') || $2 || E'\\n', updated_at = now() WHERE id = $1";
+        sqlx::query(sql_str)
+            .bind(id)
+            .bind(format!("[{}] {}", Utc::now().to_rfc3339(), line))
+            .execute(pool)
+            .await?;
+
+        AnyOk(())
+    }
+
+    pub(crate) async fn finish_steps(
+        pool: &sqlx::PgPool,
+        id: i64,
+        finished_steps: usize,
+    ) -> Result<(), anyhow::Error> {
+        let sql_str =
+            "UPDATE biomedgps_import_job SET finished_steps = $2, updated_at = now() WHERE id = $1";
+        sqlx::query(sql_str)
+            .bind(id)
+            .bind(finished_steps as i32)
+            .execute(pool)
+            .await?;
+
+        AnyOk(())
+    }
+
+    async fn is_cancelled(pool: &sqlx::PgPool, id: i64) -> Result<bool, anyhow::Error> {
+        let status: String =
+            sqlx::query_scalar("SELECT status FROM biomedgps_import_job WHERE id = $1")
+                .bind(id)
+                .fetch_one(pool)
+                .await?;
+
+        AnyOk(status == "cancelled")
+    }
+
+    /// Queue a pending job for the import worker, the job is marked as failed if the queue is full.
+    pub(crate) async fn enqueue(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        id: i64,
+    ) -> Result<(), anyhow::Error> {
+        let task = ImportTask {
+            database_url: database_url.to_string(),
+            job_id: id,
+        };
+        let err = match IMPORT_JOB_QUEUE.try_send(task) {
+            Ok(_) => return AnyOk(()),
+            Err(TrySendError::Full(_)) => format!(
+                "The import queue is full, {} jobs are waiting.",
+                IMPORT_JOB_QUEUE_SIZE
+            ),
+            Err(TrySendError::Disconnected(_)) => "The import worker is not running.".to_string(),
+        };
+
+        let sql_str = "UPDATE biomedgps_import_job SET status = 'failed', message = $1, finished_at = now(), updated_at = now() WHERE id = $2";
+        sqlx::query(sql_str)
+            .bind(&err)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Err(anyhow::anyhow!(err))
+    }
+
+    /// Run a queued job with its own pool, a registry job notifies the callback url of its event when it's finished.
+    async fn execute(database_url: &str, job_id: i64) {
+        let pool_name = format!("import-job-{}", job_id);
+        let pool_config = PoolConfig::job();
+        let pool = connect_db_with_config(database_url, &pool_config).await;
+        register_pool(&pool_name, &pool, &pool_config);
+
+        match Self::run(&pool, database_url, job_id).await {
+            Ok(Some(job)) if job.source == REGISTRY_IMPORT_JOB => job.notify_registry().await,
+            Ok(_) => {}
+            Err(e) => error!("Failed to update the import job {}: {}", job_id, e),
+        }
+
+        unregister_pool(&pool_name);
+        pool.close().await;
+    }
+
+    /// Start a pending job and finish it as succeeded or failed, the finished job is returned. None is returned if the job is cancelled before it's started.
+    async fn run(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        id: i64,
+    ) -> Result<Option<ImportJob>, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_import_job SET status = 'running', attempts = attempts + 1, updated_at = now() WHERE id = $1 AND status = 'pending' RETURNING *";
+        let job = match sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+        {
+            Some(job) => job,
+            None => return AnyOk(None),
+        };
+
+        let result = if job.source == REGISTRY_IMPORT_JOB {
+            job.run_reimport(pool, database_url).await
+        } else {
+            job.run_steps(pool, database_url).await
+        };
+
+        // The cancelled job keeps its status.
+        let query = match &result {
+            Ok(_) => {
+                let sql_str = "UPDATE biomedgps_import_job SET status = 'succeeded', finished_at = now(), updated_at = now() WHERE id = $1 AND status = 'running' RETURNING *";
+                sqlx::query_as::<_, ImportJob>(sql_str).bind(id)
+            }
+            Err(e) => {
+                error!("The import job {} failed: {}", id, e);
+                let sql_str = "UPDATE biomedgps_import_job SET status = 'failed', message = $2, finished_at = now(), updated_at = now() WHERE id = $1 AND status <> 'cancelled' RETURNING *";
+                sqlx::query_as::<_, ImportJob>(sql_str)
+                    .bind(id)
+                    .bind(e.to_string())
+            }
+        };
+        let job = query.fetch_optional(pool).await?;
+
+        AnyOk(job)
+    }
+
+    async fn run_steps(
+        &self,
+        pool: &sqlx::PgPool,
+        database_url: &str,
+    ) -> Result<(), anyhow::Error> {
+        let id = self.id;
+        let request: ImportJobRequest = serde_json::from_value(self.payload.clone())?;
+        let steps = request.steps;
+        let data_dir = get_import_data_dir();
+        let first_step = self.finished_steps as usize;
+        for (idx, step) in steps.iter().enumerate().skip(first_step) {
+            // The interrupted step continues from the last imported chunk when the job is resumed.
+            let resume = self.attempts > 1 && idx == first_step && step.is_resumable();
+            Self::append_log(
+                pool,
+                id,
+                &format!(
+                    "Step {}/{}: {}{}.",
+                    idx + 1,
+                    steps.len(),
+                    step.describe(),
+                    if resume { " (resumed)" } else { "" }
+                ),
+            )
+            .await?;
+
+            let step_future =
+                AssertUnwindSafe(step.run(database_url, &data_dir, resume)).catch_unwind();
+            tokio::pin!(step_future);
+            let result = loop {
+                tokio::select! {
+                    result = &mut step_future => break Some(result),
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(IMPORT_CANCEL_POLL_INTERVAL_SECS)) => {
+                        if Self::is_cancelled(pool, id).await? {
+                            break None;
+                        }
+                    }
+                }
+            };
+
+            let result = match result {
+                Some(Ok(result)) => result,
+                Some(Err(panic)) => {
+                    let reason = panic
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                        .unwrap_or_default();
+                    Err(anyhow::anyhow!("The step panicked: {}", reason))
+                }
+                None => {
+                    // The stopped step doesn't exit the maintenance mode by itself.
+                    if step.drop {
+                        if let Err(e) = MaintenanceState::exit(pool).await {
+                            warn!("Failed to exit the maintenance mode: {}", e);
+                        }
+                    }
+                    Self::append_log(pool, id, &format!("Step {} is cancelled.", idx + 1)).await?;
+                    return AnyOk(());
+                }
+            };
+
+            match result {
+                Ok(warnings) => {
+                    for warning in &warnings {
+                        Self::append_log(pool, id, &format!("Warning: {}", warning.message))
+                            .await?;
+                    }
+                    Self::finish_steps(pool, id, idx + 1).await?;
+                }
+                Err(e) => {
+                    if step.drop {
+                        if let Err(e) = MaintenanceState::exit(pool).await {
+                            warn!("Failed to exit the maintenance mode: {}", e);
+                        }
+                    }
+                    Self::append_log(pool, id, &format!("Step {} failed: {}", idx + 1, e)).await?;
+                    return Err(anyhow::anyhow!("Step {} failed: {}", idx + 1, e));
+                }
+            }
+        }
+
+        Self::append_log(pool, id, "All steps are finished.").await?;
+
+        AnyOk(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(table: &str, filepath: Option<&str>) -> ImportJobStep {
+        ImportJobStep {
+            table: table.to_string(),
+            filepath: filepath.map(|s| s.to_string()),
+            dataset: None,
+            annotation_file: None,
+            drop: false,
+            skip_check: false,
+            chunk_size: None,
+            model_name: None,
+            model_type: None,
+            metric: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_import_job_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_path_buf();
+        std::fs::create_dir_all(data_dir.join(IMPORT_UPLOAD_DIR)).unwrap();
+        std::fs::write(data_dir.join("uploads/entities.tsv"), "id\tname\n").unwrap();
+
+        let request = ImportJobRequest {
+            steps: vec![
+                step("entity", Some("uploads/entities.tsv")),
+                step("entity_metadata", None),
+            ],
+        };
+        assert!(request.validate(&data_dir).is_ok());
+        assert!(request.steps[0].is_resumable());
+
+        assert!(ImportJobRequest { steps: vec![] }
+            .validate(&data_dir)
+            .is_err());
+        assert!(step("unknown", None).validate(&data_dir).is_err());
+        assert!(step("entity", None).validate(&data_dir).is_err());
+        assert!(step("entity", Some("uploads/missing.tsv"))
+            .validate(&data_dir)
+            .is_err());
+        assert!(step("entity", Some("../entities.tsv"))
+            .validate(&data_dir)
+            .is_err());
+        // The relation table needs the dataset and the annotation file.
+        assert!(step("relation", Some("uploads/entities.tsv"))
+            .validate(&data_dir)
+            .is_err());
+
+        let mut dropped = step("relation", Some("uploads/entities.tsv"));
+        dropped.drop = true;
+        assert!(!dropped.is_resumable());
+    }
+}
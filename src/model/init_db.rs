@@ -1,17 +1,236 @@
 //! SQL initialization strings for creating tables.
 
 use crate::model::core::Relation;
+use crate::model::graph::RELATION_TYPE_REGEX;
 use crate::model::kge::{
     get_embedding_metadata, get_entity_emb_table_name, get_relation_emb_table_name,
     EmbeddingMetadata, DEFAULT_MODEL_NAME,
 };
-use crate::model::util::ValidationError;
+use crate::model::util::{update_dataset_relation_metadata, ValidationError};
+use chrono::{DateTime, TimeZone, Utc};
 use futures::stream::{FuturesUnordered, StreamExt};
-use log::{debug, error, info};
+use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
 use neo4rs::{query, Graph};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+// The dataset label of the inverse relations which are generated by the graph completion job.
+pub const INVERSE_RELATION_DATASET: &str = "biomedgps_inverse";
+
+/// The semantics of a relation type, it decides how to generate the inverse relation.
+///
+/// * `symmetric` - The relation is the same in both directions, such as the binding between two genes. The inverse relation has the same relation type.
+/// * `inverse` - The name of the inverse relation, such as `has_symptom` and `symptom_of`. The inverse relation type is composed of the same resource, the inverse name and the swapped entity types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationSemantics {
+    pub symmetric: bool,
+    pub inverse: Option<&'static str>,
+}
+
+lazy_static! {
+    // The relation types which are inherently bidirectional. The key is the relation type, such as STRING::BINDING::Gene:Gene.
+    pub static ref RELATION_TYPE_SEMANTICS: HashMap<&'static str, RelationSemantics> = {
+        let symmetric = RelationSemantics { symmetric: true, inverse: None };
+        let mut m = HashMap::new();
+        m.insert("STRING::BINDING::Gene:Gene", symmetric.clone());
+        m.insert("STRING::REACTION::Gene:Gene", symmetric.clone());
+        m.insert("STRING::OTHER::Gene:Gene", symmetric.clone());
+        m.insert("INTACT::PHYSICAL ASSOCIATION::Gene:Gene", symmetric.clone());
+        m.insert("INTACT::ASSOCIATION::Gene:Gene", symmetric.clone());
+        m.insert("Hetionet::GiG::Gene:Gene", symmetric.clone());
+        m.insert("Hetionet::CrC::Compound:Compound", symmetric.clone());
+        m.insert("Hetionet::DrD::Disease:Disease", symmetric.clone());
+        m.insert("DRUGBANK::ddi-interactor-in::Compound:Compound", symmetric.clone());
+        m.insert("HSDN::has_symptom::Disease:Symptom", RelationSemantics { symmetric: false, inverse: Some("symptom_of") });
+        m
+    };
+}
+
+/// Get the inverse relation type by the relation-type semantics registry.
+///
+/// # Arguments
+/// * `relation_type` - The relation type, such as "HSDN::has_symptom::Disease:Symptom".
+///
+/// # Returns
+/// `Option<String>` - The inverse relation type, such as "HSDN::symptom_of::Symptom:Disease". None if the relation type is not bidirectional.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::init_db::get_inverse_relation_type;
+/// let inverse = get_inverse_relation_type("HSDN::has_symptom::Disease:Symptom");
+/// assert_eq!(inverse, Some("HSDN::symptom_of::Symptom:Disease".to_string()));
+/// ```
+///
+pub fn get_inverse_relation_type(relation_type: &str) -> Option<String> {
+    let semantics = RELATION_TYPE_SEMANTICS.get(relation_type)?;
+    if semantics.symmetric {
+        return Some(relation_type.to_string());
+    }
+
+    let captures = RELATION_TYPE_REGEX.captures(relation_type)?;
+    let inverse = semantics.inverse?;
+    Some(format!(
+        "{}::{}::{}:{}",
+        &captures[1], inverse, &captures[4], &captures[3]
+    ))
+}
+
+/// The result of the graph completion job for one relation type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InverseRelationReport {
+    pub relation_type: String,
+    pub inverse_relation_type: String,
+    pub added: u64,
+    // The relations which already have an inverse relation.
+    pub skipped: u64,
+}
+
+/// Generate the missing inverse relations for the bidirectional relation types in the biomedgps_relation table.
+/// The generated relations are labeled by the dataset (INVERSE_RELATION_DATASET by default), so they can be removed or excluded easily.
+/// The evidence counts and the relation metadata of the dataset are refreshed in the same operation, the graph database is synced by [`sync_graph_relations`](../../fn.sync_graph_relations.html).
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `relation_types` - Only complete these relation types, None means all relation types in the registry.
+/// * `dataset` - The synthetic dataset label of the generated relations.
+/// * `dry_run` - Only report how many relations will be added.
+///
+/// # Returns
+/// `Result<Vec<InverseRelationReport>, ValidationError>` - How many relations are added/skipped for each relation type.
+pub async fn add_inverse_relations(
+    pool: &PgPool,
+    relation_types: Option<Vec<&str>>,
+    dataset: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<InverseRelationReport>, ValidationError> {
+    let dataset = dataset.unwrap_or(INVERSE_RELATION_DATASET);
+    let relation_types: Vec<&str> = match relation_types {
+        Some(relation_types) => relation_types,
+        None => RELATION_TYPE_SEMANTICS.keys().cloned().collect(),
+    };
+
+    let mut reports = vec![];
+    for relation_type in relation_types {
+        let inverse_relation_type = match get_inverse_relation_type(relation_type) {
+            Some(inverse_relation_type) => inverse_relation_type,
+            None => {
+                return Err(ValidationError::new(
+                    &format!(
+                        "The relation type {} is not a bidirectional relation type in the semantics registry.",
+                        relation_type
+                    ),
+                    vec![],
+                ))
+            }
+        };
+
+        let missing_clause = "FROM biomedgps_relation r
+             WHERE r.relation_type = $1 AND r.dataset <> $3
+               AND NOT EXISTS (
+                 SELECT 1 FROM biomedgps_relation i
+                 WHERE i.relation_type = $2
+                   AND i.source_id = r.target_id AND i.source_type = r.target_type
+                   AND i.target_id = r.source_id AND i.target_type = r.source_type
+               )";
+
+        let total = match sqlx::query_as::<_, (i64,)>(
+            "SELECT COUNT(*) FROM biomedgps_relation WHERE relation_type = $1 AND dataset <> $2",
+        )
+        .bind(relation_type)
+        .bind(dataset)
+        .fetch_one(pool)
+        .await
+        {
+            Ok(total) => total.0 as u64,
+            Err(e) => {
+                return Err(ValidationError::new(
+                    &format!("Failed to count the relations of {}: {}", relation_type, e),
+                    vec![],
+                ))
+            }
+        };
+
+        let sql_str = if dry_run {
+            format!("SELECT COUNT(*) {}", missing_clause)
+        } else {
+            // The same relation might exist in several datasets, so we only insert one inverse relation for them.
+            format!(
+                "WITH inserted AS (
+                   INSERT INTO biomedgps_relation (relation_type, formatted_relation_type, source_id, source_type, target_id, target_type, resource, key_sentence, pmids, dataset)
+                   SELECT DISTINCT ON (r.source_id, r.source_type, r.target_id, r.target_type, r.resource)
+                          $2, $2, r.target_id, r.target_type, r.source_id, r.source_type, r.resource, r.key_sentence, r.pmids, $3
+                   {}
+                   ON CONFLICT DO NOTHING
+                   RETURNING 1
+                 )
+                 SELECT COUNT(*) FROM inserted",
+                missing_clause
+            )
+        };
+
+        let added = match sqlx::query_as::<_, (i64,)>(&sql_str)
+            .bind(relation_type)
+            .bind(&inverse_relation_type)
+            .bind(dataset)
+            .fetch_one(pool)
+            .await
+        {
+            Ok(added) => added.0 as u64,
+            Err(e) => {
+                return Err(ValidationError::new(
+                    &format!(
+                        "Failed to add the inverse relations of {}: {}",
+                        relation_type, e
+                    ),
+                    vec![],
+                ))
+            }
+        };
+
+        info!(
+            "{} inverse relations ({}) are added for {}, {} relations are skipped.",
+            added,
+            inverse_relation_type,
+            relation_type,
+            total.saturating_sub(added)
+        );
+
+        reports.push(InverseRelationReport {
+            relation_type: relation_type.to_string(),
+            inverse_relation_type,
+            added,
+            skipped: total.saturating_sub(added),
+        });
+    }
+
+    if !dry_run {
+        if let Err(e) = Relation::refresh_evidence_counts(pool, dataset).await {
+            return Err(ValidationError::new(
+                &format!(
+                    "Failed to refresh the evidence counts of {}: {}",
+                    dataset, e
+                ),
+                vec![],
+            ));
+        }
+
+        // The relation types of the inverse relations need to be in the metadata table, otherwise the frontend can't show them.
+        if let Err(e) = update_dataset_relation_metadata(pool, dataset).await {
+            return Err(ValidationError::new(
+                &format!(
+                    "Failed to update the relation metadata of {}: {}",
+                    dataset, e
+                ),
+                vec![],
+            ));
+        }
+    }
+
+    Ok(reports)
+}
+
 /// Generate a table name for the score table of the triple entity.
 ///
 /// # Arguments
@@ -398,6 +617,13 @@ pub fn init_kg_score_sql(
                 resource AS resource,
                 dataset AS dataset,
                 pmids AS pmids,
+                n_pmids AS n_pmids,
+                n_datasets AS n_datasets,
+                n_curations AS n_curations,
+                tissue AS tissue,
+                tissue_id AS tissue_id,
+                cell_type AS cell_type,
+                cell_type_id AS cell_type_id,
                 {score_function_name}(
                     vector_to_float4(tt.source_embedding, {dimension}, false),
                     vector_to_float4(tt.relation_type_embedding, {dimension}, false),
@@ -483,6 +709,25 @@ pub async fn create_kg_score_table(
         }
     }
 
+    // The top relations table is derived from the score table, so it must be invalidated when the score table is refreshed.
+    let invalidate_sql_str = format!(
+        "DROP TABLE IF EXISTS {top_relations_table};",
+        top_relations_table =
+            get_top_relations_table_name(table_prefix.unwrap_or(DEFAULT_MODEL_NAME))
+    );
+    match sqlx::query(&invalidate_sql_str).execute(&mut tx).await {
+        Ok(_) => {
+            debug!("The top relations table is invalidated successfully");
+        }
+        Err(e) => {
+            error!("Failed to invalidate the top relations table: {}", e);
+            return Err(ValidationError::new(
+                &format!("Failed to invalidate the top relations table: {}", e),
+                vec![],
+            ));
+        }
+    }
+
     match sqlx::query(&init_sql).execute(&mut tx).await {
         Ok(_) => {
             debug!("The kg score table is created successfully");
@@ -496,6 +741,38 @@ pub async fn create_kg_score_table(
         }
     };
 
+    // Record when and from how many relations the score table is computed, it's used to check the freshness of the score table.
+    let num_relations =
+        match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM biomedgps_relation")
+            .fetch_one(&mut tx)
+            .await
+        {
+            Ok(num_relations) => num_relations,
+            Err(e) => {
+                error!("Failed to count the relations: {}", e);
+                return Err(ValidationError::new(
+                    &format!("Failed to count the relations: {}", e),
+                    vec![],
+                ));
+            }
+        };
+
+    let comment_sql_str = format!(
+        "COMMENT ON TABLE {score_table} IS '{comment}';",
+        score_table = get_kg_score_table_name(table_prefix.unwrap_or(DEFAULT_MODEL_NAME)),
+        comment = format_kg_score_table_comment(Utc::now().timestamp(), num_relations)
+    );
+    match sqlx::query(&comment_sql_str).execute(&mut tx).await {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to comment the score table: {}", e);
+            return Err(ValidationError::new(
+                &format!("Failed to comment the score table: {}", e),
+                vec![],
+            ));
+        }
+    };
+
     // Commit the transaction
     match tx.commit().await {
         Ok(_) => Ok(()),
@@ -509,6 +786,378 @@ pub async fn create_kg_score_table(
     }
 }
 
+// The table comment of the kg score table, such as "created_at=1706140800;num_relations=1000".
+fn format_kg_score_table_comment(created_at: i64, num_relations: i64) -> String {
+    format!("created_at={};num_relations={}", created_at, num_relations)
+}
+
+fn parse_kg_score_table_comment(comment: &str) -> (Option<DateTime<Utc>>, Option<i64>) {
+    let mut created_at = None;
+    let mut num_relations = None;
+    for item in comment.split(";") {
+        match item.split_once("=") {
+            Some(("created_at", value)) => {
+                created_at = value
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|ts| Utc.timestamp_opt(ts, 0).single());
+            }
+            Some(("num_relations", value)) => {
+                num_relations = value.parse::<i64>().ok();
+            }
+            _ => {}
+        }
+    }
+
+    (created_at, num_relations)
+}
+
+/// The status of the kg score table of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KgScoreTableStatus {
+    pub table_name: String,
+    pub exists: bool,
+    /// It's None if the table doesn't exist or it's created by an old version which doesn't record the metadata.
+    pub created_at: Option<DateTime<Utc>>,
+    /// The number of the relations when the score table is computed.
+    pub num_relations: Option<i64>,
+    /// The score table is fresh if it's computed after the embeddings are imported and no relation is added or removed since then.
+    pub is_fresh: bool,
+    /// Why the score table is missing or stale.
+    pub message: Option<String>,
+}
+
+/// Check whether the kg score table exists and whether it's fresh.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `table_prefix` - The prefix of the table name, such as "biomedgps".
+///
+/// # Returns
+/// `Result<KgScoreTableStatus, ValidationError>` - The status of the score table.
+///
+pub async fn get_kg_score_table_status(
+    pool: &PgPool,
+    table_prefix: &str,
+) -> Result<KgScoreTableStatus, ValidationError> {
+    let table_name = get_kg_score_table_name(table_prefix);
+    let (exists, comment) = match sqlx::query_as::<_, (bool, Option<String>)>(
+        "SELECT to_regclass($1) IS NOT NULL, obj_description(to_regclass($1), 'pg_class')",
+    )
+    .bind(&table_name)
+    .fetch_one(pool)
+    .await
+    {
+        Ok(record) => record,
+        Err(e) => {
+            error!("Failed to get the status of the score table: {}", e);
+            return Err(ValidationError::new(
+                &format!("Failed to get the status of the score table: {}", e),
+                vec![],
+            ));
+        }
+    };
+
+    if !exists {
+        return Ok(KgScoreTableStatus {
+            message: Some(format!(
+                "The score table {} doesn't exist, please run `biomedgps-cli inittable -t knowledge-score -T {}` to create it.",
+                table_name, table_prefix
+            )),
+            table_name,
+            exists,
+            created_at: None,
+            num_relations: None,
+            is_fresh: false,
+        });
+    }
+
+    let (created_at, num_relations) = match comment {
+        Some(comment) => parse_kg_score_table_comment(&comment),
+        None => (None, None),
+    };
+
+    let current_num_relations =
+        match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM biomedgps_relation")
+            .fetch_one(pool)
+            .await
+        {
+            Ok(num_relations) => num_relations,
+            Err(e) => {
+                error!("Failed to count the relations: {}", e);
+                return Err(ValidationError::new(
+                    &format!("Failed to count the relations: {}", e),
+                    vec![],
+                ));
+            }
+        };
+
+    let message = match (created_at, num_relations) {
+        (Some(created_at), Some(num_relations)) => {
+            let embedding_created_at =
+                get_embedding_metadata(table_prefix).map(|metadata| metadata.created_at);
+            if embedding_created_at.map_or(false, |t| t > created_at) {
+                Some("The embeddings are imported after the score table is computed.".to_string())
+            } else if num_relations != current_num_relations {
+                Some(format!(
+                    "The score table is computed from {} relations, but there are {} relations now.",
+                    num_relations, current_num_relations
+                ))
+            } else {
+                None
+            }
+        }
+        _ => Some(
+            "The score table doesn't record when it's computed, so we cannot check its freshness."
+                .to_string(),
+        ),
+    };
+
+    Ok(KgScoreTableStatus {
+        table_name,
+        exists,
+        created_at,
+        num_relations,
+        is_fresh: message.is_none(),
+        message,
+    })
+}
+
+/// Make sure the kg score table exists before querying it, so the routes can return an informative error instead of a raw database error. A stale score table is still usable, so we only log a warning for it.
+///
+/// # Returns
+/// `Result<String, ValidationError>` - The name of the score table.
+///
+pub async fn check_kg_score_table(
+    pool: &PgPool,
+    table_prefix: &str,
+) -> Result<String, ValidationError> {
+    let status = get_kg_score_table_status(pool, table_prefix).await?;
+    if !status.exists {
+        return Err(ValidationError::new(
+            &status.message.unwrap_or_default(),
+            vec![],
+        ));
+    }
+
+    if !status.is_fresh {
+        warn!(
+            "The score table {} may be stale: {}",
+            status.table_name,
+            status.message.unwrap_or_default()
+        );
+    }
+
+    Ok(status.table_name)
+}
+
+/// Drop the kg score table and the top relations table which is derived from it.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `table_prefix` - Optional prefix for the table name. If not provided, the default model name will be used.
+///
+/// # Returns
+/// `Result<(), ValidationError>` - The result of dropping the score table.
+///
+pub async fn drop_kg_score_table(
+    pool: &PgPool,
+    table_prefix: Option<&str>,
+) -> Result<(), ValidationError> {
+    let table_prefix = table_prefix.unwrap_or(DEFAULT_MODEL_NAME);
+    let score_table = get_kg_score_table_name(table_prefix);
+    let sql_str = format!(
+        "DROP TABLE IF EXISTS {top_relations_table}, {score_table};",
+        top_relations_table = get_top_relations_table_name(table_prefix),
+        score_table = score_table
+    );
+
+    match sqlx::query(&sql_str).execute(pool).await {
+        Ok(_) => {
+            info!("The score table {} is dropped successfully", score_table);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to drop the score table: {}", e);
+            Err(ValidationError::new(
+                &format!("Failed to drop the score table: {}", e),
+                vec![],
+            ))
+        }
+    }
+}
+
+// The default number of the top scored relations per node in the top relations table.
+pub const DEFAULT_TOP_RELATIONS_PER_NODE: usize = 100;
+
+// The table comment is used to record how many relations per node are kept, such as "top_n=100".
+const TOP_RELATIONS_COMMENT_PREFIX: &str = "top_n=";
+
+/// Generate the table name for the top relations table.
+///
+/// # Arguments
+/// * `table_prefix` - The prefix of the table name, such as "biomedgps".
+///
+/// # Returns
+/// `String` - The table name for the top relations table, such as "biomedgps_top_relations".
+///
+/// # Example
+/// ```
+/// use biomedgps::model::init_db::get_top_relations_table_name;
+/// let table_name = get_top_relations_table_name("biomedgps");
+/// assert_eq!(table_name, "biomedgps_top_relations");
+/// ```
+///
+pub fn get_top_relations_table_name(table_prefix: &str) -> String {
+    format!("{}_top_relations", table_prefix)
+}
+
+/// Keep the top-N scored relations for each node from the kg score table. A relation is kept if it is in the top-N outgoing relations of its source node or in the top-N incoming relations of its target node.
+pub fn init_top_relations_sql(table_prefix: &str, top_n: usize) -> String {
+    format!(
+        r#"
+            WITH ranked_relations AS (
+                SELECT
+                    *,
+                    ROW_NUMBER() OVER (PARTITION BY source_id, source_type ORDER BY score DESC, id) AS source_rank,
+                    ROW_NUMBER() OVER (PARTITION BY target_id, target_type ORDER BY score DESC, id) AS target_rank
+                FROM {score_table}
+            )
+            SELECT
+                id,
+                source_id,
+                source_type,
+                target_id,
+                target_type,
+                relation_type,
+                formatted_relation_type,
+                key_sentence,
+                resource,
+                dataset,
+                pmids,
+                score
+            INTO TABLE {top_relations_table}
+            FROM ranked_relations
+            WHERE source_rank <= {top_n} OR target_rank <= {top_n};
+        "#,
+        score_table = get_kg_score_table_name(table_prefix),
+        top_relations_table = get_top_relations_table_name(table_prefix),
+        top_n = top_n
+    )
+}
+
+/// Materialize the top-N scored relations per node into a compact table. It's used as a fast path by the fetch_linked_nodes function when the query only filters the relations by node ids. You must rebuild it after the kg score table is refreshed, because the create_kg_score_table function drops it.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `table_prefix` - Optional prefix for the table name. If not provided, the default model name will be used.
+/// * `top_n` - The number of the relations to keep for each node.
+///
+/// # Returns
+/// `Result<(), ValidationError>` - The result of creating the top relations table.
+///
+pub async fn create_top_relations_table(
+    pool: &PgPool,
+    table_prefix: Option<&str>,
+    top_n: usize,
+) -> Result<(), ValidationError> {
+    let table_prefix = table_prefix.unwrap_or(DEFAULT_MODEL_NAME);
+    let top_relations_table = get_top_relations_table_name(table_prefix);
+
+    if top_n == 0 {
+        return Err(ValidationError::new(
+            "The number of the top relations per node should be greater than 0.",
+            vec![],
+        ));
+    }
+
+    let init_sql = init_top_relations_sql(table_prefix, top_n);
+    debug!("init_sql: {}", init_sql);
+
+    let sql_strs = vec![
+        format!("DROP TABLE IF EXISTS {};", top_relations_table),
+        init_sql,
+        format!(
+            "CREATE INDEX IF NOT EXISTS {table}_source_id_idx ON {table} (source_id);",
+            table = top_relations_table
+        ),
+        format!(
+            "CREATE INDEX IF NOT EXISTS {table}_target_id_idx ON {table} (target_id);",
+            table = top_relations_table
+        ),
+        format!(
+            "COMMENT ON TABLE {} IS '{}{}';",
+            top_relations_table, TOP_RELATIONS_COMMENT_PREFIX, top_n
+        ),
+    ];
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return Err(ValidationError::new(
+                &format!("Failed to start the transaction: {}", e),
+                vec![],
+            ))
+        }
+    };
+
+    for sql_str in sql_strs {
+        match sqlx::query(&sql_str).execute(&mut tx).await {
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to create the top relations table: {}", e);
+                return Err(ValidationError::new(
+                    &format!("Failed to create the top relations table: {}", e),
+                    vec![],
+                ));
+            }
+        }
+    }
+
+    match tx.commit().await {
+        Ok(_) => {
+            info!(
+                "The top relations table {} is created successfully",
+                top_relations_table
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to commit the transaction: {}", e);
+            return Err(ValidationError::new(
+                &format!("Failed to commit the transaction: {}", e),
+                vec![],
+            ));
+        }
+    }
+}
+
+/// Get the number of the relations per node which are kept in the top relations table.
+///
+/// # Returns
+/// `Option<usize>` - None if the table doesn't exist, such as it's invalidated by refreshing the kg score table.
+///
+pub async fn get_top_relations_size(pool: &PgPool, table_prefix: &str) -> Option<usize> {
+    let comment = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT obj_description(to_regclass($1), 'pg_class')",
+    )
+    .bind(get_top_relations_table_name(table_prefix))
+    .fetch_one(pool)
+    .await;
+
+    match comment {
+        Ok(Some(comment)) => match comment.strip_prefix(TOP_RELATIONS_COMMENT_PREFIX) {
+            Some(top_n) => top_n.parse::<usize>().ok(),
+            None => None,
+        },
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to get the size of the top relations table: {}", e);
+            None
+        }
+    }
+}
+
 /// Generate the attribute name for the score of the relation in the graph database.
 ///
 /// # Arguments
@@ -628,6 +1277,16 @@ mod tests {
         assert_eq!(table_name, "biomedgps_compound_disease_symptom_score");
     }
 
+    #[test]
+    fn test_parse_kg_score_table_comment() {
+        let comment = format_kg_score_table_comment(1706140800, 1000);
+        let (created_at, num_relations) = parse_kg_score_table_comment(&comment);
+        assert_eq!(created_at.map(|t| t.timestamp()), Some(1706140800));
+        assert_eq!(num_relations, Some(1000));
+
+        assert_eq!(parse_kg_score_table_comment("top_n=100"), (None, None));
+    }
+
     #[test]
     fn test_init_score_sql() {
         let table_prefix = "biomedgps";
@@ -647,6 +1306,8 @@ mod tests {
             created_at: DateTime::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
             datasets: vec!["STRING".to_string()],
             description: "The entity embedding trained by the TransE_l2 model".to_string(),
+            metric: "l2".to_string(),
+            score_percentiles: None,
         };
 
         let sql = init_score_sql(
@@ -685,6 +1346,22 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_get_inverse_relation_type() {
+        assert_eq!(
+            get_inverse_relation_type("STRING::BINDING::Gene:Gene"),
+            Some("STRING::BINDING::Gene:Gene".to_string())
+        );
+        assert_eq!(
+            get_inverse_relation_type("HSDN::has_symptom::Disease:Symptom"),
+            Some("HSDN::symptom_of::Symptom:Disease".to_string())
+        );
+        assert_eq!(
+            get_inverse_relation_type("DRUGBANK::treats::Compound:Disease"),
+            None
+        );
+    }
+
     #[test]
     fn test_get_kg_score_table_name() {
         let table_name = get_kg_score_table_name("biomedgps");
@@ -705,6 +1382,8 @@ mod tests {
             created_at: DateTime::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
             datasets: vec!["STRING".to_string()],
             description: "The entity embedding trained by the TransE_l2 model".to_string(),
+            metric: "l2".to_string(),
+            score_percentiles: None,
         };
         let sql = init_kg_score_sql(Some(table_prefix), gamma, &embedding_metadata);
         println!("sql: {}", sql);
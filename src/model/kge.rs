@@ -2,7 +2,11 @@ use super::core::{
     CheckData, DEFAULT_DATASET_NAME, DEFAULT_MAX_LENGTH, DEFAULT_MIN_LENGTH, ENTITY_ID_REGEX,
     ENTITY_LABEL_REGEX, ENTITY_NAME_MAX_LENGTH,
 };
-use super::util::{drop_table, parse_csv_error, read_annotation_file, ValidationError};
+use super::graph::{Graph, DEFAULT_KGE_GAMMA};
+use super::init_db::get_kg_score_table_status;
+use super::util::{
+    drop_table, open_data_file, parse_csv_error, read_annotation_file, ValidationError,
+};
 use crate::pgvector::Vector;
 use crate::query_builder::sql_builder::ComposeQuery;
 use anyhow::Ok as AnyOk;
@@ -31,6 +35,9 @@ pub const DEFAULT_MODEL_TYPES: [&str; 8] = [
     "ComplEx",
 ];
 
+/// The distance metrics which are supported by the pgvector extension. The translational models (such as TransE) assume the l2 distance and the semantic matching models (such as DistMult, ComplEx) assume the inner product.
+pub const DEFAULT_EMBEDDING_METRICS: [&str; 3] = ["l2", "inner_product", "cosine"];
+
 lazy_static! {
     static ref KGE_MODELS: Mutex<HashMap<String, EmbeddingMetadata>> = Mutex::new(HashMap::new());
 }
@@ -69,6 +76,163 @@ pub fn get_relation_emb_table_name(table_name: &str) -> String {
     format!("{}_relation_embedding", table_name)
 }
 
+/// Get the default distance metric of the model type, it's used when the metric is not declared.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::get_default_metric;
+///
+/// assert_eq!(get_default_metric("TransE_l2"), "l2");
+/// assert_eq!(get_default_metric("DistMult"), "inner_product");
+/// ```
+pub fn get_default_metric(model_type: &str) -> &'static str {
+    match model_type {
+        "DistMult" | "ComplEx" => "inner_product",
+        _ => "l2",
+    }
+}
+
+/// Get the pgvector operator of the distance metric. All operators return a distance, so the smaller the closer. NOTICE: `<#>` returns the negative inner product.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::get_metric_operator;
+///
+/// assert_eq!(get_metric_operator("l2"), "<->");
+/// assert_eq!(get_metric_operator("inner_product"), "<#>");
+/// assert_eq!(get_metric_operator("cosine"), "<=>");
+/// ```
+pub fn get_metric_operator(metric: &str) -> &'static str {
+    match metric {
+        "inner_product" => "<#>",
+        "cosine" => "<=>",
+        _ => "<->",
+    }
+}
+
+/// How many triples are scored to calibrate a model at import time.
+pub const CALIBRATION_NUM_SAMPLES: usize = 10000;
+
+/// How many relation types are sampled to calibrate a model, the triples are split evenly between them.
+pub const CALIBRATION_NUM_RELATION_TYPES: usize = 20;
+
+/// Compute the 0th, 1st, ..., 100th percentiles of the sampled scores by the linear interpolation. It returns an empty vector if there are no scores.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::compute_score_percentiles;
+///
+/// let percentiles = compute_score_percentiles(&vec![3.0, 1.0, 2.0]);
+/// assert_eq!(percentiles.len(), 101);
+/// assert_eq!(percentiles[0], 1.0);
+/// assert_eq!(percentiles[50], 2.0);
+/// assert_eq!(percentiles[100], 3.0);
+/// assert!(compute_score_percentiles(&vec![]).is_empty());
+/// ```
+pub fn compute_score_percentiles(scores: &Vec<f64>) -> Vec<f64> {
+    let mut scores = scores
+        .iter()
+        .cloned()
+        .filter(|score| score.is_finite())
+        .collect::<Vec<f64>>();
+    if scores.is_empty() {
+        return vec![];
+    }
+    scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    (0..=100)
+        .map(|percent| {
+            let rank = percent as f64 / 100.0 * (scores.len() - 1) as f64;
+            let lower = rank.floor() as usize;
+            let upper = rank.ceil() as usize;
+            scores[lower] + (scores[upper] - scores[lower]) * (rank - lower as f64)
+        })
+        .collect()
+}
+
+/// Map a raw score to its percentile (0-100) among the sampled scores of the model, it's interpolated between the two nearest percentiles. It returns None if the model is not calibrated.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::{compute_score_percentiles, get_score_percentile};
+///
+/// let percentiles = compute_score_percentiles(&(0..=100).map(|i| i as f64 / 10.0).collect());
+/// assert_eq!(get_score_percentile(&percentiles, 5.0), Some(50.0));
+/// assert_eq!(get_score_percentile(&percentiles, 5.05), Some(50.5));
+/// assert_eq!(get_score_percentile(&percentiles, 20.0), Some(100.0));
+/// assert_eq!(get_score_percentile(&percentiles, -1.0), Some(0.0));
+/// assert_eq!(get_score_percentile(&vec![], 1.0), None);
+/// ```
+pub fn get_score_percentile(percentiles: &Vec<f64>, score: f64) -> Option<f64> {
+    if percentiles.len() < 2 {
+        return None;
+    }
+
+    let last = percentiles.len() - 1;
+    if score <= percentiles[0] {
+        return Some(0.0);
+    }
+    if score >= percentiles[last] {
+        return Some(100.0);
+    }
+
+    // The first percentile which is not less than the score, the score is between it and the previous one.
+    let upper = percentiles.partition_point(|p| *p < score);
+    let lower = upper - 1;
+    let fraction = if percentiles[upper] > percentiles[lower] {
+        (score - percentiles[lower]) / (percentiles[upper] - percentiles[lower])
+    } else {
+        0.0
+    };
+    let percentile = (lower as f64 + fraction) * 100.0 / last as f64;
+
+    Some((percentile * 100.0).round() / 100.0)
+}
+
+/// Normalize the embedding for the distance metric before importing it. The embedding is scaled to the unit length for the cosine metric, so the inner product of two embeddings is the same as their cosine similarity. The embedding is kept as it is for the other metrics.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::normalize_embedding;
+///
+/// assert_eq!(normalize_embedding("cosine", &vec![3.0, 4.0]), vec![0.6, 0.8]);
+/// assert_eq!(normalize_embedding("l2", &vec![3.0, 4.0]), vec![3.0, 4.0]);
+/// assert_eq!(normalize_embedding("cosine", &vec![0.0, 0.0]), vec![0.0, 0.0]);
+/// ```
+pub fn normalize_embedding(metric: &str, embedding: &Vec<f32>) -> Vec<f32> {
+    if metric != "cosine" {
+        return embedding.clone();
+    }
+
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        embedding.clone()
+    } else {
+        embedding.iter().map(|x| x / norm).collect()
+    }
+}
+
+/// Get the declared distance metric of the embedding tables, the l2 distance is used if the tables are not registered in the embedding metadata table.
+pub async fn get_declared_metric(pool: &sqlx::PgPool, table_name: &str) -> String {
+    let sql_str =
+        "SELECT metric FROM biomedgps_embedding_metadata WHERE table_name = $1 ORDER BY id LIMIT 1";
+    match sqlx::query_as::<_, (String,)>(sql_str)
+        .bind(table_name)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(record)) => record.0,
+        Ok(None) => "l2".to_string(),
+        Err(e) => {
+            warn!(
+                "Failed to get the metric of the embedding table {}, use the l2 distance instead: {}",
+                table_name, e
+            );
+            "l2".to_string()
+        }
+    }
+}
+
 pub async fn check_default_model_is_valid(pool: &sqlx::PgPool) -> Result<(), ValidationError> {
     let table_names = vec![
         get_entity_emb_table_name(DEFAULT_MODEL_NAME),
@@ -136,6 +300,8 @@ pub async fn add_default_model(pool: &sqlx::PgPool) -> Result<(), anyhow::Error>
                 created_at: Utc::now(),
                 dimension: 400,
                 metadata: None,
+                metric: get_default_metric("TransE").to_string(),
+                score_percentiles: None,
             };
             match &metadata.insert(pool).await {
                 Ok(_) => {
@@ -210,6 +376,118 @@ pub fn get_embedding_metadata(key: &str) -> Option<EmbeddingMetadata> {
     }
 }
 
+/// List the registered models which are loaded by `init_kge_models`, their model names are the valid `model_name` values of the prediction endpoints.
+pub fn list_registered_models() -> Vec<EmbeddingMetadata> {
+    let kge_models = KGE_MODELS.lock().unwrap();
+    // Each model is registered by both the model name and the table name.
+    let mut models = kge_models
+        .values()
+        .map(|metadata| (metadata.id, metadata.clone()))
+        .collect::<HashMap<i64, EmbeddingMetadata>>()
+        .into_values()
+        .collect::<Vec<EmbeddingMetadata>>();
+    models.sort_by_key(|metadata| metadata.id);
+    models
+}
+
+/// Resolve a model name (or a table name) to the table prefix of its embedding and score tables, so the users can choose the model which computes the scores. None means the default model.
+///
+/// The model must be registered in the embedding metadata table, otherwise an error with all registered models is returned.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::kge::{get_model_table_prefix, DEFAULT_MODEL_NAME};
+///
+/// assert_eq!(get_model_table_prefix(None).unwrap(), DEFAULT_MODEL_NAME);
+/// assert!(get_model_table_prefix(Some("not_registered_model")).is_err());
+/// ```
+pub fn get_model_table_prefix(model_name: Option<&str>) -> Result<String, ValidationError> {
+    let model_name = match model_name {
+        Some(model_name) if !model_name.trim().is_empty() => model_name.trim(),
+        _ => return Ok(DEFAULT_MODEL_NAME.to_string()),
+    };
+
+    match get_embedding_metadata(model_name) {
+        Some(metadata) => Ok(metadata.table_name),
+        None => {
+            let registered_models = list_registered_models()
+                .into_iter()
+                .map(|metadata| metadata.model_name)
+                .collect::<Vec<String>>();
+
+            Err(ValidationError::new(
+                &format!(
+                    "The model {} is not registered in the embedding metadata table, please choose one of [{}].",
+                    model_name,
+                    registered_models.join(", ")
+                ),
+                vec![],
+            ))
+        }
+    }
+}
+
+/// A model with the status of its kg score table, it's used for listing the models.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::FromRow, Object)]
+pub struct KgeModelStatus {
+    pub table_name: String,
+    pub model_name: String,
+    pub model_type: String,
+    pub description: String,
+    pub datasets: Vec<String>,
+    pub dimension: i32,
+    pub metric: String,
+    pub created_at: DateTime<Utc>,
+    pub score_table_name: String,
+    pub score_table_exists: bool,
+    pub score_table_is_fresh: bool,
+    pub score_table_created_at: Option<DateTime<Utc>>,
+    // Why the score table is missing or stale, such as "The score table biomedgps_relation_with_score doesn't exist, ...".
+    pub score_table_message: Option<String>,
+}
+
+impl KgeModelStatus {
+    /// List all models in the database with the status of their kg score tables.
+    pub async fn list(pool: &sqlx::PgPool) -> Result<Vec<KgeModelStatus>, ValidationError> {
+        let sql_str = "SELECT * FROM biomedgps_embedding_metadata ORDER BY id";
+        let records = match sqlx::query_as::<_, EmbeddingMetadata>(sql_str)
+            .fetch_all(pool)
+            .await
+        {
+            Ok(records) => records,
+            Err(e) => {
+                return Err(ValidationError::new(
+                    &format!("Failed to fetch the models: {}", e),
+                    vec![],
+                ))
+            }
+        };
+
+        let mut models = vec![];
+        for record in records {
+            // The score table is shared by the models with the same table name.
+            let status = get_kg_score_table_status(pool, &record.table_name).await?;
+            models.push(KgeModelStatus {
+                table_name: record.table_name,
+                model_name: record.model_name,
+                model_type: record.model_type,
+                description: record.description,
+                datasets: record.datasets,
+                dimension: record.dimension,
+                metric: record.metric,
+                created_at: record.created_at,
+                score_table_name: status.table_name,
+                score_table_exists: status.exists,
+                score_table_is_fresh: status.is_fresh,
+                score_table_created_at: status.created_at,
+                score_table_message: status.message,
+            });
+        }
+
+        Ok(models)
+    }
+}
+
 fn text2vector<'de, D>(deserializer: D) -> Result<Vector, D::Error>
 where
     D: Deserializer<'de>,
@@ -365,6 +643,17 @@ pub struct EmbeddingMetadata {
     pub dimension: i32, // The dimension of embedding, such as 400, 768, 1024, etc.
 
     pub metadata: Option<String>, // The metadata of embedding, such as hyperparameters, etc.
+
+    // The distance metric of the embeddings, such as l2, inner_product and cosine. It's detected by the model type if it's not declared.
+    #[serde(default)]
+    #[oai(default)]
+    pub metric: String,
+
+    // The 0th, 1st, ..., 100th percentiles of the sampled scores, they map the raw scores of the model to the percentiles, so the scores of the different models are comparable. They are sampled when the model is imported, it's None if the model is not calibrated.
+    #[serde(default, skip_deserializing)]
+    #[sqlx(default)]
+    #[oai(read_only, skip_serializing_if_is_none)]
+    pub score_percentiles: Option<Vec<f64>>,
 }
 
 impl EmbeddingMetadata {
@@ -385,6 +674,8 @@ impl EmbeddingMetadata {
     ///     created_at: Utc::now(),
     ///     dimension: 400,
     ///     metadata: None,
+    ///     metric: "l2".to_string(),
+    ///     score_percentiles: None,
     /// };
     ///
     /// let score_function_name = metadata.detect_score_fn();
@@ -510,6 +801,7 @@ impl EmbeddingMetadata {
                 .collect::<Vec<&str>>(),
             self.dimension as usize,
             self.metadata.clone(),
+            &self.metric,
         )
         .await;
     }
@@ -525,6 +817,7 @@ impl EmbeddingMetadata {
     /// * `datasets` - The datasets of embedding metadata.
     /// * `dimension` - The dimension of embedding metadata.
     /// * `metadata` - The metadata of embedding metadata.
+    /// * `metric` - The distance metric of the embeddings, such as l2, inner_product and cosine. The default metric of the model type is used if it's empty.
     ///
     /// # Returns
     /// * `Result<EmbeddingMetadata, Box<dyn Error>>` - The embedding metadata.
@@ -537,7 +830,24 @@ impl EmbeddingMetadata {
         datasets: &Vec<&str>,
         dimension: usize,
         metadata: Option<String>,
+        metric: &str,
     ) -> Result<EmbeddingMetadata, Box<dyn Error>> {
+        let metric = if metric.is_empty() {
+            get_default_metric(model_type)
+        } else {
+            metric
+        };
+
+        if !DEFAULT_EMBEDDING_METRICS.contains(&metric) {
+            return Err(Box::new(ValidationError::new(
+                &format!(
+                    "Invalid metric: {}, the valid metrics are {:?}",
+                    metric, DEFAULT_EMBEDDING_METRICS
+                ),
+                vec![],
+            )));
+        }
+
         // Begin to transaction
         let mut tx = pool.begin().await?;
 
@@ -564,7 +874,7 @@ impl EmbeddingMetadata {
             None => "".to_string(),
         };
 
-        let sql_str = "INSERT INTO biomedgps_embedding_metadata (table_name, model_name, model_type, description, datasets, dimension, metadata) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id, created_at";
+        let sql_str = "INSERT INTO biomedgps_embedding_metadata (table_name, model_name, model_type, description, datasets, dimension, metadata, metric) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id, created_at";
 
         let record = sqlx::query_as::<_, (i64, DateTime<Utc>)>(&sql_str)
             .bind(table_name)
@@ -574,6 +884,7 @@ impl EmbeddingMetadata {
             .bind(datasets)
             .bind(dimension as i32)
             .bind(&m)
+            .bind(metric)
             .fetch_one(&mut tx)
             .await?;
 
@@ -641,9 +952,188 @@ impl EmbeddingMetadata {
             created_at: record.1,
             dimension: dimension as i32,
             metadata: Some(m.clone()),
+            metric: metric.to_string(),
+            score_percentiles: None,
         })
     }
 
+    /// Map a raw score of the model to its percentile (0-100), so the UI can use the same cutoff for all models. It returns None if the model is not calibrated.
+    pub fn get_score_percentile(&self, score: f64) -> Option<f64> {
+        self.score_percentiles
+            .as_ref()
+            .and_then(|percentiles| get_score_percentile(percentiles, score))
+    }
+
+    /// Calibrate the scores of the model and save the percentiles. The random heads and tails are scored with the sampled relation types, the heads and the tails match the entity types of the relation types like the predictions do. It should be called after the embeddings are imported.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `num_samples` - How many triples are scored, such as CALIBRATION_NUM_SAMPLES.
+    ///
+    /// # Returns
+    /// * `Result<Vec<f64>, anyhow::Error>` - The percentiles of the sampled scores.
+    pub async fn calibrate_scores(
+        &self,
+        pool: &sqlx::PgPool,
+        num_samples: usize,
+    ) -> Result<Vec<f64>, anyhow::Error> {
+        let entity_table = get_entity_emb_table_name(&self.table_name);
+        let relation_table = get_relation_emb_table_name(&self.table_name);
+
+        let sql_str = format!(
+            "SELECT relation_type FROM {} ORDER BY random() LIMIT $1",
+            relation_table
+        );
+        let relation_types = sqlx::query_scalar::<_, String>(&sql_str)
+            .bind(CALIBRATION_NUM_RELATION_TYPES as i64)
+            .fetch_all(pool)
+            .await?;
+        if relation_types.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No relation embeddings are found in {}.",
+                relation_table
+            ));
+        }
+
+        // The heads and the tails are crossed, so the square root of the samples per relation type are sampled for each side.
+        let num_entities = ((num_samples / relation_types.len()).max(1) as f64)
+            .sqrt()
+            .ceil() as i64;
+        let sql_str = format!(
+            "SELECT {score_function_name}(
+                    vector_to_float4(h.embedding, {dimension}, false),
+                    vector_to_float4(r.embedding, {dimension}, false),
+                    vector_to_float4(t.embedding, {dimension}, false),
+                    {gamma},
+                    true,
+                    false
+                )::FLOAT8 AS score
+            FROM
+                (SELECT embedding FROM {entity_table} WHERE entity_type = $1 ORDER BY random() LIMIT $3) h,
+                (SELECT embedding FROM {relation_table} WHERE relation_type = $4) r,
+                (SELECT embedding FROM {entity_table} WHERE entity_type = $2 ORDER BY random() LIMIT $3) t",
+            score_function_name = self.detect_score_fn(),
+            dimension = self.dimension,
+            gamma = DEFAULT_KGE_GAMMA,
+            entity_table = entity_table,
+            relation_table = relation_table,
+        );
+
+        let mut scores: Vec<f64> = vec![];
+        for relation_type in &relation_types {
+            let (source_type, target_type) = match Graph::parse_relation_type(relation_type) {
+                Ok(types) => types,
+                Err(e) => {
+                    warn!(
+                        "Skip the relation type {} when calibrating the scores: {}",
+                        relation_type, e
+                    );
+                    continue;
+                }
+            };
+
+            let mut sampled = sqlx::query_scalar::<_, Option<f64>>(&sql_str)
+                .bind(&source_type)
+                .bind(&target_type)
+                .bind(num_entities)
+                .bind(relation_type)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<f64>>();
+            scores.append(&mut sampled);
+        }
+
+        let percentiles = compute_score_percentiles(&scores);
+        if percentiles.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No scores are sampled, the entity embeddings may not match the relation types."
+            ));
+        }
+        info!(
+            "Calibrate the scores of the model {} with {} sampled triples.",
+            self.model_name,
+            scores.len()
+        );
+
+        let sql_str = "UPDATE biomedgps_embedding_metadata SET score_percentiles = $1 WHERE table_name = $2 AND model_name = $3";
+        sqlx::query(sql_str)
+            .bind(&percentiles)
+            .bind(&self.table_name)
+            .bind(&self.model_name)
+            .execute(pool)
+            .await?;
+
+        AnyOk(percentiles)
+    }
+
+    /// Score the triples by the model with the same score function as the predictions, such as the edges of a path.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `triples` - The (source_type, source_id, relation_type, target_type, target_id) of the triples.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Option<f64>>, anyhow::Error>` - The scores in the same order as the triples, None if the model has no embedding of an entity or the relation type.
+    pub async fn score_triples(
+        &self,
+        pool: &sqlx::PgPool,
+        triples: &Vec<(String, String, String, String, String)>,
+    ) -> Result<Vec<Option<f64>>, anyhow::Error> {
+        let mut scores = vec![None; triples.len()];
+        if triples.is_empty() {
+            return AnyOk(scores);
+        }
+
+        let sql_str = format!(
+            "SELECT
+                t.idx,
+                {score_function_name}(
+                    vector_to_float4(h.embedding, {dimension}, false),
+                    vector_to_float4(r.embedding, {dimension}, false),
+                    vector_to_float4(e.embedding, {dimension}, false),
+                    {gamma},
+                    true,
+                    false
+                )::FLOAT8 AS score
+            FROM
+                UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[])
+                    WITH ORDINALITY AS t(source_type, source_id, relation_type, target_type, target_id, idx)
+                JOIN {entity_table} h ON h.entity_type = t.source_type AND h.entity_id = t.source_id
+                JOIN {relation_table} r ON r.relation_type = t.relation_type
+                JOIN {entity_table} e ON e.entity_type = t.target_type AND e.entity_id = t.target_id",
+            score_function_name = self.detect_score_fn(),
+            dimension = self.dimension,
+            gamma = DEFAULT_KGE_GAMMA,
+            entity_table = get_entity_emb_table_name(&self.table_name),
+            relation_table = get_relation_emb_table_name(&self.table_name),
+        );
+
+        let source_types = triples.iter().map(|t| t.0.clone()).collect::<Vec<String>>();
+        let source_ids = triples.iter().map(|t| t.1.clone()).collect::<Vec<String>>();
+        let relation_types = triples.iter().map(|t| t.2.clone()).collect::<Vec<String>>();
+        let target_types = triples.iter().map(|t| t.3.clone()).collect::<Vec<String>>();
+        let target_ids = triples.iter().map(|t| t.4.clone()).collect::<Vec<String>>();
+        let rows = sqlx::query_as::<_, (i64, Option<f64>)>(&sql_str)
+            .bind(&source_types)
+            .bind(&source_ids)
+            .bind(&relation_types)
+            .bind(&target_types)
+            .bind(&target_ids)
+            .fetch_all(pool)
+            .await?;
+
+        // The ordinality starts from 1.
+        for (idx, score) in rows {
+            if let Some(slot) = scores.get_mut((idx - 1) as usize) {
+                *slot = score;
+            }
+        }
+
+        AnyOk(scores)
+    }
+
     /// Get the embedding metadata by the id.
     ///
     /// # Arguments
@@ -719,15 +1209,9 @@ impl EmbeddingMetadata {
         };
 
         // Build the CSV reader
-        let mut reader = match csv::ReaderBuilder::new()
+        let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Box::new(e));
-            }
-        };
+            .from_reader(open_data_file(filepath)?);
 
         let mut line_number = 1;
         for result in reader.deserialize() {
@@ -742,7 +1226,11 @@ impl EmbeddingMetadata {
             record.id = line_number;
             line_number += 1;
 
-            let sql_str = "INSERT INTO biomedgps_embedding_metadata (id, table_name, model_name, model_type, description, datasets, dimension, metadata) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)";
+            if record.metric.is_empty() {
+                record.metric = get_default_metric(&record.model_type).to_string();
+            }
+
+            let sql_str = "INSERT INTO biomedgps_embedding_metadata (id, table_name, model_name, model_type, description, datasets, dimension, metadata, metric) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
 
             let query = sqlx::query(&sql_str)
                 .bind(record.id)
@@ -752,7 +1240,8 @@ impl EmbeddingMetadata {
                 .bind(record.description)
                 .bind(record.datasets)
                 .bind(record.dimension)
-                .bind(record.metadata);
+                .bind(record.metadata)
+                .bind(record.metric);
 
             match query.execute(pool).await {
                 Ok(_) => {}
@@ -785,6 +1274,7 @@ impl CheckData for EmbeddingMetadata {
             "datasets".to_string(),
             "dimension".to_string(),
             "metadata".to_string(),
+            "metric".to_string(),
         ]
     }
 }
@@ -856,25 +1346,18 @@ impl EntityEmbedding {
         table_name: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
         let mut tx = pool.begin().await.unwrap();
-        let real_table_name = match table_name {
-            Some(t) => get_entity_emb_table_name(t),
-            None => get_entity_emb_table_name(DEFAULT_MODEL_NAME),
-        };
+        let table_name = table_name.unwrap_or(DEFAULT_MODEL_NAME);
+        let real_table_name = get_entity_emb_table_name(table_name);
+        let metric = get_declared_metric(pool, table_name).await;
 
         if drop {
             drop_table(&pool, &real_table_name).await;
         };
 
         // Build the CSV reader
-        let mut reader = match csv::ReaderBuilder::new()
+        let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Box::new(e));
-            }
-        };
+            .from_reader(open_data_file(filepath)?);
 
         let mut line_number = 1;
         for result in reader.deserialize() {
@@ -897,7 +1380,10 @@ impl EntityEmbedding {
                 .bind(record.entity_id)
                 .bind(record.entity_type)
                 .bind(record.entity_name)
-                .bind(record.embedding)
+                .bind(Vector::from(normalize_embedding(
+                    &metric,
+                    &record.embedding.to_vec(),
+                )))
                 .execute(&mut tx)
                 .await;
 
@@ -915,6 +1401,73 @@ impl EntityEmbedding {
     }
 }
 
+/// A similar entity of the query entity, the distance is computed by the declared metric of the model, so the smaller the closer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::FromRow, Object)]
+pub struct SimilarEntity {
+    pub entity_id: String,
+    pub entity_type: String,
+    pub entity_name: String,
+    pub distance: f64,
+}
+
+impl EntityEmbedding {
+    /// Format the sql for fetching the similar entities by the pgvector operator of the declared metric. The arguments are the entity id, the entity type and the topk.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::kge::EntityEmbedding;
+    ///
+    /// let sql_str = EntityEmbedding::format_similarity_sql("biomedgps", "cosine");
+    /// assert!(sql_str.contains("ee.embedding <=> q.embedding"));
+    /// assert!(sql_str.contains("FROM biomedgps_entity_embedding ee"));
+    /// ```
+    pub fn format_similarity_sql(table_name: &str, metric: &str) -> String {
+        let real_table_name = get_entity_emb_table_name(table_name);
+        let operator = get_metric_operator(metric);
+
+        format!(
+            "SELECT ee.entity_id, ee.entity_type, ee.entity_name, (ee.embedding {operator} q.embedding)::FLOAT8 AS distance
+             FROM {table} ee, (SELECT embedding FROM {table} WHERE entity_id = $1 AND entity_type = $2) q
+             WHERE NOT (ee.entity_id = $1 AND ee.entity_type = $2)
+             ORDER BY ee.embedding {operator} q.embedding
+             LIMIT $3",
+            operator = operator,
+            table = real_table_name
+        )
+    }
+
+    /// Fetch the most similar entities of an entity by the embeddings of the model.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool.
+    /// * `metadata` - The embedding metadata of the model, the declared metric decides the pgvector operator.
+    /// * `entity_id` - The entity id, such as ENTREZ:6747.
+    /// * `entity_type` - The entity type, such as Gene.
+    /// * `topk` - The number of similar entities.
+    ///
+    /// # Returns
+    /// * `Result<Vec<SimilarEntity>, anyhow::Error>` - The similar entities ordered by the distance.
+    pub async fn fetch_similar_entities(
+        pool: &sqlx::PgPool,
+        metadata: &EmbeddingMetadata,
+        entity_id: &str,
+        entity_type: &str,
+        topk: u64,
+    ) -> Result<Vec<SimilarEntity>, anyhow::Error> {
+        let sql_str = Self::format_similarity_sql(&metadata.table_name, &metadata.metric);
+        debug!("sql_str: {}", sql_str);
+
+        let records = sqlx::query_as::<_, SimilarEntity>(&sql_str)
+            .bind(entity_id)
+            .bind(entity_type)
+            .bind(topk as i64)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(records)
+    }
+}
+
 impl CheckData for EntityEmbedding {
     fn check_csv_is_valid(filepath: &PathBuf) -> Vec<Box<dyn Error>> {
         Self::check_csv_is_valid_default::<EntityEmbedding>(filepath)
@@ -958,10 +1511,9 @@ impl LegacyRelationEmbedding {
         table_name: Option<&str>,
         delimiter: u8,
     ) -> Result<(), Box<dyn Error>> {
-        let real_table_name = match table_name {
-            Some(t) => get_relation_emb_table_name(t),
-            None => get_relation_emb_table_name(DEFAULT_MODEL_NAME),
-        };
+        let table_name = table_name.unwrap_or(DEFAULT_MODEL_NAME);
+        let real_table_name = get_relation_emb_table_name(table_name);
+        let metric = get_declared_metric(pool, table_name).await;
 
         if drop {
             drop_table(&pool, &real_table_name).await;
@@ -975,15 +1527,9 @@ impl LegacyRelationEmbedding {
             },
         };
 
-        let mut reader = match csv::ReaderBuilder::new()
+        let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Box::new(e));
-            }
-        };
+            .from_reader(open_data_file(filepath)?);
 
         debug!(
             "The columns of the relation embedding csv file: {:?}",
@@ -1004,7 +1550,10 @@ impl LegacyRelationEmbedding {
                 .bind(line_num)
                 .bind(relation_type)
                 .bind(formatted_relation_type)
-                .bind(record.embedding);
+                .bind(Vector::from(normalize_embedding(
+                    &metric,
+                    &record.embedding.to_vec(),
+                )));
 
             match query.execute(pool).await {
                 Ok(_) => {}
@@ -1068,10 +1617,9 @@ impl RelationEmbedding {
         embedding: &Vec<f32>,
         table_name: Option<&str>,
     ) -> Result<RelationEmbedding, Box<dyn Error>> {
-        let real_table_name = match table_name {
-            Some(t) => get_relation_emb_table_name(t),
-            None => get_relation_emb_table_name(DEFAULT_MODEL_NAME),
-        };
+        let table_name = table_name.unwrap_or(DEFAULT_MODEL_NAME);
+        let real_table_name = get_relation_emb_table_name(table_name);
+        let metric = get_declared_metric(pool, table_name).await;
 
         let sql_str = format!(
             "SELECT COUNT(*) FROM {} WHERE relation_type = $1 AND formatted_relation_type = $2",
@@ -1093,10 +1641,11 @@ impl RelationEmbedding {
 
         let sql_str = format!("INSERT INTO {} (relation_type, formatted_relation_type, embedding) VALUES ($1, $2, $3) RETURNING embedding_id", real_table_name);
 
+        let embedding = normalize_embedding(&metric, embedding);
         let embedding_id = sqlx::query_as::<_, (i64,)>(&sql_str)
             .bind(relation_type)
             .bind(formatted_relation_type)
-            .bind(embedding)
+            .bind(&embedding)
             .fetch_one(pool)
             .await?;
 
@@ -1115,25 +1664,18 @@ impl RelationEmbedding {
         drop: bool,
         table_name: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let real_table_name = match table_name {
-            Some(t) => get_relation_emb_table_name(t),
-            None => get_relation_emb_table_name(DEFAULT_MODEL_NAME),
-        };
+        let table_name = table_name.unwrap_or(DEFAULT_MODEL_NAME);
+        let real_table_name = get_relation_emb_table_name(table_name);
+        let metric = get_declared_metric(pool, table_name).await;
 
         if drop {
             drop_table(&pool, &real_table_name).await;
         };
 
         // Build the CSV reader
-        let mut reader = match csv::ReaderBuilder::new()
+        let mut reader = csv::ReaderBuilder::new()
             .delimiter(delimiter)
-            .from_path(filepath)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Box::new(e));
-            }
-        };
+            .from_reader(open_data_file(filepath)?);
 
         let mut line_number = 1;
         for result in reader.deserialize() {
@@ -1154,7 +1696,10 @@ impl RelationEmbedding {
                 .bind(record.embedding_id)
                 .bind(record.relation_type)
                 .bind(record.formatted_relation_type)
-                .bind(record.embedding);
+                .bind(Vector::from(normalize_embedding(
+                    &metric,
+                    &record.embedding.to_vec(),
+                )));
 
             match query.execute(pool).await {
                 Ok(_) => {}
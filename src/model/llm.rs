@@ -1,6 +1,7 @@
 //! This module defines the data model for LLMs (Large Language Model), such as OpenAI GPT-3/4, etc. Also, it can use the LLM to answer the question.
 
-use super::core::{Entity, Relation};
+use super::core::{Entity, Publication, Relation};
+use super::util::get_ontology_relation_description;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
@@ -41,8 +42,8 @@ impl Context {
     ) -> Result<LlmResponse, anyhow::Error> {
         let resp = if self.entity.is_some() {
             let entity = self.entity.unwrap();
-            let mut llm_msg = LlmMessage::new(&prompt_template_id, entity, None).unwrap();
-            let answer = llm_msg.answer(&chatbot, pool).await.unwrap();
+            let mut llm_msg = LlmMessage::new(&prompt_template_id, entity, None)?;
+            let answer = llm_msg.answer(&chatbot, pool).await?;
             Ok(LlmResponse {
                 prompt: answer.prompt.to_owned(),
                 response: answer.message.to_owned(),
@@ -50,9 +51,8 @@ impl Context {
             })
         } else if self.expanded_relation.is_some() {
             let expanded_relation = self.expanded_relation.unwrap();
-            let mut llm_msg =
-                LlmMessage::new(&prompt_template_id, expanded_relation, None).unwrap();
-            let answer = llm_msg.answer(&chatbot, pool).await.unwrap();
+            let mut llm_msg = LlmMessage::new(&prompt_template_id, expanded_relation, None)?;
+            let answer = llm_msg.answer(&chatbot, pool).await?;
             Ok(LlmResponse {
                 prompt: answer.prompt.to_owned(),
                 response: answer.message.to_owned(),
@@ -61,16 +61,8 @@ impl Context {
         } else if self.symptoms_with_disease_ctx.is_some() {
             let symptoms_with_disease_ctx = self.symptoms_with_disease_ctx.unwrap();
             let mut llm_msg =
-                LlmMessage::new(&prompt_template_id, symptoms_with_disease_ctx, None).unwrap();
-            let answer = match llm_msg.answer(&chatbot, pool).await {
-                Ok(answer) => answer,
-                Err(e) => {
-                    return Err(anyhow::anyhow!(
-                        "Failed to answer the question: {}",
-                        e.to_string()
-                    ));
-                }
-            };
+                LlmMessage::new(&prompt_template_id, symptoms_with_disease_ctx, None)?;
+            let answer = llm_msg.answer(&chatbot, pool).await?;
             Ok(LlmResponse {
                 prompt: answer.prompt.to_owned(),
                 response: answer.message.to_owned(),
@@ -155,9 +147,280 @@ impl LlmContext for SymptomsWithDiseaseCtx {
     }
 }
 
+/// The RelationWithPublications is used to store the relation and the publications which are linked by the pmids of the relation. It is used to verify the relation by the LLM.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct RelationWithPublications {
+    pub relation: Relation,
+    pub source_name: String,
+    pub target_name: String,
+    pub publications: Vec<Publication>,
+}
+
+impl LlmContext for RelationWithPublications {
+    fn get_context(&self) -> Self {
+        self.clone()
+    }
+
+    fn render_prompt(&self, prompt_template: &str) -> String {
+        let abstracts = self
+            .publications
+            .iter()
+            .map(|p| {
+                format!(
+                    "PMID: {}\nTitle: {}\nAbstract: {}",
+                    p.pmid, p.title, p.abstract_text
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        let mut prompt = prompt_template.to_string();
+        prompt = prompt.replace("{{source_name}}", &self.source_name);
+        prompt = prompt.replace("{{source_id}}", &self.relation.source_id);
+        prompt = prompt.replace("{{source_type}}", &self.relation.source_type);
+        prompt = prompt.replace("{{relation_type}}", &self.relation.relation_type);
+        prompt = prompt.replace("{{target_name}}", &self.target_name);
+        prompt = prompt.replace("{{target_id}}", &self.relation.target_id);
+        prompt = prompt.replace("{{target_type}}", &self.relation.target_type);
+        prompt = prompt.replace("{{abstracts}}", &abstracts);
+        prompt
+    }
+}
+
+/// A step of a path, it's an edge between two adjacent nodes, such as Compound::DrugBank:DB01050 -> DRUGBANK::treats::Compound:Disease -> Disease::MESH:D010146. The target of a step must be the source of the next step.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct PathStep {
+    pub source_id: String,
+    pub source_type: String,
+    pub relation_type: String,
+    pub target_id: String,
+    pub target_type: String,
+    // The pmids of the edge separated by `|`, `,` or `;`, they are cited by the sentence of the step.
+    #[oai(skip_serializing_if_is_none)]
+    pub pmids: Option<String>,
+}
+
+impl PathStep {
+    pub fn get_pmids(&self) -> Vec<String> {
+        match &self.pmids {
+            Some(pmids) => pmids
+                .split(|c| c == '|' || c == ',' || c == ';')
+                .map(|pmid| pmid.trim().to_string())
+                .filter(|pmid| !pmid.is_empty())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Get the readable predicate of the relation type, such as "treats" for "DRUGBANK::treats::Compound:Disease".
+    pub fn get_predicate(&self) -> String {
+        let parts = self.relation_type.split("::").collect::<Vec<&str>>();
+        let predicate = if parts.len() >= 2 {
+            parts[1]
+        } else {
+            self.relation_type.as_str()
+        };
+
+        predicate.replace('_', " ").to_lowercase()
+    }
+}
+
+/// Format a step of a path as a sentence, such as "IBUPROFEN [Compound] treats Pain [Disease] (The description of the relation type.) [PMID: 123, 456]."
+///
+/// # Example
+/// ```
+/// use biomedgps::model::llm::{format_path_sentence, PathStep};
+///
+/// let step = PathStep {
+///     source_id: "DrugBank:DB01050".to_string(),
+///     source_type: "Compound".to_string(),
+///     relation_type: "DRUGBANK::treats::Compound:Disease".to_string(),
+///     target_id: "MESH:D010146".to_string(),
+///     target_type: "Disease".to_string(),
+///     pmids: Some("123|456".to_string()),
+/// };
+/// let sentence = format_path_sentence(&step, "IBUPROFEN", "Pain", None);
+/// assert_eq!(sentence, "IBUPROFEN [Compound] treats Pain [Disease] [PMID: 123, 456].");
+/// ```
+pub fn format_path_sentence(
+    step: &PathStep,
+    source_name: &str,
+    target_name: &str,
+    description: Option<&str>,
+) -> String {
+    let mut sentence = format!(
+        "{} [{}] {} {} [{}]",
+        source_name,
+        step.source_type,
+        step.get_predicate(),
+        target_name,
+        step.target_type
+    );
+
+    if let Some(description) = description {
+        sentence.push_str(&format!(" ({})", description.trim().trim_end_matches('.')));
+    }
+
+    let pmids = step.get_pmids();
+    if !pmids.is_empty() {
+        sentence.push_str(&format!(" [PMID: {}]", pmids.join(", ")));
+    }
+
+    sentence.push('.');
+    sentence
+}
+
+/// The natural-language narrative of a path, it's used for the reports. The narrative is composed of one sentence per step, and it can be polished by the LLM.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct PathNarrative {
+    pub sentences: Vec<String>,
+    pub narrative: String,
+    // The unique pmids of all steps in the order of the path.
+    pub citations: Vec<String>,
+    // The narrative which is rewritten by the LLM, only available when the polishing is requested.
+    #[oai(skip_serializing_if_is_none)]
+    pub polished_narrative: Option<String>,
+}
+
+impl LlmContext for PathNarrative {
+    fn get_context(&self) -> Self {
+        self.clone()
+    }
+
+    fn render_prompt(&self, prompt_template: &str) -> String {
+        let mut prompt = prompt_template.to_string();
+        prompt = prompt.replace("{{narrative}}", &self.narrative);
+        prompt = prompt.replace("{{citations}}", &self.citations.join(", "));
+        prompt
+    }
+}
+
+impl PathNarrative {
+    /// Check whether the steps are a chain, the target of each step must be the source of the next step.
+    pub fn validate_steps(steps: &Vec<PathStep>) -> Result<(), anyhow::Error> {
+        if steps.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The path should contain at least one step."
+            ));
+        }
+
+        for (index, pair) in steps.windows(2).enumerate() {
+            if pair[0].target_id != pair[1].source_id || pair[0].target_type != pair[1].source_type
+            {
+                return Err(anyhow::anyhow!(
+                    "The step {} ends with {}::{}, but the step {} starts with {}::{}, they are not a path.",
+                    index + 1,
+                    pair[0].target_type,
+                    pair[0].target_id,
+                    index + 2,
+                    pair[1].source_type,
+                    pair[1].source_id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Convert a path into sentences by the entity names and the descriptions of the relation types in the relation metadata table. The description from the bundled ontology mapping file is used if the relation type is not described in the relation metadata table.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `steps` - The steps of the path
+    ///
+    /// # Returns
+    /// * `Result<PathNarrative, anyhow::Error>` - The narrative without polishing.
+    pub async fn from_steps(
+        pool: &sqlx::PgPool,
+        steps: &Vec<PathStep>,
+    ) -> Result<PathNarrative, anyhow::Error> {
+        Self::validate_steps(steps)?;
+
+        let mut ids = vec![];
+        let mut labels = vec![];
+        for step in steps {
+            ids.push(step.source_id.clone());
+            labels.push(step.source_type.clone());
+            ids.push(step.target_id.clone());
+            labels.push(step.target_type.clone());
+        }
+
+        let sql_str = "SELECT id, label, name FROM biomedgps_entity WHERE (id, label) IN (SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[]))";
+        let names = sqlx::query_as::<_, (String, String, String)>(sql_str)
+            .bind(&ids)
+            .bind(&labels)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|(id, label, name)| ((id, label), name))
+            .collect::<HashMap<(String, String), String>>();
+
+        let relation_types = steps
+            .iter()
+            .map(|step| step.relation_type.clone())
+            .collect::<Vec<String>>();
+        let sql_str = "SELECT DISTINCT ON (relation_type) relation_type, description FROM biomedgps_relation_metadata WHERE relation_type = ANY($1) AND description IS NOT NULL AND description != ''";
+        let descriptions = sqlx::query_as::<_, (String, String)>(sql_str)
+            .bind(&relation_types)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect::<HashMap<String, String>>();
+
+        let get_name = |id: &str, label: &str| -> String {
+            match names.get(&(id.to_string(), label.to_string())) {
+                Some(name) => name.clone(),
+                None => id.to_string(),
+            }
+        };
+
+        let mut sentences = vec![];
+        let mut citations: Vec<String> = vec![];
+        for step in steps {
+            let description = match descriptions.get(&step.relation_type) {
+                Some(description) => Some(description.clone()),
+                None => get_ontology_relation_description(&step.relation_type),
+            };
+
+            sentences.push(format_path_sentence(
+                step,
+                &get_name(&step.source_id, &step.source_type),
+                &get_name(&step.target_id, &step.target_type),
+                description.as_deref(),
+            ));
+
+            for pmid in step.get_pmids() {
+                if !citations.contains(&pmid) {
+                    citations.push(pmid);
+                }
+            }
+        }
+
+        Ok(PathNarrative {
+            narrative: sentences.join(" "),
+            sentences,
+            citations,
+            polished_narrative: None,
+        })
+    }
+
+    /// Rewrite the narrative by the LLM, the citations must be kept in the polished narrative.
+    pub async fn polish(
+        &mut self,
+        chatbot: &ChatBot,
+        pool: Option<&sqlx::PgPool>,
+    ) -> Result<(), anyhow::Error> {
+        let mut llm_msg = LlmMessage::new("path_narrative", self.clone(), None)?;
+        let answer = llm_msg.answer(chatbot, pool).await?;
+        self.polished_narrative = Some(answer.message.clone());
+        Ok(())
+    }
+}
+
 lazy_static! {
     pub static ref UUID_REGEX: Regex =
         Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$").unwrap();
+    pub static ref MONTH_REGEX: Regex = Regex::new(r"^[0-9]{4}-(0[1-9]|1[0-2])$").unwrap();
     // Only for predicted edge
     pub static ref PROMPT_TEMPLATE: HashMap<&'static str, &'static str> = {
         let mut m = HashMap::new();
@@ -171,8 +434,202 @@ lazy_static! {
         // You need to prepare two fields: 1) subgraph: a json string; 2) disease_name: a string.
         m.insert("subgraph_symptoms_with_disease_ctx", "Knowledge Subgraph: {{subgraph}}\n\nKnowledge Subgraph Analysis Request:\n\nSubgraph Overview: I have compiled a Knowledge Subgraph dedicated to exploring the complex landscape surrounding {{disease_name}}, incorporating elements such as related symptoms, co-occurring diseases, therapeutic medications, and underlying genes/pathways. This Subgraph aims to elucidate:\n\nDisease-Symptom Associations: The linkages between symptoms of {{disease_name}} and their correlation with various diseases.\nMedication and Genetic/Pathway Connections: How medications align with and influence the genes or pathways associated with these diseases.\nMechanisms of Action: The specific pathways through which medications exert their therapeutic effects on these diseases.\nSymptom Detailing for {{disease_name}}: Specific symptoms related to {{disease_name}} include: {{symptoms}}.\nResearch Questions:\n\nIn light of the above, my queries are as follows:\n\nCritical Knowledge Identification: Within the context of {{disease_name}}, this Knowledge Subgraph houses an extensive array of entities and relationships fundamental to unraveling the disease's mechanisms and scrutinizing relevant treatment drugs. Leveraging your expertise, concentrate on the graph's relations pivotal to understanding {{disease_name}}'s mechanisms and its associated treatments. Identify and emphasize essential knowledge aspects that significantly aid in decoding the disease's pathology and therapeutic measures. This entails pinpointing vital biological pathways, gene-disease correlations, drug-target engagements, and any novel research insights that could reveal innovative therapeutic approaches. Your analysis is expected to prioritize data with a direct bearing on treatment efficacy and enhance our molecular-level understanding of the disease.\n\nEmerging Therapies: Are there any novel studies or predictive analyses indicating unrecognized medications that might benefit {{disease_name}} symptoms or the disease itself?\n\nSymptom-Disease Correlation: Which diseases are directly linked to {{disease_name}} symptoms, and what are the common treatments for these diseases?\n\nAction Mechanisms of Medications: How do these medications influence specific genes or pathways?\n\nGuidance for Response:\n\nPlease address the aforementioned inquiries based on the Knowledge Subgraph and your expertise. For each of the questions related to the Knowledge Subgraph and its implications for {{disease_name}}, it is imperative that you provide supporting literature. This literature must exclusively come from PubMed, which is a critical repository for reliable medical research findings. Your responses should not only incorporate insights derived from these studies but also include citations formatted according to standard academic practices. Specifically, citations should detail the authors, title, journal name, year of publication, and the PubMed ID (PMID) to facilitate easy verification and further reading.\n\nFor example, a proper citation format would be: Doe J, Smith A, Jones B. Title of the Article. Journal Name. Year;Volume(Issue):Page numbers. PMID: XXXXXXX.\nThis requirement is non-negotiable, ensuring that all information provided is backed by credible and accessible scientific evidence. Leveraging PubMed as a source is essential for maintaining the accuracy and reliability of the insights shared in your analysis.");
 
+        // You need to prepare a RelationWithPublications context for the following two templates. The answer must start with a verdict line and a rationale line, see parse_verdict for more details.
+        m.insert("edge_verification", "You are a biomedical curator. You need to verify the following claim only based on the abstracts I send you, don't use any other knowledge.\n\nClaim: {{source_name}}[{{source_id}}, {{source_type}}] -> {{relation_type}} -> {{target_name}}[{{target_id}}, {{target_type}}]\n\nAbstracts:\n{{abstracts}}\n\nPlease answer in the following format:\nVerdict: <SUPPORTED, REFUTED or INSUFFICIENT_EVIDENCE>\nRationale: <no more than 200 words, cite the PMIDs you used>");
+
+        // You need to prepare a PathNarrative context, the narrative is generated from a path in the knowledge graph.
+        m.insert("path_narrative", "You are a biomedical writer. The following sentences describe a path in a biomedical knowledge graph, each sentence is an edge between two entities and it might cite several PubMed articles.\n\nSentences: {{narrative}}\n\nPlease rewrite the sentences as a fluent paragraph for a report. Don't add any facts which are not in the sentences, keep the order of the entities and keep all citations in the format [PMID: XXXXXXX]. The cited PMIDs are: {{citations}}");
+
+        m.insert("treatment_edge_verification", "You are a biomedical curator. You need to verify the following treatment claim only based on the abstracts I send you, don't use any other knowledge. A treatment claim is high-stakes, so you should only answer SUPPORTED when at least one abstract reports that {{source_name}} is used to treat or improves {{target_name}} in human subjects, animal models or clinical trials. A mention of an association, a hypothesis, or an in silico prediction is not enough.\n\nClaim: {{source_name}}[{{source_id}}, {{source_type}}] -> {{relation_type}} -> {{target_name}}[{{target_id}}, {{target_type}}]\n\nAbstracts:\n{{abstracts}}\n\nPlease answer in the following format:\nVerdict: <SUPPORTED, REFUTED or INSUFFICIENT_EVIDENCE>\nRationale: <no more than 200 words, cite the PMIDs you used and the study type>");
+
         m
     };
+
+    // The prompt template for verifying a relation depends on the relation type. The key is the second part of the relation type (lowercase), such as "treats" in "DRUGBANK::treats::Compound:Disease". All other relation types will use the edge_verification template.
+    pub static ref VERIFICATION_PROMPT_TEMPLATE: HashMap<&'static str, &'static str> = {
+        let mut m = HashMap::new();
+        m.insert("treats", "treatment_edge_verification");
+        m.insert("treatment", "treatment_edge_verification");
+        m.insert("palliates", "treatment_edge_verification");
+        m
+    };
+}
+
+pub const DEFAULT_VERIFICATION_PROMPT_TEMPLATE: &str = "edge_verification";
+pub const VERIFICATION_VERDICTS: [&str; 3] = ["SUPPORTED", "REFUTED", "INSUFFICIENT_EVIDENCE"];
+
+/// Get the prompt template category for verifying a relation, such as "treatment_edge_verification" for "DRUGBANK::treats::Compound:Disease".
+pub fn get_verification_prompt_template(relation_type: &str) -> &'static str {
+    let parts = relation_type.split("::").collect::<Vec<&str>>();
+    if parts.len() < 2 {
+        return DEFAULT_VERIFICATION_PROMPT_TEMPLATE;
+    }
+
+    match VERIFICATION_PROMPT_TEMPLATE.get(parts[1].to_lowercase().as_str()) {
+        Some(prompt_template) => prompt_template,
+        None => DEFAULT_VERIFICATION_PROMPT_TEMPLATE,
+    }
+}
+
+/// Parse the verdict and the rationale from the answer of the LLM. If the LLM doesn't follow the format, the verdict will be INSUFFICIENT_EVIDENCE and the whole answer will be treated as the rationale.
+pub fn parse_verdict(message: &str) -> (String, String) {
+    let mut verdict = "INSUFFICIENT_EVIDENCE".to_string();
+    let mut rationale = message.trim().to_string();
+
+    for line in message.lines() {
+        let line = line.trim();
+        if line.to_lowercase().starts_with("verdict:") {
+            let value = line["verdict:".len()..]
+                .trim()
+                .trim_matches('*')
+                .trim()
+                .to_uppercase();
+            if let Some(v) = VERIFICATION_VERDICTS.iter().find(|v| value.starts_with(*v)) {
+                verdict = v.to_string();
+            }
+        } else if line.to_lowercase().starts_with("rationale:") {
+            let index = message.find(line).unwrap_or(0) + "rationale:".len();
+            rationale = message[index..].trim().to_string();
+            break;
+        }
+    }
+
+    (verdict, rationale)
+}
+
+/// The verdict which is generated by the LLM for verifying a relation against the abstracts of the linked publications.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct RelationVerification {
+    #[oai(read_only)]
+    pub id: i64,
+    pub relation_id: i64,
+    pub relation_type: String,
+    pub source_id: String,
+    pub source_type: String,
+    pub target_id: String,
+    pub target_type: String,
+    pub pmids: String,
+    pub verdict: String,
+    pub rationale: String,
+    pub prompt_template_category: String,
+    pub session_uuid: String,
+
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl RelationVerification {
+    /// Verify a relation by the LLM. The abstracts of the linked publications are fetched from the biomedgps_publication table, so you need to import the publications before verifying the relations.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `chatbot` - The chatbot which is used to verify the relation
+    /// * `relation_id` - The ID of the relation in the biomedgps_relation table
+    /// * `prompt_template_category` - The prompt template category, if it is None, it will be detected by the relation type.
+    ///
+    /// # Returns
+    /// * `Result<RelationVerification, anyhow::Error>` - The verification record which is saved in the biomedgps_relation_verification table.
+    pub async fn verify(
+        pool: &sqlx::PgPool,
+        chatbot: &ChatBot,
+        relation_id: i64,
+        prompt_template_category: Option<&str>,
+    ) -> Result<RelationVerification, anyhow::Error> {
+        let relation =
+            sqlx::query_as::<_, Relation>("SELECT * FROM biomedgps_relation WHERE id = $1")
+                .bind(relation_id)
+                .fetch_one(pool)
+                .await?;
+
+        let pmids = match &relation.pmids {
+            Some(pmids) => pmids
+                .split(|c| c == '|' || c == ',' || c == ';')
+                .map(|pmid| pmid.trim())
+                .filter(|pmid| !pmid.is_empty())
+                .collect::<Vec<&str>>(),
+            None => vec![],
+        };
+
+        if pmids.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The relation {} doesn't have any pmids, so we cannot verify it.",
+                relation_id
+            ));
+        }
+
+        let publications = Publication::fetch_by_pmids(pool, &pmids).await?;
+        if publications.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Cannot find any publications for the pmids ({}) in the publication cache, please import them first.",
+                pmids.join(", ")
+            ));
+        }
+
+        let source_name =
+            Self::fetch_entity_name(pool, &relation.source_id, &relation.source_type).await;
+        let target_name =
+            Self::fetch_entity_name(pool, &relation.target_id, &relation.target_type).await;
+
+        let prompt_template_category = match prompt_template_category {
+            Some(prompt_template_category) => prompt_template_category,
+            None => get_verification_prompt_template(&relation.relation_type),
+        };
+
+        let context = RelationWithPublications {
+            relation: relation.clone(),
+            source_name,
+            target_name,
+            publications: publications.clone(),
+        };
+        let mut llm_msg = LlmMessage::new(prompt_template_category, context, None)?;
+        let answer = llm_msg.answer(chatbot, Some(pool)).await?;
+        let (verdict, rationale) = parse_verdict(&answer.message);
+
+        let sql_str = "INSERT INTO biomedgps_relation_verification (relation_id, relation_type, source_id, source_type, target_id, target_type, pmids, verdict, rationale, prompt_template_category, session_uuid) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (relation_id, prompt_template_category) DO UPDATE SET pmids = EXCLUDED.pmids, verdict = EXCLUDED.verdict, rationale = EXCLUDED.rationale, session_uuid = EXCLUDED.session_uuid, created_at = now() RETURNING *";
+        let verification = sqlx::query_as::<_, RelationVerification>(sql_str)
+            .bind(relation_id)
+            .bind(&relation.relation_type)
+            .bind(&relation.source_id)
+            .bind(&relation.source_type)
+            .bind(&relation.target_id)
+            .bind(&relation.target_type)
+            .bind(
+                publications
+                    .iter()
+                    .map(|p| p.pmid.clone())
+                    .collect::<Vec<String>>()
+                    .join("|"),
+            )
+            .bind(&verdict)
+            .bind(&rationale)
+            .bind(prompt_template_category)
+            .bind(&answer.session_uuid)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(verification)
+    }
+
+    async fn fetch_entity_name(pool: &sqlx::PgPool, entity_id: &str, entity_type: &str) -> String {
+        match sqlx::query_as::<_, (String,)>(
+            "SELECT name FROM biomedgps_entity WHERE id = $1 AND label = $2",
+        )
+        .bind(entity_id)
+        .bind(entity_type)
+        .fetch_one(pool)
+        .await
+        {
+            Ok(record) => record.0,
+            Err(e) => {
+                warn!(
+                    "Failed to fetch the name of {}::{}: {}",
+                    entity_type, entity_id, e
+                );
+                entity_id.to_string()
+            }
+        }
+    }
 }
 
 pub async fn fetch_by_session_uuid(
@@ -324,8 +781,13 @@ where
         if self.message.len() > 0 {
             return Ok(self);
         } else {
-            self.message = match chatbot.answer(prompt) {
-                Ok(message) => message,
+            // Only the calls which reach the LLM are counted, the cached answers are free.
+            if let Some(pool) = pool {
+                LlmUsage::check_budget(pool, chatbot.username.as_deref()).await?;
+            }
+
+            let (message, usage) = match chatbot.answer_with_usage(prompt) {
+                Ok(answer) => answer,
                 Err(e) => {
                     warn!("Failed to answer the question: {}", e.to_string());
                     return Err(anyhow::anyhow!(
@@ -335,8 +797,16 @@ where
                 }
             };
 
+            self.message = message;
             self.updated_at = Utc::now();
 
+            if let Some(pool) = pool {
+                // The answer is still useful, so we don't return an error if we cannot record the usage.
+                if let Err(e) = usage.save2db(pool).await {
+                    warn!("Failed to record the token usage: {}", e);
+                }
+            }
+
             if pool.is_some() {
                 match self.save2db(pool.unwrap()).await {
                     Ok(_) => return Ok(self),
@@ -354,6 +824,191 @@ where
     }
 }
 
+/// The error which is returned when the monthly token budget is exhausted. The routes use it to return a 429 response.
+#[derive(Debug)]
+pub struct LlmBudgetExceeded {
+    pub scope: String,
+    pub used: i64,
+    pub budget: i64,
+}
+
+impl std::fmt::Display for LlmBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The monthly LLM token budget of {} is exhausted ({} of {} tokens used), please try again next month or ask the administrator to raise the budget.",
+            self.scope, self.used, self.budget
+        )
+    }
+}
+
+impl std::error::Error for LlmBudgetExceeded {}
+
+// The monthly token budget for each user and for the whole deployment. No limit if the environment variable is not set.
+pub const LLM_USER_BUDGET_ENV: &str = "LLM_MONTHLY_TOKEN_BUDGET_PER_USER";
+pub const LLM_DEPLOYMENT_BUDGET_ENV: &str = "LLM_MONTHLY_TOKEN_BUDGET";
+
+fn get_budget_from_env(name: &str) -> Option<i64> {
+    match std::env::var(name) {
+        Ok(v) => match v.trim().parse::<i64>() {
+            Ok(budget) if budget >= 0 => Some(budget),
+            _ => {
+                warn!(
+                    "Invalid {}: {}, it should be a non-negative integer.",
+                    name, v
+                );
+                None
+            }
+        },
+        Err(_) => None,
+    }
+}
+
+/// The token usage of one LLM call, it's saved in the biomedgps_llm_usage table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct LlmUsage {
+    #[oai(read_only)]
+    pub id: i64,
+    pub username: String,
+    pub endpoint: String,
+    pub model: String,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+
+    #[serde(with = "ts_seconds")]
+    #[oai(read_only)]
+    pub created_at: DateTime<Utc>,
+}
+
+/// The usage summary of a user or the whole deployment in a month.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct LlmUsageSummary {
+    // The username, or `*` for the whole deployment.
+    pub username: String,
+    // The month, such as 2024-01.
+    pub month: String,
+    pub num_calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    #[sqlx(default)]
+    #[oai(skip_serializing_if_is_none)]
+    pub budget: Option<i64>,
+}
+
+impl LlmUsage {
+    pub async fn save2db(&self, pool: &sqlx::PgPool) -> Result<(), anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_llm_usage (username, endpoint, model, prompt_tokens, completion_tokens, total_tokens) VALUES ($1, $2, $3, $4, $5, $6)";
+        sqlx::query(sql_str)
+            .bind(&self.username)
+            .bind(&self.endpoint)
+            .bind(&self.model)
+            .bind(self.prompt_tokens)
+            .bind(self.completion_tokens)
+            .bind(self.total_tokens)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn fetch_used_tokens(
+        pool: &sqlx::PgPool,
+        username: Option<&str>,
+    ) -> Result<i64, anyhow::Error> {
+        let sql_str = "SELECT COALESCE(SUM(total_tokens), 0)::BIGINT FROM biomedgps_llm_usage WHERE created_at >= date_trunc('month', now()) AND ($1::VARCHAR IS NULL OR username = $1)";
+        let used = sqlx::query_scalar::<_, i64>(sql_str)
+            .bind(username)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(used)
+    }
+
+    /// Check whether the monthly budgets are exhausted. The per-user budget is only checked when the username is known.
+    ///
+    /// # Returns
+    /// * `Err(anyhow::Error)` - It wraps a [`LlmBudgetExceeded`](struct.LlmBudgetExceeded.html) error if one of the budgets is exhausted.
+    pub async fn check_budget(
+        pool: &sqlx::PgPool,
+        username: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        if let Some(budget) = get_budget_from_env(LLM_DEPLOYMENT_BUDGET_ENV) {
+            let used = Self::fetch_used_tokens(pool, None).await?;
+            if used >= budget {
+                return Err(LlmBudgetExceeded {
+                    scope: "the deployment".to_string(),
+                    used,
+                    budget,
+                }
+                .into());
+            }
+        }
+
+        if let (Some(username), Some(budget)) = (username, get_budget_from_env(LLM_USER_BUDGET_ENV))
+        {
+            let used = Self::fetch_used_tokens(pool, Some(username)).await?;
+            if used >= budget {
+                return Err(LlmBudgetExceeded {
+                    scope: format!("the user {}", username),
+                    used,
+                    budget,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Summarize the token usage in a month.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `per_user` - Summarize the usage for each user if it is true, otherwise for the whole deployment.
+    /// * `month` - The month, such as 2024-01. The current month will be used if it is None.
+    pub async fn summarize(
+        pool: &sqlx::PgPool,
+        per_user: bool,
+        month: Option<&str>,
+    ) -> Result<Vec<LlmUsageSummary>, anyhow::Error> {
+        let month = match month {
+            Some(month) => {
+                if !MONTH_REGEX.is_match(month) {
+                    return Err(anyhow::anyhow!(
+                        "Invalid month: {}, it should be like 2024-01.",
+                        month
+                    ));
+                }
+                month.to_string()
+            }
+            None => Utc::now().format("%Y-%m").to_string(),
+        };
+
+        let username_column = if per_user { "username" } else { "'*'" };
+        let sql_str = format!(
+            "SELECT {username_column}::VARCHAR AS username, $1::VARCHAR AS month, COUNT(*)::BIGINT AS num_calls, COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens, COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens, COALESCE(SUM(total_tokens), 0)::BIGINT AS total_tokens FROM biomedgps_llm_usage WHERE created_at >= to_date($1, 'YYYY-MM') AND created_at < to_date($1, 'YYYY-MM') + interval '1 month' GROUP BY 1 ORDER BY total_tokens DESC"
+        );
+
+        let mut summaries = sqlx::query_as::<_, LlmUsageSummary>(&sql_str)
+            .bind(&month)
+            .fetch_all(pool)
+            .await?;
+
+        let budget = if per_user {
+            get_budget_from_env(LLM_USER_BUDGET_ENV)
+        } else {
+            get_budget_from_env(LLM_DEPLOYMENT_BUDGET_ENV)
+        };
+        for summary in summaries.iter_mut() {
+            summary.budget = budget;
+        }
+
+        Ok(summaries)
+    }
+}
+
 pub struct ChatBot {
     role: MessageRole,
     name: Option<String>,
@@ -361,6 +1016,9 @@ pub struct ChatBot {
     function_call: Option<FunctionCall>,
     model_name: String,
     client: Client,
+    // Who and which endpoint calls the LLM, they are recorded with the token usage.
+    username: Option<String>,
+    endpoint: Option<String>,
 }
 
 impl ChatBot {
@@ -383,10 +1041,23 @@ impl ChatBot {
             function_call: None,
             model_name: model,
             client: client,
+            username: None,
+            endpoint: None,
         }
     }
 
+    pub fn with_usage_context(mut self, username: &str, endpoint: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
     pub fn answer(&self, prompt: String) -> Result<String, anyhow::Error> {
+        let (message, _) = self.answer_with_usage(prompt)?;
+        Ok(message)
+    }
+
+    pub fn answer_with_usage(&self, prompt: String) -> Result<(String, LlmUsage), anyhow::Error> {
         let model_name = self.model_name.clone();
         let req = ChatCompletionRequest::new(
             model_name,
@@ -400,9 +1071,19 @@ impl ChatBot {
 
         let result = self.client.chat_completion(req)?;
         let message = result.choices[0].message.content.clone();
+        let usage = LlmUsage {
+            id: 0,
+            username: self.username.clone().unwrap_or_default(),
+            endpoint: self.endpoint.clone().unwrap_or_default(),
+            model: self.model_name.clone(),
+            prompt_tokens: result.usage.prompt_tokens,
+            completion_tokens: result.usage.completion_tokens,
+            total_tokens: result.usage.total_tokens,
+            created_at: Utc::now(),
+        };
 
         match message {
-            Some(message) => Ok(message),
+            Some(message) => Ok((message, usage)),
             None => Err(anyhow::anyhow!("No message returned")),
         }
     }
@@ -411,6 +1092,55 @@ impl ChatBot {
 // Write unit tests
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_parse_verdict() {
+        let (verdict, rationale) = super::parse_verdict(
+            "Verdict: SUPPORTED\nRationale: PMID 123 reports a randomized trial.",
+        );
+        assert_eq!(verdict, "SUPPORTED");
+        assert_eq!(rationale, "PMID 123 reports a randomized trial.");
+
+        let (verdict, rationale) = super::parse_verdict("I cannot verify it.");
+        assert_eq!(verdict, "INSUFFICIENT_EVIDENCE");
+        assert_eq!(rationale, "I cannot verify it.");
+    }
+
+    #[test]
+    fn test_get_verification_prompt_template() {
+        assert_eq!(
+            super::get_verification_prompt_template("DRUGBANK::treats::Compound:Disease"),
+            "treatment_edge_verification"
+        );
+        assert_eq!(
+            super::get_verification_prompt_template("STRING::BINDING::Gene:Gene"),
+            "edge_verification"
+        );
+    }
+
+    #[test]
+    fn test_validate_path_steps() {
+        let step = |source_id: &str, target_id: &str| super::PathStep {
+            source_id: source_id.to_string(),
+            source_type: "Gene".to_string(),
+            relation_type: "STRING::BINDING::Gene:Gene".to_string(),
+            target_id: target_id.to_string(),
+            target_type: "Gene".to_string(),
+            pmids: None,
+        };
+
+        assert!(super::PathNarrative::validate_steps(&vec![]).is_err());
+        assert!(super::PathNarrative::validate_steps(&vec![
+            step("ENTREZ:1", "ENTREZ:2"),
+            step("ENTREZ:2", "ENTREZ:3")
+        ])
+        .is_ok());
+        assert!(super::PathNarrative::validate_steps(&vec![
+            step("ENTREZ:1", "ENTREZ:2"),
+            step("ENTREZ:3", "ENTREZ:4")
+        ])
+        .is_err());
+    }
+
     #[tokio::test]
     async fn test_answer() {
         let OPENAI_API_KEY = std::env::var("OPENAI_API_KEY").unwrap();
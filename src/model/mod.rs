@@ -6,3 +6,13 @@ pub mod graph;
 pub mod llm;
 pub mod kge;
 pub mod init_db;
+pub mod registry;
+pub mod export;
+pub mod import_job;
+pub mod benchmark;
+pub mod variant;
+pub mod image;
+pub mod ontology;
+pub mod schedule;
+pub mod backup;
+pub mod node2vec;
@@ -0,0 +1,206 @@
+//! This module is used to train the entity embeddings by node2vec on the relations in the database, for the users who don't have a GPU pipeline to train the KGE models.
+//!
+//! The relations in the biomedgps_relation table are treated as an undirected graph, the embeddings are trained by the functions in the algorithm::node2vec module and registered as a new model by EmbeddingMetadata::init_embedding_table. Node2vec doesn't learn the relation embeddings, so the model can be used to find the similar entities but not to score the triples.
+
+use crate::algorithm::node2vec::{train_node2vec, Node2VecConfig};
+use crate::model::kge::{get_entity_emb_table_name, normalize_embedding, EmbeddingMetadata};
+use crate::pgvector::Vector;
+use log::{info, warn};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+
+pub const NODE2VEC_MODEL_TYPE: &str = "Node2Vec";
+
+/// How many rows are read from or written to the database at a time.
+const NODE2VEC_BATCH_SIZE: i64 = 10000;
+
+/// The node2vec training task, the trained embeddings are stored in the entity embedding table of the table name.
+pub struct Node2VecTraining {
+    pub table_name: String,
+    pub model_name: String,
+    pub description: String,
+    // Only the relations of these resources are used, all relations are used if it's empty.
+    pub datasets: Vec<String>,
+    // The cosine similarity is used if it's empty, the node2vec embeddings are not trained for the l2 distance.
+    pub metric: String,
+    pub config: Node2VecConfig,
+}
+
+/// The graph which is read from the relation table, the nodes are the (entity_type, entity_id) pairs.
+struct RelationGraph {
+    nodes: Vec<(String, String)>,
+    edges: Vec<(usize, usize)>,
+    resources: BTreeSet<String>,
+}
+
+impl Node2VecTraining {
+    /// Train the embeddings and register them as a new model, the metadata of the model is returned.
+    pub async fn run(&self, pool: &sqlx::PgPool) -> Result<EmbeddingMetadata, anyhow::Error> {
+        if self.config.dimension == 0 || self.config.walk_length < 2 {
+            return Err(anyhow::anyhow!(
+                "The dimension should be larger than 0 and the walk length should be larger than 1."
+            ));
+        }
+
+        if self.config.p <= 0.0 || self.config.q <= 0.0 {
+            return Err(anyhow::anyhow!("The p and q should be larger than 0."));
+        }
+
+        let graph = self.load_graph(pool).await?;
+        if graph.edges.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No relations are found in the datasets {:?}.",
+                self.datasets
+            ));
+        }
+
+        info!(
+            "Train the node2vec embeddings on {} entities and {} relations.",
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+        let num_nodes = graph.nodes.len();
+        let edges = graph.edges.clone();
+        let config = self.config.clone();
+        let embeddings =
+            tokio::task::spawn_blocking(move || train_node2vec(num_nodes, &edges, &config)).await?;
+
+        let names = load_entity_names(pool, &graph.nodes).await?;
+
+        let metric = if self.metric.is_empty() {
+            "cosine"
+        } else {
+            self.metric.as_str()
+        };
+        let datasets: Vec<&str> = graph.resources.iter().map(|r| r.as_str()).collect();
+        let metadata = json!({
+            "walk_length": self.config.walk_length,
+            "walks_per_node": self.config.walks_per_node,
+            "window_size": self.config.window_size,
+            "num_negatives": self.config.num_negatives,
+            "epochs": self.config.epochs,
+            "learning_rate": self.config.learning_rate,
+            "p": self.config.p,
+            "q": self.config.q,
+            "seed": self.config.seed,
+        });
+        let embedding_metadata = EmbeddingMetadata::init_embedding_table(
+            pool,
+            &self.table_name,
+            &self.model_name,
+            NODE2VEC_MODEL_TYPE,
+            &self.description,
+            &datasets,
+            self.config.dimension,
+            Some(metadata.to_string()),
+            metric,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to register the model: {}", e))?;
+
+        let sql_str = format!("INSERT INTO {} (embedding_id, entity_id, entity_type, entity_name, embedding) VALUES ($1, $2, $3, $4, $5)", get_entity_emb_table_name(&self.table_name));
+        let mut tx = pool.begin().await?;
+        for (i, ((entity_type, entity_id), embedding)) in
+            graph.nodes.iter().zip(embeddings.iter()).enumerate()
+        {
+            let entity_name = names
+                .get(&(entity_type.clone(), entity_id.clone()))
+                .unwrap_or(entity_id);
+            sqlx::query(&sql_str)
+                .bind(i as i64 + 1)
+                .bind(entity_id)
+                .bind(entity_type)
+                .bind(entity_name)
+                .bind(Vector::from(normalize_embedding(metric, embedding)))
+                .execute(&mut tx)
+                .await?;
+
+            // Commit in batches, so a large graph doesn't hold a huge transaction.
+            if (i as i64 + 1) % NODE2VEC_BATCH_SIZE == 0 {
+                tx.commit().await?;
+                tx = pool.begin().await?;
+            }
+        }
+        tx.commit().await?;
+
+        info!(
+            "The node2vec embeddings of {} entities are stored in the model {}.",
+            graph.nodes.len(),
+            self.model_name
+        );
+
+        Ok(embedding_metadata)
+    }
+
+    async fn load_graph(&self, pool: &sqlx::PgPool) -> Result<RelationGraph, anyhow::Error> {
+        let mut graph = RelationGraph {
+            nodes: vec![],
+            edges: vec![],
+            resources: BTreeSet::new(),
+        };
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+        let mut add_node = |graph: &mut RelationGraph, node: (String, String)| -> usize {
+            *index.entry(node.clone()).or_insert_with(|| {
+                graph.nodes.push(node);
+                graph.nodes.len() - 1
+            })
+        };
+
+        let mut last_id: i64 = 0;
+        loop {
+            let records = sqlx::query_as::<_, (i64, String, String, String, String, String)>(
+                "SELECT id, source_type, source_id, target_type, target_id, resource FROM biomedgps_relation WHERE id > $1 AND (cardinality($3::TEXT[]) = 0 OR resource = ANY($3)) ORDER BY id LIMIT $2",
+            )
+            .bind(last_id)
+            .bind(NODE2VEC_BATCH_SIZE)
+            .bind(&self.datasets)
+            .fetch_all(pool)
+            .await?;
+
+            if records.is_empty() {
+                break;
+            }
+
+            last_id = records.last().unwrap().0;
+            for (_, source_type, source_id, target_type, target_id, resource) in records {
+                let source = add_node(&mut graph, (source_type, source_id));
+                let target = add_node(&mut graph, (target_type, target_id));
+                graph.edges.push((source, target));
+                graph.resources.insert(resource);
+            }
+        }
+
+        Ok(graph)
+    }
+}
+
+/// Read the names of the entities in the graph, the entities which are not in the entity table are missing in the result.
+async fn load_entity_names(
+    pool: &sqlx::PgPool,
+    nodes: &Vec<(String, String)>,
+) -> Result<HashMap<(String, String), String>, anyhow::Error> {
+    let mut names = HashMap::new();
+    for chunk in nodes.chunks(NODE2VEC_BATCH_SIZE as usize) {
+        let (types, ids): (Vec<String>, Vec<String>) = chunk.iter().cloned().unzip();
+        let records = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT e.label, e.id, e.name FROM biomedgps_entity e JOIN UNNEST($1::TEXT[], $2::TEXT[]) AS n(label, id) ON e.label = n.label AND e.id = n.id",
+        )
+        .bind(&types)
+        .bind(&ids)
+        .fetch_all(pool)
+        .await?;
+
+        for (label, id, name) in records {
+            names.insert((label, id), name);
+        }
+    }
+
+    if names.len() < nodes.len() {
+        warn!(
+            "{} entities are not found in the entity table, their ids are used as the names.",
+            nodes.len() - names.len()
+        );
+    }
+
+    Ok(names)
+}
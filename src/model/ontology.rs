@@ -0,0 +1,433 @@
+//! This module is used to import the disease and phenotype ontologies, such as MONDO, DOID and HPO, which are distributed as OBO/OWL files instead of the tsv files.
+//!
+//! The ontologies are read in the OBO graph JSON format (such as `mondo.json`, it can be converted from an OBO/OWL file by `robot convert --format json`). The classes are imported as entities with their synonyms and xrefs, and the `is_a` edges are imported into the entity hierarchy table, so the terms can be expanded to their descendants.
+
+use crate::model::core::{Entity, ENTITY_ID_REGEX};
+use crate::model::util::{open_data_file, ConflictStrategy};
+use log::{info, warn};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::PathBuf;
+use validator::Validate;
+
+/// The entity types of the terms by the prefixes of the ontologies. The terms of other ontologies, such as the UBERON terms referenced by MONDO, are skipped.
+pub const ONTOLOGY_ENTITY_TYPES: [(&str, &str); 7] = [
+    ("MONDO", "Disease"),
+    ("DOID", "Disease"),
+    ("EFO", "Disease"),
+    ("ORPHANET", "Disease"),
+    ("OMIM", "Disease"),
+    ("MESH", "Disease"),
+    ("HP", "Symptom"),
+];
+
+/// The predicates of the subclass edges in the OBO graph JSON files.
+pub const IS_A_PREDICATES: [&str; 2] = ["is_a", "http://www.w3.org/2000/01/rdf-schema#subClassOf"];
+
+/// The relation of the imported hierarchy rows.
+pub const IS_A_RELATION: &str = "is_a";
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboGraphDocument {
+    #[serde(default)]
+    graphs: Vec<OboGraph>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboGraph {
+    #[serde(default)]
+    nodes: Vec<OboNode>,
+    #[serde(default)]
+    edges: Vec<OboEdge>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboNode {
+    id: String,
+    lbl: Option<String>,
+    // Such as CLASS, PROPERTY and INDIVIDUAL, only the classes are imported.
+    #[serde(rename = "type")]
+    node_type: Option<String>,
+    meta: Option<OboMeta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboMeta {
+    definition: Option<OboValue>,
+    #[serde(default)]
+    synonyms: Vec<OboValue>,
+    #[serde(default)]
+    xrefs: Vec<OboValue>,
+    #[serde(default)]
+    deprecated: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboValue {
+    val: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OboEdge {
+    sub: String,
+    pred: String,
+    obj: String,
+}
+
+/// A parent-child relation between two entities, such as a disease and its subtype.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Object, sqlx::FromRow)]
+pub struct EntityHierarchy {
+    pub child_id: String,
+    pub child_type: String,
+    pub parent_id: String,
+    pub parent_type: String,
+    // Such as is_a.
+    pub relation: String,
+    // The ontology which defines the relation, such as MONDO.
+    pub resource: String,
+}
+
+/// Convert the id of an ontology term into an entity id. The IRIs of the OBO Foundry ontologies are converted into CURIEs, such as `http://purl.obolibrary.org/obo/MONDO_0005015` into `MONDO:0005015`.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::ontology::obo_curie;
+///
+/// assert_eq!(obo_curie("http://purl.obolibrary.org/obo/MONDO_0005015"), Some("MONDO:0005015".to_string()));
+/// assert_eq!(obo_curie("HP:0000118"), Some("HP:0000118".to_string()));
+/// assert_eq!(obo_curie("http://www.orpha.net/ORDO/Orphanet_558"), Some("Orphanet:558".to_string()));
+/// assert_eq!(obo_curie("http://purl.obolibrary.org/obo/mondo#disease_grouping"), None);
+/// ```
+pub fn obo_curie(id: &str) -> Option<String> {
+    let id = id.trim();
+    let local = if id.starts_with("http://") || id.starts_with("https://") {
+        id.rsplit(|c: char| c == '/' || c == '#')
+            .next()
+            .unwrap_or("")
+    } else {
+        id
+    };
+
+    let curie = if local.contains(':') {
+        local.to_string()
+    } else {
+        match local.split_once('_') {
+            Some((prefix, value)) => format!("{}:{}", prefix, value),
+            None => return None,
+        }
+    };
+
+    // The prefixes of the ontologies start with an uppercase letter, such as MONDO and Orphanet, so the local names like disease_grouping are not ids.
+    let is_prefix = curie
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_uppercase());
+    if is_prefix && ENTITY_ID_REGEX.is_match(&curie) {
+        Some(curie)
+    } else {
+        None
+    }
+}
+
+/// Get the entity type of a term by the prefix of its id, None if the ontology is not supported.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::ontology::ontology_entity_type;
+///
+/// assert_eq!(ontology_entity_type("MONDO:0005015"), Some("Disease"));
+/// assert_eq!(ontology_entity_type("Orphanet:558"), Some("Disease"));
+/// assert_eq!(ontology_entity_type("HP:0000118"), Some("Symptom"));
+/// assert_eq!(ontology_entity_type("UBERON:0000948"), None);
+/// ```
+pub fn ontology_entity_type(curie: &str) -> Option<&'static str> {
+    let prefix = curie.split(':').next().unwrap_or("").to_uppercase();
+    ONTOLOGY_ENTITY_TYPES
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, entity_type)| *entity_type)
+}
+
+/// Join the values by `|` like the synonyms of the entities, the empty and duplicated values are removed.
+fn join_values(values: Vec<String>, excluded: &str) -> Option<String> {
+    let mut seen = HashSet::new();
+    let values = values
+        .into_iter()
+        .map(|v| v.replace('|', " ").trim().to_string())
+        .filter(|v| !v.is_empty() && v != excluded && seen.insert(v.clone()))
+        .collect::<Vec<String>>();
+
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join("|"))
+    }
+}
+
+/// Parse an OBO graph JSON document into the entities and the hierarchy.
+///
+/// The deprecated classes, the classes without a label and the classes of the unsupported ontologies are skipped, and so are the `is_a` edges which link to the skipped classes.
+///
+/// # Arguments
+/// * `content` - The content of an OBO graph JSON file
+///
+/// # Returns
+/// * `Result<(Vec<Entity>, Vec<EntityHierarchy>), anyhow::Error>` - The entities and the hierarchy or an error
+pub fn parse_obograph(content: &str) -> Result<(Vec<Entity>, Vec<EntityHierarchy>), anyhow::Error> {
+    let document: OboGraphDocument = serde_json::from_str(content)?;
+
+    let mut entities: Vec<Entity> = vec![];
+    let mut entity_types: HashMap<String, String> = HashMap::new();
+    for node in document.graphs.iter().flat_map(|g| g.nodes.iter()) {
+        if node.node_type.as_deref().unwrap_or("CLASS") != "CLASS" {
+            continue;
+        }
+
+        let meta = node.meta.as_ref();
+        if meta.map(|m| m.deprecated).unwrap_or(false) {
+            continue;
+        }
+
+        let (id, entity_type) =
+            match obo_curie(&node.id).and_then(|id| ontology_entity_type(&id).map(|t| (id, t))) {
+                Some(v) => v,
+                None => continue,
+            };
+
+        let name = match node
+            .lbl
+            .as_ref()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+        {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let name = match node
+            .lbl
+            .as_ref()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+        {
+            Some(name) => name.to_string(),
+            None => {
+                warn!("The term {} has no label, skip it.", id);
+                continue;
+            }
+        };
+
+        if entity_types.contains_key(&id) {
+            continue;
+        }
+
+        let entity = Entity {
+            idx: 0,
+            id: id.clone(),
+            name: name.clone(),
+            label: entity_type.to_string(),
+            resource: id.split(':').next().unwrap_or("").to_uppercase(),
+            description: meta
+                .and_then(|m| m.definition.as_ref())
+                .map(|d| d.val.trim().to_string())
+                .filter(|d| !d.is_empty()),
+            taxid: None,
+            synonyms: join_values(
+                meta.map(|m| m.synonyms.iter().map(|s| s.val.clone()).collect())
+                    .unwrap_or_default(),
+                &name,
+            ),
+            pmids: None,
+            xrefs: join_values(
+                meta.map(|m| m.xrefs.iter().filter_map(|x| obo_curie(&x.val)).collect())
+                    .unwrap_or_default(),
+                &id,
+            ),
+        };
+
+        if let Err(e) = entity.validate() {
+            warn!("The term {} is invalid, skip it: ({})", id, e);
+            continue;
+        }
+
+        entity_types.insert(id, entity_type.to_string());
+        entities.push(entity);
+    }
+
+    let mut seen = HashSet::new();
+    let mut hierarchy = vec![];
+    for edge in document.graphs.iter().flat_map(|g| g.edges.iter()) {
+        if !IS_A_PREDICATES.contains(&edge.pred.as_str()) {
+            continue;
+        }
+
+        let (child_id, parent_id) = match (obo_curie(&edge.sub), obo_curie(&edge.obj)) {
+            (Some(child_id), Some(parent_id)) if child_id != parent_id => (child_id, parent_id),
+            _ => continue,
+        };
+
+        if let (Some(child_type), Some(parent_type)) =
+            (entity_types.get(&child_id), entity_types.get(&parent_id))
+        {
+            let row = EntityHierarchy {
+                resource: child_id.split(':').next().unwrap_or("").to_uppercase(),
+                child_id,
+                child_type: child_type.clone(),
+                parent_id,
+                parent_type: parent_type.clone(),
+                relation: IS_A_RELATION.to_string(),
+            };
+            if seen.insert(row.clone()) {
+                hierarchy.push(row);
+            }
+        }
+    }
+
+    Ok((entities, hierarchy))
+}
+
+impl EntityHierarchy {
+    /// Import an OBO graph JSON file, the classes are imported into the entity table and the `is_a` edges are imported into the entity hierarchy table.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `filepath` - The OBO graph JSON file, it might be compressed by gzip or zstd
+    /// * `on_conflict` - How to import the terms which are already in the entity table, the hierarchy rows which exist are always kept
+    ///
+    /// # Returns
+    /// * `Result<(usize, usize), anyhow::Error>` - The number of the imported entities and hierarchy rows or an error
+    pub async fn import_obograph(
+        pool: &sqlx::PgPool,
+        filepath: &PathBuf,
+        on_conflict: ConflictStrategy,
+    ) -> Result<(usize, usize), anyhow::Error> {
+        let mut content = String::new();
+        open_data_file(filepath)
+            .map_err(|e| anyhow::anyhow!("{}", e))?
+            .read_to_string(&mut content)?;
+        let (entities, hierarchy) = parse_obograph(&content)?;
+
+        if entities.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No terms are found in {}, only the OBO graph JSON files of the {} ontologies are supported.",
+                filepath.display(),
+                ONTOLOGY_ENTITY_TYPES
+                    .iter()
+                    .map(|(prefix, _)| *prefix)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+
+        let on_conflict_str = match on_conflict {
+            ConflictStrategy::Skip => "DO NOTHING",
+            ConflictStrategy::Overwrite => "DO UPDATE SET name = EXCLUDED.name, resource = EXCLUDED.resource, description = EXCLUDED.description, synonyms = EXCLUDED.synonyms, xrefs = EXCLUDED.xrefs",
+            ConflictStrategy::Merge => "DO UPDATE SET name = EXCLUDED.name, resource = EXCLUDED.resource, description = COALESCE(EXCLUDED.description, biomedgps_entity.description), synonyms = COALESCE(EXCLUDED.synonyms, biomedgps_entity.synonyms), xrefs = COALESCE(EXCLUDED.xrefs, biomedgps_entity.xrefs)",
+        };
+
+        let mut tx = pool.begin().await?;
+        let mut num_of_entities = 0;
+        for entity in entities.iter() {
+            let sql_str = format!(
+                "INSERT INTO biomedgps_entity (id, name, label, resource, description, synonyms, xrefs) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (id, label) {}",
+                on_conflict_str
+            );
+            let result = sqlx::query(&sql_str)
+                .bind(&entity.id)
+                .bind(&entity.name)
+                .bind(&entity.label)
+                .bind(&entity.resource)
+                .bind(&entity.description)
+                .bind(&entity.synonyms)
+                .bind(&entity.xrefs)
+                .execute(&mut tx)
+                .await?;
+            num_of_entities += result.rows_affected() as usize;
+        }
+
+        let mut num_of_hierarchy = 0;
+        for row in hierarchy.iter() {
+            let sql_str = "INSERT INTO biomedgps_entity_hierarchy (child_id, child_type, parent_id, parent_type, relation, resource) VALUES ($1, $2, $3, $4, $5, $6)
+                           ON CONFLICT ON CONSTRAINT biomedgps_entity_hierarchy_uniq_key DO NOTHING";
+            let result = sqlx::query(sql_str)
+                .bind(&row.child_id)
+                .bind(&row.child_type)
+                .bind(&row.parent_id)
+                .bind(&row.parent_type)
+                .bind(&row.relation)
+                .bind(&row.resource)
+                .execute(&mut tx)
+                .await?;
+            num_of_hierarchy += result.rows_affected() as usize;
+        }
+        tx.commit().await?;
+
+        info!(
+            "Imported {} of {} terms and {} of {} is_a relations from {}.",
+            num_of_entities,
+            entities.len(),
+            num_of_hierarchy,
+            hierarchy.len(),
+            filepath.display()
+        );
+
+        Ok((num_of_entities, num_of_hierarchy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obograph() {
+        let content = r#"{"graphs": [{
+            "nodes": [
+                {"id": "http://purl.obolibrary.org/obo/MONDO_0000001", "lbl": "disease", "type": "CLASS"},
+                {"id": "http://purl.obolibrary.org/obo/MONDO_0005015", "lbl": "diabetes mellitus", "type": "CLASS",
+                 "meta": {"definition": {"val": "A metabolic disorder."},
+                          "synonyms": [{"pred": "hasExactSynonym", "val": "diabetes"}, {"pred": "hasExactSynonym", "val": "diabetes mellitus"}],
+                          "xrefs": [{"val": "DOID:9351"}, {"val": "MESH:D003920"}]}},
+                {"id": "http://purl.obolibrary.org/obo/MONDO_0000002", "lbl": "obsolete term", "type": "CLASS", "meta": {"deprecated": true}},
+                {"id": "http://purl.obolibrary.org/obo/UBERON_0000948", "lbl": "heart", "type": "CLASS"},
+                {"id": "http://purl.obolibrary.org/obo/RO_0002200", "lbl": "has phenotype", "type": "PROPERTY"}
+            ],
+            "edges": [
+                {"sub": "http://purl.obolibrary.org/obo/MONDO_0005015", "pred": "is_a", "obj": "http://purl.obolibrary.org/obo/MONDO_0000001"},
+                {"sub": "http://purl.obolibrary.org/obo/MONDO_0005015", "pred": "is_a", "obj": "http://purl.obolibrary.org/obo/UBERON_0000948"},
+                {"sub": "http://purl.obolibrary.org/obo/MONDO_0005015", "pred": "http://purl.obolibrary.org/obo/RO_0002200", "obj": "http://purl.obolibrary.org/obo/MONDO_0000001"}
+            ]
+        }]}"#;
+
+        let (entities, hierarchy) = parse_obograph(content).unwrap();
+        assert_eq!(
+            entities
+                .iter()
+                .map(|e| e.id.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["MONDO:0000001", "MONDO:0005015"]
+        );
+
+        let entity = &entities[1];
+        assert_eq!(entity.label, "Disease");
+        assert_eq!(entity.resource, "MONDO");
+        assert_eq!(entity.description.as_deref(), Some("A metabolic disorder."));
+        assert_eq!(entity.synonyms.as_deref(), Some("diabetes"));
+        assert_eq!(entity.xrefs.as_deref(), Some("DOID:9351|MESH:D003920"));
+
+        assert_eq!(
+            hierarchy,
+            vec![EntityHierarchy {
+                child_id: "MONDO:0005015".to_string(),
+                child_type: "Disease".to_string(),
+                parent_id: "MONDO:0000001".to_string(),
+                parent_type: "Disease".to_string(),
+                relation: IS_A_RELATION.to_string(),
+                resource: "MONDO".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! This module is used to reimport the datasets which are published to an external data registry, such as an object store used by the data engineering pipeline.
+//!
+//! The registry calls the webhook with a dataset-published event. The event is signed by a shared secret, the files in the event are downloaded, validated and imported by a background import job, and the registry is notified by the callback url when the job is finished.
+
+use crate::model::import_job::{ImportJob, REGISTRY_IMPORT_JOB};
+use crate::model::util::{ConflictStrategy, ImportWarningKind};
+use crate::{check_data_file, import_data, ImportOptions};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The shared secret between the registry and the webhook, the webhook is disabled if it's not set.
+pub const REGISTRY_WEBHOOK_SECRET_ENV: &str = "DATA_REGISTRY_WEBHOOK_SECRET";
+
+/// The prefix of the signature header value, such as `sha256=<hex digest>`.
+pub const REGISTRY_SIGNATURE_PREFIX: &str = "sha256=";
+
+/// How old the timestamp of a signed delivery can be, the older deliveries are rejected, so a captured delivery can't be replayed later.
+pub const REGISTRY_SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+pub const DATASET_PUBLISHED_EVENT: &str = "dataset.published";
+
+/// The tables which can be reimported from the registry.
+pub const REGISTRY_SUPPORTED_TABLES: [&str; 5] = [
+    "entity",
+    "relation",
+    "entity_attribute",
+    "entity2d",
+    "publication",
+];
+
+/// A file in the published dataset.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct RegistryFile {
+    // Which table the file is imported into, such as entity, relation, etc.
+    pub table: String,
+    // The download url of the file, the filename must end with .tsv, .csv or .txt.
+    pub url: String,
+    // The sha256 checksum (hex) of the file, it's checked after downloading if it's provided.
+    #[oai(skip_serializing_if_is_none)]
+    pub sha256: Option<String>,
+}
+
+/// The event which is sent by the registry when a new version of a dataset is published.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+pub struct DatasetPublishedEvent {
+    // The event type, only `dataset.published` is supported.
+    pub event: String,
+    // The dataset name, such as drkg, ctd, etc. It's used as the dataset of the relations.
+    pub dataset: String,
+    pub version: String,
+    pub files: Vec<RegistryFile>,
+    // The url which will be notified with the import job when the job is finished.
+    #[oai(skip_serializing_if_is_none)]
+    pub callback_url: Option<String>,
+}
+
+impl DatasetPublishedEvent {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.event != DATASET_PUBLISHED_EVENT {
+            return Err(anyhow::anyhow!(
+                "The event {} is not supported, only {} is supported.",
+                self.event,
+                DATASET_PUBLISHED_EVENT
+            ));
+        }
+
+        if self.dataset.is_empty() || self.version.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The dataset and the version should not be empty."
+            ));
+        }
+
+        if self.files.is_empty() {
+            return Err(anyhow::anyhow!("The files should not be empty."));
+        }
+
+        for file in self.files.iter() {
+            if !REGISTRY_SUPPORTED_TABLES.contains(&file.table.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "The table {} is not supported, only {:?} are supported.",
+                    file.table,
+                    REGISTRY_SUPPORTED_TABLES
+                ));
+            }
+
+            if get_filename(&file.url).is_none() {
+                return Err(anyhow::anyhow!(
+                    "The url {} is invalid, the filename must end with .tsv, .csv or .txt.",
+                    file.url
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_filename(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let filename = url.path_segments()?.last()?.to_string();
+    if filename.ends_with(".tsv") || filename.ends_with(".csv") || filename.ends_with(".txt") {
+        Some(filename)
+    } else {
+        None
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Verify the signature of the webhook payload. The signature is the HMAC-SHA256 of the timestamp, a dot and the raw body with the shared secret, such as `sha256=<hex digest>`. The timestamp is the unix time when the registry sends the delivery, it must be within [`REGISTRY_SIGNATURE_TOLERANCE_SECS`] of `now`.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::registry::verify_signature;
+///
+/// // The HMAC-SHA256 of `1700000000.hello` with `secret`.
+/// let signature = "sha256=47b1df0ab12338b2685470b0d2b37033add7c3b2bc8172f313e77413f1bb78c8";
+/// assert!(verify_signature("secret", "1700000000", b"hello", signature, 1700000060));
+/// assert!(!verify_signature("another-secret", "1700000000", b"hello", signature, 1700000000));
+/// assert!(!verify_signature("secret", "not-a-timestamp", b"hello", signature, 1700000000));
+/// ```
+pub fn verify_signature(
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+) -> bool {
+    match timestamp.parse::<i64>() {
+        Ok(timestamp) if (now - timestamp).abs() <= REGISTRY_SIGNATURE_TOLERANCE_SECS => {}
+        _ => return false,
+    };
+
+    let signature = match signature
+        .strip_prefix(REGISTRY_SIGNATURE_PREFIX)
+        .and_then(from_hex)
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+impl ImportJob {
+    /// Save a registry job as pending and queue it for the import worker, the worker runs the jobs one by one with their own connection pools.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `database_url` - The database url which is used by the import job
+    /// * `delivery_id` - The id of the webhook delivery, such as the signature of the delivery
+    /// * `event` - The dataset-published event
+    ///
+    /// # Returns
+    /// * `Result<Option<ImportJob>, anyhow::Error>` - The pending job, None if the delivery has scheduled a job, or an error
+    pub async fn schedule_reimport(
+        pool: &sqlx::PgPool,
+        database_url: &str,
+        delivery_id: &str,
+        event: &DatasetPublishedEvent,
+    ) -> Result<Option<ImportJob>, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_import_job (source, dataset, version, status, payload, total_steps, delivery_id) VALUES ($1, $2, $3, 'pending', $4, $5, $6) ON CONFLICT (delivery_id) DO NOTHING RETURNING *";
+        let job = match sqlx::query_as::<_, ImportJob>(sql_str)
+            .bind(REGISTRY_IMPORT_JOB)
+            .bind(&event.dataset)
+            .bind(&event.version)
+            .bind(serde_json::to_value(event)?)
+            .bind(event.files.len() as i32)
+            .bind(delivery_id)
+            .fetch_optional(pool)
+            .await?
+        {
+            Some(job) => job,
+            None => return Ok(None),
+        };
+
+        Self::enqueue(pool, database_url, job.id).await?;
+        Ok(Some(job))
+    }
+
+    async fn download(file: &RegistryFile, dir: &PathBuf) -> Result<PathBuf, anyhow::Error> {
+        let filename = match get_filename(&file.url) {
+            Some(filename) => filename,
+            None => return Err(anyhow::anyhow!("The url {} is invalid.", file.url)),
+        };
+
+        // The file is written chunk by chunk, so a large dataset isn't held in the memory.
+        let mut response = reqwest::get(&file.url).await?.error_for_status()?;
+        let filepath = dir.join(filename);
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&filepath)?);
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            writer.write_all(&chunk)?;
+        }
+        writer.flush()?;
+
+        if let Some(expected) = &file.sha256 {
+            let checksum = to_hex(&hasher.finalize());
+            if checksum != expected.to_lowercase() {
+                return Err(anyhow::anyhow!(
+                    "The checksum of {} is {}, but {} is expected.",
+                    file.url,
+                    checksum,
+                    expected
+                ));
+            }
+        }
+
+        Ok(filepath)
+    }
+
+    /// Download and validate all files of the event before importing any of them, so an invalid file doesn't leave a partially imported dataset.
+    pub(crate) async fn run_reimport(
+        &self,
+        pool: &sqlx::PgPool,
+        database_url: &str,
+    ) -> Result<(), anyhow::Error> {
+        let id = self.id;
+        let event: DatasetPublishedEvent = serde_json::from_value(self.payload.clone())?;
+
+        let tempdir = tempfile::tempdir()?;
+        let mut files = vec![];
+        for (index, file) in event.files.iter().enumerate() {
+            // Keep the files in separate directories in case that they have the same filename.
+            let dir = tempdir.path().join(index.to_string());
+            std::fs::create_dir_all(&dir)?;
+            let filepath = Self::download(file, &dir).await?;
+
+            let errors = check_data_file(&file.table, &filepath)
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>();
+            if !errors.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "The file {} is invalid: {}",
+                    file.url,
+                    errors.join("; ")
+                ));
+            }
+
+            files.push((file.table.clone(), filepath));
+        }
+
+        let mut warnings = vec![];
+        for (idx, (table, filepath)) in files.iter().enumerate() {
+            Self::append_log(
+                pool,
+                id,
+                &format!(
+                    "Step {}/{}: import the {} table from {}.",
+                    idx + 1,
+                    files.len(),
+                    table,
+                    event.files[idx].url
+                ),
+            )
+            .await?;
+            let file_warnings = import_data(
+                database_url,
+                &Some(filepath.to_string_lossy().to_string()),
+                table,
+                &ImportOptions {
+                    dataset: Some(event.dataset.clone()),
+                    // A new version updates the existing rows of the dataset instead of keeping the stale values.
+                    on_conflict: ConflictStrategy::Overwrite,
+                    ..ImportOptions::default()
+                },
+            )
+            .await;
+            let failed = file_warnings
+                .iter()
+                .any(|w| w.kind == ImportWarningKind::FailedFile);
+            warnings.extend(file_warnings);
+            // The remaining files are not imported after a failure, the dataset needs to be published again anyway.
+            if failed {
+                break;
+            }
+            Self::finish_steps(pool, id, idx + 1).await?;
+        }
+
+        // The warnings are saved before the job is finished, so they are sent to the callback url with the job.
+        let sql_str =
+            "UPDATE biomedgps_import_job SET warnings = $1, updated_at = now() WHERE id = $2";
+        sqlx::query(sql_str)
+            .bind(serde_json::to_value(&warnings)?)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        let failures = warnings
+            .iter()
+            .filter(|w| w.kind == ImportWarningKind::FailedFile)
+            .map(|w| w.message.clone())
+            .collect::<Vec<String>>();
+        if !failures.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} data files failed to import: {}",
+                failures.len(),
+                failures.join("; ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Notify the callback url of the event with the finished job, the registry jobs without a callback url are skipped.
+    pub(crate) async fn notify_registry(&self) {
+        let callback_url =
+            match serde_json::from_value::<DatasetPublishedEvent>(self.payload.clone()) {
+                Ok(DatasetPublishedEvent {
+                    callback_url: Some(callback_url),
+                    ..
+                }) => callback_url,
+                _ => return,
+            };
+
+        let client = reqwest::Client::new();
+        match client.post(&callback_url).json(self).send().await {
+            Ok(response) if response.status().is_success() => {
+                info!("Notify {} of the import job {}.", callback_url, self.id);
+            }
+            Ok(response) => {
+                warn!(
+                    "Failed to notify {} of the import job {}: {}",
+                    callback_url,
+                    self.id,
+                    response.status()
+                );
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to notify {} of the import job {}: {}",
+                    callback_url, self.id, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_filename() {
+        assert_eq!(
+            get_filename("https://registry.example.com/drkg/v2/relations.tsv?token=1"),
+            Some("relations.tsv".to_string())
+        );
+        assert_eq!(get_filename("https://registry.example.com/drkg/v2/"), None);
+        assert_eq!(get_filename("relations.tsv"), None);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"1700000000.{}");
+        let signature = format!(
+            "{}{}",
+            REGISTRY_SIGNATURE_PREFIX,
+            to_hex(&mac.finalize().into_bytes())
+        );
+
+        let now = 1700000000 + REGISTRY_SIGNATURE_TOLERANCE_SECS;
+        let verify = |timestamp: &str, body: &[u8], signature: &str, now: i64| {
+            verify_signature("secret", timestamp, body, signature, now)
+        };
+        assert!(verify("1700000000", b"{}", &signature, now));
+        assert!(!verify("1700000000", b"{ }", &signature, now));
+        assert!(!verify("1700000001", b"{}", &signature, now));
+        assert!(!verify("1700000000", b"{}", "sha256=zz", now));
+        // The stale delivery is rejected even if the signature is valid.
+        assert!(!verify("1700000000", b"{}", &signature, now + 1));
+    }
+}
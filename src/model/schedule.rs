@@ -0,0 +1,686 @@
+//! This module is used to run the maintenance tasks of the server periodically, such as removing the expired export artifacts or refreshing the trending entities every night.
+//!
+//! A task runs at a fixed interval or at the times of a cron expression, such as `0 3 * * *` for 03:00 (UTC) every day. The tasks run on the runtime of the server, so they should be short or yield often. The runs are recorded in the biomedgps_scheduled_task_run table, see `/api/v1/scheduled-tasks`.
+//!
+//! The tasks are stopped by `stop_all_tasks` when the server shuts down, the running runs are finished or aborted after a timeout, so no run is left unfinished.
+
+use anyhow::Ok as AnyOk;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// The schedule of a maintenance task.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskSchedule {
+    // Run the task immediately and then every interval.
+    Interval(std::time::Duration),
+    // Run the task at the times which match the cron expression.
+    Cron(CronExpression),
+}
+
+impl TaskSchedule {
+    /// Parse a schedule, a number is the interval in seconds and the others are the cron expressions.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::schedule::TaskSchedule;
+    ///
+    /// assert_eq!(TaskSchedule::parse("3600").unwrap(), TaskSchedule::Interval(std::time::Duration::from_secs(3600)));
+    /// assert!(matches!(TaskSchedule::parse("0 3 * * *").unwrap(), TaskSchedule::Cron(_)));
+    /// assert!(TaskSchedule::parse("0").is_err());
+    /// ```
+    pub fn parse(schedule: &str) -> Result<Self, anyhow::Error> {
+        let schedule = schedule.trim();
+        match schedule.parse::<u64>() {
+            Ok(0) => Err(anyhow::anyhow!("The interval must be greater than 0.")),
+            Ok(secs) => AnyOk(TaskSchedule::Interval(std::time::Duration::from_secs(secs))),
+            Err(_) => AnyOk(TaskSchedule::Cron(CronExpression::parse(schedule)?)),
+        }
+    }
+
+    /// The time of the next run after the given time, None means the task runs immediately.
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            TaskSchedule::Interval(_) => None,
+            TaskSchedule::Cron(expr) => expr.next_after(time),
+        }
+    }
+}
+
+/// Read the schedule of a task from the environment variable, such as `EXPORT_CLEANUP_SCHEDULE="0 3 * * *"`. The default schedule is used if the variable is not set or invalid.
+pub fn get_task_schedule(env_var: &str, default: TaskSchedule) -> TaskSchedule {
+    match std::env::var(env_var) {
+        Ok(value) => match TaskSchedule::parse(&value) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                warn!(
+                    "Invalid {}, use the default schedule {:?}: {}",
+                    env_var, default, e
+                );
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// A cron expression with five fields: minute, hour, day of month, month and day of week. Each field supports `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and lists (`1,15`). The day of week is 0-7, both 0 and 7 are Sunday. Like the cron daemon, a day matches if either the day of month or the day of week matches when both of them are restricted. The times are in UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpression {
+    pub expr: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// Parse a field of a cron expression into the matched values, the values are indexed from 0.
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, anyhow::Error> {
+    let mut values = vec![false; (max + 1) as usize];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(anyhow::anyhow!("Invalid step {} in {}.", step, field)),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (start, end),
+                // A single value with a step runs from the value to the max, such as 5/15.
+                None if part.contains('/') => (range, ""),
+                None => (range, range),
+            };
+            let start = start
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("Invalid value {} in {}.", start, field))?;
+            let end = if end.is_empty() {
+                max
+            } else {
+                end.parse::<u32>()
+                    .map_err(|_| anyhow::anyhow!("Invalid value {} in {}.", end, field))?
+            };
+            (start, end)
+        };
+
+        if start < min || end > max || start > end {
+            return Err(anyhow::anyhow!(
+                "The range {} is out of [{}, {}] in {}.",
+                range,
+                min,
+                max,
+                field
+            ));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            values[value as usize] = true;
+        }
+    }
+
+    AnyOk(values)
+}
+
+impl CronExpression {
+    /// Parse a cron expression, such as `0 3 * * *` or `*/30 9-17 * * 1-5`.
+    pub fn parse(expr: &str) -> Result<Self, anyhow::Error> {
+        let fields = expr.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 5 {
+            return Err(anyhow::anyhow!(
+                "The cron expression {} must have 5 fields: minute, hour, day of month, month and day of week.",
+                expr
+            ));
+        }
+
+        let mut days_of_week = parse_cron_field(fields[4], 0, 7)?;
+        // Both 0 and 7 are Sunday.
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+
+        AnyOk(Self {
+            expr: fields.join(" "),
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days_of_month: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month[time.day() as usize];
+        let day_of_week = self.days_of_week[time.weekday().num_days_from_sunday() as usize];
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (false, true) => day_of_month,
+            (true, false) => day_of_week,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    /// The first matched minute after the given time. It's None if nothing matches in the next 5 years, such as `0 0 31 2 *`.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::schedule::CronExpression;
+    /// use chrono::{TimeZone, Utc};
+    ///
+    /// let expr = CronExpression::parse("0 3 * * *").unwrap();
+    /// let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+    /// assert_eq!(expr.next_after(time), Some(Utc.with_ymd_and_hms(2024, 3, 2, 3, 0, 0).unwrap()));
+    /// ```
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = Utc
+            .with_ymd_and_hms(
+                time.year(),
+                time.month(),
+                time.day(),
+                time.hour(),
+                time.minute(),
+                0,
+            )
+            .single()?
+            + Duration::minutes(1);
+        let deadline = time + Duration::days(5 * 366);
+
+        // Skip the unmatched months, days and hours at once, so it only takes a few hundred steps.
+        while next <= deadline {
+            if !self.months[next.month() as usize] {
+                let (year, month) = if next.month() == 12 {
+                    (next.year() + 1, 1)
+                } else {
+                    (next.year(), next.month() + 1)
+                };
+                next = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.matches_day(&next) {
+                next = Utc
+                    .with_ymd_and_hms(next.year(), next.month(), next.day(), 0, 0, 0)
+                    .single()?
+                    + Duration::days(1);
+            } else if !self.hours[next.hour() as usize] {
+                next = Utc
+                    .with_ymd_and_hms(next.year(), next.month(), next.day(), next.hour(), 0, 0)
+                    .single()?
+                    + Duration::hours(1);
+            } else if !self.minutes[next.minute() as usize] {
+                next = next + Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
+
+// The statuses of the runs of the scheduled tasks.
+pub const TASK_RUN_STATUSES: [&str; 3] = ["running", "succeeded", "failed"];
+
+/// A run of a scheduled task, the runs are recorded in the biomedgps_scheduled_task_run table so the admins can check whether the nightly tasks succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object, sqlx::FromRow)]
+pub struct ScheduledTaskRun {
+    pub id: i64,
+    pub task_name: String,
+    pub status: String,
+    // The summary of the run, such as how many rows are removed.
+    #[oai(skip_serializing_if_is_none)]
+    pub message: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub error: Option<String>,
+    #[serde(with = "ts_seconds")]
+    pub started_at: DateTime<Utc>,
+    #[oai(skip_serializing_if_is_none)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl ScheduledTaskRun {
+    /// Record the start of a run, it returns the id of the run.
+    pub async fn start(pool: &sqlx::PgPool, task_name: &str) -> Result<i64, anyhow::Error> {
+        let sql_str = "INSERT INTO biomedgps_scheduled_task_run (task_name, status) VALUES ($1, 'running') RETURNING id";
+        let (id,) = sqlx::query_as::<_, (i64,)>(sql_str)
+            .bind(task_name)
+            .fetch_one(pool)
+            .await?;
+
+        AnyOk(id)
+    }
+
+    /// Record the outcome of a run, the summary is kept in the message and the error is kept if the run failed.
+    pub async fn finish(
+        pool: &sqlx::PgPool,
+        id: i64,
+        result: &Result<String, anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        let (status, message, error) = match result {
+            Ok(summary) if summary.is_empty() => ("succeeded", None, None),
+            Ok(summary) => ("succeeded", Some(summary.clone()), None),
+            Err(e) => ("failed", None, Some(e.to_string())),
+        };
+
+        let sql_str = "UPDATE biomedgps_scheduled_task_run SET status = $2, message = $3, error = $4, finished_at = now() WHERE id = $1";
+        sqlx::query(sql_str)
+            .bind(id)
+            .bind(status)
+            .bind(message)
+            .bind(error)
+            .execute(pool)
+            .await?;
+
+        AnyOk(())
+    }
+
+    /// Mark the runs which are still running as failed, they are interrupted when the server stopped. It should be called before the tasks are registered.
+    pub async fn fail_interrupted(pool: &sqlx::PgPool) -> Result<u64, anyhow::Error> {
+        let sql_str = "UPDATE biomedgps_scheduled_task_run SET status = 'failed', error = 'The server stopped before the run finished.', finished_at = now() WHERE status = 'running'";
+        let result = sqlx::query(sql_str).execute(pool).await?;
+
+        AnyOk(result.rows_affected())
+    }
+
+    /// Fetch the runs of the scheduled tasks, the newest first.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `task_name` - Only the runs of the task are returned if it's set, such as refresh-trending-entities
+    /// * `statuses` - Only the runs with the statuses are returned if it's set, such as ["failed"]
+    /// * `page` - All matched runs are returned unless it's set, it starts from 1
+    /// * `page_size` - The number of the runs in a page, 10 by default
+    pub async fn list(
+        pool: &sqlx::PgPool,
+        task_name: Option<&str>,
+        statuses: &Option<Vec<String>>,
+        page: Option<u64>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<ScheduledTaskRun>, anyhow::Error> {
+        let (limit, offset) = match page {
+            Some(page) => {
+                let page_size = page_size.unwrap_or(10).max(1);
+                (
+                    Some(page_size as i64),
+                    (page.max(1) - 1) as i64 * page_size as i64,
+                )
+            }
+            None => (None, 0),
+        };
+
+        let sql_str = "SELECT * FROM biomedgps_scheduled_task_run
+                       WHERE ($1::text IS NULL OR task_name = $1)
+                         AND ($2::text[] IS NULL OR status = ANY($2))
+                       ORDER BY started_at DESC, id DESC
+                       LIMIT $3 OFFSET $4";
+        let runs = sqlx::query_as::<_, ScheduledTaskRun>(sql_str)
+            .bind(task_name)
+            .bind(statuses)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await?;
+
+        AnyOk(runs)
+    }
+}
+
+/// How long a stopped task can take to finish its running run, the run is aborted after that.
+pub const TASK_STOP_TIMEOUT_SECS: u64 = 30;
+
+/// A registered task, it stops when the stop signal is sent or the sender is dropped.
+struct RegisteredTask {
+    stop: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref REGISTERED_TASKS: Mutex<HashMap<String, RegisteredTask>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Wait until the stop signal is sent or the task is deregistered.
+async fn wait_for_stop(stop: &mut watch::Receiver<bool>) {
+    while !*stop.borrow() {
+        if stop.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Run a maintenance task on the schedule in the background. The task returns a summary of the run, such as how many rows are removed. Each run is recorded in the biomedgps_scheduled_task_run table, and the summary and the errors are also logged.
+///
+/// The task runs until it's stopped by `stop_task` or `stop_all_tasks`. A task with the same name is replaced, the old one stops after its running run.
+///
+/// # Arguments
+/// * `pool` - The database connection pool for recording the runs
+/// * `name` - The name of the task, such as cleanup-export-artifacts.
+/// * `schedule` - When the task runs.
+/// * `task` - The task, it's called for each run.
+pub fn register_task<F, Fut>(pool: &sqlx::PgPool, name: &str, schedule: TaskSchedule, task: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, anyhow::Error>> + Send,
+{
+    let pool = pool.clone();
+    let task_name = name.to_string();
+    info!(
+        "Register the task {} with the schedule {:?}.",
+        task_name, schedule
+    );
+    let (stop_sender, mut stop) = watch::channel(false);
+    let handle = tokio::spawn(async move {
+        let name = task_name;
+        let mut interval = match &schedule {
+            TaskSchedule::Interval(period) => Some(tokio::time::interval(*period)),
+            TaskSchedule::Cron(_) => None,
+        };
+
+        loop {
+            let wait = async {
+                match interval.as_mut() {
+                    Some(interval) => {
+                        interval.tick().await;
+                        true
+                    }
+                    None => match schedule.next_after(Utc::now()) {
+                        Some(next) => {
+                            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                            tokio::time::sleep(wait).await;
+                            true
+                        }
+                        None => false,
+                    },
+                }
+            };
+            tokio::select! {
+                scheduled = wait => {
+                    if !scheduled {
+                        error!("The task {} will never run again, stop it.", name);
+                        return;
+                    }
+                }
+                _ = wait_for_stop(&mut stop) => break,
+            }
+
+            // The task still runs if the run can't be recorded, such as the database is temporarily unavailable.
+            let run_id = match ScheduledTaskRun::start(&pool, &name).await {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!("Failed to record the run of the task {}: {}", name, e);
+                    None
+                }
+            };
+
+            // A stopped task finishes its running run, unless the run takes too long.
+            let run = task();
+            tokio::pin!(run);
+            let mut stopped = false;
+            let result = tokio::select! {
+                result = &mut run => result,
+                _ = wait_for_stop(&mut stop) => {
+                    stopped = true;
+                    let timeout = std::time::Duration::from_secs(TASK_STOP_TIMEOUT_SECS);
+                    match tokio::time::timeout(timeout, &mut run).await {
+                        Ok(result) => result,
+                        Err(_) => Err(anyhow::anyhow!(
+                            "The run is aborted, because the task is stopped and the run doesn't finish in {} seconds.",
+                            TASK_STOP_TIMEOUT_SECS
+                        )),
+                    }
+                }
+            };
+            match &result {
+                Ok(summary) if summary.is_empty() => {}
+                Ok(summary) => info!("The task {} is finished: {}", name, summary),
+                Err(e) => error!("The task {} failed: {}", name, e),
+            }
+
+            if let Some(id) = run_id {
+                if let Err(e) = ScheduledTaskRun::finish(&pool, id, &result).await {
+                    warn!("Failed to record the outcome of the task {}: {}", name, e);
+                }
+            }
+
+            if stopped {
+                break;
+            }
+        }
+
+        info!("The task {} is stopped.", name);
+    });
+
+    let registered = RegisteredTask {
+        stop: stop_sender,
+        handle,
+    };
+    if let Some(old) = REGISTERED_TASKS
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), registered)
+    {
+        warn!(
+            "The task {} is registered again, the old one is stopped.",
+            name
+        );
+        let _ = old.stop.send(true);
+    }
+}
+
+/// The names of the registered tasks.
+pub fn get_registered_tasks() -> Vec<String> {
+    let mut names = REGISTERED_TASKS
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<String>>();
+    names.sort();
+    names
+}
+
+/// Stop a registered task and wait until it's stopped. The running run is finished or aborted after TASK_STOP_TIMEOUT_SECS seconds, and it's recorded as usual. Return false if the task is not registered.
+pub async fn stop_task(name: &str) -> bool {
+    let registered = REGISTERED_TASKS.lock().unwrap().remove(name);
+    match registered {
+        Some(registered) => {
+            let _ = registered.stop.send(true);
+            if let Err(e) = registered.handle.await {
+                warn!("The task {} is not stopped cleanly: {}", name, e);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stop all registered tasks, such as when the server is shutting down. The tasks are stopped at the same time, so it takes at most TASK_STOP_TIMEOUT_SECS seconds.
+pub async fn stop_all_tasks() {
+    let registered = REGISTERED_TASKS
+        .lock()
+        .unwrap()
+        .drain()
+        .collect::<Vec<(String, RegisteredTask)>>();
+    for (_, task) in registered.iter() {
+        let _ = task.stop.send(true);
+    }
+    for (name, task) in registered {
+        if let Err(e) = task.handle.await {
+            warn!("The task {} is not stopped cleanly: {}", name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDatabase;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_task_runs() {
+        let db = TestDatabase::new().await.unwrap();
+
+        let id = ScheduledTaskRun::start(&db.pool, "refresh-trending-entities")
+            .await
+            .unwrap();
+        ScheduledTaskRun::finish(&db.pool, id, &Ok("100 entities are ranked.".to_string()))
+            .await
+            .unwrap();
+        let id = ScheduledTaskRun::start(&db.pool, "refresh-trending-entities")
+            .await
+            .unwrap();
+        ScheduledTaskRun::finish(&db.pool, id, &Err(anyhow::anyhow!("timeout")))
+            .await
+            .unwrap();
+        ScheduledTaskRun::start(&db.pool, "refresh-entity-metadata")
+            .await
+            .unwrap();
+        assert_eq!(
+            ScheduledTaskRun::fail_interrupted(&db.pool).await.unwrap(),
+            1
+        );
+
+        let runs = ScheduledTaskRun::list(
+            &db.pool,
+            Some("refresh-trending-entities"),
+            &None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].status, "failed");
+        assert_eq!(runs[0].error, Some("timeout".to_string()));
+        assert_eq!(runs[1].status, "succeeded");
+        assert_eq!(
+            runs[1].message,
+            Some("100 entities are ranked.".to_string())
+        );
+        assert!(runs[1].finished_at.is_some());
+
+        let runs = ScheduledTaskRun::list(
+            &db.pool,
+            None,
+            &Some(vec!["failed".to_string()]),
+            Some(1),
+            Some(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].task_name, "refresh-entity-metadata");
+
+        db.cleanup().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stop_task() {
+        let db = TestDatabase::new().await.unwrap();
+        let counter = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let task_counter = counter.clone();
+        register_task(
+            &db.pool,
+            "test-stop-task",
+            TaskSchedule::Interval(std::time::Duration::from_millis(50)),
+            move || {
+                let counter = task_counter.clone();
+                async move {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    AnyOk(String::new())
+                }
+            },
+        );
+        assert!(get_registered_tasks().contains(&"test-stop-task".to_string()));
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(stop_task("test-stop-task").await);
+        assert!(!stop_task("test-stop-task").await);
+        assert!(!get_registered_tasks().contains(&"test-stop-task".to_string()));
+
+        // No run is started after the task is stopped, and no run is left running.
+        let count = counter.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(count > 0);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(counter.load(std::sync::atomic::Ordering::SeqCst), count);
+        let runs = ScheduledTaskRun::list(
+            &db.pool,
+            Some("test-stop-task"),
+            &Some(vec!["running".to_string()]),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(runs.is_empty());
+
+        db.cleanup().await.unwrap();
+    }
+
+    #[test]
+    fn test_cron_expression() {
+        let expr = CronExpression::parse("*/15 9-17 * * 1-5").unwrap();
+        // 2024-03-01 is a Friday.
+        assert_eq!(
+            expr.next_after(at(2024, 3, 1, 9, 0)),
+            Some(at(2024, 3, 1, 9, 15))
+        );
+        assert_eq!(
+            expr.next_after(at(2024, 3, 1, 17, 45)),
+            Some(at(2024, 3, 4, 9, 0))
+        );
+
+        // The day matches if either the day of month or the day of week matches.
+        let expr = CronExpression::parse("0 0 1 * 0").unwrap();
+        assert_eq!(
+            expr.next_after(at(2024, 3, 1, 0, 0)),
+            Some(at(2024, 3, 3, 0, 0))
+        );
+        assert_eq!(
+            expr.next_after(at(2024, 3, 31, 0, 0)),
+            Some(at(2024, 4, 1, 0, 0))
+        );
+
+        let expr = CronExpression::parse("30 2 29 2 7").unwrap();
+        assert_eq!(
+            expr.next_after(at(2024, 2, 26, 0, 0)),
+            Some(at(2024, 2, 29, 2, 30))
+        );
+
+        let expr = CronExpression::parse("0 0 1 1 *").unwrap();
+        assert_eq!(
+            expr.next_after(at(2024, 12, 31, 23, 59)),
+            Some(at(2025, 1, 1, 0, 0))
+        );
+
+        assert_eq!(
+            CronExpression::parse("0 0 31 2 *")
+                .unwrap()
+                .next_after(at(2024, 1, 1, 0, 0)),
+            None
+        );
+        assert!(CronExpression::parse("0 3 * *").is_err());
+        assert!(CronExpression::parse("60 3 * * *").is_err());
+        assert!(CronExpression::parse("*/0 3 * * *").is_err());
+        assert!(CronExpression::parse("0 5-3 * * *").is_err());
+    }
+}
@@ -1,17 +1,25 @@
 //! Utility functions for the model module. Contains functions to import data from CSV files into the database, and to update the metadata tables.
 
+use super::core::notify_metadata_updated;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
-use polars::prelude::IntoVec;
+use poem_openapi::{Enum, Object};
+use polars::prelude::{CsvWriter, IntoVec, ParquetReader, SerReader, SerWriter};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Read};
 use std::sync::Mutex;
 use std::{error::Error, fmt, path::PathBuf};
 
+pub const PARQUET_EXTENSION: &str = "parquet";
+
 lazy_static! {
     static ref EXISTING_COLORS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    static ref PMID_PREFIX_REGEX: Regex = Regex::new(r"(?i)pmid\s*:\s*").unwrap();
 }
 
 /// Custom Error type for the graph module
@@ -98,8 +106,7 @@ pub fn read_annotation_file(filepath: &PathBuf) -> Result<HashMap<String, String
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(true)
         .delimiter(delimiter)
-        .from_path(filepath)
-        .unwrap();
+        .from_reader(open_data_file(filepath)?);
 
     // Check the annotation file format.
     let headers = reader.headers().unwrap();
@@ -142,9 +149,59 @@ pub fn update_existing_colors(entity_types: &Vec<String>) {
     }
 }
 
+/// The extensions of the compressed data files, they are decompressed on the fly when reading, such as `relations.tsv.gz` and `relations.tsv.zst`.
+pub const COMPRESSED_EXTENSIONS: [&str; 2] = ["gz", "zst"];
+
+/// Whether the file is compressed by gzip or zstd.
+pub fn is_compressed_file(filepath: &PathBuf) -> bool {
+    match filepath.extension().and_then(|e| e.to_str()) {
+        Some(suffix) => COMPRESSED_EXTENSIONS.contains(&suffix),
+        None => false,
+    }
+}
+
+/// Get the extension of the data in a file, the compression extension is ignored.
+///
+/// # Example
+///
+/// ```
+/// use std::path::PathBuf;
+/// use biomedgps::model::util::get_data_extension;
+///
+/// assert_eq!(get_data_extension(&PathBuf::from("relations.tsv")), Some("tsv".to_string()));
+/// assert_eq!(get_data_extension(&PathBuf::from("relations.tsv.gz")), Some("tsv".to_string()));
+/// assert_eq!(get_data_extension(&PathBuf::from("relations.csv.zst")), Some("csv".to_string()));
+/// assert_eq!(get_data_extension(&PathBuf::from("relations.gz")), None);
+/// ```
+pub fn get_data_extension(filepath: &PathBuf) -> Option<String> {
+    let filepath = if is_compressed_file(filepath) {
+        filepath.with_extension("")
+    } else {
+        filepath.clone()
+    };
+
+    filepath
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_string())
+}
+
+/// Open a data file for reading, the gzip and zstd files are decompressed on the fly, so the large dumps don't need to be decompressed to disk first.
+pub fn open_data_file(filepath: &PathBuf) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let file = std::fs::File::open(filepath)?;
+    match filepath.extension().and_then(|e| e.to_str()) {
+        // The dumps might be concatenated from several gzip members, such as the output of pigz.
+        Some("gz") => Ok(Box::new(flate2::read::MultiGzDecoder::new(BufReader::new(
+            file,
+        )))),
+        Some("zst") => Ok(Box::new(zstd::stream::read::Decoder::new(file)?)),
+        _ => Ok(Box::new(BufReader::new(file))),
+    }
+}
+
 pub fn get_delimiter(filepath: &PathBuf) -> Result<u8, Box<dyn Error>> {
-    let suffix = match filepath.extension() {
-        Some(suffix) => suffix.to_str().unwrap(),
+    let suffix = match get_data_extension(filepath) {
+        Some(suffix) => suffix,
         None => return Err("File has no extension".into()),
     };
 
@@ -159,6 +216,492 @@ pub fn get_delimiter(filepath: &PathBuf) -> Result<u8, Box<dyn Error>> {
     }
 }
 
+/// How many bytes at the beginning of a data file are sniffed for the encoding and the line endings.
+pub const SNIFF_MAX_BYTES: usize = 65536;
+
+/// How many lines with a wrong number of columns are listed in the diagnostic, the others are only counted.
+pub const SNIFF_MAX_REPORTED_LINES: usize = 5;
+
+/// The delimiters which are tried when the header can't be split by the expected delimiter.
+const CANDIDATE_DELIMITERS: [u8; 5] = [b'\t', b',', b';', b'|', b' '];
+
+fn delimiter_name(delimiter: u8) -> String {
+    match delimiter {
+        b'\t' => "tab".to_string(),
+        b',' => "comma".to_string(),
+        b';' => "semicolon".to_string(),
+        b'|' => "pipe".to_string(),
+        b' ' => "space".to_string(),
+        d => format!("{:?}", d as char),
+    }
+}
+
+/// A problem of a data file which is found before the validation, such as a wrong encoding or delimiter. The fatal problems make the validation fail, the others are only warned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDiagnostic {
+    pub fatal: bool,
+    pub message: String,
+}
+
+impl InputDiagnostic {
+    fn error(message: String) -> Self {
+        InputDiagnostic {
+            fatal: true,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        InputDiagnostic {
+            fatal: false,
+            message,
+        }
+    }
+}
+
+/// Sniff the beginning of a data file for the encoding, the byte order mark, the line endings and the delimiter of the header. These problems cause cryptic errors in the validation, such as a first column which is never found because of the BOM.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::util::sniff_data;
+///
+/// assert!(sniff_data(b"id\tname\nA\tB\n", b'\t').is_empty());
+///
+/// let diagnostics = sniff_data(b"\xEF\xBB\xBFid\tname\nA\tB\n", b'\t');
+/// assert!(diagnostics[0].fatal && diagnostics[0].message.contains("BOM"));
+///
+/// let diagnostics = sniff_data(b"id,name,label\nA,B,C\n", b'\t');
+/// assert!(diagnostics[0].fatal && diagnostics[0].message.contains("comma"));
+///
+/// let diagnostics = sniff_data(b"id\tname\nCaf\xE9\tB\n", b'\t');
+/// assert!(diagnostics[0].message.contains("line 2"));
+///
+/// let diagnostics = sniff_data(b"id\tname\r\nA\tB\r\n", b'\t');
+/// assert!(!diagnostics[0].fatal && diagnostics[0].message.contains("CRLF"));
+/// ```
+pub fn sniff_data(head: &[u8], delimiter: u8) -> Vec<InputDiagnostic> {
+    let mut diagnostics = vec![];
+
+    // The other checks are meaningless for the UTF-16 files.
+    if head.starts_with(&[0xFF, 0xFE]) || head.starts_with(&[0xFE, 0xFF]) {
+        diagnostics.push(InputDiagnostic::error(
+            "The file is encoded in UTF-16, please save it as UTF-8.".to_string(),
+        ));
+        return diagnostics;
+    }
+
+    let head = if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        diagnostics.push(InputDiagnostic::error(
+            "The file starts with a UTF-8 byte order mark (BOM), it becomes a part of the first column name. Please save the file as UTF-8 without BOM.".to_string(),
+        ));
+        &head[3..]
+    } else {
+        head
+    };
+    let line_of = |pos: usize| head[..pos].iter().filter(|b| **b == b'\n').count() + 1;
+
+    if let Some(pos) = head.iter().position(|b| *b == 0) {
+        diagnostics.push(InputDiagnostic::error(format!(
+            "The file contains a NUL byte at line {}, it might be encoded in UTF-16 or it's not a text file.",
+            line_of(pos)
+        )));
+        return diagnostics;
+    }
+
+    // The last character might be cut by the sniffing, so an incomplete character at the end is not an error.
+    if let Err(e) = std::str::from_utf8(head) {
+        if e.error_len().is_some() {
+            diagnostics.push(InputDiagnostic::error(format!(
+                "The file is not valid UTF-8 at line {}, it might be encoded in Latin-1 or Windows-1252 (such as the files exported by Excel). Please save it as UTF-8.",
+                line_of(e.valid_up_to())
+            )));
+        }
+    }
+
+    let mut crlf = 0;
+    let mut lf = 0;
+    let mut cr = 0;
+    for (i, b) in head.iter().enumerate() {
+        match b {
+            b'\n' if i > 0 && head[i - 1] == b'\r' => crlf += 1,
+            b'\n' => lf += 1,
+            b'\r' if head.get(i + 1) != Some(&b'\n') && i + 1 < head.len() => cr += 1,
+            _ => {}
+        }
+    }
+    if cr > 0 {
+        diagnostics.push(InputDiagnostic::error(format!(
+            "{} lines end with a carriage return (CR) only, such as the files of classic Mac OS. Please convert the line endings to LF.",
+            cr
+        )));
+    } else if crlf > 0 && lf > 0 {
+        diagnostics.push(InputDiagnostic::warning(format!(
+            "The line endings are mixed, {} lines end with CRLF and {} lines end with LF.",
+            crlf, lf
+        )));
+    } else if crlf > 0 {
+        diagnostics.push(InputDiagnostic::warning(
+            "The lines end with CRLF (Windows line endings), the carriage returns are removed from the values.".to_string(),
+        ));
+    }
+
+    // The header rarely contains quoted delimiters, so it's split naively.
+    let header_end = head.iter().position(|b| *b == b'\n').unwrap_or(head.len());
+    let header = String::from_utf8_lossy(&head[..header_end]);
+    let header = header.trim_end_matches('\r');
+    let num_columns = header.split(delimiter as char).count();
+    if num_columns == 1 {
+        let detected = CANDIDATE_DELIMITERS
+            .iter()
+            .filter(|d| **d != delimiter)
+            .map(|d| (*d, header.split(*d as char).count()))
+            .filter(|(_, n)| *n > 1)
+            .max_by_key(|(_, n)| *n);
+        if let Some((detected, n)) = detected {
+            diagnostics.push(InputDiagnostic::error(format!(
+                "The header has only one column when it's split by {}, but it has {} columns when it's split by {}. Please check the delimiter and the extension of the file, the .tsv files are separated by tab and the .csv files are separated by comma.",
+                delimiter_name(delimiter),
+                n,
+                delimiter_name(detected)
+            )));
+        }
+    }
+
+    diagnostics
+}
+
+/// Sniff a data file for the problems which cause cryptic errors in the validation. The beginning of the file is checked by [`sniff_data`], and then all lines are checked for the invalid UTF-8 and the number of columns, the lines with a wrong number of columns usually have an unquoted delimiter or mixed delimiters.
+pub fn sniff_data_file(
+    filepath: &PathBuf,
+    delimiter: u8,
+) -> Result<Vec<InputDiagnostic>, Box<dyn Error>> {
+    let mut head = vec![];
+    open_data_file(filepath)?
+        .take(SNIFF_MAX_BYTES as u64)
+        .read_to_end(&mut head)?;
+    let mut diagnostics = sniff_data(&head, delimiter);
+    if diagnostics.iter().any(|d| d.fatal) {
+        return Ok(diagnostics);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(open_data_file(filepath)?);
+    let num_columns = reader.byte_headers()?.len();
+
+    let mut invalid_utf8_line = None;
+    let mut mismatched_lines: Vec<String> = vec![];
+    let mut num_mismatched = 0;
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        if invalid_utf8_line.is_none() && std::str::from_utf8(record.as_slice()).is_err() {
+            invalid_utf8_line = Some(line);
+        }
+
+        if record.len() != num_columns {
+            num_mismatched += 1;
+            if mismatched_lines.len() < SNIFF_MAX_REPORTED_LINES {
+                // A line without the delimiter might use another one.
+                let other = CANDIDATE_DELIMITERS
+                    .iter()
+                    .find(|d| **d != delimiter && record.len() == 1 && record[0].contains(*d));
+                mismatched_lines.push(match other {
+                    Some(d) => format!(
+                        "line {} ({} column, it contains {})",
+                        line,
+                        record.len(),
+                        delimiter_name(*d)
+                    ),
+                    None => format!("line {} ({} columns)", line, record.len()),
+                });
+            }
+        }
+    }
+
+    if let Some(line) = invalid_utf8_line {
+        diagnostics.push(InputDiagnostic::error(format!(
+            "The file is not valid UTF-8 at line {}, it might be encoded in Latin-1 or Windows-1252 (such as the files exported by Excel). Please save it as UTF-8.",
+            line
+        )));
+    }
+
+    if num_mismatched > 0 {
+        diagnostics.push(InputDiagnostic::error(format!(
+            "{} lines don't have {} columns like the header, such as {}. The values might contain an unquoted {} or the lines use a different delimiter.",
+            num_mismatched,
+            num_columns,
+            mismatched_lines.join(", "),
+            delimiter_name(delimiter)
+        )));
+    }
+
+    Ok(diagnostics)
+}
+
+/// Whether the file is a parquet file, it's converted into a tsv file before checking and importing.
+pub fn is_parquet_file(filepath: &PathBuf) -> bool {
+    match filepath.extension() {
+        Some(suffix) => suffix.to_str() == Some(PARQUET_EXTENSION),
+        None => false,
+    }
+}
+
+/// Whether the file can be imported, the tsv/csv/txt and parquet files are supported. The tsv/csv/txt files might be compressed by gzip or zstd.
+///
+/// # Example
+///
+/// ```
+/// use std::path::PathBuf;
+/// use biomedgps::model::util::is_supported_file;
+///
+/// assert!(is_supported_file(&PathBuf::from("entities.parquet")));
+/// assert!(is_supported_file(&PathBuf::from("entities.tsv")));
+/// assert!(is_supported_file(&PathBuf::from("entities.tsv.gz")));
+/// assert!(is_supported_file(&PathBuf::from("entities.tsv.zst")));
+/// assert!(!is_supported_file(&PathBuf::from("entities.json")));
+/// ```
+pub fn is_supported_file(filepath: &PathBuf) -> bool {
+    get_delimiter(filepath).is_ok() || is_parquet_file(filepath)
+}
+
+/// Convert a parquet file into a tsv file with the same columns.
+///
+/// # Arguments
+/// * `in_filepath` - The path of the parquet file
+/// * `out_filepath` - The path of the tsv file
+pub fn convert_parquet_to_tsv(
+    in_filepath: &PathBuf,
+    out_filepath: &PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    let mut df = ParquetReader::new(std::fs::File::open(in_filepath)?).finish()?;
+    let writer = std::fs::File::create(out_filepath)?;
+    CsvWriter::new(writer)
+        .has_header(true)
+        .with_delimiter(b'\t')
+        .finish(&mut df)?;
+
+    Ok(())
+}
+
+/// Prepare a data file for checking and importing. A parquet file is converted into a temporary tsv file in the same directory, the temporary file is removed when the returned TempPath is dropped. Other files are returned as they are.
+///
+/// # Arguments
+/// * `filepath` - The path of the data file
+///
+/// # Returns
+/// * `Result<(PathBuf, Option<tempfile::TempPath>), Box<dyn Error>>` - The path of the file to import and the temporary file if it's converted
+pub fn prepare_data_file(
+    filepath: &PathBuf,
+) -> Result<(PathBuf, Option<tempfile::TempPath>), Box<dyn Error>> {
+    if !is_parquet_file(filepath) {
+        return Ok((filepath.clone(), None));
+    }
+
+    // The temporary file must be in the same directory, the database server might need to access it when importing.
+    let pardir = match filepath.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let temp_path = tempfile::Builder::new()
+        .suffix(".tsv")
+        .tempfile_in(&pardir)?
+        .into_temp_path();
+    let temp_filepath = temp_path.to_path_buf();
+    convert_parquet_to_tsv(filepath, &temp_filepath)?;
+    info!(
+        "Convert the parquet file {} into {}.",
+        filepath.display(),
+        temp_filepath.display()
+    );
+
+    Ok((temp_filepath, Some(temp_path)))
+}
+
+/// Get the columns which contain the entity ids in the data file of a table, they are rewritten by the id mapping. It's empty if the table is not supported.
+pub fn get_id_columns(table: &str) -> Vec<&'static str> {
+    match table {
+        "entity" => vec!["id"],
+        "relation" => vec!["source_id", "target_id"],
+        _ => vec![],
+    }
+}
+
+/// Maps the entity ids from the source resources to the canonical ids, such as HGNC:5 and ENSEMBL:ENSG00000121410 to ENTREZ:1. The source files might mix the ids from several resources, they are normalized before importing.
+#[derive(Debug, Clone, Default)]
+pub struct IdMapping {
+    mappings: HashMap<String, String>,
+    source_prefixes: HashSet<String>,
+}
+
+impl IdMapping {
+    /// Read the mapping file which contains the source_id, source_prefix and canonical_id columns. The source_id might contain the prefix or not, such as HGNC:5 or 5.
+    pub fn from_file(filepath: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let delimiter = get_delimiter(filepath)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(open_data_file(filepath)?);
+
+        let headers = reader.headers()?.clone();
+        let mut indices = vec![];
+        for col in ["source_id", "source_prefix", "canonical_id"] {
+            match headers.iter().position(|h| h == col) {
+                Some(i) => indices.push(i),
+                None => {
+                    return Err(format!(
+                        "The id mapping file should have the source_id, source_prefix and canonical_id columns, but the {} column is not found.",
+                        col
+                    )
+                    .into())
+                }
+            }
+        }
+
+        let mut id_mapping = IdMapping::default();
+        for record in reader.records() {
+            let record = record?;
+            id_mapping.insert(
+                &record[indices[0]],
+                &record[indices[1]],
+                &record[indices[2]],
+            );
+        }
+
+        Ok(id_mapping)
+    }
+
+    pub fn insert(&mut self, source_id: &str, source_prefix: &str, canonical_id: &str) {
+        let prefix = format!("{}:", source_prefix);
+        let source_id = if source_id.starts_with(&prefix) {
+            source_id.to_string()
+        } else {
+            format!("{}{}", prefix, source_id)
+        };
+
+        self.source_prefixes.insert(source_prefix.to_string());
+        self.mappings.insert(source_id, canonical_id.to_string());
+    }
+
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Map an id to the canonical id. It's None if the id doesn't need to be mapped or it's not in the mapping, the `is_unmapped` function tells the difference.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::util::IdMapping;
+    ///
+    /// let mut id_mapping = IdMapping::default();
+    /// id_mapping.insert("5", "HGNC", "ENTREZ:1");
+    /// id_mapping.insert("ENSEMBL:ENSG00000121410", "ENSEMBL", "ENTREZ:1");
+    ///
+    /// assert_eq!(id_mapping.map_id("HGNC:5"), Some("ENTREZ:1"));
+    /// assert_eq!(id_mapping.map_id("ENSEMBL:ENSG00000121410"), Some("ENTREZ:1"));
+    /// assert_eq!(id_mapping.map_id("ENTREZ:1"), None);
+    /// assert!(!id_mapping.is_unmapped("ENTREZ:1"));
+    /// assert!(id_mapping.is_unmapped("HGNC:6"));
+    /// ```
+    pub fn map_id(&self, id: &str) -> Option<&str> {
+        self.mappings.get(id).map(|c| c.as_str())
+    }
+
+    /// Whether the id comes from a source resource of the mapping but it's not in the mapping.
+    pub fn is_unmapped(&self, id: &str) -> bool {
+        match id.split_once(':') {
+            Some((prefix, _)) => {
+                self.source_prefixes.contains(prefix) && !self.mappings.contains_key(id)
+            }
+            None => false,
+        }
+    }
+
+    /// Rewrite the ids in the columns of a data file into a temporary file in the same directory. The unmapped ids are kept as they are and written into the report file with the number of rows.
+    ///
+    /// # Arguments
+    /// * `filepath` - The path of the data file
+    /// * `columns` - The columns which contain the entity ids, such as source_id and target_id
+    /// * `report_filepath` - The path of the report file, it's only written if there are unmapped ids
+    ///
+    /// # Returns
+    /// * `Result<(tempfile::TempPath, usize, usize), Box<dyn Error>>` - The temporary file, how many ids are rewritten and how many distinct ids are unmapped
+    pub fn apply(
+        &self,
+        filepath: &PathBuf,
+        columns: &Vec<&str>,
+        report_filepath: &PathBuf,
+    ) -> Result<(tempfile::TempPath, usize, usize), Box<dyn Error>> {
+        let delimiter = get_delimiter(filepath)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(open_data_file(filepath)?);
+        let headers = reader.headers()?.clone();
+        let indices = headers
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| columns.contains(h))
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+
+        // The temporary file must be in the same directory like the converted parquet file, the database server might need to access it when importing.
+        let pardir = match filepath.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        // The rewritten file is not compressed, so the database server can read it.
+        let extension = get_data_extension(filepath).unwrap_or_default();
+        let temp_path = tempfile::Builder::new()
+            .suffix(&format!(".{}", extension))
+            .tempfile_in(&pardir)?
+            .into_temp_path();
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_path(&temp_path)?;
+        writer.write_record(&headers)?;
+
+        let mut num_of_mapped = 0;
+        let mut unmapped: BTreeMap<String, usize> = BTreeMap::new();
+        for record in reader.records() {
+            let mut record = record?
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>();
+            for &i in indices.iter() {
+                match self.map_id(&record[i]) {
+                    Some(canonical_id) => {
+                        record[i] = canonical_id.to_string();
+                        num_of_mapped += 1;
+                    }
+                    None if self.is_unmapped(&record[i]) => {
+                        *unmapped.entry(record[i].clone()).or_insert(0) += 1;
+                    }
+                    None => {}
+                }
+            }
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+
+        if !unmapped.is_empty() {
+            let mut report = csv::WriterBuilder::new()
+                .delimiter(b'\t')
+                .from_path(report_filepath)?;
+            report.write_record(&["id", "num_of_rows"])?;
+            for (id, count) in unmapped.iter() {
+                report.write_record(&[id.as_str(), count.to_string().as_str()])?;
+            }
+            report.flush()?;
+        }
+
+        Ok((temp_path, num_of_mapped, unmapped.len()))
+    }
+}
+
 pub async fn drop_table(pool: &sqlx::PgPool, table: &str) {
     debug!("Dropping table {}...", table);
     sqlx::query(&format!(
@@ -192,6 +735,169 @@ pub async fn drop_records(pool: &sqlx::PgPool, table: &str, colname: &str, colva
     .unwrap();
 }
 
+/// How the rows which conflict with the existing rows on the unique columns are imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the existing rows and ignore the conflicted rows.
+    Skip,
+    /// Replace the values of the existing rows with the values of the conflicted rows.
+    Overwrite,
+    /// Merge the pmids and the key sentences into the existing rows, the other values are replaced if they are not empty.
+    Merge,
+}
+
+impl ConflictStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(ConflictStrategy::Skip),
+            "overwrite" => Some(ConflictStrategy::Overwrite),
+            "merge" => Some(ConflictStrategy::Merge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ConflictStrategy::Skip => "skip",
+            ConflictStrategy::Overwrite => "overwrite",
+            ConflictStrategy::Merge => "merge",
+        }
+    }
+
+    /// The expression which updates a column of an existing row from the staging table, None if the column is kept.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::util::ConflictStrategy;
+    ///
+    /// assert_eq!(ConflictStrategy::Skip.update_expr("biomedgps_relation", "score"), None);
+    /// assert_eq!(
+    ///     ConflictStrategy::Overwrite.update_expr("biomedgps_relation", "score"),
+    ///     Some("staging.score".to_string())
+    /// );
+    /// assert_eq!(
+    ///     ConflictStrategy::Merge.update_expr("biomedgps_relation", "score"),
+    ///     Some("COALESCE(staging.score, biomedgps_relation.score)".to_string())
+    /// );
+    /// ```
+    pub fn update_expr(&self, table_name: &str, column: &str) -> Option<String> {
+        match (self, column) {
+            (ConflictStrategy::Skip, _) => None,
+            (ConflictStrategy::Overwrite, _) => Some(format!("staging.{}", column)),
+            (ConflictStrategy::Merge, "pmids") => Some(format!(
+                "NULLIF(array_to_string(ARRAY(SELECT DISTINCT p FROM unnest(string_to_array(concat_ws('|', {t}.pmids, staging.pmids), '|')) AS p WHERE p <> '' ORDER BY p), '|'), '')",
+                t = table_name
+            )),
+            // The new key sentence is appended unless it has been in the existing key sentence.
+            (ConflictStrategy::Merge, "key_sentence") => Some(format!(
+                "CASE
+                    WHEN COALESCE({t}.key_sentence, '') = '' THEN staging.key_sentence
+                    WHEN COALESCE(staging.key_sentence, '') = '' OR position(staging.key_sentence IN {t}.key_sentence) > 0 THEN {t}.key_sentence
+                    ELSE {t}.key_sentence || ' ' || staging.key_sentence
+                END",
+                t = table_name
+            )),
+            (ConflictStrategy::Merge, _) => Some(format!(
+                "COALESCE(staging.{c}, {t}.{c})",
+                c = column,
+                t = table_name
+            )),
+        }
+    }
+}
+
+/// The format of the data files to import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// The csv/tsv/parquet/json table dumps, the columns are the same as the table.
+    Table,
+    /// The OBO graph JSON files of the ontologies, the terms are imported as entities and the is_a edges are imported into the entity hierarchy table.
+    Obograph,
+}
+
+impl ImportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "table" => Some(ImportFormat::Table),
+            "obograph" => Some(ImportFormat::Obograph),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ImportFormat::Table => "table",
+            ImportFormat::Obograph => "obograph",
+        }
+    }
+}
+
+/// Move the rows from the staging table into the table, the rows which conflict with the unique columns are handled by the conflict strategy.
+///
+/// # Arguments
+/// * `tx` - The transaction which creates the staging table
+/// * `table_name` - The table name, such as biomedgps_relation
+/// * `columns` - The columns of the staging table which are imported
+/// * `unique_columns` - The unique columns of the table
+/// * `strategy` - How the conflicted rows are handled
+///
+/// # Returns
+/// * `Result<u64, Box<dyn Error>>` - How many rows are inserted or updated
+pub async fn upsert_from_staging(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table_name: &str,
+    columns: &Vec<String>,
+    unique_columns: &Vec<String>,
+    strategy: ConflictStrategy,
+) -> Result<u64, Box<dyn Error>> {
+    let where_clause = unique_columns
+        .iter()
+        .map(|c| format!("{}.{} = staging.{}", table_name, c, c))
+        .collect::<Vec<String>>()
+        .join(" AND ");
+
+    let mut num_of_rows = 0;
+    let set_clause = columns
+        .iter()
+        .filter(|c| !unique_columns.contains(c))
+        .filter_map(|c| {
+            strategy
+                .update_expr(table_name, c)
+                .map(|e| format!("{} = {}", c, e))
+        })
+        .collect::<Vec<String>>();
+    if !set_clause.is_empty() {
+        let result = sqlx::query(&format!(
+            "UPDATE {} SET {} FROM staging WHERE {}",
+            table_name,
+            set_clause.join(", "),
+            where_clause
+        ))
+        .execute(&mut *tx)
+        .await?;
+        debug!(
+            "Update {} existing rows of {} by the {} strategy.",
+            result.rows_affected(),
+            table_name,
+            strategy.as_str()
+        );
+        num_of_rows += result.rows_affected();
+    }
+
+    let columns = columns.join(",");
+    let result = sqlx::query(&format!(
+        "INSERT INTO {} ({})
+         SELECT {} FROM staging
+         WHERE NOT EXISTS (SELECT 1 FROM {} WHERE {})
+         ON CONFLICT DO NOTHING",
+        table_name, columns, columns, table_name, where_clause
+    ))
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(num_of_rows + result.rows_affected())
+}
+
 pub async fn import_file_in_loop(
     pool: &sqlx::PgPool,
     filepath: &PathBuf,
@@ -199,6 +905,7 @@ pub async fn import_file_in_loop(
     expected_columns: &Vec<String>,
     unique_columns: &Vec<String>,
     delimiter: u8,
+    strategy: ConflictStrategy,
 ) -> Result<(), Box<dyn Error>> {
     match sqlx::query("DROP TABLE IF EXISTS staging")
         .execute(pool)
@@ -229,20 +936,13 @@ pub async fn import_file_in_loop(
 
     sqlx::query(&query_str).execute(&mut tx).await?;
 
-    let where_clause = unique_columns
-        .iter()
-        .map(|c| format!("{}.{} = staging.{}", table_name, c, c))
-        .collect::<Vec<String>>()
-        .join(" AND ");
-
-    sqlx::query(&format!(
-        "INSERT INTO {} ({})
-         SELECT {} FROM staging
-         WHERE NOT EXISTS (SELECT 1 FROM {} WHERE {})
-         ON CONFLICT DO NOTHING",
-        table_name, columns, columns, table_name, where_clause
-    ))
-    .execute(&mut tx)
+    upsert_from_staging(
+        &mut tx,
+        table_name,
+        expected_columns,
+        unique_columns,
+        strategy,
+    )
     .await?;
 
     tx.commit().await?;
@@ -258,6 +958,411 @@ pub async fn import_file_in_loop(
     Ok(())
 }
 
+/// The kind of a non-fatal issue of an import.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Enum)]
+#[oai(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ImportWarningKind {
+    // An option which doesn't apply to the table or the mode, such as --chunk-size for the publication table.
+    IgnoredOption,
+    // A column of the data file which is not in the table, it's not imported.
+    SkippedColumn,
+    // A relation type which is not in the relation type mappings, it's imported without formatting.
+    UnknownRelationType,
+    // An id which comes from a source resource of the id mapping but it's not in the mapping, it's imported as it is.
+    UnmappedId,
+    // The dataset is not set, the default dataset is used.
+    DefaultDataset,
+    // An entity of the curated knowledges which is not in the entity file.
+    MissingCuratedEntity,
+    // A data file which fails to import, such as an invalid file, the other files are still imported.
+    FailedFile,
+}
+
+/// A non-fatal issue of an import, the import continues but the pipelines might decide not to proceed with the imported data.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object)]
+pub struct ImportWarning {
+    pub kind: ImportWarningKind,
+    // The data file which causes the warning, it's None if the warning is about the options.
+    #[oai(skip_serializing_if_is_none)]
+    pub file: Option<String>,
+    pub message: String,
+}
+
+/// Collects the warnings of an import, each warning is logged when it's collected. The same warning is only collected once, such as an unknown relation type in many rows.
+#[derive(Debug, Clone, Default)]
+pub struct ImportWarnings {
+    warnings: Vec<ImportWarning>,
+    seen: HashSet<(ImportWarningKind, Option<String>, String)>,
+}
+
+impl ImportWarnings {
+    /// Collect a warning.
+    ///
+    /// # Example
+    /// ```
+    /// use biomedgps::model::util::{ImportWarningKind, ImportWarnings};
+    ///
+    /// let mut warnings = ImportWarnings::default();
+    /// let message = "The relation type DRUGBANK::treats is not in the relation_type_mappings.";
+    /// warnings.push(ImportWarningKind::UnknownRelationType, Some("relations.tsv"), message);
+    /// warnings.push(ImportWarningKind::UnknownRelationType, Some("relations.tsv"), message);
+    /// warnings.push(ImportWarningKind::IgnoredOption, None, "The --chunk-size option is ignored.");
+    /// assert_eq!(warnings.len(), 2);
+    ///
+    /// let warnings = warnings.into_vec();
+    /// assert_eq!(warnings[0].kind, ImportWarningKind::UnknownRelationType);
+    /// assert_eq!(warnings[1].file, None);
+    /// ```
+    pub fn push(&mut self, kind: ImportWarningKind, file: Option<&str>, message: &str) {
+        let file = file.map(|f| f.to_string());
+        if !self.seen.insert((kind, file.clone(), message.to_string())) {
+            return;
+        }
+
+        warn!("{}", message);
+        self.warnings.push(ImportWarning {
+            kind,
+            file,
+            message: message.to_string(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<ImportWarning> {
+        self.warnings
+    }
+}
+
+/// The progress of importing a file, it's saved after each chunk so a failed import can be resumed from the last successful chunk.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct ImportProgress {
+    // Where the file is imported into, such as biomedgps_relation:<dataset> or neo4j:relation.
+    pub target: String,
+    // The path, size and modified time of the file, the progress is ignored if the file is changed.
+    pub file_key: String,
+    pub imported_rows: i64,
+    pub finished: bool,
+}
+
+impl ImportProgress {
+    /// Generate the key of a file from its path, size and modified time.
+    pub fn gen_file_key(filepath: &PathBuf) -> Result<String, Box<dyn Error>> {
+        let metadata = std::fs::metadata(filepath)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let path = std::fs::canonicalize(filepath)?;
+
+        Ok(format!(
+            "{}:{}:{}",
+            path.display(),
+            metadata.len(),
+            modified
+        ))
+    }
+
+    pub async fn fetch(
+        pool: &sqlx::PgPool,
+        target: &str,
+        file_key: &str,
+    ) -> Result<Option<ImportProgress>, sqlx::Error> {
+        sqlx::query_as::<_, ImportProgress>(
+            "SELECT target, file_key, imported_rows, finished FROM biomedgps_import_progress WHERE target = $1 AND file_key = $2",
+        )
+        .bind(target)
+        .bind(file_key)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn save(
+        pool: &sqlx::PgPool,
+        target: &str,
+        file_key: &str,
+        imported_rows: usize,
+        finished: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO biomedgps_import_progress (target, file_key, imported_rows, finished) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (target, file_key) DO UPDATE SET imported_rows = EXCLUDED.imported_rows, finished = EXCLUDED.finished, updated_at = now()",
+        )
+        .bind(target)
+        .bind(file_key)
+        .bind(imported_rows as i64)
+        .bind(finished)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get how many rows can be skipped when resuming the import of a file, None means the file has been imported completely.
+    pub async fn get_resume_point(
+        pool: &sqlx::PgPool,
+        target: &str,
+        file_key: &str,
+    ) -> Result<Option<usize>, sqlx::Error> {
+        match Self::fetch(pool, target, file_key).await? {
+            Some(progress) if progress.finished => Ok(None),
+            Some(progress) => Ok(Some(progress.imported_rows as usize)),
+            None => Ok(Some(0)),
+        }
+    }
+}
+
+/// The summary of a data file which is checked by the dry-run mode of the importdb command, nothing is written into the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub filename: String,
+    pub table_name: String,
+    pub num_of_rows: usize,
+    // The rows which have the same unique columns with a previous row in the file, they are ignored by the import.
+    pub num_of_duplicated_rows: usize,
+    // The rows which exist in the table, it's None if the existing rows are not checked, such as the table would be dropped.
+    pub num_of_existing_rows: Option<usize>,
+    // The entities of the curated knowledges which are not in the entity file, the curated knowledges would be broken by the import.
+    pub num_of_missing_curated_entities: usize,
+    pub entity_types: BTreeMap<String, usize>,
+    pub relation_types: BTreeMap<String, usize>,
+    pub datasets: BTreeMap<String, usize>,
+    // The unique columns and the unique keys of the rows, they are used to count the existing rows.
+    unique_columns: Vec<String>,
+    unique_keys: Vec<Vec<String>>,
+}
+
+impl ImportSummary {
+    /// Summarize a data file whose columns have been selected, such as the temporary file which is generated by the select_expected_columns function.
+    ///
+    /// # Arguments
+    /// * `filepath` - The data file
+    /// * `table_name` - The table which the file would be imported into, such as biomedgps_relation
+    /// * `unique_columns` - The unique columns of the table, the columns which are not in the file are ignored
+    /// * `delimiter` - The delimiter of the file
+    pub fn from_file(
+        filepath: &PathBuf,
+        table_name: &str,
+        unique_columns: &Vec<String>,
+        delimiter: u8,
+    ) -> Result<ImportSummary, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_path(filepath)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+
+        let unique_columns = unique_columns
+            .iter()
+            .filter(|c| column(c).is_some())
+            .cloned()
+            .collect::<Vec<String>>();
+        let unique_indexes = unique_columns
+            .iter()
+            .filter_map(|c| column(c))
+            .collect::<Vec<usize>>();
+        // The entity table has a label column, the relation table has the source_type and target_type columns.
+        let entity_type_indexes = ["label", "entity_type", "source_type", "target_type"]
+            .iter()
+            .filter_map(|c| column(c))
+            .collect::<Vec<usize>>();
+        let relation_type_index = column("relation_type");
+        let dataset_index = column("dataset");
+
+        let mut summary = ImportSummary {
+            filename: filepath.display().to_string(),
+            table_name: table_name.to_string(),
+            unique_columns,
+            ..Default::default()
+        };
+        let mut seen_keys = std::collections::HashSet::new();
+        for record in reader.records() {
+            let record = record?;
+            summary.num_of_rows += 1;
+
+            let count = |values: &mut BTreeMap<String, usize>, index: Option<usize>| {
+                if let Some(value) = index.and_then(|i| record.get(i)) {
+                    *values.entry(value.to_string()).or_insert(0) += 1;
+                }
+            };
+            for index in &entity_type_indexes {
+                count(&mut summary.entity_types, Some(*index));
+            }
+            count(&mut summary.relation_types, relation_type_index);
+            count(&mut summary.datasets, dataset_index);
+
+            if unique_indexes.is_empty() {
+                continue;
+            }
+
+            let key = unique_indexes
+                .iter()
+                .map(|i| record.get(*i).unwrap_or("").to_string())
+                .collect::<Vec<String>>();
+            if seen_keys.insert(key.clone()) {
+                summary.unique_keys.push(key);
+            } else {
+                summary.num_of_duplicated_rows += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Count the rows which exist in the table by the unique columns, the table is only read.
+    pub async fn count_existing_rows(&mut self, pool: &sqlx::PgPool) -> Result<usize, sqlx::Error> {
+        if self.unique_columns.is_empty() {
+            return Ok(0);
+        }
+
+        // The empty values are imported as NULL, so they are compared with IS NOT DISTINCT FROM.
+        let arrays = (1..=self.unique_columns.len())
+            .map(|i| format!("${}::text[]", i))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let conditions = self
+            .unique_columns
+            .iter()
+            .map(|c| format!("t.{c}::text IS NOT DISTINCT FROM NULLIF(k.{c}, '')", c = c))
+            .collect::<Vec<String>>()
+            .join(" AND ");
+        let sql_str = format!(
+            "SELECT COUNT(*) FROM unnest({arrays}) AS k({columns}) WHERE EXISTS (SELECT 1 FROM {table} t WHERE {conditions})",
+            arrays = arrays,
+            columns = self.unique_columns.join(", "),
+            table = self.table_name,
+            conditions = conditions
+        );
+
+        let mut existing = 0;
+        for keys in self.unique_keys.chunks(10000) {
+            let mut query = sqlx::query_scalar::<_, i64>(&sql_str);
+            for i in 0..self.unique_columns.len() {
+                query = query.bind(keys.iter().map(|k| k[i].clone()).collect::<Vec<String>>());
+            }
+            existing += query.fetch_one(pool).await? as usize;
+        }
+
+        self.num_of_existing_rows = Some(existing);
+        Ok(existing)
+    }
+
+    /// How many rows would be inserted, the duplicated rows and the existing rows are ignored by the import.
+    pub fn num_of_new_rows(&self) -> usize {
+        self.num_of_rows - self.num_of_duplicated_rows - self.num_of_existing_rows.unwrap_or(0)
+    }
+
+    /// Format the summary as a report for the terminal.
+    pub fn report(&self) -> String {
+        let format_counts = |values: &BTreeMap<String, usize>| {
+            if values.is_empty() {
+                return "-".to_string();
+            }
+            values
+                .iter()
+                .map(|(k, v)| format!("{} ({})", k, v))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let mut lines = vec![
+            format!("Dry-run summary of {} ({})", self.filename, self.table_name),
+            format!("  Rows: {}", self.num_of_rows),
+            format!("  Duplicated rows: {}", self.num_of_duplicated_rows),
+            match self.num_of_existing_rows {
+                Some(n) => format!("  Existing rows: {}", n),
+                None => "  Existing rows: not checked".to_string(),
+            },
+            format!("  Rows would be inserted: {}", self.num_of_new_rows()),
+            format!(
+                "  Entity types ({}): {}",
+                self.entity_types.len(),
+                format_counts(&self.entity_types)
+            ),
+            format!(
+                "  Relation types ({}): {}",
+                self.relation_types.len(),
+                format_counts(&self.relation_types)
+            ),
+            format!(
+                "  Datasets ({}): {}",
+                self.datasets.len(),
+                format_counts(&self.datasets)
+            ),
+        ];
+
+        if self.num_of_missing_curated_entities > 0 {
+            lines.push(format!(
+                "  Entities of the curated knowledges which are not in the file: {}",
+                self.num_of_missing_curated_entities
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Copy a chunk of rows into a table, the rows which conflict with the unique columns are handled by the conflict strategy like `import_file_in_loop`. The rows are sent by `COPY FROM STDIN`, so the database server doesn't need to access the data file.
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `table_name` - The table name, such as biomedgps_relation
+/// * `columns` - The columns of the rows
+/// * `unique_columns` - The unique columns of the table
+/// * `rows` - The values of the rows, the empty values are imported as NULL
+/// * `strategy` - How the conflicted rows are handled
+///
+/// # Returns
+/// * `Result<u64, Box<dyn Error>>` - How many rows are inserted or updated, or an error
+pub async fn copy_rows_in_chunk(
+    pool: &sqlx::PgPool,
+    table_name: &str,
+    columns: &Vec<String>,
+    unique_columns: &Vec<String>,
+    rows: &Vec<Vec<String>>,
+    strategy: ConflictStrategy,
+) -> Result<u64, Box<dyn Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    let data = writer.into_inner().map_err(|e| e.to_string())?;
+
+    let mut tx = pool.begin().await?;
+    // The staging table is dropped with the transaction, so each chunk has its own staging table.
+    sqlx::query(&format!(
+        "CREATE TEMPORARY TABLE staging (LIKE {} INCLUDING DEFAULTS) ON COMMIT DROP",
+        table_name
+    ))
+    .execute(&mut tx)
+    .await?;
+
+    let mut copy = tx
+        .copy_in_raw(&format!(
+            "COPY staging ({}) FROM STDIN WITH (FORMAT csv)",
+            columns.join(",")
+        ))
+        .await?;
+    copy.send(data).await?;
+    copy.finish().await?;
+
+    let num_of_rows =
+        upsert_from_staging(&mut tx, table_name, columns, unique_columns, strategy).await?;
+
+    tx.commit().await?;
+
+    Ok(num_of_rows)
+}
+
 pub async fn import_file(
     pool: &sqlx::PgPool,
     filepath: &PathBuf,
@@ -315,6 +1420,7 @@ pub async fn update_entity_metadata(pool: &sqlx::PgPool, drop: bool) -> Result<(
         .expect("Failed to update data.");
     info!("{} updated.", table_name);
 
+    notify_metadata_updated(pool).await?;
     Ok(())
 }
 
@@ -324,77 +1430,326 @@ struct RelationMetadata {
     description: String,
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct OntologyRelation {
+    ontology: String,
+    term: String,
+    label: String,
+    description: String,
+}
+
+lazy_static! {
+    // The descriptions of the relations from the source ontologies, such as RO and SemMedDB predicates. The key is (ontology, lowercase term or label), such as ("RO", "ro:0002434"), ("RO", "interacts_with") and ("SemMedDB", "treats").
+    static ref ONTOLOGY_RELATION_DESCRIPTIONS: HashMap<(String, String), String> = {
+        let mut m = HashMap::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_reader(include_str!("../../resources/relation_descriptions.tsv").as_bytes());
+
+        for result in reader.deserialize::<OntologyRelation>() {
+            let record = result.expect("The bundled relation_descriptions.tsv is not valid.");
+            let description = format!("{} [{}: {}]", record.description, record.ontology, record.term);
+            m.insert(
+                (record.ontology.clone(), normalize_predicate(&record.term)),
+                description.clone(),
+            );
+            m.insert((record.ontology, normalize_predicate(&record.label)), description);
+        }
+        m
+    };
+}
+
+fn normalize_predicate(predicate: &str) -> String {
+    predicate
+        .trim()
+        .to_lowercase()
+        .replace(|c: char| c == ' ' || c == '-', "_")
+}
+
+/// Get the description of a relation type from the bundled mapping file of the source ontologies. The second part of the relation type is treated as a SemMedDB predicate if the resource is SemMedDB, otherwise as a RO term or label.
+///
+/// # Arguments
+/// * `relation_type` - The relation type, such as "SemMedDB::TREATS::Compound:Disease" or "Custom::RO:0002434::Gene:Gene".
+///
+/// # Returns
+/// * `Option<String>` - The description with the ontology term, None if the predicate is not in the mapping file.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::util::get_ontology_relation_description;
+/// let description = get_ontology_relation_description("SemMedDB::TREATS::Compound:Disease").unwrap();
+/// assert!(description.ends_with("[SemMedDB: TREATS]"));
+///
+/// let description = get_ontology_relation_description("Custom::interacts with::Gene:Gene").unwrap();
+/// assert!(description.ends_with("[RO: RO:0002434]"));
+///
+/// assert_eq!(get_ontology_relation_description("DRUGBANK::unknown::Compound:Gene"), None);
+/// ```
+pub fn get_ontology_relation_description(relation_type: &str) -> Option<String> {
+    let parts = relation_type.split("::").collect::<Vec<&str>>();
+    if parts.len() < 2 {
+        return None;
+    }
+
+    let resource = parts[0].to_lowercase();
+    let predicate = normalize_predicate(parts[1]);
+    if resource == "semmeddb" || resource == "semmed" {
+        // The negated predicates in SemMedDB, such as NEG_TREATS.
+        return match predicate.strip_prefix("neg_") {
+            Some(predicate) => ONTOLOGY_RELATION_DESCRIPTIONS
+                .get(&("SemMedDB".to_string(), predicate.to_string()))
+                .map(|description| format!("Negated: {}", description)),
+            None => ONTOLOGY_RELATION_DESCRIPTIONS
+                .get(&("SemMedDB".to_string(), predicate))
+                .cloned(),
+        };
+    }
+
+    ONTOLOGY_RELATION_DESCRIPTIONS
+        .get(&("RO".to_string(), predicate))
+        .cloned()
+}
+
+/// Update the relation metadata table from the relation table and annotate the relation types with descriptions.
+///
+/// The descriptions are populated from the bundled mapping file of the source ontologies (see [`get_ontology_relation_description`](fn.get_ontology_relation_description.html)) at first, and then from the metadata file if it is provided, so the descriptions in the metadata file have a higher priority.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `metadata_filepath` - Optional csv/tsv file with the columns 'relation_type' and 'description'.
+/// * `drop` - Drop the table before updating.
+/// * `only_missing_descriptions` - Don't rebuild the table, only fill the descriptions which are empty.
 pub async fn update_relation_metadata(
     pool: &sqlx::PgPool,
-    metadata_filepath: &PathBuf,
+    metadata_filepath: Option<&PathBuf>,
     drop: bool,
+    only_missing_descriptions: bool,
 ) -> Result<(), Box<dyn Error>> {
     let table_name = "biomedgps_relation_metadata";
-    if drop {
-        drop_table(&pool, table_name).await;
-    };
-
-    info!("Load relation metadata from an annotation file.");
 
-    let delimiter = get_delimiter(metadata_filepath)?;
-    let mut reader = csv::ReaderBuilder::new()
-        .delimiter(delimiter)
-        .from_path(metadata_filepath)?;
+    let mut records = Vec::new();
+    if let Some(metadata_filepath) = metadata_filepath {
+        info!("Load relation metadata from an annotation file.");
+
+        let delimiter = get_delimiter(metadata_filepath)?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(open_data_file(metadata_filepath)?);
+
+        let headers = reader.headers().unwrap();
+        for col in ["relation_type", "description"].iter() {
+            if !headers.into_iter().contains(col) {
+                return Err(format!(
+                    "Column {} not found in the {} file. You should specify a file with the columns 'relation_type' and 'description' for annotating the relation types in the relation table.",
+                    col,
+                    metadata_filepath.display()
+                )
+                .into());
+            }
+        }
 
-    let headers = reader.headers().unwrap();
-    for col in ["relation_type", "description"].iter() {
-        if !headers.into_iter().contains(col) {
-            return Err(format!(
-                "Column {} not found in the {} file. You should specify a file with the columns 'relation_type' and 'description' for annotating the relation types in the relation table.",
-                col,
-                metadata_filepath.display()
-            )
-            .into());
+        for result in reader.deserialize::<RelationMetadata>() {
+            let record: RelationMetadata = result?;
+            records.push(record);
         }
     }
 
-    let mut records = Vec::new();
-    for result in reader.deserialize::<RelationMetadata>() {
-        let record: RelationMetadata = result?;
-        records.push(record);
+    if only_missing_descriptions {
+        info!("Only fill the missing descriptions of the relation types.");
+    } else {
+        if drop {
+            drop_table(&pool, table_name).await;
+        };
+
+        info!("Update relation metadata from relation table.");
+
+        let query_str = format!("
+            INSERT INTO {} (relation_type, formatted_relation_type, start_entity_type, end_entity_type, relation_count, resource, dataset)
+            SELECT relation_type, formatted_relation_type, source_type as start_entity_type, target_type as end_entity_type, count(*) as relation_count, resource, dataset
+            FROM biomedgps_relation
+            GROUP BY relation_type, formatted_relation_type, source_type, target_type, resource, dataset;
+        ", table_name);
+
+        sqlx::query(&query_str)
+            .execute(pool)
+            .await
+            .expect("Failed to update data.");
     }
 
-    info!("Update relation metadata from relation table.");
-
-    let query_str = format!("
-        INSERT INTO {} (relation_type, formatted_relation_type, start_entity_type, end_entity_type, relation_count, resource, dataset)
-        SELECT relation_type, formatted_relation_type, source_type as start_entity_type, target_type as end_entity_type, count(*) as relation_count, resource, dataset
-        FROM biomedgps_relation
-        GROUP BY relation_type, formatted_relation_type, source_type, target_type, resource, dataset;
-    ", table_name);
+    let missing_condition = if only_missing_descriptions {
+        "AND (description IS NULL OR description = '')"
+    } else {
+        ""
+    };
+    let update_sql_str = format!(
+        "UPDATE {} SET description = $1 WHERE relation_type = $2 {};",
+        table_name, missing_condition
+    );
 
-    sqlx::query(&query_str)
-        .execute(pool)
-        .await
-        .expect("Failed to update data.");
+    let relation_types = sqlx::query_scalar::<_, String>(&format!(
+        "SELECT DISTINCT relation_type FROM {} WHERE 1 = 1 {}",
+        table_name, missing_condition
+    ))
+    .fetch_all(pool)
+    .await?;
 
     // Update the description of the relation types.
     let mut tx = pool.begin().await?;
+    let mut num_ontology_descriptions = 0;
+    for relation_type in relation_types {
+        if let Some(description) = get_ontology_relation_description(&relation_type) {
+            sqlx::query(&update_sql_str)
+                .bind(description)
+                .bind(relation_type)
+                .execute(&mut tx)
+                .await?;
+            num_ontology_descriptions += 1;
+        }
+    }
+
     for record in records {
-        sqlx::query(
-            "
-            UPDATE biomedgps_relation_metadata
-            SET description = $1
-            WHERE relation_type = $2;
-        ",
-        )
-        .bind(record.description)
-        .bind(record.relation_type)
-        .execute(&mut tx)
-        .await?;
+        sqlx::query(&update_sql_str)
+            .bind(record.description)
+            .bind(record.relation_type)
+            .execute(&mut tx)
+            .await?;
     }
     tx.commit().await?;
 
-    info!("{} updated.", table_name);
+    info!(
+        "{} updated, {} relation types are described by the source ontologies.",
+        table_name, num_ontology_descriptions
+    );
 
+    notify_metadata_updated(pool).await?;
     Ok(())
 }
 
+/// Rebuild the relation metadata of one dataset from the relation table, such as the synthetic dataset of the inverse relations. The metadata of the other datasets are kept, so the descriptions from the metadata files are not lost, and the missing descriptions are filled from the source ontologies at last.
+///
+/// # Arguments
+/// * `pool` - The database connection pool.
+/// * `dataset` - The dataset whose relations were changed.
+pub async fn update_dataset_relation_metadata(
+    pool: &sqlx::PgPool,
+    dataset: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM biomedgps_relation_metadata WHERE dataset = $1")
+        .bind(dataset)
+        .execute(&mut tx)
+        .await?;
+
+    sqlx::query("
+        INSERT INTO biomedgps_relation_metadata (relation_type, formatted_relation_type, start_entity_type, end_entity_type, relation_count, resource, dataset)
+        SELECT relation_type, formatted_relation_type, source_type as start_entity_type, target_type as end_entity_type, count(*) as relation_count, resource, dataset
+        FROM biomedgps_relation
+        WHERE dataset = $1
+        GROUP BY relation_type, formatted_relation_type, source_type, target_type, resource, dataset;
+    ")
+    .bind(dataset)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    update_relation_metadata(pool, None, false, true).await
+}
+
+// The largest PMID we accept, the PMIDs are 8-digit numbers at most for now.
+pub const MAX_PMID: u64 = 99_999_999;
+// The delimiter of the PMIDs in the canonical form, such as 12345|23456.
+pub const PMID_DELIMITER: &str = "|";
+
+/// Normalize the PMIDs into the canonical form which is used by the publication lookups.
+/// The PMIDs can be separated by `|`, `,`, `;` or whitespaces, and the `PMID:` prefix will be stripped. The duplicated PMIDs will be removed.
+///
+/// # Arguments
+/// * `pmids` - The PMIDs string, such as "PMID:12345, 23456; PMID: 12345".
+///
+/// # Returns
+/// * `Ok(String)` - The canonical PMIDs, such as "12345|23456".
+/// * `Err(ValidationError)` - Some PMIDs are not valid numbers or out of range.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::util::normalize_pmids;
+/// assert_eq!(normalize_pmids("PMID:12345, 23456;pmid: 12345").unwrap(), "12345|23456");
+/// assert_eq!(normalize_pmids("").unwrap(), "");
+/// assert!(normalize_pmids("12345|abc").is_err());
+/// assert!(normalize_pmids("0").is_err());
+/// ```
+pub fn normalize_pmids(pmids: &str) -> Result<String, ValidationError> {
+    let mut normalized: Vec<String> = vec![];
+    let mut invalid_pmids: Vec<String> = vec![];
+
+    // Remove the prefix first, so the whitespace after the prefix (e.g. "PMID: 12345") is not treated as a delimiter.
+    let pmids = PMID_PREFIX_REGEX.replace_all(pmids, "");
+    for pmid in pmids.split(|c: char| c == '|' || c == ',' || c == ';' || c.is_whitespace()) {
+        let pmid = pmid.trim();
+        if pmid.is_empty() {
+            continue;
+        }
+
+        match pmid.parse::<u64>() {
+            Ok(value) if value >= 1 && value <= MAX_PMID => {
+                let value = value.to_string();
+                if !normalized.contains(&value) {
+                    normalized.push(value);
+                }
+            }
+            _ => invalid_pmids.push(pmid.to_string()),
+        }
+    }
+
+    if !invalid_pmids.is_empty() {
+        return Err(ValidationError::new(
+            &format!(
+                "Invalid PMIDs: {}, the PMID must be a number between 1 and {}.",
+                invalid_pmids.join(", "),
+                MAX_PMID
+            ),
+            vec![],
+        ));
+    }
+
+    Ok(normalized.join(PMID_DELIMITER))
+}
+
+/// Validate the PMIDs field by the validator crate, such as `#[validate(custom = "validate_pmids")]`.
+pub fn validate_pmids(pmids: &str) -> Result<(), validator::ValidationError> {
+    match normalize_pmids(pmids) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(validator::ValidationError::new("invalid_pmids")),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum PmidValue {
+    Int(i64),
+    Str(String),
+}
+
+/// Deserialize a single PMID which might have a `PMID:` prefix, such as "PMID:12345", it is used by `#[serde(deserialize_with = "deserialize_pmid")]`.
+pub fn deserialize_pmid<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let pmid = match PmidValue::deserialize(deserializer)? {
+        PmidValue::Int(value) => return Ok(value),
+        PmidValue::Str(value) => normalize_pmids(&value).map_err(serde::de::Error::custom)?,
+    };
+
+    if pmid.contains(PMID_DELIMITER) || pmid.is_empty() {
+        return Err(serde::de::Error::custom(format!(
+            "Expected a single PMID, but got {}",
+            pmid
+        )));
+    }
+
+    pmid.parse::<i64>().map_err(serde::de::Error::custom)
+}
+
 pub fn parse_csv_error(e: &csv::Error) -> String {
     match *e.kind() {
         csv::ErrorKind::Deserialize {
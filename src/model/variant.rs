@@ -0,0 +1,377 @@
+//! This module is used to import the genetic variants and link them to the genes and the diseases, so the clinical users can query the knowledge graph by a rsID or a HGVS string.
+//!
+//! A variant is stored as an entity with the Variant label, such as `dbSNP:rs1042522`. The attributes of the variants, such as the location and the clinical significance, are stored in the variant table. The variants which have no rsID are identified by a hash of the normalized HGVS string, because a HGVS string can't be used as an entity id.
+
+use crate::model::core::ENTITY_ID_REGEX;
+use crate::model::util::{get_delimiter, normalize_pmids};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use poem_openapi::Object;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The entity type of the variants.
+pub const VARIANT_ENTITY_TYPE: &str = "Variant";
+
+/// The dataset of the variant relations if no dataset is specified.
+pub const DEFAULT_VARIANT_DATASET: &str = "variant";
+
+/// The resource of the variants and the variant relations if the resource column is empty.
+pub const DEFAULT_VARIANT_RESOURCE: &str = "VARIANT";
+
+/// The id prefixes of the variants, such as `dbSNP:rs1042522` and `HGVS:5b1c2f0e9d8a7c6b`.
+pub const RSID_PREFIX: &str = "dbSNP";
+pub const HGVS_PREFIX: &str = "HGVS";
+
+lazy_static! {
+    static ref RSID_REGEX: Regex = Regex::new(r"^(?i)(?:dbsnp:)?rs0*([1-9][0-9]*)$").unwrap();
+    // Such as NM_000546.6:c.215C>G, NC_000017.11:g.7676154G>C and NM_000546.6(TP53):c.215C>G.
+    static ref HGVS_REGEX: Regex =
+        Regex::new(r"^([A-Za-z]{1,6}_?[0-9]+(?:\.[0-9]+)?)(\([A-Za-z0-9\-]+\))?:([cgmnoprCGMNOPR])\.(\S+)$").unwrap();
+}
+
+/// Normalize a rsID, the prefix and the leading zeros are removed.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::variant::normalize_rsid;
+///
+/// assert_eq!(normalize_rsid(" RS1042522 "), Some("rs1042522".to_string()));
+/// assert_eq!(normalize_rsid("dbSNP:rs001042522"), Some("rs1042522".to_string()));
+/// assert_eq!(normalize_rsid("NM_000546.6:c.215C>G"), None);
+/// ```
+pub fn normalize_rsid(value: &str) -> Option<String> {
+    RSID_REGEX
+        .captures(value.trim())
+        .map(|caps| format!("rs{}", &caps[1]))
+}
+
+/// Normalize a HGVS string, the whitespaces are removed, the accession and the gene symbol are uppercased and the coordinate type is lowercased. The change is kept as it is, because the case of the amino acids matters.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::variant::normalize_hgvs;
+///
+/// assert_eq!(normalize_hgvs("nm_000546.6:C.215C>G"), Some("NM_000546.6:c.215C>G".to_string()));
+/// assert_eq!(normalize_hgvs("NM_000546.6(tp53): c.215C>G"), Some("NM_000546.6(TP53):c.215C>G".to_string()));
+/// assert_eq!(normalize_hgvs("NP_000537.3:p.Pro72Arg"), Some("NP_000537.3:p.Pro72Arg".to_string()));
+/// assert_eq!(normalize_hgvs("rs1042522"), None);
+/// ```
+pub fn normalize_hgvs(value: &str) -> Option<String> {
+    let value = value.split_whitespace().collect::<String>();
+    HGVS_REGEX.captures(&value).map(|caps| {
+        format!(
+            "{}{}:{}.{}",
+            caps[1].to_uppercase(),
+            caps.get(2)
+                .map(|m| m.as_str().to_uppercase())
+                .unwrap_or_default(),
+            caps[3].to_lowercase(),
+            &caps[4]
+        )
+    })
+}
+
+/// Get the entity id of a variant from a rsID or a HGVS string. The id of a HGVS string is the first 16 hex characters of the sha256 of the normalized string.
+///
+/// # Example
+/// ```
+/// use biomedgps::model::variant::variant_entity_id;
+///
+/// assert_eq!(variant_entity_id("rs1042522"), Some("dbSNP:rs1042522".to_string()));
+/// let id = variant_entity_id("NM_000546.6:c.215C>G").unwrap();
+/// assert!(id.starts_with("HGVS:"));
+/// assert_eq!(variant_entity_id("nm_000546.6: c.215C>G"), Some(id));
+/// assert_eq!(variant_entity_id("TP53"), None);
+/// ```
+pub fn variant_entity_id(value: &str) -> Option<String> {
+    if let Some(rsid) = normalize_rsid(value) {
+        return Some(format!("{}:{}", RSID_PREFIX, rsid));
+    }
+
+    normalize_hgvs(value).map(|hgvs| {
+        let digest = Sha256::digest(hgvs.as_bytes());
+        let hex = digest
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        format!("{}:{}", HGVS_PREFIX, hex)
+    })
+}
+
+/// The attributes of a variant. The HGVS strings are separated by `|` like the synonyms of the entities.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Object, sqlx::FromRow)]
+pub struct Variant {
+    // The entity id of the variant, such as dbSNP:rs1042522.
+    pub id: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub rsid: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub hgvs: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub chromosome: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub position: Option<i64>,
+    #[oai(skip_serializing_if_is_none)]
+    pub ref_allele: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    pub alt_allele: Option<String>,
+    // The molecular consequence, such as missense_variant.
+    #[oai(skip_serializing_if_is_none)]
+    pub consequence: Option<String>,
+    // The clinical significance, such as pathogenic.
+    #[oai(skip_serializing_if_is_none)]
+    pub clinical_significance: Option<String>,
+    pub dataset: String,
+}
+
+/// A relation between a variant and a gene or a disease, it's inserted into the relation table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct VariantRelation {
+    relation_type: String,
+    source_id: String,
+    target_id: String,
+    target_type: String,
+    resource: String,
+    pmids: Option<String>,
+}
+
+impl Variant {
+    /// Find the variants by a rsID or a HGVS string. The HGVS string is also matched against the known HGVS strings of the variants, so a variant with a rsID can be found by its HGVS string.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `query` - A rsID or a HGVS string, such as rs1042522 or NM_000546.6:c.215C>G
+    ///
+    /// # Returns
+    /// * `Result<Vec<Variant>, anyhow::Error>` - The matched variants or an error
+    pub async fn search(pool: &sqlx::PgPool, query: &str) -> Result<Vec<Variant>, anyhow::Error> {
+        let id = match variant_entity_id(query) {
+            Some(id) => id,
+            None => return Err(anyhow::anyhow!(
+                "{} is not a valid rsID or HGVS string, such as rs1042522 or NM_000546.6:c.215C>G.",
+                query
+            )),
+        };
+        let hgvs = normalize_hgvs(query);
+
+        let sql_str = "SELECT * FROM biomedgps_variant WHERE id = $1 OR ($2::TEXT IS NOT NULL AND $2 = ANY(string_to_array(hgvs, '|'))) ORDER BY id";
+        let variants = sqlx::query_as::<_, Variant>(sql_str)
+            .bind(&id)
+            .bind(&hgvs)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(variants)
+    }
+
+    /// Import a variant annotation file. Each row is a variant and its optional gene and disease, the variants are added as the Variant entities, and the Variant-Gene and Variant-Disease relations are added into the dataset.
+    ///
+    /// The `variant` column is required, it's a rsID or a HGVS string. The optional columns are `hgvs` (more HGVS strings separated by `|`), `chromosome`, `position`, `ref`, `alt`, `consequence`, `clinical_significance`, `gene_id` (such as ENTREZ:7157), `disease_id` (such as MESH:D009369), `pmids` and `resource`.
+    ///
+    /// # Arguments
+    /// * `pool` - The database connection pool
+    /// * `filepath` - The path of the tsv/csv file
+    /// * `dataset` - The dataset of the variants and the relations
+    ///
+    /// # Returns
+    /// * `Result<(usize, usize), anyhow::Error>` - The numbers of the variants and the relations or an error
+    pub async fn import_from_file(
+        pool: &sqlx::PgPool,
+        filepath: &PathBuf,
+        dataset: &str,
+    ) -> Result<(usize, usize), anyhow::Error> {
+        let delimiter = get_delimiter(filepath).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_path(filepath)?;
+
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let variant_index = match column("variant") {
+            Some(index) => index,
+            None => {
+                return Err(anyhow::anyhow!(
+                    "The variant column is required for importing the variants."
+                ))
+            }
+        };
+
+        let mut variants: HashMap<String, Variant> = HashMap::new();
+        let mut relations: Vec<VariantRelation> = vec![];
+        for (line, record) in reader.records().enumerate() {
+            let record = record?;
+            // The first line is the header.
+            let line = line + 2;
+            let get = |name: &str| {
+                column(name)
+                    .and_then(|i| record.get(i))
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+            };
+
+            let raw_variant = record.get(variant_index).unwrap_or("").trim();
+            let id = match variant_entity_id(raw_variant) {
+                Some(id) => id,
+                None => {
+                    warn!(
+                        "Line {}: {} is not a valid rsID or HGVS string, skip it.",
+                        line, raw_variant
+                    );
+                    continue;
+                }
+            };
+
+            let mut hgvs = vec![];
+            hgvs.extend(normalize_hgvs(raw_variant));
+            if let Some(values) = get("hgvs") {
+                hgvs.extend(values.split('|').filter_map(normalize_hgvs));
+            }
+
+            let resource = get("resource")
+                .unwrap_or(DEFAULT_VARIANT_RESOURCE.to_string())
+                .to_uppercase();
+            let pmids = get("pmids").map(|p| normalize_pmids(&p).unwrap_or(p));
+
+            let variant = variants.entry(id.clone()).or_insert(Variant {
+                id: id.clone(),
+                rsid: normalize_rsid(raw_variant),
+                hgvs: None,
+                chromosome: None,
+                position: None,
+                ref_allele: None,
+                alt_allele: None,
+                consequence: None,
+                clinical_significance: None,
+                dataset: dataset.to_string(),
+            });
+            // A variant might be in several rows, such as one row per gene, so the attributes are merged.
+            let mut known_hgvs = variant
+                .hgvs
+                .as_ref()
+                .map(|h| h.split('|').map(|s| s.to_string()).collect::<Vec<String>>())
+                .unwrap_or_default();
+            for h in hgvs {
+                if !known_hgvs.contains(&h) {
+                    known_hgvs.push(h);
+                }
+            }
+            if !known_hgvs.is_empty() {
+                variant.hgvs = Some(known_hgvs.join("|"));
+            }
+            variant.chromosome = variant.chromosome.take().or(get("chromosome"));
+            variant.position = variant
+                .position
+                .or(get("position").and_then(|p| p.parse::<i64>().ok()));
+            variant.ref_allele = variant.ref_allele.take().or(get("ref"));
+            variant.alt_allele = variant.alt_allele.take().or(get("alt"));
+            variant.consequence = variant.consequence.take().or(get("consequence"));
+            variant.clinical_significance = variant
+                .clinical_significance
+                .take()
+                .or(get("clinical_significance"));
+
+            for (field, target_type, relation) in [
+                ("gene_id", "Gene", "located_in"),
+                ("disease_id", "Disease", "associated_with"),
+            ] {
+                if let Some(target_id) = get(field) {
+                    if !ENTITY_ID_REGEX.is_match(&target_id) {
+                        warn!(
+                            "Line {}: the {} {} is invalid, such as ENTREZ:7157 or MESH:D009369, skip it.",
+                            line, field, target_id
+                        );
+                        continue;
+                    }
+
+                    relations.push(VariantRelation {
+                        relation_type: format!(
+                            "{}::{}::{}:{}",
+                            resource, relation, VARIANT_ENTITY_TYPE, target_type
+                        ),
+                        source_id: id.clone(),
+                        target_id,
+                        target_type: target_type.to_string(),
+                        resource: resource.clone(),
+                        pmids: pmids.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+        for variant in variants.values() {
+            let sql_str = "INSERT INTO biomedgps_variant (id, rsid, hgvs, chromosome, position, ref_allele, alt_allele, consequence, clinical_significance, dataset) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                           ON CONFLICT (id) DO UPDATE SET rsid = EXCLUDED.rsid, hgvs = EXCLUDED.hgvs, chromosome = EXCLUDED.chromosome, position = EXCLUDED.position, ref_allele = EXCLUDED.ref_allele, alt_allele = EXCLUDED.alt_allele, consequence = EXCLUDED.consequence, clinical_significance = EXCLUDED.clinical_significance, dataset = EXCLUDED.dataset";
+            sqlx::query(sql_str)
+                .bind(&variant.id)
+                .bind(&variant.rsid)
+                .bind(&variant.hgvs)
+                .bind(&variant.chromosome)
+                .bind(variant.position)
+                .bind(&variant.ref_allele)
+                .bind(&variant.alt_allele)
+                .bind(&variant.consequence)
+                .bind(&variant.clinical_significance)
+                .bind(&variant.dataset)
+                .execute(&mut tx)
+                .await?;
+
+            // The rsID is the name if it exists, so the variant can be found by the entity search, and the HGVS strings are the synonyms.
+            let name = variant
+                .rsid
+                .clone()
+                .or(variant
+                    .hgvs
+                    .as_ref()
+                    .and_then(|h| h.split('|').next().map(|s| s.to_string())))
+                .unwrap_or(variant.id.clone());
+            let sql_str = "INSERT INTO biomedgps_entity (id, name, label, resource, synonyms) VALUES ($1, $2, $3, $4, $5)
+                           ON CONFLICT (id, label) DO UPDATE SET name = EXCLUDED.name, synonyms = EXCLUDED.synonyms";
+            sqlx::query(sql_str)
+                .bind(&variant.id)
+                .bind(&name)
+                .bind(VARIANT_ENTITY_TYPE)
+                .bind(if variant.rsid.is_some() {
+                    RSID_PREFIX
+                } else {
+                    HGVS_PREFIX
+                })
+                .bind(&variant.hgvs)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        let mut num_of_relations = 0;
+        for relation in relations.iter() {
+            let sql_str = "INSERT INTO biomedgps_relation (relation_type, source_id, source_type, target_id, target_type, resource, pmids, dataset) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                           ON CONFLICT ON CONSTRAINT biomedgps_relation_uniq_key DO NOTHING";
+            let result = sqlx::query(sql_str)
+                .bind(&relation.relation_type)
+                .bind(&relation.source_id)
+                .bind(VARIANT_ENTITY_TYPE)
+                .bind(&relation.target_id)
+                .bind(&relation.target_type)
+                .bind(&relation.resource)
+                .bind(&relation.pmids)
+                .bind(dataset)
+                .execute(&mut tx)
+                .await?;
+            num_of_relations += result.rows_affected() as usize;
+        }
+        tx.commit().await?;
+
+        info!(
+            "Imported {} variants and {} relations into the {} dataset.",
+            variants.len(),
+            num_of_relations,
+            dataset
+        );
+
+        Ok((variants.len(), num_of_relations))
+    }
+}
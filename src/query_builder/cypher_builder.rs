@@ -1,7 +1,262 @@
 use crate::model::graph::{EdgeData, NodeData, COMPOSED_ENTITY_DELIMITER, COMPOSED_ENTITY_REGEX};
+use lazy_static::lazy_static;
 use log::{debug, error, info};
-use neo4rs::{query, Graph, Node as NeoNode, Relation, RowStream};
+use neo4rs::{query, Graph, Node as NeoNode, Relation, Row};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The predicates of the relation types which mean a gene is a member of a pathway, such as `Hetionet::GpPW::Gene:Pathway` and `REACTOME::member_of::Gene:Pathway`. They are compared case-insensitively.
+pub const PATHWAY_MEMBERSHIP_PREDICATES: [&str; 6] = [
+    "gppw",
+    "member_of",
+    "has_member",
+    "participates_in",
+    "part_of",
+    "in_pathway",
+];
+
+/// The max number of hops between the member genes and the diseases in the pathway expansion.
+pub const MAX_PATHWAY_EXPANSION_DEPTH: usize = 3;
+
+/// The max number of the gene-disease paths in the pathway expansion, a pathway with many members can link to millions of diseases within a few hops.
+pub const MAX_PATHWAY_EXPANSION_LIMIT: usize = 1000;
+
+/// How long the results of the shared nodes and paths queries are memoized. The graph database is rarely updated, so a short TTL is enough for the repeated identical queries, such as the classroom demos.
+pub const QUERY_MEMO_TTL: Duration = Duration::from_secs(300);
+
+/// The max number of the memoized results of each kind of query, the oldest one is evicted when it's full.
+pub const QUERY_MEMO_MAX_ENTRIES: usize = 256;
+
+/// The kinds of the memoized queries.
+pub const MEMOIZED_QUERY_KINDS: [&str; 2] = ["shared_nodes", "nhops"];
+
+type QueryResult = (Vec<NodeData>, Vec<EdgeData>);
+
+/// The memoized results of a kind of query and the counters for the hit rate.
+#[derive(Default)]
+struct QueryMemo {
+    entries: HashMap<String, (Instant, QueryResult)>,
+    hits: u64,
+    misses: u64,
+}
+
+lazy_static! {
+    // The key is the kind of the query, such as shared_nodes.
+    static ref QUERY_MEMOS: Mutex<HashMap<&'static str, QueryMemo>> = Mutex::new(HashMap::new());
+}
+
+tokio::task_local! {
+    // Whether all memoized queries of the current request are hits, it's only tracked inside `track_memo_hits`.
+    static MEMO_HIT: Cell<Option<bool>>;
+}
+
+/// Run a future and report whether its memoized queries are served by the memo, None if it doesn't run any memoized query.
+pub async fn track_memo_hits<F: Future>(future: F) -> (F::Output, Option<bool>) {
+    MEMO_HIT
+        .scope(Cell::new(None), async move {
+            let output = future.await;
+            (output, MEMO_HIT.with(|hit| hit.get()))
+        })
+        .await
+}
+
+/// The stats of the memoized queries of a kind.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Object)]
+pub struct QueryMemoStats {
+    pub kind: String,
+    pub hits: i64,
+    pub misses: i64,
+    // The ratio of the hits to all requests, between 0 and 1. It's 0 if there is no request.
+    pub hit_rate: f64,
+    // How many results are memoized, some of them might be expired.
+    pub entries: i64,
+}
+
+/// Get the memoized result of a query if it isn't expired, the hit or the miss is counted.
+fn get_memoized(kind: &'static str, key: &str) -> Option<QueryResult> {
+    let mut memos = QUERY_MEMOS.lock().unwrap();
+    let memo = memos.entry(kind).or_default();
+    let result = match memo.entries.get(key) {
+        Some((created_at, result)) if created_at.elapsed() < QUERY_MEMO_TTL => Some(result.clone()),
+        _ => None,
+    };
+
+    match result {
+        Some(_) => memo.hits += 1,
+        None => memo.misses += 1,
+    };
+    let _ = MEMO_HIT.try_with(|hit| hit.set(Some(hit.get().unwrap_or(true) && result.is_some())));
+    result
+}
+
+fn memoize(kind: &'static str, key: String, result: &QueryResult) {
+    let mut memos = QUERY_MEMOS.lock().unwrap();
+    let memo = memos.entry(kind).or_default();
+    memo.entries
+        .retain(|_, (created_at, _)| created_at.elapsed() < QUERY_MEMO_TTL);
+
+    if memo.entries.len() >= QUERY_MEMO_MAX_ENTRIES {
+        let oldest_key = memo
+            .entries
+            .iter()
+            .min_by_key(|(_, (created_at, _))| *created_at)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest_key) = oldest_key {
+            memo.entries.remove(&oldest_key);
+        }
+    }
+
+    memo.entries.insert(key, (Instant::now(), result.clone()));
+}
+
+/// Get the hit rates of the memoized queries, one row for each kind of query.
+pub fn get_query_memo_stats() -> Vec<QueryMemoStats> {
+    let memos = QUERY_MEMOS.lock().unwrap();
+    MEMOIZED_QUERY_KINDS
+        .iter()
+        .map(|kind| {
+            let (hits, misses, entries) = match memos.get(kind) {
+                Some(memo) => (memo.hits, memo.misses, memo.entries.len()),
+                None => (0, 0, 0),
+            };
+            let total = hits + misses;
+            QueryMemoStats {
+                kind: kind.to_string(),
+                hits: hits as i64,
+                misses: misses as i64,
+                hit_rate: if total == 0 {
+                    0.0
+                } else {
+                    hits as f64 / total as f64
+                },
+                entries: entries as i64,
+            }
+        })
+        .collect()
+}
+
+/// Generate the memo key of a shared nodes query. The order of the node ids and the target node types doesn't change the results, so they are sorted.
+///
+/// # Example
+/// ```
+/// use biomedgps::query_builder::cypher_builder::gen_shared_nodes_memo_key;
+///
+/// let key1 = gen_shared_nodes_memo_key(&vec!["Gene::ENTREZ:1", "Disease::MONDO:0005404"], &Some(vec!["Compound"]), 2, 10, 2);
+/// let key2 = gen_shared_nodes_memo_key(&vec!["Disease::MONDO:0005404", " Gene::ENTREZ:1"], &Some(vec!["Compound"]), 2, 10, 2);
+/// assert_eq!(key1, key2);
+///
+/// let key3 = gen_shared_nodes_memo_key(&vec!["Gene::ENTREZ:1", "Disease::MONDO:0005404"], &None, 2, 10, 2);
+/// assert_ne!(key1, key3);
+/// ```
+pub fn gen_shared_nodes_memo_key(
+    node_ids: &Vec<&str>,
+    target_node_types: &Option<Vec<&str>>,
+    nhops: usize,
+    topk: usize,
+    nums_shared_by: usize,
+) -> String {
+    let mut node_ids = node_ids.iter().map(|id| id.trim()).collect::<Vec<&str>>();
+    node_ids.sort();
+    let target_node_types = match target_node_types {
+        Some(target_node_types) => {
+            let mut target_node_types = target_node_types
+                .iter()
+                .map(|t| t.trim())
+                .collect::<Vec<&str>>();
+            target_node_types.sort();
+            target_node_types.join(",")
+        }
+        None => "*".to_string(),
+    };
+
+    format!(
+        "{}|{}|{}|{}|{}",
+        node_ids.join(","),
+        target_node_types,
+        nhops,
+        topk,
+        nums_shared_by
+    )
+}
+
+/// The expansion modes of a node, the mode decides which relations are followed from the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpansionMode {
+    // Expand a Pathway node to its member genes and the diseases linked to the genes.
+    Pathway,
+}
+
+impl ExpansionMode {
+    pub fn from_name(mode: &str) -> Option<Self> {
+        match mode {
+            "pathway" => Some(ExpansionMode::Pathway),
+            _ => None,
+        }
+    }
+}
+
+/// Check whether a relation type is a pathway membership by its semantics, the relation must be between a gene and a pathway and its predicate must be one of the membership predicates.
+///
+/// # Example
+/// ```
+/// use biomedgps::query_builder::cypher_builder::is_pathway_membership_relation;
+///
+/// assert!(is_pathway_membership_relation("Hetionet::GpPW::Gene:Pathway"));
+/// assert!(is_pathway_membership_relation("REACTOME::has_member::Pathway:Gene"));
+/// assert!(!is_pathway_membership_relation("GNBR::J::Gene:Disease"));
+/// assert!(!is_pathway_membership_relation("Hetionet::GpBP::Gene:Biological Process"));
+/// ```
+pub fn is_pathway_membership_relation(relation_type: &str) -> bool {
+    let parts = relation_type.split("::").collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        return false;
+    }
+
+    let mut entity_types = parts[2].split(':').collect::<Vec<&str>>();
+    entity_types.sort();
+    entity_types == vec!["Gene", "Pathway"]
+        && PATHWAY_MEMBERSHIP_PREDICATES.contains(&parts[1].to_lowercase().as_str())
+}
+
+/// Generate the condition which checks whether a relationship is a pathway membership, it's the same rule as `is_pathway_membership_relation` in cypher.
+fn gen_membership_condition(variable: &str) -> String {
+    format!(
+        "toLower(split(type({}), '::')[1]) IN ['{}']",
+        variable,
+        PATHWAY_MEMBERSHIP_PREDICATES.join("', '")
+    )
+}
+
+/// Generate the query string to expand a pathway to its member genes and the diseases which are linked to the genes within the depth.
+///
+/// # Arguments
+/// * `pathway_id` - The pathway id. Such as 'REACT:R-HSA-109581'
+/// * `depth` - The max number of hops between the member genes and the diseases, 0 means only the member genes.
+/// * `limit` - The max number of the gene-disease paths.
+///
+/// # Returns
+/// * `query_str` - The query string.
+fn gen_pathway_expansion_query_str(pathway_id: &str, depth: usize, limit: usize) -> String {
+    let membership_condition = gen_membership_condition("m");
+    let members_query_str = format!(
+        "MATCH path = (p:Pathway)-[m]-(g:Gene) WHERE p.id IN ['{}'] AND {} UNWIND nodes(path) AS node UNWIND relationships(path) AS edge RETURN DISTINCT node, edge",
+        pathway_id, membership_condition
+    );
+
+    if depth == 0 {
+        return members_query_str;
+    }
+
+    format!(
+        "{} UNION MATCH (p:Pathway)-[m]-(g:Gene) WHERE p.id IN ['{}'] AND {} WITH DISTINCT g MATCH path = (g)-[*1..{}]-(d:Disease) WITH path LIMIT {} UNWIND nodes(path) AS node UNWIND relationships(path) AS edge RETURN DISTINCT node, edge",
+        members_query_str, pathway_id, membership_condition, depth, limit
+    )
+}
 
 /// Split the composed entity id into two parts: the entity type and the entity id.
 ///
@@ -85,26 +340,34 @@ fn gen_nhops_query_str(
     query_str
 }
 
-async fn parse_nhops_results(
-    result: &mut RowStream,
-) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
+/// Execute a query and read all rows of the result.
+async fn fetch_rows(graph: &Graph, query_str: &str) -> Result<Vec<Row>, anyhow::Error> {
+    let mut result = graph.execute(query(query_str)).await?;
+    let mut rows = vec![];
+    while let Some(row) = result.next().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_nhops_results(rows: Vec<Row>) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
     let mut node_map: HashMap<i64, NodeData> = HashMap::new();
     let mut edges = Vec::new();
     let mut relations = Vec::new();
 
-    while let Some(row) = result.next().await? {
+    for row in rows {
         match row.get::<NeoNode>("node") {
-            Some(node) => {
+            Ok(node) => {
                 let id = node.id();
                 let node = NodeData::from_neo_node(node);
                 node_map.insert(id, node);
             }
-            None => continue,
+            Err(_) => continue,
         };
 
         let relation: Relation = match row.get::<Relation>("edge") {
-            Some(relation) => relation,
-            None => continue,
+            Ok(relation) => relation,
+            Err(_) => continue,
         };
         relations.push(relation);
     }
@@ -142,6 +405,12 @@ pub async fn query_nhops(
     end_node_id: &str,   // Such as 'Disease::MONDO:0005404'
     nhops: usize,
 ) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
+    let memo_key = format!("{}|{}|{}", start_node_id.trim(), end_node_id.trim(), nhops);
+    if let Some(r) = get_memoized("nhops", &memo_key) {
+        debug!("Use the memoized paths of {}.", memo_key);
+        return Ok(r);
+    }
+
     let (start_node_type, start_node_id) = split_id(start_node_id)?;
     let (end_node_type, end_node_id) = split_id(end_node_id)?;
     let query_str = gen_nhops_query_str(
@@ -152,8 +421,62 @@ pub async fn query_nhops(
         nhops,
     );
 
-    let mut result = graph.execute(query(&query_str)).await?;
-    let r = parse_nhops_results(&mut result).await?;
+    let rows = fetch_rows(graph, &query_str).await?;
+    let r = parse_nhops_results(rows)?;
+    memoize("nhops", memo_key, &r);
+    Ok(r)
+}
+
+/// Expand a node by the expansion mode, such as expanding a pathway to its member genes and their diseases.
+///
+/// # Arguments
+/// * `graph` - The graph database connection.
+/// * `node_id` - The node id. Such as 'Pathway::REACT:R-HSA-109581'
+/// * `mode` - The expansion mode.
+/// * `depth` - The max number of hops between the member genes and the diseases, it's capped by `MAX_PATHWAY_EXPANSION_DEPTH`.
+/// * `limit` - The max number of the gene-disease paths.
+///
+/// # Returns
+/// * `Ok((nodes, edges))` - The nodes and edges of the expansion.
+/// * `Err(e)` - The error message.
+pub async fn query_expanded_nodes(
+    graph: &Graph,
+    node_id: &str,
+    mode: ExpansionMode,
+    depth: usize,
+    limit: usize,
+) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
+    let (node_type, node_id) = split_id(node_id)?;
+    let query_str = match mode {
+        ExpansionMode::Pathway => {
+            if node_type != "Pathway" {
+                return Err(anyhow::anyhow!(
+                    "The pathway expansion needs a Pathway node, but got a {} node.",
+                    node_type
+                ));
+            }
+
+            if depth > MAX_PATHWAY_EXPANSION_DEPTH {
+                return Err(anyhow::anyhow!(
+                    "The depth should be less than or equal to {}.",
+                    MAX_PATHWAY_EXPANSION_DEPTH
+                ));
+            }
+
+            if limit == 0 || limit > MAX_PATHWAY_EXPANSION_LIMIT {
+                return Err(anyhow::anyhow!(
+                    "The limit should be between 1 and {}.",
+                    MAX_PATHWAY_EXPANSION_LIMIT
+                ));
+            }
+
+            gen_pathway_expansion_query_str(&node_id, depth, limit)
+        }
+    };
+
+    debug!("query_expanded_nodes's query_str: {}", query_str);
+    let rows = fetch_rows(graph, &query_str).await?;
+    let r = parse_nhops_results(rows)?;
     Ok(r)
 }
 
@@ -166,44 +489,42 @@ pub async fn query_nhops(
 // # Returns
 // * `Ok((nodes, edges))` - The nodes and edges between the start node and the end node.
 // * `Err(e)` - The error message.
-async fn parse_shared_results(
-    result: &mut RowStream,
-) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
+fn parse_shared_results(rows: Vec<Row>) -> Result<(Vec<NodeData>, Vec<EdgeData>), anyhow::Error> {
     let mut node_map: HashMap<i64, NodeData> = HashMap::new();
     let mut edges = Vec::new();
     let mut relations = Vec::new();
 
-    while let Some(row) = result.next().await? {
+    for row in rows {
         match row.get::<NeoNode>("common") {
-            Some(node) => {
+            Ok(node) => {
                 let id = node.id();
                 let node = NodeData::from_neo_node(node);
                 node_map.insert(id, node);
             }
-            None => continue,
+            Err(_) => continue,
         };
 
         match row.get::<Vec<NeoNode>>("relatedStartNodes") {
-            Some(related_start_nodes) => {
+            Ok(related_start_nodes) => {
                 for node in related_start_nodes {
                     let id = node.id();
                     let node = NodeData::from_neo_node(node);
                     node_map.insert(id, node);
                 }
             }
-            None => continue,
+            Err(_) => continue,
         };
 
         // If you want to know the format of the relations, you can run the related cypher query in the Neo4j Browser.
         match row.get::<Vec<Vec<Relation>>>("relations") {
-            Some(relation) => {
+            Ok(relation) => {
                 for r1 in relation {
                     for r2 in r1 {
                         relations.push(r2);
                     }
                 }
             }
-            None => continue,
+            Err(_) => continue,
         };
     }
 
@@ -223,7 +544,7 @@ async fn parse_shared_results(
     Ok((nodes, edges))
 }
 
-// Query the graph database to get the shared shared nodes between the start nodes.
+// Query the graph database to get the shared shared nodes between the start nodes. The results are memoized for `QUERY_MEMO_TTL`.
 //
 // # Arguments
 // * `graph` - The graph database connection.
@@ -274,6 +595,13 @@ pub async fn query_shared_nodes(
         nums_shared_by
     };
 
+    let memo_key =
+        gen_shared_nodes_memo_key(node_ids, &target_node_types, nhops, topk, nums_shared_by);
+    if let Some(r) = get_memoized("shared_nodes", &memo_key) {
+        debug!("Use the memoized shared nodes of {}.", memo_key);
+        return Ok(r);
+    }
+
     let where_clauses = match target_node_types {
         Some(target_node_types) => {
             format!(
@@ -313,12 +641,80 @@ pub async fn query_shared_nodes(
     );
 
     info!("query_shared_nodes's query_str: {}", query_str);
-    let mut result = graph.execute(query(&query_str)).await?;
-    let r = parse_shared_results(&mut result).await?;
+    let rows = fetch_rows(graph, &query_str).await?;
+    let r = parse_shared_results(rows)?;
+    memoize("shared_nodes", memo_key, &r);
 
     Ok(r)
 }
 
+/// Count the nodes with the label in the graph database.
+///
+/// # Arguments
+/// * `graph` - The graph database connection.
+/// * `label` - The node label. Such as 'Gene'
+/// * `dataset` - Only count the nodes which are linked by the relations in the dataset, None means all nodes.
+///
+/// # Returns
+/// * `Ok(count)` - The number of the nodes.
+/// * `Err(e)` - The error message.
+pub async fn count_nodes_by_label(
+    graph: &Graph,
+    label: &str,
+    dataset: Option<&str>,
+) -> Result<i64, anyhow::Error> {
+    let query_str = match dataset {
+        Some(_) => format!(
+            "MATCH (n:`{}`)-[r]-() WHERE r.dataset = $dataset RETURN count(DISTINCT n) AS count",
+            label
+        ),
+        None => format!("MATCH (n:`{}`) RETURN count(n) AS count", label),
+    };
+    let mut result = graph
+        .execute(query(&query_str).param("dataset", dataset.unwrap_or_default()))
+        .await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("count").unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+/// Count the relations in the graph database, grouped by the relation type and the dataset.
+///
+/// # Arguments
+/// * `graph` - The graph database connection.
+/// * `dataset` - Only count the relations in the dataset, None means all datasets.
+///
+/// # Returns
+/// * `Ok(counts)` - The number of the relations, the key is (relation_type, dataset).
+/// * `Err(e)` - The error message.
+pub async fn count_relations_by_type(
+    graph: &Graph,
+    dataset: Option<&str>,
+) -> Result<HashMap<(String, String), i64>, anyhow::Error> {
+    let where_clause = match dataset {
+        Some(_) => "WHERE r.dataset = $dataset",
+        None => "",
+    };
+    let query_str = format!(
+        "MATCH ()-[r]->() {} RETURN type(r) AS relation_type, r.dataset AS dataset, count(r) AS count",
+        where_clause
+    );
+
+    let mut result = graph
+        .execute(query(&query_str).param("dataset", dataset.unwrap_or_default()))
+        .await?;
+    let mut counts = HashMap::new();
+    while let Some(row) = result.next().await? {
+        let relation_type = row.get::<String>("relation_type").unwrap_or_default();
+        let dataset = row.get::<String>("dataset").unwrap_or_default();
+        let count = row.get::<i64>("count").unwrap_or(0);
+        *counts.entry((relation_type, dataset)).or_insert(0) += count;
+    }
+
+    Ok(counts)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -335,6 +731,51 @@ mod tests {
         assert_eq!(start_node_id, "DrugBank:DB00818");
     }
 
+    #[test]
+    fn test_query_memo() {
+        let result = (vec![], vec![]);
+        memoize(
+            "nhops",
+            "Gene::ENTREZ:1|Disease::MONDO:0005404|2".to_string(),
+            &result,
+        );
+        assert_eq!(
+            get_memoized("nhops", "Gene::ENTREZ:1|Disease::MONDO:0005404|2"),
+            Some(result)
+        );
+        assert_eq!(
+            get_memoized("nhops", "Gene::ENTREZ:1|Disease::MONDO:0005404|3"),
+            None
+        );
+
+        let stats = get_query_memo_stats();
+        let nhops_stats = stats.iter().find(|s| s.kind == "nhops").unwrap();
+        assert!(nhops_stats.hits >= 1 && nhops_stats.misses >= 1);
+        assert!(nhops_stats.hit_rate > 0.0 && nhops_stats.hit_rate < 1.0);
+    }
+
+    #[async_test]
+    async fn test_track_memo_hits() {
+        let result = (vec![], vec![]);
+        memoize("shared_nodes", "Gene::ENTREZ:2|3|10|2".to_string(), &result);
+
+        let (_, hit) = track_memo_hits(async {
+            get_memoized("shared_nodes", "Gene::ENTREZ:2|3|10|2")
+        })
+        .await;
+        assert_eq!(hit, Some(true));
+
+        let (_, hit) = track_memo_hits(async {
+            get_memoized("shared_nodes", "Gene::ENTREZ:2|3|10|2");
+            get_memoized("shared_nodes", "Gene::ENTREZ:2|3|10|3")
+        })
+        .await;
+        assert_eq!(hit, Some(false));
+
+        let (_, hit) = track_memo_hits(async {}).await;
+        assert_eq!(hit, None);
+    }
+
     #[test]
     fn test_gen_nhops_query_str() {
         let start_node_type = "Compound";
@@ -355,6 +796,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_gen_pathway_expansion_query_str() {
+        let query_str = gen_pathway_expansion_query_str("REACT:R-HSA-109581", 0, 100);
+        assert_eq!(
+            query_str,
+            "MATCH path = (p:Pathway)-[m]-(g:Gene) WHERE p.id IN ['REACT:R-HSA-109581'] AND toLower(split(type(m), '::')[1]) IN ['gppw', 'member_of', 'has_member', 'participates_in', 'part_of', 'in_pathway'] UNWIND nodes(path) AS node UNWIND relationships(path) AS edge RETURN DISTINCT node, edge"
+        );
+
+        let query_str = gen_pathway_expansion_query_str("REACT:R-HSA-109581", 2, 100);
+        assert!(query_str.contains(" UNION "));
+        assert!(query_str.contains("MATCH path = (g)-[*1..2]-(d:Disease) WITH path LIMIT 100"));
+    }
+
     #[async_test]
     async fn test_query_neo4j() {
         // 从环境变量中获取数据库连接字符串
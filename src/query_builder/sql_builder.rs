@@ -1,8 +1,13 @@
 //! A SQL builder for building SQL queries.
 
 use log::{debug, info, warn};
+use poem_openapi::registry::{MetaSchema, MetaSchemaRef, Registry};
+use poem_openapi::types::{Example, ParseError, ParseFromJSON, ParseResult, ToJSON, Type};
+use poem_openapi::{Object, Union};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
+/// The value of a query item. The arrays are only for the in and not in operators.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum Value {
@@ -17,11 +22,88 @@ pub enum Value {
     ArrayBool(Vec<bool>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+// The value is untagged, so its schema is written by hand instead of deriving it.
+impl Type for Value {
+    const IS_REQUIRED: bool = true;
+
+    type RawValueType = Self;
+
+    type RawElementValueType = Self;
+
+    fn name() -> Cow<'static, str> {
+        "QueryValue".into()
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        let array_of = |ty: &'static str| {
+            MetaSchemaRef::Inline(Box::new(MetaSchema {
+                items: Some(Box::new(MetaSchemaRef::Inline(Box::new(MetaSchema::new(
+                    ty,
+                ))))),
+                ..MetaSchema::new("array")
+            }))
+        };
+
+        MetaSchemaRef::Inline(Box::new(MetaSchema {
+            description: Some("A number, a string, a boolean or null. An array of them is only for the in and not in operators."),
+            any_of: vec![
+                MetaSchemaRef::Inline(Box::new(MetaSchema::new("number"))),
+                MetaSchemaRef::Inline(Box::new(MetaSchema::new("string"))),
+                MetaSchemaRef::Inline(Box::new(MetaSchema::new("boolean"))),
+                array_of("number"),
+                array_of("string"),
+                array_of("boolean"),
+            ],
+            ..MetaSchema::ANY
+        }))
+    }
+
+    fn register(_registry: &mut Registry) {}
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        Some(self)
+    }
+
+    fn raw_element_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+        Box::new(self.as_raw_value().into_iter())
+    }
+}
+
+impl ParseFromJSON for Value {
+    fn parse_from_json(value: Option<serde_json::Value>) -> ParseResult<Self> {
+        serde_json::from_value(value.unwrap_or_default()).map_err(ParseError::custom)
+    }
+}
+
+impl ToJSON for Value {
+    fn to_json(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+/// A condition on a field, such as `{"field": "label", "operator": "=", "value": "Gene"}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+#[oai(example)]
 pub struct QueryItem {
+    // The column name, such as id, name, label and relation_type.
+    #[oai(validator(pattern = r"^[A-Za-z_][A-Za-z0-9_]*$"))]
     pub field: String,
     pub value: Value,
-    pub operator: String, // =, !=, like, not like, ilike, in, not in
+    // One of =, !=, <>, <, >, <=, >=, like, not like, ilike, in and not in. The in and not in operators need an array value.
+    #[oai(validator(pattern = r"(?i)^(=|!=|<>|<|>|<=|>=|like|not like|ilike|in|not in)$"))]
+    pub operator: String,
+}
+
+impl Example for QueryItem {
+    fn example() -> Self {
+        QueryItem {
+            field: "label".to_string(),
+            value: Value::String("Gene".to_string()),
+            operator: "=".to_string(),
+        }
+    }
 }
 
 impl QueryItem {
@@ -148,15 +230,32 @@ impl QueryItem {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A group of the conditions which are combined by and / or, such as `{"operator": "and", "items": [{"field": "label", "operator": "=", "value": "Gene"}, {"field": "name", "operator": "ilike", "value": "%TP53%"}]}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Object)]
+#[oai(example)]
 pub struct ComposeQueryItem {
     /// and, or
+    #[oai(validator(pattern = r"(?i)^(and|or)$"))]
     pub operator: String,
     /// QueryItem or ComposeQuery
     pub items: Vec<ComposeQuery>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl Example for ComposeQueryItem {
+    fn example() -> Self {
+        let mut query = ComposeQueryItem::new("and");
+        query.add_item(ComposeQuery::QueryItem(QueryItem::example()));
+        query.add_item(ComposeQuery::QueryItem(QueryItem {
+            field: "name".to_string(),
+            value: Value::String("%TP53%".to_string()),
+            operator: "ilike".to_string(),
+        }));
+        query
+    }
+}
+
+/// A query which is a single condition or a nested group of conditions. It's accepted as the `query_str` param (a json string) of the list endpoints, or as the body of their search endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Union)]
 #[serde(untagged)]
 pub enum ComposeQuery {
     QueryItem(QueryItem),
@@ -366,4 +465,26 @@ mod tests {
         debug!("pairs: {:?}", pairs);
         assert_eq!(2, pairs.len());
     }
+
+    #[test]
+    fn test_parse_compose_query_from_json() {
+        let value = serde_json::json!({
+            "operator": "and",
+            "items": [
+                {"field": "label", "operator": "=", "value": "Gene"},
+                {"operator": "or", "items": [{"field": "id", "operator": "in", "value": ["ENTREZ:1", "ENTREZ:2"]}]}
+            ]
+        });
+        match ComposeQuery::parse_from_json(Some(value)) {
+            Ok(ComposeQuery::ComposeQueryItem(query)) => assert_eq!(
+                query.format(),
+                "label = 'Gene' and (id in ('ENTREZ:1','ENTREZ:2'))"
+            ),
+            other => panic!("Unexpected query: {:?}", other),
+        }
+
+        // The field is a column name, it can't contain any sql.
+        let value = serde_json::json!({"field": "label; DROP TABLE biomedgps_entity", "operator": "=", "value": "Gene"});
+        assert!(ComposeQuery::parse_from_json(Some(value)).is_err());
+    }
 }